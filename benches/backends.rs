@@ -0,0 +1,62 @@
+//! Benchmarks comparing the overlap-query backends (see `OverlapQuery`).
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use granges::{prelude::*, testing::random_granges};
+
+#[cfg(not(feature = "bench-big"))]
+const NUM_RANGES: usize = 100_000;
+#[cfg(feature = "bench-big")]
+const NUM_RANGES: usize = 1_000_000;
+
+fn bench_overlap_backends(c: &mut Criterion) {
+    // create the benchmark group
+    let mut group = c.benchmark_group("backends");
+
+    // create the test data
+    let genome = read_seqlens("tests_data/hg38_seqlens.tsv").unwrap();
+    let left = random_granges(&genome, NUM_RANGES).unwrap();
+    let right = random_granges(&genome, NUM_RANGES).unwrap();
+
+    let coitrees = right.clone().into_coitrees().unwrap();
+    let lapper = right.clone().into_lapper().unwrap();
+    let nclist = right.clone().into_nclist().unwrap();
+
+    // configure the sample size for the group
+    group.sample_size(10);
+
+    // construction
+    group.bench_function("build_coitrees", |b| {
+        b.iter(|| right.clone().into_coitrees().unwrap().len());
+    });
+    group.bench_function("build_lapper", |b| {
+        b.iter(|| right.clone().into_lapper().unwrap().len());
+    });
+    group.bench_function("build_nclist", |b| {
+        b.iter(|| right.clone().into_nclist().unwrap().len());
+    });
+
+    // filtering joins
+    group.bench_function("filter_coitrees", |b| {
+        b.iter(|| left.clone().filter_overlaps(&coitrees).unwrap().len());
+    });
+    group.bench_function("filter_lapper", |b| {
+        b.iter(|| left.clone().filter_overlaps(&lapper).unwrap().len());
+    });
+    group.bench_function("filter_nclist", |b| {
+        b.iter(|| left.clone().filter_overlaps(&nclist).unwrap().len());
+    });
+
+    // left joins
+    group.bench_function("left_overlaps_coitrees", |b| {
+        b.iter(|| left.clone().left_overlaps(&coitrees).unwrap().len());
+    });
+    group.bench_function("left_overlaps_lapper", |b| {
+        b.iter(|| left.clone().left_overlaps(&lapper).unwrap().len());
+    });
+    group.bench_function("left_overlaps_nclist", |b| {
+        b.iter(|| left.clone().left_overlaps(&nclist).unwrap().len());
+    });
+}
+
+criterion_group!(benches, bench_overlap_backends,);
+criterion_main!(benches);
@@ -5,9 +5,7 @@
 //! ensure the output is the *exact* same.
 
 use criterion::{criterion_group, criterion_main, Criterion};
-use granges::test_utilities::{
-    granges_binary_path, random_bed3file, random_bed5file, temp_bedfile,
-};
+use granges::testing::{granges_binary_path, random_bed3file, random_bed5file, temp_bedfile};
 use std::{
     fs::File,
     process::{Command, Stdio},
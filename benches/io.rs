@@ -1,8 +1,8 @@
 use criterion::{criterion_group, criterion_main, Criterion};
 use csv::{self, ReaderBuilder};
-use granges::io::parsers::Bed5Addition;
+use granges::io::parsers::{mmap::BorrowedBedIterator, Bed5Addition};
 use granges::ranges::GenomicRangeRecord;
-use granges::test_utilities::random_bed5file;
+use granges::testing::{random_bed3file, random_bed5file};
 use granges::{prelude::*, Position};
 
 #[derive(Debug, serde::Deserialize, PartialEq)]
@@ -37,6 +37,17 @@ fn bench_io_shootout(c: &mut Criterion) {
         });
     });
 
+    // The general serde-based TSV parser, which Bed5Iterator's bytewise parser replaces
+    group.bench_function("tsv_record_iterator", |b| {
+        b.iter(|| {
+            let iter =
+                TsvRecordIterator::<GenomicRangeRecord<Bed5Addition>>::new(input_bedfile.path())
+                    .unwrap();
+            let gr = GRanges::from_iter(iter, &genome).unwrap();
+            gr.len()
+        });
+    });
+
     // CSV
     group.bench_function("csv", |b| {
         b.iter(|| {
@@ -62,5 +73,29 @@ fn bench_io_shootout(c: &mut Criterion) {
     });
 }
 
-criterion_group!(benches, bench_io_shootout,);
+fn bench_zero_copy(c: &mut Criterion) {
+    let mut group = c.benchmark_group("zero_copy");
+    let input_bedfile = random_bed3file(BED_LENGTH);
+    let genome = read_seqlens("tests_data/hg38_seqlens.tsv").unwrap();
+    group.sample_size(10);
+
+    group.bench_function("bed3iterator", |b| {
+        b.iter(|| {
+            let iter = Bed3Iterator::new(input_bedfile.path()).unwrap();
+            let gr = GRangesEmpty::from_iter(iter, &genome).unwrap();
+            gr.len()
+        });
+    });
+
+    // includes the time to read the file into memory, which memory-mapping avoids
+    group.bench_function("borrowed", |b| {
+        b.iter(|| {
+            let bytes = std::fs::read(input_bedfile.path()).unwrap();
+            let gr = GRangesEmpty::from_iter(BorrowedBedIterator::new(&bytes), &genome).unwrap();
+            gr.len()
+        });
+    });
+}
+
+criterion_group!(benches, bench_io_shootout, bench_zero_copy);
 criterion_main!(benches);
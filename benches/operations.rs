@@ -0,0 +1,141 @@
+//! Benchmarks of the core library operations (parsing, joins, map, merge, and
+//! coverage) on random datasets of several sizes, to evaluate performance changes
+//! and catch regressions.
+//!
+//! Unlike `bedtools_comparison.rs`, these call the library directly, so they don't
+//! include process startup or output writing. Compare against a saved baseline
+//! with e.g. `cargo bench --bench operations -- --save-baseline main` and then
+//! `cargo bench --bench operations -- --baseline main`.
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use granges::{
+    prelude::*,
+    sequences::rle::RleList,
+    testing::{random_bed3file, random_bed5file, random_granges, random_granges_mock_bed5},
+};
+
+#[cfg(not(feature = "bench-big"))]
+const SIZES: &[usize] = &[10_000, 100_000];
+#[cfg(feature = "bench-big")]
+const SIZES: &[usize] = &[10_000, 100_000, 1_000_000];
+
+fn bench_parsing(c: &mut Criterion) {
+    let mut group = c.benchmark_group("parsing");
+    group.sample_size(10);
+    let genome = read_seqlens("tests_data/hg38_seqlens.tsv").unwrap();
+
+    for &size in SIZES {
+        group.throughput(Throughput::Elements(size as u64));
+        let bed3file = random_bed3file(size);
+        group.bench_with_input(BenchmarkId::new("bed3", size), &bed3file, |b, file| {
+            b.iter(|| {
+                let iter = Bed3Iterator::new(file.path()).unwrap();
+                GRangesEmpty::from_iter(iter, &genome).unwrap().len()
+            });
+        });
+        let bed5file = random_bed5file(size);
+        group.bench_with_input(BenchmarkId::new("bed5", size), &bed5file, |b, file| {
+            b.iter(|| {
+                let iter = Bed5Iterator::new(file.path()).unwrap();
+                GRanges::from_iter(iter, &genome).unwrap().len()
+            });
+        });
+    }
+}
+
+fn bench_joins(c: &mut Criterion) {
+    let mut group = c.benchmark_group("joins");
+    group.sample_size(10);
+    let genome = read_seqlens("tests_data/hg38_seqlens.tsv").unwrap();
+
+    for &size in SIZES {
+        group.throughput(Throughput::Elements(size as u64));
+        let left = random_granges(&genome, size).unwrap();
+        let right = random_granges(&genome, size)
+            .unwrap()
+            .into_coitrees()
+            .unwrap();
+        group.bench_with_input(BenchmarkId::new("left_overlaps", size), &left, |b, left| {
+            b.iter(|| left.clone().left_overlaps(&right).unwrap().len());
+        });
+        group.bench_with_input(
+            BenchmarkId::new("filter_overlaps", size),
+            &left,
+            |b, left| {
+                b.iter(|| left.clone().filter_overlaps(&right).unwrap().len());
+            },
+        );
+    }
+}
+
+fn bench_map(c: &mut Criterion) {
+    let mut group = c.benchmark_group("map");
+    group.sample_size(10);
+    let genome = read_seqlens("tests_data/hg38_seqlens.tsv").unwrap();
+
+    for &size in SIZES {
+        group.throughput(Throughput::Elements(size as u64));
+        let left = random_granges(&genome, size).unwrap();
+        let right = random_granges_mock_bed5(&genome, size)
+            .unwrap()
+            .into_coitrees()
+            .unwrap();
+        group.bench_with_input(BenchmarkId::new("sum_scores", size), &left, |b, left| {
+            b.iter(|| {
+                left.clone()
+                    .left_overlaps(&right)
+                    .unwrap()
+                    .map_joins(|join| {
+                        join.right_data
+                            .iter()
+                            .filter_map(|data| data.score)
+                            .sum::<f64>()
+                    })
+                    .unwrap()
+                    .len()
+            });
+        });
+    }
+}
+
+fn bench_merge(c: &mut Criterion) {
+    let mut group = c.benchmark_group("merge");
+    group.sample_size(10);
+
+    for &size in SIZES {
+        group.throughput(Throughput::Elements(size as u64));
+        // random_bed3file() is sorted, as merging requires
+        let bed3file = random_bed3file(size);
+        let ranges: Vec<_> = Bed3Iterator::new(bed3file.path())
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        group.bench_with_input(BenchmarkId::new("merge", size), &ranges, |b, ranges| {
+            b.iter(|| MergingEmptyIterator::new(ranges.clone(), 0).count());
+        });
+    }
+}
+
+fn bench_coverage(c: &mut Criterion) {
+    let mut group = c.benchmark_group("coverage");
+    group.sample_size(10);
+    let genome = read_seqlens("tests_data/hg38_seqlens.tsv").unwrap();
+
+    for &size in SIZES {
+        group.throughput(Throughput::Elements(size as u64));
+        let gr = random_granges(&genome, size).unwrap();
+        group.bench_with_input(BenchmarkId::new("rle", size), &gr, |b, gr| {
+            b.iter(|| RleList::<usize>::coverage(gr).unwrap().iter().count());
+        });
+    }
+}
+
+criterion_group!(
+    benches,
+    bench_parsing,
+    bench_joins,
+    bench_map,
+    bench_merge,
+    bench_coverage
+);
+criterion_main!(benches);
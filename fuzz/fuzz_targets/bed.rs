@@ -0,0 +1,31 @@
+//! Fuzz the BED parsers (and genome files): malformed input must give errors, not
+//! panics.
+#![no_main]
+
+use granges::{
+    io::parsers::bed::fast::{parse_bed3_line, parse_bed5_line, FastBedIterator},
+    prelude::*,
+    ranges::GenomicRangeRecordEmpty,
+};
+use libfuzzer_sys::fuzz_target;
+
+/// Parse all the records, stopping at the first error (which may be an I/O error
+/// that would repeat).
+fn drain<T>(iter: impl Iterator<Item = Result<T, GRangesError>>) {
+    for result in iter {
+        if result.is_err() {
+            break;
+        }
+    }
+}
+
+fuzz_target!(|data: &[u8]| {
+    drain(FastBedIterator::from_bytes(data, parse_bed3_line));
+    drain(FastBedIterator::from_bytes(data, parse_bed5_line));
+    drain(Bed4Iterator::from_bytes(data));
+    drain(BedlikeIterator::from_bytes(data));
+    drain(TsvRecordIterator::<GenomicRangeRecordEmpty>::from_bytes(
+        data,
+    ));
+    let _ = read_seqlens_from_bytes(data);
+});
@@ -0,0 +1,24 @@
+//! Fuzz reading bedGraph tracks into run-length encoded vectors: malformed input
+//! must give errors, not panics.
+#![no_main]
+
+use granges::{prelude::*, sequences::rle::RleList};
+use indexmap::IndexMap;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let seqlens: IndexMap<String, Position> = [("chr1", 1000), ("chr2", 10)]
+        .into_iter()
+        .map(|(seqname, length)| (seqname.to_string(), length))
+        .collect();
+    let values = BedlikeIterator::from_bytes(data).map(|result| -> Result<_, GRangesError> {
+        let range = result?;
+        let value = range
+            .data
+            .as_deref()
+            .and_then(|data| data.split('\t').next())
+            .and_then(|value| value.parse::<f64>().ok());
+        Ok(range.into_map_data(|_| value))
+    });
+    let _ = RleList::<Option<f64>>::from_ranges(values, &seqlens);
+});
@@ -0,0 +1,24 @@
+//! Fuzz input format detection, and the parsing iterator it chooses: any file must
+//! be detected (or rejected) with an error, not a panic.
+#![no_main]
+
+use granges::io::parsers::{GenomicRangesFile, GenomicRangesParser};
+use libfuzzer_sys::fuzz_target;
+use std::io::Write;
+
+fuzz_target!(|data: &[u8]| {
+    // detection uses the extension as a hint
+    for suffix in [".bed", ".tsv", ".gff", ".vcf", ".bedGraph", ".bed.gz", ""] {
+        let mut file = tempfile::Builder::new().suffix(suffix).tempfile().unwrap();
+        file.write_all(data).unwrap();
+        let _ = GenomicRangesFile::detect(file.path());
+        // parse all the records, stopping at the first error
+        match GenomicRangesFile::parsing_iterator(file.path()) {
+            Ok(GenomicRangesParser::Bed3(iter)) => iter.take_while(Result::is_ok).for_each(drop),
+            Ok(GenomicRangesParser::Bed4(iter)) => iter.take_while(Result::is_ok).for_each(drop),
+            Ok(GenomicRangesParser::Bed5(iter)) => iter.take_while(Result::is_ok).for_each(drop),
+            Ok(GenomicRangesParser::Bedlike(iter)) => iter.take_while(Result::is_ok).for_each(drop),
+            Ok(GenomicRangesParser::Unsupported) | Err(_) => {}
+        }
+    }
+});
@@ -0,0 +1,42 @@
+//! Fuzz the GFF/GTF parsers, and the gene and transcript model readers (including
+//! BED12): malformed input must give errors, not panics.
+#![no_main]
+
+use granges::io::parsers::{
+    bed::bedlike::parse_gff_lazy, read_gene_models, read_transcript_models, TranscriptFeature,
+};
+use libfuzzer_sys::fuzz_target;
+use std::io::Write;
+
+const FEATURES: [TranscriptFeature; 7] = [
+    TranscriptFeature::Exons,
+    TranscriptFeature::Introns,
+    TranscriptFeature::Cds,
+    TranscriptFeature::FivePrimeUtr,
+    TranscriptFeature::ThreePrimeUtr,
+    TranscriptFeature::FirstExon,
+    TranscriptFeature::Tss,
+];
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(text) = std::str::from_utf8(data) {
+        for line in text.lines() {
+            let _ = parse_gff_lazy(line);
+        }
+    }
+
+    // the gene model readers detect the format by extension
+    for suffix in [".gff3", ".gtf", ".bed"] {
+        let mut file = tempfile::Builder::new().suffix(suffix).tempfile().unwrap();
+        file.write_all(data).unwrap();
+        let _ = read_gene_models(file.path());
+        if let Ok(transcripts) = read_transcript_models(file.path()) {
+            for transcript in transcripts {
+                let _ = transcript.length();
+                for feature in FEATURES {
+                    let _ = transcript.features(feature);
+                }
+            }
+        }
+    }
+});
@@ -0,0 +1,13 @@
+//! Fuzz the VCF parser: malformed input must give errors, not panics.
+#![no_main]
+
+use granges::io::parsers::bed::bedlike::parse_vcf_lazy;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(text) = std::str::from_utf8(data) {
+        for line in text.lines() {
+            let _ = parse_vcf_lazy(line);
+        }
+    }
+});
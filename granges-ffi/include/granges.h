@@ -0,0 +1,89 @@
+/*
+ * granges.h: the C API of the GRanges genomic range library.
+ *
+ * Build the library with `cargo build --release -p granges-ffi`, and link against
+ * target/release/libgranges_ffi.{so,dylib,a}.
+ *
+ * Ranges are 0-based and right-exclusive. Functions that can fail return NULL or
+ * -1, and set a message that can be retrieved with granges_last_error(). Objects
+ * returned by this API must be freed with the corresponding *_free() function.
+ */
+#ifndef GRANGES_H
+#define GRANGES_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* A set of ranges, each with an optional score. Ranges are identified by their
+ * row, i.e. the order they were added in. */
+typedef struct GRangesSet GRangesSet;
+
+/* The overlaps between two sets of ranges. */
+typedef struct GRangesJoin GRangesJoin;
+
+/* A range. The sequence name is valid as long as the range's GRangesSet. */
+typedef struct GRangesRange {
+    const char *seqname;
+    uint64_t start;
+    uint64_t end;
+    double score; /* NaN if the range has no score */
+} GRangesRange;
+
+/* An overlapping pair of left and right ranges (by row). */
+typedef struct GRangesOverlap {
+    size_t left;
+    size_t right;
+    uint64_t width; /* the number of overlapping basepairs */
+} GRangesOverlap;
+
+/* The version of the GRanges library. */
+const char *granges_version(void);
+
+/* The message of the last error on this thread, or NULL if there has been no
+ * error. The message is valid until the next error on this thread. */
+const char *granges_last_error(void);
+
+/* Create an empty set on num_sequences sequences, with names seqnames and
+ * lengths lengths. */
+GRangesSet *granges_new(const char *const *seqnames, const uint64_t *lengths,
+                        size_t num_sequences);
+
+/* Read a set from a BED file (which may be gzip-compressed), on the sequences in
+ * the TSV genome file genome_path. Scores are read from BED5 files. */
+GRangesSet *granges_read_bed(const char *bed_path, const char *genome_path);
+
+/* Add the range [start, end) on sequence seqname, with score (or NaN, for no
+ * score). Returns 0 on success. */
+int granges_push(GRangesSet *set, const char *seqname, uint64_t start,
+                 uint64_t end, double score);
+
+/* The number of ranges in set. */
+size_t granges_len(const GRangesSet *set);
+
+/* Write the range in row row of set to range. Returns 0 on success. */
+int granges_get(const GRangesSet *set, size_t row, GRangesRange *range);
+
+void granges_free(GRangesSet *set);
+
+/* Find the overlaps between the left and right ranges. */
+GRangesJoin *granges_join(const GRangesSet *left, const GRangesSet *right);
+
+/* The total number of overlaps in join. */
+size_t granges_join_len(const GRangesJoin *join);
+
+/* Write the next overlap of join to overlap, in order of left row and then right
+ * row. Returns 1 if there was a next overlap, 0 if all overlaps have been
+ * iterated over, or -1 on error. */
+int granges_join_next(GRangesJoin *join, GRangesOverlap *overlap);
+
+void granges_join_free(GRangesJoin *join);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* GRANGES_H */
@@ -0,0 +1,483 @@
+//! A C API for the GRanges library, for linking GRanges into R packages, C++
+//! pipelines, etc. instead of calling the `granges` command line tool.
+//!
+//! The API (declared in `include/granges.h`) is built around two opaque types:
+//!
+//!  - `GRangesSet`: a set of ranges on the sequences of a genome, each with an
+//!    optional score, built range by range (`granges_new()` and `granges_push()`),
+//!    or read from a BED file (`granges_read_bed()`). Ranges are identified by
+//!    their row, i.e. the order they were added in.
+//!
+//!  - `GRangesJoin`: the result of an overlap join between two sets
+//!    (`granges_join()`), which is iterated over as `GRangesOverlap` pairs of left
+//!    and right rows, ordered by left row and then right row.
+//!
+//! Functions that can fail return `NULL` or `-1`, and set a message that can be
+//! retrieved with `granges_last_error()`. Objects returned by this API must be
+//! freed with the corresponding `*_free()` function.
+
+use std::{
+    cell::RefCell,
+    error::Error,
+    ffi::{c_char, c_int, CStr, CString},
+    panic::{catch_unwind, AssertUnwindSafe},
+    path::PathBuf,
+    ptr,
+};
+
+use granges::{
+    io::parsers::{GenomicRangesFile, GenomicRangesParser},
+    prelude::*,
+    ranges::GenomicRangeRecord,
+};
+use indexmap::IndexMap;
+
+type FfiResult<T> = Result<T, Box<dyn Error>>;
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+fn set_last_error(message: String) {
+    let message = CString::new(message.replace('\0', " ")).unwrap_or_default();
+    LAST_ERROR.with(|last_error| *last_error.borrow_mut() = Some(message));
+}
+
+/// Run `func`, returning `on_error` (and setting the last error) if it fails or panics,
+/// since neither errors nor panics can cross the FFI boundary.
+fn ffi_try<T>(on_error: T, func: impl FnOnce() -> FfiResult<T>) -> T {
+    match catch_unwind(AssertUnwindSafe(func)) {
+        Ok(Ok(value)) => value,
+        Ok(Err(err)) => {
+            set_last_error(err.to_string());
+            on_error
+        }
+        Err(_) => {
+            set_last_error("internal error (panic) in granges".to_string());
+            on_error
+        }
+    }
+}
+
+/// Borrow a C string argument as a `&str`.
+///
+/// # Safety
+/// `string` must be null or a valid, nul-terminated C string.
+unsafe fn str_arg<'a>(string: *const c_char, name: &str) -> FfiResult<&'a str> {
+    if string.is_null() {
+        return Err(format!("{} is NULL", name).into());
+    }
+    Ok(CStr::from_ptr(string).to_str()?)
+}
+
+/// Borrow an object argument.
+///
+/// # Safety
+/// `object` must be null or point to a valid `T`.
+unsafe fn ref_arg<'a, T>(object: *const T, name: &str) -> FfiResult<&'a T> {
+    object
+        .as_ref()
+        .ok_or_else(|| format!("{} is NULL", name).into())
+}
+
+fn to_position(value: u64) -> FfiResult<Position> {
+    Position::try_from(value).map_err(|_| format!("position {} is too large", value).into())
+}
+
+/// A set of ranges, each with an optional score.
+pub struct GRangesSet {
+    seqlens: IndexMap<String, Position>,
+    /// The sequence names, as C strings for [`granges_get()`].
+    seqnames: Vec<CString>,
+    /// The sequence index, start, and end of each row.
+    rows: Vec<(usize, Position, Position)>,
+    ranges: GRanges<VecRangesIndexed, Vec<Option<f64>>>,
+}
+
+impl GRangesSet {
+    fn new(seqlens: IndexMap<String, Position>) -> FfiResult<Self> {
+        let seqnames = seqlens
+            .keys()
+            .map(|seqname| CString::new(seqname.as_str()))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Self {
+            ranges: GRanges::new_vec(&seqlens),
+            seqlens,
+            seqnames,
+            rows: Vec::new(),
+        })
+    }
+
+    fn push(
+        &mut self,
+        seqname: &str,
+        start: Position,
+        end: Position,
+        score: Option<f64>,
+    ) -> FfiResult<()> {
+        let seqname_index = self
+            .seqlens
+            .get_index_of(seqname)
+            .ok_or_else(|| GRangesError::missing_sequence(seqname))?;
+        self.ranges.push_range(seqname, start, end, score)?;
+        self.rows.push((seqname_index, start, end));
+        Ok(())
+    }
+
+    fn push_record<U>(
+        &mut self,
+        record: Result<GenomicRangeRecord<U>, GRangesError>,
+        score: impl Fn(U) -> Option<f64>,
+    ) -> FfiResult<()> {
+        let record = record?;
+        self.push(
+            &record.seqname,
+            record.start,
+            record.end,
+            score(record.data),
+        )
+    }
+}
+
+/// A range, as returned by [`granges_get()`].
+#[repr(C)]
+pub struct GRangesRange {
+    /// The sequence name, which is valid as long as its `GRangesSet`.
+    pub seqname: *const c_char,
+    pub start: u64,
+    pub end: u64,
+    /// The score, or NaN if the range has no score.
+    pub score: f64,
+}
+
+/// An overlapping pair of left and right ranges, as returned by [`granges_join_next()`].
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GRangesOverlap {
+    pub left: usize,
+    pub right: usize,
+    /// The number of overlapping basepairs.
+    pub width: u64,
+}
+
+/// The overlaps of an overlap join, and the position of the next one.
+pub struct GRangesJoin {
+    overlaps: Vec<GRangesOverlap>,
+    next: usize,
+}
+
+/// The version of the GRanges library, as a static C string.
+#[no_mangle]
+pub extern "C" fn granges_version() -> *const c_char {
+    concat!(env!("CARGO_PKG_VERSION"), "\0").as_ptr() as *const c_char
+}
+
+/// The message of the last error on this thread, or `NULL` if there has been no
+/// error. The message is valid until the next error on this thread.
+#[no_mangle]
+pub extern "C" fn granges_last_error() -> *const c_char {
+    LAST_ERROR.with(|last_error| {
+        last_error
+            .borrow()
+            .as_ref()
+            .map_or(ptr::null(), |message| message.as_ptr())
+    })
+}
+
+/// Create a new, empty `GRangesSet` on `num_sequences` sequences, with names
+/// `seqnames` and lengths `lengths`. Returns `NULL` on error.
+///
+/// # Safety
+/// `seqnames` and `lengths` must point to arrays of `num_sequences` elements, and
+/// each sequence name must be a nul-terminated C string.
+#[no_mangle]
+pub unsafe extern "C" fn granges_new(
+    seqnames: *const *const c_char,
+    lengths: *const u64,
+    num_sequences: usize,
+) -> *mut GRangesSet {
+    ffi_try(ptr::null_mut(), || {
+        let mut seqlens = IndexMap::new();
+        if num_sequences > 0 {
+            if seqnames.is_null() || lengths.is_null() {
+                return Err("seqnames or lengths is NULL".into());
+            }
+            let seqnames = std::slice::from_raw_parts(seqnames, num_sequences);
+            let lengths = std::slice::from_raw_parts(lengths, num_sequences);
+            for (seqname, length) in seqnames.iter().zip(lengths) {
+                seqlens.insert(
+                    str_arg(*seqname, "sequence name")?.to_string(),
+                    to_position(*length)?,
+                );
+            }
+        }
+        Ok(Box::into_raw(Box::new(GRangesSet::new(seqlens)?)))
+    })
+}
+
+/// Read a `GRangesSet` from a BED file (which may be gzip-compressed), with the
+/// sequences in the TSV genome file `genome_path`. Scores are read from the fifth
+/// column of BED5 files. Returns `NULL` on error.
+///
+/// # Safety
+/// `bed_path` and `genome_path` must be nul-terminated C strings.
+#[no_mangle]
+pub unsafe extern "C" fn granges_read_bed(
+    bed_path: *const c_char,
+    genome_path: *const c_char,
+) -> *mut GRangesSet {
+    ffi_try(ptr::null_mut(), || {
+        let bed_path = PathBuf::from(str_arg(bed_path, "bed_path")?);
+        let genome = read_seqlens(str_arg(genome_path, "genome_path")?)?;
+        let mut set = GRangesSet::new(genome)?;
+        match GenomicRangesFile::parsing_iterator(bed_path)? {
+            GenomicRangesParser::Bed3(iter) => {
+                for record in iter {
+                    let record = record?;
+                    set.push(&record.seqname, record.start, record.end, None)?;
+                }
+            }
+            GenomicRangesParser::Bed4(iter) => {
+                for record in iter {
+                    set.push_record(record, |_| None)?;
+                }
+            }
+            GenomicRangesParser::Bed5(iter) => {
+                for record in iter {
+                    set.push_record(record, |data| data.score)?;
+                }
+            }
+            GenomicRangesParser::Bedlike(iter) => {
+                for record in iter {
+                    set.push_record(record, |_| None)?;
+                }
+            }
+            GenomicRangesParser::Unsupported => {
+                return Err(GRangesError::UnsupportedGenomicRangesFileFormat.into())
+            }
+        }
+        Ok(Box::into_raw(Box::new(set)))
+    })
+}
+
+/// Add the range `[start, end)` on sequence `seqname` to `set`, with `score` (or
+/// NaN, for no score). Returns `0` on success, or `-1` on error.
+///
+/// # Safety
+/// `set` must be a valid `GRangesSet`, and `seqname` a nul-terminated C string.
+#[no_mangle]
+pub unsafe extern "C" fn granges_push(
+    set: *mut GRangesSet,
+    seqname: *const c_char,
+    start: u64,
+    end: u64,
+    score: f64,
+) -> c_int {
+    ffi_try(-1, || {
+        let set = set.as_mut().ok_or("set is NULL")?;
+        let score = Some(score).filter(|score| !score.is_nan());
+        set.push(
+            str_arg(seqname, "seqname")?,
+            to_position(start)?,
+            to_position(end)?,
+            score,
+        )?;
+        Ok(0)
+    })
+}
+
+/// The number of ranges in `set` (or `0`, if `set` is `NULL`).
+///
+/// # Safety
+/// `set` must be null or a valid `GRangesSet`.
+#[no_mangle]
+pub unsafe extern "C" fn granges_len(set: *const GRangesSet) -> usize {
+    set.as_ref().map_or(0, |set| set.rows.len())
+}
+
+/// Write the range in row `row` of `set` to `range`. Returns `0` on success, or
+/// `-1` on error.
+///
+/// # Safety
+/// `set` must be a valid `GRangesSet`, and `range` must point to a `GRangesRange`.
+#[no_mangle]
+pub unsafe extern "C" fn granges_get(
+    set: *const GRangesSet,
+    row: usize,
+    range: *mut GRangesRange,
+) -> c_int {
+    ffi_try(-1, || {
+        let set = ref_arg(set, "set")?;
+        let range = range.as_mut().ok_or("range is NULL")?;
+        let &(seqname_index, start, end) = set
+            .rows
+            .get(row)
+            .ok_or_else(|| format!("row {} is out of bounds", row))?;
+        let score = set.ranges.data().and_then(|scores| scores[row]);
+        *range = GRangesRange {
+            seqname: set.seqnames[seqname_index].as_ptr(),
+            start: start.into(),
+            end: end.into(),
+            score: score.unwrap_or(f64::NAN),
+        };
+        Ok(0)
+    })
+}
+
+/// Free a `GRangesSet`.
+///
+/// # Safety
+/// `set` must be null or a `GRangesSet` that has not already been freed.
+#[no_mangle]
+pub unsafe extern "C" fn granges_free(set: *mut GRangesSet) {
+    if !set.is_null() {
+        drop(Box::from_raw(set));
+    }
+}
+
+/// Find the overlaps between the `left` and `right` ranges. Returns `NULL` on error.
+///
+/// # Safety
+/// `left` and `right` must be valid `GRangesSet`s.
+#[no_mangle]
+pub unsafe extern "C" fn granges_join(
+    left: *const GRangesSet,
+    right: *const GRangesSet,
+) -> *mut GRangesJoin {
+    ffi_try(ptr::null_mut(), || {
+        let left = ref_arg(left, "left")?;
+        let right = ref_arg(right, "right")?.ranges.clone().into_coitrees()?;
+        let mut joins = left
+            .ranges
+            .clone()
+            .left_overlaps(&right)?
+            .map_joins(|join_data| join_data.join)?;
+
+        let mut overlaps = Vec::new();
+        for join in joins.take_data()? {
+            let Some(left) = join.left_index() else {
+                continue;
+            };
+            for (right, width) in join.right_indices().into_iter().zip(join.overlap_widths()) {
+                if let Some(right) = right {
+                    overlaps.push(GRangesOverlap {
+                        left,
+                        right,
+                        width: width.into(),
+                    });
+                }
+            }
+        }
+        overlaps.sort_by_key(|overlap| (overlap.left, overlap.right));
+        Ok(Box::into_raw(Box::new(GRangesJoin { overlaps, next: 0 })))
+    })
+}
+
+/// The total number of overlaps in `join` (or `0`, if `join` is `NULL`).
+///
+/// # Safety
+/// `join` must be null or a valid `GRangesJoin`.
+#[no_mangle]
+pub unsafe extern "C" fn granges_join_len(join: *const GRangesJoin) -> usize {
+    join.as_ref().map_or(0, |join| join.overlaps.len())
+}
+
+/// Write the next overlap of `join` to `overlap`. Returns `1` if there was a next
+/// overlap, `0` if all overlaps have been iterated over, or `-1` on error.
+///
+/// # Safety
+/// `join` must be a valid `GRangesJoin`, and `overlap` must point to a
+/// `GRangesOverlap`.
+#[no_mangle]
+pub unsafe extern "C" fn granges_join_next(
+    join: *mut GRangesJoin,
+    overlap: *mut GRangesOverlap,
+) -> c_int {
+    ffi_try(-1, || {
+        let join = join.as_mut().ok_or("join is NULL")?;
+        let overlap = overlap.as_mut().ok_or("overlap is NULL")?;
+        match join.overlaps.get(join.next) {
+            Some(next) => {
+                *overlap = *next;
+                join.next += 1;
+                Ok(1)
+            }
+            None => Ok(0),
+        }
+    })
+}
+
+/// Free a `GRangesJoin`.
+///
+/// # Safety
+/// `join` must be null or a `GRangesJoin` that has not already been freed.
+#[no_mangle]
+pub unsafe extern "C" fn granges_join_free(join: *mut GRangesJoin) {
+    if !join.is_null() {
+        drop(Box::from_raw(join));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_set(ranges: &[(u64, u64, f64)]) -> *mut GRangesSet {
+        let chr1 = CString::new("chr1").unwrap();
+        let set = unsafe { granges_new([chr1.as_ptr()].as_ptr(), [100].as_ptr(), 1) };
+        assert!(!set.is_null());
+        for &(start, end, score) in ranges {
+            let pushed = unsafe { granges_push(set, chr1.as_ptr(), start, end, score) };
+            assert_eq!(pushed, 0);
+        }
+        set
+    }
+
+    #[test]
+    fn test_join() {
+        let left = test_set(&[(50, 60, f64::NAN), (0, 10, 1.0)]);
+        let right = test_set(&[(5, 55, 2.0), (8, 9, 3.0)]);
+        unsafe {
+            let join = granges_join(left, right);
+            assert_eq!(granges_join_len(join), 3);
+            let mut overlap = GRangesOverlap {
+                left: 0,
+                right: 0,
+                width: 0,
+            };
+            let mut overlaps = Vec::new();
+            while granges_join_next(join, &mut overlap) == 1 {
+                overlaps.push((overlap.left, overlap.right, overlap.width));
+            }
+            assert_eq!(overlaps, vec![(0, 0, 5), (1, 0, 5), (1, 1, 1)]);
+
+            let mut range = GRangesRange {
+                seqname: ptr::null(),
+                start: 0,
+                end: 0,
+                score: 0.0,
+            };
+            assert_eq!(granges_get(right, 1, &mut range), 0);
+            assert_eq!(CStr::from_ptr(range.seqname).to_str(), Ok("chr1"));
+            assert_eq!((range.start, range.end, range.score), (8, 9, 3.0));
+
+            granges_join_free(join);
+            granges_free(left);
+            granges_free(right);
+        }
+    }
+
+    #[test]
+    fn test_errors() {
+        let set = test_set(&[]);
+        unsafe {
+            let chr_x = CString::new("chrX").unwrap();
+            assert_eq!(granges_push(set, chr_x.as_ptr(), 0, 1, 0.0), -1);
+            let message = CStr::from_ptr(granges_last_error()).to_str().unwrap();
+            assert!(message.contains("chrX"));
+            assert_eq!(granges_get(set, 0, ptr::null_mut()), -1);
+            assert!(granges_join(set, ptr::null()).is_null());
+            granges_free(set);
+        }
+    }
+}
@@ -0,0 +1,47 @@
+"""Python bindings for the GRanges genomic range library.
+
+Ranges are 0-based and right-exclusive, on sequences given by a dict of
+sequence names and lengths (e.g. from a genome file, see `read_seqlens()`).
+"""
+
+import numpy as np
+
+from ._granges import GRanges
+
+__all__ = ["GRanges", "from_pandas", "read_seqlens", "to_pandas"]
+
+
+def read_seqlens(path):
+    """Read a TSV genome file of sequence names and lengths into a dict."""
+    seqlens = {}
+    with open(path) as genome:
+        for line in genome:
+            if line.strip() and not line.startswith("#"):
+                seqname, length = line.split("\t")[:2]
+                seqlens[seqname] = int(length)
+    return seqlens
+
+
+def from_pandas(df, seqlens, seqname="chrom", start="start", end="end", score=None):
+    """Create a `GRanges` from the columns of a pandas `DataFrame`."""
+    scores = None
+    if score is not None:
+        scores = df[score].to_numpy(dtype=np.float64, na_value=np.nan)
+    return GRanges(
+        seqlens,
+        df[seqname].astype(str).tolist(),
+        df[start].to_numpy(dtype=np.int64),
+        df[end].to_numpy(dtype=np.int64),
+        scores,
+    )
+
+
+def to_pandas(granges, **columns):
+    """Convert a `GRanges` to a pandas `DataFrame`, with any additional
+    `columns` (e.g. the results of `GRanges.map()`)."""
+    import pandas as pd
+
+    df = pd.DataFrame(granges.to_dict())
+    for name, values in columns.items():
+        df[name] = values
+    return df
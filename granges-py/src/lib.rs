@@ -0,0 +1,319 @@
+//! Python bindings for the core GRanges API.
+//!
+//! This exposes a `GRanges` class holding genomic ranges with an optional float
+//! score per range, built from NumPy arrays (or a pandas `DataFrame`, through the
+//! `granges` Python package), and supporting overlap joins, `map`, `merge`, and
+//! windows.
+//!
+//! Rows (e.g. the row indices returned by joins, and the columns returned by
+//! `map`) are in the order the ranges were given in. Ranges created by `merge`
+//! and `windows` are in sorted order (by sequence, in the order of the sequence
+//! lengths, and then by start position).
+//!
+//! Build the Python package with [maturin](https://www.maturin.rs), e.g. `maturin
+//! develop --release` in this directory.
+
+use granges::{data::operations::FloatOperation, prelude::*, ranges::GenomicRangeRecord};
+use indexmap::IndexMap;
+use numpy::{IntoPyArray, PyArray1, PyReadonlyArray1};
+use pyo3::{exceptions::PyValueError, prelude::*, types::PyDict};
+
+/// The ranges and their (possibly missing) scores.
+type ScoredRanges = GRanges<VecRangesIndexed, Vec<Option<f64>>>;
+
+fn value_error(err: GRangesError) -> PyErr {
+    PyValueError::new_err(err.to_string())
+}
+
+/// Parse the name of a `granges map` operation (e.g. "mean").
+fn parse_operation(name: &str) -> PyResult<FloatOperation> {
+    <FloatOperation as clap::ValueEnum>::from_str(name, true)
+        .map_err(|_| PyValueError::new_err(format!("unknown operation '{}'", name)))
+}
+
+fn to_position(value: i64) -> PyResult<Position> {
+    Position::try_from(value)
+        .map_err(|_| PyValueError::new_err(format!("invalid position {}", value)))
+}
+
+fn datum_to_object(py: Python<'_>, datum: DatumType) -> PyObject {
+    match datum {
+        DatumType::Float32(value) => value.into_py(py),
+        DatumType::Float64(value) => value.into_py(py),
+        DatumType::String(value) => value.into_py(py),
+        DatumType::Integer32(value) => value.into_py(py),
+        DatumType::Integer64(value) => value.into_py(py),
+        DatumType::Unsigned32(value) => value.into_py(py),
+        DatumType::Unsigned64(value) => value.into_py(py),
+        DatumType::NoValue => py.None(),
+    }
+}
+
+/// Genomic ranges on the sequences of a genome, each with an optional score.
+#[pyclass(name = "GRanges", module = "granges")]
+#[derive(Clone)]
+pub struct PyGRanges {
+    inner: ScoredRanges,
+}
+
+impl PyGRanges {
+    fn from_records<I>(records: I, seqlens: &IndexMap<String, Position>) -> PyResult<Self>
+    where
+        I: Iterator<Item = Result<GenomicRangeRecord<Option<f64>>, GRangesError>>,
+    {
+        let inner = GRanges::from_iter(records, seqlens).map_err(value_error)?;
+        Ok(Self { inner })
+    }
+
+    /// The ranges, as interval trees for overlap queries.
+    fn overlap_index(&self) -> PyResult<GRanges<COITreesIndexed, Vec<Option<f64>>>> {
+        self.inner.clone().into_coitrees().map_err(value_error)
+    }
+}
+
+#[pymethods]
+impl PyGRanges {
+    /// Create ranges on the sequences in `seqlens` (a dict of sequence names and
+    /// lengths) from arrays of sequence names, 0-based starts, and right-exclusive
+    /// ends, and optionally, scores (where NaN is a missing score).
+    #[new]
+    #[pyo3(signature = (seqlens, seqnames, starts, ends, scores=None))]
+    fn new(
+        seqlens: IndexMap<String, Position>,
+        seqnames: Vec<String>,
+        starts: PyReadonlyArray1<i64>,
+        ends: PyReadonlyArray1<i64>,
+        scores: Option<PyReadonlyArray1<f64>>,
+    ) -> PyResult<Self> {
+        let (starts, ends) = (starts.as_slice()?, ends.as_slice()?);
+        let scores = scores
+            .as_ref()
+            .map(|scores| scores.as_slice())
+            .transpose()?;
+        let num_ranges = seqnames.len();
+        if starts.len() != num_ranges
+            || ends.len() != num_ranges
+            || scores.is_some_and(|scores| scores.len() != num_ranges)
+        {
+            return Err(PyValueError::new_err(
+                "all arrays must have the same length",
+            ));
+        }
+
+        let mut inner = GRanges::new_vec(&seqlens);
+        for (i, seqname) in seqnames.iter().enumerate() {
+            let score = scores
+                .map(|scores| scores[i])
+                .filter(|score| !score.is_nan());
+            inner
+                .push_range(
+                    seqname,
+                    to_position(starts[i])?,
+                    to_position(ends[i])?,
+                    score,
+                )
+                .map_err(value_error)?;
+        }
+        Ok(Self { inner })
+    }
+
+    /// Create windows of `width` basepairs across the sequences in `seqlens`, every
+    /// `step` basepairs (by default, `width`). With `chop`, the last window on each
+    /// sequence is dropped if it is shorter than `width`.
+    #[staticmethod]
+    #[pyo3(signature = (seqlens, width, step=None, chop=false))]
+    fn windows(
+        seqlens: IndexMap<String, Position>,
+        width: Position,
+        step: Option<Position>,
+        chop: bool,
+    ) -> PyResult<Self> {
+        if width == 0 || step == Some(0) {
+            return Err(value_error(GRangesError::ZeroWindowSize));
+        }
+        let windows =
+            GRangesEmpty::from_windows(&seqlens, width, step, chop).map_err(value_error)?;
+        let seqnames = windows.seqnames();
+        let records = windows.iter_ranges().map(|range| {
+            let seqname = seqnames[range.seqname_index].clone();
+            Ok(GenomicRangeRecord::new(
+                seqname,
+                range.start,
+                range.end,
+                None,
+            ))
+        });
+        Self::from_records(records, &seqlens)
+    }
+
+    fn __len__(&self) -> usize {
+        self.inner.len()
+    }
+
+    /// The sequence lengths, as a dict.
+    fn seqlens(&self) -> IndexMap<String, Position> {
+        self.inner.seqlens()
+    }
+
+    /// The ranges as a dict of `seqname`, `start`, `end`, and `score` columns (as
+    /// arrays, except for `seqname`), in row order.
+    fn to_dict<'py>(&self, py: Python<'py>) -> PyResult<&'py PyDict> {
+        let num_ranges = self.inner.len();
+        let seqnames = self.inner.seqnames();
+        let mut rows = vec![(0, 0, 0); num_ranges];
+        for range in self.inner.iter_ranges() {
+            if let Some(index) = range.index {
+                rows[index] = (range.seqname_index, range.start, range.end);
+            }
+        }
+        let mut names = Vec::with_capacity(num_ranges);
+        let mut starts = Vec::with_capacity(num_ranges);
+        let mut ends = Vec::with_capacity(num_ranges);
+        for (seqname_index, start, end) in rows {
+            names.push(seqnames[seqname_index].as_str());
+            starts.push(start as i64);
+            ends.push(end as i64);
+        }
+        let scores: Vec<f64> = self
+            .inner
+            .data()
+            .into_iter()
+            .flatten()
+            .map(|score| score.unwrap_or(f64::NAN))
+            .collect();
+
+        let columns = PyDict::new(py);
+        columns.set_item("seqname", names)?;
+        columns.set_item("start", starts.into_pyarray(py))?;
+        columns.set_item("end", ends.into_pyarray(py))?;
+        columns.set_item("score", scores.into_pyarray(py))?;
+        Ok(columns)
+    }
+
+    /// The left grouped overlap join of these ranges with the `right` ranges, as a
+    /// tuple of arrays of the row indices of each overlapping pair of left and right
+    /// ranges.
+    fn join<'py>(
+        &self,
+        py: Python<'py>,
+        right: &PyGRanges,
+    ) -> PyResult<(&'py PyArray1<usize>, &'py PyArray1<usize>)> {
+        let right = right.overlap_index()?;
+        let joins = self
+            .inner
+            .clone()
+            .left_overlaps(&right)
+            .and_then(|joined| {
+                joined.map_joins(|join_data| {
+                    let left = join_data.join.left_index();
+                    (left, join_data.join.right_indices())
+                })
+            })
+            .map_err(value_error)?;
+
+        let (mut left_rows, mut right_rows) = (Vec::new(), Vec::new());
+        for (left, rights) in joins.data().into_iter().flatten() {
+            for right in rights.iter().flatten() {
+                left_rows.extend(*left);
+                right_rows.push(*right);
+            }
+        }
+        Ok((left_rows.into_pyarray(py), right_rows.into_pyarray(py)))
+    }
+
+    /// Apply each of the `granges map` operations in `funcs` (e.g. "mean", "max",
+    /// or "count") to the scores of the `right` ranges overlapping each range,
+    /// returning a dict of result lists (where missing results are `None`).
+    fn map<'py>(
+        &self,
+        py: Python<'py>,
+        right: &PyGRanges,
+        funcs: Vec<String>,
+    ) -> PyResult<&'py PyDict> {
+        let operations = funcs
+            .iter()
+            .map(|name| parse_operation(name))
+            .collect::<PyResult<Vec<_>>>()?;
+        let right = right.overlap_index()?;
+        let results = self
+            .inner
+            .clone()
+            .left_overlaps(&right)
+            .and_then(|joined| {
+                joined.map_joins(|join_data| {
+                    let num_overlaps = join_data.right_data.len();
+                    let mut scores: Vec<f64> = join_data.right_data.into_iter().flatten().collect();
+                    let results: Vec<DatumType> = operations
+                        .iter()
+                        .map(|operation| match operation {
+                            // like granges map, count ranges including those with missing values
+                            FloatOperation::Count => DatumType::Unsigned64(num_overlaps as u64),
+                            _ => operation.run(&mut scores),
+                        })
+                        .collect();
+                    (join_data.join.left_index(), results)
+                })
+            })
+            .map_err(value_error)?;
+
+        // put the results back into the order of the left ranges
+        let mut rows = vec![Vec::new(); self.inner.len()];
+        for (left, row) in results.data().into_iter().flatten() {
+            if let Some(left) = left {
+                rows[*left] = row.clone();
+            }
+        }
+        let columns = PyDict::new(py);
+        for (i, name) in funcs.iter().enumerate() {
+            let column: Vec<PyObject> = rows
+                .iter_mut()
+                .map(|row| datum_to_object(py, std::mem::replace(&mut row[i], DatumType::NoValue)))
+                .collect();
+            columns.set_item(name, column)?;
+        }
+        Ok(columns)
+    }
+
+    /// Merge ranges that are at most `distance` basepairs apart (or, if negative,
+    /// that overlap by at least `-distance` basepairs), summarizing the merged
+    /// ranges' scores with the `func` operation (e.g. "sum"), if given.
+    #[pyo3(signature = (distance=0, func=None))]
+    fn merge(&self, distance: PositionOffset, func: Option<&str>) -> PyResult<Self> {
+        let operation = func.map(parse_operation).transpose()?;
+        if let Some(FloatOperation::Collapse) = operation {
+            return Err(value_error(GRangesError::NonNumericOperation(
+                "collapse".to_string(),
+            )));
+        }
+        let sorted = self.inner.clone().sort();
+        let records = sorted.iter_records().map(Ok);
+        let merged = MergingResultIterator::new(records, distance, |scores: Vec<Option<f64>>| {
+            let operation = operation.as_ref()?;
+            let num_scores = scores.len();
+            let mut scores: Vec<f64> = scores.into_iter().flatten().collect();
+            match operation {
+                FloatOperation::Count => Some(num_scores as f64),
+                _ => match operation.run(&mut scores) {
+                    DatumType::Float64(value) => Some(value),
+                    _ => None,
+                },
+            }
+        });
+        Self::from_records(merged, &self.inner.seqlens())
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "GRanges({} ranges on {} sequences)",
+            self.inner.len(),
+            self.inner.seqnames().len()
+        )
+    }
+}
+
+/// The native module of the `granges` Python package.
+#[pymodule]
+fn _granges(_py: Python<'_>, module: &PyModule) -> PyResult<()> {
+    module.add_class::<PyGRanges>()?;
+    Ok(())
+}
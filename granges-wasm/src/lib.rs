@@ -0,0 +1,188 @@
+//! WebAssembly bindings for the GRanges library, for running genomic range
+//! operations client-side, e.g. in web genome browsers.
+//!
+//! Build the JavaScript package with
+//! [wasm-pack](https://rustwasm.github.io/wasm-pack/), e.g. `wasm-pack build
+//! --target web` in this directory. Since there is no filesystem in the browser,
+//! inputs are read from in-memory buffers (e.g. a `Uint8Array` of a fetched BED
+//! file, which may be gzip-compressed).
+//!
+//! ```js
+//! import init, { RangeSet } from "./pkg/granges_wasm.js";
+//!
+//! await init();
+//! const bytes = async (url) => new Uint8Array(await (await fetch(url)).arrayBuffer());
+//! const peaks = RangeSet.fromBed(await bytes("peaks.bed.gz"), await bytes("hg38.seqlens"));
+//! const rows = peaks.query("chr1", 1000000, 2000000);
+//! ```
+
+use granges::{data::operations::FloatOperation, io::BedlikeIterator, prelude::*};
+use wasm_bindgen::prelude::*;
+
+type ScoredRanges = GRanges<VecRangesIndexed, Vec<Option<f64>>>;
+
+/// A set of ranges, each with an optional score, indexed for overlap queries.
+/// Ranges are identified by their row, i.e. their order in the input.
+#[wasm_bindgen]
+pub struct RangeSet {
+    seqnames: Vec<String>,
+    /// The sequence index, start, and end of each row.
+    rows: Vec<(usize, Position, Position)>,
+    ranges: ScoredRanges,
+    index: GRanges<COITreesIndexed, Vec<Option<f64>>>,
+}
+
+#[wasm_bindgen]
+impl RangeSet {
+    /// Read the ranges of a BED file (with scores, if it has a fifth column), on
+    /// the sequences of a TSV genome file.
+    #[wasm_bindgen(js_name = fromBed)]
+    pub fn from_bed(bed: &[u8], genome: &[u8]) -> Result<RangeSet, JsError> {
+        let seqlens = read_seqlens_from_bytes(genome)?;
+        let mut ranges = GRanges::new_vec(&seqlens);
+        let mut rows = Vec::new();
+        for record in BedlikeIterator::from_bytes(bed) {
+            let record = record?;
+            let seqname_index = seqlens
+                .get_index_of(&record.seqname)
+                .ok_or_else(|| GRangesError::missing_sequence(record.seqname.clone()))?;
+            // the score is the fifth column, i.e. the second of the remaining columns
+            let score = record
+                .data
+                .as_deref()
+                .and_then(|columns| columns.split('\t').nth(1))
+                .and_then(|score| score.parse().ok());
+            ranges.push_range(&record.seqname, record.start, record.end, score)?;
+            rows.push((seqname_index, record.start, record.end));
+        }
+        let index = ranges.clone().into_coitrees()?;
+        Ok(RangeSet {
+            seqnames: seqlens.into_keys().collect(),
+            rows,
+            ranges,
+            index,
+        })
+    }
+
+    /// The number of ranges.
+    #[wasm_bindgen(getter)]
+    pub fn length(&self) -> usize {
+        self.rows.len()
+    }
+
+    /// The sequence name of each range.
+    pub fn seqnames(&self) -> Vec<String> {
+        self.rows
+            .iter()
+            .map(|&(seqname_index, _, _)| self.seqnames[seqname_index].clone())
+            .collect()
+    }
+
+    /// The 0-based start of each range.
+    pub fn starts(&self) -> Vec<f64> {
+        self.rows
+            .iter()
+            .map(|&(_, start, _)| start as f64)
+            .collect()
+    }
+
+    /// The right-exclusive end of each range.
+    pub fn ends(&self) -> Vec<f64> {
+        self.rows.iter().map(|&(_, _, end)| end as f64).collect()
+    }
+
+    /// The score of each range (`NaN` for ranges without a score).
+    pub fn scores(&self) -> Vec<f64> {
+        self.ranges
+            .data()
+            .into_iter()
+            .flatten()
+            .map(|score| score.unwrap_or(f64::NAN))
+            .collect()
+    }
+
+    /// The rows of the ranges overlapping the region `[start, end)` on sequence
+    /// `seqname`, in increasing order.
+    pub fn query(&self, seqname: &str, start: Position, end: Position) -> Vec<usize> {
+        let mut rows = Vec::new();
+        if let Some(ranges) = self.index.get_ranges(seqname) {
+            ranges.query_overlaps(start, end, |overlap| rows.extend(overlap.index()));
+        }
+        rows.sort_unstable();
+        rows
+    }
+
+    /// The overlaps between these (left) ranges and the `right` ranges, as a flat
+    /// array of pairs of left and right rows, ordered by left row and then right row.
+    pub fn overlaps(&self, right: &RangeSet) -> Result<Vec<usize>, JsError> {
+        let joins = self
+            .ranges
+            .clone()
+            .left_overlaps(&right.index)?
+            .map_joins(|join_data| join_data.join)?;
+        let mut pairs = Vec::new();
+        for join in joins.data().into_iter().flatten() {
+            if let Some(left) = join.left_index() {
+                pairs.extend(
+                    join.right_indices()
+                        .into_iter()
+                        .flatten()
+                        .map(|right| (left, right)),
+                );
+            }
+        }
+        pairs.sort_unstable();
+        Ok(pairs
+            .into_iter()
+            .flat_map(|(left, right)| [left, right])
+            .collect())
+    }
+
+    /// Summarize the scores of the `right` ranges overlapping each range with the
+    /// `granges map` operation `func` (e.g. "mean", "max", or "count"), with `NaN`
+    /// for missing results.
+    pub fn map(&self, right: &RangeSet, func: &str) -> Result<Vec<f64>, JsError> {
+        let operation = parse_operation(func)?;
+        let results = self
+            .ranges
+            .clone()
+            .left_overlaps(&right.index)?
+            .map_joins(|join_data| {
+                let num_overlaps = join_data.right_data.len();
+                let mut scores: Vec<f64> = join_data.right_data.into_iter().flatten().collect();
+                let result = match operation {
+                    // like granges map, count ranges including those with missing values
+                    FloatOperation::Count => Some(num_overlaps as f64),
+                    _ => match operation.run(&mut scores) {
+                        DatumType::Float64(value) => Some(value),
+                        _ => None,
+                    },
+                };
+                (join_data.join.left_index(), result)
+            })?;
+
+        // put the results back into row order
+        let mut values = vec![f64::NAN; self.rows.len()];
+        for (left, result) in results.data().into_iter().flatten() {
+            if let (Some(left), Some(result)) = (left, result) {
+                values[*left] = *result;
+            }
+        }
+        Ok(values)
+    }
+}
+
+/// Parse the name of a numeric `granges map` operation.
+fn parse_operation(name: &str) -> Result<FloatOperation, JsError> {
+    let operation = match name {
+        "sum" => FloatOperation::Sum,
+        "sum-not-empty" => FloatOperation::SumNotEmpty,
+        "min" => FloatOperation::Min,
+        "max" => FloatOperation::Max,
+        "mean" => FloatOperation::Mean,
+        "median" => FloatOperation::Median,
+        "count" => FloatOperation::Count,
+        _ => return Err(JsError::new(&format!("unknown operation '{}'", name))),
+    };
+    Ok(operation)
+}
@@ -0,0 +1,484 @@
+//! Annotation of genomic ranges by their nearest gene.
+//!
+//! A [`GeneIndex`] is built from [`GeneModel`]s (e.g. read with
+//! [`read_gene_models()`](crate::io::parsers::read_gene_models)), and
+//! annotates each range with:
+//!
+//!  1. The nearest gene: the gene overlapping the range with the closest
+//!     transcription start site (TSS), or if none overlap, the gene with the
+//!     smallest gap to the range.
+//!
+//!  2. The signed distance from this gene's TSS to the range, relative to the
+//!     gene's strand: negative distances are upstream, positive distances are
+//!     downstream, and zero means the range contains the TSS.
+//!
+//!  3. The [`GenomicContext`] of the range, considering all genes (not just the
+//!     nearest one): a promoter, exon, intron, or intergenic region, in that order
+//!     of priority.
+//!
+//! These are the annotations added by `granges tag`. Alternatively, like BEDOPS
+//! `closest-features`, each range can be annotated with both its nearest upstream
+//! and downstream genes ([`GeneIndex::closest_features()`]), or with its `k` nearest
+//! genes, in order of distance ([`GeneIndex::nearest()`]).
+//!
+//! # Example
+//!
+//! ```
+//! use granges::annotation::{GeneIndex, GenomicContext};
+//! use granges::io::parsers::{bed::Strand, GeneModel};
+//!
+//! let gene = GeneModel {
+//!     seqname: "chr1".to_string(),
+//!     start: 1000,
+//!     end: 5000,
+//!     strand: Some(Strand::Forward),
+//!     name: "ABC".to_string(),
+//!     exons: vec![(1000, 1200), (4000, 5000)],
+//! };
+//! let index = GeneIndex::new(vec![gene], 500);
+//! let annotation = index.annotate("chr1", 2000, 2100).unwrap();
+//! assert_eq!(annotation.gene.name, "ABC");
+//! assert_eq!(annotation.tss_distance, 1000);
+//! assert_eq!(annotation.context, GenomicContext::Intron);
+//! ```
+
+use std::collections::HashMap;
+
+use crate::{io::parsers::bed::Strand, io::parsers::GeneModel, Position};
+
+/// Where a range falls relative to genes. Variants are ordered by priority, so
+/// a range overlapping both an exon and a promoter is a promoter range.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum GenomicContext {
+    Intergenic,
+    Intron,
+    Exon,
+    Promoter,
+}
+
+impl std::fmt::Display for GenomicContext {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let context = match self {
+            GenomicContext::Intergenic => "intergenic",
+            GenomicContext::Intron => "intron",
+            GenomicContext::Exon => "exon",
+            GenomicContext::Promoter => "promoter",
+        };
+        write!(f, "{}", context)
+    }
+}
+
+/// The annotation of a range by its nearest gene (see the [module documentation](self)).
+#[derive(Clone, Debug)]
+pub struct GeneAnnotation<'a> {
+    pub gene: &'a GeneModel,
+    pub tss_distance: i64,
+    pub context: GenomicContext,
+}
+
+/// The nearest genes upstream and downstream of a range, from
+/// [`GeneIndex::closest_features()`], with their signed distances to the range.
+#[derive(Clone, Debug)]
+pub struct ClosestFeatures<'a> {
+    pub upstream: Option<(&'a GeneModel, i64)>,
+    pub downstream: Option<(&'a GeneModel, i64)>,
+}
+
+/// The genes on one sequence, sorted by start, with the index of the gene with the
+/// greatest end among each gene and those before it (so that overlap queries can
+/// stop scanning back through genes once none can reach the query).
+#[derive(Debug)]
+struct SequenceGenes {
+    genes: Vec<GeneModel>,
+    max_end_index: Vec<usize>,
+    /// The gene indices, sorted by end.
+    by_end: Vec<usize>,
+}
+
+impl SequenceGenes {
+    fn new(mut genes: Vec<GeneModel>) -> Self {
+        genes.sort_by_key(|gene| (gene.start, gene.end));
+        let mut max_end_index = Vec::with_capacity(genes.len());
+        for (i, gene) in genes.iter().enumerate() {
+            match max_end_index.last() {
+                Some(&last) if genes[last].end >= gene.end => max_end_index.push(last),
+                _ => max_end_index.push(i),
+            }
+        }
+        let mut by_end: Vec<usize> = (0..genes.len()).collect();
+        by_end.sort_by_key(|&i| genes[i].end);
+        Self {
+            genes,
+            max_end_index,
+            by_end,
+        }
+    }
+
+    /// The gene ending nearest before (or at) `start`, if any.
+    fn upstream(&self, start: Position) -> Option<&GeneModel> {
+        let i = self.by_end.partition_point(|&i| self.genes[i].end <= start);
+        i.checked_sub(1).map(|i| &self.genes[self.by_end[i]])
+    }
+
+    /// The gene starting nearest after (or at) `end`, if any.
+    fn downstream(&self, end: Position) -> Option<&GeneModel> {
+        let i = self.genes.partition_point(|gene| gene.start < end);
+        self.genes.get(i)
+    }
+
+    /// The genes overlapping the range `[start, end)`.
+    fn overlapping(&self, start: Position, end: Position) -> impl Iterator<Item = &GeneModel> {
+        let right = self.genes.partition_point(|gene| gene.start < end);
+        (0..right)
+            .rev()
+            .take_while(move |&i| self.genes[self.max_end_index[i]].end > start)
+            .map(|i| &self.genes[i])
+            .filter(move |gene| gene.end > start)
+    }
+
+    /// The (at most) `k` genes nearest to the range `[start, end)`, within
+    /// `max_distance` of it, with their signed distances (see [`GeneIndex::nearest()`]).
+    fn nearest(
+        &self,
+        start: Position,
+        end: Position,
+        k: usize,
+        max_distance: Option<Position>,
+    ) -> Vec<(&GeneModel, i64)> {
+        let mut nearest: Vec<_> = self.overlapping(start, end).map(|gene| (gene, 0)).collect();
+        nearest.reverse();
+        nearest.truncate(k);
+
+        // the genes ending before the range, and starting after it, nearest first
+        let upstream = self.by_end.partition_point(|&i| self.genes[i].end <= start);
+        let mut upstream = self.by_end[..upstream]
+            .iter()
+            .rev()
+            .map(|&i| (&self.genes[i], self.genes[i].end as i64 - start as i64))
+            .peekable();
+        let downstream = self.genes.partition_point(|gene| gene.start < end);
+        let mut downstream = self.genes[downstream..]
+            .iter()
+            .map(|gene| (gene, gene.start as i64 - end as i64))
+            .peekable();
+        while nearest.len() < k {
+            let next = match (upstream.peek(), downstream.peek()) {
+                (Some(up), Some(down)) if up.1.unsigned_abs() > down.1.unsigned_abs() => {
+                    downstream.next()
+                }
+                (Some(_), _) => upstream.next(),
+                (None, _) => downstream.next(),
+            };
+            match next {
+                Some((gene, distance))
+                    if !max_distance.is_some_and(|max| distance.abs() > max as i64) =>
+                {
+                    nearest.push((gene, distance))
+                }
+                _ => break,
+            }
+        }
+        nearest
+    }
+
+    /// The gene nearest to the range `[start, end)` that does not overlap it, if any.
+    fn nearest_nonoverlapping(&self, start: Position, end: Position) -> Option<&GeneModel> {
+        let right = self.genes.partition_point(|gene| gene.start < end);
+        let left = right
+            .checked_sub(1)
+            .map(|i| &self.genes[self.max_end_index[i]]);
+        let right = self.genes.get(right);
+        match (left, right) {
+            (Some(left), Some(right)) => {
+                if start - left.end <= right.start - end {
+                    Some(left)
+                } else {
+                    Some(right)
+                }
+            }
+            (left, right) => left.or(right),
+        }
+    }
+}
+
+/// An index of genes, for annotating ranges by their nearest gene and genomic context.
+#[derive(Debug)]
+pub struct GeneIndex {
+    sequences: HashMap<String, SequenceGenes>,
+    promoter_length: Position,
+}
+
+impl GeneIndex {
+    /// Create a new [`GeneIndex`], where promoters are the `promoter_length` basepairs
+    /// upstream of each gene's TSS (and the TSS itself).
+    pub fn new(genes: Vec<GeneModel>, promoter_length: Position) -> Self {
+        let mut by_sequence: HashMap<String, Vec<GeneModel>> = HashMap::new();
+        for gene in genes {
+            by_sequence
+                .entry(gene.seqname.clone())
+                .or_default()
+                .push(gene);
+        }
+        let sequences = by_sequence
+            .into_iter()
+            .map(|(seqname, genes)| (seqname, SequenceGenes::new(genes)))
+            .collect();
+        Self {
+            sequences,
+            promoter_length,
+        }
+    }
+
+    /// Annotate the range `[start, end)` on sequence `seqname` by its nearest gene,
+    /// or return `None` if there are no genes on this sequence.
+    pub fn annotate(
+        &self,
+        seqname: &str,
+        start: Position,
+        end: Position,
+    ) -> Option<GeneAnnotation<'_>> {
+        let sequence = self.sequences.get(seqname)?;
+
+        let gene = sequence
+            .overlapping(start, end)
+            .min_by_key(|gene| tss_distance(gene, start, end).unsigned_abs())
+            .or_else(|| sequence.nearest_nonoverlapping(start, end))?;
+
+        // promoters can extend past their gene, so consider genes within promoter_length
+        let context = sequence
+            .overlapping(
+                start.saturating_sub(self.promoter_length),
+                end.saturating_add(self.promoter_length),
+            )
+            .map(|gene| self.context(gene, start, end))
+            .max()
+            .unwrap_or(GenomicContext::Intergenic);
+
+        Some(GeneAnnotation {
+            gene,
+            tss_distance: tss_distance(gene, start, end),
+            context,
+        })
+    }
+
+    /// The nearest genes upstream and downstream of the range `[start, end)` on
+    /// sequence `seqname` that do not overlap it, or `None` if there are no genes on
+    /// this sequence. Like BEDOPS `closest-features`, upstream and downstream are
+    /// relative to the sequence (not the genes' strands): the upstream gene is the
+    /// one ending closest before the range, with a negative distance (the number of
+    /// basepairs between them), and the downstream gene is the one starting closest
+    /// after it, with a positive distance. Book-ended genes have a distance of zero.
+    pub fn closest_features(
+        &self,
+        seqname: &str,
+        start: Position,
+        end: Position,
+    ) -> Option<ClosestFeatures<'_>> {
+        let sequence = self.sequences.get(seqname)?;
+        let upstream = sequence
+            .upstream(start)
+            .map(|gene| (gene, gene.end as i64 - start as i64));
+        let downstream = sequence
+            .downstream(end)
+            .map(|gene| (gene, gene.start as i64 - end as i64));
+        Some(ClosestFeatures {
+            upstream,
+            downstream,
+        })
+    }
+
+    /// The (at most) `k` genes nearest to the range `[start, end)` on sequence
+    /// `seqname`, in order of distance, with their signed distances to the range, as
+    /// in [`GeneIndex::closest_features()`]. Overlapping genes come first, with a
+    /// distance of zero (in order of their starts), followed by the nearest upstream
+    /// or downstream genes (with ties going to the upstream gene). With a
+    /// `max_distance`, genes further than it from the range are excluded. Sequences
+    /// without genes have no nearest genes.
+    pub fn nearest(
+        &self,
+        seqname: &str,
+        start: Position,
+        end: Position,
+        k: usize,
+        max_distance: Option<Position>,
+    ) -> Vec<(&GeneModel, i64)> {
+        self.sequences
+            .get(seqname)
+            .map(|sequence| sequence.nearest(start, end, k, max_distance))
+            .unwrap_or_default()
+    }
+
+    /// The [`GenomicContext`] of the range `[start, end)` relative to a single gene.
+    /// Genes without exons are treated as a single exon.
+    fn context(&self, gene: &GeneModel, start: Position, end: Position) -> GenomicContext {
+        let tss = gene.tss();
+        let (promoter_start, promoter_end) = match gene.strand {
+            Some(Strand::Reverse) => (tss, tss.saturating_add(self.promoter_length + 1)),
+            _ => (tss.saturating_sub(self.promoter_length), tss + 1),
+        };
+        if promoter_start < end && start < promoter_end {
+            return GenomicContext::Promoter;
+        }
+        if gene.end <= start || end <= gene.start {
+            return GenomicContext::Intergenic;
+        }
+        let i = gene
+            .exons
+            .partition_point(|&(_, exon_end)| exon_end <= start);
+        let in_exon = gene
+            .exons
+            .get(i)
+            .is_some_and(|&(exon_start, _)| exon_start < end);
+        if in_exon || gene.exons.is_empty() {
+            GenomicContext::Exon
+        } else {
+            GenomicContext::Intron
+        }
+    }
+}
+
+/// The signed distance from the TSS of `gene` to the range `[start, end)`, relative
+/// to the gene's strand (negative is upstream), or zero if the range contains the TSS.
+fn tss_distance(gene: &GeneModel, start: Position, end: Position) -> i64 {
+    let tss = gene.tss() as i64;
+    let (start, end) = (start as i64, end as i64);
+    let distance = if tss < start {
+        start - tss
+    } else if tss >= end {
+        end - 1 - tss
+    } else {
+        0
+    };
+    match gene.strand {
+        Some(Strand::Reverse) => -distance,
+        _ => distance,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gene(name: &str, start: Position, end: Position, strand: Strand) -> GeneModel {
+        GeneModel {
+            seqname: "chr1".to_string(),
+            start,
+            end,
+            strand: Some(strand),
+            name: name.to_string(),
+            exons: vec![(start, start + 100), (end - 100, end)],
+        }
+    }
+
+    fn test_index() -> GeneIndex {
+        let genes = vec![
+            gene("A", 1000, 2000, Strand::Forward),
+            gene("B", 5000, 9000, Strand::Reverse),
+            // nested in B
+            gene("C", 6000, 6500, Strand::Forward),
+        ];
+        GeneIndex::new(genes, 500)
+    }
+
+    #[test]
+    fn test_annotate_overlapping() {
+        let index = test_index();
+
+        // in B's intron, but closer to C's TSS
+        let annotation = index.annotate("chr1", 6200, 6250).unwrap();
+        assert_eq!(annotation.gene.name, "C");
+        assert_eq!(annotation.tss_distance, 200);
+        assert_eq!(annotation.context, GenomicContext::Intron);
+
+        // B's first exon, on the reverse strand
+        let annotation = index.annotate("chr1", 8950, 8960).unwrap();
+        assert_eq!(annotation.gene.name, "B");
+        assert_eq!(annotation.tss_distance, 40);
+        assert_eq!(annotation.context, GenomicContext::Exon);
+    }
+
+    #[test]
+    fn test_annotate_nonoverlapping() {
+        let index = test_index();
+
+        // upstream of A, in its promoter
+        let annotation = index.annotate("chr1", 700, 800).unwrap();
+        assert_eq!(annotation.gene.name, "A");
+        assert_eq!(annotation.tss_distance, -201);
+        assert_eq!(annotation.context, GenomicContext::Promoter);
+
+        // upstream of B (which is on the reverse strand), in its promoter
+        let annotation = index.annotate("chr1", 9200, 9300).unwrap();
+        assert_eq!(annotation.gene.name, "B");
+        assert_eq!(annotation.tss_distance, -201);
+        assert_eq!(annotation.context, GenomicContext::Promoter);
+
+        // between A and B, closer to the end of A
+        let annotation = index.annotate("chr1", 3000, 3100).unwrap();
+        assert_eq!(annotation.gene.name, "A");
+        assert_eq!(annotation.tss_distance, 2000);
+        assert_eq!(annotation.context, GenomicContext::Intergenic);
+
+        assert!(index.annotate("chr2", 0, 10).is_none());
+    }
+
+    #[test]
+    fn test_closest_features() {
+        let index = test_index();
+
+        // between A and B
+        let closest = index.closest_features("chr1", 3000, 3100).unwrap();
+        let (upstream, distance) = closest.upstream.unwrap();
+        assert_eq!((upstream.name.as_str(), distance), ("A", -1000));
+        let (downstream, distance) = closest.downstream.unwrap();
+        assert_eq!((downstream.name.as_str(), distance), ("B", 1900));
+
+        // in B (overlapping genes are skipped), after the end of C
+        let closest = index.closest_features("chr1", 7000, 7100).unwrap();
+        let (upstream, distance) = closest.upstream.unwrap();
+        assert_eq!((upstream.name.as_str(), distance), ("C", -500));
+        assert!(closest.downstream.is_none());
+
+        // book-ended with A
+        let closest = index.closest_features("chr1", 500, 1000).unwrap();
+        assert!(closest.upstream.is_none());
+        assert_eq!(closest.downstream.unwrap().1, 0);
+
+        assert!(index.closest_features("chr2", 0, 10).is_none());
+    }
+
+    #[test]
+    fn test_nearest() {
+        let index = test_index();
+        let names = |nearest: Vec<(&GeneModel, i64)>| -> Vec<(String, i64)> {
+            nearest
+                .into_iter()
+                .map(|(gene, distance)| (gene.name.clone(), distance))
+                .collect()
+        };
+
+        // between A and B
+        let nearest = index.nearest("chr1", 3000, 3100, 3, None);
+        assert_eq!(
+            names(nearest),
+            vec![
+                ("A".to_string(), -1000),
+                ("B".to_string(), 1900),
+                ("C".to_string(), 2900)
+            ]
+        );
+        let nearest = index.nearest("chr1", 3000, 3100, 3, Some(2000));
+        assert_eq!(nearest.len(), 2);
+
+        // overlapping genes come first
+        let nearest = index.nearest("chr1", 7000, 7100, 2, None);
+        assert_eq!(
+            names(nearest),
+            vec![("B".to_string(), 0), ("C".to_string(), -500)]
+        );
+        let nearest = index.nearest("chr1", 6100, 6200, 1, None);
+        assert_eq!(names(nearest), vec![("B".to_string(), 0)]);
+
+        assert!(index.nearest("chr2", 0, 10, 1, None).is_empty());
+    }
+}
@@ -0,0 +1,363 @@
+//! Aggregation of scores into genome-wide, fixed-width bins.
+//!
+//! A [`BinnedMatrix`] has a row for each bin of the genome, and a column for each
+//! input (e.g. a score file), where each entry summarizes the values of the input's
+//! ranges overlapping the bin with a [`FloatOperation`]. This bins × inputs matrix
+//! is the starting point for genome-wide correlation or PCA analyses of many
+//! tracks. Each input's ranges are aggregated in parallel across sequences.
+//!
+//! Bins are in sorted order (the order GRanges outputs sequences), and the last bin
+//! on each sequence is truncated at the sequence end.
+//!
+//! Windows can also be created in memory as a [`GRangesEmpty`], with [`tile_genome()`]
+//! (fixed-width, possibly overlapping windows across the genome, like
+//! `granges windows`), or [`tile_ranges()`] (splitting each range into equal parts).
+//! These can be used directly with overlap operations, e.g.
+//! [`LeftOverlaps::left_overlaps()`](crate::traits::LeftOverlaps::left_overlaps).
+//!
+//! # Example
+//!
+//! ```
+//! use granges::{binning::BinnedMatrix, data::operations::FloatOperation, prelude::*};
+//! use indexmap::IndexMap;
+//!
+//! let seqlens = seqlens! { "chr1" => 25 };
+//! let mut matrix = BinnedMatrix::new(&seqlens, 10).unwrap();
+//! assert_eq!(matrix.num_bins(), 3);
+//!
+//! let mut ranges = IndexMap::new();
+//! ranges.insert("chr1".to_string(), vec![(0, 15, Some(2.0)), (5, 8, Some(4.0))]);
+//! matrix.add_column("sample", ranges, &FloatOperation::Sum).unwrap();
+//! assert_eq!(matrix.column(0), &[Some(6.0), Some(2.0), Some(0.0)]);
+//! ```
+
+use std::io::Write;
+
+use indexmap::IndexMap;
+use rayon::prelude::*;
+
+use crate::{
+    data::{operations::FloatOperation, DatumType},
+    error::GRangesError,
+    granges::GRangesEmpty,
+    ranges::vec::VecRangesEmpty,
+    streaming::sequence_order,
+    traits::{AsGRangesRef, IterableRangeContainer},
+    Position,
+};
+
+/// A range, and its (possibly missing) value.
+pub type ValuedRange = (Position, Position, Option<f64>);
+
+/// A sequence, which is divided into bins.
+#[derive(Clone, Debug)]
+struct SequenceBins {
+    seqname: String,
+    length: Position,
+}
+
+impl SequenceBins {
+    fn num_bins(&self, width: Position) -> usize {
+        self.length.div_ceil(width) as usize
+    }
+}
+
+/// A matrix of the values of several inputs, aggregated in fixed-width bins
+/// across the genome (see the [module documentation](self)).
+#[derive(Clone, Debug)]
+pub struct BinnedMatrix {
+    width: Position,
+    sequences: Vec<SequenceBins>,
+    num_bins: usize,
+    names: Vec<String>,
+    columns: Vec<Vec<Option<f64>>>,
+}
+
+impl BinnedMatrix {
+    /// Create a new [`BinnedMatrix`] with no columns, with bins of `width`
+    /// basepairs across the sequences in `seqlens`.
+    pub fn new(
+        seqlens: &IndexMap<String, Position>,
+        width: Position,
+    ) -> Result<Self, GRangesError> {
+        if width == 0 {
+            return Err(GRangesError::ZeroWindowSize);
+        }
+        let mut sequences = Vec::with_capacity(seqlens.len());
+        let mut num_bins = 0;
+        for seqname in sequence_order(seqlens)?.names() {
+            let sequence = SequenceBins {
+                length: seqlens[&seqname],
+                seqname,
+            };
+            num_bins += sequence.num_bins(width);
+            sequences.push(sequence);
+        }
+        Ok(Self {
+            width,
+            sequences,
+            num_bins,
+            names: Vec::new(),
+            columns: Vec::new(),
+        })
+    }
+
+    /// The number of bins (i.e. rows).
+    pub fn num_bins(&self) -> usize {
+        self.num_bins
+    }
+
+    /// The names of the columns.
+    pub fn names(&self) -> &[String] {
+        &self.names
+    }
+
+    /// The values of the `i`th column, in bin order.
+    pub fn column(&self, i: usize) -> &[Option<f64>] {
+        &self.columns[i]
+    }
+
+    /// Iterate over the bins, as `(seqname, start, end)`.
+    pub fn bins(&self) -> impl Iterator<Item = (&str, Position, Position)> + '_ {
+        self.sequences.iter().flat_map(move |sequence| {
+            (0..sequence.num_bins(self.width)).map(move |bin| {
+                let start = bin as Position * self.width;
+                let end = (start + self.width).min(sequence.length);
+                (sequence.seqname.as_str(), start, end)
+            })
+        })
+    }
+
+    /// Add a column named `name`, summarizing the values of the `ranges` (grouped by
+    /// sequence) overlapping each bin with `operation`. Bins without overlapping ranges
+    /// are summarized as an empty set of values (e.g. a sum of zero, or a missing mean).
+    /// Sequences are aggregated in parallel.
+    pub fn add_column(
+        &mut self,
+        name: impl Into<String>,
+        ranges: IndexMap<String, Vec<ValuedRange>>,
+        operation: &FloatOperation,
+    ) -> Result<(), GRangesError> {
+        if let FloatOperation::Collapse = operation {
+            return Err(GRangesError::NonNumericOperation("collapse".to_string()));
+        }
+        if let Some(seqname) = ranges
+            .keys()
+            .find(|seqname| !self.sequences.iter().any(|seq| &seq.seqname == *seqname))
+        {
+            return Err(GRangesError::missing_sequence(seqname));
+        }
+
+        let width = self.width;
+        let sequence_columns: Vec<Vec<Option<f64>>> = self
+            .sequences
+            .par_iter()
+            .map(|sequence| {
+                let seq_ranges = ranges
+                    .get(&sequence.seqname)
+                    .map_or(&[][..], |seq_ranges| seq_ranges.as_slice());
+                aggregate_bins(seq_ranges, sequence.num_bins(width), width, operation)
+            })
+            .collect();
+        self.names.push(name.into());
+        self.columns.push(sequence_columns.concat());
+        Ok(())
+    }
+
+    /// Write the matrix as a TSV with a header, with a row for each bin of its range
+    /// (`chrom`, `start`, and `end`) and values, where missing values are written
+    /// as `no_value`.
+    pub fn write_tsv<W: Write>(&self, mut writer: W, no_value: &str) -> Result<(), GRangesError> {
+        let mut header = vec!["chrom", "start", "end"];
+        header.extend(self.names.iter().map(|name| name.as_str()));
+        writeln!(writer, "{}", header.join("\t"))?;
+        for (row, (seqname, start, end)) in self.bins().enumerate() {
+            write!(writer, "{}\t{}\t{}", seqname, start, end)?;
+            for column in &self.columns {
+                match column[row] {
+                    Some(value) => write!(writer, "\t{}", value)?,
+                    None => write!(writer, "\t{}", no_value)?,
+                }
+            }
+            writeln!(writer)?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Create a new [`DataFrame`](polars::prelude::DataFrame) with `chrom`, `start`,
+    /// and `end` columns, followed by a column for each input.
+    #[cfg(feature = "polars")]
+    pub fn to_dataframe(&self) -> Result<polars::prelude::DataFrame, GRangesError> {
+        use polars::prelude::*;
+        let mut columns = crate::data::polars::range_series(self.bins());
+        for (name, values) in self.names.iter().zip(&self.columns) {
+            columns.push(Series::new(name, values));
+        }
+        Ok(DataFrame::new(columns)?)
+    }
+}
+
+/// Tile the sequences in `genome` with windows of `width` basepairs, starting every
+/// `step` basepairs (use `step = width` for adjacent windows). The last window on
+/// each sequence is truncated at the sequence end.
+pub fn tile_genome(
+    genome: &IndexMap<String, Position>,
+    width: Position,
+    step: Position,
+) -> Result<GRangesEmpty<VecRangesEmpty>, GRangesError> {
+    if width == 0 || step == 0 {
+        return Err(GRangesError::ZeroWindowSize);
+    }
+    GRangesEmpty::from_windows(genome, width, Some(step), false)
+}
+
+/// Split each range of `granges` into `n` adjacent tiles of (nearly) equal width.
+/// Ranges narrower than `n` basepairs are split into single-basepair tiles.
+pub fn tile_ranges<'a, C, T>(
+    granges: &'a impl AsGRangesRef<'a, C, T>,
+    n: usize,
+) -> Result<GRangesEmpty<VecRangesEmpty>, GRangesError>
+where
+    C: IterableRangeContainer + 'a,
+    T: 'a,
+{
+    if n == 0 {
+        return Err(GRangesError::ZeroWindowSize);
+    }
+    let granges = granges.as_granges_ref();
+    let mut tiles = GRangesEmpty::new_vec(&granges.seqlens());
+    for range in granges.iter_named_ranges() {
+        let width = u64::from(range.end - range.start);
+        let mut start = range.start;
+        for tile in 1..=n as u64 {
+            // the tile end offset is at most the range width, so it fits a Position
+            let offset = Position::try_from(width * tile / n as u64).unwrap();
+            let end = range.start + offset;
+            if end > start {
+                tiles.push_range(range.seqname, start, end)?;
+                start = end;
+            }
+        }
+    }
+    Ok(tiles)
+}
+
+/// Summarize the values of the `ranges` on a sequence, in each of its `num_bins` bins
+/// of `width` basepairs.
+fn aggregate_bins(
+    ranges: &[ValuedRange],
+    num_bins: usize,
+    width: Position,
+    operation: &FloatOperation,
+) -> Vec<Option<f64>> {
+    let mut values: Vec<Vec<f64>> = vec![Vec::new(); num_bins];
+    let mut num_overlaps = vec![0_usize; num_bins];
+    for &(start, end, value) in ranges {
+        if end <= start || num_bins == 0 {
+            continue;
+        }
+        let first = (start / width) as usize;
+        let last = (((end - 1) / width) as usize).min(num_bins - 1);
+        for bin in first..=last {
+            num_overlaps[bin] += 1;
+            if let Some(value) = value {
+                values[bin].push(value);
+            }
+        }
+    }
+    values
+        .iter_mut()
+        .zip(num_overlaps)
+        .map(|(bin_values, num_overlaps)| match operation {
+            // like granges map, count ranges including those with missing values
+            FloatOperation::Count => Some(num_overlaps as f64),
+            _ => match operation.run(bin_values) {
+                DatumType::Float64(value) => Some(value),
+                _ => None,
+            },
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::*;
+
+    #[test]
+    fn test_binned_matrix() {
+        let seqlens = seqlens! { "chr2" => 20, "chr1" => 15 };
+        let mut matrix = BinnedMatrix::new(&seqlens, 10).unwrap();
+        let bins: Vec<_> = matrix.bins().collect();
+        assert_eq!(
+            bins,
+            vec![
+                ("chr1", 0, 10),
+                ("chr1", 10, 15),
+                ("chr2", 0, 10),
+                ("chr2", 10, 20)
+            ]
+        );
+
+        let mut ranges = IndexMap::new();
+        ranges.insert("chr2".to_string(), vec![(5, 12, Some(1.0)), (8, 9, None)]);
+        matrix
+            .add_column("a", ranges.clone(), &FloatOperation::Mean)
+            .unwrap();
+        matrix
+            .add_column("b", ranges, &FloatOperation::Count)
+            .unwrap();
+        assert_eq!(matrix.column(0), &[None, None, Some(1.0), Some(1.0)]);
+        assert_eq!(
+            matrix.column(1),
+            &[Some(0.0), Some(0.0), Some(2.0), Some(1.0)]
+        );
+
+        let mut tsv = Vec::new();
+        matrix.write_tsv(&mut tsv, ".").unwrap();
+        let tsv = String::from_utf8(tsv).unwrap();
+        let mut lines = tsv.lines();
+        assert_eq!(lines.next(), Some("chrom\tstart\tend\ta\tb"));
+        assert_eq!(lines.next(), Some("chr1\t0\t10\t.\t0"));
+    }
+
+    #[test]
+    fn test_tile_genome() {
+        let seqlens = seqlens! { "chr1" => 25 };
+        let windows: Vec<_> = tile_genome(&seqlens, 10, 10)
+            .unwrap()
+            .iter_named_ranges()
+            .map(|range| (range.start, range.end))
+            .collect();
+        assert_eq!(windows, vec![(0, 10), (10, 20), (20, 25)]);
+        assert!(tile_genome(&seqlens, 10, 0).is_err());
+    }
+
+    #[test]
+    fn test_tile_ranges() {
+        let seqlens = seqlens! { "chr1" => 100 };
+        let gr =
+            GRangesEmpty::from_vectors(&["chr1", "chr1"], &[0, 50], &[10, 52], &seqlens).unwrap();
+        let tiles: Vec<_> = tile_ranges(&gr, 3)
+            .unwrap()
+            .iter_named_ranges()
+            .map(|range| (range.start, range.end))
+            .collect();
+        assert_eq!(tiles, vec![(0, 3), (3, 6), (6, 10), (50, 51), (51, 52)]);
+    }
+
+    #[test]
+    fn test_binned_matrix_errors() {
+        let seqlens = seqlens! { "chr1" => 15 };
+        assert!(BinnedMatrix::new(&seqlens, 0).is_err());
+
+        let mut matrix = BinnedMatrix::new(&seqlens, 10).unwrap();
+        let mut ranges = IndexMap::new();
+        ranges.insert("chrX".to_string(), vec![(0, 1, Some(1.0))]);
+        assert!(matches!(
+            matrix.add_column("a", ranges, &FloatOperation::Sum),
+            Err(GRangesError::MissingSequence { .. })
+        ));
+    }
+}
@@ -0,0 +1,499 @@
+//! Block-bootstrap confidence intervals for statistics of the overlap between two
+//! sets of genomic ranges.
+//!
+//! Resampling individual ranges treats them as independent, but genomic ranges
+//! cluster (e.g. peaks in gene-dense regions), so confidence intervals from an
+//! ordinary bootstrap are too narrow. A block bootstrap instead resamples large
+//! blocks of the genome (e.g. 1Mb windows) with replacement, keeping the dependence
+//! between nearby ranges within each block.
+//!
+//! A [`BlockBootstrap`] tiles the genome into blocks, and counts the basepairs
+//! covered in each by the left ranges, the right ranges, and both (see
+//! [`BlockCounts`]). Each bootstrap replicate draws as many blocks as there are,
+//! with replacement, and sums their counts, from which the overlap statistics (see
+//! [`OverlapStatistic`]) are computed. With a block length shorter than the gaps
+//! between ranges, this approaches an ordinary bootstrap of the ranges. These are
+//! the confidence intervals reported by `granges bootstrap`.
+//!
+//! # Example
+//!
+//! ```
+//! use granges::bootstrap::{BlockBootstrap, OverlapStatistic};
+//! use granges::enrichment::Intervals;
+//! use granges::prelude::*;
+//!
+//! let seqlens = seqlens! { "chr1" => 1000 };
+//! let left = Intervals::from_ranges(vec![("chr1".to_string(), vec![(0, 100)])]);
+//! let right = Intervals::from_ranges(vec![("chr1".to_string(), vec![(50, 150)])]);
+//! let bootstrap = BlockBootstrap::new(&left, &right, &seqlens, 250, None).unwrap();
+//! assert_eq!(bootstrap.blocks().len(), 4);
+//! assert_eq!(OverlapStatistic::Overlap.value(&bootstrap.observed()), 50.0);
+//!
+//! let results = bootstrap.confidence_intervals(100, 0.95, 1).unwrap();
+//! assert_eq!(results.statistics.len(), OverlapStatistic::ALL.len());
+//! ```
+
+use std::{io::Write, iter::Sum, ops::AddAssign};
+
+use clap::ValueEnum;
+use indexmap::IndexMap;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use rayon::prelude::*;
+use serde::Serialize;
+
+use crate::{
+    enrichment::{intersection_bases, Intervals},
+    error::GRangesError,
+    ranges::{operations::complement_intervals, GenomicRangeRecord},
+    stats::StatsFormat,
+    Position,
+};
+
+/// The basepairs of a block (or of a bootstrap replicate, the sum of its blocks)
+/// covered by the left ranges, the right ranges, and both.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize)]
+pub struct BlockCounts {
+    /// The number of basepairs in the block (less any gaps).
+    pub length: u64,
+    pub left: u64,
+    pub right: u64,
+    pub both: u64,
+}
+
+impl AddAssign for BlockCounts {
+    fn add_assign(&mut self, other: Self) {
+        self.length += other.length;
+        self.left += other.left;
+        self.right += other.right;
+        self.both += other.both;
+    }
+}
+
+impl Sum for BlockCounts {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        let mut total = Self::default();
+        for counts in iter {
+            total += counts;
+        }
+        total
+    }
+}
+
+/// A statistic of the overlap between two sets of ranges, computed from the
+/// basepairs they cover (see [`BlockCounts`]).
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum OverlapStatistic {
+    /// The number of basepairs covered by both sets.
+    Overlap,
+    /// The basepairs covered by both sets, divided by those covered by either.
+    Jaccard,
+    /// The fraction of the basepairs covered by the left ranges that are also
+    /// covered by the right ranges.
+    FractionLeft,
+    /// The fraction of the basepairs covered by the right ranges that are also
+    /// covered by the left ranges.
+    FractionRight,
+    /// The ratio of the basepairs covered by both sets to the number expected if the
+    /// sets were independent (as in `granges fisher`).
+    FoldEnrichment,
+}
+
+impl OverlapStatistic {
+    /// All the overlap statistics, in the order they're reported.
+    pub const ALL: [OverlapStatistic; 5] = [
+        OverlapStatistic::Overlap,
+        OverlapStatistic::Jaccard,
+        OverlapStatistic::FractionLeft,
+        OverlapStatistic::FractionRight,
+        OverlapStatistic::FoldEnrichment,
+    ];
+
+    /// The name of this statistic in the output of `granges bootstrap` and `granges
+    /// significance`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            OverlapStatistic::Overlap => "overlap",
+            OverlapStatistic::Jaccard => "jaccard",
+            OverlapStatistic::FractionLeft => "fraction_left",
+            OverlapStatistic::FractionRight => "fraction_right",
+            OverlapStatistic::FoldEnrichment => "fold_enrichment",
+        }
+    }
+
+    /// The value of this statistic for the basepairs in `counts`. Ratios with a
+    /// denominator of zero are zero, except the fold enrichment, which is not
+    /// finite (as in [`crate::enrichment::FisherTest`]).
+    pub fn value(&self, counts: &BlockCounts) -> f64 {
+        let ratio = |numerator: u64, denominator: u64| {
+            if denominator == 0 {
+                return 0.0;
+            }
+            numerator as f64 / denominator as f64
+        };
+        match self {
+            OverlapStatistic::Overlap => counts.both as f64,
+            OverlapStatistic::Jaccard => {
+                ratio(counts.both, counts.left + counts.right - counts.both)
+            }
+            OverlapStatistic::FractionLeft => ratio(counts.both, counts.left),
+            OverlapStatistic::FractionRight => ratio(counts.both, counts.right),
+            OverlapStatistic::FoldEnrichment => {
+                let expected = counts.left as f64 * counts.right as f64 / counts.length as f64;
+                counts.both as f64 / expected
+            }
+        }
+    }
+}
+
+/// The parts of the sorted, non-overlapping `intervals` between `start` and `end`.
+fn clip(
+    intervals: &[(Position, Position)],
+    start: Position,
+    end: Position,
+) -> Vec<(Position, Position)> {
+    let first = intervals.partition_point(|&(_, interval_end)| interval_end <= start);
+    intervals[first..]
+        .iter()
+        .take_while(|&&(interval_start, _)| interval_start < end)
+        .map(|&(interval_start, interval_end)| (interval_start.max(start), interval_end.min(end)))
+        .collect()
+}
+
+/// The number of basepairs covered by sorted, non-overlapping intervals.
+fn covered_bases(intervals: &[(Position, Position)]) -> u64 {
+    intervals
+        .iter()
+        .map(|(start, end)| (end - start) as u64)
+        .sum()
+}
+
+/// The value at quantile `p` of the `sorted` values, interpolated linearly between
+/// the nearest ranks (like R's default `quantile()`).
+fn quantile(sorted: &[f64], p: f64) -> f64 {
+    let rank = p * (sorted.len() - 1) as f64;
+    let (lower, upper) = (rank.floor() as usize, rank.ceil() as usize);
+    sorted[lower] + (rank - lower as f64) * (sorted[upper] - sorted[lower])
+}
+
+/// A block bootstrap of the overlap between two sets of ranges (see the [module
+/// documentation](self)).
+#[derive(Clone, Debug)]
+pub struct BlockBootstrap {
+    blocks: Vec<GenomicRangeRecord<BlockCounts>>,
+    block_length: Position,
+}
+
+impl BlockBootstrap {
+    /// Tile the sequences in `seqlens` into blocks of `block_length` (the last
+    /// block of each sequence may be shorter), and count the basepairs covered in
+    /// each by the `left` and `right` intervals. Basepairs in `gaps` (e.g. assembly
+    /// gaps; see [`crate::gaps`]) are left out of every block, and blocks entirely
+    /// in gaps are dropped.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`GRangesError::InvalidBootstrap`] if the block length is zero, or
+    /// no block has any basepairs.
+    pub fn new(
+        left: &Intervals,
+        right: &Intervals,
+        seqlens: &IndexMap<String, Position>,
+        block_length: Position,
+        gaps: Option<&Intervals>,
+    ) -> Result<Self, GRangesError> {
+        if block_length == 0 {
+            return Err(GRangesError::InvalidBootstrap(
+                "the block length must be greater than zero".to_string(),
+            ));
+        }
+        let (left, right) = match gaps {
+            Some(gaps) => (left.subtract(gaps), right.subtract(gaps)),
+            None => (left.clone(), right.clone()),
+        };
+        let mut blocks = Vec::new();
+        for (seqname, &length) in seqlens {
+            let seq_gaps = gaps.map_or(&[][..], |gaps| gaps.on(seqname));
+            for start in (0..length).step_by(block_length as usize) {
+                let end = start.saturating_add(block_length).min(length);
+                let ungapped = complement_intervals(clip(seq_gaps, start, end), &[(start, end)]);
+                let block_left = clip(left.on(seqname), start, end);
+                let block_right = clip(right.on(seqname), start, end);
+                let counts = BlockCounts {
+                    length: covered_bases(&ungapped),
+                    left: covered_bases(&block_left),
+                    right: covered_bases(&block_right),
+                    both: intersection_bases(&block_left, &block_right),
+                };
+                if counts.length > 0 {
+                    blocks.push(GenomicRangeRecord::new(seqname.clone(), start, end, counts));
+                }
+            }
+        }
+        if blocks.is_empty() {
+            return Err(GRangesError::InvalidBootstrap(
+                "there are no blocks outside of gaps".to_string(),
+            ));
+        }
+        Ok(Self {
+            blocks,
+            block_length,
+        })
+    }
+
+    /// The blocks, and the basepairs covered in each.
+    pub fn blocks(&self) -> &[GenomicRangeRecord<BlockCounts>] {
+        &self.blocks
+    }
+
+    /// The basepairs covered in all blocks, i.e. the observed data.
+    pub fn observed(&self) -> BlockCounts {
+        self.blocks.iter().map(|block| block.data).sum()
+    }
+
+    /// Draw one bootstrap replicate: as many blocks as there are, with
+    /// replacement, and the sum of their counts.
+    pub fn resample(&self, rng: &mut impl Rng) -> BlockCounts {
+        (0..self.blocks.len())
+            .map(|_| self.blocks[rng.gen_range(0..self.blocks.len())].data)
+            .sum()
+    }
+
+    /// Draw `replicates` bootstrap replicates, in parallel. Each uses a random
+    /// number generator seeded with `seed` plus its index, so results are
+    /// reproducible regardless of the number of threads.
+    pub fn replicates(&self, replicates: usize, seed: u64) -> Vec<BlockCounts> {
+        (0..replicates)
+            .into_par_iter()
+            .map(|i| self.resample(&mut StdRng::seed_from_u64(seed.wrapping_add(i as u64))))
+            .collect()
+    }
+
+    /// Percentile confidence intervals of each overlap statistic (see
+    /// [`OverlapStatistic::ALL`]), at level `confidence` (e.g. 0.95), over
+    /// `replicates` bootstrap replicates (see [`BlockBootstrap::replicates()`]).
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`GRangesError::InvalidBootstrap`] if there are no replicates, or
+    /// the confidence level is not between zero and one.
+    pub fn confidence_intervals(
+        &self,
+        replicates: usize,
+        confidence: f64,
+        seed: u64,
+    ) -> Result<BootstrapResults, GRangesError> {
+        if replicates == 0 {
+            return Err(GRangesError::InvalidBootstrap(
+                "the number of replicates must be greater than zero".to_string(),
+            ));
+        }
+        if !(confidence > 0.0 && confidence < 1.0) {
+            return Err(GRangesError::InvalidBootstrap(format!(
+                "the confidence level {} is not between 0 and 1",
+                confidence
+            )));
+        }
+        let observed = self.observed();
+        let samples = self.replicates(replicates, seed);
+        let alpha = (1.0 - confidence) / 2.0;
+        let statistics = OverlapStatistic::ALL
+            .iter()
+            .map(|&statistic| {
+                let mut values: Vec<f64> = samples
+                    .iter()
+                    .map(|counts| statistic.value(counts))
+                    .collect();
+                values.sort_by(f64::total_cmp);
+                let mean = values.iter().sum::<f64>() / replicates as f64;
+                let variance = values
+                    .iter()
+                    .map(|value| (value - mean).powi(2))
+                    .sum::<f64>()
+                    / (replicates.max(2) - 1) as f64;
+                BootstrapInterval {
+                    statistic,
+                    observed: statistic.value(&observed),
+                    mean,
+                    std_error: variance.sqrt(),
+                    lower: quantile(&values, alpha),
+                    upper: quantile(&values, 1.0 - alpha),
+                }
+            })
+            .collect();
+        Ok(BootstrapResults {
+            block_length: self.block_length,
+            blocks: self.blocks.len(),
+            replicates,
+            confidence,
+            statistics,
+        })
+    }
+}
+
+/// The bootstrap distribution of an [`OverlapStatistic`], and its percentile
+/// confidence interval.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct BootstrapInterval {
+    pub statistic: OverlapStatistic,
+    /// The value of the statistic for the observed data.
+    pub observed: f64,
+    /// The mean of the statistic over the bootstrap replicates.
+    pub mean: f64,
+    /// The standard deviation of the statistic over the bootstrap replicates.
+    pub std_error: f64,
+    pub lower: f64,
+    pub upper: f64,
+}
+
+/// The results of a block bootstrap, as reported by `granges bootstrap`.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct BootstrapResults {
+    pub block_length: Position,
+    /// The number of blocks.
+    pub blocks: usize,
+    pub replicates: usize,
+    pub confidence: f64,
+    pub statistics: Vec<BootstrapInterval>,
+}
+
+impl BootstrapResults {
+    /// Write these results in the specified format. The TSV format is a table of
+    /// each statistic and its observed value, bootstrap mean and standard error, and
+    /// confidence interval.
+    pub fn write(&self, mut writer: impl Write, format: &StatsFormat) -> Result<(), GRangesError> {
+        match format {
+            StatsFormat::Text => {
+                writeln!(writer, "# block bootstrap")?;
+                writeln!(writer, "block length:        {}", self.block_length)?;
+                writeln!(writer, "blocks:              {}", self.blocks)?;
+                writeln!(writer, "replicates:          {}", self.replicates)?;
+                writeln!(writer, "confidence:          {}", self.confidence)?;
+                writeln!(
+                    writer,
+                    "{:<16}{:>14}{:>14}{:>14}{:>14}{:>14}",
+                    "statistic", "observed", "mean", "std. error", "lower", "upper"
+                )?;
+                for interval in &self.statistics {
+                    writeln!(
+                        writer,
+                        "{:<16}{:>14.4}{:>14.4}{:>14.4}{:>14.4}{:>14.4}",
+                        interval.statistic.name(),
+                        interval.observed,
+                        interval.mean,
+                        interval.std_error,
+                        interval.lower,
+                        interval.upper
+                    )?;
+                }
+            }
+            StatsFormat::Json => {
+                serde_json::to_writer_pretty(&mut writer, self)
+                    .map_err(|e| GRangesError::ReportError(e.to_string()))?;
+                writeln!(writer)?;
+            }
+            StatsFormat::Tsv => {
+                writeln!(writer, "statistic\tobserved\tmean\tstd_error\tlower\tupper")?;
+                for interval in &self.statistics {
+                    writeln!(
+                        writer,
+                        "{}\t{}\t{}\t{}\t{}\t{}",
+                        interval.statistic.name(),
+                        interval.observed,
+                        interval.mean,
+                        interval.std_error,
+                        interval.lower,
+                        interval.upper
+                    )?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::seqlens;
+
+    #[test]
+    fn test_clip() {
+        let intervals = [(0, 10), (20, 30), (40, 50)];
+        assert_eq!(clip(&intervals, 5, 25), vec![(5, 10), (20, 25)]);
+        assert_eq!(clip(&intervals, 10, 20), vec![]);
+        assert_eq!(clip(&intervals, 45, 100), vec![(45, 50)]);
+    }
+
+    #[test]
+    fn test_quantile() {
+        let values = [1.0, 2.0, 3.0, 4.0, 5.0];
+        assert_eq!(quantile(&values, 0.0), 1.0);
+        assert_eq!(quantile(&values, 0.5), 3.0);
+        assert_eq!(quantile(&values, 0.125), 1.5);
+        assert_eq!(quantile(&values, 1.0), 5.0);
+    }
+
+    #[test]
+    fn test_blocks() {
+        let seqlens = seqlens! { "chr1" => 250, "chr2" => 100 };
+        let left = Intervals::from_ranges(vec![("chr1".to_string(), vec![(90, 120)])]);
+        let right = Intervals::from_ranges(vec![("chr1".to_string(), vec![(100, 200)])]);
+        let gaps = Intervals::from_ranges(vec![("chr2".to_string(), vec![(0, 100)])]);
+        let bootstrap = BlockBootstrap::new(&left, &right, &seqlens, 100, Some(&gaps)).unwrap();
+
+        // chr2 is entirely a gap, and the last block of chr1 is shorter
+        let blocks: Vec<_> = bootstrap
+            .blocks()
+            .iter()
+            .map(|block| (block.start, block.end, block.data))
+            .collect();
+        let counts = |length, left, right, both| BlockCounts {
+            length,
+            left,
+            right,
+            both,
+        };
+        assert_eq!(
+            blocks,
+            vec![
+                (0, 100, counts(100, 10, 0, 0)),
+                (100, 200, counts(100, 20, 100, 20)),
+                (200, 250, counts(50, 0, 0, 0)),
+            ]
+        );
+        assert_eq!(bootstrap.observed(), counts(250, 30, 100, 20));
+        assert!(BlockBootstrap::new(&left, &right, &seqlens, 0, None).is_err());
+    }
+
+    #[test]
+    fn test_confidence_intervals() {
+        let seqlens = seqlens! { "chr1" => 1000 };
+        let left = Intervals::from_ranges(vec![(
+            "chr1".to_string(),
+            vec![(0, 10), (100, 150), (500, 520)],
+        )]);
+        let right = Intervals::from_ranges(vec![(
+            "chr1".to_string(),
+            vec![(5, 10), (120, 200), (900, 950)],
+        )]);
+        let bootstrap = BlockBootstrap::new(&left, &right, &seqlens, 100, None).unwrap();
+        let results = bootstrap.confidence_intervals(200, 0.9, 1).unwrap();
+        // replicates are reproducible
+        assert_eq!(
+            results,
+            bootstrap.confidence_intervals(200, 0.9, 1).unwrap()
+        );
+
+        for interval in &results.statistics {
+            assert!(interval.lower <= interval.upper);
+            assert!(interval.std_error >= 0.0);
+        }
+        let overlap = &results.statistics[0];
+        assert_eq!(overlap.statistic, OverlapStatistic::Overlap);
+        assert_eq!(overlap.observed, 35.0);
+
+        assert!(bootstrap.confidence_intervals(0, 0.9, 1).is_err());
+        assert!(bootstrap.confidence_intervals(10, 1.0, 1).is_err());
+    }
+}
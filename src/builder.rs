@@ -0,0 +1,214 @@
+//! A builder for constructing [`GRanges`] objects programmatically.
+//!
+//! [`GRangesBuilder`] collects ranges and their data, pushed in any order, and
+//! then sorts and indexes them into a [`GRanges<COITreesIndexed, Vec<U>>`] with
+//! [`GRangesBuilder::build()`]. Sequence names are interned, so each is stored
+//! only once, however many ranges are on it.
+//!
+//! If sequence lengths are given (with [`GRangesBuilder::with_seqlens()`]), each
+//! range is checked against them as it is pushed. Otherwise, any sequence name is
+//! accepted, and each sequence's length is the largest end position of its ranges.
+//!
+//! # Example
+//!
+//! ```
+//! use granges::{builder::GRangesBuilder, prelude::*};
+//!
+//! let mut builder = GRangesBuilder::new();
+//! builder.push("chr2", 5, 10, 1.0).unwrap();
+//! builder.push("chr1", 20, 30, 2.0).unwrap();
+//! builder.push("chr1", 0, 10, 3.0).unwrap();
+//!
+//! let gr = builder.build().unwrap();
+//! assert_eq!(gr.len(), 3);
+//! assert_eq!(gr.seqlens(), seqlens! { "chr2" => 10, "chr1" => 30 });
+//! ```
+
+use indexmap::IndexMap;
+
+use crate::{
+    error::GRangesError,
+    granges::GRanges,
+    ranges::{coitrees::COITreesIndexed, vec::VecRangesIndexed, GenomicRangeRecord},
+    Position,
+};
+
+/// Collects ranges with data in any order, to build an indexed [`GRanges`] (see
+/// the [module documentation](self)).
+#[derive(Clone, Debug)]
+pub struct GRangesBuilder<U> {
+    /// The interned sequence names, and their lengths.
+    seqlens: IndexMap<String, Position>,
+    /// Whether the sequence lengths were given, rather than inferred.
+    fixed_seqlens: bool,
+    /// The ranges, as (sequence index, start, end).
+    ranges: Vec<(usize, Position, Position)>,
+    data: Vec<U>,
+}
+
+impl<U> Default for GRangesBuilder<U> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<U> GRangesBuilder<U> {
+    /// Create a new [`GRangesBuilder`] that accepts ranges on any sequence, and
+    /// infers the sequence lengths from the ranges.
+    pub fn new() -> Self {
+        Self {
+            seqlens: IndexMap::new(),
+            fixed_seqlens: false,
+            ranges: Vec::new(),
+            data: Vec::new(),
+        }
+    }
+
+    /// Create a new [`GRangesBuilder`] that only accepts ranges on the sequences in
+    /// `seqlens`, within their lengths. The built [`GRanges`] has these sequences,
+    /// in this order.
+    pub fn with_seqlens(seqlens: &IndexMap<String, Position>) -> Self {
+        Self {
+            seqlens: seqlens.clone(),
+            fixed_seqlens: true,
+            ranges: Vec::new(),
+            data: Vec::new(),
+        }
+    }
+
+    /// Push the range `[start, end)` on sequence `seqname`, with its data.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`GRangesError::InvalidGenomicRange`] if `start` is greater than
+    /// `end`. If sequence lengths were given, this returns a
+    /// [`GRangesError::MissingSequence`] for a sequence that is not in them, or a
+    /// [`GRangesError::InvalidGenomicRangeForSequence`] for a range that extends past
+    /// the sequence end.
+    pub fn push(
+        &mut self,
+        seqname: &str,
+        start: Position,
+        end: Position,
+        data: U,
+    ) -> Result<(), GRangesError> {
+        if start > end {
+            return Err(GRangesError::InvalidGenomicRange {
+                seqname: Some(seqname.to_string()),
+                start,
+                end,
+            });
+        }
+        let index = match self.seqlens.get_full_mut(seqname) {
+            Some((index, _, &mut length)) if self.fixed_seqlens => {
+                if end > length {
+                    return Err(GRangesError::InvalidGenomicRangeForSequence {
+                        seqname: Some(seqname.to_string()),
+                        start,
+                        end,
+                        length,
+                    });
+                }
+                index
+            }
+            Some((index, _, length)) => {
+                *length = (*length).max(end);
+                index
+            }
+            None if self.fixed_seqlens => return Err(GRangesError::missing_sequence(seqname)),
+            None => self.seqlens.insert_full(seqname.to_string(), end).0,
+        };
+        self.ranges.push((index, start, end));
+        self.data.push(data);
+        Ok(())
+    }
+
+    /// Push a [`GenomicRangeRecord`] (see [`GRangesBuilder::push()`]).
+    pub fn push_record(&mut self, record: GenomicRangeRecord<U>) -> Result<(), GRangesError> {
+        self.push(&record.seqname, record.start, record.end, record.data)
+    }
+
+    /// The number of ranges pushed so far.
+    pub fn len(&self) -> usize {
+        self.ranges.len()
+    }
+
+    /// Whether no ranges have been pushed.
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    /// Build a [`GRanges`] with vector range containers, with the ranges of each
+    /// sequence sorted by start and end position.
+    pub fn build_vec(self) -> Result<GRanges<VecRangesIndexed, Vec<U>>, GRangesError> {
+        let mut gr = GRanges::new_vec(&self.seqlens);
+        for ((index, start, end), data) in self.ranges.into_iter().zip(self.data) {
+            let (seqname, _) = self
+                .seqlens
+                .get_index(index)
+                .expect("Internal error: please report");
+            gr.push_range(seqname, start, end, data)?;
+        }
+        Ok(gr.sort())
+    }
+
+    /// Build an indexed [`GRanges`], with interval tree range containers for fast
+    /// overlap queries.
+    pub fn build(self) -> Result<GRanges<COITreesIndexed, Vec<U>>, GRangesError> {
+        self.build_vec()?.into_coitrees()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::*;
+
+    #[test]
+    fn test_builder_unsorted() {
+        let mut builder = GRangesBuilder::new();
+        builder.push("chr2", 5, 10, "a").unwrap();
+        builder.push("chr1", 20, 30, "b").unwrap();
+        builder.push("chr1", 0, 10, "c").unwrap();
+        assert_eq!(builder.len(), 3);
+
+        let gr = builder.build_vec().unwrap();
+        assert_eq!(gr.seqlens(), seqlens! { "chr2" => 10, "chr1" => 30 });
+        let records: Vec<_> = gr
+            .iter_records()
+            .map(|record| (record.seqname, record.start, record.end, record.data))
+            .collect();
+        assert_eq!(
+            records,
+            vec![
+                ("chr2".to_string(), 5, 10, "a"),
+                ("chr1".to_string(), 0, 10, "c"),
+                ("chr1".to_string(), 20, 30, "b"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_builder_seqlens() {
+        let seqlens = seqlens! { "chr1" => 25, "chr2" => 100 };
+        let mut builder = GRangesBuilder::with_seqlens(&seqlens);
+        builder.push("chr2", 5, 10, 1.0).unwrap();
+
+        assert!(matches!(
+            builder.push("chr3", 0, 10, 2.0),
+            Err(GRangesError::MissingSequence { .. })
+        ));
+        assert!(matches!(
+            builder.push("chr1", 20, 30, 2.0),
+            Err(GRangesError::InvalidGenomicRangeForSequence { length: 25, .. })
+        ));
+        assert!(matches!(
+            builder.push("chr1", 10, 5, 2.0),
+            Err(GRangesError::InvalidGenomicRange { .. })
+        ));
+
+        let gr = builder.build().unwrap();
+        assert_eq!(gr.len(), 1);
+        assert_eq!(gr.seqlens(), seqlens);
+    }
+}
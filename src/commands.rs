@@ -2,40 +2,309 @@
 //!
 // TODO: these functions should be methods of the input struct.
 
-use clap::Parser;
+use clap::{Parser, ValueEnum};
 use csv::{Writer, WriterBuilder};
+use genomap::GenomeMap;
+use indexmap::IndexMap;
+use rand::Rng;
+use rayon::prelude::*;
 use std::{
-    collections::HashMap,
-    fs::File,
-    io::{self, Write},
-    path::PathBuf,
+    collections::{BTreeMap, HashMap, HashSet},
+    io::{self, BufRead, Write},
+    num::NonZeroUsize,
+    path::{Path, PathBuf},
+    sync::{mpsc, OnceLock},
+    thread,
 };
+use tracing::{debug, info, info_span, trace};
 
 use crate::{
-    data::{operations::FloatOperation, SerializableDatumType},
+    annotation::GeneIndex,
+    binning::{BinnedMatrix, ValuedRange},
+    bootstrap::{BlockBootstrap, OverlapStatistic},
+    comparison::Tolerance,
+    data::{
+        columns::{column_position, datum_into},
+        operations::{carry_columns, Aggregate, ColumnCarry, EmptyPolicy, FloatOperation},
+        SerializableDatumType,
+    },
+    enrichment::{EnrichmentTests, FisherTest, Intervals, MonteCarloTest, PermutationTest},
+    expr::Expr,
+    gaps::{read_gaps, ungapped_regions},
     io::{
-        parsers::{Bed5Iterator, GenomicRangesParser},
-        tsv::BED_TSV,
+        columnar::{create_columnar_output, ColumnarFormat},
+        file::{create_output, is_arrow_ipc_path, is_parquet_path, InputStream},
+        parsers::{
+            bed::{bedlike::SAF_HEADER, Strand},
+            bedpe::BedpeIterator,
+            cytoband::Cytobands,
+            genes::parse_strand,
+            read_gene_models, read_transcript_models,
+            utils::is_header_line,
+            Bed5Iterator, BoundsPolicy, BoundsPolicyExt, FilteredRanges, GenomicRangesParser,
+            TranscriptFeature, TranscriptModel,
+        },
+        track::{parse_color, write_track_hub, HubTrack, TrackLine},
+        tsv::{is_na_value, na_value, BEDTOOLS_PRECISION, BED_TSV},
         TsvConfig,
     },
-    merging_iterators::{MergingEmptyResultIterator, MergingResultIterator},
+    join::{DuplicateKeyPolicy, KeyJoin},
+    merging_iterators::{
+        GroupedMergingResultIterator, MergingEmptyResultIterator, MergingResultIterator,
+    },
+    paired::{GRangesPaired, PairFormat, PairToBedType, PairToPairType, RangePair},
+    parallel::MIN_CHUNK_LENGTH,
     prelude::*,
-    ranges::{operations::adjust_range, GenomicRangeRecord, GenomicRangeRecordEmpty},
-    reporting::{CommandOutput, Report},
-    test_utilities::{random_granges, random_granges_mock_bed5},
+    random::{
+        random_granges_mock_bed5_with_rng, random_granges_with_rng, seed, seeded_rng,
+        MatchedFeature, RandomRanges, WidthDistribution, DEFAULT_MAX_ATTEMPTS,
+    },
+    ranges::{
+        checked_width,
+        operations::{
+            adjust_range_with_policy, center_range, complement_intervals, merge_intervals,
+            shift_range, InvertedRangePolicy,
+        },
+        GenomicRangeRecord, GenomicRangeRecordEmpty,
+    },
+    reporting::{CommandOutput, ProgressExt, Report},
+    sequences::{
+        nucleotide::{gc_content_strict, NucleotideSequences},
+        rle::{Rle, RleList},
+    },
+    stats::{
+        count_overlapping_pairs, ranges_by_sequence, Histogram, Normalization, RangeStats,
+        SequenceSummary, StatsFormat,
+    },
+    streaming::{is_sorted, sequence_order, GenomicWindows, StreamingDepth, StreamingLeftOverlaps},
+    traits::{AdjustableGenericRange, GenomicRecord, RangeContainer, Sequences},
     unique_id::UniqueIdentifier,
     Position, PositionOffset,
 };
 
+/// Create an output file, with its path in any error. Files with a gzip extension are
+/// compressed (see [`create_output()`]).
+fn create_file(path: impl AsRef<Path>) -> Result<Box<dyn Write + Send>, GRangesError> {
+    let path = path.as_ref();
+    create_output(path).map_err(|source| GRangesError::FileError {
+        path: path.to_path_buf(),
+        source,
+    })
+}
+
+/// The default size (in bytes) of the batches output is written in.
+pub const DEFAULT_OUTPUT_BUFFER_SIZE: usize = 1 << 20;
+
+/// How TSV output is batched (see [`BatchedWriter`]).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct OutputBuffering {
+    /// The size (in bytes) of each batch of output.
+    pub size: usize,
+    /// Whether batches are written by a background thread, so that processing
+    /// continues while they are written.
+    pub background: bool,
+}
+
+impl Default for OutputBuffering {
+    fn default() -> Self {
+        Self {
+            size: DEFAULT_OUTPUT_BUFFER_SIZE,
+            background: false,
+        }
+    }
+}
+
+static OUTPUT_BUFFERING: OnceLock<OutputBuffering> = OnceLock::new();
+
+/// Set how TSV output is batched, globally.
+///
+/// Like [`set_num_threads()`](crate::parallel::set_num_threads), this must be called
+/// before any output is written, and can only be called once.
+pub fn set_output_buffering(buffering: OutputBuffering) -> Result<(), GRangesError> {
+    OUTPUT_BUFFERING
+        .set(buffering)
+        .map_err(|buffering| GRangesError::OutputBufferingAlreadySet(buffering.size))
+}
+
+/// How TSV output is batched (see [`set_output_buffering()`]).
+pub fn output_buffering() -> OutputBuffering {
+    *OUTPUT_BUFFERING.get_or_init(OutputBuffering::default)
+}
+
+/// A message to the background thread of a [`BatchedWriter`].
+enum Batch {
+    Data(Vec<u8>),
+    /// Flush the output, and acknowledge (with any error) on this channel.
+    Flush(mpsc::SyncSender<io::Result<()>>),
+}
+
+/// Where a [`BatchedWriter`] writes its batches.
+enum BatchSink {
+    Direct(Box<dyn Write + Send>),
+    Background {
+        sender: Option<mpsc::SyncSender<Batch>>,
+        handle: Option<thread::JoinHandle<io::Result<()>>>,
+    },
+}
+
+/// A writer that collects output into large batches, so that each write to the
+/// underlying file (or standard output, which is otherwise flushed at every line) is
+/// one system call per batch, rather than per record. With background writing, the
+/// batches are written by another thread, while the next one is filled.
+pub struct BatchedWriter {
+    buffer: Vec<u8>,
+    size: usize,
+    sink: BatchSink,
+}
+
+impl BatchedWriter {
+    /// Create a new [`BatchedWriter`], writing to `inner` in batches as set by
+    /// `buffering`.
+    pub fn new(inner: Box<dyn Write + Send>, buffering: OutputBuffering) -> Self {
+        let size = buffering.size.max(1);
+        let sink = if buffering.background {
+            // one batch can be written while the next is filled
+            let (sender, receiver) = mpsc::sync_channel::<Batch>(1);
+            let handle = thread::spawn(move || {
+                let mut inner = inner;
+                for batch in receiver {
+                    match batch {
+                        Batch::Data(data) => inner.write_all(&data)?,
+                        Batch::Flush(ack) => {
+                            let _ = ack.send(inner.flush());
+                        }
+                    }
+                }
+                inner.flush()
+            });
+            BatchSink::Background {
+                sender: Some(sender),
+                handle: Some(handle),
+            }
+        } else {
+            BatchSink::Direct(inner)
+        };
+        Self {
+            buffer: Vec::with_capacity(size),
+            size,
+            sink,
+        }
+    }
+
+    /// Send a message to the background thread, raising its error if it has stopped.
+    fn send(
+        sender: &mpsc::SyncSender<Batch>,
+        handle: &mut Option<thread::JoinHandle<io::Result<()>>>,
+        batch: Batch,
+    ) -> io::Result<()> {
+        if sender.send(batch).is_ok() {
+            return Ok(());
+        }
+        match handle.take().map(|handle| handle.join()) {
+            Some(Ok(Err(err))) => Err(err),
+            _ => Err(io::Error::new(
+                io::ErrorKind::BrokenPipe,
+                "the output writer thread stopped",
+            )),
+        }
+    }
+
+    /// Write the current batch.
+    fn write_batch(&mut self) -> io::Result<()> {
+        if self.buffer.is_empty() {
+            return Ok(());
+        }
+        match &mut self.sink {
+            BatchSink::Direct(inner) => {
+                inner.write_all(&self.buffer)?;
+                self.buffer.clear();
+            }
+            BatchSink::Background { sender, handle } => {
+                let batch = std::mem::replace(&mut self.buffer, Vec::with_capacity(self.size));
+                let sender = sender.as_ref().expect("Internal error: please report");
+                Self::send(sender, handle, Batch::Data(batch))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Write for BatchedWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.buffer.extend_from_slice(buf);
+        if self.buffer.len() >= self.size {
+            self.write_batch()?;
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.write_batch()?;
+        match &mut self.sink {
+            BatchSink::Direct(inner) => inner.flush(),
+            BatchSink::Background { sender, handle } => {
+                let (ack, acknowledged) = mpsc::sync_channel(1);
+                let sender = sender.as_ref().expect("Internal error: please report");
+                Self::send(sender, handle, Batch::Flush(ack))?;
+                acknowledged.recv().unwrap_or_else(|_| {
+                    Err(io::Error::new(
+                        io::ErrorKind::BrokenPipe,
+                        "the output writer thread stopped",
+                    ))
+                })
+            }
+        }
+    }
+}
+
+impl Drop for BatchedWriter {
+    /// Write the last batch, and wait for the background thread (if any) to finish
+    /// (errors are ignored, as they can't be raised here; call
+    /// [`flush()`](Write::flush) first to handle them).
+    fn drop(&mut self) {
+        let _ = self.write_batch();
+        if let BatchSink::Background { sender, handle } = &mut self.sink {
+            // closing the channel stops the thread
+            sender.take();
+            if let Some(handle) = handle.take() {
+                let _ = handle.join();
+            }
+        }
+    }
+}
+
+/// Open the output file (or standard output, if `None`) for batched writing (see
+/// [`BatchedWriter`]).
+fn batched_output(output: Option<&PathBuf>) -> Result<Box<dyn Write>, GRangesError> {
+    let inner: Box<dyn Write + Send> = match output {
+        Some(path) => create_file(path)?,
+        None => Box::new(io::stdout()),
+    };
+    Ok(Box::new(BatchedWriter::new(inner, output_buffering())))
+}
+
+/// The output for range table rows (e.g. BED), which is a columnar file if `output`
+/// has an Arrow IPC or Parquet extension (see [`ColumnarFormat`]), and otherwise a
+/// batched TSV output (see [`batched_output()`]).
+fn range_table_output(
+    output: Option<&PathBuf>,
+    has_header: bool,
+) -> Result<Box<dyn Write>, GRangesError> {
+    match output.and_then(|path| Some((path, ColumnarFormat::from_path(path)?))) {
+        Some((path, format)) => {
+            let columnar: Box<dyn Write> = create_columnar_output(path, format, has_header)?;
+            Ok(columnar)
+        }
+        None => batched_output(output),
+    }
+}
+
 /// Build a new TSV writer
 pub fn build_tsv_writer(
     output: Option<impl Into<PathBuf>>,
 ) -> Result<Writer<Box<dyn Write>>, GRangesError> {
     let output = output.map(|path| path.into());
-    let writer_boxed: Box<dyn io::Write> = match &output {
-        Some(path) => Box::new(File::create(path)?),
-        None => Box::new(io::stdout()),
-    };
+    let writer_boxed = batched_output(output.as_ref())?;
 
     let writer = WriterBuilder::new()
         .delimiter(b'\t')
@@ -45,6 +314,14 @@ pub fn build_tsv_writer(
     Ok(writer)
 }
 
+/// Build a new TSV writer for BED-like output, configured with [`BED_TSV`] (so
+/// data columns containing tabs are written as-is, not quoted).
+pub fn build_bed_writer(
+    output: Option<impl Into<PathBuf>>,
+) -> Result<Writer<Box<dyn Write>>, GRangesError> {
+    build_tsv_writer_with_config(output, &BED_TSV)
+}
+
 /// Build a new TSV writer with config, i.e. for manual headers, metadata etc.
 // TODO: use proper builder pattern here and above?
 pub fn build_tsv_writer_with_config(
@@ -52,10 +329,7 @@ pub fn build_tsv_writer_with_config(
     config: &TsvConfig,
 ) -> Result<Writer<Box<dyn Write>>, GRangesError> {
     let output = output.map(|path| path.into());
-    let mut writer_boxed: Box<dyn io::Write> = match &output {
-        Some(path) => Box::new(File::create(path)?),
-        None => Box::new(io::stdout()),
-    };
+    let mut writer_boxed = range_table_output(output.as_ref(), config.headers.is_some())?;
 
     // Write metadata, if there.
     if let Some(metadata_rows) = &config.metadata {
@@ -72,6 +346,7 @@ pub fn build_tsv_writer_with_config(
     let writer = WriterBuilder::new()
         .delimiter(b'\t')
         .has_headers(false)
+        .quote_style(config.quote_style)
         .from_writer(writer_boxed);
 
     Ok(writer)
@@ -82,20 +357,177 @@ pub fn build_tsv_writer_with_config(
 pub enum ProcessingMode {
     Streaming,
     InMemory,
+    /// Process the input in batches of at most this many ranges, each on a single
+    /// sequence, writing the results of each batch before the next is read.
+    Chunked(usize),
+    /// Use the streaming algorithm if the inputs are sorted, and the in-memory one
+    /// (which sorts them) otherwise. The inputs are checked with an extra streaming
+    /// pass over each (see [`is_sorted()`]).
+    Auto,
+}
+
+/// Resolve [`ProcessingMode::Auto`] to [`ProcessingMode::Streaming`] if all the
+/// BED-like inputs at `paths` are sorted, and to [`ProcessingMode::InMemory`]
+/// otherwise. Other modes are returned as they are.
+fn resolve_processing_mode(
+    mode: ProcessingMode,
+    paths: &[&PathBuf],
+    genome: &IndexMap<String, Position>,
+    skip_missing: bool,
+    bounds: BoundsPolicy,
+) -> Result<ProcessingMode, GRangesError> {
+    let ProcessingMode::Auto = mode else {
+        return Ok(mode);
+    };
+    let _span = info_span!("sort check").entered();
+    for path in paths {
+        let ranges = checked_ranges(BedlikeIterator::new(*path)?, genome, skip_missing, bounds);
+        if !is_sorted(ranges, genome)? {
+            info!(
+                "{} is not sorted, so the inputs are joined in memory",
+                path.display()
+            );
+            return Ok(ProcessingMode::InMemory);
+        }
+    }
+    info!("inputs are sorted, so they are joined by streaming");
+    Ok(ProcessingMode::Streaming)
+}
+
+/// What [`granges_filter()`] writes for the left ranges.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FilterOutput {
+    /// The left ranges with at least one overlap (a semi-join).
+    #[default]
+    Overlapping,
+    /// The left ranges with no overlaps (an anti-join, like `bedtools intersect -v`).
+    NonOverlapping,
+    /// Every left range, with its number of overlaps appended (like
+    /// `bedtools intersect -c`).
+    Count,
+}
+
+/// Box a parsing iterator, retaining only ranges on sequences in `genome` if
+/// `skip_missing` is set, and applying the [`BoundsPolicy`] `bounds` to the rest.
+fn checked_ranges<I, R>(
+    iter: I,
+    genome: &IndexMap<String, Position>,
+    skip_missing: bool,
+    bounds: BoundsPolicy,
+) -> Box<dyn Iterator<Item = Result<R, GRangesError>>>
+where
+    I: GeneralRangeRecordIterator<R> + 'static,
+    R: GenomicRecord + AdjustableGenericRange + 'static,
+    FilteredRanges<I, R>: Iterator<Item = Result<R, GRangesError>>,
+{
+    if skip_missing {
+        let seqnames: Vec<String> = genome.keys().cloned().collect();
+        Box::new(
+            iter.retain_seqnames(&seqnames)
+                .with_bounds_policy(genome, bounds),
+        )
+    } else {
+        Box::new(iter.with_bounds_policy(genome, bounds))
+    }
+}
+
+/// Load the regions at `path` to exclude from a join (e.g. the ENCODE blacklist), as
+/// interval trees for [`excluding_ranges()`]. Regions on sequences not in `genome`
+/// can't overlap any ranges, so they're skipped.
+fn load_excluded(
+    path: Option<&PathBuf>,
+    genome: &IndexMap<String, Position>,
+    bounds: BoundsPolicy,
+) -> Result<Option<GRangesEmpty<COITreesEmpty>>, GRangesError> {
+    let Some(path) = path else {
+        return Ok(None);
+    };
+    let _span = info_span!("parse excluded").entered();
+    let regions = checked_ranges(Bed3Iterator::new(path)?, genome, true, bounds);
+    let excluded = GRangesEmpty::from_iter(regions, genome)?.into_coitrees()?;
+    Ok(Some(excluded))
+}
+
+/// Remove the parsed ranges that overlap any of the `excluded` regions, if any.
+fn excluding_ranges<'a, R>(
+    iter: impl Iterator<Item = Result<R, GRangesError>> + 'a,
+    excluded: Option<&'a GRangesEmpty<COITreesEmpty>>,
+) -> impl Iterator<Item = Result<R, GRangesError>> + 'a
+where
+    R: GenomicRecord,
+{
+    iter.filter(move |result| match (excluded, result) {
+        (Some(excluded), Ok(range)) => excluded
+            .on(range.seqname())
+            .is_none_or(|view| view.count_overlaps(range.start(), range.end()) == 0),
+        _ => true,
+    })
+}
+
+/// Whether adjusting a range by `start_delta` and `end_delta` would extend it past
+/// either end of its sequence, so that it is truncated.
+fn is_truncated(
+    start: Position,
+    end: Position,
+    start_delta: PositionOffset,
+    end_delta: PositionOffset,
+    length: Position,
+) -> bool {
+    (start as PositionOffset) + start_delta < 0
+        || (end as PositionOffset) + end_delta > length as PositionOffset
+}
+
+/// Adjust each of the parsed ranges by `start_delta` and `end_delta` (see
+/// [`adjust_range_with_policy()`]), removing those that are dropped, and counting
+/// the kept ranges that were truncated in `truncated`.
+fn adjust_records<'a, R>(
+    iter: impl Iterator<Item = Result<R, GRangesError>> + 'a,
+    genome: &'a IndexMap<String, Position>,
+    start_delta: PositionOffset,
+    end_delta: PositionOffset,
+    inverted: InvertedRangePolicy,
+    truncated: &'a mut usize,
+) -> impl Iterator<Item = Result<R, GRangesError>> + 'a
+where
+    R: AdjustableGenericRange + GenomicRecord + 'a,
+{
+    iter.filter_map(move |record| {
+        let adjust = |range: R| -> Result<Option<R>, GRangesError> {
+            let seqname = range.seqname().to_string();
+            let length = *genome
+                .get(&seqname)
+                .ok_or_else(|| GRangesError::missing_sequence(&seqname))?;
+            let was_truncated =
+                is_truncated(range.start(), range.end(), start_delta, end_delta, length);
+            let adjusted =
+                adjust_range_with_policy(range, start_delta, end_delta, length, inverted)
+                    .map_err(|error| error.with_seqname(&seqname))?;
+            if adjusted.is_some() && was_truncated {
+                *truncated += 1;
+            }
+            Ok(adjusted)
+        };
+        record.and_then(adjust).transpose()
+    })
 }
 
 /// Adjusts genomic ranges in a BED file by a specified amount.
 ///
 /// This function modifies the start and end positions of each range in the input BED file based on
 /// the provided offsets, ensuring the adjusted ranges do not exceed the sequence lengths specified
-/// in the `seqlens` file. If sorting is enabled, the output will be sorted based on the sequence names
-/// and start positions.
+/// in the `seqlens` file. Negative offsets shrink ranges; ranges that would be inverted are handled
+/// according to `inverted` (see [`InvertedRangePolicy`] for how this compares to `bedtools slop`).
+/// If sorting is enabled, the output will be sorted based on the sequence names and start
+/// positions.
 ///
 /// # Arguments
 ///
 /// * `bedfile` - A reference to a `PathBuf` for the input BED file.
 /// * `seqlens` - A reference to a `PathBuf` for the file containing sequence lengths.
-/// * `both` - A [`PositionOffset`] specifying how much to adjust the start and end positions.
+/// * `left` - A [`PositionOffset`] specifying how much to move the start positions leftwards
+///   (i.e. to extend the ranges by, or to shrink them by if negative).
+/// * `right` - A [`PositionOffset`] specifying how much to move the end positions rightwards.
+/// * `inverted` - The [`InvertedRangePolicy`] for ranges that shrinking would invert.
 /// * `output` - An optional reference to a `PathBuf` where the adjusted ranges will be written. Writes
 ///   to stdout if `None`.
 /// * `sort` - A boolean indicating whether to sort the output.
@@ -106,22 +538,24 @@ pub enum ProcessingMode {
 ///
 /// # Errors
 ///
-/// Returns `GRangesError` if the input BED file or sequence lengths file cannot be read, or if
-/// an adjusted range exceeds the sequence boundaries.
+/// Returns `GRangesError` if the input BED file or sequence lengths file cannot be read, if
+/// an adjusted range exceeds the sequence boundaries, or if a range would be inverted with
+/// [`InvertedRangePolicy::Error`].
 pub fn granges_adjust(
     bedfile: &PathBuf,
     seqlens: &PathBuf,
-    both: PositionOffset,
+    left: PositionOffset,
+    right: PositionOffset,
+    inverted: InvertedRangePolicy,
     output: Option<&PathBuf>,
     sort: bool,
 ) -> Result<CommandOutput<()>, GRangesError> {
     let genome = read_seqlens(seqlens)?;
 
-    let mut writer = build_tsv_writer(output)?;
+    let mut writer = build_bed_writer(output)?;
 
     // For reporting stuff to the user.
     let mut report = Report::new();
-    let mut skipped_ranges = 0;
 
     if !sort {
         // Create the parsing iterator, and detect which variant we need based on
@@ -129,63 +563,84 @@ pub fn granges_adjust(
         let bedlike_iterator = BedlikeIterator::new(bedfile)?;
 
         // If we don't need to sort, use iterator-based streaming processing.
-        for record in bedlike_iterator {
+        for record in bedlike_iterator.progress("adjust") {
             let range = record?;
-            let seqname = &range.seqname;
+            report.records_read += 1;
+            let seqname = range.seqname.clone();
             let length = *genome
-                .get(seqname)
-                .ok_or(GRangesError::MissingSequence(seqname.to_string()))?;
+                .get(&seqname)
+                .ok_or(GRangesError::missing_sequence(&seqname))?;
 
-            let possibly_adjusted_range = adjust_range(range, -both, both, length);
+            let truncated = is_truncated(range.start, range.end, -left, right, length);
+            let possibly_adjusted_range =
+                adjust_range_with_policy(range, -left, right, length, inverted)
+                    .map_err(|error| error.with_seqname(&seqname))?;
 
             if let Some(range_adjusted) = possibly_adjusted_range {
                 writer.serialize(range_adjusted)?;
+                report.records_written += 1;
+                if truncated {
+                    report.ranges_truncated += 1;
+                }
             } else {
-                skipped_ranges += 1;
-            }
-
-            if skipped_ranges > 0 {
-                report.add_issue(format!(
-                    "{} ranges were removed because their widths after adjustment were ≤ 0",
-                    skipped_ranges
-                ))
+                report.records_skipped += 1;
             }
         }
+        writer.flush()?;
     } else {
-        // If we do need to sort, build up a GRanges variant and adjust ranges through
-        // the GRanges interface. Note we need to detect and build a specific iterator
-        // for the filetype.
+        // If we do need to sort, adjust the ranges as they are parsed, and build up a
+        // GRanges variant from them. Note we need to detect and build a specific
+        // iterator for the filetype.
 
         let ranges_iter = GenomicRangesFile::parsing_iterator(bedfile)?;
+        let mut records_read = 0;
+        let mut truncated = 0;
         match ranges_iter {
             GenomicRangesParser::Bed3(iter) => {
+                let iter = iter.inspect(|_| records_read += 1);
+                let iter = adjust_records(iter, &genome, -left, right, inverted, &mut truncated);
                 let gr = GRangesEmpty::from_iter(iter, &genome)?;
-                gr.adjust_ranges(-both, both)
-                    .write_to_tsv(output, &BED_TSV)?
+                report.records_written = gr.len();
+                gr.write_to_tsv(output, &BED_TSV)?
             }
             GenomicRangesParser::Bed4(iter) => {
+                let iter = iter.inspect(|_| records_read += 1);
+                let iter = adjust_records(iter, &genome, -left, right, inverted, &mut truncated);
                 let gr = GRanges::from_iter(iter, &genome)?;
-                gr.adjust_ranges(-both, both)
-                    .write_to_tsv(output, &BED_TSV)?
+                report.records_written = gr.len();
+                gr.write_to_tsv(output, &BED_TSV)?
             }
             GenomicRangesParser::Bed5(iter) => {
+                let iter = iter.inspect(|_| records_read += 1);
+                let iter = adjust_records(iter, &genome, -left, right, inverted, &mut truncated);
                 let gr = GRanges::from_iter(iter, &genome)?;
-                gr.adjust_ranges(-both, both)
-                    .write_to_tsv(output, &BED_TSV)?
+                report.records_written = gr.len();
+                gr.write_to_tsv(output, &BED_TSV)?
             }
             GenomicRangesParser::Bedlike(iter) => {
                 // Note the call to try_unwrap_data() here: this is because
                 // we know that the records *do* have data. Unwrapping the Option<String>
                 // values means that writing to TSV doesn't have to deal with this (which
                 // always creates headaches).
-                let gr = GRanges::from_iter(iter.try_unwrap_data(), &genome)?;
-                gr.adjust_ranges(-both, both)
-                    .write_to_tsv(output, &BED_TSV)?
+                let iter = iter.try_unwrap_data().inspect(|_| records_read += 1);
+                let iter = adjust_records(iter, &genome, -left, right, inverted, &mut truncated);
+                let gr = GRanges::from_iter(iter, &genome)?;
+                report.records_written = gr.len();
+                gr.write_to_tsv(output, &BED_TSV)?
             }
             GenomicRangesParser::Unsupported => {
                 return Err(GRangesError::UnsupportedGenomicRangesFileFormat)
             }
         }
+        report.records_read = records_read;
+        report.records_skipped = report.records_read - report.records_written;
+        report.ranges_truncated = truncated;
+    }
+    if report.records_skipped > 0 {
+        report.add_issue(format!(
+            "{} ranges were removed because their widths after adjustment were ≤ 0",
+            report.records_skipped
+        ))
     }
     Ok(CommandOutput::new((), Some(report)))
 }
@@ -193,8 +648,10 @@ pub fn granges_adjust(
 /// Filters genomic ranges based on overlaps with another set of ranges.
 ///
 /// Retains only the ranges from the `left_path` file that have at least one overlap with
-/// the ranges in the `right_path` file. The function can optionally skip ranges that do not
-/// exist in the provided sequence lengths (e.g. a "genome" file in `bedtools` lingo).
+/// the ranges in the `right_path` file (or, depending on `filter_output`, only those with
+/// none, or all of them with their numbers of overlaps). The function can optionally skip
+/// ranges that do not exist in the provided sequence lengths (e.g. a "genome" file in
+/// `bedtools` lingo).
 ///
 /// # Arguments
 ///
@@ -204,6 +661,14 @@ pub fn granges_adjust(
 /// * `output` - An optional reference to a `PathBuf` where the filtered ranges will be written. Writes
 ///   to stdout if `None`.
 /// * `skip_missing` - A boolean indicating whether to skip ranges missing in the sequence lengths file.
+/// * `bounds` - The [`BoundsPolicy`] for ranges that fall outside the genome.
+/// * `filter` - The [`JoinFilter`] an overlap must pass, e.g. an [`OverlapMode`] relation.
+/// * `filter_output` - A [`FilterOutput`]: whether to write the left ranges with overlaps
+///   that pass `filter`, those without (an anti-join), or all of them with their overlap counts.
+/// * `mode` - A [`ProcessingMode`]. With [`ProcessingMode::Streaming`], both inputs must be sorted,
+///   and are joined in a single pass without loading either into memory (see [`StreamingLeftOverlaps`]).
+/// * `exclude` - An optional BED file of regions (e.g. the ENCODE blacklist): right ranges that
+///   overlap them are ignored.
 ///
 /// # Returns
 ///
@@ -212,108 +677,321 @@ pub fn granges_adjust(
 /// # Errors
 ///
 /// Returns [`GRangesError`] if any input file cannot be read, or if there's an issue processing the ranges.
+#[allow(clippy::too_many_arguments)]
 pub fn granges_filter(
     seqlens: &PathBuf,
     left_path: &PathBuf,
     right_path: &PathBuf,
     output: Option<&PathBuf>,
     skip_missing: bool,
+    bounds: BoundsPolicy,
+    filter: &JoinFilter,
+    filter_output: FilterOutput,
+    mode: ProcessingMode,
+    exclude: Option<&PathBuf>,
 ) -> Result<CommandOutput<()>, GRangesError> {
     let genome = read_seqlens(seqlens)?;
-    let seqnames: Vec<String> = genome.keys().cloned().collect();
+    let excluded = load_excluded(exclude, &genome, bounds)?;
+    let mode = resolve_processing_mode(
+        mode,
+        &[left_path, right_path],
+        &genome,
+        skip_missing,
+        bounds,
+    )?;
 
     let left_iter = GenomicRangesFile::parsing_iterator(left_path)?;
     let right_iter = GenomicRangesFile::parsing_iterator(right_path)?;
 
+    if let ProcessingMode::Streaming = mode {
+        let report = match (left_iter, right_iter) {
+            (GenomicRangesParser::Bed3(left), GenomicRangesParser::Bed3(right)) => {
+                granges_filter_streaming(
+                    &genome,
+                    checked_ranges(left, &genome, skip_missing, bounds),
+                    excluding_ranges(
+                        checked_ranges(right, &genome, skip_missing, bounds),
+                        excluded.as_ref(),
+                    ),
+                    filter,
+                    filter_output,
+                    output,
+                )?
+            }
+            (GenomicRangesParser::Bed3(left), GenomicRangesParser::Bedlike(right)) => {
+                granges_filter_streaming(
+                    &genome,
+                    checked_ranges(left, &genome, skip_missing, bounds),
+                    excluding_ranges(
+                        checked_ranges(right.try_unwrap_data(), &genome, skip_missing, bounds),
+                        excluded.as_ref(),
+                    ),
+                    filter,
+                    filter_output,
+                    output,
+                )?
+            }
+            (GenomicRangesParser::Bedlike(left), GenomicRangesParser::Bed3(right)) => {
+                granges_filter_streaming(
+                    &genome,
+                    checked_ranges(left.try_unwrap_data(), &genome, skip_missing, bounds),
+                    excluding_ranges(
+                        checked_ranges(right, &genome, skip_missing, bounds),
+                        excluded.as_ref(),
+                    ),
+                    filter,
+                    filter_output,
+                    output,
+                )?
+            }
+            (GenomicRangesParser::Bedlike(left), GenomicRangesParser::Bedlike(right)) => {
+                granges_filter_streaming(
+                    &genome,
+                    checked_ranges(left.try_unwrap_data(), &genome, skip_missing, bounds),
+                    excluding_ranges(
+                        checked_ranges(right.try_unwrap_data(), &genome, skip_missing, bounds),
+                        excluded.as_ref(),
+                    ),
+                    filter,
+                    filter_output,
+                    output,
+                )?
+            }
+            _ => return Err(GRangesError::UnsupportedGenomicRangesFileFormat),
+        };
+        return Ok(CommandOutput::new((), Some(report)));
+    }
+
     match (left_iter, right_iter) {
         (GenomicRangesParser::Bed3(left), GenomicRangesParser::Bed3(right)) => {
-            let left_gr;
-            let right_gr;
-
-            if skip_missing {
-                left_gr = GRangesEmpty::from_iter(left.retain_seqnames(&seqnames), &genome)?;
-                right_gr = GRangesEmpty::from_iter(right.retain_seqnames(&seqnames), &genome)?;
-            } else {
-                left_gr = GRangesEmpty::from_iter(left, &genome)?;
-                right_gr = GRangesEmpty::from_iter(right, &genome)?;
-            }
+            let parse = info_span!("parse").entered();
+            let left_gr = GRangesEmpty::from_iter(
+                checked_ranges(left, &genome, skip_missing, bounds),
+                &genome,
+            )?;
+            let right_gr = GRangesEmpty::from_iter(
+                excluding_ranges(
+                    checked_ranges(right, &genome, skip_missing, bounds),
+                    excluded.as_ref(),
+                ),
+                &genome,
+            )?;
 
             let right_gr = right_gr.into_coitrees()?;
+            drop(parse);
 
-            let semijoin = left_gr.filter_overlaps(&right_gr)?;
-            semijoin.write_to_tsv(output, &BED_TSV)?;
+            let records_read = left_gr.len();
+            let join = info_span!("join").entered();
+            let records_written = match filter_output {
+                FilterOutput::Overlapping => {
+                    let filtered = left_gr.filter_overlaps_with(&right_gr, filter)?;
+                    drop(join);
+                    write_filtered(&filtered, output)?;
+                    filtered.len()
+                }
+                FilterOutput::NonOverlapping => {
+                    let filtered = left_gr.antifilter_overlaps_with(&right_gr, filter)?;
+                    drop(join);
+                    write_filtered(&filtered, output)?;
+                    filtered.len()
+                }
+                FilterOutput::Count => {
+                    let counts = left_gr.count_overlaps_with(&right_gr, filter)?;
+                    drop(join);
+                    write_filtered(&counts, output)?;
+                    counts.len()
+                }
+            };
 
-            Ok(CommandOutput::new((), None))
+            Ok(CommandOutput::new(
+                (),
+                Some(Report::with_counts(records_read, records_written)),
+            ))
         }
         (GenomicRangesParser::Bed3(left), GenomicRangesParser::Bedlike(right)) => {
-            let left_gr;
-            let right_gr;
-
-            if skip_missing {
-                left_gr = GRangesEmpty::from_iter(left.retain_seqnames(&seqnames), &genome)?;
-                right_gr = GRanges::from_iter(
-                    right.try_unwrap_data().retain_seqnames(&seqnames),
-                    &genome,
-                )?;
-            } else {
-                left_gr = GRangesEmpty::from_iter(left, &genome)?;
-                right_gr = GRanges::from_iter(right.try_unwrap_data(), &genome)?;
-            }
+            let parse = info_span!("parse").entered();
+            let left_gr = GRangesEmpty::from_iter(
+                checked_ranges(left, &genome, skip_missing, bounds),
+                &genome,
+            )?;
+            let right_gr = GRanges::from_iter(
+                excluding_ranges(
+                    checked_ranges(right.try_unwrap_data(), &genome, skip_missing, bounds),
+                    excluded.as_ref(),
+                ),
+                &genome,
+            )?;
 
             let right_gr = right_gr.into_coitrees()?;
+            drop(parse);
 
-            let semijoin = left_gr.filter_overlaps(&right_gr)?;
-            semijoin.write_to_tsv(output, &BED_TSV)?;
+            let records_read = left_gr.len();
+            let join = info_span!("join").entered();
+            let records_written = match filter_output {
+                FilterOutput::Overlapping => {
+                    let filtered = left_gr.filter_overlaps_with(&right_gr, filter)?;
+                    drop(join);
+                    write_filtered(&filtered, output)?;
+                    filtered.len()
+                }
+                FilterOutput::NonOverlapping => {
+                    let filtered = left_gr.antifilter_overlaps_with(&right_gr, filter)?;
+                    drop(join);
+                    write_filtered(&filtered, output)?;
+                    filtered.len()
+                }
+                FilterOutput::Count => {
+                    let counts = left_gr.count_overlaps_with(&right_gr, filter)?;
+                    drop(join);
+                    write_filtered(&counts, output)?;
+                    counts.len()
+                }
+            };
 
-            Ok(CommandOutput::new((), None))
+            Ok(CommandOutput::new(
+                (),
+                Some(Report::with_counts(records_read, records_written)),
+            ))
         }
         (GenomicRangesParser::Bedlike(left), GenomicRangesParser::Bed3(right)) => {
-            let left_gr;
-            let right_gr;
-
-            if skip_missing {
-                left_gr =
-                    GRanges::from_iter(left.try_unwrap_data().retain_seqnames(&seqnames), &genome)?;
-                right_gr = GRangesEmpty::from_iter(right.retain_seqnames(&seqnames), &genome)?;
-            } else {
-                left_gr = GRanges::from_iter(left.try_unwrap_data(), &genome)?;
-                right_gr = GRangesEmpty::from_iter(right, &genome)?;
-            }
+            let parse = info_span!("parse").entered();
+            let left_gr = GRanges::from_iter(
+                checked_ranges(left.try_unwrap_data(), &genome, skip_missing, bounds),
+                &genome,
+            )?;
+            let right_gr = GRangesEmpty::from_iter(
+                excluding_ranges(
+                    checked_ranges(right, &genome, skip_missing, bounds),
+                    excluded.as_ref(),
+                ),
+                &genome,
+            )?;
 
             let right_gr = right_gr.into_coitrees()?;
+            drop(parse);
 
-            let semijoin = left_gr.filter_overlaps(&right_gr)?;
-            semijoin.write_to_tsv(output, &BED_TSV)?;
+            let records_read = left_gr.len();
+            let join = info_span!("join").entered();
+            let records_written = match filter_output {
+                FilterOutput::Overlapping => {
+                    let filtered = left_gr.filter_overlaps_with(&right_gr, filter)?;
+                    drop(join);
+                    write_filtered(&filtered, output)?;
+                    filtered.len()
+                }
+                FilterOutput::NonOverlapping => {
+                    let filtered = left_gr.antifilter_overlaps_with(&right_gr, filter)?;
+                    drop(join);
+                    write_filtered(&filtered, output)?;
+                    filtered.len()
+                }
+                FilterOutput::Count => {
+                    let counts = left_gr.count_overlaps_with(&right_gr, filter)?;
+                    drop(join);
+                    write_filtered(&counts, output)?;
+                    counts.len()
+                }
+            };
 
-            Ok(CommandOutput::new((), None))
+            Ok(CommandOutput::new(
+                (),
+                Some(Report::with_counts(records_read, records_written)),
+            ))
         }
         (GenomicRangesParser::Bedlike(left), GenomicRangesParser::Bedlike(right)) => {
-            let left_gr;
-            let right_gr;
-
-            if skip_missing {
-                left_gr =
-                    GRanges::from_iter(left.try_unwrap_data().retain_seqnames(&seqnames), &genome)?;
-                right_gr = GRanges::from_iter(
-                    right.try_unwrap_data().retain_seqnames(&seqnames),
-                    &genome,
-                )?;
-            } else {
-                left_gr = GRanges::from_iter(left.try_unwrap_data(), &genome)?;
-                right_gr = GRanges::from_iter(right.try_unwrap_data(), &genome)?;
-            }
+            let parse = info_span!("parse").entered();
+            let left_gr = GRanges::from_iter(
+                checked_ranges(left.try_unwrap_data(), &genome, skip_missing, bounds),
+                &genome,
+            )?;
+            let right_gr = GRanges::from_iter(
+                excluding_ranges(
+                    checked_ranges(right.try_unwrap_data(), &genome, skip_missing, bounds),
+                    excluded.as_ref(),
+                ),
+                &genome,
+            )?;
 
             let right_gr = right_gr.into_coitrees()?;
+            drop(parse);
 
-            let intersection = left_gr.filter_overlaps(&right_gr)?;
-            intersection.write_to_tsv(output, &BED_TSV)?;
+            let records_read = left_gr.len();
+            let join = info_span!("join").entered();
+            let records_written = match filter_output {
+                FilterOutput::Overlapping => {
+                    let filtered = left_gr.filter_overlaps_with(&right_gr, filter)?;
+                    drop(join);
+                    write_filtered(&filtered, output)?;
+                    filtered.len()
+                }
+                FilterOutput::NonOverlapping => {
+                    let filtered = left_gr.antifilter_overlaps_with(&right_gr, filter)?;
+                    drop(join);
+                    write_filtered(&filtered, output)?;
+                    filtered.len()
+                }
+                FilterOutput::Count => {
+                    let counts = left_gr.count_overlaps_with(&right_gr, filter)?;
+                    drop(join);
+                    write_filtered(&counts, output)?;
+                    counts.len()
+                }
+            };
 
-            Ok(CommandOutput::new((), None))
+            Ok(CommandOutput::new(
+                (),
+                Some(Report::with_counts(records_read, records_written)),
+            ))
         }
         _ => Err(GRangesError::UnsupportedGenomicRangesFileFormat),
     }
 }
 
+/// Write the sorted `left` ranges that overlap at least one of the sorted
+/// `right` ranges, using a streaming join.
+/// Write the result of an in-memory [`granges_filter()`] join.
+fn write_filtered<'a, C, G>(gr: &'a G, output: Option<&PathBuf>) -> Result<(), GRangesError>
+where
+    G: GenomicRangesTsvSerialize<'a, C>,
+{
+    let _span = info_span!("write").entered();
+    gr.write_to_tsv(output, &BED_TSV)
+}
+
+fn granges_filter_streaming<L, R>(
+    genome: &IndexMap<String, Position>,
+    left: impl Iterator<Item = Result<L, GRangesError>>,
+    right: impl Iterator<Item = Result<R, GRangesError>>,
+    filter: &JoinFilter,
+    filter_output: FilterOutput,
+    output: Option<&PathBuf>,
+) -> Result<Report, GRangesError>
+where
+    L: GenomicRecord + serde::Serialize,
+    R: GenomicRecord,
+{
+    let _span = info_span!("streaming join").entered();
+    let mut writer = build_bed_writer(output)?;
+    let (mut records_read, mut records_written) = (0, 0);
+    for result in StreamingLeftOverlaps::new(left.progress("filter"), right, genome)? {
+        let (left_range, overlaps) = result?;
+        records_read += 1;
+        let num_overlaps = overlaps
+            .iter()
+            .filter(|right| filter.passes(&left_range, *right))
+            .count();
+        match filter_output {
+            FilterOutput::Overlapping if num_overlaps == 0 => continue,
+            FilterOutput::NonOverlapping if num_overlaps > 0 => continue,
+            FilterOutput::Count => writer.serialize((left_range, num_overlaps))?,
+            _ => writer.serialize(left_range)?,
+        }
+        records_written += 1;
+    }
+    writer.flush()?;
+    Ok(Report::with_counts(records_read, records_written))
+}
+
 /// Generates flanking regions for genomic ranges in a BED file.
 ///
 /// For each range in the input BED file, this function computes the flanking regions based on
@@ -328,6 +1006,7 @@ pub fn granges_filter(
 /// * `right` - An optional `Position` specifying the right flank size.
 /// * `output` - An optional reference to a `PathBuf` for the output file. Writes to stdout if `None`.
 /// * `skip_missing` - A boolean indicating whether to skip ranges missing in the sequence lengths file.
+/// * `bounds` - The [`BoundsPolicy`] for ranges that fall outside the genome.
 /// * `mode` - A [`ProcessingMode`] indicating whether to use in-memory or streaming processing.
 ///
 /// # Returns
@@ -338,6 +1017,7 @@ pub fn granges_filter(
 ///
 /// Returns [`GRangesError`] if the input BED file or sequence lengths file cannot be read, or if there's
 /// an issue generating the flanking regions.
+#[allow(clippy::too_many_arguments)]
 pub fn granges_flank(
     seqlens: &PathBuf,
     bedfile: &PathBuf,
@@ -345,10 +1025,32 @@ pub fn granges_flank(
     right: Option<Position>,
     output: Option<&PathBuf>,
     skip_missing: bool,
+    bounds: BoundsPolicy,
     mode: ProcessingMode,
+    carry: bool,
 ) -> Result<CommandOutput<()>, GRangesError> {
     let genome = read_seqlens(seqlens)?;
-    let seqnames: Vec<String> = genome.keys().cloned().collect();
+
+    // carrying the other columns of each range onto its flanks works on any BED-like
+    // file, one range at a time
+    if carry {
+        let mut writer = build_bed_writer(output)?;
+        let iter = BedlikeIterator::new(bedfile)?;
+        for record in checked_ranges(iter, &genome, skip_missing, bounds) {
+            let range = record?;
+            let length = *genome
+                .get(&range.seqname)
+                .ok_or(GRangesError::missing_sequence(&range.seqname))?;
+            for flanking_range in
+                range.flanking_ranges::<GenomicRangeRecord<Option<String>>>(left, right, length)
+            {
+                writer.write_record(bedlike_columns(flanking_range))?;
+            }
+        }
+        writer.flush()?;
+        return Ok(CommandOutput::new((), None));
+    }
+
     let ranges_iter = GenomicRangesFile::parsing_iterator(bedfile)?;
 
     match mode {
@@ -356,20 +1058,18 @@ pub fn granges_flank(
         // objects is versus using streaming.
         ProcessingMode::InMemory => match ranges_iter {
             GenomicRangesParser::Bed3(iter) => {
-                let gr = if skip_missing {
-                    GRangesEmpty::from_iter(iter.retain_seqnames(&seqnames), &genome)?
-                } else {
-                    GRangesEmpty::from_iter(iter, &genome)?
-                };
+                let gr = GRangesEmpty::from_iter(
+                    checked_ranges(iter, &genome, skip_missing, bounds),
+                    &genome,
+                )?;
                 gr.flanking_ranges(left, right)?
                     .write_to_tsv(output, &BED_TSV)?
             }
             GenomicRangesParser::Bed4(iter) => {
-                let gr = if skip_missing {
-                    GRanges::from_iter(iter.retain_seqnames(&seqnames), &genome)?
-                } else {
-                    GRanges::from_iter(iter, &genome)?
-                };
+                let gr = GRanges::from_iter(
+                    checked_ranges(iter, &genome, skip_missing, bounds),
+                    &genome,
+                )?;
                 gr.flanking_ranges(left, right)?
                     .write_to_tsv(output, &BED_TSV)?
             }
@@ -378,11 +1078,10 @@ pub fn granges_flank(
                 unimplemented!()
             }
             GenomicRangesParser::Bedlike(iter) => {
-                let gr = if skip_missing {
-                    GRanges::from_iter(iter.try_unwrap_data().retain_seqnames(&seqnames), &genome)?
-                } else {
-                    GRanges::from_iter(iter.try_unwrap_data(), &genome)?
-                };
+                let gr = GRanges::from_iter(
+                    checked_ranges(iter.try_unwrap_data(), &genome, skip_missing, bounds),
+                    &genome,
+                )?;
                 gr.flanking_ranges(left, right)?
                     .write_to_tsv(output, &BED_TSV)?
             }
@@ -390,39 +1089,24 @@ pub fn granges_flank(
                 return Err(GRangesError::UnsupportedGenomicRangesFileFormat)
             }
         },
-        ProcessingMode::Streaming => {
-            let mut writer = build_tsv_writer(output)?;
+        // flanking ranges are computed one range at a time, so need no sorting
+        ProcessingMode::Streaming | ProcessingMode::Chunked(_) | ProcessingMode::Auto => {
+            let mut writer = build_bed_writer(output)?;
 
             match ranges_iter {
                 // FIXME: code redundancy. But too early now to design traits, etc.
                 GenomicRangesParser::Bed3(iter) => {
-                    if skip_missing {
-                        for record in iter.retain_seqnames(&seqnames) {
-                            let range = record?;
-                            let seqname = &range.seqname;
-                            let length = *genome
-                                .get(seqname)
-                                .ok_or(GRangesError::MissingSequence(seqname.to_string()))?;
-
-                            let flanking_ranges = range
-                                .flanking_ranges::<GenomicRangeRecord<String>>(left, right, length);
-                            for flanking_range in flanking_ranges {
-                                writer.serialize(flanking_range)?;
-                            }
-                        }
-                    } else {
-                        for record in iter {
-                            let range = record?;
-                            let seqname = &range.seqname;
-                            let length = *genome
-                                .get(seqname)
-                                .ok_or(GRangesError::MissingSequence(seqname.to_string()))?;
-
-                            let flanking_ranges = range
-                                .flanking_ranges::<GenomicRangeRecordEmpty>(left, right, length);
-                            for flanking_range in flanking_ranges {
-                                writer.serialize(flanking_range)?;
-                            }
+                    for record in checked_ranges(iter, &genome, skip_missing, bounds) {
+                        let range = record?;
+                        let seqname = &range.seqname;
+                        let length = *genome
+                            .get(seqname)
+                            .ok_or(GRangesError::missing_sequence(seqname))?;
+
+                        let flanking_ranges =
+                            range.flanking_ranges::<GenomicRangeRecordEmpty>(left, right, length);
+                        for flanking_range in flanking_ranges {
+                            writer.serialize(flanking_range)?;
                         }
                     }
                 }
@@ -433,33 +1117,17 @@ pub fn granges_flank(
                     unimplemented!()
                 }
                 GenomicRangesParser::Bedlike(iter) => {
-                    if skip_missing {
-                        for record in iter.retain_seqnames(&seqnames) {
-                            let range = record?;
-                            let seqname = &range.seqname;
-                            let length = *genome
-                                .get(seqname)
-                                .ok_or(GRangesError::MissingSequence(seqname.to_string()))?;
-
-                            let flanking_ranges = range
-                                .flanking_ranges::<GenomicRangeRecord<String>>(left, right, length);
-                            for flanking_range in flanking_ranges {
-                                writer.serialize(flanking_range)?;
-                            }
-                        }
-                    } else {
-                        for record in iter {
-                            let range = record?;
-                            let seqname = &range.seqname;
-                            let length = *genome
-                                .get(seqname)
-                                .ok_or(GRangesError::MissingSequence(seqname.to_string()))?;
-
-                            let flanking_ranges = range
-                                .flanking_ranges::<GenomicRangeRecordEmpty>(left, right, length);
-                            for flanking_range in flanking_ranges {
-                                writer.serialize(flanking_range)?;
-                            }
+                    for record in checked_ranges(iter, &genome, skip_missing, bounds) {
+                        let range = record?;
+                        let seqname = &range.seqname;
+                        let length = *genome
+                            .get(seqname)
+                            .ok_or(GRangesError::missing_sequence(seqname))?;
+
+                        let flanking_ranges =
+                            range.flanking_ranges::<GenomicRangeRecordEmpty>(left, right, length);
+                        for flanking_range in flanking_ranges {
+                            writer.serialize(flanking_range)?;
                         }
                     }
                 }
@@ -472,242 +1140,4602 @@ pub fn granges_flank(
     Ok(CommandOutput::new((), None))
 }
 
+/// Select the named column of BED5 data as a float.
+fn select_float(bed5_cols: &Bed5Addition, column: &str) -> Result<Option<f64>, GRangesError> {
+    let datum = bed5_cols
+        .select_by_name(column)
+        .ok_or_else(|| GRangesError::NoSuchColumn(column.to_string()))?;
+    datum_into::<f64>(column, datum)
+}
+
+/// Run all operations on the scores, of `num_overlaps` overlapping ranges, followed
+/// by the `custom` aggregation, if any. The results are written with `config`. The
+/// results with no overlapping ranges follow the `empty` policy, and are `None` if the
+/// range is dropped.
+fn run_operations<'a>(
+    operations: &[FloatOperation],
+    custom: Option<&dyn Aggregate>,
+    overlap_scores: &mut [f64],
+    num_overlaps: usize,
+    empty: EmptyPolicy,
+    config: &'a TsvConfig,
+) -> Result<Option<Vec<SerializableDatumType<'a>>>, GRangesError> {
+    if num_overlaps == 0 && empty != EmptyPolicy::Operation {
+        let Some(fill) = empty.fill() else {
+            return Ok(None);
+        };
+        let mut results: Vec<_> = operations
+            .iter()
+            .map(|operation| match operation {
+                FloatOperation::Count => count_datum(0),
+                _ => fill.clone().into_serializable(config),
+            })
+            .collect();
+        if custom.is_some() {
+            results.push(fill.into_serializable(config));
+        }
+        return Ok(Some(results));
+    }
+    let mut results: Vec<_> = operations
+        .iter()
+        .map(|operation| match operation {
+            FloatOperation::Count => count_datum(num_overlaps),
+            _ => operation.run(overlap_scores).into_serializable(config),
+        })
+        .collect();
+    if let Some(custom) = custom {
+        results.push(custom.aggregate(overlap_scores)?.into_serializable(config));
+    }
+    Ok(Some(results))
+}
+
+/// The result of the count operation, for `num_overlaps` overlapping ranges.
+fn count_datum(num_overlaps: usize) -> SerializableDatumType<'static> {
+    DatumType::Unsigned64(num_overlaps as u64).into_serializable(&BED_TSV)
+}
+
+/// The TSV configuration for writing floats with `precision` significant digits,
+/// like bedtools' `-prec` option (see
+/// [`format_float_general()`](crate::io::tsv::format_float_general)).
+pub fn precision_tsv_config(precision: usize) -> TsvConfig {
+    TsvConfig {
+        float_precision: Some(precision),
+        ..BED_TSV.clone()
+    }
+}
+
 /// # Developer Notes
 /// This function is a great way to see GRange's methods in action.
+///
+/// With [`ProcessingMode::Streaming`], both inputs must be sorted, and are
+/// joined in a single pass without loading either into memory (see
+/// [`StreamingLeftOverlaps`]).
+///
+/// With [`ProcessingMode::Chunked`], the right ranges are loaded into memory, but
+/// the left ranges are processed (and written, in input order) in batches, for
+/// when there are too many left ranges (e.g. genome-wide fine windows) to build
+/// in memory.
+///
+/// A `custom` [`Aggregate`] (e.g. a Lua script) adds a column after those of the
+/// `operations`.
+///
+/// With a [`Normalization`], the counts (the only allowed operation) are normalized
+/// by the number of right ranges (the library size) and the widths of the left
+/// ranges. This needs all the counts, so the ranges are always counted in memory.
+/// Zero-width left ranges have no RPKM or TPM, so these are written as missing.
+///
+/// The results for left ranges with no overlapping right ranges follow the `empty`
+/// policy (see [`EmptyPolicy`]); counts are always zero, unless the range is dropped.
+///
+/// The right ranges that overlap the regions of the `exclude` BED file (e.g. the
+/// ENCODE blacklist), if any, are ignored.
+#[allow(clippy::too_many_arguments)]
 pub fn granges_map(
     seqlens: impl Into<PathBuf>,
     left_path: &PathBuf,
     right_path: &PathBuf,
     operations: Vec<FloatOperation>,
+    custom: Option<&dyn Aggregate>,
+    normalize: Option<Normalization>,
+    empty: EmptyPolicy,
+    column: &str,
     output: Option<&PathBuf>,
     skip_missing: bool,
+    bounds: BoundsPolicy,
+    mode: ProcessingMode,
+    precision: usize,
+    exclude: Option<&PathBuf>,
 ) -> Result<CommandOutput<()>, GRangesError> {
+    if column_position(Bed5Addition::schema(), column).is_none() {
+        return Err(GRangesError::NoSuchColumn(column.to_string()));
+    }
+    // Counting needs neither the right ranges' data nor the join data, so
+    // this common case uses a fast path.
+    let count_only = custom.is_none()
+        && operations
+            .iter()
+            .all(|operation| matches!(operation, FloatOperation::Count));
+    let mode = match normalize {
+        Some(_) if !count_only => return Err(GRangesError::NormalizationRequiresCounts),
+        Some(_) => ProcessingMode::InMemory,
+        None => mode,
+    };
     let genome = read_seqlens(seqlens)?;
-    let seqnames: Vec<String> = genome.keys().cloned().collect();
+    let excluded = load_excluded(exclude, &genome, bounds)?;
+    let config = precision_tsv_config(precision);
+    let mode = resolve_processing_mode(
+        mode,
+        &[left_path, right_path],
+        &genome,
+        skip_missing,
+        bounds,
+    )?;
 
     let left_iter = Bed3Iterator::new(left_path)?;
     let right_iter = Bed5Iterator::new(right_path)?;
 
-    let left_gr;
-    let right_gr;
+    if let ProcessingMode::Streaming = mode {
+        let left = checked_ranges(left_iter, &genome, skip_missing, bounds);
+        let right = checked_ranges(right_iter, &genome, skip_missing, bounds);
+        let right = excluding_ranges(right, excluded.as_ref());
 
-    if skip_missing {
-        left_gr = GRangesEmpty::from_iter(left_iter.retain_seqnames(&seqnames), &genome)?;
-        right_gr = GRanges::from_iter(right_iter.retain_seqnames(&seqnames), &genome)?;
-    } else {
-        left_gr = GRangesEmpty::from_iter(left_iter, &genome)?;
-        right_gr = GRanges::from_iter(right_iter, &genome)?;
+        let _span = info_span!("streaming join").entered();
+        let mut writer = build_bed_writer(output)?;
+        let (mut records_read, mut records_written) = (0, 0);
+        for result in StreamingLeftOverlaps::new(left.progress("map"), right, &genome)? {
+            let (left_range, overlaps) = result?;
+            records_read += 1;
+            let mut overlap_scores: Vec<f64> = overlaps
+                .iter()
+                .map(|right_range| select_float(&right_range.data, column))
+                .collect::<Result<Vec<_>, _>>()?
+                .into_iter()
+                // Filter out the `None` values.
+                .flatten()
+                .collect();
+            let Some(results) = run_operations(
+                &operations,
+                custom,
+                &mut overlap_scores,
+                overlaps.len(),
+                empty,
+                &config,
+            )?
+            else {
+                continue;
+            };
+            let record = GenomicRangeRecord::new(
+                left_range.seqname,
+                left_range.start,
+                left_range.end,
+                results,
+            );
+            writer.serialize(record)?;
+            records_written += 1;
+        }
+        writer.flush()?;
+        let report = Report::with_counts(records_read, records_written);
+        return Ok(CommandOutput::new((), Some(report)));
     }
 
-    if left_gr.is_empty() {
-        return Err(GRangesError::NoRows);
+    let load_left = |left_iter: Bed3Iterator| {
+        let left = checked_ranges(left_iter, &genome, skip_missing, bounds);
+        let left_gr = GRangesEmpty::from_iter(left, &genome)?;
+        if left_gr.is_empty() {
+            return Err(GRangesError::NoRows);
+        }
+        Ok(left_gr)
+    };
+    let drop_empty = empty == EmptyPolicy::Drop;
+
+    if count_only {
+        debug!("map: using the counting fast path");
+        let parse = info_span!("parse right").entered();
+        let right_iter = BedlikeIterator::new(right_path)?;
+        let right = checked_ranges(right_iter, &genome, skip_missing, bounds);
+        let right = excluding_ranges(right, excluded.as_ref());
+        let right_gr = GRangesEmpty::from_iter(right, &genome)?.into_coitrees()?;
+        drop(parse);
+        if right_gr.is_empty() {
+            return Err(GRangesError::NoRows);
+        }
+        let counts = |num_overlaps| vec![count_datum(num_overlaps); operations.len()];
+        let counts_or_drop = |num_overlaps: usize| {
+            let dropped = drop_empty && num_overlaps == 0;
+            (!dropped).then(|| counts(num_overlaps))
+        };
+
+        if let ProcessingMode::Chunked(chunk_size) = mode {
+            let left = checked_ranges(left_iter, &genome, skip_missing, bounds);
+            let report = granges_map_chunked(
+                left,
+                right_gr.as_granges_ref(),
+                chunk_size,
+                output,
+                |right_ranges, range| {
+                    Ok(counts_or_drop(
+                        right_ranges.count_overlaps(range.start, range.end),
+                    ))
+                },
+            )?;
+            return Ok(CommandOutput::new((), Some(report)));
+        }
+
+        let parse = info_span!("parse left").entered();
+        let left_gr = load_left(left_iter)?;
+        drop(parse);
+        let num_left = left_gr.len();
+        let join = info_span!("count overlaps").entered();
+        let counts_gr = if drop_empty {
+            left_gr.filter_overlaps(&right_gr)?.count_overlaps(&right_gr)?
+        } else {
+            left_gr.count_overlaps(&right_gr)?
+        };
+        drop(join);
+        let num_ranges = counts_gr.len();
+        let write = info_span!("write").entered();
+        match normalize {
+            None => counts_gr.map_data(counts)?.write_to_tsv(output, &BED_TSV)?,
+            Some(normalization) => {
+                let counts: Vec<u64> = counts_gr
+                    .data()
+                    .ok_or(GRangesError::NoDataContainer)?
+                    .iter()
+                    .map(|&count| count as u64)
+                    .collect();
+                let mut widths = vec![0; counts.len()];
+                for range in counts_gr.iter_ranges() {
+                    widths[range.index.expect("Internal error: please report")] = range.width();
+                }
+                let values = normalization.normalize(&counts, &widths, right_gr.len() as u64);
+
+                let seqnames = counts_gr.seqnames();
+                let mut result_gr = GRanges::new_vec(&counts_gr.seqlens());
+                for range in counts_gr.iter_ranges() {
+                    let value = values[range.index.expect("Internal error: please report")];
+                    let datum = value
+                        .map_or(DatumType::NoValue, DatumType::Float64)
+                        .into_serializable(&config);
+                    result_gr.push_range(
+                        &seqnames[range.seqname_index],
+                        range.start,
+                        range.end,
+                        vec![datum; operations.len()],
+                    )?;
+                }
+                result_gr.write_to_tsv(output, &config)?;
+            }
+        }
+        drop(write);
+        let report = Report::with_counts(num_left, num_ranges);
+        return Ok(CommandOutput::new((), Some(report)));
     }
+
+    let parse = info_span!("parse right").entered();
+    let right = checked_ranges(right_iter, &genome, skip_missing, bounds);
+    let right = excluding_ranges(right, excluded.as_ref());
+    let mut right_gr = GRanges::from_iter(right, &genome)?;
     if right_gr.is_empty() {
         return Err(GRangesError::NoRows);
     }
 
-    let right_gr = {
-        // Convert to interval trees for join.
-        right_gr
-            .into_coitrees()?
-            // Select out the score.
-            .map_data(|bed5_cols| {
-                // Extract out just the score.
-                bed5_cols.score
-            })?
+    // Select out the column to operate on by name, converting it to floats.
+    let values = right_gr
+        .take_data()?
+        .iter()
+        .map(|bed5_cols| select_float(bed5_cols, column))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    // Convert to interval trees for join.
+    let right_gr = right_gr.clone_with_data(Some(values)).into_coitrees()?;
+    drop(parse);
+    debug!("map: loaded {} right ranges", right_gr.len());
+
+    if let ProcessingMode::Chunked(chunk_size) = mode {
+        let values = right_gr
+            .data
+            .as_ref()
+            .ok_or(GRangesError::NoDataContainer)?;
+        let left = checked_ranges(left_iter, &genome, skip_missing, bounds);
+        let report = granges_map_chunked(
+            left,
+            &right_gr,
+            chunk_size,
+            output,
+            |right_ranges, range| {
+                let mut overlap_scores = Vec::new();
+                let mut num_overlaps = 0;
+                right_ranges.query_overlaps(range.start, range.end, |overlap| {
+                    num_overlaps += 1;
+                    if let Some(score) = overlap.index().and_then(|index| values[index]) {
+                        overlap_scores.push(score);
+                    }
+                });
+                run_operations(
+                    &operations,
+                    custom,
+                    &mut overlap_scores,
+                    num_overlaps,
+                    empty,
+                    &config,
+                )
+            },
+        )?;
+        return Ok(CommandOutput::new((), Some(report)));
+    }
+
+    let parse = info_span!("parse left").entered();
+    let left_gr = load_left(left_iter)?;
+    drop(parse);
+    let num_left = left_gr.len();
+    debug!("map: loaded {} left ranges", num_left);
+
+    // Find the overlaps.
+    let join = info_span!("join").entered();
+    let left_join_gr = if drop_empty {
+        left_gr.filter_overlaps(&right_gr)?.left_overlaps(&right_gr)?
+    } else {
+        left_gr.left_overlaps(&right_gr)?
     };
 
-    // Find the overlaps.
-    let left_join_gr = left_gr.left_overlaps(&right_gr)?;
+    // Process all the overlaps.
+    let mut result_gr = left_join_gr.map_joins(|join_data| {
+        let num_overlaps = join_data.right_data.len();
+
+        // Get the "right data" -- the BED5 scores
+        let mut overlap_scores: Vec<f64> = join_data
+            .right_data
+            .into_iter()
+            // Filter out the `None` values.
+            .flatten()
+            .collect();
+
+        // Run all operations on the scores (the ranges to drop were filtered out above).
+        run_operations(
+            &operations,
+            custom,
+            &mut overlap_scores,
+            num_overlaps,
+            empty,
+            &config,
+        )
+        .map(|results| results.expect("Internal error: please report"))
+    })?;
+    // Raise the first error from the operations (e.g. a failed custom aggregation).
+    let (ranges, results) = result_gr.take_both()?;
+    let results = results.into_iter().collect::<Result<Vec<_>, _>>()?;
+    let result_gr = GRanges {
+        ranges,
+        data: Some(results),
+    };
+    drop(join);
+
+    let write = info_span!("write").entered();
+    result_gr.write_to_tsv(output, &BED_TSV)?;
+    drop(write);
+
+    let report = Report::with_counts(num_left, result_gr.len());
+    Ok(CommandOutput::new((), Some(report)))
+}
+
+/// Map the left ranges in batches of at most `chunk_size` ranges, each on a single
+/// sequence, so that only one batch of left ranges is in memory at a time. Each batch's
+/// ranges are summarized in parallel with `summarize` (which is passed the right ranges
+/// on the batch's sequence, and returns `None` to drop a range), and written in their
+/// input order.
+fn granges_map_chunked<'a, I, C, T, F>(
+    left: I,
+    right: &GRanges<C, T>,
+    chunk_size: usize,
+    output: Option<&PathBuf>,
+    summarize: F,
+) -> Result<Report, GRangesError>
+where
+    I: Iterator<Item = Result<GenomicRangeRecordEmpty, GRangesError>>,
+    C: RangeContainer + Sync,
+    F: Fn(
+            &C,
+            &GenomicRangeRecordEmpty,
+        ) -> Result<Option<Vec<SerializableDatumType<'a>>>, GRangesError>
+        + Sync,
+{
+    let _span = info_span!("chunked map").entered();
+    let mut left = left.progress("map").peekable();
+    // as when the left ranges are loaded into memory, no left ranges is an error
+    if left.peek().is_none() {
+        return Err(GRangesError::NoRows);
+    }
+    let mut writer = build_bed_writer(output)?;
+
+    let (mut records_read, mut records_written) = (0, 0);
+    let mut batch: Vec<GenomicRangeRecordEmpty> = Vec::with_capacity(chunk_size);
+    while let Some(result) = left.next() {
+        batch.push(result?);
+        let batch_done = match left.peek() {
+            Some(Ok(next)) => batch.len() >= chunk_size || next.seqname != batch[0].seqname,
+            // errors are raised on the next iteration, after this batch is written
+            _ => true,
+        };
+        if !batch_done {
+            continue;
+        }
+
+        let seqname = &batch[0].seqname;
+        trace!(
+            "map: processing a batch of {} ranges on {}",
+            batch.len(),
+            seqname
+        );
+        let right_ranges = right
+            .get_ranges(seqname)
+            .ok_or_else(|| GRangesError::missing_sequence(seqname))?;
+        let results: Vec<_> = batch
+            .par_iter()
+            .with_min_len(MIN_CHUNK_LENGTH)
+            .map(|range| summarize(right_ranges, range))
+            .collect::<Result<_, _>>()?;
+
+        records_read += batch.len();
+        for (range, data) in batch.drain(..).zip(results) {
+            let Some(data) = data else {
+                continue;
+            };
+            let record = GenomicRangeRecord::new(range.seqname, range.start, range.end, data);
+            writer.serialize(record)?;
+            records_written += 1;
+        }
+    }
+    writer.flush()?;
+    Ok(Report::with_counts(records_read, records_written))
+}
+
+/// How [`granges_windows()`] names each window, in a fourth column.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum WindowName {
+    /// `window_<i>`, numbering all the windows from 1.
+    Index,
+    /// The window's coordinates, as `chrom:start-end` (with a 0-based start, as in
+    /// BED).
+    Coordinates,
+    /// `<source>_<i>`, the name of the range the window is in (or its sequence, for
+    /// windows across the genome) and the window's number within it, from 1.
+    Source,
+}
+
+/// The windows of `[start, end)` of width `width`, every `step` basepairs. The
+/// windows at the end are truncated to end at `end`, or dropped if `chop` is set.
+fn range_windows(
+    start: Position,
+    end: Position,
+    width: Position,
+    step: Position,
+    chop: bool,
+) -> Vec<(Position, Position)> {
+    let mut windows = Vec::new();
+    let mut window_start = start;
+    while window_start < end {
+        let window_end = window_start + width;
+        if window_end >= end {
+            if chop {
+                break;
+            }
+            windows.push((window_start, end));
+        } else {
+            windows.push((window_start, window_end));
+        }
+        window_start += step;
+    }
+    windows
+}
+
+/// A range that [`granges_windows()`] makes windows of: a whole sequence, or a range
+/// of a BED-like file, with its name and other columns.
+struct WindowSource {
+    seqname: String,
+    start: Position,
+    end: Position,
+    name: String,
+    data: Option<String>,
+}
+
+/// Generate a BED3 file of genomic windows, across the genome or within each range
+/// of the BED-like file `per_range` (like `bedtools makewindows -b`).
+///
+/// With a [`WindowName`] style, each window is named in a fourth column. A range
+/// without a name (in the fourth column of `per_range`) is named by its coordinates.
+/// With `carry`, each window is written with all the columns of its range after the
+/// first three (and after its name, if named), e.g. its name, score, and strand.
+#[allow(clippy::too_many_arguments)]
+pub fn granges_windows(
+    seqlens: impl Into<PathBuf>,
+    width: Position,
+    step: Option<Position>,
+    chop: bool,
+    per_range: Option<&PathBuf>,
+    name: Option<WindowName>,
+    carry: bool,
+    output: Option<impl Into<PathBuf>>,
+) -> Result<CommandOutput<()>, GRangesError> {
+    let step = step.unwrap_or(width);
+    if width == 0 || step == 0 {
+        return Err(GRangesError::ZeroWindowSize);
+    }
+    let genome = read_seqlens(seqlens)?;
+    let sources: Vec<WindowSource> = match per_range {
+        None => genome
+            .iter()
+            .map(|(seqname, length)| WindowSource {
+                seqname: seqname.clone(),
+                start: 0,
+                end: *length,
+                name: seqname.clone(),
+                data: None,
+            })
+            .collect(),
+        Some(path) => {
+            let mut sources = Vec::new();
+            for result in BedlikeIterator::new(path)? {
+                let range = result?;
+                let name = match bedlike_name_strand(&range.data) {
+                    (Some(name), _) => name,
+                    (None, _) => format!("{}:{}-{}", range.seqname, range.start, range.end),
+                };
+                sources.push(WindowSource {
+                    seqname: range.seqname,
+                    start: range.start,
+                    end: range.end,
+                    name,
+                    data: range.data,
+                });
+            }
+            sources
+        }
+    };
+
+    // each window, with its name and the source range's other columns
+    let mut windows = Vec::new();
+    for source in &sources {
+        let source_windows = range_windows(source.start, source.end, width, step, chop);
+        for (i, (window_start, window_end)) in source_windows.into_iter().enumerate() {
+            let window_name = name.map(|style| match style {
+                WindowName::Index => format!("window_{}", windows.len() + 1),
+                WindowName::Coordinates => {
+                    format!("{}:{}-{}", source.seqname, window_start, window_end)
+                }
+                WindowName::Source => format!("{}_{}", source.name, i + 1),
+            });
+            windows.push((source, window_start, window_end, window_name));
+        }
+    }
+
+    let output = output.map(|path| path.into());
+    let arrow_path = output.as_ref().filter(|&path| is_arrow_ipc_path(path));
+
+    let mut report = Report::new();
+    report.records_written = windows.len();
+    if carry {
+        let mut gr = GRanges::new_vec(&genome);
+        for (source, start, end, window_name) in windows {
+            let columns: Vec<&str> = window_name
+                .as_deref()
+                .into_iter()
+                .chain(source.data.as_deref())
+                .collect();
+            gr.push_range(&source.seqname, start, end, columns.join("\t"))?;
+        }
+        gr.write_to_tsv(output, &BED_TSV)?;
+    } else if name.is_some() {
+        let mut gr = GRanges::new_vec(&genome);
+        for (source, start, end, window_name) in windows {
+            let name = window_name.expect("Internal error: please report");
+            gr.push_range(&source.seqname, start, end, Bed4Addition { name })?;
+        }
+        if let Some(path) = arrow_path {
+            #[cfg(feature = "arrow")]
+            {
+                gr.write_to_arrow_ipc(path)?;
+                return Ok(CommandOutput::new((), None));
+            }
+            #[cfg(not(feature = "arrow"))]
+            return Err(GRangesError::OutputFormatRequiresFeature(
+                path.display().to_string(),
+                "arrow".to_string(),
+            ));
+        }
+        gr.write_to_tsv(output, &BED_TSV)?;
+    } else {
+        let mut gr = GRangesEmpty::new_vec(&genome);
+        for (source, start, end, _) in windows {
+            gr.push_range(&source.seqname, start, end)?;
+        }
+        if let Some(path) = arrow_path {
+            #[cfg(feature = "arrow")]
+            {
+                gr.write_to_arrow_ipc(path)?;
+                return Ok(CommandOutput::new((), None));
+            }
+            #[cfg(not(feature = "arrow"))]
+            return Err(GRangesError::OutputFormatRequiresFeature(
+                path.display().to_string(),
+                "arrow".to_string(),
+            ));
+        }
+        gr.write_to_tsv(output, &BED_TSV)?;
+    }
+    Ok(CommandOutput::new((), Some(report)))
+}
+
+/// Compute a rolling statistic of a BED5 column (e.g. the score) in sliding windows
+/// across the genome, e.g. smoothed coverage or SNP density (with `--func count`).
+/// This is equivalent to `granges windows` followed by `granges map --assume-sorted`, but
+/// in one streaming pass: the windows are never written or stored, and the input
+/// must be sorted (by sequence, in the order of the genome file, and then by start
+/// position). The output is a bedGraph of each window and its statistic.
+#[derive(Parser)]
+pub struct Slide {
+    /// A TSV genome file of chromosome names and their lengths
+    #[arg(short, long, required = true)]
+    genome: PathBuf,
+
+    /// The sorted input BED5 file
+    #[arg(required = true)]
+    bedfile: PathBuf,
+
+    /// Width (in basepairs) of each window
+    #[arg(short, long, required = true)]
+    width: Position,
+
+    /// Step width (by default: window size)
+    #[arg(short, long)]
+    step: Option<Position>,
+
+    /// If last window remainder is shorter than width, remove?
+    #[arg(long)]
+    chop: bool,
+
+    /// The operation to apply to the values of ranges overlapping each window
+    #[arg(short, long, value_enum, default_value_t = FloatOperation::Mean)]
+    func: FloatOperation,
+
+    /// The name of the BED5 file's column to apply the operation to
+    #[arg(short, long, default_value = "score")]
+    column: String,
+
+    /// The number of significant digits of results (like bedtools' -prec)
+    #[arg(long, default_value_t = BEDTOOLS_PRECISION)]
+    precision: usize,
+
+    /// An optional output file (standard output will be used if not specified)
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+
+    /// Skip ranges from sequences (e.g. chromosomes) not present in the genome file.
+    /// By default, ranges with sequence names not in the genome file will raise an error.
+    #[arg(long)]
+    skip_missing: bool,
+
+    /// How to handle ranges that fall outside the genome: on sequences not in the
+    /// genome file, or extending past the end of their sequence
+    #[arg(long, value_enum, default_value_t)]
+    bounds: BoundsPolicy,
+}
+
+impl Slide {
+    pub fn run(&self) -> Result<CommandOutput<()>, GRangesError> {
+        if column_position(Bed5Addition::schema(), &self.column).is_none() {
+            return Err(GRangesError::NoSuchColumn(self.column.to_string()));
+        }
+        let genome = read_seqlens(&self.genome)?;
+        let windows = GenomicWindows::new(&genome, self.width, self.step, self.chop)?;
+        let ranges = checked_ranges(
+            Bed5Iterator::new(&self.bedfile)?,
+            &genome,
+            self.skip_missing,
+            self.bounds,
+        );
+
+        let _span = info_span!("streaming join").entered();
+        let mut writer = build_bed_writer(self.output.as_ref())?;
+        let operations = [self.func.clone()];
+        let config = precision_tsv_config(self.precision);
+        let mut records_written = 0;
+        let join = StreamingLeftOverlaps::new(windows.map(Ok), ranges.progress("slide"), &genome)?;
+        for result in join {
+            let (window, overlaps) = result?;
+            let mut overlap_scores: Vec<f64> = overlaps
+                .iter()
+                .map(|range| select_float(&range.data, &self.column))
+                .collect::<Result<Vec<_>, _>>()?
+                .into_iter()
+                // Filter out the `None` values.
+                .flatten()
+                .collect();
+            let Some(results) = run_operations(
+                &operations,
+                None,
+                &mut overlap_scores,
+                overlaps.len(),
+                EmptyPolicy::Operation,
+                &config,
+            )?
+            else {
+                continue;
+            };
+            let record = GenomicRangeRecord::new(window.seqname, window.start, window.end, results);
+            writer.serialize(record)?;
+            records_written += 1;
+        }
+        writer.flush()?;
+
+        let mut report = Report::new();
+        report.records_written = records_written;
+        Ok(CommandOutput::new((), Some(report)))
+    }
+}
+
+/// Aggregate the scores of several BED5 files into fixed-width bins across the
+/// genome, writing a matrix with a row for each bin, and a column for each file
+/// (named by the file name, without extensions). This is the starting point for
+/// genome-wide correlation or PCA analyses. The matrix is written as a TSV with
+/// a header, or as an Arrow IPC or Parquet file if the output file has one of
+/// their extensions (which requires the `arrow` or `parquet` feature).
+#[derive(Parser)]
+pub struct Bin {
+    /// A TSV genome file of chromosome names and their lengths
+    #[arg(short, long, required = true)]
+    genome: PathBuf,
+
+    /// The input BED5 files
+    #[arg(required = true, num_args = 1..)]
+    bedfiles: Vec<PathBuf>,
+
+    /// Width (in basepairs) of each bin
+    #[arg(short, long, required = true)]
+    width: Position,
+
+    /// The operation to apply to the values of ranges overlapping each bin
+    #[arg(short, long, value_enum, default_value_t = FloatOperation::Mean)]
+    func: FloatOperation,
+
+    /// The name of the BED5 files' column to apply the operation to
+    #[arg(short, long, default_value = "score")]
+    column: String,
+
+    /// An optional output file (standard output will be used if not specified)
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+
+    /// Skip ranges from sequences (e.g. chromosomes) not present in the genome file.
+    /// By default, ranges with sequence names not in the genome file will raise an error.
+    #[arg(long)]
+    skip_missing: bool,
+
+    /// How to handle ranges that fall outside the genome: on sequences not in the
+    /// genome file, or extending past the end of their sequence
+    #[arg(long, value_enum, default_value_t)]
+    bounds: BoundsPolicy,
+}
+
+/// The name of a file without its directory or any extensions, e.g. `sample1`
+/// for `data/sample1.bed.gz`.
+fn file_stem(path: &Path) -> String {
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    match name.split_once('.') {
+        Some((stem, _)) if !stem.is_empty() => stem.to_string(),
+        _ => name,
+    }
+}
+
+impl Bin {
+    pub fn run(&self) -> Result<CommandOutput<()>, GRangesError> {
+        if column_position(Bed5Addition::schema(), &self.column).is_none() {
+            return Err(GRangesError::NoSuchColumn(self.column.to_string()));
+        }
+        let genome = read_seqlens(&self.genome)?;
+        let mut matrix = BinnedMatrix::new(&genome, self.width)?;
+        info!("bin: {} bins", matrix.num_bins());
+
+        let mut report = Report::new();
+        for bedfile in &self.bedfiles {
+            let _span = info_span!("bin file").entered();
+            let iter = Bed5Iterator::new(bedfile)?;
+            let mut ranges: IndexMap<String, Vec<ValuedRange>> = IndexMap::new();
+            for result in checked_ranges(iter, &genome, self.skip_missing, self.bounds) {
+                let range = result?;
+                let value = select_float(&range.data, &self.column)?;
+                ranges
+                    .entry(range.seqname)
+                    .or_default()
+                    .push((range.start, range.end, value));
+                report.records_read += 1;
+            }
+            matrix.add_column(file_stem(bedfile), ranges, &self.func)?;
+        }
+        report.records_written = matrix.num_bins();
+
+        let output = self.output.as_ref();
+        if let Some(path) = output.filter(|&path| is_arrow_ipc_path(path)) {
+            #[cfg(feature = "arrow")]
+            {
+                crate::io::arrow::write_dataframe_ipc(&mut matrix.to_dataframe()?, path)?;
+                return Ok(CommandOutput::new((), Some(report)));
+            }
+            #[cfg(not(feature = "arrow"))]
+            return Err(GRangesError::OutputFormatRequiresFeature(
+                path.display().to_string(),
+                "arrow".to_string(),
+            ));
+        }
+        if let Some(path) = output.filter(|&path| is_parquet_path(path)) {
+            #[cfg(feature = "parquet")]
+            {
+                crate::io::parquet::write_dataframe_parquet(&mut matrix.to_dataframe()?, path)?;
+                return Ok(CommandOutput::new((), Some(report)));
+            }
+            #[cfg(not(feature = "parquet"))]
+            return Err(GRangesError::OutputFormatRequiresFeature(
+                path.display().to_string(),
+                "parquet".to_string(),
+            ));
+        }
+        let writer: Box<dyn Write> = match output {
+            Some(path) => Box::new(io::BufWriter::new(create_file(path)?)),
+            None => Box::new(io::BufWriter::new(io::stdout())),
+        };
+        matrix.write_tsv(writer, &BED_TSV.no_value_string)?;
+        Ok(CommandOutput::new((), Some(report)))
+    }
+}
+
+/// The statistic [`Pairwise`] calculates between each pair of files.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum PairwiseStatistic {
+    /// The Jaccard index: the basepairs covered by both files, divided by those
+    /// covered by either.
+    #[default]
+    Jaccard,
+    /// The number of basepairs covered by both files.
+    Intersection,
+    /// The number of pairs of overlapping ranges, one from each file.
+    Overlaps,
+}
+
+/// Calculate an overlap statistic between all pairs of several BED-like files, e.g.
+/// to cluster ChIP-seq peak sets by their similarity. The statistic is the Jaccard
+/// index (by default), the number of basepairs covered by both files, or the number
+/// of pairs of overlapping ranges. These are all symmetric, and are written as a
+/// matrix with a row and column for each file (named by the file name, without
+/// extensions). Pairs of files are compared in parallel.
+#[derive(Parser)]
+pub struct Pairwise {
+    /// The input BED-like TSV files.
+    #[arg(required = true, num_args = 2..)]
+    bedfiles: Vec<PathBuf>,
+
+    /// The statistic to calculate between each pair of files
+    #[arg(short, long, value_enum, default_value_t)]
+    statistic: PairwiseStatistic,
+
+    /// The number of significant digits of Jaccard indices (like bedtools' -prec)
+    #[arg(long, default_value_t = BEDTOOLS_PRECISION)]
+    precision: usize,
+
+    /// An optional output file (standard output will be used if not specified)
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+}
+
+impl Pairwise {
+    pub fn run(&self) -> Result<CommandOutput<()>, GRangesError> {
+        let mut report = Report::new();
+        let ranges = self
+            .bedfiles
+            .iter()
+            .map(|bedfile| -> Result<_, GRangesError> {
+                let _span = info_span!("read file").entered();
+                let mut iter = BedlikeIterator::new(bedfile)?.progress("pairwise");
+                let ranges = ranges_by_sequence(iter.by_ref())?;
+                report.records_read += iter.num_items();
+                Ok(ranges)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let intervals: Vec<Intervals> = ranges
+            .iter()
+            .map(|ranges| Intervals::from_ranges(ranges.clone()))
+            .collect();
+
+        let _span = info_span!("compare pairs").entered();
+        let num_files = ranges.len();
+        let pairs: Vec<(usize, usize)> = (0..num_files)
+            .flat_map(|i| (i..num_files).map(move |j| (i, j)))
+            .collect();
+        let config = precision_tsv_config(self.precision);
+        let values: Vec<String> = pairs
+            .par_iter()
+            .map(|&(i, j)| match self.statistic {
+                PairwiseStatistic::Jaccard => {
+                    config.format_float(intervals[i].jaccard(&intervals[j]))
+                }
+                PairwiseStatistic::Intersection => {
+                    intervals[i].intersection_bases(&intervals[j]).to_string()
+                }
+                PairwiseStatistic::Overlaps => {
+                    count_overlapping_pairs(&ranges[i], &ranges[j]).to_string()
+                }
+            })
+            .collect();
+        let mut matrix = vec![vec![String::new(); num_files]; num_files];
+        for ((i, j), value) in pairs.into_iter().zip(values) {
+            matrix[j][i].clone_from(&value);
+            matrix[i][j] = value;
+        }
+
+        let names: Vec<String> = self.bedfiles.iter().map(|path| file_stem(path)).collect();
+        let mut writer = build_tsv_writer(self.output.as_ref())?;
+        writer.write_record(std::iter::once("file").chain(names.iter().map(String::as_str)))?;
+        for (name, row) in names.iter().zip(matrix) {
+            writer.write_record(std::iter::once(name.clone()).chain(row))?;
+        }
+        writer.flush()?;
+        report.records_written = num_files;
+        Ok(CommandOutput::new((), Some(report)))
+    }
+}
+
+/// Generate a random BED-like file with genomic ranges, using the random number
+/// generator `rng`.
+pub fn granges_random_bed(
+    seqlens: impl Into<PathBuf>,
+    num: usize,
+    output: Option<impl Into<PathBuf>>,
+    sort: bool,
+    bed5: bool,
+    rng: &mut impl Rng,
+) -> Result<CommandOutput<()>, GRangesError> {
+    // get the genome info
+    let genome = read_seqlens(seqlens)?;
+
+    if bed5 {
+        let mut gr = random_granges_mock_bed5_with_rng(&genome, num, rng)?;
+        if sort {
+            gr = gr.sort()
+        }
+        gr.write_to_tsv(output, &BED_TSV)?;
+    } else {
+        let mut gr = random_granges_with_rng(&genome, num, rng)?;
+        if sort {
+            gr = gr.sort();
+        }
+        gr.write_to_tsv(output, &BED_TSV)?;
+    };
+
+    Ok(CommandOutput::new((), None))
+}
+
+/// The regions random ranges should not overlap: the ranges of a BED-like file of
+/// excluded regions, and assembly gaps (see [`read_gaps()`]), if either is given.
+fn excluded_regions(
+    exclude: Option<&PathBuf>,
+    gaps: Option<&PathBuf>,
+    genome: &IndexMap<String, Position>,
+) -> Result<Option<GRangesEmpty<COITreesEmpty>>, GRangesError> {
+    if exclude.is_none() && gaps.is_none() {
+        return Ok(None);
+    }
+    let mut ranges = match exclude {
+        Some(exclude) => GRangesEmpty::from_iter(Bed3Iterator::new(exclude)?, genome)?,
+        None => GRangesEmpty::new_vec(genome),
+    };
+    if let Some(gaps) = gaps {
+        for (seqname, seq_gaps) in read_gaps(gaps)?.iter() {
+            // gaps on sequences not in the genome file can't be overlapped
+            if genome.contains_key(seqname) {
+                for &(start, end) in seq_gaps {
+                    ranges.push_range(seqname, start, end)?;
+                }
+            }
+        }
+    }
+    Ok(Some(ranges.into_coitrees()?))
+}
+
+/// Generate random ranges on a genome, e.g. as a null model (see the [`random`]
+/// module documentation). Sequences are chosen with probability proportional to
+/// their length, and the ranges' widths are fixed (`--width`), uniform
+/// (`--min-width` and `--max-width`), or drawn from the widths of the ranges in a
+/// template file (`--template`). The output is sorted, and reproducible with the
+/// global `--seed` option.
+///
+/// [`random`]: crate::random
+#[derive(Parser)]
+#[command(group(
+    clap::ArgGroup::new("widths")
+        .required(true)
+        .args(["width", "min_width", "template"])
+))]
+pub struct Random {
+    /// A TSV genome file of chromosome names and their lengths
+    #[arg(short, long, required = true)]
+    genome: PathBuf,
+
+    /// The number of random ranges to generate
+    #[arg(short, long, required = true)]
+    num: usize,
+
+    /// The width of every range
+    #[arg(short, long)]
+    width: Option<Position>,
+
+    /// The minimum width of uniformly distributed range widths
+    #[arg(long, requires = "max_width")]
+    min_width: Option<Position>,
+
+    /// The maximum width of uniformly distributed range widths
+    #[arg(long, requires = "min_width")]
+    max_width: Option<Position>,
+
+    /// A BED-like file whose range widths are sampled (with replacement)
+    #[arg(short, long)]
+    template: Option<PathBuf>,
+
+    /// A BED-like file of regions the random ranges should not overlap
+    #[arg(short, long)]
+    exclude: Option<PathBuf>,
+
+    /// Assembly gaps the random ranges should not overlap: a gap track (e.g. a UCSC
+    /// gap table, or a BED file), or a FASTA file (by its extension), whose runs of
+    /// Ns are the gaps
+    #[arg(long)]
+    gaps: Option<PathBuf>,
+
+    /// Add name and random score columns (BED5)
+    #[arg(long, conflicts_with = "bed6")]
+    bed5: bool,
+
+    /// Add name, random score, and random strand columns (BED6)
+    #[arg(long)]
+    bed6: bool,
+
+    /// An optional output file (standard output will be used if not specified)
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+}
+
+impl Random {
+    pub fn run(&self) -> Result<CommandOutput<()>, GRangesError> {
+        let genome = read_seqlens(&self.genome)?;
+        let widths = match (self.width, self.min_width, self.max_width, &self.template) {
+            (Some(width), ..) => WidthDistribution::Fixed(width),
+            (None, Some(min), Some(max), _) => WidthDistribution::Uniform(min, max),
+            (.., Some(template)) => WidthDistribution::from_template(template)?,
+            _ => unreachable!("clap requires one width option"),
+        };
+        let mut generator = RandomRanges::new(&genome, widths)?;
+        if let Some(exclude) = excluded_regions(self.exclude.as_ref(), self.gaps.as_ref(), &genome)?
+        {
+            generator = generator.exclude(exclude);
+        }
+
+        let mut rng = seeded_rng();
+        let output = self.output.as_ref();
+        if self.bed6 {
+            let gr = generator.generate_bed6(self.num, &mut rng)?;
+            gr.write_to_tsv(output, &BED_TSV)?;
+        } else if self.bed5 {
+            let gr = generator.generate_bed5(self.num, &mut rng)?;
+            gr.write_to_tsv(output, &BED_TSV)?;
+        } else {
+            let gr = generator.generate(self.num, &mut rng)?;
+            gr.write_to_tsv(output, &BED_TSV)?;
+        }
+        let report = Report::with_counts(0, self.num);
+        Ok(CommandOutput::new((), Some(report)))
+    }
+}
+
+/// The GC content of the called bases (A, C, G, or T) of a sequence, or `None` if
+/// it has none (e.g. a run of Ns).
+fn called_gc_content(sequence: &[u8], region: (&str, Position, Position)) -> Option<f64> {
+    sequence
+        .iter()
+        .any(|base| matches!(base.to_ascii_uppercase(), b'A' | b'C' | b'G' | b'T'))
+        .then(|| gc_content_strict(sequence, region))
+}
+
+/// Shuffle the ranges of a BED-like file to random positions on the genome, keeping
+/// their widths and other columns, e.g. as a null set for enrichment tests (see
+/// [`RandomRanges::shuffle_matched()`]). Sequences are chosen with probability
+/// proportional to their length.
+///
+/// With `--fasta`, each shuffled range's GC content (of its called bases) is
+/// within `--gc-tolerance` of that of the original range, and with
+/// `--mappability`, its mean mappability score is within `--mappability-tolerance`
+/// of the original's, so that the null set matches these biases of the input. The
+/// output is in the input's order, and reproducible with the global `--seed`
+/// option.
+#[derive(Parser)]
+pub struct Shuffle {
+    /// A TSV genome file of chromosome names and their lengths
+    #[arg(short, long, required = true)]
+    genome: PathBuf,
+
+    /// The input BED-like TSV file
+    #[arg(required = true)]
+    bedfile: PathBuf,
+
+    /// A FASTA file of the genome, to match the GC content of each shuffled range
+    /// to that of the original range
+    #[arg(long)]
+    fasta: Option<PathBuf>,
+
+    /// The largest allowed difference between the GC content (a fraction) of a
+    /// shuffled range and that of the original range, with --fasta
+    #[arg(long, default_value_t = 0.05)]
+    gc_tolerance: f64,
+
+    /// A mappability track as a bedGraph (bigWig files can be converted with UCSC's
+    /// bigWigToBedGraph), to match the mean mappability score of each shuffled range
+    /// to that of the original range. Basepairs not in the track have a score of 0.
+    #[arg(long, value_name = "BEDGRAPH")]
+    mappability: Option<PathBuf>,
+
+    /// The largest allowed difference between the mean mappability score of a
+    /// shuffled range and that of the original range, with --mappability
+    #[arg(long, default_value_t = 0.1)]
+    mappability_tolerance: f64,
+
+    /// A BED-like file of regions the shuffled ranges should not overlap
+    #[arg(short, long)]
+    exclude: Option<PathBuf>,
+
+    /// Assembly gaps the shuffled ranges should not overlap: a gap track (e.g. a
+    /// UCSC gap table, or a BED file), or a FASTA file (by its extension), whose runs
+    /// of Ns are the gaps
+    #[arg(long)]
+    gaps: Option<PathBuf>,
+
+    /// The number of random positions tried for each range before giving up
+    #[arg(long, default_value_t = DEFAULT_MAX_ATTEMPTS)]
+    max_attempts: usize,
+
+    /// An optional output file (standard output will be used if not specified)
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+
+    /// Skip ranges from sequences (e.g. chromosomes) not present in the genome file.
+    /// By default, ranges with sequence names not in the genome file will raise an error.
+    #[arg(short, long)]
+    skip_missing: bool,
+
+    /// How to handle ranges that fall outside the genome
+    #[arg(long, value_enum, default_value_t)]
+    bounds: BoundsPolicy,
+}
+
+impl Shuffle {
+    pub fn run(&self) -> Result<CommandOutput<()>, GRangesError> {
+        let genome = read_seqlens(&self.genome)?;
+        let mut report = Report::new();
+        let parse = info_span!("parse").entered();
+        let iter = BedlikeIterator::new(&self.bedfile)?;
+        let ranges = checked_ranges(iter, &genome, self.skip_missing, self.bounds)
+            .collect::<Result<Vec<_>, _>>()?;
+        report.records_read = ranges.len();
+        let sequences = self
+            .fasta
+            .as_ref()
+            .map(|fasta| NucleotideSequences::from_fasta(fasta, None))
+            .transpose()?;
+        // mappability scores on sequences not in the genome file are never used
+        let mappability = self
+            .mappability
+            .as_ref()
+            .map(|path| read_score_track(path, &genome, true, self.bounds, "mappability"))
+            .transpose()?
+            .map(|(track, _)| track);
+        drop(parse);
+
+        let mut writer = build_bed_writer(self.output.as_ref())?;
+        if !ranges.is_empty() {
+            let _span = info_span!("shuffle").entered();
+            let widths = ranges.iter().map(|range| range.end - range.start).collect();
+            let mut generator = RandomRanges::new(&genome, WidthDistribution::Empirical(widths))?
+                .max_attempts(self.max_attempts);
+            if let Some(exclude) =
+                excluded_regions(self.exclude.as_ref(), self.gaps.as_ref(), &genome)?
+            {
+                generator = generator.exclude(exclude);
+            }
+
+            let mut features = Vec::new();
+            if let Some(sequences) = &sequences {
+                features.push(MatchedFeature::new(
+                    move |seqname, start, end| {
+                        sequences.region_map(&called_gc_content, seqname, start, end)
+                    },
+                    self.gc_tolerance,
+                ));
+            }
+            if let Some(track) = &mappability {
+                // basepairs not in the track have a score of 0
+                let mean_score = |view: Rle<Option<f64>>, _: (&str, Position, Position)| {
+                    view.map(|score| score.unwrap_or(0.0)).mean()
+                };
+                features.push(MatchedFeature::new(
+                    move |seqname, start, end| track.region_map(&mean_score, seqname, start, end),
+                    self.mappability_tolerance,
+                ));
+            }
+
+            let shuffled = generator.shuffle_matched(&ranges, &features, &mut seeded_rng())?;
+            for range in shuffled {
+                writer.write_record(bedlike_columns(range))?;
+                report.records_written += 1;
+            }
+        }
+        writer.flush()?;
+        Ok(CommandOutput::new((), Some(report)))
+    }
+}
+
+/// Merges all the genomic ranges if they overlap by `distance`.
+#[derive(Parser)]
+pub struct Merge {
+    /// The input BED-like TSV file to merge.
+    #[arg(short, long, required = true)]
+    bedfile: PathBuf,
+
+    /// The minimum distance at which to merge ranges. Like `bedtools merge`,
+    /// `--distance 0` will merge "book-ended" ranges. Negative numbers
+    /// will only merge ranges that overlap by that degree of overlap.
+    #[clap(short, long, default_value_t = 0)]
+    distance: PositionOffset,
+
+    /// Also merge ranges that are within this fraction of the width of the
+    /// narrower of the two ranges (e.g. `--fraction 0.5` merges two 100bp peaks
+    /// up to 50bp apart), if that is larger than `--distance`.
+    #[arg(long)]
+    fraction: Option<f64>,
+
+    /// Only merge ranges on the same strand (the sixth BED column), like
+    /// `bedtools merge -s`. The strand is output as a fourth column.
+    #[arg(short, long)]
+    stranded: bool,
+
+    /// Only merge (and output) the ranges on this strand ('+' or '-').
+    #[arg(short = 'S', long, value_parser = parse_strand_arg)]
+    strand: Option<Strand>,
+
+    ///// Whether to "group by" feature name, i.e. overlapping ranges
+    ///// with different feature names will not be merged.
+    //#[clap(short, long)]
+    //group_features: usize,
+    /// Operation to do to summarize the score column.
+    #[clap(short, long, value_parser = clap::value_parser!(FloatOperation))]
+    func: Option<FloatOperation>,
+
+    /// Columns to carry onto the merged ranges, as comma-separated
+    /// '<column>:<policy>' pairs, e.g. 'name:collapse,score:sum'. The column is
+    /// name, score, strand, or a column number after the first three, and the
+    /// policy is first, collapse, distinct, or sum.
+    #[arg(long, value_delimiter = ',', conflicts_with = "func")]
+    carry: Vec<ColumnCarry>,
+
+    /// An optional output file (standard output will be used if not specified)
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+}
+
+/// Parse a strand command line argument, `+` or `-`.
+fn parse_strand_arg(arg: &str) -> Result<Strand, String> {
+    parse_strand(arg).ok_or_else(|| format!("invalid strand '{}': expected '+' or '-'", arg))
+}
+
+/// The [`CommandOutput`] of merging `records_read` ranges into `records_written` ranges.
+fn merge_output(records_read: usize, records_written: usize) -> CommandOutput<()> {
+    let mut report = Report::new();
+    report.records_read = records_read;
+    report.records_written = records_written;
+    CommandOutput::new((), Some(report))
+}
+
+/// The strand (sixth) column of a BED-like range, which must be `+` or `-`.
+fn required_strand(range: &GenomicRangeRecord<Option<String>>) -> Result<Strand, GRangesError> {
+    range
+        .data
+        .as_deref()
+        .and_then(|data| data.split('\t').nth(2))
+        .and_then(parse_strand)
+        .ok_or_else(|| {
+            GRangesError::MissingStrand(format!(
+                "the range {}:{}-{}",
+                range.seqname, range.start, range.end
+            ))
+        })
+}
+
+impl Merge {
+    // TODO optional genome file for validation?
+    pub fn run(&self) -> Result<CommandOutput<()>, GRangesError> {
+        if !self.carry.is_empty() {
+            return self.run_carrying();
+        }
+        let bedfile = &self.bedfile;
+        let distance = &self.distance;
+        let fraction = self.fraction;
+        let ranges_iter = GenomicRangesFile::parsing_iterator(bedfile)?;
+        let func = &self.func;
+        let stranded = self.stranded || self.strand.is_some();
+
+        let mut writer = build_bed_writer(self.output.as_ref())?;
+
+        // only BED-like files (BED6 and beyond) have a strand column
+        if stranded && !matches!(ranges_iter, GenomicRangesParser::Bedlike(_)) {
+            return Err(GRangesError::MissingStrand(bedfile.display().to_string()));
+        }
+
+        match ranges_iter {
+            GenomicRangesParser::Bed3(iter) => {
+                let mut iter = iter.progress("merge");
+                let merging_iter =
+                    MergingEmptyResultIterator::new(iter.by_ref(), *distance).fraction(fraction);
+                let mut records_written = 0;
+                for result in merging_iter {
+                    let record = result?;
+                    writer.serialize(record)?;
+                    records_written += 1;
+                }
+                Ok(merge_output(iter.num_items(), records_written))
+            }
+            GenomicRangesParser::Bed4(iter) => {
+                let mut iter = iter.progress("merge");
+                let merging_iter = MergingResultIterator::new(iter.by_ref(), *distance, |data| {
+                    data.into_iter()
+                        .map(|x| x.name)
+                        .collect::<Vec<_>>()
+                        .join(",")
+                })
+                .fraction(fraction);
+                let mut records_written = 0;
+                for result in merging_iter {
+                    let record = result?;
+                    writer.serialize(record)?;
+                    records_written += 1;
+                }
+                Ok(merge_output(iter.num_items(), records_written))
+            }
+            GenomicRangesParser::Bed5(iter) => {
+                // merging iterator, where we extract scores and apply an operation to all merged genomic ranges' scores
+                let mut iter = iter.progress("merge");
+                let merging_iter = MergingResultIterator::new(iter.by_ref(), *distance, |data| {
+                    let mut scores: Vec<f64> = data
+                        .into_iter()
+                        .filter_map(|bed5_cols| bed5_cols.score)
+                        .collect();
+                    // this unwrap is safe -- if func is None, we use Bed3
+                    func.as_ref().unwrap().run(&mut scores)
+                })
+                .fraction(fraction);
+
+                let mut records_written = 0;
+                for result in merging_iter {
+                    let record = result?;
+                    writer.serialize(record)?;
+                    records_written += 1;
+                }
+                Ok(merge_output(iter.num_items(), records_written))
+            }
+            GenomicRangesParser::Bedlike(iter) if stranded => {
+                let mut iter = iter.progress("merge");
+                let only_strand = self.strand;
+                let stranded_iter = iter
+                    .by_ref()
+                    .map(|result| -> Result<_, GRangesError> {
+                        let range = result?;
+                        let strand = required_strand(&range)?;
+                        Ok(GenomicRangeRecord {
+                            seqname: range.seqname,
+                            start: range.start,
+                            end: range.end,
+                            data: strand,
+                        })
+                    })
+                    .filter(|result| match (result, only_strand) {
+                        (Ok(range), Some(strand)) => range.data == strand,
+                        _ => true,
+                    });
+                let merging_iter = GroupedMergingResultIterator::new(
+                    stranded_iter,
+                    *distance,
+                    |strands: Vec<Strand>| strands[0],
+                    |range: &GenomicRangeRecord<Strand>| range.data,
+                )
+                .fraction(fraction);
+
+                let mut records_written = 0;
+                for result in merging_iter {
+                    let record = result?;
+                    writer.serialize(record)?;
+                    records_written += 1;
+                }
+                Ok(merge_output(iter.num_items(), records_written))
+            }
+            GenomicRangesParser::Bedlike(iter) => {
+                // the extra columns are dropped, as with BED3 input
+                let mut iter = iter.progress("merge");
+                let empty_iter = iter
+                    .by_ref()
+                    .map(|result| result.map(|range| range.into_empty()));
+                let merging_iter =
+                    MergingEmptyResultIterator::new(empty_iter, *distance).fraction(fraction);
+                let mut records_written = 0;
+                for result in merging_iter {
+                    let record = result?;
+                    writer.serialize(record)?;
+                    records_written += 1;
+                }
+                Ok(merge_output(iter.num_items(), records_written))
+            }
+            GenomicRangesParser::Unsupported => {
+                Err(GRangesError::UnsupportedGenomicRangesFileFormat)
+            }
+        }
+    }
+
+    /// Merge the ranges of any BED-like file, combining the `--carry` columns of the
+    /// merged ranges (see [`carry_columns()`]).
+    fn run_carrying(&self) -> Result<CommandOutput<()>, GRangesError> {
+        let mut writer = build_bed_writer(self.output.as_ref())?;
+        let mut iter = BedlikeIterator::new(&self.bedfile)?.progress("merge");
+        let mut records_written = 0;
+
+        if self.stranded || self.strand.is_some() {
+            let only_strand = self.strand;
+            let stranded_iter = iter
+                .by_ref()
+                .map(|result| -> Result<_, GRangesError> {
+                    let range = result?;
+                    let strand = required_strand(&range)?;
+                    Ok(GenomicRangeRecord {
+                        seqname: range.seqname,
+                        start: range.start,
+                        end: range.end,
+                        data: (strand, range.data),
+                    })
+                })
+                .filter(|result| match (result, only_strand) {
+                    (Ok(range), Some(strand)) => range.data.0 == strand,
+                    _ => true,
+                });
+            let merging_iter = GroupedMergingResultIterator::new(
+                stranded_iter,
+                self.distance,
+                |data: Vec<(Strand, Option<String>)>| {
+                    let strand = data[0].0;
+                    (strand, data.into_iter().map(|(_, data)| data).collect())
+                },
+                |range: &GenomicRangeRecord<(Strand, Option<String>)>| range.data.0,
+            )
+            .fraction(self.fraction);
+            for result in merging_iter {
+                let range = result?;
+                let (strand, data): (Strand, Vec<_>) = range.data;
+                writer.write_record([
+                    range.seqname,
+                    range.start.to_string(),
+                    range.end.to_string(),
+                    strand.symbol().to_string(),
+                    carry_columns(&self.carry, &data)?,
+                ])?;
+                records_written += 1;
+            }
+        } else {
+            // the data of the merged ranges are combined as they're written, since
+            // combining them can fail
+            let merging_iter =
+                MergingResultIterator::new(iter.by_ref(), self.distance, |data| data)
+                    .fraction(self.fraction);
+            for result in merging_iter {
+                let range = result?;
+                writer.write_record([
+                    range.seqname,
+                    range.start.to_string(),
+                    range.end.to_string(),
+                    carry_columns(&self.carry, &range.data)?,
+                ])?;
+                records_written += 1;
+            }
+        }
+        writer.flush()?;
+        Ok(merge_output(iter.num_items(), records_written))
+    }
+}
+
+/// Write the complement of the ranges of a BED-like file: the parts of the genome
+/// not covered by any range, like `bedtools complement`. The input need not be
+/// sorted, and the complement is written in the genome file's order. With
+/// `--restrict`, the complement is only within the given regions (e.g. the
+/// mappable genome, or a set of loci), rather than the whole sequences. With
+/// `--stranded`, the complement is found separately for each strand, e.g. the
+/// regions of each strand without a transcript.
+#[derive(Parser)]
+pub struct Complement {
+    /// A TSV genome file of chromosome names and their lengths
+    #[arg(short, long, required = true)]
+    genome: PathBuf,
+
+    /// The input BED-like TSV file
+    #[arg(required = true)]
+    bedfile: PathBuf,
+
+    /// A BED-like file of the regions to find the complement within (by default,
+    /// the whole sequences of the genome file). Regions on sequences not in the
+    /// genome file are ignored.
+    #[arg(long, value_name = "BEDFILE")]
+    restrict: Option<PathBuf>,
+
+    /// Assembly gaps to leave out of the complement: a gap track (e.g. a UCSC gap
+    /// table, or a BED file), or a FASTA file (by its extension), whose runs of Ns
+    /// are the gaps
+    #[arg(long)]
+    gaps: Option<PathBuf>,
+
+    /// Find the complement of each strand (the sixth BED column) separately. The
+    /// strand is output as a fourth column.
+    #[arg(short, long)]
+    stranded: bool,
+
+    /// An optional output file (standard output will be used if not specified)
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+
+    /// Skip ranges from sequences (e.g. chromosomes) not present in the genome file.
+    /// By default, ranges with sequence names not in the genome file will raise an error.
+    #[arg(long)]
+    skip_missing: bool,
+
+    /// How to handle ranges that fall outside the genome: on sequences not in the
+    /// genome file, or extending past the end of their sequence
+    #[arg(long, value_enum, default_value_t)]
+    bounds: BoundsPolicy,
+}
+
+impl Complement {
+    pub fn run(&self) -> Result<CommandOutput<()>, GRangesError> {
+        let genome = read_seqlens(&self.genome)?;
+        let mut report = Report::new();
+
+        // the covered ranges of each sequence (and strand, if stranded)
+        let mut covered: HashMap<(String, Option<Strand>), Vec<(Position, Position)>> =
+            HashMap::new();
+        let iter = BedlikeIterator::new(&self.bedfile)?;
+        let ranges = checked_ranges(iter, &genome, self.skip_missing, self.bounds);
+        for result in ranges.progress("complement") {
+            let range = result?;
+            report.records_read += 1;
+            let strand = if self.stranded {
+                Some(required_strand(&range)?)
+            } else {
+                None
+            };
+            covered
+                .entry((range.seqname, strand))
+                .or_default()
+                .push((range.start, range.end));
+        }
+
+        // the regions of each sequence to find the complement within
+        let mut restricted: HashMap<String, Vec<(Position, Position)>> = HashMap::new();
+        if let Some(path) = &self.restrict {
+            let iter = BedlikeIterator::new(path)?;
+            for result in checked_ranges(iter, &genome, true, self.bounds) {
+                let region = result?;
+                restricted
+                    .entry(region.seqname)
+                    .or_default()
+                    .push((region.start, region.end));
+            }
+        }
+
+        let gaps = self.gaps.as_ref().map(read_gaps).transpose()?;
+        let strands = if self.stranded {
+            vec![Some(Strand::Forward), Some(Strand::Reverse)]
+        } else {
+            vec![None]
+        };
+        let mut writer = build_bed_writer(self.output.as_ref())?;
+        for (seqname, length) in &genome {
+            let mut universe = match self.restrict {
+                Some(_) => merge_intervals(restricted.remove(seqname).unwrap_or_default()),
+                None => vec![(0, *length)],
+            };
+            if let Some(gaps) = &gaps {
+                universe = complement_intervals(gaps.on(seqname).to_vec(), &universe);
+            }
+            let mut gaps = Vec::new();
+            for strand in &strands {
+                let ranges = covered
+                    .remove(&(seqname.clone(), *strand))
+                    .unwrap_or_default();
+                for (start, end) in complement_intervals(ranges, &universe) {
+                    gaps.push((start, end, *strand));
+                }
+            }
+            // the gaps of both strands in order (the forward strand's first, on ties)
+            gaps.sort_by_key(|&(start, end, _)| (start, end));
+            for (start, end, strand) in gaps {
+                let mut record = vec![seqname.clone(), start.to_string(), end.to_string()];
+                record.extend(strand.map(|strand| strand.symbol().to_string()));
+                writer.write_record(&record)?;
+                report.records_written += 1;
+            }
+        }
+        writer.flush()?;
+        Ok(CommandOutput::new((), Some(report)))
+    }
+}
+
+/// Filter out ranges not in the specified "genome" file.
+#[derive(Parser)]
+pub struct FilterChroms {
+    /// A TSV genome file of chromosome names and their lengths
+    #[arg(short, long, required = true)]
+    genome: PathBuf,
+
+    /// The input BED file.
+    #[arg(short, long, required = true)]
+    bedfile: PathBuf,
+
+    /// An optional output file (standard output will be used if not specified)
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+}
+
+impl FilterChroms {
+    pub fn run(&self) -> Result<CommandOutput<()>, GRangesError> {
+        let bedfile = &self.bedfile;
+        let genome = read_seqlens(&self.genome)?;
+        let bedlike_iterator = BedlikeIterator::new(bedfile)?;
+
+        let mut writer = build_bed_writer(self.output.as_ref())?;
+
+        // If we don't need to sort, use iterator-based streaming processing.
+        for record in bedlike_iterator {
+            let range = record?;
+            let seqname = &range.seqname;
+            let passes_filter = genome.contains_key(seqname);
+            if passes_filter {
+                writer.serialize(range)?;
+            }
+        }
+
+        Ok(CommandOutput::new((), None))
+    }
+}
+
+/// Save a BED-like file as a binary `.granges` file, which can be loaded
+/// back quickly without re-parsing (see [`crate::io::binary`]).
+#[derive(Parser)]
+pub struct Save {
+    /// A TSV genome file of chromosome names and their lengths
+    #[arg(short, long, required = true)]
+    genome: PathBuf,
+
+    /// The input BED-like TSV file.
+    #[arg(required = true)]
+    bedfile: PathBuf,
+
+    /// The output `.granges` file.
+    #[arg(short, long, required = true)]
+    output: PathBuf,
+
+    /// Skip ranges from sequences (e.g. chromosomes) not present in the genome file.
+    /// By default, ranges with sequence names not in the genome file will raise an error.
+    #[arg(short, long)]
+    skip_missing: bool,
+
+    /// How to handle ranges that fall outside the genome
+    #[arg(long, value_enum, default_value_t)]
+    bounds: BoundsPolicy,
+}
+
+impl Save {
+    pub fn run(&self) -> Result<CommandOutput<()>, GRangesError> {
+        let genome = read_seqlens(&self.genome)?;
+        let bedlike_iterator = BedlikeIterator::new(&self.bedfile)?;
+
+        let ranges = checked_ranges(bedlike_iterator, &genome, self.skip_missing, self.bounds);
+        let gr = GRanges::from_iter(ranges, &genome)?;
+        gr.save(&self.output)?;
+        Ok(CommandOutput::new((), None))
+    }
+}
+
+/// Load a binary `.granges` file created by `granges save`, writing it
+/// as a BED-like TSV file.
+#[derive(Parser)]
+pub struct Load {
+    /// The input `.granges` file.
+    #[arg(required = true)]
+    input: PathBuf,
+
+    /// An optional output file (standard output will be used if not specified)
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+}
+
+impl Load {
+    pub fn run(&self) -> Result<CommandOutput<()>, GRangesError> {
+        let gr: GRanges<VecRangesIndexed, Vec<Option<String>>> = GRanges::load(&self.input)?;
+        let has_data = gr
+            .data()
+            .ok_or(GRangesError::NoDataContainer)?
+            .iter()
+            .any(|data| data.is_some());
+        if has_data {
+            gr.write_to_tsv(self.output.as_ref(), &BED_TSV)?;
+        } else {
+            // BED3 input: write without the empty data column
+            gr.into_granges_empty()?
+                .write_to_tsv(self.output.as_ref(), &BED_TSV)?;
+        }
+        Ok(CommandOutput::new((), None))
+    }
+}
+
+/// Report summary statistics of the ranges in a BED-like file: the number of
+/// ranges, the basepairs covered (overall and per sequence), the distribution of
+/// range widths, and the fraction of ranges overlapping another range in the file.
+#[derive(Parser)]
+pub struct Stats {
+    /// The input BED-like TSV file.
+    #[arg(required = true)]
+    bedfile: PathBuf,
+
+    /// The output format
+    #[arg(short, long, value_enum, default_value_t = StatsFormat::Text)]
+    format: StatsFormat,
+
+    /// Report per-sequence statistics by chromosome arm (e.g. 'chr1p') instead, using
+    /// the bands of this UCSC cytoBand file. Ranges are assigned to the arm containing
+    /// their midpoint.
+    #[arg(long, value_name = "CYTOBANDS")]
+    by_arm: Option<PathBuf>,
+
+    /// An optional output file (standard output will be used if not specified)
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+}
+
+/// Name each range's sequence by the chromosome arm containing its midpoint (e.g.
+/// `chr1p`), if `cytobands` are given, to group ranges by arm rather than by
+/// sequence. Ranges that aren't in a band with an arm keep their sequence name.
+fn ranges_by_arm<'a, I>(
+    iter: I,
+    cytobands: Option<&'a Cytobands>,
+) -> impl Iterator<Item = Result<GenomicRangeRecord<Option<String>>, GRangesError>> + 'a
+where
+    I: Iterator<Item = Result<GenomicRangeRecord<Option<String>>, GRangesError>> + 'a,
+{
+    iter.map(move |result| {
+        let mut range = result?;
+        if let Some(arm) = cytobands.and_then(|cytobands| cytobands.range_arm(&range)) {
+            range.seqname = arm;
+        }
+        Ok(range)
+    })
+}
+
+impl Stats {
+    pub fn run(&self) -> Result<CommandOutput<()>, GRangesError> {
+        let cytobands = self.by_arm.as_ref().map(Cytobands::from_file).transpose()?;
+        let iter = BedlikeIterator::new(&self.bedfile)?.progress("stats");
+        let stats = RangeStats::from_ranges(ranges_by_arm(iter, cytobands.as_ref()))?;
+        let writer: Box<dyn Write> = match &self.output {
+            Some(path) => create_file(path)?,
+            None => Box::new(io::stdout()),
+        };
+        stats.write(writer, &self.format)?;
+        let mut report = Report::new();
+        report.records_read = stats.num_ranges;
+        Ok(CommandOutput::new((), Some(report)))
+    }
+}
+
+/// Summarize a BED-like file by sequence (e.g. chromosome) in a single pass, writing
+/// a TSV with each sequence's number of ranges, the number of basepairs covered by at
+/// least one range, and the mean score (the fifth column) of the ranges with one. The
+/// input does not need to be sorted; sequences are written in the order they are first
+/// seen.
+#[derive(Parser)]
+pub struct SummaryPerChrom {
+    /// The input BED-like TSV file.
+    #[arg(required = true)]
+    bedfile: PathBuf,
+
+    /// The number of significant digits of the mean scores (like bedtools' -prec)
+    #[arg(long, default_value_t = BEDTOOLS_PRECISION)]
+    precision: usize,
+
+    /// Summarize by chromosome arm (e.g. 'chr1p') instead, using the bands of this
+    /// UCSC cytoBand file. Ranges are assigned to the arm containing their midpoint.
+    #[arg(long, value_name = "CYTOBANDS")]
+    by_arm: Option<PathBuf>,
+
+    /// An optional output file (standard output will be used if not specified)
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+}
+
+impl SummaryPerChrom {
+    pub fn run(&self) -> Result<CommandOutput<()>, GRangesError> {
+        let cytobands = self.by_arm.as_ref().map(Cytobands::from_file).transpose()?;
+        let mut iter = BedlikeIterator::new(&self.bedfile)?.progress("summary");
+        let summaries = SequenceSummary::from_ranges(
+            ranges_by_arm(iter.by_ref(), cytobands.as_ref()),
+            |range: &GenomicRangeRecord<Option<String>>| {
+                range
+                    .data
+                    .as_deref()
+                    .and_then(|data| data.split('\t').nth(1))
+                    .filter(|score| !is_na_value(score))
+                    .and_then(|score| score.parse().ok())
+            },
+        )?;
+
+        let config = precision_tsv_config(self.precision);
+        let mut writer = build_tsv_writer(self.output.as_ref())?;
+        let group = if cytobands.is_some() {
+            "arm"
+        } else {
+            "sequence"
+        };
+        writer.write_record([group, "ranges", "covered_bases", "mean_score"])?;
+        for (seqname, summary) in &summaries {
+            let mean_score = match summary.mean_score {
+                Some(mean_score) => config.format_float(mean_score),
+                None => na_value().to_string(),
+            };
+            writer.write_record([
+                seqname.clone(),
+                summary.num_ranges.to_string(),
+                summary.covered_bases.to_string(),
+                mean_score,
+            ])?;
+        }
+        writer.flush()?;
+
+        let mut report = Report::new();
+        report.records_read = iter.num_items();
+        report.records_written = summaries.len();
+        Ok(CommandOutput::new((), Some(report)))
+    }
+}
+
+/// Calculate a histogram of the widths of the ranges in a BED-like file, or of
+/// a column of a BED5 file (e.g. the score), for quality control of e.g. peak or
+/// fragment files. The histogram is written as a TSV of bin start, end, and count,
+/// or with `--plot`, as a quick plot for viewing in the terminal.
+#[derive(Parser)]
+pub struct Hist {
+    /// The input BED-like TSV file.
+    #[arg(required = true)]
+    bedfile: PathBuf,
+
+    /// The BED5 column to calculate the histogram of, e.g. 'score'. By default,
+    /// the histogram of range widths is calculated.
+    #[arg(short, long)]
+    column: Option<String>,
+
+    /// The number of bins
+    #[arg(short, long, default_value = "20")]
+    bins: NonZeroUsize,
+
+    /// Use bins of equal width on a log10 scale. Values that are not positive are skipped.
+    #[arg(short, long)]
+    log: bool,
+
+    /// Write a quick plot for viewing in the terminal, rather than a TSV
+    #[arg(short, long)]
+    plot: bool,
+
+    /// An optional output file (standard output will be used if not specified)
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+}
+
+impl Hist {
+    pub fn run(&self) -> Result<CommandOutput<()>, GRangesError> {
+        let mut values = Vec::new();
+        let mut num_missing = 0;
+        match &self.column {
+            Some(column) => {
+                if column_position(Bed5Addition::schema(), column).is_none() {
+                    return Err(GRangesError::NoSuchColumn(column.to_string()));
+                }
+                for record in Bed5Iterator::new(&self.bedfile)?.progress("hist") {
+                    match select_float(&record?.data, column)? {
+                        Some(value) => values.push(value),
+                        None => num_missing += 1,
+                    }
+                }
+            }
+            None => {
+                for record in BedlikeIterator::new(&self.bedfile)?.progress("hist") {
+                    values.push(checked_width(&record?)? as f64);
+                }
+            }
+        }
+
+        let histogram = Histogram::new(&values, self.bins, self.log)?;
+        let writer: Box<dyn Write> = match &self.output {
+            Some(path) => create_file(path)?,
+            None => Box::new(io::stdout()),
+        };
+        if self.plot {
+            histogram.write_plot(writer)?;
+        } else {
+            histogram.write_tsv(writer)?;
+        }
+
+        let records_read = values.len() + num_missing;
+        let mut report = Report::with_counts(records_read, records_read);
+        report.records_skipped = num_missing + histogram.num_skipped;
+        Ok(CommandOutput::new((), Some(report)))
+    }
+}
+
+/// Test whether two sets of ranges overlap more (or less) than expected by chance,
+/// with Fisher's exact test on the contingency table of basepairs covered by each
+/// set (like `bedtools fisher`, but counting basepairs), and optionally, a
+/// permutation test where the left ranges are randomly placed on their sequence.
+/// Both tests report p-values and the fold enrichment of the overlap. The
+/// permutations are reproducible with the global `--seed` option.
+#[derive(Parser)]
+pub struct Fisher {
+    /// A TSV genome file of chromosome names and their lengths
+    #[arg(short, long, required = true)]
+    genome: PathBuf,
+
+    /// The "left" BED-like TSV file
+    #[arg(short, long, required = true)]
+    left: PathBuf,
+
+    /// The "right" BED-like TSV file
+    #[arg(short, long, required = true)]
+    right: PathBuf,
+
+    /// The number of permutations for a permutation test (by default, none are run)
+    #[arg(short, long, default_value_t = 0)]
+    permutations: usize,
+
+    /// Assembly gaps to exclude from both tests, so that shuffled ranges are never
+    /// placed in them and they don't count as basepairs in neither set: a gap track
+    /// (e.g. a UCSC gap table, or a BED file), or a FASTA file (by its extension),
+    /// whose runs of Ns are the gaps
+    #[arg(long)]
+    gaps: Option<PathBuf>,
+
+    /// The output format
+    #[arg(short, long, value_enum, default_value_t = StatsFormat::Text)]
+    format: StatsFormat,
+
+    /// An optional output file (standard output will be used if not specified)
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+
+    /// Skip ranges from sequences (e.g. chromosomes) not present in the genome file.
+    /// By default, ranges with sequence names not in the genome file will raise an error.
+    #[arg(long)]
+    skip_missing: bool,
+
+    /// How to handle ranges that fall outside the genome: on sequences not in the
+    /// genome file, or extending past the end of their sequence
+    #[arg(long, value_enum, default_value_t)]
+    bounds: BoundsPolicy,
+}
+
+impl Fisher {
+    pub fn run(&self) -> Result<CommandOutput<()>, GRangesError> {
+        let genome = read_seqlens(&self.genome)?;
+        let read_ranges = |path: &PathBuf| {
+            let iter = BedlikeIterator::new(path)?;
+            let ranges = checked_ranges(iter, &genome, self.skip_missing, self.bounds);
+            ranges_by_sequence(ranges)
+        };
+        let left = read_ranges(&self.left)?;
+        let right = read_ranges(&self.right)?;
+        if left.is_empty() || right.is_empty() {
+            return Err(GRangesError::NoRows);
+        }
+        let records_read = [&left, &right]
+            .iter()
+            .flat_map(|ranges| ranges.values())
+            .map(|seq_ranges| seq_ranges.len())
+            .sum();
+
+        let right = Intervals::from_ranges(right);
+        let left_intervals = Intervals::from_ranges(left.clone());
+        let gaps = self.gaps.as_ref().map(read_gaps).transpose()?;
+        let fisher = match &gaps {
+            Some(gaps) => {
+                FisherTest::from_intervals_excluding(&left_intervals, &right, &genome, gaps)
+            }
+            None => FisherTest::from_intervals(&left_intervals, &right, &genome),
+        };
+        let permutation = (self.permutations > 0).then(|| {
+            let seed = seed();
+            info!(
+                "fisher: running {} permutations with seed {}",
+                self.permutations, seed
+            );
+            let _span = info_span!("permutations").entered();
+            match &gaps {
+                Some(gaps) => {
+                    let regions = ungapped_regions(&genome, gaps);
+                    PermutationTest::within(&left, &right, &regions, self.permutations, seed)
+                }
+                None => PermutationTest::new(&left, &right, &genome, self.permutations, seed),
+            }
+        });
+
+        let writer: Box<dyn Write> = match &self.output {
+            Some(path) => create_file(path)?,
+            None => Box::new(io::stdout()),
+        };
+        EnrichmentTests { fisher, permutation }.write(writer, &self.format)?;
+
+        let mut report = Report::new();
+        report.records_read = records_read;
+        Ok(CommandOutput::new((), Some(report)))
+    }
+}
+
+/// Estimate confidence intervals of statistics of the overlap between the left and
+/// right ranges (the overlapping basepairs, Jaccard index, fractions of each set
+/// overlapped, and fold enrichment) with a block bootstrap: the genome is tiled into
+/// blocks of `--block-length`, which are resampled with replacement, so that
+/// clustered ranges aren't treated as independent (see the [`bootstrap`] module
+/// documentation). The replicates are run in parallel, and are reproducible with the
+/// global `--seed` option.
+///
+/// [`bootstrap`]: crate::bootstrap
+#[derive(Parser)]
+pub struct Bootstrap {
+    /// A TSV genome file of chromosome names and their lengths
+    #[arg(short, long, required = true)]
+    genome: PathBuf,
+
+    /// The "left" BED-like TSV file
+    #[arg(short, long, required = true)]
+    left: PathBuf,
+
+    /// The "right" BED-like TSV file
+    #[arg(short, long, required = true)]
+    right: PathBuf,
+
+    /// The length of the resampled blocks (in basepairs), which should be longer
+    /// than the typical distance between clustered ranges
+    #[arg(short, long, default_value_t = 1_000_000)]
+    block_length: Position,
+
+    /// The number of bootstrap replicates
+    #[arg(short = 'n', long, default_value_t = 1000)]
+    replicates: usize,
+
+    /// The confidence level of the (percentile) confidence intervals
+    #[arg(short, long, default_value_t = 0.95)]
+    confidence: f64,
+
+    /// Assembly gaps to leave out of every block: a gap track (e.g. a UCSC gap
+    /// table, or a BED file), or a FASTA file (by its extension), whose runs of Ns
+    /// are the gaps
+    #[arg(long)]
+    gaps: Option<PathBuf>,
+
+    /// The output format
+    #[arg(short, long, value_enum, default_value_t = StatsFormat::Text)]
+    format: StatsFormat,
+
+    /// An optional output file (standard output will be used if not specified)
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+
+    /// Skip ranges from sequences (e.g. chromosomes) not present in the genome file.
+    /// By default, ranges with sequence names not in the genome file will raise an error.
+    #[arg(long)]
+    skip_missing: bool,
+
+    /// How to handle ranges that fall outside the genome: on sequences not in the
+    /// genome file, or extending past the end of their sequence
+    #[arg(long, value_enum, default_value_t)]
+    bounds: BoundsPolicy,
+}
+
+impl Bootstrap {
+    pub fn run(&self) -> Result<CommandOutput<()>, GRangesError> {
+        let genome = read_seqlens(&self.genome)?;
+        let read_ranges = |path: &PathBuf| {
+            let iter = BedlikeIterator::new(path)?;
+            let ranges = checked_ranges(iter, &genome, self.skip_missing, self.bounds);
+            ranges_by_sequence(ranges)
+        };
+        let left = read_ranges(&self.left)?;
+        let right = read_ranges(&self.right)?;
+        let records_read = [&left, &right]
+            .iter()
+            .flat_map(|ranges| ranges.values())
+            .map(|seq_ranges| seq_ranges.len())
+            .sum();
+
+        let gaps = self.gaps.as_ref().map(read_gaps).transpose()?;
+        let bootstrap = BlockBootstrap::new(
+            &Intervals::from_ranges(left),
+            &Intervals::from_ranges(right),
+            &genome,
+            self.block_length,
+            gaps.as_ref(),
+        )?;
+        let seed = seed();
+        info!(
+            "bootstrap: running {} replicates of {} blocks with seed {}",
+            self.replicates,
+            bootstrap.blocks().len(),
+            seed
+        );
+        let span = info_span!("bootstrap").entered();
+        let results = bootstrap.confidence_intervals(self.replicates, self.confidence, seed)?;
+        drop(span);
+
+        let writer: Box<dyn Write> = match &self.output {
+            Some(path) => create_file(path)?,
+            None => Box::new(io::stdout()),
+        };
+        results.write(writer, &self.format)?;
+
+        let mut report = Report::new();
+        report.records_read = records_read;
+        Ok(CommandOutput::new((), Some(report)))
+    }
+}
+
+/// Test the significance of the overlap between the left and right ranges with a
+/// Monte Carlo test: the left ranges are shuffled (in parallel) to random positions
+/// on their sequences, and an overlap statistic (e.g. the overlapping basepairs, or
+/// the Jaccard index) of the observed ranges is compared to its distribution over
+/// the shuffles, with a z-score and empirical p-values (see [`MonteCarloTest`]).
+/// Excluded regions and assembly gaps are never shuffled into, and don't count
+/// towards the statistic. The shuffles are reproducible with the global `--seed`
+/// option, and the results are also added to the report (e.g. with `--report
+/// json`).
+#[derive(Parser)]
+pub struct Significance {
+    /// A TSV genome file of chromosome names and their lengths
+    #[arg(short, long, required = true)]
+    genome: PathBuf,
+
+    /// The "left" BED-like TSV file, whose ranges are shuffled
+    #[arg(short, long, required = true)]
+    left: PathBuf,
+
+    /// The "right" BED-like TSV file
+    #[arg(short, long, required = true)]
+    right: PathBuf,
+
+    /// The number of shuffles of the left ranges
+    #[arg(short = 'n', long, default_value = "1000")]
+    shuffles: NonZeroUsize,
+
+    /// The statistic of the overlap to test
+    #[arg(short, long, value_enum, default_value_t = OverlapStatistic::Overlap)]
+    statistic: OverlapStatistic,
+
+    /// A BED-like file of regions to exclude (e.g. a blacklist)
+    #[arg(short, long)]
+    exclude: Option<PathBuf>,
+
+    /// Assembly gaps to exclude: a gap track (e.g. a UCSC gap table, or a BED file),
+    /// or a FASTA file (by its extension), whose runs of Ns are the gaps
+    #[arg(long)]
+    gaps: Option<PathBuf>,
+
+    /// The output format
+    #[arg(short, long, value_enum, default_value_t = StatsFormat::Text)]
+    format: StatsFormat,
+
+    /// An optional output file (standard output will be used if not specified)
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+
+    /// Skip ranges from sequences (e.g. chromosomes) not present in the genome file.
+    /// By default, ranges with sequence names not in the genome file will raise an error.
+    #[arg(long)]
+    skip_missing: bool,
+
+    /// How to handle ranges that fall outside the genome: on sequences not in the
+    /// genome file, or extending past the end of their sequence
+    #[arg(long, value_enum, default_value_t)]
+    bounds: BoundsPolicy,
+}
+
+impl Significance {
+    pub fn run(&self) -> Result<CommandOutput<()>, GRangesError> {
+        let genome = read_seqlens(&self.genome)?;
+        let read_ranges = |path: &PathBuf| {
+            let iter = BedlikeIterator::new(path)?;
+            let ranges = checked_ranges(iter, &genome, self.skip_missing, self.bounds);
+            ranges_by_sequence(ranges)
+        };
+        let left = read_ranges(&self.left)?;
+        let right = read_ranges(&self.right)?;
+        if left.is_empty() || right.is_empty() {
+            return Err(GRangesError::NoRows);
+        }
+        let records_read = [&left, &right]
+            .iter()
+            .flat_map(|ranges| ranges.values())
+            .map(|seq_ranges| seq_ranges.len())
+            .sum();
+
+        let mut excluded = match &self.exclude {
+            // regions on sequences not in the genome file can't overlap any ranges
+            Some(path) => {
+                let regions = checked_ranges(Bed3Iterator::new(path)?, &genome, true, self.bounds);
+                ranges_by_sequence(regions)?
+            }
+            None => IndexMap::new(),
+        };
+        if let Some(gaps) = &self.gaps {
+            for (seqname, seq_gaps) in read_gaps(gaps)?.iter() {
+                excluded
+                    .entry(seqname.clone())
+                    .or_default()
+                    .extend(seq_gaps);
+            }
+        }
+        let excluded = (!excluded.is_empty()).then(|| Intervals::from_ranges(excluded));
+
+        let seed = seed();
+        info!(
+            "significance: running {} shuffles with seed {}",
+            self.shuffles, seed
+        );
+        let span = info_span!("shuffles").entered();
+        let test = MonteCarloTest::new(
+            &left,
+            &Intervals::from_ranges(right),
+            &genome,
+            excluded.as_ref(),
+            self.statistic,
+            self.shuffles.get(),
+            seed,
+        );
+        drop(span);
+
+        let writer: Box<dyn Write> = match &self.output {
+            Some(path) => create_file(path)?,
+            None => Box::new(io::stdout()),
+        };
+        test.write(writer, &self.format)?;
+
+        let mut report = Report::new();
+        report.records_read = records_read;
+        test.add_to_report(&mut report);
+        Ok(CommandOutput::new((), Some(report)))
+    }
+}
+
+/// Tag each range in a BED-like file with its nearest gene in a GFF3, GTF, or BED12
+/// gene annotation. Three columns are appended to each range: the gene name, the
+/// signed distance from the gene's TSS (negative is upstream), and the genomic
+/// context of the range (promoter, exon, intron, or intergenic). Ranges on
+/// sequences without genes are tagged with two missing values (`.`, by default)
+/// and `intergenic`.
+///
+/// With `--closest-features`, each range is instead tagged with its nearest
+/// upstream and downstream genes that do not overlap it, like BEDOPS
+/// `closest-features`: four columns are appended, the upstream gene name and its
+/// (negative) distance, and the downstream gene name and its (positive) distance.
+/// Missing genes are tagged with missing values.
+///
+/// With `--k-nearest`, each range is instead tagged with its k nearest genes, in
+/// order of distance: overlapping genes first (with a distance of zero), then the
+/// nearest upstream (negative distance) or downstream (positive distance) genes. Each
+/// range is written once per gene, with the gene name and distance appended, or with
+/// `--collapse`, once, with comma-separated lists of the gene names and distances.
+/// Ranges without genes (e.g. beyond `--max-distance`) are tagged with missing values.
+/// With `--format`, each range and gene pair is instead written as a BEDPE or UCSC
+/// interact line, with the distance as its score (or value), and ranges without
+/// genes are skipped.
+#[derive(Parser)]
+pub struct Tag {
+    /// The input BED-like TSV file.
+    #[arg(required = true)]
+    bedfile: PathBuf,
+
+    /// The gene annotation file (GFF3, GTF, or BED12; possibly gzip-compressed)
+    #[arg(long, required = true)]
+    genes: PathBuf,
+
+    /// The number of basepairs upstream of each gene's TSS that are its promoter
+    #[arg(long, default_value_t = 1000)]
+    promoter: Position,
+
+    /// Tag each range with its nearest upstream and downstream genes, and their
+    /// signed distances, rather than its single nearest gene
+    #[arg(long)]
+    closest_features: bool,
+
+    /// Tag each range with its k nearest genes, and their signed distances, rather
+    /// than its single nearest gene
+    #[arg(short, long, conflicts_with = "closest_features")]
+    k_nearest: Option<NonZeroUsize>,
+
+    /// Only tag ranges with genes within this many basepairs of them (with
+    /// --k-nearest)
+    #[arg(long, requires = "k_nearest")]
+    max_distance: Option<Position>,
+
+    /// Write one row per range, with comma-separated lists of its k nearest genes and
+    /// their distances, rather than one row per range and gene (with --k-nearest)
+    #[arg(long, requires = "k_nearest")]
+    collapse: bool,
+
+    /// The output format of each pair of a range and one of its k nearest genes
+    /// (with --k-nearest)
+    #[arg(
+        long,
+        value_enum,
+        default_value_t,
+        requires = "k_nearest",
+        conflicts_with = "collapse"
+    )]
+    format: PairFormat,
+
+    /// An optional output file (standard output will be used if not specified)
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+}
+
+impl Tag {
+    pub fn run(&self) -> Result<CommandOutput<()>, GRangesError> {
+        let genes = read_gene_models(&self.genes)?;
+        info!("tag: read {} genes", genes.len());
+        let index = GeneIndex::new(genes, self.promoter);
+
+        let mut writer = build_bed_writer(self.output.as_ref())?;
+        let mut report = Report::new();
+        for result in BedlikeIterator::new(&self.bedfile)?.progress("tag") {
+            let range = result?;
+            report.records_read += 1;
+            let mut record = vec![
+                range.seqname.clone(),
+                range.start.to_string(),
+                range.end.to_string(),
+            ];
+            record.extend(range.data.iter().cloned());
+            let missing = || BED_TSV.no_value_string.clone();
+            if let Some(k) = self.k_nearest {
+                let nearest = index.nearest(
+                    &range.seqname,
+                    range.start,
+                    range.end,
+                    k.get(),
+                    self.max_distance,
+                );
+                if self.format != PairFormat::Tsv {
+                    let (name, strand) = bedlike_name_strand(&range.data);
+                    for (gene, distance) in nearest {
+                        let range_pair = RangePair {
+                            first: GenomicRangeRecordEmpty {
+                                seqname: range.seqname.clone(),
+                                start: range.start,
+                                end: range.end,
+                            },
+                            second: GenomicRangeRecordEmpty {
+                                seqname: gene.seqname.clone(),
+                                start: gene.start,
+                                end: gene.end,
+                            },
+                            names: (name.clone(), Some(gene.name.clone())),
+                            strands: (strand, gene.strand),
+                            value: Some(distance as f64),
+                        };
+                        let pair = if self.format == PairFormat::Interact {
+                            range_pair.interact_columns()
+                        } else {
+                            range_pair.bedpe_columns()
+                        };
+                        writer.write_record(&pair)?;
+                        report.records_written += 1;
+                    }
+                    continue;
+                }
+                if nearest.is_empty() {
+                    record.extend([missing(), missing()]);
+                } else if self.collapse {
+                    let (names, distances): (Vec<_>, Vec<_>) = nearest
+                        .iter()
+                        .map(|(gene, distance)| (gene.name.as_str(), distance.to_string()))
+                        .unzip();
+                    record.extend([names.join(","), distances.join(",")]);
+                } else {
+                    for (gene, distance) in nearest {
+                        let mut pair = record.clone();
+                        pair.extend([gene.name.clone(), distance.to_string()]);
+                        writer.write_record(&pair)?;
+                        report.records_written += 1;
+                    }
+                    continue;
+                }
+            } else if self.closest_features {
+                let closest = index.closest_features(&range.seqname, range.start, range.end);
+                for feature in closest
+                    .map(|closest| [closest.upstream, closest.downstream])
+                    .unwrap_or_default()
+                {
+                    match feature {
+                        Some((gene, distance)) => {
+                            record.extend([gene.name.clone(), distance.to_string()])
+                        }
+                        None => record.extend([missing(), missing()]),
+                    }
+                }
+            } else {
+                match index.annotate(&range.seqname, range.start, range.end) {
+                    Some(annotation) => record.extend([
+                        annotation.gene.name.clone(),
+                        annotation.tss_distance.to_string(),
+                        annotation.context.to_string(),
+                    ]),
+                    None => record.extend([missing(), missing(), "intergenic".to_string()]),
+                }
+            }
+            writer.write_record(&record)?;
+            report.records_written += 1;
+        }
+        writer.flush()?;
+        Ok(CommandOutput::new((), Some(report)))
+    }
+}
+
+/// Extract features of the transcripts in a GFF3, GTF, or BED12 gene annotation:
+/// exons, introns, CDS, 5' and 3' UTRs, first exons, and TSSs. Each feature is
+/// written to its own BED6+1 file, `<prefix>.<feature>.bed` (e.g.
+/// `features.introns.bed`), with the transcript name, a missing score, the strand,
+/// and the gene name. UTRs and first exons respect the transcript's strand.
+/// Non-coding transcripts have no CDS or UTRs.
+///
+/// GFF3 and GTF transcripts are built from their exon and CDS features (grouped by
+/// their GTF `transcript_id` or GFF3 `Parent`), and BED12 transcripts' coding
+/// regions are from their thickStart and thickEnd columns.
+#[derive(Parser)]
+pub struct GeneFeatures {
+    /// The gene annotation file (GFF3, GTF, or BED12; possibly gzip-compressed)
+    #[arg(short, long, required = true)]
+    annotation: PathBuf,
+
+    /// The features to extract (by default, all of them)
+    #[arg(short, long, value_enum, value_delimiter = ',')]
+    features: Vec<TranscriptFeature>,
+
+    /// The prefix of the output files' names
+    #[arg(short, long, default_value = "features")]
+    prefix: String,
+}
+
+impl GeneFeatures {
+    pub fn run(&self) -> Result<CommandOutput<()>, GRangesError> {
+        let transcripts = read_transcript_models(&self.annotation)?;
+        info!("gene-features: read {} transcripts", transcripts.len());
+        let features = if self.features.is_empty() {
+            TranscriptFeature::value_variants().to_vec()
+        } else {
+            self.features.clone()
+        };
+
+        let mut report = Report::new();
+        report.records_read = transcripts.len();
+        let mut summary = build_tsv_writer(None::<PathBuf>)?;
+        for feature in features {
+            let name = feature
+                .to_possible_value()
+                .expect("features are not skipped")
+                .get_name()
+                .to_string();
+            let path = format!("{}.{}.bed", self.prefix, name);
+            let mut writer = build_bed_writer(Some(&path))?;
+            let mut num_ranges = 0_usize;
+            for transcript in &transcripts {
+                let strand = strand_symbol(transcript.strand);
+                for (start, end) in transcript.features(feature) {
+                    writer.write_record([
+                        transcript.seqname.clone(),
+                        start.to_string(),
+                        end.to_string(),
+                        transcript.name.clone(),
+                        ".".to_string(),
+                        strand.to_string(),
+                        transcript.gene.clone(),
+                    ])?;
+                    num_ranges += 1;
+                }
+            }
+            writer.flush()?;
+            report.records_written += num_ranges;
+            summary.write_record([path, name, num_ranges.to_string()])?;
+        }
+        summary.flush()?;
+        Ok(CommandOutput::new((), Some(report)))
+    }
+}
+
+/// The gene annotation format [`ConvertAnnotation`] writes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum AnnotationFormat {
+    /// One BED12 line per transcript, with its exons as blocks and its coding
+    /// region as the thick part.
+    #[default]
+    Bed12,
+    /// GFF3 gene, mRNA (or transcript), exon, and CDS features.
+    Gff3,
+    /// featureCounts' Simplified Annotation Format: each gene's exons (merged across
+    /// its transcripts), with the gene name as the GeneID.
+    Saf,
+}
+
+/// Convert a GFF3, GTF, or BED12 gene annotation to BED12, GFF3, or SAF. GFF3 and
+/// GTF transcripts are built from their exon and CDS features, as with
+/// `gene-features`. BED12 output has one line per transcript, named by the
+/// transcript, with a score of 0; non-coding transcripts have an empty thick part.
+/// GFF3 output has a `gene` feature for each gene (with the ID `gene:<name>`,
+/// spanning its transcripts), then each of its transcripts, with their exons and
+/// phased CDS. SAF output (for featureCounts, or Rsubread in limma/edgeR pipelines)
+/// has a header line, then each gene's merged exons. BED12 transcripts are their own
+/// genes.
+#[derive(Parser)]
+pub struct ConvertAnnotation {
+    /// The gene annotation file (GFF3, GTF, or BED12; possibly gzip-compressed)
+    #[arg(required = true)]
+    annotation: PathBuf,
+
+    /// The output format
+    #[arg(short, long, value_enum, default_value_t)]
+    to: AnnotationFormat,
+
+    /// An optional output file (standard output will be used if not specified)
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+}
+
+impl ConvertAnnotation {
+    pub fn run(&self) -> Result<CommandOutput<()>, GRangesError> {
+        let transcripts = read_transcript_models(&self.annotation)?;
+        let mut report = Report::new();
+        report.records_read = transcripts.len();
+        match self.to {
+            AnnotationFormat::Bed12 => {
+                let mut writer = build_bed_writer(self.output.as_ref())?;
+                for transcript in &transcripts {
+                    writer.write_record(bed12_columns(
+                        &transcript.seqname,
+                        &transcript.name,
+                        "0",
+                        transcript.strand,
+                        transcript.coding,
+                        &transcript.exons,
+                    ))?;
+                }
+                writer.flush()?;
+            }
+            AnnotationFormat::Gff3 => {
+                let config = TsvConfig {
+                    metadata: Some(vec!["#gff-version 3".to_string()]),
+                    ..BED_TSV.clone()
+                };
+                let mut writer = build_tsv_writer_with_config(self.output.as_ref(), &config)?;
+                for (gene, transcripts) in transcripts_by_gene(&transcripts) {
+                    for record in gff3_gene_records(gene, &transcripts) {
+                        writer.write_record(record)?;
+                    }
+                }
+                writer.flush()?;
+            }
+            AnnotationFormat::Saf => {
+                let config = TsvConfig {
+                    headers: Some(SAF_HEADER.split('\t').map(String::from).collect()),
+                    ..BED_TSV.clone()
+                };
+                let mut writer = build_tsv_writer_with_config(self.output.as_ref(), &config)?;
+                for (gene, transcripts) in transcripts_by_gene(&transcripts) {
+                    for record in saf_gene_records(gene, &transcripts) {
+                        writer.write_record(record)?;
+                    }
+                }
+                writer.flush()?;
+            }
+        }
+        report.records_written = transcripts.len();
+        Ok(CommandOutput::new((), Some(report)))
+    }
+}
+
+/// Map ranges in transcript coordinates to the genome, using the exons of the
+/// transcripts in a GFF3, GTF, or BED12 gene annotation (e.g. CLIP peaks or
+/// ribosome footprints called on transcript sequences). The input's sequence names
+/// are transcript names, and positions are 0-based from the 5' end of the
+/// transcript. Each range is written as a BED12 line of its spliced genomic blocks,
+/// on the transcript's strand, named by the input's fourth column and scored by its
+/// fifth (or named by the transcript, with a score of 0, if they are missing).
+#[derive(Parser)]
+pub struct MapToGenome {
+    /// The input BED-like TSV file, in transcript coordinates
+    #[arg(required = true)]
+    bedfile: PathBuf,
+
+    /// The gene annotation file (GFF3, GTF, or BED12; possibly gzip-compressed)
+    #[arg(short, long, required = true)]
+    annotation: PathBuf,
+
+    /// An optional output file (standard output will be used if not specified)
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+
+    /// Skip ranges on transcripts missing from the annotation, or past their ends,
+    /// rather than raising an error
+    #[arg(short, long)]
+    skip_missing: bool,
+}
+
+impl MapToGenome {
+    pub fn run(&self) -> Result<CommandOutput<()>, GRangesError> {
+        let transcripts: HashMap<String, TranscriptModel> =
+            read_transcript_models(&self.annotation)?
+                .into_iter()
+                .map(|transcript| (transcript.name.clone(), transcript))
+                .collect();
+
+        let mut writer = build_bed_writer(self.output.as_ref())?;
+        let mut report = Report::new();
+        for result in BedlikeIterator::new(&self.bedfile)?.progress("map-to-genome") {
+            let range = result?;
+            report.records_read += 1;
+            let Some(transcript) = transcripts.get(&range.seqname) else {
+                if self.skip_missing {
+                    report.records_skipped += 1;
+                    continue;
+                }
+                return Err(GRangesError::missing_sequence(range.seqname));
+            };
+            let Some(blocks) = transcript.to_genome(range.start, range.end) else {
+                if self.skip_missing {
+                    report.records_skipped += 1;
+                    continue;
+                }
+                return Err(GRangesError::InvalidGenomicRangeForSequence {
+                    seqname: Some(range.seqname),
+                    start: range.start,
+                    end: range.end,
+                    length: transcript.length(),
+                });
+            };
+
+            let mut columns = range.data.as_deref().unwrap_or_default().split('\t');
+            let name = columns.next().filter(|name| !name.is_empty());
+            let score = columns.next();
+            let thick = (blocks[0].0, blocks[blocks.len() - 1].1);
+            writer.write_record(bed12_columns(
+                &transcript.seqname,
+                name.unwrap_or(&transcript.name),
+                score.unwrap_or("0"),
+                transcript.strand,
+                Some(thick),
+                &blocks,
+            ))?;
+            report.records_written += 1;
+        }
+        writer.flush()?;
+        Ok(CommandOutput::new((), Some(report)))
+    }
+}
+
+/// Map genomic ranges to transcript coordinates, using the exons of the
+/// transcripts in a GFF3, GTF, or BED12 gene annotation. Each range is written once
+/// per transcript whose exons it overlaps, as the transcript name and the range
+/// spanning its exonic parts (0-based, from the 5' end of the transcript),
+/// followed by the range's other columns. Ranges that do not overlap any exons are
+/// skipped.
+#[derive(Parser)]
+pub struct MapToTranscript {
+    /// The input BED-like TSV file, in genomic coordinates
+    #[arg(required = true)]
+    bedfile: PathBuf,
+
+    /// The gene annotation file (GFF3, GTF, or BED12; possibly gzip-compressed)
+    #[arg(short, long, required = true)]
+    annotation: PathBuf,
+
+    /// An optional output file (standard output will be used if not specified)
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+}
+
+impl MapToTranscript {
+    pub fn run(&self) -> Result<CommandOutput<()>, GRangesError> {
+        // the transcripts on each sequence, sorted by start, and their maximum width
+        let mut by_sequence: HashMap<String, (Vec<TranscriptModel>, Position)> = HashMap::new();
+        for transcript in read_transcript_models(&self.annotation)? {
+            let (transcripts, max_width) =
+                by_sequence.entry(transcript.seqname.clone()).or_default();
+            *max_width = (*max_width).max(transcript.end - transcript.start);
+            transcripts.push(transcript);
+        }
+        for (transcripts, _) in by_sequence.values_mut() {
+            transcripts.sort_by_key(|transcript| transcript.start);
+        }
+
+        let mut writer = build_bed_writer(self.output.as_ref())?;
+        let mut report = Report::new();
+        for result in BedlikeIterator::new(&self.bedfile)?.progress("map-to-transcript") {
+            let range = result?;
+            report.records_read += 1;
+            let mut num_written = 0;
+            if let Some((transcripts, max_width)) = by_sequence.get(&range.seqname) {
+                let first = transcripts
+                    .partition_point(|transcript| transcript.start + max_width <= range.start);
+                let last = transcripts.partition_point(|transcript| transcript.start < range.end);
+                for transcript in &transcripts[first..last] {
+                    let Some((start, end)) = transcript.to_transcript(range.start, range.end)
+                    else {
+                        continue;
+                    };
+                    let mut record =
+                        vec![transcript.name.clone(), start.to_string(), end.to_string()];
+                    record.extend(range.data.clone());
+                    writer.write_record(&record)?;
+                    num_written += 1;
+                }
+            }
+            if num_written == 0 {
+                report.records_skipped += 1;
+            }
+            report.records_written += num_written;
+        }
+        writer.flush()?;
+        Ok(CommandOutput::new((), Some(report)))
+    }
+}
+
+/// The BED strand column for an optional strand.
+fn strand_symbol(strand: Option<Strand>) -> &'static str {
+    match strand {
+        Some(Strand::Forward) => "+",
+        Some(Strand::Reverse) => "-",
+        None => ".",
+    }
+}
+
+/// The columns of a BED12 line of the (sorted, non-empty) `blocks`, spanning from
+/// the start of the first block to the end of the last. Lines without a thick part
+/// have their thickStart and thickEnd at the start, by the UCSC convention.
+fn bed12_columns(
+    seqname: &str,
+    name: &str,
+    score: &str,
+    strand: Option<Strand>,
+    thick: Option<(Position, Position)>,
+    blocks: &[(Position, Position)],
+) -> [String; 12] {
+    let (start, end) = (blocks[0].0, blocks[blocks.len() - 1].1);
+    let (thick_start, thick_end) = thick.unwrap_or((start, start));
+    let block_sizes: String = blocks
+        .iter()
+        .map(|(block_start, block_end)| format!("{},", block_end - block_start))
+        .collect();
+    let block_starts: String = blocks
+        .iter()
+        .map(|(block_start, _)| format!("{},", block_start - start))
+        .collect();
+    [
+        seqname.to_string(),
+        start.to_string(),
+        end.to_string(),
+        name.to_string(),
+        score.to_string(),
+        strand_symbol(strand).to_string(),
+        thick_start.to_string(),
+        thick_end.to_string(),
+        "0".to_string(),
+        blocks.len().to_string(),
+        block_sizes,
+        block_starts,
+    ]
+}
+
+/// A GFF3 record, from a 0-based, right-exclusive range.
+fn gff3_record(
+    seqname: &str,
+    feature: &str,
+    (start, end): (Position, Position),
+    strand: Option<Strand>,
+    phase: Option<Position>,
+    attributes: String,
+) -> [String; 9] {
+    [
+        seqname.to_string(),
+        "granges".to_string(),
+        feature.to_string(),
+        (start + 1).to_string(),
+        end.to_string(),
+        ".".to_string(),
+        strand_symbol(strand).to_string(),
+        phase.map_or(".".to_string(), |phase| phase.to_string()),
+        attributes,
+    ]
+}
+
+/// Group transcripts by their gene, in the order genes first appear.
+fn transcripts_by_gene(transcripts: &[TranscriptModel]) -> IndexMap<&str, Vec<&TranscriptModel>> {
+    let mut genes: IndexMap<&str, Vec<&TranscriptModel>> = IndexMap::new();
+    for transcript in transcripts {
+        genes.entry(&transcript.gene).or_default().push(transcript);
+    }
+    genes
+}
+
+/// The strand of a gene's (non-empty) transcripts, if they all have the same one.
+fn gene_strand(transcripts: &[&TranscriptModel]) -> Option<Strand> {
+    let strand = transcripts[0].strand;
+    transcripts
+        .iter()
+        .all(|transcript| transcript.strand == strand)
+        .then_some(strand)
+        .flatten()
+}
+
+/// The GFF3 records of a gene's transcripts: a `gene` feature spanning them
+/// (with the ID `gene:<name>`), then an `mRNA` (or, if non-coding, a `transcript`)
+/// feature for each transcript, followed by its `exon` and `CDS` features.
+fn gff3_gene_records(gene: &str, transcripts: &[&TranscriptModel]) -> Vec<[String; 9]> {
+    let first = transcripts[0];
+    let start = transcripts.iter().map(|transcript| transcript.start).min();
+    let end = transcripts.iter().map(|transcript| transcript.end).max();
+    let strand = gene_strand(transcripts);
+    let gene_id = format!("gene:{}", gene);
+    let mut records = vec![gff3_record(
+        &first.seqname,
+        "gene",
+        (start.unwrap_or(first.start), end.unwrap_or(first.end)),
+        strand,
+        None,
+        format!("ID={};Name={}", gene_id, gene),
+    )];
+    for transcript in transcripts {
+        let (seqname, name, strand) = (&transcript.seqname, &transcript.name, transcript.strand);
+        let feature = if transcript.coding.is_some() {
+            "mRNA"
+        } else {
+            "transcript"
+        };
+        records.push(gff3_record(
+            seqname,
+            feature,
+            (transcript.start, transcript.end),
+            strand,
+            None,
+            format!("ID={};Parent={};Name={}", name, gene_id, name),
+        ));
+        for &exon in &transcript.exons {
+            let attributes = format!("Parent={}", name);
+            records.push(gff3_record(seqname, "exon", exon, strand, None, attributes));
+        }
+        for (cds, phase) in transcript.cds_with_phases() {
+            let attributes = format!("ID=cds:{};Parent={}", name, name);
+            let record = gff3_record(seqname, "CDS", cds, strand, Some(phase), attributes);
+            records.push(record);
+        }
+    }
+    records
+}
+
+/// The SAF records of a gene's exons, merged across its transcripts (separately on
+/// each sequence its transcripts are on).
+fn saf_gene_records(gene: &str, transcripts: &[&TranscriptModel]) -> Vec<[String; 5]> {
+    let strand = strand_symbol(gene_strand(transcripts));
+    let mut exons: IndexMap<&str, Vec<(Position, Position)>> = IndexMap::new();
+    for transcript in transcripts {
+        let seqname_exons = exons.entry(&transcript.seqname).or_default();
+        seqname_exons.extend_from_slice(&transcript.exons);
+    }
+    exons
+        .into_iter()
+        .flat_map(|(seqname, seqname_exons)| {
+            merge_intervals(seqname_exons)
+                .into_iter()
+                .map(move |(start, end)| {
+                    [
+                        gene.to_string(),
+                        seqname.to_string(),
+                        (start + 1).to_string(),
+                        end.to_string(),
+                        strand.to_string(),
+                    ]
+                })
+        })
+        .collect()
+}
+
+/// The name (fourth column) and strand (sixth column) of a BED-like record, from its
+/// data columns, if present.
+fn bedlike_name_strand(data: &Option<String>) -> (Option<String>, Option<Strand>) {
+    let mut columns = data.as_deref().unwrap_or_default().split('\t');
+    let name = columns
+        .next()
+        .filter(|name| !name.is_empty() && !is_na_value(name))
+        .map(String::from);
+    let strand = columns.nth(1).and_then(parse_strand);
+    (name, strand)
+}
+
+/// A [`RangePair`] of two BED-like records, with their names and strands from their
+/// data columns.
+fn bedlike_range_pair(
+    left: GenomicRangeRecordEmpty,
+    left_data: &Option<String>,
+    right: GenomicRangeRecordEmpty,
+    right_data: &Option<String>,
+) -> RangePair {
+    let (left_name, left_strand) = bedlike_name_strand(left_data);
+    let (right_name, right_strand) = bedlike_name_strand(right_data);
+    RangePair {
+        names: (left_name, right_name),
+        strands: (left_strand, right_strand),
+        ..RangePair::new(left, right)
+    }
+}
+
+/// Join each left range with the right ranges within a window of it, like
+/// `bedtools window`: each left range is padded by `--window` basepairs on both
+/// sides (or by `--left-window` before it and `--right-window` after it) while
+/// finding overlaps, without adjusting the ranges themselves. Each pair is written
+/// as the left record's columns followed by the right record's columns, or with
+/// `--format`, as a BEDPE or UCSC interact line (with the ranges' names and strands
+/// from their fourth and sixth columns), e.g. to view the pairs as arcs in a genome
+/// browser.
+///
+/// With `--strand-aware`, the left and right windows are upstream and downstream of
+/// each left range, according to its strand (the sixth BED column).
+#[derive(Parser)]
+pub struct Window {
+    /// A TSV genome file of chromosome names and their lengths
+    #[arg(short, long, required = true)]
+    genome: PathBuf,
+
+    /// The "left" BED-like TSV file
+    #[arg(short = 'a', long, required = true)]
+    left: PathBuf,
+
+    /// The "right" BED-like TSV file
+    #[arg(short = 'b', long, required = true)]
+    right: PathBuf,
+
+    /// The number of basepairs to pad each left range by on both sides
+    #[arg(short, long, default_value_t = 1000)]
+    window: Position,
+
+    /// The number of basepairs to pad each left range by before its start
+    /// (overriding --window)
+    #[arg(short, long)]
+    left_window: Option<Position>,
+
+    /// The number of basepairs to pad each left range by after its end
+    /// (overriding --window)
+    #[arg(short, long)]
+    right_window: Option<Position>,
+
+    /// Treat the left and right windows as upstream and downstream of each left
+    /// range, according to its strand
+    #[arg(long)]
+    strand_aware: bool,
+
+    /// Write each left record once if any right range is within its window, rather
+    /// than once per right range
+    #[arg(short, long)]
+    unique: bool,
+
+    /// The output format of each pair of a left range and a right range
+    #[arg(long, value_enum, default_value_t, conflicts_with = "unique")]
+    format: PairFormat,
+
+    /// An optional output file (standard output will be used if not specified)
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+
+    /// Skip ranges from sequences (e.g. chromosomes) not present in the genome file.
+    /// By default, ranges with sequence names not in the genome file will raise an error.
+    #[arg(short, long)]
+    skip_missing: bool,
+
+    /// How to handle ranges that fall outside the genome
+    #[arg(long, value_enum, default_value_t)]
+    bounds: BoundsPolicy,
+}
+
+impl Window {
+    pub fn run(&self) -> Result<CommandOutput<()>, GRangesError> {
+        let genome = read_seqlens(&self.genome)?;
+        let filter = JoinFilter::new().window(
+            self.left_window.unwrap_or(self.window),
+            self.right_window.unwrap_or(self.window),
+        );
+
+        let parse = info_span!("parse").entered();
+        let right_iter = BedlikeIterator::new(&self.right)?;
+        let right_ranges = checked_ranges(right_iter, &genome, self.skip_missing, self.bounds);
+        let right_gr = GRanges::from_iter(right_ranges, &genome)?.into_coitrees()?;
+        drop(parse);
+
+        let _span = info_span!("window join").entered();
+        let mut writer = build_bed_writer(self.output.as_ref())?;
+        let mut report = Report::new();
+        let left_iter = BedlikeIterator::new(&self.left)?;
+        let left_ranges = checked_ranges(left_iter, &genome, self.skip_missing, self.bounds);
+        for result in left_ranges.progress("window") {
+            let left_range = result?;
+            report.records_read += 1;
+
+            let strand = if self.strand_aware {
+                left_range
+                    .data
+                    .as_deref()
+                    .and_then(|data| data.split('\t').nth(2))
+                    .and_then(parse_strand)
+            } else {
+                None
+            };
+            let filter = filter.for_strand(strand);
+            let (start, end) = filter.query_range(left_range.start, left_range.end);
+
+            let mut right_hits = Vec::new();
+            if let Some(view) = right_gr.on(&left_range.seqname) {
+                view.ranges().query_overlaps(start, end, |right_range| {
+                    if filter.passes(&left_range, right_range) {
+                        right_hits.push((
+                            right_range.start(),
+                            right_range.end(),
+                            right_range.index(),
+                        ));
+                    }
+                });
+            }
+            if right_hits.is_empty() {
+                continue;
+            }
+
+            let mut left_record = vec![
+                left_range.seqname.clone(),
+                left_range.start.to_string(),
+                left_range.end.to_string(),
+            ];
+            left_record.extend(left_range.data.iter().cloned());
+            if self.unique {
+                writer.write_record(&left_record)?;
+                report.records_written += 1;
+                continue;
+            }
+
+            // the interval tree visits the right ranges in no particular order
+            right_hits.sort_unstable();
+            for (right_start, right_end, index) in right_hits {
+                let index = index.expect("Internal error: please report");
+                let right_data = right_gr.get_data_value(index);
+                let range_pair = || {
+                    let left = GenomicRangeRecordEmpty {
+                        seqname: left_range.seqname.clone(),
+                        start: left_range.start,
+                        end: left_range.end,
+                    };
+                    let right = GenomicRangeRecordEmpty {
+                        seqname: left_range.seqname.clone(),
+                        start: right_start,
+                        end: right_end,
+                    };
+                    bedlike_range_pair(left, &left_range.data, right, right_data)
+                };
+                let record = match self.format {
+                    PairFormat::Tsv => {
+                        let mut record = left_record.clone();
+                        record.extend([
+                            left_range.seqname.clone(),
+                            right_start.to_string(),
+                            right_end.to_string(),
+                        ]);
+                        record.extend(right_data.clone());
+                        record
+                    }
+                    PairFormat::Bedpe => range_pair().bedpe_columns(),
+                    PairFormat::Interact => range_pair().interact_columns(),
+                };
+                writer.write_record(&record)?;
+                report.records_written += 1;
+            }
+        }
+        writer.flush()?;
+        Ok(CommandOutput::new((), Some(report)))
+    }
+}
+
+/// The columns of a BED-like record, with its data columns (if any) as one field.
+fn bedlike_columns(range: GenomicRangeRecord<Option<String>>) -> Vec<String> {
+    let mut columns = vec![
+        range.seqname,
+        range.start.to_string(),
+        range.end.to_string(),
+    ];
+    columns.extend(range.data);
+    columns
+}
+
+/// Join two BED-like files by the names of their ranges (their fourth column),
+/// rather than by their coordinates, e.g. to attach the metadata of transcripts to
+/// ranges labeled with transcript IDs. Each left record is written with the columns
+/// of each right record with the same name, like `join(1)`, but neither file needs
+/// to be sorted: the right records are loaded into a hash table (see [`KeyJoin`]),
+/// and the left records are streamed. Records without names are never joined.
+#[derive(Parser)]
+pub struct JoinByName {
+    /// The "left" BED-like TSV file
+    #[arg(short = 'a', long, required = true)]
+    left: PathBuf,
+
+    /// The "right" BED-like TSV file
+    #[arg(short = 'b', long, required = true)]
+    right: PathBuf,
+
+    /// How to handle right records with the same name: join each left record with
+    /// all of them, with only the first, or raise an error
+    #[arg(long, value_enum, default_value_t)]
+    duplicates: DuplicateKeyPolicy,
+
+    /// Also write the left records with no right record with the same name (a left
+    /// outer join), with missing values for the right columns
+    #[arg(long)]
+    keep_unmatched: bool,
+
+    /// An optional output file (standard output will be used if not specified)
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+}
+
+impl JoinByName {
+    pub fn run(&self) -> Result<CommandOutput<()>, GRangesError> {
+        let parse = info_span!("parse right").entered();
+        let mut right = Vec::new();
+        for result in BedlikeIterator::new(&self.right)? {
+            let range = result?;
+            if let (Some(name), _) = bedlike_name_strand(&range.data) {
+                right.push((name, bedlike_columns(range)));
+            }
+        }
+        // the missing values written for unmatched left records, one per right column
+        let missing: Vec<String> = right.first().map_or(Vec::new(), |(_, columns)| {
+            columns
+                .iter()
+                .map(|column| vec![na_value(); column.split('\t').count()].join("\t"))
+                .collect()
+        });
+        let join = KeyJoin::new(right, self.duplicates)?;
+        drop(parse);
+
+        let _span = info_span!("name join").entered();
+        let mut writer = build_bed_writer(self.output.as_ref())?;
+        let mut report = Report::new();
+        for result in BedlikeIterator::new(&self.left)?.progress("join-by-name") {
+            let left_range = result?;
+            report.records_read += 1;
+            let (name, _) = bedlike_name_strand(&left_range.data);
+            let matches = name.as_deref().map_or(&[][..], |name| join.get(name));
+            let left_record = bedlike_columns(left_range);
+            if matches.is_empty() {
+                if self.keep_unmatched {
+                    let mut record = left_record;
+                    record.extend(missing.iter().cloned());
+                    writer.write_record(&record)?;
+                    report.records_written += 1;
+                }
+                continue;
+            }
+            for right_record in matches {
+                let mut record = left_record.clone();
+                record.extend(right_record.iter().cloned());
+                writer.write_record(&record)?;
+                report.records_written += 1;
+            }
+        }
+        writer.flush()?;
+        Ok(CommandOutput::new((), Some(report)))
+    }
+}
+
+/// Expand ranges into per-basepair records, for tools that need positionwise input.
+/// By default, each range is written as one line per basepair: its sequence name,
+/// the position, and the range's remaining columns (e.g. the value of a bedGraph).
+/// With `--depth`, the input is instead written as the depth (the number of ranges
+/// overlapping each position) at every position of the genome, like
+/// `bedtools genomecov -d`; this requires sorted input.
+///
+/// Positions are 1-based (as with `bedtools genomecov -d` and `samtools depth`)
+/// unless `--zero-based` is set. Both modes stream their input, so memory use
+/// does not grow with the size of the genome.
+#[derive(Parser)]
+pub struct Expand {
+    /// A TSV genome file of chromosome names and their lengths
+    #[arg(short, long, required = true)]
+    genome: PathBuf,
+
+    /// The input BED-like TSV file.
+    #[arg(required = true)]
+    bedfile: PathBuf,
+
+    /// Write the depth at every position of the genome, rather than each range's
+    /// positions
+    #[arg(long)]
+    depth: bool,
+
+    /// Write 0-based positions, rather than 1-based positions
+    #[arg(long)]
+    zero_based: bool,
+
+    /// An optional output file (standard output will be used if not specified)
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+
+    /// Skip ranges from sequences (e.g. chromosomes) not present in the genome file.
+    /// By default, ranges with sequence names not in the genome file will raise an error.
+    #[arg(short, long)]
+    skip_missing: bool,
+
+    /// How to handle ranges that fall outside the genome
+    #[arg(long, value_enum, default_value_t)]
+    bounds: BoundsPolicy,
+}
+
+impl Expand {
+    pub fn run(&self) -> Result<CommandOutput<()>, GRangesError> {
+        let genome = read_seqlens(&self.genome)?;
+        let offset = if self.zero_based { 0 } else { 1 };
+        let iter = BedlikeIterator::new(&self.bedfile)?;
+        let ranges = checked_ranges(iter, &genome, self.skip_missing, self.bounds);
+
+        let _span = info_span!("expand").entered();
+        let mut writer = build_bed_writer(self.output.as_ref())?;
+        let mut report = Report::new();
+        if self.depth {
+            let mut ranges = ranges.progress("expand");
+            for result in StreamingDepth::new(ranges.by_ref(), &genome)? {
+                let run = result?;
+                let depth = run.data.to_string();
+                for position in run.start..run.end {
+                    let position = (position + offset).to_string();
+                    writer.write_record([
+                        run.seqname.as_str(),
+                        position.as_str(),
+                        depth.as_str(),
+                    ])?;
+                }
+                report.records_written += (run.end - run.start) as usize;
+            }
+            report.records_read = ranges.num_items();
+        } else {
+            for result in ranges.progress("expand") {
+                let range = result?;
+                report.records_read += 1;
+                for position in range.start..range.end {
+                    let position = (position + offset).to_string();
+                    let mut record = vec![range.seqname.as_str(), position.as_str()];
+                    record.extend(range.data.as_deref());
+                    writer.write_record(&record)?;
+                }
+                report.records_written += (range.end - range.start) as usize;
+            }
+        }
+        writer.flush()?;
+        Ok(CommandOutput::new((), Some(report)))
+    }
+}
+
+/// An arithmetic operation between the values of two tracks, for `granges math`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum TrackOperation {
+    /// The first value plus the second.
+    #[default]
+    Add,
+    /// The first value minus the second.
+    Subtract,
+    /// The first value times the second.
+    Multiply,
+    /// The first value divided by the second.
+    Divide,
+    /// The log2 of the first value over the second, e.g. ChIP over input.
+    Log2Ratio,
+    /// The smaller of the two values.
+    Min,
+    /// The larger of the two values.
+    Max,
+    /// The mean of the two values.
+    Mean,
+}
+
+impl TrackOperation {
+    /// Apply this operation to values `x` and `y`, adding `pseudocount` to both
+    /// first for [`TrackOperation::Log2Ratio`].
+    pub fn apply(&self, x: f64, y: f64, pseudocount: f64) -> f64 {
+        match self {
+            TrackOperation::Add => x + y,
+            TrackOperation::Subtract => x - y,
+            TrackOperation::Multiply => x * y,
+            TrackOperation::Divide => x / y,
+            TrackOperation::Log2Ratio => ((x + pseudocount) / (y + pseudocount)).log2(),
+            TrackOperation::Min => x.min(y),
+            TrackOperation::Max => x.max(y),
+            TrackOperation::Mean => (x + y) / 2.0,
+        }
+    }
+}
+
+/// Read a score track (e.g. a bedGraph) into a run-length encoded vector of its
+/// values (see [`RleList::from_ranges()`]), and the number of ranges read.
+fn read_score_track(
+    path: &Path,
+    genome: &IndexMap<String, Position>,
+    skip_missing: bool,
+    bounds: BoundsPolicy,
+    label: &'static str,
+) -> Result<(RleList<Option<f64>>, usize), GRangesError> {
+    let iter = BedlikeIterator::new(path)?;
+    let mut ranges = checked_ranges(iter, genome, skip_missing, bounds).progress(label);
+    let values = ranges.by_ref().map(|result| -> Result<_, GRangesError> {
+        let range = result?;
+        let value = range
+            .data
+            .as_deref()
+            .and_then(|data| data.split('\t').next());
+        let value = match value {
+            Some(value) if !is_na_value(value) => Some(value.parse::<f64>()?),
+            _ => None,
+        };
+        Ok(GenomicRangeRecord {
+            seqname: range.seqname,
+            start: range.start,
+            end: range.end,
+            data: value,
+        })
+    });
+    // ranges with missing values are the same as uncovered regions
+    let track = RleList::from_ranges(values, genome)?.map(|value| value.flatten())?;
+    Ok((track, ranges.num_items()))
+}
+
+/// Do arithmetic between two score tracks (bedGraph files, or any BED-like file
+/// with the value in the fourth column), e.g. the log2 ratio of ChIP signal over
+/// input. The output is a bedGraph, re-segmented at the breakpoints of both
+/// tracks, with adjacent ranges with equal values joined.
+///
+/// By default, regions missing from either track (not covered by a range, or
+/// with a missing value) are not output. With `--missing-value`, regions missing
+/// from one track take this value instead (e.g. 0 for coverage). Regions missing
+/// from both tracks are never output.
+#[derive(Parser)]
+pub struct Math {
+    /// A TSV genome file of chromosome names and their lengths
+    #[arg(short, long, required = true)]
+    genome: PathBuf,
+
+    /// The first track
+    #[arg(required = true)]
+    left: PathBuf,
+
+    /// The second track
+    #[arg(required = true)]
+    right: PathBuf,
+
+    /// The operation to apply to the two tracks' values
+    #[arg(short, long, value_enum, default_value_t)]
+    func: TrackOperation,
+
+    /// The value of regions missing from one track
+    #[arg(short, long)]
+    missing_value: Option<f64>,
+
+    /// A pseudocount added to both values of the log2 ratio
+    #[arg(short, long, default_value_t = 0.0)]
+    pseudocount: f64,
+
+    /// The number of significant digits of results (like bedtools' -prec)
+    #[arg(long, default_value_t = BEDTOOLS_PRECISION)]
+    precision: usize,
+
+    /// An optional output file (standard output will be used if not specified)
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+
+    /// Skip ranges from sequences (e.g. chromosomes) not present in the genome file.
+    /// By default, ranges with sequence names not in the genome file will raise an error.
+    #[arg(short, long)]
+    skip_missing: bool,
+
+    /// How to handle ranges that fall outside the genome
+    #[arg(long, value_enum, default_value_t)]
+    bounds: BoundsPolicy,
+}
+
+impl Math {
+    pub fn run(&self) -> Result<CommandOutput<()>, GRangesError> {
+        let genome = read_seqlens(&self.genome)?;
+        let mut report = Report::new();
+        let parse = info_span!("parse").entered();
+        let (left, left_read) =
+            read_score_track(&self.left, &genome, self.skip_missing, self.bounds, "math")?;
+        let (right, right_read) =
+            read_score_track(&self.right, &genome, self.skip_missing, self.bounds, "math")?;
+        report.records_read = left_read + right_read;
+        drop(parse);
+
+        let _span = info_span!("math").entered();
+        let result = left.zip_with(&right, |x, y| {
+            let (x, y) = match (x, y, self.missing_value) {
+                (Some(x), Some(y), _) => (*x, *y),
+                (Some(x), None, Some(missing)) => (*x, missing),
+                (None, Some(y), Some(missing)) => (missing, *y),
+                _ => return None,
+            };
+            Some(self.func.apply(x, y, self.pseudocount))
+        })?;
+
+        let config = precision_tsv_config(self.precision);
+        let mut writer = build_bed_writer(self.output.as_ref())?;
+        for (seqname, rle) in result.iter() {
+            for (start, end, value) in rle.runs() {
+                if let Some(value) = value {
+                    writer.write_record([
+                        seqname.clone(),
+                        start.to_string(),
+                        end.to_string(),
+                        config.format_float(*value),
+                    ])?;
+                    report.records_written += 1;
+                }
+            }
+        }
+        writer.flush()?;
+        Ok(CommandOutput::new((), Some(report)))
+    }
+}
+
+/// Concatenate several BED-like files into one. Records with fewer columns than
+/// others are padded with NA values, so that all records have the same number of
+/// columns. Optionally, each record can be tagged with its source file (as a last
+/// column), the records sorted (by sequence in natural order, then start and end
+/// position), and exact duplicate records removed (keeping the first, and ignoring
+/// the source file column), or with `--dedup-by ranges`, records with duplicate
+/// ranges. The same can be done for a single file, e.g. `granges cat --sort
+/// --dedup in.bed` sorts and de-duplicates `in.bed`.
+#[derive(Parser)]
+pub struct Cat {
+    /// The input BED-like TSV files.
+    #[arg(required = true)]
+    bedfiles: Vec<PathBuf>,
+
+    /// Add a last column with the path of each record's source file
+    #[arg(short, long)]
+    tag: bool,
+
+    /// Sort the records by sequence, start, and end position
+    #[arg(short, long)]
+    sort: bool,
+
+    /// Remove exact duplicate records, keeping the first
+    #[arg(long)]
+    dedup: bool,
+
+    /// Whether records with --dedup are duplicates if their ranges and data columns
+    /// are equal, or just their ranges
+    #[arg(long, value_enum, default_value_t, requires = "dedup")]
+    dedup_by: DuplicateKey,
+
+    /// An optional output file (standard output will be used if not specified)
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+}
+
+impl Cat {
+    pub fn run(&self) -> Result<CommandOutput<()>, GRangesError> {
+        let mut report = Report::new();
+        // each record's range, data columns, and source file index
+        let mut records: Vec<(GenomicRangeRecord<Vec<String>>, usize)> = Vec::new();
+        let mut num_columns = 0;
+        for (source, bedfile) in self.bedfiles.iter().enumerate() {
+            for result in BedlikeIterator::new(bedfile)?.progress("cat") {
+                let range = result?;
+                let columns: Vec<String> = range
+                    .data
+                    .as_deref()
+                    .map(|data| data.split('\t').map(String::from).collect())
+                    .unwrap_or_default();
+                num_columns = num_columns.max(columns.len());
+                records.push((
+                    GenomicRangeRecord {
+                        seqname: range.seqname,
+                        start: range.start,
+                        end: range.end,
+                        data: columns,
+                    },
+                    source,
+                ));
+            }
+        }
+        report.records_read = records.len();
+
+        for (record, _) in records.iter_mut() {
+            record.data.resize(num_columns, na_value().to_string());
+        }
+        if self.dedup {
+            let mut seen = HashSet::new();
+            records.retain(|(record, _)| {
+                let data = match self.dedup_by {
+                    DuplicateKey::RangesAndData => record.data.clone(),
+                    DuplicateKey::Ranges => Vec::new(),
+                };
+                seen.insert((record.seqname.clone(), record.start, record.end, data))
+            });
+        }
+        if self.sort {
+            let mut order = GenomeMap::new();
+            for (record, _) in &records {
+                if order.get_index_by_name(&record.seqname).is_none() {
+                    order.insert(&record.seqname, ())?;
+                }
+            }
+            records.sort_by_key(|(record, _)| {
+                (
+                    order.get_index_by_name(&record.seqname),
+                    record.start,
+                    record.end,
+                )
+            });
+        }
+
+        let mut writer = build_bed_writer(self.output.as_ref())?;
+        for (record, source) in records {
+            let mut columns = vec![
+                record.seqname,
+                record.start.to_string(),
+                record.end.to_string(),
+            ];
+            columns.extend(record.data);
+            if self.tag {
+                columns.push(self.bedfiles[source].display().to_string());
+            }
+            writer.write_record(&columns)?;
+            report.records_written += 1;
+        }
+        writer.flush()?;
+        report.records_skipped = report.records_read - report.records_written;
+        Ok(CommandOutput::new((), Some(report)))
+    }
+}
+
+/// Compare two BED-like files, like a range-aware `diff`, e.g. to validate the
+/// output of a changed pipeline. Records are matched by their ranges: records with
+/// the same range and data columns in both files are identical (with float columns
+/// equal within `--tolerance`, if set), and records with the same range but
+/// different data columns are changed.
+///
+/// Each differing record is written with a status column: `<` for records only in
+/// the left file, `>` for records only in the right file, and `~<` and `~>` for the
+/// left and right versions of a changed record. Records are written sorted by range
+/// (with sequence names sorted lexically). The counts of each are summarized on
+/// standard error, or with `--summary`, instead of the records.
+#[derive(Parser)]
+pub struct Compare {
+    /// The "left" BED-like TSV file
+    #[arg(required = true)]
+    left: PathBuf,
+
+    /// The "right" BED-like TSV file
+    #[arg(required = true)]
+    right: PathBuf,
+
+    /// Only write the summary counts (to the output), not the differing records
+    #[arg(long)]
+    summary: bool,
+
+    /// The absolute tolerance of float data columns, e.g. to ignore differences in
+    /// float precision
+    #[arg(long, default_value_t = 0.0)]
+    tolerance: f64,
+
+    /// The tolerance of one column, as `<column>=<tolerance>` with the (1-based) BED
+    /// column number, e.g. `5=0.01` for the score column. This overrides
+    /// --tolerance for that column, and can be repeated.
+    #[arg(long, value_parser = parse_column_tolerance)]
+    column_tolerance: Vec<(usize, f64)>,
+
+    /// An optional output file (standard output will be used if not specified)
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+}
+
+/// Parse a `--column-tolerance` command line argument, `<column>=<tolerance>`, into
+/// the zero-based data column index and the tolerance.
+fn parse_column_tolerance(arg: &str) -> Result<(usize, f64), String> {
+    let invalid = || format!("invalid column tolerance '{}': expected e.g. '5=0.01'", arg);
+    let (column, tolerance) = arg.split_once('=').ok_or_else(invalid)?;
+    let column: usize = column.parse().map_err(|_| invalid())?;
+    let tolerance: f64 = tolerance.parse().map_err(|_| invalid())?;
+    if column < 4 {
+        return Err(format!(
+            "invalid column tolerance '{}': only data columns (4 and up) have tolerances",
+            arg
+        ));
+    }
+    Ok((column - 4, tolerance))
+}
+
+/// The counts of records found by [`Compare`].
+#[derive(Debug, Default, PartialEq)]
+struct ComparisonCounts {
+    left: usize,
+    right: usize,
+    identical: usize,
+    left_only: usize,
+    right_only: usize,
+    changed: usize,
+}
+
+/// A range (sequence name, start, and end), for matching records in [`Compare`].
+type RangeKey = (String, Position, Position);
+
+/// A record's range and data columns, for [`Compare`].
+type KeyedRecord = (RangeKey, Option<String>);
+
+/// A differing record found by [`Compare`], with its status.
+type RecordDifference = (&'static str, RangeKey, Option<String>);
+
+/// Compare the left and right records, returning the differing records (each with
+/// its status), sorted by range, and the counts of each kind of record. Data columns
+/// are compared within `tolerance`.
+fn compare_records(
+    left: Vec<KeyedRecord>,
+    right: impl Iterator<Item = Result<KeyedRecord, GRangesError>>,
+    tolerance: &Tolerance,
+) -> Result<(Vec<RecordDifference>, ComparisonCounts), GRangesError> {
+    let mut counts = ComparisonCounts {
+        left: left.len(),
+        ..Default::default()
+    };
+    // the indices of the left records with each range not yet matched
+    let mut unmatched: HashMap<&RangeKey, Vec<usize>> = HashMap::new();
+    for (index, (key, _)) in left.iter().enumerate() {
+        unmatched.entry(key).or_default().push(index);
+    }
+
+    let mut right_only = Vec::new();
+    for result in right {
+        let (key, data) = result?;
+        counts.right += 1;
+        let identical = unmatched.get_mut(&key).and_then(|indices| {
+            let position = indices
+                .iter()
+                .position(|&index| tolerance.bedlike_data_eq(&left[index].1, &data))?;
+            Some(indices.remove(position))
+        });
+        match identical {
+            Some(_) => counts.identical += 1,
+            None => right_only.push((key, data)),
+        }
+    }
+
+    let mut differences = Vec::new();
+    for (key, data) in right_only {
+        let left_index = unmatched
+            .get_mut(&key)
+            .and_then(|indices| (!indices.is_empty()).then(|| indices.remove(0)));
+        match left_index {
+            Some(index) => {
+                counts.changed += 1;
+                differences.push(("~<", left[index].0.clone(), left[index].1.clone()));
+                differences.push(("~>", key, data));
+            }
+            None => {
+                counts.right_only += 1;
+                differences.push((">", key, data));
+            }
+        }
+    }
+    let mut left_only: Vec<usize> = unmatched.into_values().flatten().collect();
+    left_only.sort_unstable();
+    for index in left_only {
+        counts.left_only += 1;
+        differences.push(("<", left[index].0.clone(), left[index].1.clone()));
+    }
+    // a stable sort, so each changed record's left version stays before its right version
+    differences.sort_by(|a, b| a.1.cmp(&b.1));
+    Ok((differences, counts))
+}
+
+/// Write a summary of the counts of records found by [`Compare`].
+fn write_comparison_summary(
+    mut writer: impl Write,
+    counts: &ComparisonCounts,
+) -> Result<(), GRangesError> {
+    writeln!(writer, "left records:      {}", counts.left)?;
+    writeln!(writer, "right records:     {}", counts.right)?;
+    writeln!(writer, "identical:         {}", counts.identical)?;
+    writeln!(writer, "only in left:      {}", counts.left_only)?;
+    writeln!(writer, "only in right:     {}", counts.right_only)?;
+    writeln!(writer, "changed:           {}", counts.changed)?;
+    Ok(())
+}
+
+impl Compare {
+    pub fn run(&self) -> Result<CommandOutput<()>, GRangesError> {
+        let records = |path: &PathBuf| -> Result<_, GRangesError> {
+            Ok(BedlikeIterator::new(path)?.map(|result| {
+                result.map(|range| ((range.seqname, range.start, range.end), range.data))
+            }))
+        };
+        let left = records(&self.left)?
+            .progress("compare")
+            .collect::<Result<Vec<_>, _>>()?;
+        let right = records(&self.right)?.progress("compare");
+        let tolerance = self.column_tolerance.iter().fold(
+            Tolerance::new(self.tolerance),
+            |tolerance, &(column, value)| tolerance.column(column, value),
+        );
+        let (differences, counts) = compare_records(left, right, &tolerance)?;
+
+        let mut report = Report::new();
+        report.records_read = counts.left + counts.right;
+        if self.summary {
+            let summary: Box<dyn Write> = match &self.output {
+                Some(path) => create_file(path)?,
+                None => Box::new(io::stdout()),
+            };
+            write_comparison_summary(summary, &counts)?;
+            return Ok(CommandOutput::new((), Some(report)));
+        }
+
+        let mut writer = build_bed_writer(self.output.as_ref())?;
+        for (status, (seqname, start, end), data) in differences {
+            let mut record = vec![
+                status.to_string(),
+                seqname,
+                start.to_string(),
+                end.to_string(),
+            ];
+            record.extend(data);
+            writer.write_record(&record)?;
+            report.records_written += 1;
+        }
+        writer.flush()?;
+        write_comparison_summary(io::stderr(), &counts)?;
+        Ok(CommandOutput::new((), Some(report)))
+    }
+}
+
+/// Report the pairs of a BEDPE file (e.g. Hi-C contacts or structural variant
+/// breakpoints) by how their ends overlap the ranges of a BED-like file, like
+/// `bedtools pairtobed`. Each reported pair is written with each range it overlaps
+/// (the pair's BEDPE columns, then the range's columns), or alone with the types
+/// that report pairs without overlaps (e.g. `--type neither`). Strands are ignored.
+#[derive(Parser)]
+pub struct PairToBed {
+    /// A TSV genome file of chromosome names and their lengths
+    #[arg(short, long, required = true)]
+    genome: PathBuf,
+
+    /// The BEDPE file of pairs
+    #[arg(short, long, required = true)]
+    pairs: PathBuf,
+
+    /// The BED-like file of ranges to overlap the pairs with
+    #[arg(short, long, required = true)]
+    right: PathBuf,
+
+    /// Which pairs to report, by how their ends (or spans) overlap the ranges
+    #[arg(short = 't', long = "type", value_enum, default_value_t)]
+    overlap_type: PairToBedType,
+
+    /// An optional output file (standard output will be used if not specified)
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+}
+
+impl PairToBed {
+    pub fn run(&self) -> Result<CommandOutput<()>, GRangesError> {
+        let genome = read_seqlens(&self.genome)?;
+        let parse = info_span!("parse right").entered();
+        // the ranges' lines are their data, to write with the pairs
+        let right = BedlikeIterator::new(&self.right)?.map(|result| {
+            result.map(|range| {
+                let mut line = format!("{}\t{}\t{}", range.seqname, range.start, range.end);
+                if let Some(data) = &range.data {
+                    line.push('\t');
+                    line.push_str(data);
+                }
+                GenomicRangeRecord {
+                    seqname: range.seqname,
+                    start: range.start,
+                    end: range.end,
+                    data: line,
+                }
+            })
+        });
+        let right_gr = GRanges::from_iter(right, &genome)?.into_coitrees()?;
+        drop(parse);
+        let lines: &[String] = right_gr.data().map(Vec::as_slice).unwrap_or_default();
+
+        let mut report = Report::new();
+        let mut writer = build_bed_writer(self.output.as_ref())?;
+        for result in BedpeIterator::new(&self.pairs)?.progress("pairtobed") {
+            let pair = result?;
+            report.records_read += 1;
+            let Some(overlaps) = self.overlap_type.overlapping_ranges(&pair, &right_gr) else {
+                continue;
+            };
+            let pair = pair.to_string();
+            if overlaps.is_empty() {
+                writer.write_record([&pair])?;
+                report.records_written += 1;
+            }
+            for index in overlaps {
+                writer.write_record([&pair, &lines[index]])?;
+                report.records_written += 1;
+            }
+        }
+        writer.flush()?;
+        Ok(CommandOutput::new((), Some(report)))
+    }
+}
+
+/// Report the pairs of a BEDPE file by how their ends overlap the ends of the pairs
+/// of another BEDPE file, like `bedtools pairtopair`. Each reported left pair is
+/// written with each right pair it overlaps (both pairs' BEDPE columns), or alone
+/// with the types that report pairs without overlaps (e.g. `--type neither`). Pairs
+/// overlap in either orientation, and strands are ignored.
+#[derive(Parser)]
+pub struct PairToPair {
+    /// A TSV genome file of chromosome names and their lengths
+    #[arg(short, long, required = true)]
+    genome: PathBuf,
+
+    /// The "left" BEDPE file of pairs, which are reported
+    #[arg(short, long, required = true)]
+    left: PathBuf,
+
+    /// The "right" BEDPE file of pairs to overlap the left pairs with
+    #[arg(short, long, required = true)]
+    right: PathBuf,
+
+    /// Which left pairs to report, by how their ends overlap the right pairs' ends
+    #[arg(short = 't', long = "type", value_enum, default_value_t)]
+    overlap_type: PairToPairType,
+
+    /// An optional output file (standard output will be used if not specified)
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+}
+
+impl PairToPair {
+    pub fn run(&self) -> Result<CommandOutput<()>, GRangesError> {
+        let genome = read_seqlens(&self.genome)?;
+        let parse = info_span!("parse right").entered();
+        let right = BedpeIterator::new(&self.right)?;
+        let right_pairs = GRangesPaired::from_iter(right, &genome)?.into_coitrees()?;
+        drop(parse);
+
+        let mut report = Report::new();
+        let mut writer = build_bed_writer(self.output.as_ref())?;
+        for result in BedpeIterator::new(&self.left)?.progress("pairtopair") {
+            let pair = result?;
+            report.records_read += 1;
+            let Some(overlaps) = right_pairs.overlapping_pairs(&pair, self.overlap_type) else {
+                continue;
+            };
+            let pair = pair.to_string();
+            if overlaps.is_empty() {
+                writer.write_record([&pair])?;
+                report.records_written += 1;
+            }
+            for index in overlaps {
+                let other = right_pairs.pairs()[index].to_string();
+                writer.write_record([&pair, &other])?;
+                report.records_written += 1;
+            }
+        }
+        writer.flush()?;
+        Ok(CommandOutput::new((), Some(report)))
+    }
+}
+
+/// The basepair [`Center`] reduces each range to.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum CenterPoint {
+    /// The midpoint of the range.
+    #[default]
+    Midpoint,
+    /// The summit of a narrowPeak peak: its start plus the offset in the tenth
+    /// column (peaks with an offset of -1, i.e. no summit, use their midpoint).
+    Summit,
+}
+
+/// Reduce ranges to a single basepair, their midpoints or (for narrowPeak input)
+/// their summits, optionally expanded by some number of basepairs on each side
+/// (truncated at the sequence ends), e.g. to get fixed-width windows around peak
+/// summits for motif analysis. Other columns are kept, with the summit offset
+/// column of narrowPeak input updated for the new ranges.
+#[derive(Parser)]
+pub struct Center {
+    /// A TSV genome file of chromosome names and their lengths
+    #[arg(short, long, required = true)]
+    genome: PathBuf,
+
+    /// The input BED-like TSV file.
+    #[arg(required = true)]
+    bedfile: PathBuf,
+
+    /// The basepair to reduce each range to
+    #[arg(short, long, value_enum, default_value_t)]
+    center: CenterPoint,
+
+    /// The number of basepairs to expand the center by on each side
+    #[arg(short, long, default_value_t = 0)]
+    flank: Position,
+
+    /// An optional output file (standard output will be used if not specified)
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+
+    /// Skip ranges from sequences (e.g. chromosomes) not present in the genome file.
+    /// By default, ranges with sequence names not in the genome file will raise an error.
+    #[arg(long)]
+    skip_missing: bool,
+
+    /// How to handle ranges that fall outside the genome
+    #[arg(long, value_enum, default_value_t)]
+    bounds: BoundsPolicy,
+}
+
+/// The index of the summit offset column among the extra columns of a narrowPeak
+/// record (the tenth column).
+const SUMMIT_COLUMN: usize = 6;
+
+impl Center {
+    pub fn run(&self) -> Result<CommandOutput<()>, GRangesError> {
+        let genome = read_seqlens(&self.genome)?;
+        let iter = BedlikeIterator::new(&self.bedfile)?;
+        let ranges = checked_ranges(iter, &genome, self.skip_missing, self.bounds);
+
+        let _span = info_span!("center").entered();
+        let mut writer = build_bed_writer(self.output.as_ref())?;
+        let mut report = Report::new();
+        let mut ranges = ranges.progress("center");
+        for result in ranges.by_ref() {
+            let mut range = result?;
+            let length = *genome
+                .get(&range.seqname)
+                .ok_or_else(|| GRangesError::missing_sequence(&range.seqname))?;
+            let center = match self.center {
+                CenterPoint::Midpoint => range.midpoint(),
+                CenterPoint::Summit => summit(&range)?,
+            };
+            let data = range.data.take();
+            let mut centered = center_range(range, center, self.flank, length);
+            if centered.width() != 2 * self.flank + 1 {
+                report.ranges_truncated += 1;
+            }
+            centered.data = match self.center {
+                CenterPoint::Midpoint => data,
+                CenterPoint::Summit => {
+                    // the summit offset is relative to the new start
+                    let offset = (center - centered.start).to_string();
+                    data.map(|data| {
+                        let mut columns: Vec<&str> = data.split('\t').collect();
+                        columns[SUMMIT_COLUMN] = &offset;
+                        columns.join("\t")
+                    })
+                }
+            };
+            writer.serialize(centered)?;
+            report.records_written += 1;
+        }
+        writer.flush()?;
+        report.records_read = ranges.num_items();
+        Ok(CommandOutput::new((), Some(report)))
+    }
+}
+
+/// The summit position of a narrowPeak peak, or its midpoint if it has no summit
+/// (an offset of -1).
+fn summit(range: &GenomicRangeRecord<Option<String>>) -> Result<Position, GRangesError> {
+    let missing = || {
+        GRangesError::MissingSummit(format!(
+            "the range {}:{}-{}",
+            range.seqname, range.start, range.end
+        ))
+    };
+    let offset: PositionOffset = range
+        .data
+        .as_deref()
+        .and_then(|data| data.split('\t').nth(SUMMIT_COLUMN))
+        .ok_or_else(missing)?
+        .parse()?;
+    match Position::try_from(offset) {
+        Ok(offset) if offset < range.width() => Ok(range.start + offset),
+        _ if offset == -1 => Ok(range.midpoint()),
+        _ => Err(missing()),
+    }
+}
+
+/// Shift ranges by some number of basepairs without changing their widths, like
+/// `bedtools shift`. Ranges shifted past a sequence end are truncated at it (keeping
+/// at least one basepair). With `--plus` and `--minus`, ranges are shifted by
+/// different amounts depending on their strand (the sixth BED column): ranges on the
+/// `-` strand are shifted by the `--minus` amount, and all others by the `--plus`
+/// amount. With `--fraction`, the amounts are fractions of each range's width.
+#[derive(Parser)]
+pub struct Shift {
+    /// A TSV genome file of chromosome names and their lengths
+    #[arg(short, long, required = true)]
+    genome: PathBuf,
+
+    /// The input BED-like TSV file.
+    #[arg(required = true)]
+    bedfile: PathBuf,
+
+    /// The number of basepairs to shift ranges rightwards by (or leftwards, if
+    /// negative)
+    #[arg(
+        short,
+        long,
+        allow_negative_numbers = true,
+        required_unless_present_any = ["plus", "minus"],
+        conflicts_with_all = ["plus", "minus"]
+    )]
+    shift: Option<f64>,
+
+    /// The number of basepairs to shift ranges on the '+' strand (or without a
+    /// strand) by
+    #[arg(short, long, allow_negative_numbers = true, requires = "minus")]
+    plus: Option<f64>,
+
+    /// The number of basepairs to shift ranges on the '-' strand by
+    #[arg(short, long, allow_negative_numbers = true, requires = "plus")]
+    minus: Option<f64>,
+
+    /// Interpret the shift amounts as fractions of each range's width
+    #[arg(long)]
+    fraction: bool,
+
+    /// An optional output file (standard output will be used if not specified)
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+
+    /// Skip ranges from sequences (e.g. chromosomes) not present in the genome file.
+    /// By default, ranges with sequence names not in the genome file will raise an error.
+    #[arg(long)]
+    skip_missing: bool,
+
+    /// How to handle ranges that fall outside the genome
+    #[arg(long, value_enum, default_value_t)]
+    bounds: BoundsPolicy,
+}
+
+impl Shift {
+    /// The shift (in basepairs) of `range`.
+    fn amount(&self, range: &GenomicRangeRecord<Option<String>>) -> PositionOffset {
+        let amount = match (self.shift, self.plus, self.minus) {
+            (Some(shift), _, _) => shift,
+            (None, plus, minus) => {
+                let strand = range
+                    .data
+                    .as_deref()
+                    .and_then(|data| data.split('\t').nth(2))
+                    .and_then(parse_strand);
+                match strand {
+                    Some(Strand::Reverse) => minus.unwrap_or(0.0),
+                    _ => plus.unwrap_or(0.0),
+                }
+            }
+        };
+        if self.fraction {
+            (amount * range.width() as f64).round() as PositionOffset
+        } else {
+            amount.round() as PositionOffset
+        }
+    }
+
+    pub fn run(&self) -> Result<CommandOutput<()>, GRangesError> {
+        let genome = read_seqlens(&self.genome)?;
+        let iter = BedlikeIterator::new(&self.bedfile)?;
+        let ranges = checked_ranges(iter, &genome, self.skip_missing, self.bounds);
+
+        let _span = info_span!("shift").entered();
+        let mut writer = build_bed_writer(self.output.as_ref())?;
+        let mut report = Report::new();
+        let mut ranges = ranges.progress("shift");
+        for result in ranges.by_ref() {
+            let range = result?;
+            let length = *genome
+                .get(&range.seqname)
+                .ok_or_else(|| GRangesError::missing_sequence(&range.seqname))?;
+            let width = range.width();
+            let shift = self.amount(&range);
+            let shifted = shift_range(range, shift, length);
+            if shifted.width() != width {
+                report.ranges_truncated += 1;
+            }
+            writer.serialize(shifted)?;
+            report.records_written += 1;
+        }
+        writer.flush()?;
+        report.records_read = ranges.num_items();
+        Ok(CommandOutput::new((), Some(report)))
+    }
+}
+
+/// How [`Split`] balances its output files.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum SplitBalance {
+    /// Balance the total basepairs covered by each file's ranges.
+    #[default]
+    Basepairs,
+    /// Balance the number of records in each file.
+    Records,
+}
+
+/// Split a BED-like file into some number of files, balanced by their total
+/// basepairs (or numbers of records), e.g. to scatter work across cluster jobs.
+/// Ranges are split into consecutive runs, so each file keeps the input's order.
+/// With `--keep-chroms`, all the ranges of a sequence go into the same file (with
+/// sequences assigned to files heaviest first). The files are named like
+/// `<prefix>.00001.bed`, and a line with each file's name, total basepairs, and
+/// number of records is written to standard output, like `bedtools split`.
+#[derive(Parser)]
+pub struct Split {
+    /// The input BED-like TSV file.
+    #[arg(required = true)]
+    bedfile: PathBuf,
 
-    // Process all the overlaps.
-    let result_gr = left_join_gr.map_joins(|join_data| {
-        // Get the "right data" -- the BED5 scores
-        let mut overlap_scores: Vec<f64> = join_data
-            .right_data
-            .into_iter()
-            // Filter out the `None` values.
-            .flatten()
-            .collect();
+    /// The number of files to split the ranges into
+    #[arg(short, long, required = true)]
+    number: NonZeroUsize,
 
-        // Run all operations on the scores.
-        operations
-            .iter()
-            .map(|operation| {
-                operation
-                    .run(&mut overlap_scores)
-                    .into_serializable(&BED_TSV)
-            })
-            .collect::<Vec<SerializableDatumType>>()
-    })?;
+    /// Whether to balance the files by their total basepairs or their numbers of records
+    #[arg(short, long, value_enum, default_value_t)]
+    by: SplitBalance,
 
-    result_gr.write_to_tsv(output, &BED_TSV)?;
+    /// Keep all the ranges of each sequence (e.g. chromosome) in the same file
+    #[arg(short, long)]
+    keep_chroms: bool,
 
-    Ok(CommandOutput::new((), None))
+    /// The prefix of the output files' names
+    #[arg(short, long, default_value = "split")]
+    prefix: String,
 }
 
-/// Generate a BED3 file of genomic windows.
-pub fn granges_windows(
-    seqlens: impl Into<PathBuf>,
-    width: Position,
-    step: Option<Position>,
-    chop: bool,
-    output: Option<impl Into<PathBuf>>,
-) -> Result<CommandOutput<()>, GRangesError> {
-    let genome = read_seqlens(seqlens)?;
-    GRangesEmpty::from_windows(&genome, width, step, chop)?.write_to_tsv(output, &BED_TSV)?;
-    Ok(CommandOutput::new((), None))
-}
+impl Split {
+    pub fn run(&self) -> Result<CommandOutput<()>, GRangesError> {
+        let number = self.number.get();
+        let mut report = Report::new();
+        // validate the ranges up front, as their widths are used for the
+        // basepair weights and totals below
+        let ranges = BedlikeIterator::new(&self.bedfile)?
+            .progress("split")
+            .map(|range| {
+                let range = range?;
+                checked_width(&range)?;
+                Ok(range)
+            })
+            .collect::<Result<Vec<_>, GRangesError>>()?;
+        report.records_read = ranges.len();
+        let weight = |range: &GenomicRangeRecord<Option<String>>| match self.by {
+            SplitBalance::Basepairs => range.width() as u64,
+            SplitBalance::Records => 1,
+        };
 
-/// Generate a random BED-like file with genomic ranges.
-pub fn granges_random_bed(
-    seqlens: impl Into<PathBuf>,
-    num: usize,
-    output: Option<impl Into<PathBuf>>,
-    sort: bool,
-    bed5: bool,
-) -> Result<CommandOutput<()>, GRangesError> {
-    // get the genome info
-    let genome = read_seqlens(seqlens)?;
+        let chunks = if self.keep_chroms {
+            let mut seqname_weights: IndexMap<&str, u64> = IndexMap::new();
+            for range in &ranges {
+                *seqname_weights.entry(range.seqname.as_str()).or_default() += weight(range);
+            }
+            let weights: Vec<u64> = seqname_weights.values().copied().collect();
+            let seqname_chunks = packed_chunks(&weights, number);
+            ranges
+                .iter()
+                .map(|range| {
+                    seqname_chunks[seqname_weights
+                        .get_index_of(range.seqname.as_str())
+                        .unwrap()]
+                })
+                .collect()
+        } else {
+            let weights: Vec<u64> = ranges.iter().map(weight).collect();
+            contiguous_chunks(&weights, number)
+        };
 
-    if bed5 {
-        let mut gr = random_granges_mock_bed5(&genome, num)?;
-        if sort {
-            gr = gr.sort()
+        let paths: Vec<String> = (1..=number)
+            .map(|i| format!("{}.{:05}.bed", self.prefix, i))
+            .collect();
+        let mut writers = paths
+            .iter()
+            .map(|path| build_bed_writer(Some(path)))
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut totals = vec![(0_u64, 0_usize); number];
+        for (range, chunk) in ranges.into_iter().zip(chunks) {
+            totals[chunk].0 += range.width() as u64;
+            totals[chunk].1 += 1;
+            writers[chunk].serialize(range)?;
+            report.records_written += 1;
         }
-        gr.write_to_tsv(output, &BED_TSV)?;
-    } else {
-        let mut gr = random_granges(&genome, num)?;
-        if sort {
-            gr = gr.sort();
+
+        let mut summary = build_tsv_writer(None::<PathBuf>)?;
+        for ((path, writer), (basepairs, records)) in paths.iter().zip(&mut writers).zip(totals) {
+            writer.flush()?;
+            summary.write_record([path.clone(), basepairs.to_string(), records.to_string()])?;
         }
-        gr.write_to_tsv(output, &BED_TSV)?;
-    };
+        summary.flush()?;
+        Ok(CommandOutput::new((), Some(report)))
+    }
+}
 
-    Ok(CommandOutput::new((), None))
+/// Assign each of the items with these `weights` to one of `n` chunks, such that
+/// the chunks' total weights are balanced, and the items are in order across chunks.
+fn contiguous_chunks(weights: &[u64], n: usize) -> Vec<usize> {
+    let total = weights.iter().sum::<u64>().max(1);
+    let mut cumulative = 0;
+    weights
+        .iter()
+        .map(|weight| {
+            // the chunk the item's midpoint falls into
+            let chunk = (cumulative + weight / 2) * n as u64 / total;
+            cumulative += weight;
+            (chunk as usize).min(n - 1)
+        })
+        .collect()
 }
 
-/// Merges all the genomic ranges if they overlap by `distance`.
+/// Assign each of the items with these `weights` to one of `n` chunks, such that
+/// the chunks' total weights are balanced, by assigning the heaviest items first,
+/// each to the lightest chunk.
+fn packed_chunks(weights: &[u64], n: usize) -> Vec<usize> {
+    let mut chunks = vec![0; weights.len()];
+    let mut totals = vec![0; n];
+    let mut order: Vec<usize> = (0..weights.len()).collect();
+    order.sort_by_key(|&i| std::cmp::Reverse(weights[i]));
+    for i in order {
+        let (chunk, _) = totals
+            .iter()
+            .enumerate()
+            .min_by_key(|(_, total)| **total)
+            .expect("n must be positive");
+        chunks[i] = chunk;
+        totals[chunk] += weights[i];
+    }
+    chunks
+}
+
+/// Filter the rows of a BED-like file, or add new columns to them, with expressions
+/// like `width() > 1000 && score > 5` or `log2(score + 1)` (see the [`expr`] module
+/// documentation for the syntax). Rows are processed one at a time, like `awk`,
+/// and each column added with `--add-col` is appended in order to the rows that
+/// pass the `--expr` filter.
+///
+/// [`expr`]: crate::expr
 #[derive(Parser)]
-pub struct Merge {
-    /// The input BED-like TSV file to merge.
-    #[arg(short, long, required = true)]
+#[command(group(
+    clap::ArgGroup::new("expressions")
+        .required(true)
+        .multiple(true)
+        .args(["expr", "add_col"])
+))]
+pub struct Eval {
+    /// The input BED-like TSV file.
+    #[arg(required = true)]
     bedfile: PathBuf,
 
-    /// The minimum distance at which to merge ranges. Like `bedtools merge`,
-    /// `--distance 0` will merge "book-ended" ranges. Negative numbers
-    /// will only merge ranges that overlap by that degree of overlap.
-    #[clap(short, long, default_value_t = 0)]
-    distance: PositionOffset,
+    /// Only output rows for which this expression is true
+    #[arg(short, long)]
+    expr: Option<String>,
 
-    ///// Whether to "group by" feature name, i.e. overlapping ranges
-    ///// with different feature names will not be merged.
-    //#[clap(short, long)]
-    //group_features: usize,
-    /// Operation to do to summarize the score column.
-    #[clap(short, long, value_parser = clap::value_parser!(FloatOperation))]
-    func: Option<FloatOperation>,
+    /// Add a column with the value of this expression (may be used multiple times)
+    #[arg(short, long)]
+    add_col: Vec<String>,
 
     /// An optional output file (standard output will be used if not specified)
     #[arg(short, long)]
     output: Option<PathBuf>,
 }
 
-impl Merge {
-    // TODO optional genome file for validation?
+impl Eval {
     pub fn run(&self) -> Result<CommandOutput<()>, GRangesError> {
-        let bedfile = &self.bedfile;
-        let distance = &self.distance;
-        let ranges_iter = GenomicRangesFile::parsing_iterator(bedfile)?;
-        let func = &self.func;
-
-        let mut writer = build_tsv_writer(self.output.as_ref())?;
+        let filter = self.expr.as_deref().map(Expr::parse).transpose()?;
+        let columns = self
+            .add_col
+            .iter()
+            .map(|expression| Expr::parse(expression))
+            .collect::<Result<Vec<_>, _>>()?;
 
-        match ranges_iter {
-            GenomicRangesParser::Bed3(iter) => {
-                let merging_iter = MergingEmptyResultIterator::new(iter, *distance);
-                for result in merging_iter {
-                    let record = result?;
-                    writer.serialize(record)?;
-                }
-                Ok(CommandOutput::new((), None))
+        let reader = InputStream::new(&self.bedfile).reader()?;
+        let mut writer = build_bed_writer(self.output.as_ref())?;
+        let mut report = Report::new();
+        for result in reader.lines().progress("eval") {
+            let line = result?;
+            if is_header_line(&line) {
+                continue;
             }
-            GenomicRangesParser::Bed4(iter) => {
-                let merging_iter = MergingResultIterator::new(iter, *distance, |data| {
-                    data.into_iter()
-                        .map(|x| x.name)
-                        .collect::<Vec<_>>()
-                        .join(",")
-                });
-                for result in merging_iter {
-                    let record = result?;
-                    writer.serialize(record)?;
-                }
-                Ok(CommandOutput::new((), None))
-            }
-            GenomicRangesParser::Bed5(iter) => {
-                // merging iterator, where we extract scores and apply an operation to all merged genomic ranges' scores
-                let merging_iter = MergingResultIterator::new(iter, *distance, |data| {
-                    let mut scores: Vec<f64> = data
-                        .into_iter()
-                        .filter_map(|bed5_cols| bed5_cols.score)
-                        .collect();
-                    // this unwrap is safe -- if func is None, we use Bed3
-                    func.as_ref().unwrap().run(&mut scores)
-                });
-
-                for result in merging_iter {
-                    let record = result?;
-                    writer.serialize(record)?;
+            report.records_read += 1;
+            let row: Vec<&str> = line.split('\t').collect();
+            if let Some(filter) = &filter {
+                if !filter.matches(&row)? {
+                    report.records_skipped += 1;
+                    continue;
                 }
-                Ok(CommandOutput::new((), None))
             }
-            GenomicRangesParser::Bedlike(_iter) => {
-                todo!()
-            }
-            GenomicRangesParser::Unsupported => {
-                Err(GRangesError::UnsupportedGenomicRangesFileFormat)
+            let mut record: Vec<String> = row.iter().map(|column| column.to_string()).collect();
+            for column in &columns {
+                record.push(column.eval(&row)?.to_string());
             }
+            writer.write_record(&record)?;
+            report.records_written += 1;
         }
+        writer.flush()?;
+        Ok(CommandOutput::new((), Some(report)))
     }
 }
 
-/// Filter out ranges not in the specified "genome" file.
+/// How `granges validate --fix` repairs invalid records.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum ValidationFix {
+    /// Truncate ranges that extend past the end of their sequence, and drop
+    /// all other invalid records.
+    Truncate,
+    /// Drop all invalid records.
+    Drop,
+}
+
+/// The classes of problems found by `granges validate`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ValidationIssue {
+    /// The line has fewer than three columns, or a start or end that is not
+    /// a valid position.
+    MalformedColumns,
+    /// The range start is after its end.
+    StartAfterEnd,
+    /// The sequence name is not in the genome file.
+    UnknownSequence,
+    /// The range extends past the end of its sequence.
+    OutOfBounds,
+    /// The range comes before the previous range in sort order.
+    Unsorted,
+}
+
+impl std::fmt::Display for ValidationIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let description = match self {
+            ValidationIssue::MalformedColumns => "malformed columns",
+            ValidationIssue::StartAfterEnd => "start after end",
+            ValidationIssue::UnknownSequence => "unknown sequence",
+            ValidationIssue::OutOfBounds => "out of bounds",
+            ValidationIssue::Unsorted => "unsorted",
+        };
+        f.pad(description)
+    }
+}
+
+/// The maximum number of line numbers listed per [`ValidationIssue`] in the summary.
+const MAX_LISTED_LINES: usize = 10;
+
+/// Check that a BED-like file is valid for a genome: that every line has a
+/// sequence name, start, and end; that each range's start is not after its end;
+/// that sequence names are in the genome file; that ranges are within their
+/// sequence; and that the ranges are sorted (by sequence, in the order of the
+/// genome file, and then by start position).
+///
+/// The number of invalid records of each class is summarized, with their line
+/// numbers. With `--fix`, a repaired copy of the input is written to the output
+/// and the summary to standard error. Unsorted records cannot be repaired, and are
+/// only reported.
 #[derive(Parser)]
-pub struct FilterChroms {
+pub struct Validate {
     /// A TSV genome file of chromosome names and their lengths
     #[arg(short, long, required = true)]
     genome: PathBuf,
 
-    /// The input BED file.
-    #[arg(short, long, required = true)]
+    /// The input BED-like TSV file to check.
+    #[arg(required = true)]
     bedfile: PathBuf,
 
+    /// Write a repaired copy of the input, truncating or dropping invalid records.
+    #[arg(long, value_enum)]
+    fix: Option<ValidationFix>,
+
     /// An optional output file (standard output will be used if not specified)
     #[arg(short, long)]
     output: Option<PathBuf>,
 }
 
-impl FilterChroms {
+/// The outcome of validating a single BED-like record.
+#[derive(Debug, PartialEq)]
+enum RecordCheck {
+    Valid,
+    /// A range extending past its sequence end, which could be truncated to this end.
+    OutOfBounds(Position),
+    Invalid(ValidationIssue),
+}
+
+/// Check the columns of a BED-like record against `genome`, returning the record's
+/// sequence index in sort `order` and start (for sort order checks) if the sequence
+/// is known.
+fn check_record(
+    columns: &[&str],
+    genome: &IndexMap<String, Position>,
+    order: &GenomeMap<()>,
+) -> (RecordCheck, Option<(usize, Position)>) {
+    let (seqname, start, end) = match columns {
+        [seqname, start, end, ..] => match (start.parse::<Position>(), end.parse::<Position>()) {
+            (Ok(start), Ok(end)) => (*seqname, start, end),
+            _ => return (RecordCheck::Invalid(ValidationIssue::MalformedColumns), None),
+        },
+        _ => return (RecordCheck::Invalid(ValidationIssue::MalformedColumns), None),
+    };
+    if start > end {
+        return (RecordCheck::Invalid(ValidationIssue::StartAfterEnd), None);
+    }
+    let (Some(&length), Some(index)) = (genome.get(seqname), order.get_index_by_name(seqname))
+    else {
+        return (RecordCheck::Invalid(ValidationIssue::UnknownSequence), None);
+    };
+    let check = if end > length {
+        RecordCheck::OutOfBounds(length)
+    } else {
+        RecordCheck::Valid
+    };
+    (check, Some((index, start)))
+}
+
+impl Validate {
     pub fn run(&self) -> Result<CommandOutput<()>, GRangesError> {
-        let bedfile = &self.bedfile;
         let genome = read_seqlens(&self.genome)?;
-        let bedlike_iterator = BedlikeIterator::new(bedfile)?;
+        // check sort order in the order of the genome file's sequences
+        let order = sequence_order(&genome)?;
 
-        let mut writer = build_tsv_writer(self.output.as_ref())?;
+        let reader = InputStream::new(&self.bedfile).reader()?;
+        let mut writer = match self.fix {
+            Some(_) => Some(build_bed_writer(self.output.as_ref())?),
+            None => None,
+        };
 
-        // If we don't need to sort, use iterator-based streaming processing.
-        for record in bedlike_iterator {
-            let range = record?;
-            let seqname = &range.seqname;
-            let passes_filter = genome.contains_key(seqname);
-            if passes_filter {
-                writer.serialize(range)?;
+        let mut issues: BTreeMap<ValidationIssue, Vec<usize>> = BTreeMap::new();
+        let mut report = Report::new();
+        let mut last: Option<(usize, Position)> = None;
+        for (i, result) in reader.lines().enumerate() {
+            let line = result?;
+            if is_header_line(&line) {
+                continue;
+            }
+            let line_number = i + 1;
+            report.records_read += 1;
+
+            let truncated_end;
+            let mut columns: Vec<&str> = line.split('\t').collect();
+            let (check, position) = check_record(&columns, &genome, &order);
+            if let Some(position) = position {
+                if last.is_some_and(|last| position < last) {
+                    issues
+                        .entry(ValidationIssue::Unsorted)
+                        .or_default()
+                        .push(line_number);
+                } else {
+                    last = Some(position);
+                }
+            }
+
+            let keep = match check {
+                RecordCheck::Valid => true,
+                RecordCheck::OutOfBounds(length) => {
+                    issues
+                        .entry(ValidationIssue::OutOfBounds)
+                        .or_default()
+                        .push(line_number);
+                    // ranges starting past the sequence end cannot be truncated
+                    let start: Position = columns[1].parse()?;
+                    if self.fix == Some(ValidationFix::Truncate) && start < length {
+                        truncated_end = length.to_string();
+                        columns[2] = &truncated_end;
+                        report.ranges_truncated += 1;
+                        true
+                    } else {
+                        false
+                    }
+                }
+                RecordCheck::Invalid(issue) => {
+                    issues.entry(issue).or_default().push(line_number);
+                    false
+                }
+            };
+
+            if let Some(writer) = writer.as_mut() {
+                if keep {
+                    writer.write_record(&columns)?;
+                    report.records_written += 1;
+                } else {
+                    report.records_skipped += 1;
+                }
             }
         }
 
-        Ok(CommandOutput::new((), None))
+        let num_issues: usize = issues.values().map(|lines| lines.len()).sum();
+        for (issue, lines) in &issues {
+            report.add_issue(format!("{} records are {}", lines.len(), issue));
+        }
+
+        match writer {
+            Some(mut writer) => {
+                writer.flush()?;
+                write_validation_summary(io::stderr(), report.records_read, &issues)?;
+                Ok(CommandOutput::new((), Some(report)))
+            }
+            None => {
+                let summary: Box<dyn Write> = match &self.output {
+                    Some(path) => create_file(path)?,
+                    None => Box::new(io::stdout()),
+                };
+                write_validation_summary(summary, report.records_read, &issues)?;
+                if num_issues > 0 {
+                    return Err(GRangesError::ValidationFailed(num_issues));
+                }
+                Ok(CommandOutput::new((), Some(report)))
+            }
+        }
+    }
+}
+
+/// Write a summary of the number of invalid records of each class, and their line numbers.
+fn write_validation_summary(
+    mut writer: impl Write,
+    records_checked: usize,
+    issues: &BTreeMap<ValidationIssue, Vec<usize>>,
+) -> Result<(), GRangesError> {
+    writeln!(writer, "records checked:   {}", records_checked)?;
+    for (issue, lines) in issues {
+        let listed: Vec<String> = lines
+            .iter()
+            .take(MAX_LISTED_LINES)
+            .map(|line| line.to_string())
+            .collect();
+        let more = if lines.len() > MAX_LISTED_LINES {
+            ", ..."
+        } else {
+            ""
+        };
+        writeln!(
+            writer,
+            "{:<18} {} (lines {}{})",
+            format!("{}:", issue),
+            lines.len(),
+            listed.join(", "),
+            more
+        )?;
     }
+    if issues.is_empty() {
+        writeln!(writer, "no problems found")?;
+    }
+    Ok(())
 }
 
 // tranpose two nested vecs
@@ -726,11 +5754,111 @@ fn transpose<T>(v: Vec<Vec<T>>) -> Vec<Vec<T>> {
         .collect()
 }
 
-/// Calculate the density of features in a BED4 file, per window. There are two 
+/// Write a BED-like file as a browser-ready track: a BED file with a `track` line
+/// giving its name, description, and color, that can be loaded as a custom track
+/// in the UCSC Genome Browser or IGV.
+///
+/// With `--hub`, a minimal UCSC track hub directory is written instead, with
+/// `hub.txt`, `genomes.txt`, and `<assembly>/trackDb.txt`, and the track's sorted
+/// BED file and `chrom.sizes` file in `<assembly>/`. GRanges does not write bigBed
+/// files, so the hub's `<name>.bb` must be built with UCSC's `bedToBigBed`, e.g.
+/// `bedToBigBed <name>.bed chrom.sizes <name>.bb` in `<assembly>/`.
+#[derive(Parser)]
+pub struct Track {
+    /// The input BED-like TSV file.
+    #[arg(required = true)]
+    bedfile: PathBuf,
+
+    /// The track name
+    #[arg(short, long, required = true)]
+    name: String,
+
+    /// The track description (the name, by default)
+    #[arg(long)]
+    description: Option<String>,
+
+    /// The track color, as an RGB color, e.g. '255,0,0'
+    #[arg(short, long)]
+    color: Option<String>,
+
+    /// Write a UCSC track hub to this directory, rather than a BED file
+    #[arg(long, requires_all = ["genome", "assembly", "email"])]
+    hub: Option<PathBuf>,
+
+    /// A TSV genome file of chromosome names and their lengths (for --hub)
+    #[arg(short, long)]
+    genome: Option<PathBuf>,
+
+    /// The hub's genome assembly name, e.g. 'hg38' (for --hub)
+    #[arg(long)]
+    assembly: Option<String>,
+
+    /// The hub's contact email address (for --hub)
+    #[arg(long)]
+    email: Option<String>,
+
+    /// An optional output file (standard output will be used if not specified)
+    #[arg(short, long, conflicts_with = "hub")]
+    output: Option<PathBuf>,
+}
+
+impl Track {
+    pub fn run(&self) -> Result<CommandOutput<()>, GRangesError> {
+        let track = TrackLine {
+            name: self.name.clone(),
+            description: self.description.clone(),
+            color: self.color.as_deref().map(parse_color).transpose()?,
+        };
+
+        // existing track, browser, and comment lines are replaced
+        let reader = InputStream::new(&self.bedfile).reader()?;
+        let mut lines = Vec::new();
+        for result in reader.lines() {
+            let line = result?;
+            if is_header_line(&line) {
+                continue;
+            }
+            lines.push(line);
+        }
+        let mut report = Report::new();
+        report.records_read = lines.len();
+
+        if let (Some(dir), Some(genome), Some(assembly), Some(email)) =
+            (&self.hub, &self.genome, &self.assembly, &self.email)
+        {
+            let genome = read_seqlens(genome)?;
+            let num_lines = lines.len();
+            let tracks = [HubTrack { track, lines }];
+            write_track_hub(dir, &self.name, email, assembly, &genome, &tracks)?;
+            info!(
+                "track: wrote hub to {}; build {}/{}.bb with bedToBigBed",
+                dir.display(),
+                assembly,
+                self.name
+            );
+            report.records_written = num_lines;
+            return Ok(CommandOutput::new((), Some(report)));
+        }
+
+        let mut writer: Box<dyn Write> = match &self.output {
+            Some(path) => Box::new(io::BufWriter::new(create_file(path)?)),
+            None => Box::new(io::BufWriter::new(io::stdout())),
+        };
+        writeln!(writer, "{}", track)?;
+        for line in &lines {
+            writeln!(writer, "{}", line)?;
+        }
+        writer.flush()?;
+        report.records_written = lines.len();
+        Ok(CommandOutput::new((), Some(report)))
+    }
+}
+
+/// Calculate the density of features in a BED4 file, per window. There are two
 /// modes:
 ///
 ///   1. Without --exclusive (default): a basepair overlapping two features will
-///   increment the counts of both. 
+///   increment the counts of both.
 ///
 ///   2. With --exclusive: a basepair overlapping two features will be assigned to
 ///      a new composite "feature set" of the two features, and increment the 
@@ -794,7 +5922,7 @@ pub struct FeatureDensity {
 type GRangesFeatureMatrix = GRanges<VecRangesIndexed, Vec<Vec<Position>>>;
 
 impl FeatureDensity {
-    /// Calculate feature density per window, with non-exclusive assignment 
+    /// Calculate feature density per window, with non-exclusive assignment
     /// of basepairs to features. E.g. a basepair that overlaps
     /// "CDS" and "exon" features will be added to the tallies of both.
     pub fn feature_density(&self) -> Result<(GRangesFeatureMatrix, Vec<String>), GRangesError> {
@@ -803,8 +5931,7 @@ impl FeatureDensity {
         let bed4_iter = Bed4Iterator::new(bedfile)?;
 
         // Split the elements in the iterator by feature into multiple GRanges objects.
-        let mut records_by_features: HashMap<String, Vec<GenomicRangeRecordEmpty>> =
-            HashMap::new();
+        let mut records_by_features: HashMap<String, Vec<GenomicRangeRecordEmpty>> = HashMap::new();
         for result in bed4_iter {
             let range = result?;
             let feature = &range.data.name;
@@ -963,7 +6090,7 @@ impl FeatureDensity {
                 let config = TsvConfig {
                     no_value_string: "NA".to_string(),
                     headers: Some(headers),
-                    metadata: None,
+                    ..BED_TSV.clone()
                 };
                 window_counts.write_to_tsv(self.output.as_ref(), &config)?;
             }
@@ -975,7 +6102,7 @@ impl FeatureDensity {
             let config = TsvConfig {
                 no_value_string: "NA".to_string(),
                 headers: Some(headers),
-                metadata: None,
+                ..BED_TSV.clone()
             };
             window_counts.write_to_tsv(self.output.as_ref(), &config)?;
         }
@@ -1009,3 +6136,247 @@ fn sorted_indices_by_values(values: &[Position]) -> Vec<usize> {
     indices.sort_by_key(|&i| std::cmp::Reverse(values[i]));
     indices
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_run_operations_precision() {
+        let config = precision_tsv_config(3);
+        let operations = [FloatOperation::Mean, FloatOperation::Sum, FloatOperation::Count];
+        let mut scores = vec![1.0, 2.0, 2.0];
+        let empty = EmptyPolicy::default();
+        let results = run_operations(&operations, None, &mut scores, 4, empty, &config).unwrap();
+        let mut columns = Vec::new();
+        for result in results.unwrap() {
+            result.datum.write_columns(&mut columns, result.config);
+        }
+        assert_eq!(columns, vec!["1.67", "5", "4"]);
+    }
+
+    #[test]
+    fn test_run_operations_empty() {
+        let config = BED_TSV.clone();
+        let operations = [FloatOperation::Sum, FloatOperation::Mean, FloatOperation::Count];
+        let columns = |empty| {
+            let results = run_operations(&operations, None, &mut [], 0, empty, &config).unwrap();
+            results.map(|results| {
+                let mut columns = Vec::new();
+                for result in results {
+                    result.datum.write_columns(&mut columns, result.config);
+                }
+                columns
+            })
+        };
+        assert_eq!(
+            columns(EmptyPolicy::Operation).unwrap(),
+            vec!["0", ".", "0"]
+        );
+        assert_eq!(columns(EmptyPolicy::Missing).unwrap(), vec![".", ".", "0"]);
+        assert_eq!(columns(EmptyPolicy::Na).unwrap(), vec!["NA", "NA", "0"]);
+        assert_eq!(columns(EmptyPolicy::Zero).unwrap(), vec!["0", "0", "0"]);
+        assert!(columns(EmptyPolicy::Drop).is_none());
+    }
+
+    #[test]
+    fn test_batched_writer() {
+        let lines: String = (0..1000)
+            .map(|i| format!("chr1\t{}\t{}\n", i, i + 1))
+            .collect();
+        for background in [false, true] {
+            let file = tempfile::NamedTempFile::new().unwrap();
+            let buffering = OutputBuffering {
+                size: 100,
+                background,
+            };
+            let mut writer = BatchedWriter::new(Box::new(file.reopen().unwrap()), buffering);
+            writer.write_all(lines.as_bytes()).unwrap();
+            writer.write_all(b"chr2\t0\t1\n").unwrap();
+            writer.flush().unwrap();
+            drop(writer);
+            let written = std::fs::read_to_string(file.path()).unwrap();
+            assert_eq!(written, format!("{}chr2\t0\t1\n", lines));
+        }
+    }
+
+    #[test]
+    fn test_adjust_truncated() {
+        let dir = tempfile::tempdir().unwrap();
+        let genome = dir.path().join("genome.tsv");
+        std::fs::write(&genome, "chr1\t100\nchr2\t50\n").unwrap();
+        let bedfile = dir.path().join("ranges.bed");
+        std::fs::write(&bedfile, "chr2\t40\t48\nchr1\t5\t20\nchr1\t30\t40\n").unwrap();
+        let output = dir.path().join("adjusted.bed");
+
+        for sort in [false, true] {
+            let policy = InvertedRangePolicy::default();
+            let result =
+                granges_adjust(&bedfile, &genome, 10, 5, policy, Some(&output), sort).unwrap();
+            let report = result.report().unwrap();
+            assert_eq!(report.records_written, 3);
+            assert_eq!(report.ranges_truncated, 2, "sort = {}", sort);
+        }
+    }
+
+    #[test]
+    fn test_map_chunked_no_rows() {
+        let seqlens = seqlens! { "chr1" => 100 };
+        let right = GRangesEmpty::new_vec(&seqlens);
+        let result = granges_map_chunked(
+            std::iter::empty(),
+            right.as_granges_ref(),
+            10,
+            None,
+            |_, _| Ok(None),
+        );
+        assert!(matches!(result, Err(GRangesError::NoRows)));
+    }
+
+    #[test]
+    fn test_check_record() {
+        let genome = seqlens! { "chr1" => 100, "chr2" => 50 };
+        let order = sequence_order(&genome).unwrap();
+        let check = |line: &str| {
+            let columns: Vec<&str> = line.split('\t').collect();
+            check_record(&columns, &genome, &order)
+        };
+
+        assert_eq!(check("chr1\t10\t20"), (RecordCheck::Valid, Some((0, 10))));
+        assert_eq!(check("chr2\t0\t5\tname"), (RecordCheck::Valid, Some((1, 0))));
+        assert_eq!(
+            check("chr2\t40\t60"),
+            (RecordCheck::OutOfBounds(50), Some((1, 40)))
+        );
+        assert_eq!(
+            check("chr1\t10"),
+            (RecordCheck::Invalid(ValidationIssue::MalformedColumns), None)
+        );
+        assert_eq!(
+            check("chr1\t-1\t20"),
+            (RecordCheck::Invalid(ValidationIssue::MalformedColumns), None)
+        );
+        assert_eq!(
+            check("chr1\t20\t10"),
+            (RecordCheck::Invalid(ValidationIssue::StartAfterEnd), None)
+        );
+        assert_eq!(
+            check("chr3\t0\t10"),
+            (RecordCheck::Invalid(ValidationIssue::UnknownSequence), None)
+        );
+    }
+
+    #[test]
+    fn test_track_operation() {
+        assert_eq!(TrackOperation::Subtract.apply(3.0, 1.0, 0.0), 2.0);
+        assert_eq!(TrackOperation::Log2Ratio.apply(8.0, 2.0, 0.0), 2.0);
+        assert_eq!(TrackOperation::Log2Ratio.apply(3.0, 0.0, 1.0), 2.0);
+        assert_eq!(TrackOperation::Max.apply(3.0, 1.0, 0.0), 3.0);
+        assert_eq!(TrackOperation::Mean.apply(3.0, 1.0, 0.0), 2.0);
+    }
+
+    #[test]
+    fn test_compare_records() {
+        let record = |seqname: &str, start, end, data: Option<&str>| {
+            ((seqname.to_string(), start, end), data.map(|data| data.to_string()))
+        };
+        let left = vec![
+            record("chr1", 0, 10, Some("a")),
+            record("chr1", 5, 10, Some("b")),
+            record("chr2", 0, 10, None),
+            record("chr1", 20, 30, Some("c")),
+        ];
+        let right = vec![
+            record("chr1", 0, 10, Some("a")),
+            record("chr1", 5, 10, Some("B")),
+            record("chr1", 40, 50, Some("d")),
+        ];
+        let (differences, counts) =
+            compare_records(left, right.into_iter().map(Ok), &Tolerance::exact()).unwrap();
+        let expected = vec![
+            ("~<", ("chr1".to_string(), 5, 10), Some("b".to_string())),
+            ("~>", ("chr1".to_string(), 5, 10), Some("B".to_string())),
+            ("<", ("chr1".to_string(), 20, 30), Some("c".to_string())),
+            (">", ("chr1".to_string(), 40, 50), Some("d".to_string())),
+            ("<", ("chr2".to_string(), 0, 10), None),
+        ];
+        assert_eq!(differences, expected);
+        assert_eq!(
+            counts,
+            ComparisonCounts {
+                left: 4,
+                right: 3,
+                identical: 1,
+                left_only: 2,
+                right_only: 1,
+                changed: 1,
+            }
+        );
+    }
+
+    #[test]
+    fn test_compare_records_tolerance() {
+        let record = |score: &str| (("chr1".to_string(), 0, 10), Some(format!("a\t{}", score)));
+        let left = vec![record("1.0"), record("2.0")];
+        let right = vec![record("1.001"), record("2.1")];
+        let tolerance = Tolerance::new(0.01);
+        let (_, counts) = compare_records(left, right.into_iter().map(Ok), &tolerance).unwrap();
+        assert_eq!((counts.identical, counts.changed), (1, 1));
+
+        assert_eq!(parse_column_tolerance("5=0.01"), Ok((1, 0.01)));
+        assert!(parse_column_tolerance("3=0.01").is_err());
+        assert!(parse_column_tolerance("5").is_err());
+    }
+
+    #[test]
+    fn test_hist_inverted_range() {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(file.path(), "chr1\t0\t10\nchr1\t20\t15\n").unwrap();
+        let hist = Hist {
+            bedfile: file.path().to_path_buf(),
+            column: None,
+            bins: NonZeroUsize::new(2).unwrap(),
+            log: false,
+            plot: false,
+            output: None,
+        };
+        assert!(matches!(
+            hist.run(),
+            Err(GRangesError::InvalidGenomicRange {
+                start: 20,
+                end: 15,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_split_inverted_range() {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(file.path(), "chr1\t0\t10\nchr1\t20\t15\n").unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let split = Split {
+            bedfile: file.path().to_path_buf(),
+            number: NonZeroUsize::new(2).unwrap(),
+            by: SplitBalance::Basepairs,
+            keep_chroms: false,
+            prefix: dir.path().join("split").to_string_lossy().into_owned(),
+        };
+        assert!(matches!(
+            split.run(),
+            Err(GRangesError::InvalidGenomicRange {
+                start: 20,
+                end: 15,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_split_chunks() {
+        assert_eq!(contiguous_chunks(&[1; 8], 4), vec![0, 0, 1, 1, 2, 2, 3, 3]);
+        assert_eq!(contiguous_chunks(&[10, 1, 1, 8], 2), vec![0, 1, 1, 1]);
+        assert_eq!(contiguous_chunks(&[1, 1], 4), vec![0, 2]);
+        assert_eq!(packed_chunks(&[5, 10, 3, 4], 2), vec![1, 0, 1, 1]);
+    }
+}
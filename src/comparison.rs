@@ -0,0 +1,317 @@
+//! Comparisons of [`GRanges`] objects (and BED-like data columns) that tolerate
+//! small differences in float columns, e.g. from summing in a different order, or
+//! from writing floats with a different precision.
+//!
+//! Data is compared by its TSV columns (see [`TsvFormat`]): two columns that both
+//! parse as floats are equal if they differ by at most that column's [`Tolerance`],
+//! and other columns must be identical. Exact equality of [`GRanges`] objects is
+//! their [`PartialEq`] implementation.
+//!
+//! # Example
+//!
+//! ```
+//! use granges::prelude::*;
+//! use granges::comparison::Tolerance;
+//!
+//! let seqlens = seqlens! { "chr1" => 100 };
+//! let mut left = GRanges::new_vec(&seqlens);
+//! left.push_range("chr1", 0, 10, 0.1 + 0.2).unwrap();
+//! let mut right = GRanges::new_vec(&seqlens);
+//! right.push_range("chr1", 0, 10, 0.3).unwrap();
+//!
+//! assert!(left != right);
+//! assert!(left.approx_eq(&right, &Tolerance::new(1e-9)));
+//! ```
+
+use std::{collections::HashMap, fmt};
+
+use crate::{
+    granges::{GRanges, GRangesEmpty},
+    io::BED_TSV,
+    traits::{IndexedDataContainer, IterableRangeContainer, TsvFormat},
+    Position,
+};
+
+/// The absolute tolerances of float data columns, by their (zero-based) index
+/// among a range's data columns, with a default for all other columns.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Tolerance {
+    default: f64,
+    columns: HashMap<usize, f64>,
+}
+
+impl Tolerance {
+    /// Create a new [`Tolerance`] of `default` for all columns.
+    pub fn new(default: f64) -> Self {
+        Self {
+            default,
+            columns: HashMap::new(),
+        }
+    }
+
+    /// A [`Tolerance`] of zero, so all columns must be equal.
+    pub fn exact() -> Self {
+        Self::new(0.0)
+    }
+
+    /// Set the tolerance of the data column `column` (zero-based).
+    pub fn column(mut self, column: usize, tolerance: f64) -> Self {
+        self.columns.insert(column, tolerance);
+        self
+    }
+
+    /// The tolerance of the data column `column`.
+    pub fn get(&self, column: usize) -> f64 {
+        self.columns.get(&column).copied().unwrap_or(self.default)
+    }
+
+    /// Whether two data values, `left` and `right` of the data column `column`, are
+    /// equal within its tolerance. Values that are not both floats must be identical.
+    pub fn value_eq(&self, column: usize, left: &str, right: &str) -> bool {
+        if left == right {
+            return true;
+        }
+        match (left.parse::<f64>(), right.parse::<f64>()) {
+            (Ok(left), Ok(right)) => (left - right).abs() <= self.get(column),
+            _ => false,
+        }
+    }
+
+    /// Whether two sets of data columns are equal within their tolerances.
+    pub fn columns_eq<L, R>(&self, left: &[L], right: &[R]) -> bool
+    where
+        L: AsRef<str>,
+        R: AsRef<str>,
+    {
+        let values_eq = |(column, (left, right)): (usize, (&L, &R))| {
+            self.value_eq(column, left.as_ref(), right.as_ref())
+        };
+        left.len() == right.len() && left.iter().zip(right).enumerate().all(values_eq)
+    }
+
+    /// Whether the tab-delimited data columns of two BED-like records (e.g. from a
+    /// [`BedlikeIterator`](crate::io::BedlikeIterator)) are equal within their
+    /// tolerances.
+    pub fn bedlike_data_eq(&self, left: &Option<String>, right: &Option<String>) -> bool {
+        let columns = |data: &Option<String>| -> Vec<String> {
+            data.as_deref()
+                .map(|data| data.split('\t').map(String::from).collect())
+                .unwrap_or_default()
+        };
+        left == right || self.columns_eq(&columns(left), &columns(right))
+    }
+}
+
+/// The first difference found between two [`GRanges`] objects, by
+/// [`GRanges::first_difference()`].
+#[derive(Clone, Debug, PartialEq)]
+pub enum GRangesDifference {
+    /// The objects have different numbers of ranges.
+    Length(usize, usize),
+    /// The `index`-th ranges (in genome order) or their data differ. These are
+    /// formatted as BED-like lines.
+    Record {
+        index: usize,
+        left: String,
+        right: String,
+    },
+}
+
+impl fmt::Display for GRangesDifference {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GRangesDifference::Length(left, right) => {
+                write!(f, "the left has {} ranges, the right has {}", left, right)
+            }
+            GRangesDifference::Record { index, left, right } => write!(
+                f,
+                "range {} differs:\n  left:  {}\n  right: {}",
+                index, left, right
+            ),
+        }
+    }
+}
+
+/// A range's sequence name, start, end, and data columns.
+type Record = (String, Position, Position, Vec<String>);
+
+fn format_record((seqname, start, end, columns): &Record) -> String {
+    let mut line = format!("{}\t{}\t{}", seqname, start, end);
+    for column in columns {
+        line.push('\t');
+        line.push_str(column);
+    }
+    line
+}
+
+/// The first difference between the `left` and `right` records (of the given
+/// numbers of ranges).
+fn first_record_difference(
+    (left_len, left): (usize, impl Iterator<Item = Record>),
+    (right_len, right): (usize, impl Iterator<Item = Record>),
+    tolerance: &Tolerance,
+) -> Option<GRangesDifference> {
+    if left_len != right_len {
+        return Some(GRangesDifference::Length(left_len, right_len));
+    }
+    left.zip(right)
+        .enumerate()
+        .find(|(_, (left, right))| {
+            left.0 != right.0
+                || left.1 != right.1
+                || left.2 != right.2
+                || !tolerance.columns_eq(&left.3, &right.3)
+        })
+        .map(|(index, (left, right))| GRangesDifference::Record {
+            index,
+            left: format_record(&left),
+            right: format_record(&right),
+        })
+}
+
+impl<C, T> GRanges<C, T>
+where
+    C: IterableRangeContainer,
+    T: IndexedDataContainer,
+{
+    /// The ranges in genome order, with their data formatted as TSV columns.
+    fn records<'a>(&'a self) -> Vec<Record>
+    where
+        <T as IndexedDataContainer>::Item<'a>: TsvFormat,
+    {
+        let seqnames = &self.ranges.sorted_keys;
+        let data = self.data.as_ref();
+        self.iter_ranges()
+            .map(move |range| {
+                let mut columns = Vec::new();
+                if let (Some(index), Some(data)) = (range.index, data) {
+                    data.get_value(index).write_columns(&mut columns, &BED_TSV);
+                }
+                let seqname = range.seqname(seqnames).clone();
+                (seqname, range.start, range.end, columns)
+            })
+            .collect()
+    }
+
+    /// Find the first difference between this [`GRanges`] object and `other`, in
+    /// their numbers of ranges, their ranges (in genome order), or their data, with
+    /// float data columns compared within `tolerance`. Returns `None` if they are
+    /// equal.
+    pub fn first_difference<'a, 'b, CR, TR>(
+        &'a self,
+        other: &'b GRanges<CR, TR>,
+        tolerance: &Tolerance,
+    ) -> Option<GRangesDifference>
+    where
+        <T as IndexedDataContainer>::Item<'a>: TsvFormat,
+        CR: IterableRangeContainer,
+        TR: IndexedDataContainer,
+        <TR as IndexedDataContainer>::Item<'b>: TsvFormat,
+    {
+        first_record_difference(
+            (self.len(), self.records().into_iter()),
+            (other.len(), other.records().into_iter()),
+            tolerance,
+        )
+    }
+
+    /// Whether this [`GRanges`] object equals `other`, with float data columns
+    /// compared within `tolerance` (see [`GRanges::first_difference()`]).
+    pub fn approx_eq<'a, 'b, CR, TR>(
+        &'a self,
+        other: &'b GRanges<CR, TR>,
+        tolerance: &Tolerance,
+    ) -> bool
+    where
+        <T as IndexedDataContainer>::Item<'a>: TsvFormat,
+        CR: IterableRangeContainer,
+        TR: IndexedDataContainer,
+        <TR as IndexedDataContainer>::Item<'b>: TsvFormat,
+    {
+        self.first_difference(other, tolerance).is_none()
+    }
+}
+
+impl<C: IterableRangeContainer> GRangesEmpty<C> {
+    /// The ranges in genome order, without data columns.
+    fn records(&self) -> impl Iterator<Item = Record> + '_ {
+        self.iter_named_ranges().map(|range| {
+            (
+                range.seqname.to_string(),
+                range.start,
+                range.end,
+                Vec::new(),
+            )
+        })
+    }
+
+    /// Find the first difference between the ranges of this [`GRangesEmpty`]
+    /// object and `other` (see [`GRanges::first_difference()`]). There are no data
+    /// columns, so `tolerance` is unused, but this has the same signature as
+    /// [`GRanges::first_difference()`] for use in
+    /// [`assert_granges_eq!`](crate::assert_granges_eq).
+    pub fn first_difference<CR: IterableRangeContainer>(
+        &self,
+        other: &GRangesEmpty<CR>,
+        tolerance: &Tolerance,
+    ) -> Option<GRangesDifference> {
+        first_record_difference(
+            (self.len(), self.records()),
+            (other.len(), other.records()),
+            tolerance,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{prelude::*, testing::granges_test_case_01};
+
+    #[test]
+    fn test_tolerance() {
+        let tolerance = Tolerance::new(0.01).column(1, 0.5);
+        assert!(tolerance.value_eq(0, "1.0", "1.005"));
+        assert!(!tolerance.value_eq(0, "1.0", "1.1"));
+        assert!(tolerance.value_eq(1, "1.0", "1.4"));
+        assert!(tolerance.value_eq(0, "a", "a"));
+        assert!(!tolerance.value_eq(0, "a", "b"));
+        assert!(!tolerance.value_eq(0, ".", "0"));
+        assert!(tolerance.columns_eq(&["x", "2"], &["x", "2.001"]));
+        assert!(!tolerance.columns_eq(&["x"], &["x", "2"]));
+        assert!(tolerance.bedlike_data_eq(&Some("x\t2".into()), &Some("x\t2.001".into())));
+        assert!(!tolerance.bedlike_data_eq(&None, &Some("x".into())));
+    }
+
+    #[test]
+    fn test_granges_first_difference() {
+        let gr = granges_test_case_01();
+        assert_eq!(gr.first_difference(&gr.clone(), &Tolerance::exact()), None);
+
+        let mut other = gr.clone();
+        other.data.as_mut().unwrap()[1] += 1e-6;
+        assert!(gr.approx_eq(&other, &Tolerance::new(1e-3)));
+        assert_eq!(
+            gr.first_difference(&other, &Tolerance::exact()),
+            Some(GRangesDifference::Record {
+                index: 1,
+                left: "chr1\t4\t7\t8.1".to_string(),
+                right: format!("chr1\t4\t7\t{}", 8.1 + 1e-6),
+            })
+        );
+
+        let mut shorter = GRanges::new_vec(&gr.seqlens());
+        shorter.push_range("chr1", 0, 5, 1.1).unwrap();
+        assert_eq!(
+            gr.first_difference(&shorter, &Tolerance::exact()),
+            Some(GRangesDifference::Length(5, 1))
+        );
+
+        let mut empty = GRangesEmpty::new_vec(&gr.seqlens());
+        empty.push_range("chr1", 0, 5).unwrap();
+        assert_eq!(
+            empty.first_difference(&empty.clone(), &Tolerance::exact()),
+            None
+        );
+    }
+}
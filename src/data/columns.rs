@@ -0,0 +1,374 @@
+//! A lightweight column schema for data container elements.
+//!
+//! Parsed BED-like files carry additional columns beyond the three range
+//! columns (e.g. the name and score columns of a BED5 file). The [`Selection`]
+//! trait gives these record types a schema: an ordered list of named, typed
+//! [`Column`]s, such that columns can be addressed by name rather than by
+//! struct field or index. Values come out as a [`DatumType`], and can be
+//! converted back into native Rust types through [`FromDatumType`].
+//!
+//! ```
+//! use granges::prelude::*;
+//!
+//! let seqlens = seqlens! { "chr1" => 100 };
+//! let mut gr = GRanges::new_vec(&seqlens);
+//! gr.push_range("chr1", 0, 10, Bed5Addition { name: "a".to_string(), score: Some(1.1) }).unwrap();
+//! gr.push_range("chr1", 20, 30, Bed5Addition { name: "b".to_string(), score: None }).unwrap();
+//!
+//! let scores = gr.column::<f64>("score").unwrap();
+//! assert_eq!(scores, vec![Some(1.1), None]);
+//! ```
+//!
+//! [`Selection`]: crate::traits::Selection
+
+use crate::{
+    error::GRangesError,
+    io::parsers::{
+        bed::{Bed4Addition, Bed5Addition, Bed6Addition, NarrowPeakAddition, Strand},
+        rmsk::RepeatRecord,
+    },
+    traits::Selection,
+};
+
+use super::DatumType;
+
+/// The type of a data column.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColumnType {
+    String,
+    Float,
+    Integer,
+    Unsigned,
+}
+
+impl std::fmt::Display for ColumnType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            ColumnType::String => "string",
+            ColumnType::Float => "float",
+            ColumnType::Integer => "integer",
+            ColumnType::Unsigned => "unsigned integer",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// A named, typed column in a column schema.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Column {
+    pub name: &'static str,
+    pub dtype: ColumnType,
+}
+
+impl Column {
+    pub const fn new(name: &'static str, dtype: ColumnType) -> Self {
+        Self { name, dtype }
+    }
+}
+
+/// Find the position of the column `name` in `schema`, if it exists.
+pub fn column_position(schema: &[Column], name: &str) -> Option<usize> {
+    schema.iter().position(|column| column.name == name)
+}
+
+/// Convert a [`DatumType`] back into a native type.
+///
+/// Through [`datum_into`], [`DatumType::NoValue`] converts to `Ok(None)`. Numeric values are widened
+/// where this is lossless (e.g. an `i32` column can be read as `i64` or `f64`),
+/// but otherwise a type mismatch is an error.
+pub trait FromDatumType: Sized {
+    /// The [`ColumnType`] this type is read from, for error messages.
+    const COLUMN_TYPE: ColumnType;
+    fn from_datum(datum: DatumType) -> Option<Self>;
+}
+
+impl FromDatumType for f64 {
+    const COLUMN_TYPE: ColumnType = ColumnType::Float;
+    fn from_datum(datum: DatumType) -> Option<Self> {
+        match datum {
+            DatumType::Float64(x) => Some(x),
+            DatumType::Float32(x) => Some(x as f64),
+            DatumType::Integer32(x) => Some(x as f64),
+            DatumType::Unsigned32(x) => Some(x as f64),
+            _ => None,
+        }
+    }
+}
+
+impl FromDatumType for f32 {
+    const COLUMN_TYPE: ColumnType = ColumnType::Float;
+    fn from_datum(datum: DatumType) -> Option<Self> {
+        match datum {
+            DatumType::Float32(x) => Some(x),
+            _ => None,
+        }
+    }
+}
+
+impl FromDatumType for i64 {
+    const COLUMN_TYPE: ColumnType = ColumnType::Integer;
+    fn from_datum(datum: DatumType) -> Option<Self> {
+        match datum {
+            DatumType::Integer64(x) => Some(x),
+            DatumType::Integer32(x) => Some(x as i64),
+            DatumType::Unsigned32(x) => Some(x as i64),
+            _ => None,
+        }
+    }
+}
+
+impl FromDatumType for i32 {
+    const COLUMN_TYPE: ColumnType = ColumnType::Integer;
+    fn from_datum(datum: DatumType) -> Option<Self> {
+        match datum {
+            DatumType::Integer32(x) => Some(x),
+            _ => None,
+        }
+    }
+}
+
+impl FromDatumType for u64 {
+    const COLUMN_TYPE: ColumnType = ColumnType::Unsigned;
+    fn from_datum(datum: DatumType) -> Option<Self> {
+        match datum {
+            DatumType::Unsigned64(x) => Some(x),
+            DatumType::Unsigned32(x) => Some(x as u64),
+            _ => None,
+        }
+    }
+}
+
+impl FromDatumType for u32 {
+    const COLUMN_TYPE: ColumnType = ColumnType::Unsigned;
+    fn from_datum(datum: DatumType) -> Option<Self> {
+        match datum {
+            DatumType::Unsigned32(x) => Some(x),
+            _ => None,
+        }
+    }
+}
+
+impl FromDatumType for String {
+    const COLUMN_TYPE: ColumnType = ColumnType::String;
+    fn from_datum(datum: DatumType) -> Option<Self> {
+        match datum {
+            DatumType::String(x) => Some(x),
+            _ => None,
+        }
+    }
+}
+
+/// Convert a selected [`DatumType`] into `T`, returning `Ok(None)` for missing
+/// values and a [`GRangesError::ColumnTypeMismatch`] if the types are incompatible.
+pub fn datum_into<T: FromDatumType>(
+    name: &str,
+    datum: DatumType,
+) -> Result<Option<T>, GRangesError> {
+    if let DatumType::NoValue = datum {
+        return Ok(None);
+    }
+    let found = format!("{:?}", datum);
+    T::from_datum(datum)
+        .map(Some)
+        .ok_or_else(|| GRangesError::ColumnTypeMismatch {
+            column: name.to_string(),
+            expected_type: T::COLUMN_TYPE.to_string(),
+            found_value: found,
+        })
+}
+
+const BED4_SCHEMA: &[Column] = &[Column::new("name", ColumnType::String)];
+
+const BED5_SCHEMA: &[Column] = &[
+    Column::new("name", ColumnType::String),
+    Column::new("score", ColumnType::Float),
+];
+
+impl Selection for Bed4Addition {
+    fn schema() -> &'static [Column] {
+        BED4_SCHEMA
+    }
+    fn select_by_name(&self, name: &str) -> Option<DatumType> {
+        match name {
+            "name" => Some(DatumType::String(self.name.clone())),
+            _ => None,
+        }
+    }
+}
+
+impl Selection for Bed5Addition {
+    fn schema() -> &'static [Column] {
+        BED5_SCHEMA
+    }
+    fn select_by_name(&self, name: &str) -> Option<DatumType> {
+        match name {
+            "name" => Some(DatumType::String(self.name.clone())),
+            "score" => Some(self.score.map_or(DatumType::NoValue, DatumType::Float64)),
+            _ => None,
+        }
+    }
+}
+
+const BED6_SCHEMA: &[Column] = &[
+    Column::new("name", ColumnType::String),
+    Column::new("score", ColumnType::Float),
+    Column::new("strand", ColumnType::String),
+];
+
+/// A strand column's value, with unstranded features as missing.
+fn strand_datum(strand: Option<Strand>) -> DatumType {
+    strand.map_or(DatumType::NoValue, |strand| {
+        DatumType::String(strand.symbol().to_string())
+    })
+}
+
+impl Selection for Bed6Addition {
+    fn schema() -> &'static [Column] {
+        BED6_SCHEMA
+    }
+    fn select_by_name(&self, name: &str) -> Option<DatumType> {
+        match name {
+            "name" => Some(DatumType::String(self.name.clone())),
+            "score" => Some(self.score.map_or(DatumType::NoValue, DatumType::Float64)),
+            "strand" => Some(strand_datum(self.strand)),
+            _ => None,
+        }
+    }
+}
+
+/// The narrowPeak columns, named as in the [format
+/// specification](https://genome.ucsc.edu/FAQ/FAQformat.html#format12).
+const NARROWPEAK_SCHEMA: &[Column] = &[
+    Column::new("name", ColumnType::String),
+    Column::new("score", ColumnType::Float),
+    Column::new("strand", ColumnType::String),
+    Column::new("signalValue", ColumnType::Float),
+    Column::new("pValue", ColumnType::Float),
+    Column::new("qValue", ColumnType::Float),
+    Column::new("peak", ColumnType::Integer),
+];
+
+impl Selection for NarrowPeakAddition {
+    fn schema() -> &'static [Column] {
+        NARROWPEAK_SCHEMA
+    }
+    fn select_by_name(&self, name: &str) -> Option<DatumType> {
+        match name {
+            "name" => Some(DatumType::String(self.name.clone())),
+            "score" => Some(self.score.map_or(DatumType::NoValue, DatumType::Float64)),
+            "strand" => Some(strand_datum(self.strand)),
+            "signalValue" => Some(DatumType::Float64(self.signal_value)),
+            // the pValue, qValue and peak columns use -1 for no value
+            "pValue" if self.p_value == -1.0 => Some(DatumType::NoValue),
+            "pValue" => Some(DatumType::Float64(self.p_value)),
+            "qValue" if self.q_value == -1.0 => Some(DatumType::NoValue),
+            "qValue" => Some(DatumType::Float64(self.q_value)),
+            "peak" if self.peak == -1 => Some(DatumType::NoValue),
+            "peak" => Some(DatumType::Integer64(self.peak)),
+            _ => None,
+        }
+    }
+}
+
+const REPEAT_SCHEMA: &[Column] = &[
+    Column::new("name", ColumnType::String),
+    Column::new("class", ColumnType::String),
+    Column::new("family", ColumnType::String),
+    Column::new("score", ColumnType::Unsigned),
+    Column::new("strand", ColumnType::String),
+    Column::new("divergence", ColumnType::Float),
+];
+
+impl Selection for RepeatRecord {
+    fn schema() -> &'static [Column] {
+        REPEAT_SCHEMA
+    }
+    fn select_by_name(&self, name: &str) -> Option<DatumType> {
+        match name {
+            "name" => Some(DatumType::String(self.name.clone())),
+            "class" => Some(DatumType::String(self.class.clone())),
+            "family" => Some(DatumType::String(self.family.clone())),
+            "score" => Some(DatumType::Unsigned32(self.score)),
+            "strand" => Some(DatumType::String(self.strand.symbol().to_string())),
+            "divergence" => Some(DatumType::Float64(self.divergence)),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bed5(name: &str, score: Option<f64>) -> Bed5Addition {
+        Bed5Addition {
+            name: name.to_string(),
+            score,
+        }
+    }
+
+    #[test]
+    fn test_schema() {
+        assert_eq!(column_position(Bed5Addition::schema(), "score"), Some(1));
+        assert_eq!(column_position(Bed5Addition::schema(), "strand"), None);
+        assert_eq!(Bed4Addition::schema().len(), 1);
+    }
+
+    #[test]
+    fn test_select_by_name() {
+        let row = bed5("peak1", Some(2.5));
+        let score: Option<f64> = datum_into("score", row.select_by_name("score").unwrap()).unwrap();
+        assert_eq!(score, Some(2.5));
+        let name: Option<String> = datum_into("name", row.select_by_name("name").unwrap()).unwrap();
+        assert_eq!(name, Some("peak1".to_string()));
+        assert!(row.select_by_name("strand").is_none());
+    }
+
+    #[test]
+    fn test_bed6_and_narrowpeak() {
+        use crate::io::{Bed6Iterator, NarrowPeakIterator};
+
+        let bed6 = "chr1\t0\t10\tpeak1\t3\t-\nchr1\t20\t30\tpeak2\t.\t.\n";
+        let rows = Bed6Iterator::from_bytes(bed6)
+            .map(|record| record.unwrap().data)
+            .collect::<Vec<_>>();
+        let strand: Option<String> =
+            datum_into("strand", rows[0].select_by_name("strand").unwrap()).unwrap();
+        assert_eq!(strand, Some("-".to_string()));
+        assert!(matches!(
+            rows[1].select_by_name("strand"),
+            Some(DatumType::NoValue)
+        ));
+        assert!(matches!(
+            rows[1].select_by_name("score"),
+            Some(DatumType::NoValue)
+        ));
+
+        let mut peaks =
+            NarrowPeakIterator::from_bytes("chr1\t100\t200\tpeak1\t0\t.\t5.2\t-1\t2.5\t40\n");
+        let row = peaks.next().unwrap().unwrap().data;
+        let summit: Option<i64> = datum_into("peak", row.select_by_name("peak").unwrap()).unwrap();
+        assert_eq!(summit, Some(40));
+        let p_value: Option<f64> =
+            datum_into("pValue", row.select_by_name("pValue").unwrap()).unwrap();
+        assert_eq!(p_value, None);
+        assert_eq!(
+            column_position(NarrowPeakAddition::schema(), "qValue"),
+            Some(5)
+        );
+    }
+
+    #[test]
+    fn test_missing_and_mismatch() {
+        let row = bed5("peak1", None);
+        let score: Option<f64> = datum_into("score", row.select_by_name("score").unwrap()).unwrap();
+        assert_eq!(score, None);
+
+        let result: Result<Option<f64>, _> =
+            datum_into("name", row.select_by_name("name").unwrap());
+        assert!(matches!(
+            result,
+            Err(GRangesError::ColumnTypeMismatch { .. })
+        ));
+    }
+}
@@ -0,0 +1,123 @@
+//! User-defined aggregations written in [Lua](https://www.lua.org), with the
+//! `lua` feature.
+//!
+//! A script defines a global function `aggregate(values)`, which is passed a table
+//! (array) of the values to summarize, and returns a number, string, or `nil` (a
+//! missing value). For example, the root mean square:
+//!
+//! ```lua
+//! function aggregate(values)
+//!   if #values == 0 then return nil end
+//!   local sum = 0
+//!   for _, x in ipairs(values) do sum = sum + x * x end
+//!   return math.sqrt(sum / #values)
+//! end
+//! ```
+//!
+//! This can be used in `granges map --lua script.lua`. A Lua state can only run on
+//! one thread at a time, so calls to a script are serialized.
+
+use std::{path::PathBuf, sync::Mutex};
+
+use mlua::{Function, Lua, Value};
+
+use super::{operations::Aggregate, DatumType};
+use crate::error::GRangesError;
+
+/// The name of the global function a script must define.
+const AGGREGATE_FUNCTION: &str = "aggregate";
+
+/// An [`Aggregate`] implementation that calls a Lua script's `aggregate()` function.
+pub struct LuaAggregation {
+    lua: Mutex<Lua>,
+}
+
+impl LuaAggregation {
+    /// Create a new [`LuaAggregation`] from the source of a script, which is run
+    /// once to define its `aggregate()` function. `name` is used in error messages.
+    pub fn new(source: &str, name: &str) -> Result<Self, GRangesError> {
+        let lua = Lua::new();
+        lua.load(source).set_name(name).exec()?;
+        if lua
+            .globals()
+            .get::<_, Function>(AGGREGATE_FUNCTION)
+            .is_err()
+        {
+            return Err(GRangesError::LuaMissingFunction(
+                name.to_string(),
+                AGGREGATE_FUNCTION.to_string(),
+            ));
+        }
+        Ok(Self {
+            lua: Mutex::new(lua),
+        })
+    }
+
+    /// Create a new [`LuaAggregation`] from a script file.
+    pub fn from_file(path: impl Into<PathBuf>) -> Result<Self, GRangesError> {
+        let path = path.into();
+        let source = std::fs::read_to_string(&path)?;
+        Self::new(&source, &path.display().to_string())
+    }
+}
+
+impl Aggregate for LuaAggregation {
+    fn aggregate(&self, values: &[f64]) -> Result<DatumType, GRangesError> {
+        // a panic while holding the lock cannot leave the Lua state invalid
+        let lua = self
+            .lua
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let aggregate: Function = lua.globals().get(AGGREGATE_FUNCTION)?;
+        let table = lua.create_sequence_from(values.iter().copied())?;
+        let datum = match aggregate.call::<_, Value>(table)? {
+            Value::Nil => DatumType::NoValue,
+            Value::Integer(value) => DatumType::Integer64(value),
+            Value::Number(value) => DatumType::Float64(value),
+            Value::String(value) => DatumType::String(value.to_str()?.to_string()),
+            value => {
+                return Err(GRangesError::LuaInvalidReturn(
+                    value.type_name().to_string(),
+                ));
+            }
+        };
+        Ok(datum)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lua_aggregation() {
+        let script = r#"
+            function aggregate(values)
+              if #values == 0 then return nil end
+              local sum = 0
+              for _, x in ipairs(values) do sum = sum + x * x end
+              return sum
+            end
+        "#;
+        let aggregation = LuaAggregation::new(script, "test").unwrap();
+        assert!(matches!(
+            aggregation.aggregate(&[1.0, 2.0]).unwrap(),
+            DatumType::Float64(x) if x == 5.0
+        ));
+        assert!(matches!(
+            aggregation.aggregate(&[]).unwrap(),
+            DatumType::NoValue
+        ));
+    }
+
+    #[test]
+    fn test_lua_aggregation_errors() {
+        assert!(LuaAggregation::new("x = ", "syntax").is_err());
+        assert!(matches!(
+            LuaAggregation::new("function other() end", "missing"),
+            Err(GRangesError::LuaMissingFunction(..))
+        ));
+        let aggregation = LuaAggregation::new("function aggregate(v) return {} end", "t").unwrap();
+        assert!(aggregation.aggregate(&[1.0]).is_err());
+    }
+}
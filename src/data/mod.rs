@@ -3,14 +3,19 @@
 
 use crate::{
     io::TsvConfig,
-    traits::{DataContainer, IntoDatumType},
+    traits::{DataContainer, IntoDatumType, TsvFormat},
 };
 use serde::ser::Serializer;
 use serde::Serialize;
 
+pub mod columns;
+#[cfg(feature = "lua")]
+pub mod lua;
 #[cfg(feature = "ndarray")]
 pub mod ndarray;
 pub mod operations;
+#[cfg(feature = "polars")]
+pub mod polars;
 pub mod vec;
 
 impl<U> DataContainer for Vec<U> {}
@@ -53,8 +58,12 @@ impl<'a> Serialize for SerializableDatumType<'a> {
     {
         match &self.datum {
             DatumType::NoValue => serializer.serialize_str(&self.config.no_value_string),
-            DatumType::Float32(value) => serializer.serialize_str(&value.to_string()),
-            DatumType::Float64(value) => serializer.serialize_str(&value.to_string()),
+            DatumType::Float32(value) => {
+                serializer.serialize_str(&self.config.format_float32(*value))
+            }
+            DatumType::Float64(value) => {
+                serializer.serialize_str(&self.config.format_float(*value))
+            }
             DatumType::String(value) => serializer.serialize_str(value),
             DatumType::Integer32(value) => serializer.serialize_str(&value.to_string()),
             DatumType::Integer64(value) => serializer.serialize_str(&value.to_string()),
@@ -64,6 +73,22 @@ impl<'a> Serialize for SerializableDatumType<'a> {
     }
 }
 
+impl TsvFormat for DatumType {
+    fn write_columns(&self, columns: &mut Vec<String>, config: &TsvConfig) {
+        let column = match self {
+            DatumType::NoValue => config.no_value_string.clone(),
+            DatumType::Float32(value) => config.format_float32(*value),
+            DatumType::Float64(value) => config.format_float(*value),
+            DatumType::String(value) => value.clone(),
+            DatumType::Integer32(value) => value.to_string(),
+            DatumType::Integer64(value) => value.to_string(),
+            DatumType::Unsigned32(value) => value.to_string(),
+            DatumType::Unsigned64(value) => value.to_string(),
+        };
+        columns.push(column);
+    }
+}
+
 impl IntoDatumType for f64 {
     fn into_data_type(self) -> DatumType {
         DatumType::Float64(self)
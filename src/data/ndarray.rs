@@ -1,9 +1,21 @@
 //! Data container implementations for [`ndarray::Array1`] and [`ndarray::Array2`].
 
+//!
+//! These allow per-range numeric data (e.g. a samples × ranges matrix of counts, stored
+//! with one row per range) to be attached to a [`GRanges`] object. Since ranges store
+//! the index of their data row, sorting ranges keeps rows in sync automatically;
+//! filtering joins ([`GRanges::filter_overlaps`] and [`GRanges::antifilter_overlaps`])
+//! select the rows of retained ranges into a new array.
+
 use crate::error::GRangesError;
 use crate::granges::GRanges;
-use crate::traits::{DataContainer, IndexedDataContainer, RangeContainer};
-use ndarray::{Array1, Array2, ArrayView1};
+use crate::join::JoinFilter;
+use crate::ranges::vec::VecRangesIndexed;
+use crate::traits::{
+    AsGRangesRef, DataContainer, IndexedDataContainer, IterableRangeContainer, OverlapQuery,
+    RangeContainer,
+};
+use ndarray::{Array1, Array2, ArrayView1, Axis};
 
 impl<T> DataContainer for Array1<T> {}
 impl<T> DataContainer for Array2<T> {}
@@ -140,6 +152,92 @@ where
     }
 }
 
+impl<CL, U> GRanges<CL, Array1<U>>
+where
+    CL: IterableRangeContainer + Sync,
+    CL::RangeType: Send + Sync,
+    U: Clone,
+{
+    /// Retain only genomic ranges that have at least one overlap with the `right`
+    /// set of genomic ranges, selecting the corresponding elements of the [`Array1`]
+    /// data container.
+    ///
+    /// This is a type of *filtering join*, in particular a *semi-join*.
+    pub fn filter_overlaps<'a, CR: OverlapQuery + Sync + 'a, DR: 'a>(
+        self,
+        right: &'a impl AsGRangesRef<'a, CR, DR>,
+    ) -> Result<GRanges<VecRangesIndexed, Array1<U>>, GRangesError> {
+        self._filter_overlaps_array1(right, false)
+    }
+
+    /// Exclude genomic ranges in this object that have any overlaps with the `right`
+    /// set of genomic ranges, selecting the corresponding elements of the [`Array1`]
+    /// data container.
+    ///
+    /// This is a type of *filtering join*, in particular a *anti-join*.
+    pub fn antifilter_overlaps<'a, CR: OverlapQuery + Sync + 'a, DR: 'a>(
+        self,
+        right: &'a impl AsGRangesRef<'a, CR, DR>,
+    ) -> Result<GRanges<VecRangesIndexed, Array1<U>>, GRangesError> {
+        self._filter_overlaps_array1(right, true)
+    }
+
+    fn _filter_overlaps_array1<'a, CR: OverlapQuery + Sync + 'a, DR: 'a>(
+        self,
+        right: &'a impl AsGRangesRef<'a, CR, DR>,
+        anti: bool,
+    ) -> Result<GRanges<VecRangesIndexed, Array1<U>>, GRangesError> {
+        let (mut gr, kept_indices) =
+            self._filter_overlaps_indices(right, anti, &JoinFilter::default())?;
+        let data = self.data.as_ref().ok_or(GRangesError::NoDataContainer)?;
+        gr.data = Some(data.select(Axis(0), &kept_indices));
+        Ok(gr)
+    }
+}
+
+impl<CL, U> GRanges<CL, Array2<U>>
+where
+    CL: IterableRangeContainer + Sync,
+    CL::RangeType: Send + Sync,
+    U: Clone,
+{
+    /// Retain only genomic ranges that have at least one overlap with the `right`
+    /// set of genomic ranges, selecting the corresponding rows of the [`Array2`]
+    /// data container.
+    ///
+    /// This is a type of *filtering join*, in particular a *semi-join*.
+    pub fn filter_overlaps<'a, CR: OverlapQuery + Sync + 'a, DR: 'a>(
+        self,
+        right: &'a impl AsGRangesRef<'a, CR, DR>,
+    ) -> Result<GRanges<VecRangesIndexed, Array2<U>>, GRangesError> {
+        self._filter_overlaps_array2(right, false)
+    }
+
+    /// Exclude genomic ranges in this object that have any overlaps with the `right`
+    /// set of genomic ranges, selecting the corresponding rows of the [`Array2`]
+    /// data container.
+    ///
+    /// This is a type of *filtering join*, in particular a *anti-join*.
+    pub fn antifilter_overlaps<'a, CR: OverlapQuery + Sync + 'a, DR: 'a>(
+        self,
+        right: &'a impl AsGRangesRef<'a, CR, DR>,
+    ) -> Result<GRanges<VecRangesIndexed, Array2<U>>, GRangesError> {
+        self._filter_overlaps_array2(right, true)
+    }
+
+    fn _filter_overlaps_array2<'a, CR: OverlapQuery + Sync + 'a, DR: 'a>(
+        self,
+        right: &'a impl AsGRangesRef<'a, CR, DR>,
+        anti: bool,
+    ) -> Result<GRanges<VecRangesIndexed, Array2<U>>, GRangesError> {
+        let (mut gr, kept_indices) =
+            self._filter_overlaps_indices(right, anti, &JoinFilter::default())?;
+        let data = self.data.as_ref().ok_or(GRangesError::NoDataContainer)?;
+        gr.data = Some(data.select(Axis(0), &kept_indices));
+        Ok(gr)
+    }
+}
+
 impl<U> IndexedDataContainer for Array1<U>
 where
     U: Copy + Default + 'static,
@@ -211,7 +309,8 @@ where
 
 #[cfg(test)]
 mod tests {
-    use crate::test_utilities::granges_test_case_01;
+    use crate::prelude::*;
+    use crate::testing::granges_test_case_01;
 
     #[test]
     fn test_map_into_array1() {
@@ -257,4 +356,36 @@ mod tests {
         assert!(((first_col_sum as f64) - 5.0).abs() < 1e-4);
         assert!(((second_col_sum as f64) - 10.0).abs() < 1e-4);
     }
+
+    #[test]
+    fn test_array2_filter_overlaps_in_sync() {
+        // one row per range: (range start, 1.0)
+        let gr = granges_test_case_01();
+        let starts: Vec<f64> = gr.iter_ranges().map(|r| r.start() as f64).collect();
+        let gr = gr.map_into_array2(2, |_| vec![0.0, 1.0]).unwrap();
+        let mut data = gr.data().unwrap().clone();
+        for (i, start) in starts.iter().enumerate() {
+            data[[i, 0]] = *start;
+        }
+        let gr = gr.clone_with_data(Some(data));
+
+        let seqlens = seqlens! { "chr1" => 30, "chr2" => 100 };
+        let mut right: GRangesEmpty<VecRangesEmpty> = GRangesEmpty::new_vec(&seqlens);
+        right.push_range("chr1", 0, 1).unwrap();
+        let right = right.into_coitrees().unwrap();
+
+        let kept = gr.clone().filter_overlaps(&right).unwrap();
+        assert_eq!(kept.len(), 1);
+        let array = kept.data().unwrap();
+        assert_eq!(array.shape(), &[1, 2]);
+        assert_eq!(array[[0, 0]], 0.0);
+
+        let excluded = gr.antifilter_overlaps(&right).unwrap();
+        assert_eq!(excluded.len(), 4);
+        // each range's row still has its start in the first column
+        for range in excluded.iter_ranges() {
+            let row = excluded.data().unwrap().row(range.index().unwrap());
+            assert_eq!(row[0], range.start() as f64);
+        }
+    }
 }
@@ -8,10 +8,14 @@
 
 use clap::ValueEnum;
 use num_traits::{Float, ToPrimitive};
-use std::iter::Sum;
+use std::{iter::Sum, str::FromStr};
 
 use super::DatumType;
-use crate::traits::IntoDatumType;
+use crate::{
+    error::GRangesError,
+    io::tsv::{is_na_value, na_value},
+    traits::IntoDatumType,
+};
 
 /// Calculate the median.
 pub fn median<F: Float + Sum>(numbers: &mut [F]) -> Option<F> {
@@ -48,6 +52,9 @@ pub enum FloatOperation {
     Median,
     /// Concatenate all values into a string separated by commas.
     Collapse,
+    /// Count the number of values. In `granges map`, this is the number of
+    /// overlapping right ranges (including any with missing values).
+    Count,
 }
 
 impl FloatOperation {
@@ -104,6 +111,39 @@ impl FloatOperation {
                     .join(",");
                 DatumType::String(collapsed)
             }
+            FloatOperation::Count => DatumType::Unsigned64(data.len() as u64),
+        }
+    }
+}
+
+/// What the operations report for a range with no overlapping values, e.g. in
+/// `granges map`. The sum of no values is 0, but bedtools reports a missing value for
+/// every operation (except count), so this policy makes the choice explicit.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum EmptyPolicy {
+    /// Each operation's own result (e.g. 0 for sum, and a missing value for mean).
+    #[default]
+    Operation,
+    /// A missing value ('.'), like bedtools.
+    Missing,
+    /// The string 'NA'.
+    Na,
+    /// Zero.
+    Zero,
+    /// Drop the range from the output.
+    Drop,
+}
+
+impl EmptyPolicy {
+    /// The value reported by every operation (except count, which is always zero) for
+    /// no values, or `None` if each operation's own result is reported, or the range
+    /// is dropped.
+    pub fn fill(&self) -> Option<DatumType> {
+        match self {
+            EmptyPolicy::Operation | EmptyPolicy::Drop => None,
+            EmptyPolicy::Missing => Some(DatumType::NoValue),
+            EmptyPolicy::Na => Some(DatumType::String("NA".to_string())),
+            EmptyPolicy::Zero => Some(DatumType::Float64(0.0)),
         }
     }
 }
@@ -112,6 +152,145 @@ pub enum StringOperation {
     Collapse,
 }
 
+/// How the values of a carried column (see [`ColumnCarry`]) of the ranges combined
+/// into one range (e.g. by merging) are combined.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum CarryPolicy {
+    /// The value of the first range.
+    First,
+    /// All the values, separated by commas.
+    Collapse,
+    /// The distinct values, in the order they first appear, separated by commas.
+    Distinct,
+    /// The sum of the values, which must be numbers (missing values are skipped).
+    Sum,
+}
+
+/// A column of BED-like data to carry through an operation that combines ranges
+/// (e.g. `granges merge --carry`), with how its values are combined.
+///
+/// This is parsed from `<column>:<policy>`, where the column is `name`, `score`,
+/// or `strand`, or a 1-based column number after the first three columns, e.g.
+/// `score:sum` or `7:collapse`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ColumnCarry {
+    /// The 0-based index of the column in the BED-like data, i.e. after the first
+    /// three columns (so the name column is 0).
+    pub index: usize,
+    pub policy: CarryPolicy,
+}
+
+impl FromStr for ColumnCarry {
+    type Err = String;
+
+    fn from_str(arg: &str) -> Result<Self, Self::Err> {
+        let (column, policy) = arg.split_once(':').ok_or_else(|| {
+            format!(
+                "expected '<column>:<policy>', e.g. 'score:sum', not '{}'",
+                arg
+            )
+        })?;
+        let index = match column {
+            "name" => 0,
+            "score" => 1,
+            "strand" => 2,
+            _ => match column.parse::<usize>() {
+                Ok(number) if number > 3 => number - 4,
+                _ => {
+                    return Err(format!(
+                        "invalid column '{}': expected name, score, strand, or a column \
+                         number after the first three",
+                        column
+                    ))
+                }
+            },
+        };
+        let policy = CarryPolicy::from_str(policy, true)?;
+        Ok(Self { index, policy })
+    }
+}
+
+impl ColumnCarry {
+    /// Combine the values of this column in the BED-like `data` of several ranges.
+    /// Ranges without the column have missing values, which are written with
+    /// [`na_value()`].
+    pub fn combine(&self, data: &[Option<String>]) -> Result<String, GRangesError> {
+        let values: Vec<Option<&str>> = data
+            .iter()
+            .map(|data| {
+                data.as_deref()
+                    .and_then(|data| data.split('\t').nth(self.index))
+                    .filter(|value| !value.is_empty() && !is_na_value(value))
+            })
+            .collect();
+        let combined = match self.policy {
+            CarryPolicy::First => values
+                .first()
+                .copied()
+                .flatten()
+                .unwrap_or(na_value())
+                .to_string(),
+            CarryPolicy::Collapse => values
+                .iter()
+                .map(|value| value.unwrap_or(na_value()))
+                .collect::<Vec<_>>()
+                .join(","),
+            CarryPolicy::Distinct => {
+                let mut distinct: Vec<&str> = Vec::new();
+                for value in values.iter().map(|value| value.unwrap_or(na_value())) {
+                    if !distinct.contains(&value) {
+                        distinct.push(value);
+                    }
+                }
+                distinct.join(",")
+            }
+            CarryPolicy::Sum => {
+                let mut sum = 0.0;
+                for value in values.into_iter().flatten() {
+                    sum += value
+                        .parse::<f64>()
+                        .map_err(|_| GRangesError::InvalidColumnType {
+                            expected_type: "number".to_string(),
+                            found_value: value.to_string(),
+                            line: data
+                                .iter()
+                                .flatten()
+                                .cloned()
+                                .collect::<Vec<_>>()
+                                .join("\n"),
+                        })?;
+                }
+                sum.to_string()
+            }
+        };
+        Ok(combined)
+    }
+}
+
+/// Combine the BED-like `data` of several ranges (e.g. those merged into one range)
+/// into the `carries` columns, tab-separated, e.g. the data collected by a
+/// [`MergingResultIterator`] with the identity function.
+///
+/// [`MergingResultIterator`]: crate::merging_iterators::MergingResultIterator
+pub fn carry_columns(
+    carries: &[ColumnCarry],
+    data: &[Option<String>],
+) -> Result<String, GRangesError> {
+    let columns = carries
+        .iter()
+        .map(|carry| carry.combine(data))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(columns.join("\t"))
+}
+
+/// A user-defined aggregation of values, for summaries the built-in
+/// [`FloatOperation`]s can't compute (e.g. a Lua script, see
+/// [`LuaAggregation`](crate::data::lua::LuaAggregation) with the `lua` feature).
+pub trait Aggregate: Sync {
+    /// Summarize the (non-missing) `values` as a single value.
+    fn aggregate(&self, values: &[f64]) -> Result<DatumType, GRangesError>;
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -139,4 +318,49 @@ mod tests {
         let mut numbers = vec![-3.0, -1.0, -2.0];
         assert_eq!(median(&mut numbers), Some(-2.0));
     }
+
+    #[test]
+    fn test_empty_policy() {
+        assert!(EmptyPolicy::Operation.fill().is_none());
+        assert!(EmptyPolicy::Drop.fill().is_none());
+        assert!(matches!(
+            EmptyPolicy::Missing.fill(),
+            Some(DatumType::NoValue)
+        ));
+        assert!(matches!(EmptyPolicy::Na.fill(), Some(DatumType::String(na)) if na == "NA"));
+        assert!(matches!(EmptyPolicy::Zero.fill(), Some(DatumType::Float64(zero)) if zero == 0.0));
+    }
+
+    #[test]
+    fn test_carry_columns() {
+        let carry = |arg: &str| arg.parse::<ColumnCarry>().unwrap();
+        assert_eq!(
+            carry("7:collapse"),
+            ColumnCarry {
+                index: 3,
+                policy: CarryPolicy::Collapse
+            }
+        );
+        assert!("score".parse::<ColumnCarry>().is_err());
+        assert!("3:sum".parse::<ColumnCarry>().is_err());
+        assert!("name:mean".parse::<ColumnCarry>().is_err());
+
+        let data = vec![
+            Some("a\t1\t+".to_string()),
+            Some("b\t2.5\t+".to_string()),
+            Some("c\t.\t-".to_string()),
+            None,
+        ];
+        let carries = [
+            carry("name:first"),
+            carry("name:collapse"),
+            carry("score:sum"),
+            carry("strand:distinct"),
+        ];
+        assert_eq!(
+            carry_columns(&carries, &data).unwrap(),
+            "a\ta,b,c,.\t3.5\t+,-,."
+        );
+        assert!(carry("name:sum").combine(&data).is_err());
+    }
 }
@@ -0,0 +1,484 @@
+//! Conversion between [`GRanges`] and polars [`DataFrame`]s, and a [`DataFrame`]
+//! data container.
+//!
+//! A range [`DataFrame`] has the columns `chrom`, `start`, and `end`, an optional
+//! `strand` column (`+`, `-`, or missing), and then any data columns. This allows heavy columnar manipulation to be done in
+//! [polars](https://pola.rs), and the results to be handed back to GRanges for
+//! range operations (or vice versa):
+//!
+//!  - [`GRanges::from_dataframe()`] creates a [`GRanges<VecRangesIndexed, DataFrame>`],
+//!    where the data container is the [`DataFrame`] of the non-range columns, and each
+//!    range's index is its row.
+//!  - [`GRanges::to_dataframe()`] creates a [`DataFrame`] from a [`GRanges`] object with
+//!    a [`DataFrame`] data container, or with a [`Vec<U>`] container when `U` implements
+//!    [`Selection`] (so that its columns are named and typed).
+//!
+//! Rows are output in range order (by sequence, then range order). Since ranges
+//! themselves are unstranded, the `strand` column is kept with the data columns,
+//! and output directly after the range columns.
+
+use polars::prelude::*;
+
+use crate::{
+    data::columns::{datum_into, ColumnType},
+    error::GRangesError,
+    granges::{GRanges, GRangesEmpty},
+    io::tsv::is_na_value,
+    ranges::{
+        try_range,
+        vec::{VecRangesEmpty, VecRangesIndexed},
+        RangeIndexed,
+    },
+    traits::{DataContainer, IndexedDataContainer, IterableRangeContainer, Selection},
+    Position,
+};
+use indexmap::IndexMap;
+
+/// The names of the range columns of a range [`DataFrame`].
+pub const CHROM_COLUMN: &str = "chrom";
+pub const START_COLUMN: &str = "start";
+pub const END_COLUMN: &str = "end";
+/// The name of the optional strand column of a range [`DataFrame`].
+pub const STRAND_COLUMN: &str = "strand";
+
+impl DataContainer for DataFrame {}
+
+/// Trait methods for a [`DataFrame`] data container. Each item is a row,
+/// as a [`Vec<AnyValue>`].
+impl IndexedDataContainer for DataFrame {
+    type Item<'a> = Vec<AnyValue<'a>>;
+    type OwnedItem = Vec<AnyValue<'static>>;
+
+    fn get_value(&self, index: usize) -> Self::Item<'_> {
+        self.get_columns()
+            .iter()
+            .map(|series| series.get(index).unwrap())
+            .collect()
+    }
+
+    fn get_owned(&self, index: usize) -> <Self as IndexedDataContainer>::OwnedItem {
+        self.get_value(index)
+            .into_iter()
+            .map(|value| value.into_static().unwrap())
+            .collect()
+    }
+
+    fn len(&self) -> usize {
+        self.height()
+    }
+
+    fn is_valid_index(&self, index: usize) -> bool {
+        index < self.height()
+    }
+}
+
+/// Extract a position column of a [`DataFrame`], with any integer type. Negative
+/// values, and values too large for a [`Position`], are invalid.
+fn position_column(df: &DataFrame, name: &str) -> Result<Vec<Option<Position>>, GRangesError> {
+    let series = df.column(name)?;
+    let signed = series.cast(&DataType::Int64)?;
+    let unsigned = series.cast(&DataType::UInt64)?;
+    let nulls = series.is_null();
+    let invalid = |row: usize, value: String| GRangesError::DataFrameInvalidRangeValue {
+        row,
+        column: name.to_string(),
+        value,
+    };
+    signed
+        .i64()?
+        .into_iter()
+        .zip(unsigned.u64()?)
+        .zip(&nulls)
+        .enumerate()
+        .map(
+            |(row, ((signed, unsigned), is_null))| -> Result<_, GRangesError> {
+                if is_null == Some(true) {
+                    return Ok(None);
+                }
+                match (signed, unsigned) {
+                    (Some(value), _) if value < 0 => Err(invalid(row, value.to_string())),
+                    (_, Some(value)) => Position::try_from(value)
+                        .map(Some)
+                        .map_err(|_| invalid(row, value.to_string())),
+                    _ => Err(invalid(row, series.get(row)?.to_string())),
+                }
+            },
+        )
+        .collect()
+}
+
+/// Extract the range columns of a [`DataFrame`].
+fn range_columns(
+    df: &DataFrame,
+) -> Result<
+    (
+        Vec<Option<String>>,
+        Vec<Option<Position>>,
+        Vec<Option<Position>>,
+    ),
+    GRangesError,
+> {
+    let chroms = df
+        .column(CHROM_COLUMN)?
+        .str()?
+        .into_iter()
+        .map(|chrom| chrom.map(|chrom| chrom.to_string()))
+        .collect();
+    let starts = position_column(df, START_COLUMN)?;
+    let ends = position_column(df, END_COLUMN)?;
+    Ok((chroms, starts, ends))
+}
+
+/// Check the values of the `strand` column of a [`DataFrame`] (if it has one), which
+/// must be `+`, `-`, or missing.
+fn check_strand_column(df: &DataFrame) -> Result<(), GRangesError> {
+    if !df.get_column_names().contains(&STRAND_COLUMN) {
+        return Ok(());
+    }
+    for (row, strand) in df.column(STRAND_COLUMN)?.str()?.into_iter().enumerate() {
+        if let Some(strand) = strand {
+            if !matches!(strand, "+" | "-") && !is_na_value(strand) {
+                return Err(GRangesError::DataFrameInvalidRangeValue {
+                    row,
+                    column: STRAND_COLUMN.to_string(),
+                    value: strand.to_string(),
+                });
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Move the `strand` column of a [`DataFrame`] (if it has one) directly after the
+/// range columns.
+fn strand_after_range_columns(df: DataFrame) -> Result<DataFrame, GRangesError> {
+    let names = df.get_column_names();
+    if !names.contains(&STRAND_COLUMN) {
+        return Ok(df);
+    }
+    let first = [CHROM_COLUMN, START_COLUMN, END_COLUMN, STRAND_COLUMN];
+    let order: Vec<&str> = first
+        .into_iter()
+        .chain(names.into_iter().filter(|name| !first.contains(name)))
+        .collect();
+    Ok(df.select(order)?)
+}
+
+/// Iterate over the validated ranges of a [`DataFrame`], as `(row, chrom, start, end)`.
+fn for_each_range<F>(
+    df: &DataFrame,
+    seqlens: &IndexMap<String, Position>,
+    mut func: F,
+) -> Result<(), GRangesError>
+where
+    F: FnMut(usize, &str, Position, Position) -> Result<(), GRangesError>,
+{
+    let (chroms, starts, ends) = range_columns(df)?;
+    for (row, ((chrom, start), end)) in chroms.iter().zip(starts).zip(ends).enumerate() {
+        let (Some(chrom), Some(start), Some(end)) = (chrom, start, end) else {
+            return Err(GRangesError::DataFrameMissingRangeValue(row));
+        };
+        let seqlen = seqlens
+            .get(chrom)
+            .ok_or(GRangesError::missing_sequence(chrom))?;
+        try_range(start, end, *seqlen).map_err(|error| error.with_seqname(chrom))?;
+        func(row, chrom, start, end)?;
+    }
+    Ok(())
+}
+
+/// Build the range columns (in range order) of a [`DataFrame`].
+pub(crate) fn range_series<'a>(
+    ranges: impl Iterator<Item = (&'a str, Position, Position)>,
+) -> Vec<Series> {
+    let mut chroms = Vec::new();
+    let mut starts = Vec::new();
+    let mut ends = Vec::new();
+    for (chrom, start, end) in ranges {
+        chroms.push(chrom);
+        starts.push(start as u64);
+        ends.push(end as u64);
+    }
+    vec![
+        Series::new(CHROM_COLUMN, chroms),
+        Series::new(START_COLUMN, starts),
+        Series::new(END_COLUMN, ends),
+    ]
+}
+
+impl GRanges<VecRangesIndexed, DataFrame> {
+    /// Create a new [`GRanges<VecRangesIndexed, DataFrame>`] from a [`DataFrame`] with
+    /// `chrom`, `start`, and `end` columns. All other columns (including `strand`, whose
+    /// values are checked) become the [`DataFrame`] data container.
+    pub fn from_dataframe(
+        df: &DataFrame,
+        seqlens: &IndexMap<String, Position>,
+    ) -> Result<Self, GRangesError> {
+        check_strand_column(df)?;
+        let mut gr = GRanges::new_vec(seqlens);
+        for_each_range(df, seqlens, |row, chrom, start, end| {
+            gr.push_range_with_index(chrom, start, end, row)
+        })?;
+        let data_columns: Vec<&str> = df
+            .get_column_names()
+            .into_iter()
+            .filter(|name| ![CHROM_COLUMN, START_COLUMN, END_COLUMN].contains(name))
+            .collect();
+        gr.data = Some(df.select(data_columns)?);
+        Ok(gr)
+    }
+}
+
+impl GRangesEmpty<VecRangesEmpty> {
+    /// Create a new [`GRangesEmpty`] from a [`DataFrame`] with `chrom`, `start`, and
+    /// `end` columns. Other columns are ignored.
+    pub fn from_dataframe(
+        df: &DataFrame,
+        seqlens: &IndexMap<String, Position>,
+    ) -> Result<Self, GRangesError> {
+        let mut gr = GRangesEmpty::new_vec(seqlens);
+        for_each_range(df, seqlens, |_, chrom, start, end| {
+            gr.push_range(chrom, start, end)
+        })?;
+        Ok(gr)
+    }
+}
+
+impl<C> GRangesEmpty<C>
+where
+    C: IterableRangeContainer,
+{
+    /// Create a new [`DataFrame`] with `chrom`, `start`, and `end` columns.
+    pub fn to_dataframe(&self) -> Result<DataFrame, GRangesError> {
+        let seqnames = self.seqnames();
+        let ranges = self.iter_ranges().map(|range| {
+            (
+                seqnames[range.seqname_index].as_str(),
+                range.start,
+                range.end,
+            )
+        });
+        Ok(DataFrame::new(range_series(ranges))?)
+    }
+}
+
+impl<C> GRanges<C, DataFrame>
+where
+    C: IterableRangeContainer<RangeType = RangeIndexed>,
+{
+    /// Create a new [`DataFrame`] with `chrom`, `start`, and `end` columns followed
+    /// by the data container's columns (with any `strand` column first), with rows
+    /// in range order.
+    pub fn to_dataframe(&self) -> Result<DataFrame, GRangesError> {
+        let data = self.data().ok_or(GRangesError::NoDataContainer)?;
+        let seqnames = self.seqnames();
+        let mut indices = Vec::with_capacity(self.len());
+        let ranges: Vec<_> = self
+            .iter_ranges()
+            .map(|range| {
+                indices.push(range.index.unwrap() as IdxSize);
+                (
+                    seqnames[range.seqname_index].as_str(),
+                    range.start,
+                    range.end,
+                )
+            })
+            .collect();
+        let rows = data.take(&IdxCa::from_vec("index", indices))?;
+        let df = DataFrame::new(range_series(ranges.into_iter()))?;
+        strand_after_range_columns(df.hstack(rows.get_columns())?)
+    }
+}
+
+impl<C, U> GRanges<C, Vec<U>>
+where
+    C: IterableRangeContainer<RangeType = RangeIndexed>,
+    U: Selection,
+{
+    /// Create a new [`DataFrame`] with `chrom`, `start`, and `end` columns followed
+    /// by a column for each column in the data type's schema (see [`Selection`]),
+    /// with any `strand` column first, and rows in range order.
+    pub fn to_dataframe(&self) -> Result<DataFrame, GRangesError> {
+        let data = self.data().ok_or(GRangesError::NoDataContainer)?;
+        let seqnames = self.seqnames();
+        let ranges: Vec<_> = self.iter_ranges().collect();
+        let mut columns = range_series(ranges.iter().map(|range| {
+            (
+                seqnames[range.seqname_index].as_str(),
+                range.start,
+                range.end,
+            )
+        }));
+
+        for column in U::schema() {
+            let name = column.name;
+            let values = ranges.iter().map(|range| {
+                let row = data.get(range.index.unwrap()).unwrap();
+                row.select_by_name(name)
+                    .ok_or_else(|| GRangesError::NoSuchColumn(name.to_string()))
+            });
+            let series = match column.dtype {
+                ColumnType::String => Series::new(
+                    name,
+                    values
+                        .map(|datum| datum_into::<String>(name, datum?))
+                        .collect::<Result<Vec<_>, _>>()?,
+                ),
+                ColumnType::Float => Series::new(
+                    name,
+                    values
+                        .map(|datum| datum_into::<f64>(name, datum?))
+                        .collect::<Result<Vec<_>, _>>()?,
+                ),
+                ColumnType::Integer => Series::new(
+                    name,
+                    values
+                        .map(|datum| datum_into::<i64>(name, datum?))
+                        .collect::<Result<Vec<_>, _>>()?,
+                ),
+                ColumnType::Unsigned => Series::new(
+                    name,
+                    values
+                        .map(|datum| datum_into::<u64>(name, datum?))
+                        .collect::<Result<Vec<_>, _>>()?,
+                ),
+            };
+            columns.push(series);
+        }
+        strand_after_range_columns(DataFrame::new(columns)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::prelude::*;
+    use polars::prelude::*;
+
+    fn example_df() -> DataFrame {
+        df! {
+            "chrom" => &["chr2", "chr1", "chr1"],
+            "start" => &[10u32, 20, 0],
+            "end" => &[20u32, 30, 10],
+            "score" => &[1.0, 2.0, 3.0],
+        }
+        .unwrap()
+    }
+
+    #[test]
+    fn test_from_dataframe_roundtrip() {
+        let seqlens = seqlens! { "chr1" => 100, "chr2" => 100 };
+        let gr = GRanges::from_dataframe(&example_df(), &seqlens).unwrap();
+        assert_eq!(gr.len(), 3);
+        assert_eq!(gr.data().unwrap().width(), 1);
+
+        let df = gr.sort().to_dataframe().unwrap();
+        assert_eq!(df.get_column_names(), &["chrom", "start", "end", "score"]);
+        let scores: Vec<Option<f64>> = df
+            .column("score")
+            .unwrap()
+            .f64()
+            .unwrap()
+            .into_iter()
+            .collect();
+        // chr1 first (seqlens order), then sorted by start
+        assert_eq!(scores, vec![Some(3.0), Some(2.0), Some(1.0)]);
+    }
+
+    #[test]
+    fn test_from_dataframe_invalid() {
+        let seqlens = seqlens! { "chr1" => 25, "chr2" => 100 };
+        let result = GRanges::from_dataframe(&example_df(), &seqlens);
+        assert!(matches!(
+            result,
+            Err(GRangesError::InvalidGenomicRangeForSequence {
+                start: 20,
+                end: 30,
+                length: 25,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_from_dataframe_invalid_position() {
+        let seqlens = seqlens! { "chr1" => 100 };
+        let df = df! {
+            "chrom" => &["chr1", "chr1"],
+            "start" => &[0i64, -5],
+            "end" => &[10i64, 10],
+        }
+        .unwrap();
+        let result = GRanges::from_dataframe(&df, &seqlens);
+        assert!(matches!(
+            result,
+            Err(GRangesError::DataFrameInvalidRangeValue { row: 1, ref column, .. })
+                if column == "start"
+        ));
+
+        let df = df! {
+            "chrom" => &["chr1"],
+            "start" => &[0u32],
+            "end" => &[None::<u32>],
+        }
+        .unwrap();
+        let result = GRanges::from_dataframe(&df, &seqlens);
+        assert!(matches!(
+            result,
+            Err(GRangesError::DataFrameMissingRangeValue(0))
+        ));
+    }
+
+    #[test]
+    fn test_dataframe_strand() {
+        let seqlens = seqlens! { "chr1" => 100 };
+        let df = df! {
+            "chrom" => &["chr1", "chr1"],
+            "start" => &[0u32, 20],
+            "end" => &[10u32, 30],
+            "score" => &[1.0, 2.0],
+            "strand" => &[Some("+"), None],
+        }
+        .unwrap();
+        let gr = GRanges::from_dataframe(&df, &seqlens).unwrap();
+        let df = gr.to_dataframe().unwrap();
+        assert_eq!(
+            df.get_column_names(),
+            &["chrom", "start", "end", "strand", "score"]
+        );
+
+        let df = df! {
+            "chrom" => &["chr1"],
+            "start" => &[0u32],
+            "end" => &[10u32],
+            "strand" => &["x"],
+        }
+        .unwrap();
+        let result = GRanges::from_dataframe(&df, &seqlens);
+        assert!(matches!(
+            result,
+            Err(GRangesError::DataFrameInvalidRangeValue { row: 0, .. })
+        ));
+    }
+
+    #[test]
+    fn test_selection_to_dataframe() {
+        let seqlens = seqlens! { "chr1" => 100 };
+        let mut gr = GRanges::new_vec(&seqlens);
+        gr.push_range(
+            "chr1",
+            0,
+            10,
+            Bed5Addition {
+                name: "a".to_string(),
+                score: None,
+            },
+        )
+        .unwrap();
+        let df = gr.to_dataframe().unwrap();
+        assert_eq!(
+            df.get_column_names(),
+            &["chrom", "start", "end", "name", "score"]
+        );
+        assert_eq!(df.column("score").unwrap().null_count(), 1);
+    }
+}
@@ -0,0 +1,861 @@
+//! Enrichment tests of the overlap between two sets of genomic ranges.
+//!
+//! Two tests are implemented, both on the number of basepairs covered by both sets:
+//!
+//!  1. [`FisherTest`]: Fisher's exact test on the 2×2 contingency table of genome
+//!     basepairs, by whether they are covered by the left and right ranges (like
+//!     `bedtools fisher`, but counting basepairs rather than ranges).
+//!
+//!  2. [`PermutationTest`]: a test that compares the observed overlap to its
+//!     distribution when the left ranges are randomly placed (see [`shuffle_ranges()`]).
+//!     This makes no assumption that basepairs are independent, so it is often more
+//!     appropriate for genomic data, where ranges cluster.
+//!
+//! Both report the fold enrichment, i.e. the ratio of the observed overlap to the
+//! overlap expected by chance. These are the tests run by `granges fisher`.
+//!
+//! A [`MonteCarloTest`] generalizes the permutation test to any [`OverlapStatistic`]
+//! (e.g. the Jaccard index), with regions excluded from both the shuffles and the
+//! statistic (e.g. assembly gaps and blacklisted regions), and reports a z-score and
+//! empirical p-values for both tails. This is the test run by `granges
+//! significance`.
+//!
+//! # Example
+//!
+//! ```
+//! use granges::enrichment::{FisherTest, Intervals};
+//! use granges::prelude::*;
+//!
+//! let seqlens = seqlens! { "chr1" => 1000 };
+//! let left = Intervals::from_ranges(vec![("chr1".to_string(), vec![(0, 100)])]);
+//! let right = Intervals::from_ranges(vec![("chr1".to_string(), vec![(50, 150)])]);
+//! let test = FisherTest::from_intervals(&left, &right, &seqlens);
+//! assert_eq!(test.table, [[50, 50], [50, 850]]);
+//! assert!(test.right_p_value < 1e-10);
+//! ```
+
+use std::io::Write;
+
+use indexmap::IndexMap;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use rayon::prelude::*;
+use serde::Serialize;
+
+use crate::{
+    bootstrap::{BlockCounts, OverlapStatistic},
+    error::GRangesError,
+    gaps::ungapped_regions,
+    ranges::operations::{complement_intervals, merge_intervals},
+    reporting::Report,
+    stats::StatsFormat,
+    Position,
+};
+
+/// Sorted, non-overlapping intervals on each sequence, i.e. the basepairs covered
+/// by a set of ranges.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Intervals {
+    intervals: IndexMap<String, Vec<(Position, Position)>>,
+}
+
+impl Intervals {
+    /// Create the [`Intervals`] covered by the ranges on each sequence, which may
+    /// overlap and need not be sorted.
+    pub fn from_ranges(
+        ranges: impl IntoIterator<Item = (String, Vec<(Position, Position)>)>,
+    ) -> Self {
+        let intervals = ranges
+            .into_iter()
+            .map(|(seqname, seq_ranges)| (seqname, merge_intervals(seq_ranges)))
+            .collect();
+        Self { intervals }
+    }
+
+    /// The intervals on `seqname` (none, if it has none).
+    pub fn on(&self, seqname: &str) -> &[(Position, Position)] {
+        self.intervals
+            .get(seqname)
+            .map_or(&[], |intervals| intervals)
+    }
+
+    /// The intervals on each sequence.
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &Vec<(Position, Position)>)> {
+        self.intervals.iter()
+    }
+
+    /// These intervals, less the basepairs covered by the `other` intervals.
+    pub fn subtract(&self, other: &Intervals) -> Intervals {
+        let intervals = self
+            .intervals
+            .iter()
+            .map(|(seqname, intervals)| {
+                let remaining = complement_intervals(other.on(seqname).to_vec(), intervals);
+                (seqname.clone(), remaining)
+            })
+            .collect();
+        Self { intervals }
+    }
+
+    /// The number of basepairs covered.
+    pub fn covered_bases(&self) -> u64 {
+        self.intervals
+            .values()
+            .flatten()
+            .map(|(start, end)| (end - start) as u64)
+            .sum()
+    }
+
+    /// The number of basepairs covered by both these and the `other` intervals.
+    pub fn intersection_bases(&self, other: &Intervals) -> u64 {
+        self.intervals
+            .iter()
+            .filter_map(|(seqname, intervals)| {
+                let other_intervals = other.intervals.get(seqname)?;
+                Some(intersection_bases(intervals, other_intervals))
+            })
+            .sum()
+    }
+
+    /// The Jaccard index of these and the `other` intervals: the number of basepairs
+    /// covered by both, divided by the number covered by either (or zero, if neither
+    /// covers any).
+    pub fn jaccard(&self, other: &Intervals) -> f64 {
+        let intersection = self.intersection_bases(other);
+        let union = self.covered_bases() + other.covered_bases() - intersection;
+        if union == 0 {
+            return 0.0;
+        }
+        intersection as f64 / union as f64
+    }
+}
+
+/// The number of basepairs in both of two sets of sorted, non-overlapping intervals.
+pub(crate) fn intersection_bases(
+    left: &[(Position, Position)],
+    right: &[(Position, Position)],
+) -> u64 {
+    let (mut i, mut j) = (0, 0);
+    let mut bases = 0;
+    while i < left.len() && j < right.len() {
+        let start = left[i].0.max(right[j].0);
+        let end = left[i].1.min(right[j].1);
+        if start < end {
+            bases += (end - start) as u64;
+        }
+        // advance whichever interval ends first
+        if left[i].1 < right[j].1 {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+    bases
+}
+
+/// Randomly place each range on its sequence (with a length in `seqlens`), keeping
+/// its width. Ranges on sequences not in `seqlens` are dropped.
+pub fn shuffle_ranges(
+    ranges: &IndexMap<String, Vec<(Position, Position)>>,
+    seqlens: &IndexMap<String, Position>,
+    rng: &mut impl Rng,
+) -> IndexMap<String, Vec<(Position, Position)>> {
+    ranges
+        .iter()
+        .filter_map(|(seqname, seq_ranges)| {
+            let length = *seqlens.get(seqname)?;
+            let shuffled = seq_ranges
+                .iter()
+                .map(|(start, end)| {
+                    let width = (end - start).min(length);
+                    let start = rng.gen_range(0..=length - width);
+                    (start, start + width)
+                })
+                .collect();
+            Some((seqname.clone(), shuffled))
+        })
+        .collect()
+}
+
+/// Randomly place each range within the `regions` of its sequence (e.g. those
+/// outside of assembly gaps; see [`crate::gaps`]), keeping its width. Each range is
+/// placed uniformly among the positions where it fits in a region. A range wider
+/// than every region of its sequence is truncated to fit the widest, and ranges on
+/// sequences without regions are dropped.
+pub fn shuffle_ranges_within(
+    ranges: &IndexMap<String, Vec<(Position, Position)>>,
+    regions: &IndexMap<String, Vec<(Position, Position)>>,
+    rng: &mut impl Rng,
+) -> IndexMap<String, Vec<(Position, Position)>> {
+    ranges
+        .iter()
+        .filter_map(|(seqname, seq_ranges)| {
+            let seq_regions = regions.get(seqname).filter(|regions| !regions.is_empty())?;
+            let widest = seq_regions
+                .iter()
+                .map(|(start, end)| end - start)
+                .max()
+                .unwrap_or_default();
+            let shuffled = seq_ranges
+                .iter()
+                .map(|(start, end)| {
+                    let width = (end - start).min(widest);
+                    // the number of starts where the range fits in each region
+                    let num_starts = |&(region_start, region_end): &(Position, Position)| {
+                        ((region_end - region_start + 1) as u64).saturating_sub(width as u64)
+                    };
+                    let total: u64 = seq_regions.iter().map(num_starts).sum();
+                    let mut offset = rng.gen_range(0..total);
+                    for region in seq_regions {
+                        let starts = num_starts(region);
+                        if offset < starts {
+                            let start = region.0 + offset as Position;
+                            return (start, start + width);
+                        }
+                        offset -= starts;
+                    }
+                    unreachable!("the offset is less than the total number of starts")
+                })
+                .collect();
+            Some((seqname.clone(), shuffled))
+        })
+        .collect()
+}
+
+/// The natural log of `n!`.
+fn ln_factorial(n: u64) -> f64 {
+    if n < 20 {
+        return (2..=n).map(|i| (i as f64).ln()).sum();
+    }
+    // Stirling's series, which is very accurate for n ≥ 20
+    let n = n as f64;
+    n * n.ln() - n + 0.5 * (2.0 * std::f64::consts::PI * n).ln() + 1.0 / (12.0 * n)
+        - 1.0 / (360.0 * n.powi(3))
+        + 1.0 / (1260.0 * n.powi(5))
+}
+
+/// The natural log of the binomial coefficient, `n` choose `k`.
+fn ln_choose(n: u64, k: u64) -> f64 {
+    ln_factorial(n) - ln_factorial(k) - ln_factorial(n - k)
+}
+
+/// The hypergeometric distribution of the number of basepairs in both sets, given
+/// the table margins.
+struct Hypergeometric {
+    /// The total number of basepairs.
+    total: u64,
+    /// The number of basepairs in the right set.
+    successes: u64,
+    /// The number of basepairs in the left set.
+    draws: u64,
+}
+
+impl Hypergeometric {
+    fn support(&self) -> (u64, u64) {
+        let lower = (self.draws + self.successes).saturating_sub(self.total);
+        (lower, self.draws.min(self.successes))
+    }
+
+    fn mode(&self) -> u64 {
+        let mode = ((self.draws + 1) as f64 * (self.successes + 1) as f64
+            / (self.total + 2) as f64)
+            .floor() as u64;
+        let (lower, upper) = self.support();
+        mode.clamp(lower, upper)
+    }
+
+    fn ln_pmf(&self, k: u64) -> f64 {
+        ln_choose(self.successes, k) + ln_choose(self.total - self.successes, self.draws - k)
+            - ln_choose(self.total, self.draws)
+    }
+
+    /// The ratio of the probabilities P(X = k + 1) / P(X = k).
+    fn ratio_up(&self, k: u64) -> f64 {
+        (self.successes - k) as f64 * (self.draws - k) as f64
+            / ((k + 1) as f64 * (self.total + k + 1 - self.successes - self.draws) as f64)
+    }
+
+    /// The sum of the probabilities P(X = k) from `from` to the end of the support, in
+    /// the direction in which they decrease (up if `up`), relative to P(X = from).
+    /// Terms are summed until they are negligible.
+    fn relative_tail(&self, from: u64, up: bool) -> f64 {
+        let (lower, upper) = self.support();
+        let (mut k, mut term, mut sum) = (from, 1.0, 1.0);
+        loop {
+            if up {
+                if k >= upper {
+                    break;
+                }
+                term *= self.ratio_up(k);
+                k += 1;
+            } else {
+                if k <= lower {
+                    break;
+                }
+                term /= self.ratio_up(k - 1);
+                k -= 1;
+            }
+            sum += term;
+            if term < sum * f64::EPSILON {
+                break;
+            }
+        }
+        sum
+    }
+
+    /// P(X ≥ k).
+    fn upper_tail(&self, k: u64) -> f64 {
+        let (lower, upper) = self.support();
+        if k <= lower {
+            1.0
+        } else if k > upper {
+            0.0
+        } else if k > self.mode() {
+            self.ln_pmf(k).exp() * self.relative_tail(k, true)
+        } else {
+            (1.0 - self.lower_tail(k - 1)).max(0.0)
+        }
+    }
+
+    /// P(X ≤ k).
+    fn lower_tail(&self, k: u64) -> f64 {
+        let (lower, upper) = self.support();
+        if k >= upper {
+            1.0
+        } else if k < lower {
+            0.0
+        } else if k < self.mode() {
+            self.ln_pmf(k).exp() * self.relative_tail(k, false)
+        } else {
+            (1.0 - self.upper_tail(k + 1)).max(0.0)
+        }
+    }
+
+    /// The two-tailed p-value of `k`: the total probability of outcomes no more
+    /// likely than `k`.
+    fn two_tailed(&self, k: u64) -> f64 {
+        let (lower, upper) = self.support();
+        let mode = self.mode();
+        // allow for floating point error in comparing probabilities
+        let threshold = self.ln_pmf(k) + 1e-7;
+        // the probabilities increase to the mode, and then decrease, so binary search
+        // for the last outcome on the other side of the mode that is no more likely
+        let p = if k >= mode {
+            let (mut lo, mut hi) = (lower, mode);
+            if self.ln_pmf(lo) > threshold {
+                self.upper_tail(k)
+            } else {
+                while lo < hi {
+                    let mid = lo + (hi - lo + 1) / 2;
+                    if self.ln_pmf(mid) <= threshold {
+                        lo = mid;
+                    } else {
+                        hi = mid - 1;
+                    }
+                }
+                self.lower_tail(lo.min(k.saturating_sub(1))) + self.upper_tail(k)
+            }
+        } else {
+            let (mut lo, mut hi) = (mode, upper);
+            if self.ln_pmf(hi) > threshold {
+                self.lower_tail(k)
+            } else {
+                while lo < hi {
+                    let mid = lo + (hi - lo) / 2;
+                    if self.ln_pmf(mid) <= threshold {
+                        hi = mid;
+                    } else {
+                        lo = mid + 1;
+                    }
+                }
+                self.lower_tail(k) + self.upper_tail(hi)
+            }
+        };
+        p.min(1.0)
+    }
+}
+
+/// Fisher's exact test on a 2×2 contingency table of basepairs.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct FisherTest {
+    /// The number of basepairs `[[in both, in left only], [in right only, in neither]]`.
+    pub table: [[u64; 2]; 2],
+    /// The p-value of the test for depletion of overlap.
+    pub left_p_value: f64,
+    /// The p-value of the test for enrichment of overlap.
+    pub right_p_value: f64,
+    pub two_tailed_p_value: f64,
+    /// The ratio of the basepairs in both sets to the number expected if the sets
+    /// were independent.
+    pub fold_enrichment: f64,
+}
+
+impl FisherTest {
+    /// Run Fisher's exact test on a 2×2 contingency table,
+    /// `[[in both, in left only], [in right only, in neither]]`.
+    pub fn new(table: [[u64; 2]; 2]) -> Self {
+        let [[both, left_only], [right_only, neither]] = table;
+        let distribution = Hypergeometric {
+            total: both + left_only + right_only + neither,
+            successes: both + right_only,
+            draws: both + left_only,
+        };
+        let expected = distribution.draws as f64 * distribution.successes as f64
+            / distribution.total as f64;
+        Self {
+            table,
+            left_p_value: distribution.lower_tail(both),
+            right_p_value: distribution.upper_tail(both),
+            two_tailed_p_value: distribution.two_tailed(both),
+            fold_enrichment: both as f64 / expected,
+        }
+    }
+
+    /// Run Fisher's exact test on the basepairs of the genome (with sequence lengths
+    /// `seqlens`) covered by the `left` and `right` intervals.
+    pub fn from_intervals(
+        left: &Intervals,
+        right: &Intervals,
+        seqlens: &IndexMap<String, Position>,
+    ) -> Self {
+        let genome_size: u64 = seqlens.values().map(|&length| length as u64).sum();
+        Self::from_genome_size(left, right, genome_size)
+    }
+
+    /// Run Fisher's exact test as [`FisherTest::from_intervals()`] does, but only on
+    /// the basepairs of the genome outside of the `excluded` intervals (e.g.
+    /// assembly gaps, which no range can overlap; see [`crate::gaps`]).
+    pub fn from_intervals_excluding(
+        left: &Intervals,
+        right: &Intervals,
+        seqlens: &IndexMap<String, Position>,
+        excluded: &Intervals,
+    ) -> Self {
+        let genome_size: u64 = seqlens.values().map(|&length| length as u64).sum();
+        let genome_size = genome_size.saturating_sub(excluded.covered_bases());
+        Self::from_genome_size(
+            &left.subtract(excluded),
+            &right.subtract(excluded),
+            genome_size,
+        )
+    }
+
+    fn from_genome_size(left: &Intervals, right: &Intervals, genome_size: u64) -> Self {
+        let both = left.intersection_bases(right);
+        let left_only = left.covered_bases() - both;
+        let right_only = right.covered_bases() - both;
+        let neither = genome_size.saturating_sub(both + left_only + right_only);
+        Self::new([[both, left_only], [right_only, neither]])
+    }
+}
+
+/// A permutation test of the enrichment of overlap between two sets of ranges.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct PermutationTest {
+    /// The number of basepairs in both sets.
+    pub observed: u64,
+    /// The mean number of basepairs in both sets when the left ranges are shuffled.
+    pub expected: f64,
+    pub fold_enrichment: f64,
+    pub permutations: usize,
+    /// The fraction of permutations (counting the observed data as one) with at least
+    /// as many basepairs in both sets as observed.
+    pub p_value: f64,
+}
+
+impl PermutationTest {
+    /// Compare the basepairs covered by both the `left` ranges and the `right`
+    /// intervals to the overlap when the left ranges are randomly placed on their
+    /// sequence (see [`shuffle_ranges()`]), over `permutations` shuffles. Each
+    /// permutation uses a random number generator seeded with `seed` plus its
+    /// index, so results are reproducible regardless of the number of threads.
+    pub fn new(
+        left: &IndexMap<String, Vec<(Position, Position)>>,
+        right: &Intervals,
+        seqlens: &IndexMap<String, Position>,
+        permutations: usize,
+        seed: u64,
+    ) -> Self {
+        Self::with_shuffle(left, right, permutations, seed, |rng| {
+            shuffle_ranges(left, seqlens, rng)
+        })
+    }
+
+    /// Run a permutation test as [`PermutationTest::new()`] does, but placing the
+    /// left ranges only within the `regions` of each sequence (see
+    /// [`shuffle_ranges_within()`]), e.g. those outside of assembly gaps.
+    pub fn within(
+        left: &IndexMap<String, Vec<(Position, Position)>>,
+        right: &Intervals,
+        regions: &IndexMap<String, Vec<(Position, Position)>>,
+        permutations: usize,
+        seed: u64,
+    ) -> Self {
+        Self::with_shuffle(left, right, permutations, seed, |rng| {
+            shuffle_ranges_within(left, regions, rng)
+        })
+    }
+
+    fn with_shuffle<F>(
+        left: &IndexMap<String, Vec<(Position, Position)>>,
+        right: &Intervals,
+        permutations: usize,
+        seed: u64,
+        shuffle: F,
+    ) -> Self
+    where
+        F: Fn(&mut StdRng) -> IndexMap<String, Vec<(Position, Position)>> + Sync,
+    {
+        let observed = Intervals::from_ranges(left.clone()).intersection_bases(right);
+        let null: Vec<u64> = (0..permutations)
+            .into_par_iter()
+            .map(|i| {
+                let mut rng = StdRng::seed_from_u64(seed.wrapping_add(i as u64));
+                Intervals::from_ranges(shuffle(&mut rng)).intersection_bases(right)
+            })
+            .collect();
+        let expected = null.iter().sum::<u64>() as f64 / permutations as f64;
+        let num_extreme = null.iter().filter(|&&overlap| overlap >= observed).count();
+        Self {
+            observed,
+            expected,
+            fold_enrichment: observed as f64 / expected,
+            permutations,
+            p_value: (num_extreme + 1) as f64 / (permutations + 1) as f64,
+        }
+    }
+}
+
+/// A Monte Carlo test of the overlap between two sets of ranges: the observed value
+/// of an [`OverlapStatistic`], compared to its distribution when the left ranges are
+/// randomly placed.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct MonteCarloTest {
+    pub statistic: OverlapStatistic,
+    pub observed: f64,
+    /// The mean of the statistic over the shuffles.
+    pub null_mean: f64,
+    /// The standard deviation of the statistic over the shuffles.
+    pub null_std_dev: f64,
+    /// The number of standard deviations the observed statistic is from the null mean.
+    pub z_score: f64,
+    /// The fraction of shuffles (counting the observed data as one) with a statistic
+    /// at least as large as observed.
+    pub p_value_greater: f64,
+    /// The fraction of shuffles (counting the observed data as one) with a statistic
+    /// at most as large as observed.
+    pub p_value_less: f64,
+    /// Twice the smaller of the one-tailed p-values (at most one).
+    pub p_value_two_sided: f64,
+    pub shuffles: usize,
+    pub seed: u64,
+}
+
+impl MonteCarloTest {
+    /// Compare the `statistic` of the overlap between the `left` ranges and the
+    /// `right` intervals to its distribution over `shuffles` random placements of the
+    /// left ranges on their sequences (with lengths `seqlens`; see
+    /// [`shuffle_ranges_within()`]). Basepairs in the `excluded` intervals (e.g.
+    /// assembly gaps) are never shuffled into, and don't count towards the statistic.
+    /// The shuffles are run in parallel, each with a random number generator seeded
+    /// with `seed` plus its index, so results are reproducible regardless of the
+    /// number of threads.
+    pub fn new(
+        left: &IndexMap<String, Vec<(Position, Position)>>,
+        right: &Intervals,
+        seqlens: &IndexMap<String, Position>,
+        excluded: Option<&Intervals>,
+        statistic: OverlapStatistic,
+        shuffles: usize,
+        seed: u64,
+    ) -> Self {
+        let excluded = excluded.cloned().unwrap_or_default();
+        let regions = ungapped_regions(seqlens, &excluded);
+        let length: u64 = regions
+            .values()
+            .flatten()
+            .map(|(start, end)| (end - start) as u64)
+            .sum();
+        let right = right.subtract(&excluded);
+        let value = |left: IndexMap<String, Vec<(Position, Position)>>| {
+            let left = Intervals::from_ranges(left).subtract(&excluded);
+            let counts = BlockCounts {
+                length,
+                left: left.covered_bases(),
+                right: right.covered_bases(),
+                both: left.intersection_bases(&right),
+            };
+            statistic.value(&counts)
+        };
+
+        let observed = value(left.clone());
+        let null: Vec<f64> = (0..shuffles)
+            .into_par_iter()
+            .map(|i| {
+                let mut rng = StdRng::seed_from_u64(seed.wrapping_add(i as u64));
+                value(shuffle_ranges_within(left, &regions, &mut rng))
+            })
+            .collect();
+        let null_mean = null.iter().sum::<f64>() / shuffles as f64;
+        let variance = null
+            .iter()
+            .map(|sample| (sample - null_mean).powi(2))
+            .sum::<f64>()
+            / (shuffles.max(2) - 1) as f64;
+        let null_std_dev = variance.sqrt();
+        let p_value = |num_extreme: usize| (num_extreme + 1) as f64 / (shuffles + 1) as f64;
+        let p_value_greater = p_value(null.iter().filter(|&&sample| sample >= observed).count());
+        let p_value_less = p_value(null.iter().filter(|&&sample| sample <= observed).count());
+        Self {
+            statistic,
+            observed,
+            null_mean,
+            null_std_dev,
+            z_score: (observed - null_mean) / null_std_dev,
+            p_value_greater,
+            p_value_less,
+            p_value_two_sided: (2.0 * p_value_greater.min(p_value_less)).min(1.0),
+            shuffles,
+            seed,
+        }
+    }
+
+    /// Add the results of this test to a [`Report`], e.g. for `granges --report json`.
+    pub fn add_to_report(&self, report: &mut Report) {
+        report.add_statistic("observed", self.observed);
+        report.add_statistic("null_mean", self.null_mean);
+        report.add_statistic("null_std_dev", self.null_std_dev);
+        report.add_statistic("z_score", self.z_score);
+        report.add_statistic("p_value_greater", self.p_value_greater);
+        report.add_statistic("p_value_less", self.p_value_less);
+        report.add_statistic("p_value_two_sided", self.p_value_two_sided);
+    }
+
+    /// Write these results in the specified format. The TSV format is a table of
+    /// each result and its value.
+    pub fn write(&self, mut writer: impl Write, format: &StatsFormat) -> Result<(), GRangesError> {
+        match format {
+            StatsFormat::Text => {
+                writeln!(writer, "# Monte Carlo test of {}", self.statistic.name())?;
+                writeln!(writer, "shuffles:            {}", self.shuffles)?;
+                writeln!(writer, "seed:                {}", self.seed)?;
+                writeln!(writer, "observed:            {}", self.observed)?;
+                writeln!(writer, "null mean:           {:.4}", self.null_mean)?;
+                writeln!(writer, "null std. dev.:      {:.4}", self.null_std_dev)?;
+                writeln!(writer, "z-score:             {:.4}", self.z_score)?;
+                writeln!(writer, "p-value (greater):   {:e}", self.p_value_greater)?;
+                writeln!(writer, "p-value (less):      {:e}", self.p_value_less)?;
+                writeln!(writer, "p-value (two-sided): {:e}", self.p_value_two_sided)?;
+            }
+            StatsFormat::Json => {
+                serde_json::to_writer_pretty(&mut writer, self)
+                    .map_err(|e| GRangesError::ReportError(e.to_string()))?;
+                writeln!(writer)?;
+            }
+            StatsFormat::Tsv => {
+                writeln!(writer, "statistic\tvalue")?;
+                writeln!(writer, "{}\t{}", self.statistic.name(), self.observed)?;
+                writeln!(writer, "shuffles\t{}", self.shuffles)?;
+                writeln!(writer, "null_mean\t{}", self.null_mean)?;
+                writeln!(writer, "null_std_dev\t{}", self.null_std_dev)?;
+                writeln!(writer, "z_score\t{}", self.z_score)?;
+                writeln!(writer, "p_value_greater\t{}", self.p_value_greater)?;
+                writeln!(writer, "p_value_less\t{}", self.p_value_less)?;
+                writeln!(writer, "p_value_two_sided\t{}", self.p_value_two_sided)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The results of the enrichment tests run by `granges fisher`.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct EnrichmentTests {
+    pub fisher: FisherTest,
+    pub permutation: Option<PermutationTest>,
+}
+
+impl EnrichmentTests {
+    /// Write these results in the specified format. The TSV format is a table of
+    /// each statistic and its value.
+    pub fn write(&self, mut writer: impl Write, format: &StatsFormat) -> Result<(), GRangesError> {
+        let [[both, left_only], [right_only, neither]] = self.fisher.table;
+        match format {
+            StatsFormat::Text => {
+                writeln!(writer, "# contingency table of basepairs")?;
+                writeln!(writer, "{:<14}{:>16}{:>16}", "", "in right", "not in right")?;
+                writeln!(writer, "{:<14}{:>16}{:>16}", "in left", both, left_only)?;
+                writeln!(writer, "{:<14}{:>16}{:>16}", "not in left", right_only, neither)?;
+                writeln!(writer, "# Fisher's exact test")?;
+                writeln!(writer, "left p-value:        {:e}", self.fisher.left_p_value)?;
+                writeln!(writer, "right p-value:       {:e}", self.fisher.right_p_value)?;
+                writeln!(writer, "two-tailed p-value:  {:e}", self.fisher.two_tailed_p_value)?;
+                writeln!(writer, "fold enrichment:     {:.4}", self.fisher.fold_enrichment)?;
+                if let Some(permutation) = &self.permutation {
+                    writeln!(writer, "# permutation test")?;
+                    writeln!(writer, "permutations:        {}", permutation.permutations)?;
+                    writeln!(writer, "observed overlap:    {}", permutation.observed)?;
+                    writeln!(writer, "expected overlap:    {:.2}", permutation.expected)?;
+                    writeln!(writer, "fold enrichment:     {:.4}", permutation.fold_enrichment)?;
+                    writeln!(writer, "p-value:             {:e}", permutation.p_value)?;
+                }
+            }
+            StatsFormat::Json => {
+                serde_json::to_writer_pretty(&mut writer, self)
+                    .map_err(|e| GRangesError::ReportError(e.to_string()))?;
+                writeln!(writer)?;
+            }
+            StatsFormat::Tsv => {
+                writeln!(writer, "statistic\tvalue")?;
+                writeln!(writer, "in_both\t{}", both)?;
+                writeln!(writer, "in_left_only\t{}", left_only)?;
+                writeln!(writer, "in_right_only\t{}", right_only)?;
+                writeln!(writer, "in_neither\t{}", neither)?;
+                writeln!(writer, "fisher_left_p_value\t{}", self.fisher.left_p_value)?;
+                writeln!(writer, "fisher_right_p_value\t{}", self.fisher.right_p_value)?;
+                writeln!(writer, "fisher_two_tailed_p_value\t{}", self.fisher.two_tailed_p_value)?;
+                writeln!(writer, "fisher_fold_enrichment\t{}", self.fisher.fold_enrichment)?;
+                if let Some(permutation) = &self.permutation {
+                    writeln!(writer, "permutations\t{}", permutation.permutations)?;
+                    writeln!(writer, "permutation_expected\t{}", permutation.expected)?;
+                    writeln!(writer, "permutation_fold_enrichment\t{}", permutation.fold_enrichment)?;
+                    writeln!(writer, "permutation_p_value\t{}", permutation.p_value)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::seqlens;
+
+    #[test]
+    fn test_intervals() {
+        let left = Intervals::from_ranges(vec![(
+            "chr1".to_string(),
+            vec![(10, 20), (0, 5), (15, 30), (30, 35)],
+        )]);
+        // overlapping and book-ended ranges are merged
+        assert_eq!(left.covered_bases(), 5 + 25);
+
+        let right = Intervals::from_ranges(vec![
+            ("chr1".to_string(), vec![(3, 12), (25, 40)]),
+            ("chr2".to_string(), vec![(0, 10)]),
+        ]);
+        assert_eq!(left.intersection_bases(&right), 2 + 2 + 10);
+        assert_eq!(right.intersection_bases(&left), 2 + 2 + 10);
+        // the union is 30 + 34 - 14 basepairs
+        assert_eq!(left.jaccard(&right), 14.0 / 50.0);
+        assert_eq!(left.jaccard(&Intervals::default()), 0.0);
+    }
+
+    #[test]
+    fn test_fisher_exact() {
+        // compared against R's fisher.test(matrix(c(3, 1, 1, 3), nrow = 2))
+        let test = FisherTest::new([[3, 1], [1, 3]]);
+        assert!((test.right_p_value - 0.2428571).abs() < 1e-6);
+        assert!((test.left_p_value - 0.9857143).abs() < 1e-6);
+        assert!((test.two_tailed_p_value - 0.4857143).abs() < 1e-6);
+
+        // a larger table, compared against exact rational arithmetic
+        let test = FisherTest::new([[10, 30], [20, 5000]]);
+        assert!((test.right_p_value / 7.603619e-15 - 1.0).abs() < 1e-4);
+        assert!((test.two_tailed_p_value / 7.603619e-15 - 1.0).abs() < 1e-4);
+        assert!((test.left_p_value - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_permutation() {
+        let seqlens = seqlens! { "chr1" => 10_000 };
+        let left: IndexMap<_, _> = [("chr1".to_string(), vec![(100, 200), (5000, 5100)])].into();
+        let right = Intervals::from_ranges(left.clone());
+
+        let mut rng = StdRng::seed_from_u64(1);
+        let shuffled = shuffle_ranges(&left, &seqlens, &mut rng);
+        let widths: Vec<_> = shuffled["chr1"].iter().map(|(start, end)| end - start).collect();
+        assert_eq!(widths, vec![100, 100]);
+
+        let test = PermutationTest::new(&left, &right, &seqlens, 99, 1);
+        assert_eq!(test.observed, 200);
+        assert!(test.fold_enrichment > 10.0);
+        assert!(test.p_value < 0.05);
+    }
+
+    #[test]
+    fn test_shuffle_within_regions() {
+        let left: IndexMap<_, _> = [
+            ("chr1".to_string(), vec![(0, 10), (20, 60)]),
+            ("chr2".to_string(), vec![(0, 10)]),
+        ]
+        .into();
+        let regions: IndexMap<_, _> = [("chr1".to_string(), vec![(100, 120), (200, 230)])].into();
+        let mut rng = StdRng::seed_from_u64(1);
+        for _ in 0..20 {
+            let shuffled = shuffle_ranges_within(&left, &regions, &mut rng);
+            // chr2 has no regions
+            assert_eq!(shuffled.len(), 1);
+            let (first, second) = (shuffled["chr1"][0], shuffled["chr1"][1]);
+            assert_eq!(first.1 - first.0, 10);
+            assert!((100..=110).contains(&first.0) || (200..=220).contains(&first.0));
+            // truncated to the widest region
+            assert_eq!(second, (200, 230));
+        }
+    }
+
+    #[test]
+    fn test_monte_carlo() {
+        let seqlens = seqlens! { "chr1" => 10_000 };
+        let left: IndexMap<_, _> = [("chr1".to_string(), vec![(100, 200), (5000, 5100)])].into();
+        let right = Intervals::from_ranges(left.clone());
+        let test = MonteCarloTest::new(
+            &left,
+            &right,
+            &seqlens,
+            None,
+            OverlapStatistic::Jaccard,
+            99,
+            1,
+        );
+        assert_eq!(test.observed, 1.0);
+        assert!(test.z_score > 3.0);
+        assert!(test.p_value_greater < 0.05);
+        assert_eq!(test.p_value_less, 1.0);
+        assert_eq!(
+            test,
+            MonteCarloTest::new(&left, &right, &seqlens, None, test.statistic, 99, 1)
+        );
+
+        // the left range can only be shuffled onto itself
+        let left: IndexMap<_, _> = [("chr1".to_string(), vec![(100, 200)])].into();
+        let excluded =
+            Intervals::from_ranges(vec![("chr1".to_string(), vec![(0, 100), (200, 10_000)])]);
+        let test = MonteCarloTest::new(
+            &left,
+            &right,
+            &seqlens,
+            Some(&excluded),
+            OverlapStatistic::Overlap,
+            10,
+            1,
+        );
+        assert_eq!(test.observed, 100.0);
+        assert_eq!(test.null_mean, 100.0);
+        assert_eq!(test.p_value_greater, 1.0);
+
+        let mut report = Report::new();
+        test.add_to_report(&mut report);
+        assert_eq!(report.statistics()["p_value_greater"], 1.0);
+    }
+
+    #[test]
+    fn test_fisher_excluding() {
+        let seqlens = seqlens! { "chr1" => 1000 };
+        let left = Intervals::from_ranges(vec![("chr1".to_string(), vec![(0, 100)])]);
+        let right = Intervals::from_ranges(vec![("chr1".to_string(), vec![(50, 150)])]);
+        let gaps = Intervals::from_ranges(vec![("chr1".to_string(), vec![(0, 20), (500, 1000)])]);
+        let test = FisherTest::from_intervals_excluding(&left, &right, &seqlens, &gaps);
+        assert_eq!(test.table, [[50, 30], [50, 350]]);
+    }
+}
@@ -1,9 +1,10 @@
 //! The [`GRangesError`] `enum` definition and error messages.
 //!
-use crate::Position;
+use crate::{Position, PositionOffset};
 use genomap::GenomeMapError;
 use std::{
     num::{ParseFloatError, ParseIntError},
+    path::PathBuf,
     string::FromUtf8Error,
 };
 use thiserror::Error;
@@ -69,18 +70,40 @@ use thiserror::Error;
 //}
 
 // AICODE/NOTE: these are AI-generated from the above human-written ones.
+/// The [`GRangesError`] defines the standard set of errors that are passed to the
+/// user. Errors about a missing sequence, an invalid range, unsorted input, or a
+/// file carry their context (the sequence name, coordinates, or path) as named
+/// fields, so they can be matched on, and wrapped errors are available through
+/// [`std::error::Error::source()`].
+///
+/// New variants may be added in minor releases, so matches need a wildcard arm.
 #[derive(Debug, Error)]
+#[non_exhaustive]
 pub enum GRangesError {
     // IO related errors
     #[error("File reading error: {0}. Please check if the file exists and you have permission to read it.")]
     IOError(#[from] std::io::Error),
 
+    #[error("Could not open '{}': {source}. Please check if the file exists and you have permission to access it.", .path.display())]
+    FileError {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+
     #[error("The specified file '{0}' is empty.")]
     EmptyFile(String),
 
     #[error("File parsing error: {0}")]
     TsvParsingError(#[from] csv::Error),
 
+    #[error("Could not parse line {line} of {file}: {message}")]
+    ParseError {
+        file: String,
+        line: u64,
+        message: String,
+    },
+
     // File parsing related errors
     #[error("Could not determine the file type based on its extension. Ensure the file has a standard genomic data extension (.bed, .gff, etc.).")]
     CouldNotDetectRangesFiletype,
@@ -125,14 +148,32 @@ pub enum GRangesError {
     TooFewColumns,
 
     // Invalid genomic range errors
-    #[error("Invalid genomic range specified: start position ({0}) must be less than or equal to the end position ({1}).")]
-    InvalidGenomicRange(Position, Position),
+    #[error("Invalid genomic range specified{}: start position ({start}) must be less than or equal to the end position ({end}).", in_sequence(.seqname))]
+    InvalidGenomicRange {
+        seqname: Option<String>,
+        start: Position,
+        end: Position,
+    },
+
+    #[error("The specified genomic range [{start}, {end}] is invalid for a sequence{} of length {length}. Adjust the range to fit within the sequence length.", in_sequence(.seqname))]
+    InvalidGenomicRangeForSequence {
+        seqname: Option<String>,
+        start: Position,
+        end: Position,
+        length: Position,
+    },
 
-    #[error("The specified genomic range [{0}, {1}] is invalid for a sequence of length {2}. Adjust the range to fit within the sequence length.")]
-    InvalidGenomicRangeForSequence(Position, Position, Position),
+    #[error("Adjusting the genomic range [{start}, {end}]{} by {start_delta} (start) and {end_delta} (end) would invert it, as its start would be at or past its end.", in_sequence(.seqname))]
+    InvertedRange {
+        seqname: Option<String>,
+        start: Position,
+        end: Position,
+        start_delta: PositionOffset,
+        end_delta: PositionOffset,
+    },
 
-    #[error("The sequence name '{0}' is not found within the provided ranges container. Check the sequence names for typos or missing entries.")]
-    MissingSequence(String),
+    #[error("The sequence name '{seqname}' was not found. Check the sequence names for typos or missing entries.")]
+    MissingSequence { seqname: String },
 
     #[error("An error was encountered with the underlying genomap::GenomeMap: {0}")]
     GenomeMapError(#[from] GenomeMapError),
@@ -146,10 +187,6 @@ pub enum GRangesError {
     #[error("The supplied GRanges object and data container cannot be united into a new GRanges since they have differing lengths.")]
     IncompatableGRangesAndData,
 
-    // Sequences related errors
-    #[error("The sequence name '{0}' was not found in the dataset. Verify the sequence names and try again.")]
-    MissingSequenceName(String),
-
     // FASTA/noodles related errors
     #[error("An error occurred while converting bytes to a UTF-8 string. This often indicates invalid or corrupted data.")]
     FromUtf8Error(#[from] FromUtf8Error),
@@ -166,11 +203,205 @@ pub enum GRangesError {
     )]
     NoSuchOperation(String),
 
+    #[error("Could not read or write the binary .granges file: {0}")]
+    BinaryFormatError(String),
+
+    #[error("The output format of '{0}' requires granges to be compiled with the '{1}' feature.")]
+    OutputFormatRequiresFeature(String, String),
+
+    #[error("The input ranges are not sorted: the range starting at {seqname}:{start} comes after the range starting at {previous_seqname}:{previous_start}. Streaming operations require ranges sorted by sequence (in the order of the genome file) and then by start position; use --detect-sorted to join unsorted input in memory instead.")]
+    UnsortedInput {
+        seqname: String,
+        start: Position,
+        previous_seqname: String,
+        previous_start: Position,
+    },
+
+    #[error("Could not build the thread pool: {0}")]
+    ThreadPoolError(#[from] rayon::ThreadPoolBuildError),
+
+    #[error("An error occurred while reading bytes as a UTF-8 string: {0}. This often indicates invalid or corrupted data.")]
+    Utf8Error(#[from] std::str::Utf8Error),
+
+    #[error("The file '{0}' is gzip-compressed, but memory-mapped parsing requires an uncompressed file. Decompress the file first, or use the standard parsing iterators.")]
+    CompressedMmapInput(String),
+
+    #[error("Could not write the run report: {0}")]
+    ReportError(String),
+
     #[error("No operation was specified. See granges map --help.")]
     NoOperationSpecified,
 
+    #[error("Normalizing (--normalize) requires counts, so 'count' must be the only operation.")]
+    NormalizationRequiresCounts,
+
+    #[error("Validation found {0} problems with the input. Use --fix to repair or drop invalid records.")]
+    ValidationFailed(usize),
+
+    #[error("Invalid gene annotation line (GFF/GTF lines need nine columns, BED12 lines twelve):\n{0}")]
+    InvalidGeneAnnotation(String),
+
+    #[error("Strand-specific merging requires a strand ('+' or '-') in the sixth BED column, but {0} has none.")]
+    MissingStrand(String),
+
+    #[error("Summits require narrowPeak input, with a summit offset within each peak in the tenth column, but {0} has none.")]
+    MissingSummit(String),
+
+    #[error("The run-length encoded vectors have different lengths ({0} ≠ {1}).")]
+    RleLengthMismatch(Position, Position),
+
+    #[error("The window width and step must be greater than zero.")]
+    ZeroWindowSize,
+
+    #[error("The operation '{0}' does not produce a numeric value.")]
+    NonNumericOperation(String),
+
+    #[error("Invalid expression: {0}")]
+    ExpressionError(String),
+
+    #[error("{0} requires granges to be compiled with the '{1}' feature.")]
+    RequiresFeature(String, String),
+
+    #[error("Invalid color '{0}': expected an RGB color like '255,0,0'.")]
+    InvalidColor(String),
+
+    #[error("The missing value string could not be set to '{0}': it can only be set once, before any input is read or output written.")]
+    NaValueAlreadySet(String),
+
+    #[error("The input format could not be set to '{0}': it can only be set once, before any input is read.")]
+    InputFormatAlreadySet(String),
+
+    #[error("The region restriction could not be set: it can only be set once, before any input is read.")]
+    RestrictionAlreadySet,
+
+    #[error("The name '{0}' is shared by more than one right record (see --duplicates).")]
+    DuplicateJoinKey(String),
+
+    #[error("The output buffer size could not be set to {0} bytes: it can only be set once, before any output is written.")]
+    OutputBufferingAlreadySet(usize),
+
+    #[error("The input '{0}' was specified as {1}, but its header, extension, or first record indicate {2}.")]
+    InputFormatConflict(String, String, String),
+
+    #[error("Index {index} is out of bounds for a GRanges object with {len} ranges.")]
+    IndexOutOfBounds { index: usize, len: usize },
+
+    #[error("The data index {0} is too large for a compact range container, which stores 32-bit indices.")]
+    CompactIndexOverflow(usize),
+
+    // Random range related errors
+    #[error("Invalid random range widths: {0}")]
+    InvalidWidthDistribution(String),
+
+    #[error("Could not place a random range in {0} attempts: the ranges may be too wide for the genome's sequences, or the excluded regions may cover too much of it.")]
+    RandomPlacementFailed(usize),
+
+    #[error("Could not place a shuffled range matching the features of the original range in {0} attempts: the tolerances may be too small, or too little of the genome may match.")]
+    MatchedPlacementFailed(usize),
+
+    #[error("Invalid block bootstrap: {0}")]
+    InvalidBootstrap(String),
+
+    #[error("The random seed could not be set to {0}: it can only be set once, before any random numbers are generated.")]
+    SeedAlreadySet(u64),
+
+    // Column schema related errors
+    #[error("The {column} column has {found} values, but the {expected_column} column has {expected}; all columns must be the same length.")]
+    ColumnLengthMismatch {
+        column: String,
+        found: usize,
+        expected_column: String,
+        expected: usize,
+    },
+
+    #[error("The column '{0}' does not exist in the data container's column schema.")]
+    NoSuchColumn(String),
+
+    #[error("The column '{column}' could not be read as type {expected_type}; found value {found_value}.")]
+    ColumnTypeMismatch {
+        column: String,
+        expected_type: String,
+        found_value: String,
+    },
+
+    // polars related errors
+    #[cfg(feature = "polars")]
+    #[error("An error was encountered by polars: {0}")]
+    PolarsError(#[from] polars::error::PolarsError),
+
+    #[cfg(feature = "polars")]
+    #[error("The DataFrame has a missing chrom, start, or end value in row {0}.")]
+    DataFrameMissingRangeValue(usize),
+
+    #[error("The DataFrame has an invalid {column} value ({value}) in row {row}: start and end must be non-negative integers no larger than the maximum position, and strand must be '+', '-', or missing.")]
+    DataFrameInvalidRangeValue {
+        row: usize,
+        column: String,
+        value: String,
+    },
+
     // ndarray related errors
     #[cfg(feature = "ndarray")]
     #[error("Invalid shape encountered by ndarray: {0}")]
     InvalidNdarrayShape(#[from] ndarray::ShapeError),
+
+    // Lua related errors
+    #[cfg(feature = "lua")]
+    #[error("An error was encountered running a Lua script: {0}")]
+    LuaError(#[from] mlua::Error),
+
+    #[cfg(feature = "lua")]
+    #[error("The Lua script '{0}' does not define the function '{1}'.")]
+    LuaMissingFunction(String, String),
+
+    #[cfg(feature = "lua")]
+    #[error("The Lua aggregate() function returned a {0}, rather than a number, string, or nil.")]
+    LuaInvalidReturn(String),
+
+    // remote input related errors
+    #[cfg(feature = "remote")]
+    #[error("The refget request '{0}' failed: {1}")]
+    RefgetError(String, String),
+}
+
+impl GRangesError {
+    /// A [`GRangesError::MissingSequence`] error for the sequence `seqname`.
+    pub fn missing_sequence(seqname: impl Into<String>) -> Self {
+        GRangesError::MissingSequence {
+            seqname: seqname.into(),
+        }
+    }
+
+    /// Add the sequence name to an invalid range error that does not have one, e.g.
+    /// one returned by [`try_range()`](crate::ranges::try_range), which only knows
+    /// the coordinates. Other errors are returned unchanged.
+    pub fn with_seqname(mut self, name: &str) -> Self {
+        if let GRangesError::InvalidGenomicRange { seqname, .. }
+        | GRangesError::InvalidGenomicRangeForSequence { seqname, .. }
+        | GRangesError::InvertedRange { seqname, .. } = &mut self
+        {
+            seqname.get_or_insert_with(|| name.to_string());
+        }
+        self
+    }
+
+    /// The sequence name this error is about, if any.
+    pub fn seqname(&self) -> Option<&str> {
+        match self {
+            GRangesError::MissingSequence { seqname }
+            | GRangesError::UnsortedInput { seqname, .. } => Some(seqname),
+            GRangesError::InvalidGenomicRange { seqname, .. }
+            | GRangesError::InvalidGenomicRangeForSequence { seqname, .. }
+            | GRangesError::InvertedRange { seqname, .. } => seqname.as_deref(),
+            _ => None,
+        }
+    }
+}
+
+/// Format an optional sequence name for an error message.
+fn in_sequence(seqname: &Option<String>) -> String {
+    seqname
+        .as_ref()
+        .map(|seqname| format!(" on sequence '{}'", seqname))
+        .unwrap_or_default()
 }
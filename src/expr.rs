@@ -0,0 +1,652 @@
+//! A small expression language for computing new columns from, or filtering,
+//! the rows of BED-like files (like `awk`, but range-aware).
+//!
+//! # Syntax
+//!
+//! Expressions are made of:
+//!
+//!  - Columns, by their BED name (`chrom`, `start`, `end`, `name`, `score`,
+//!    `strand`, `thickStart`, `thickEnd`, `itemRgb`, `blockCount`, `blockSizes`,
+//!    and `blockStarts`), or by their 1-based position, as in `awk` (e.g. `$7`).
+//!  - Number literals (`1000`, `0.5`, `1e-3`), string literals (`"chr1"` or
+//!    `'chr1'`), and `true` and `false`.
+//!  - The range functions `width()` and `mid()`, the math functions `abs`,
+//!    `sqrt`, `exp`, `ln`, `log2`, `log10`, `floor`, `ceil`, and `round` (of one
+//!    argument), and `min` and `max` (of two arguments).
+//!  - The operators, from lowest to highest precedence, `||`, `&&`, `==` and
+//!    `!=`, `<`, `<=`, `>`, and `>=`, `+` and `-`, `*`, `/`, and `%`, and the
+//!    unary `!` and `-`. Parentheses group subexpressions.
+//!
+//! Column values are text, which is read as a number when used in arithmetic, or
+//! compared to a number. Two text values are compared as numbers if both are
+//! numbers (so `start < end` works as expected), and otherwise as text (e.g.
+//! `strand == "+"`). Missing values (`.` or the string set with `--na-value`, or
+//! columns past the end of a row) propagate through arithmetic, and comparisons
+//! with them are false.
+//!
+//! # Example
+//!
+//! ```
+//! use granges::expr::{Expr, Value};
+//!
+//! let row = ["chr1", "100", "2100", "peak1", "7.5"];
+//! let filter = Expr::parse("width() > 1000 && score > 5").unwrap();
+//! assert!(filter.matches(&row).unwrap());
+//!
+//! let column = Expr::parse("log2(score + 0.5)").unwrap();
+//! assert_eq!(column.eval(&row).unwrap(), Value::Number(3.0));
+//! ```
+
+use std::{borrow::Cow, cmp::Ordering, fmt};
+
+use crate::{
+    error::GRangesError,
+    io::tsv::{is_na_value, na_value},
+};
+
+/// The names of the standard BED columns, in order.
+const BED_COLUMNS: &[&str] = &[
+    "chrom",
+    "start",
+    "end",
+    "name",
+    "score",
+    "strand",
+    "thickStart",
+    "thickEnd",
+    "itemRgb",
+    "blockCount",
+    "blockSizes",
+    "blockStarts",
+];
+
+/// The value of an evaluated expression.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value<'a> {
+    Number(f64),
+    Text(Cow<'a, str>),
+    Bool(bool),
+    Missing,
+}
+
+impl Value<'_> {
+    /// Whether this value counts as true when filtering: `true`, non-zero numbers,
+    /// and non-empty text.
+    pub fn is_truthy(&self) -> bool {
+        match self {
+            Value::Bool(value) => *value,
+            Value::Number(value) => *value != 0.0,
+            Value::Text(text) => !text.is_empty(),
+            Value::Missing => false,
+        }
+    }
+
+    /// This value as a number, or `None` if it is missing. Text that is not
+    /// a number raises a [`GRangesError::ExpressionError`].
+    fn number(&self) -> Result<Option<f64>, GRangesError> {
+        match self {
+            Value::Number(value) => Ok(Some(*value)),
+            Value::Bool(value) => Ok(Some(if *value { 1.0 } else { 0.0 })),
+            Value::Text(text) => text
+                .parse()
+                .map(Some)
+                .map_err(|_| GRangesError::ExpressionError(format!("'{}' is not a number", text))),
+            Value::Missing => Ok(None),
+        }
+    }
+}
+
+impl fmt::Display for Value<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Number(value) => write!(f, "{}", value),
+            Value::Text(text) => write!(f, "{}", text),
+            Value::Bool(value) => write!(f, "{}", value),
+            Value::Missing => write!(f, "{}", na_value()),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum UnaryOp {
+    Not,
+    Negate,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum BinaryOp {
+    Or,
+    And,
+    Equal,
+    NotEqual,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Remainder,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Function {
+    Width,
+    Mid,
+    Abs,
+    Sqrt,
+    Exp,
+    Ln,
+    Log2,
+    Log10,
+    Floor,
+    Ceil,
+    Round,
+    Min,
+    Max,
+}
+
+impl Function {
+    fn from_name(name: &str) -> Option<Self> {
+        let function = match name {
+            "width" => Function::Width,
+            "mid" => Function::Mid,
+            "abs" => Function::Abs,
+            "sqrt" => Function::Sqrt,
+            "exp" => Function::Exp,
+            "ln" => Function::Ln,
+            "log2" => Function::Log2,
+            "log10" => Function::Log10,
+            "floor" => Function::Floor,
+            "ceil" => Function::Ceil,
+            "round" => Function::Round,
+            "min" => Function::Min,
+            "max" => Function::Max,
+            _ => return None,
+        };
+        Some(function)
+    }
+
+    fn arity(&self) -> usize {
+        match self {
+            Function::Width | Function::Mid => 0,
+            Function::Min | Function::Max => 2,
+            _ => 1,
+        }
+    }
+}
+
+/// A parsed expression, which can be evaluated on each row of a file.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Expr(Node);
+
+#[derive(Clone, Debug, PartialEq)]
+enum Node {
+    Number(f64),
+    Text(String),
+    Bool(bool),
+    Column(usize),
+    Unary(UnaryOp, Box<Node>),
+    Binary(BinaryOp, Box<Node>, Box<Node>),
+    Call(Function, Vec<Node>),
+}
+
+impl Expr {
+    /// Parse an expression (see the [module documentation](self) for the syntax).
+    pub fn parse(expression: &str) -> Result<Self, GRangesError> {
+        let tokens = tokenize(expression)?;
+        let mut parser = Parser {
+            tokens,
+            position: 0,
+        };
+        let node = parser.or()?;
+        match parser.peek() {
+            None => Ok(Expr(node)),
+            Some(token) => Err(GRangesError::ExpressionError(format!(
+                "unexpected {} in '{}'",
+                token, expression
+            ))),
+        }
+    }
+
+    /// Evaluate this expression on a row, i.e. the row's tab-separated columns.
+    pub fn eval<'a>(&self, row: &[&'a str]) -> Result<Value<'a>, GRangesError> {
+        eval(&self.0, row)
+    }
+
+    /// Whether this expression is true for a row (see [`Value::is_truthy()`]).
+    pub fn matches(&self, row: &[&str]) -> Result<bool, GRangesError> {
+        Ok(self.eval(row)?.is_truthy())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Number(f64),
+    Text(String),
+    Ident(String),
+    Column(usize),
+    Op(&'static str),
+    LeftParen,
+    RightParen,
+    Comma,
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Number(value) => write!(f, "number {}", value),
+            Token::Text(text) => write!(f, "string \"{}\"", text),
+            Token::Ident(name) => write!(f, "'{}'", name),
+            Token::Column(index) => write!(f, "'${}'", index + 1),
+            Token::Op(op) => write!(f, "'{}'", op),
+            Token::LeftParen => write!(f, "'('"),
+            Token::RightParen => write!(f, "')'"),
+            Token::Comma => write!(f, "','"),
+        }
+    }
+}
+
+/// Operators, with two-character operators first so they are matched greedily.
+const OPERATORS: &[&str] = &[
+    "||", "&&", "==", "!=", "<=", ">=", "<", ">", "!", "+", "-", "*", "/", "%",
+];
+
+fn tokenize(expression: &str) -> Result<Vec<Token>, GRangesError> {
+    let error = GRangesError::ExpressionError;
+    let mut tokens = Vec::new();
+    let mut rest = expression.trim_start();
+    while let Some(c) = rest.chars().next() {
+        if c.is_ascii_digit() || (c == '.' && rest[1..].starts_with(|c: char| c.is_ascii_digit())) {
+            let mut end = rest
+                .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+                .unwrap_or(rest.len());
+            // exponents, e.g. 1e-3
+            if rest[end..].starts_with(['e', 'E']) {
+                let exponent = rest[end + 1..]
+                    .strip_prefix(['+', '-'])
+                    .unwrap_or(&rest[end + 1..]);
+                let digits = exponent
+                    .find(|c: char| !c.is_ascii_digit())
+                    .unwrap_or(exponent.len());
+                if digits > 0 {
+                    end = rest.len() - exponent.len() + digits;
+                }
+            }
+            let number = rest[..end]
+                .parse()
+                .map_err(|_| error(format!("invalid number '{}'", &rest[..end])))?;
+            tokens.push(Token::Number(number));
+            rest = &rest[end..];
+        } else if c == '"' || c == '\'' {
+            let end = rest[1..]
+                .find(c)
+                .ok_or_else(|| error(format!("unterminated string in '{}'", expression)))?;
+            tokens.push(Token::Text(rest[1..end + 1].to_string()));
+            rest = &rest[end + 2..];
+        } else if c == '$' {
+            let end = rest[1..]
+                .find(|c: char| !c.is_ascii_digit())
+                .map_or(rest.len(), |end| end + 1);
+            let index: usize = rest[1..end]
+                .parse()
+                .ok()
+                .filter(|&index| index > 0)
+                .ok_or_else(|| error(format!("invalid column '{}'", &rest[..end])))?;
+            tokens.push(Token::Column(index - 1));
+            rest = &rest[end..];
+        } else if c.is_ascii_alphabetic() || c == '_' {
+            let end = rest
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                .unwrap_or(rest.len());
+            tokens.push(Token::Ident(rest[..end].to_string()));
+            rest = &rest[end..];
+        } else if let Some(op) = OPERATORS.iter().find(|op| rest.starts_with(**op)) {
+            tokens.push(Token::Op(*op));
+            rest = &rest[op.len()..];
+        } else {
+            let token = match c {
+                '(' => Token::LeftParen,
+                ')' => Token::RightParen,
+                ',' => Token::Comma,
+                _ => return Err(error(format!("unexpected '{}' in '{}'", c, expression))),
+            };
+            tokens.push(token);
+            rest = &rest[1..];
+        }
+        rest = rest.trim_start();
+    }
+    Ok(tokens)
+}
+
+/// A recursive descent parser, with a method for each level of precedence.
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Result<Token, GRangesError> {
+        let token = self.tokens.get(self.position).cloned().ok_or_else(|| {
+            GRangesError::ExpressionError("unexpected end of expression".to_string())
+        })?;
+        self.position += 1;
+        Ok(token)
+    }
+
+    fn expect(&mut self, expected: Token) -> Result<(), GRangesError> {
+        let token = self.next()?;
+        if token != expected {
+            return Err(GRangesError::ExpressionError(format!(
+                "expected {}, found {}",
+                expected, token
+            )));
+        }
+        Ok(())
+    }
+
+    /// Parse a left-associative sequence of operands joined by any of `ops`.
+    fn binary(
+        &mut self,
+        ops: &[(&str, BinaryOp)],
+        operand: fn(&mut Self) -> Result<Node, GRangesError>,
+    ) -> Result<Node, GRangesError> {
+        let mut node = operand(self)?;
+        while let Some(Token::Op(op)) = self.peek() {
+            let Some(&(_, binary_op)) = ops.iter().find(|(symbol, _)| symbol == op) else {
+                break;
+            };
+            self.position += 1;
+            node = Node::Binary(binary_op, Box::new(node), Box::new(operand(self)?));
+        }
+        Ok(node)
+    }
+
+    fn or(&mut self) -> Result<Node, GRangesError> {
+        self.binary(&[("||", BinaryOp::Or)], Self::and)
+    }
+
+    fn and(&mut self) -> Result<Node, GRangesError> {
+        self.binary(&[("&&", BinaryOp::And)], Self::equality)
+    }
+
+    fn equality(&mut self) -> Result<Node, GRangesError> {
+        let ops = [("==", BinaryOp::Equal), ("!=", BinaryOp::NotEqual)];
+        self.binary(&ops, Self::comparison)
+    }
+
+    fn comparison(&mut self) -> Result<Node, GRangesError> {
+        let ops = [
+            ("<", BinaryOp::Less),
+            ("<=", BinaryOp::LessEqual),
+            (">", BinaryOp::Greater),
+            (">=", BinaryOp::GreaterEqual),
+        ];
+        self.binary(&ops, Self::additive)
+    }
+
+    fn additive(&mut self) -> Result<Node, GRangesError> {
+        let ops = [("+", BinaryOp::Add), ("-", BinaryOp::Subtract)];
+        self.binary(&ops, Self::multiplicative)
+    }
+
+    fn multiplicative(&mut self) -> Result<Node, GRangesError> {
+        let ops = [
+            ("*", BinaryOp::Multiply),
+            ("/", BinaryOp::Divide),
+            ("%", BinaryOp::Remainder),
+        ];
+        self.binary(&ops, Self::unary)
+    }
+
+    fn unary(&mut self) -> Result<Node, GRangesError> {
+        let op = match self.peek() {
+            Some(Token::Op("!")) => UnaryOp::Not,
+            Some(Token::Op("-")) => UnaryOp::Negate,
+            _ => return self.primary(),
+        };
+        self.position += 1;
+        Ok(Node::Unary(op, Box::new(self.unary()?)))
+    }
+
+    fn primary(&mut self) -> Result<Node, GRangesError> {
+        match self.next()? {
+            Token::Number(value) => Ok(Node::Number(value)),
+            Token::Text(text) => Ok(Node::Text(text)),
+            Token::Column(index) => Ok(Node::Column(index)),
+            Token::LeftParen => {
+                let node = self.or()?;
+                self.expect(Token::RightParen)?;
+                Ok(node)
+            }
+            Token::Ident(name) if self.peek() == Some(&Token::LeftParen) => {
+                let function = Function::from_name(&name).ok_or_else(|| {
+                    GRangesError::ExpressionError(format!("no such function '{}'", name))
+                })?;
+                self.position += 1;
+                let mut args = Vec::new();
+                if self.peek() != Some(&Token::RightParen) {
+                    args.push(self.or()?);
+                    while self.peek() == Some(&Token::Comma) {
+                        self.position += 1;
+                        args.push(self.or()?);
+                    }
+                }
+                self.expect(Token::RightParen)?;
+                if args.len() != function.arity() {
+                    return Err(GRangesError::ExpressionError(format!(
+                        "{}() takes {} arguments, but {} were given",
+                        name,
+                        function.arity(),
+                        args.len()
+                    )));
+                }
+                Ok(Node::Call(function, args))
+            }
+            Token::Ident(name) => match name.as_str() {
+                "true" => Ok(Node::Bool(true)),
+                "false" => Ok(Node::Bool(false)),
+                _ => BED_COLUMNS
+                    .iter()
+                    .position(|column| *column == name)
+                    .map(Node::Column)
+                    .ok_or_else(|| {
+                        GRangesError::ExpressionError(format!("no such column '{}'", name))
+                    }),
+            },
+            token => Err(GRangesError::ExpressionError(format!(
+                "unexpected {}",
+                token
+            ))),
+        }
+    }
+}
+
+fn column<'a>(row: &[&'a str], index: usize) -> Value<'a> {
+    match row.get(index) {
+        Some(value) if !is_na_value(value) => Value::Text(Cow::Borrowed(value)),
+        _ => Value::Missing,
+    }
+}
+
+/// Apply a numeric function to a value, propagating missing values.
+fn map_number<'a>(value: Value<'a>, func: fn(f64) -> f64) -> Result<Value<'a>, GRangesError> {
+    Ok(value
+        .number()?
+        .map_or(Value::Missing, |value| Value::Number(func(value))))
+}
+
+/// Compare two values: as text if both are text and at least one is not a number,
+/// and otherwise as numbers.
+fn compare(left: &Value, right: &Value) -> Result<Option<Ordering>, GRangesError> {
+    match (left, right) {
+        (Value::Missing, _) | (_, Value::Missing) => Ok(None),
+        (Value::Text(left), Value::Text(right))
+            if left.parse::<f64>().is_err() || right.parse::<f64>().is_err() =>
+        {
+            Ok(Some(left.cmp(right)))
+        }
+        _ => {
+            let (Some(left), Some(right)) = (left.number()?, right.number()?) else {
+                return Ok(None);
+            };
+            Ok(left.partial_cmp(&right))
+        }
+    }
+}
+
+fn eval<'a>(node: &Node, row: &[&'a str]) -> Result<Value<'a>, GRangesError> {
+    let value = match node {
+        Node::Number(value) => Value::Number(*value),
+        Node::Text(text) => Value::Text(Cow::Owned(text.clone())),
+        Node::Bool(value) => Value::Bool(*value),
+        Node::Column(index) => column(row, *index),
+        Node::Unary(UnaryOp::Not, operand) => Value::Bool(!eval(operand, row)?.is_truthy()),
+        Node::Unary(UnaryOp::Negate, operand) => map_number(eval(operand, row)?, |x| -x)?,
+        // short-circuiting logical operators
+        Node::Binary(BinaryOp::And, left, right) => {
+            Value::Bool(eval(left, row)?.is_truthy() && eval(right, row)?.is_truthy())
+        }
+        Node::Binary(BinaryOp::Or, left, right) => {
+            Value::Bool(eval(left, row)?.is_truthy() || eval(right, row)?.is_truthy())
+        }
+        Node::Binary(op, left, right) => {
+            let (left, right) = (eval(left, row)?, eval(right, row)?);
+            let ordering = |accept: fn(Ordering) -> bool| -> Result<Value<'a>, GRangesError> {
+                Ok(Value::Bool(compare(&left, &right)?.is_some_and(accept)))
+            };
+            match op {
+                BinaryOp::Equal => ordering(Ordering::is_eq)?,
+                BinaryOp::NotEqual => ordering(Ordering::is_ne)?,
+                BinaryOp::Less => ordering(Ordering::is_lt)?,
+                BinaryOp::LessEqual => ordering(Ordering::is_le)?,
+                BinaryOp::Greater => ordering(Ordering::is_gt)?,
+                BinaryOp::GreaterEqual => ordering(Ordering::is_ge)?,
+                _ => {
+                    let (Some(x), Some(y)) = (left.number()?, right.number()?) else {
+                        return Ok(Value::Missing);
+                    };
+                    Value::Number(match op {
+                        BinaryOp::Add => x + y,
+                        BinaryOp::Subtract => x - y,
+                        BinaryOp::Multiply => x * y,
+                        BinaryOp::Divide => x / y,
+                        BinaryOp::Remainder => x % y,
+                        _ => unreachable!(),
+                    })
+                }
+            }
+        }
+        Node::Call(function, args) => match function {
+            Function::Width | Function::Mid => {
+                let (Some(start), Some(end)) = (column(row, 1).number()?, column(row, 2).number()?)
+                else {
+                    return Ok(Value::Missing);
+                };
+                match function {
+                    Function::Width => Value::Number(end - start),
+                    _ => Value::Number(((start + end) / 2.0).floor()),
+                }
+            }
+            Function::Min | Function::Max => {
+                let (Some(x), Some(y)) = (
+                    eval(&args[0], row)?.number()?,
+                    eval(&args[1], row)?.number()?,
+                ) else {
+                    return Ok(Value::Missing);
+                };
+                Value::Number(if *function == Function::Min {
+                    x.min(y)
+                } else {
+                    x.max(y)
+                })
+            }
+            _ => {
+                let func: fn(f64) -> f64 = match function {
+                    Function::Abs => f64::abs,
+                    Function::Sqrt => f64::sqrt,
+                    Function::Exp => f64::exp,
+                    Function::Ln => f64::ln,
+                    Function::Log2 => f64::log2,
+                    Function::Log10 => f64::log10,
+                    Function::Floor => f64::floor,
+                    Function::Ceil => f64::ceil,
+                    _ => f64::round,
+                };
+                map_number(eval(&args[0], row)?, func)?
+            }
+        },
+    };
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ROW: &[&str] = &["chr1", "100", "2100", "peak1", "7.5", "-"];
+
+    fn eval_str(expression: &str) -> Value<'static> {
+        Expr::parse(expression).unwrap().eval(ROW).unwrap()
+    }
+
+    #[test]
+    fn test_arithmetic() {
+        assert_eq!(eval_str("1 + 2 * 3"), Value::Number(7.0));
+        assert_eq!(eval_str("(1 + 2) * 3"), Value::Number(9.0));
+        assert_eq!(eval_str("-start + end"), Value::Number(2000.0));
+        assert_eq!(eval_str("10 % 4 - 1e1"), Value::Number(-8.0));
+        assert_eq!(eval_str("mid()"), Value::Number(1100.0));
+        assert_eq!(eval_str("max(score, $2 / 100)"), Value::Number(7.5));
+    }
+
+    #[test]
+    fn test_logic() {
+        assert_eq!(eval_str("width() > 1000 && score > 5"), Value::Bool(true));
+        assert_eq!(
+            eval_str("strand == \"+\" || chrom == 'chr2'"),
+            Value::Bool(false)
+        );
+        assert_eq!(eval_str("!(name != \"peak1\")"), Value::Bool(true));
+        // numeric text is compared as numbers, and other text as text
+        assert_eq!(eval_str("start < end"), Value::Bool(true));
+        assert_eq!(eval_str("\"9\" > \"10\""), Value::Bool(false));
+        assert_eq!(eval_str("\"b\" > \"a\""), Value::Bool(true));
+        assert_eq!(eval_str("$2 < 20"), Value::Bool(false));
+    }
+
+    #[test]
+    fn test_missing() {
+        let row = ["chr1", "0", "10", "a", "."];
+        let expr = Expr::parse("score + 1").unwrap();
+        assert_eq!(expr.eval(&row).unwrap(), Value::Missing);
+        assert!(!Expr::parse("score >= 0").unwrap().matches(&row).unwrap());
+        assert_eq!(
+            Expr::parse("$10").unwrap().eval(&row).unwrap(),
+            Value::Missing
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        for expression in [
+            "score >",
+            "(1 + 2",
+            "foo > 1",
+            "bar(1)",
+            "log2(1, 2)",
+            "1 # 2",
+            "\"open",
+            "$0",
+        ] {
+            assert!(Expr::parse(expression).is_err(), "{}", expression);
+        }
+        let expr = Expr::parse("name + 1").unwrap();
+        assert!(expr.eval(ROW).is_err());
+    }
+}
@@ -0,0 +1,174 @@
+//! Assembly gaps: the unsequenced regions of a genome, e.g. centromeres, telomeres,
+//! and the runs of `N`s between contigs.
+//!
+//! Random ranges (e.g. the null model of a permutation test) placed in gaps can't
+//! overlap anything, which biases tests of overlap towards enrichment. So gaps can
+//! be excluded, e.g. with `--gaps` in `granges complement`, `fisher`, and `random`.
+//!
+//! Gaps are read with [`read_gaps()`], either from a gap track (see
+//! [`read_gap_track()`]), or derived from the runs of `N`s in a FASTA file (see
+//! [`gaps_from_fasta()`]), and are stored as [`Intervals`].
+
+use indexmap::IndexMap;
+use noodles::fasta::reader;
+use std::{io::BufRead, path::PathBuf};
+
+use crate::{
+    enrichment::Intervals,
+    io::{
+        file::is_fasta_path,
+        parsers::utils::{is_header_line, parse_column},
+        InputStream,
+    },
+    ranges::operations::complement_intervals,
+    GRangesError, Position,
+};
+
+/// The runs of `N` (or `n`) bases in a sequence, as `(start, end)` ranges.
+pub fn n_runs(sequence: &[u8]) -> Vec<(Position, Position)> {
+    let mut runs = Vec::new();
+    let mut run_start = None;
+    for (i, base) in sequence.iter().enumerate() {
+        match (base.eq_ignore_ascii_case(&b'N'), run_start) {
+            (true, None) => run_start = Some(i),
+            (false, Some(start)) => {
+                runs.push((start as Position, i as Position));
+                run_start = None;
+            }
+            _ => {}
+        }
+    }
+    if let Some(start) = run_start {
+        runs.push((start as Position, sequence.len() as Position));
+    }
+    runs
+}
+
+/// The gaps of a genome, as the runs of `N`s in each sequence of a (possibly
+/// gzip-compressed) FASTA file.
+pub fn gaps_from_fasta(filepath: impl Into<PathBuf>) -> Result<Intervals, GRangesError> {
+    let mut reader = reader::Builder.build_from_path(filepath.into())?;
+    let mut gaps = Vec::new();
+    // each sequence is read in turn, so only one is in memory at a time
+    for result in reader.records() {
+        let record = result?;
+        let runs = n_runs(record.sequence().as_ref());
+        if !runs.is_empty() {
+            let name = String::from_utf8(record.definition().name().to_vec())?;
+            gaps.push((name, runs));
+        }
+    }
+    Ok(Intervals::from_ranges(gaps))
+}
+
+/// The sequence name, start, and end of a line of a gap track: the first three
+/// columns, or those after the leading `bin` column of a UCSC `gap` table dump
+/// (which has nine columns).
+fn parse_gap(line: &str) -> Result<(String, Position, Position), GRangesError> {
+    let mut columns: Vec<&str> = line.split('\t').collect();
+    if columns.len() == 9 && columns[0].parse::<u32>().is_ok() {
+        columns.remove(0);
+    }
+    if columns.len() < 3 {
+        return Err(GRangesError::BedTooFewColumns(
+            columns.len(),
+            3,
+            line.to_string(),
+        ));
+    }
+    let start: Position = parse_column(columns[1], line)?;
+    let end: Position = parse_column(columns[2], line)?;
+    if start > end {
+        return Err(GRangesError::InvalidGenomicRange {
+            seqname: Some(columns[0].to_string()),
+            start,
+            end,
+        });
+    }
+    Ok((columns[0].to_string(), start, end))
+}
+
+/// Read the gaps of a gap track: a BED-like file of gaps, or a dump of the UCSC
+/// Genome Browser's `gap` table (e.g. `gap.txt.gz`, with or without its leading
+/// `bin` column).
+pub fn read_gap_track(filepath: impl Into<PathBuf>) -> Result<Intervals, GRangesError> {
+    let filepath = filepath.into();
+    let reader = InputStream::new(&filepath).reader()?;
+    let mut gaps: IndexMap<String, Vec<(Position, Position)>> = IndexMap::new();
+    for (i, line) in reader.lines().enumerate() {
+        let line = line?;
+        let line = line.trim_end();
+        if is_header_line(line) {
+            continue;
+        }
+        let (seqname, start, end) = parse_gap(line).map_err(|error| GRangesError::ParseError {
+            file: filepath.display().to_string(),
+            line: i as u64 + 1,
+            message: error.to_string(),
+        })?;
+        gaps.entry(seqname).or_default().push((start, end));
+    }
+    Ok(Intervals::from_ranges(gaps))
+}
+
+/// Read the gaps of a genome from a FASTA file (see [`gaps_from_fasta()`]), if
+/// the path has a FASTA extension (see [`is_fasta_path()`]), or otherwise from a
+/// gap track (see [`read_gap_track()`]).
+pub fn read_gaps(filepath: impl Into<PathBuf>) -> Result<Intervals, GRangesError> {
+    let filepath = filepath.into();
+    if is_fasta_path(&filepath) {
+        gaps_from_fasta(filepath)
+    } else {
+        read_gap_track(filepath)
+    }
+}
+
+/// The regions of each sequence (with lengths `seqlens`) outside of the `gaps`.
+pub fn ungapped_regions(
+    seqlens: &IndexMap<String, Position>,
+    gaps: &Intervals,
+) -> IndexMap<String, Vec<(Position, Position)>> {
+    seqlens
+        .iter()
+        .map(|(seqname, &length)| {
+            let regions = complement_intervals(gaps.on(seqname).to_vec(), &[(0, length)]);
+            (seqname.clone(), regions)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::seqlens;
+
+    #[test]
+    fn test_n_runs() {
+        assert_eq!(n_runs(b"NNACGTnnnA"), vec![(0, 2), (6, 9)]);
+        assert_eq!(n_runs(b"ACGTN"), vec![(4, 5)]);
+        assert_eq!(n_runs(b"ACGT"), vec![]);
+    }
+
+    #[test]
+    fn test_parse_gap() {
+        let gap = parse_gap("chr1\t0\t10000").unwrap();
+        assert_eq!(gap, ("chr1".to_string(), 0, 10000));
+        // a UCSC gap table line, with its bin column
+        let line = "585\tchr1\t0\t10000\t1\tN\t10000\ttelomere\tno";
+        assert_eq!(parse_gap(line).unwrap(), gap);
+        assert!(parse_gap("chr1\t10").is_err());
+    }
+
+    #[test]
+    fn test_gaps() {
+        let from_fasta = read_gaps("tests_data/golden/gapped.fa").unwrap();
+        let from_track = read_gaps("tests_data/golden/gap.txt").unwrap();
+        assert_eq!(from_fasta, from_track);
+        assert_eq!(from_fasta.on("chr1"), &[(0, 10), (40, 50)]);
+
+        let seqlens = seqlens! { "chr1" => 100, "chr2" => 50 };
+        let regions = ungapped_regions(&seqlens, &from_fasta);
+        assert_eq!(regions["chr1"], vec![(10, 40), (50, 100)]);
+        assert_eq!(regions["chr2"], vec![(0, 45)]);
+    }
+}
@@ -0,0 +1,509 @@
+//! The [`Genome`] type: the sequences (e.g. chromosomes) of an assembly, in order,
+//! with their lengths, optional aliases, and an optional assembly name.
+//!
+//! A *genome file* is a tab-delimited file of sequence names and their lengths, like
+//! those used by `bedtools`. [`Genome::from_file()`] (and [`read_seqlens()`]) also read
+//! genome files with a header line naming their columns, in any order, e.g.
+//!
+//! ```text
+//! #assembly=GRCh38
+//! length	name	aliases
+//! 248956422	chr1	1,NC_000001.11
+//! 242193529	chr2	2,NC_000002.12
+//! ```
+//!
+//! The name column can be named `name`, `chrom`, `seqname`, or `sequence`; the length
+//! column `length`, `size`, or `len`; and the optional aliases column (of
+//! comma-separated alternative names) `aliases` or `alias`. Without a header, the
+//! columns are the name, the length, and optionally the aliases. Lines starting with
+//! `#` are comments, except `#assembly=<name>`, which sets the assembly name.
+//!
+//! The order of a [`Genome`]'s sequences is always the order of the genome file.
+//! [`GRanges`] objects (and their output) order sequences deterministically in
+//! natural chromosome order (`chr1`, `chr2`, ..., `chr10`), whatever this order is.
+//!
+//! # Example
+//!
+//! ```
+//! use granges::prelude::*;
+//!
+//! let genome = Genome::from_bytes("#assembly=toy\nname\tlength\taliases\nchr1\t100\t1\n")
+//!     .unwrap();
+//! assert_eq!(genome.assembly(), Some("toy"));
+//! assert_eq!(genome.length("1"), Some(100));
+//! assert_eq!(genome.resolve("1"), Some("chr1"));
+//!
+//! // a Genome dereferences to its sequence lengths
+//! let gr = GRangesEmpty::new_vec(&genome);
+//! assert_eq!(gr.seqnames(), vec!["chr1".to_string()]);
+//! ```
+//!
+//! # Interned sequence names
+//!
+//! A [`Genome`] is also a symbol table of its sequence names: each sequence's
+//! [`SeqId`] is its index in the genome's order. BED3 and BED5 files can be parsed
+//! with [`Genome::intern_bed3()`] and [`Genome::intern_bed5()`], which intern each
+//! line's sequence name as it is parsed, into [`InternedRecord`]s that store a
+//! four-byte [`SeqId`] rather than a [`String`]. No [`String`] is allocated per
+//! record, which saves memory (and time) when keeping many records, and makes
+//! comparing (and sorting, in genome order) them cheap. The names are recovered with
+//! [`Genome::seqname()`]. Records from other parsers can be interned after parsing
+//! with [`Genome::intern()`] (or [`Genome::intern_iter()`]).
+//!
+//! ```
+//! use granges::prelude::*;
+//!
+//! let genome = Genome::from_bytes("chr1\t100\nchr2\t50\nchr4\t50\n").unwrap();
+//! let iter = genome.intern_bed3("tests_data/example.bed").unwrap();
+//! let mut records = iter.collect::<Result<Vec<_>, _>>().unwrap();
+//! records.sort();
+//! assert_eq!(genome.seqname(records[0].seq_id), Some("chr1"));
+//! ```
+//!
+//! [`GRanges`]: crate::granges::GRanges
+//! [`read_seqlens()`]: crate::io::file::read_seqlens
+
+use indexmap::IndexMap;
+use std::{
+    io::{BufRead, BufReader},
+    ops::Deref,
+    path::PathBuf,
+};
+
+use crate::{
+    error::GRangesError,
+    io::{
+        file::{bytes_reader, InputStream},
+        parsers::bed::{
+            fast::{parse_bed3_line_interned, parse_bed5_line_interned, FastBedIterator},
+            Bed5Addition,
+        },
+    },
+    ranges::{GenomicRangeRecord, GenomicRangeRecordEmpty},
+    traits::{GenericRange, GenomicRecord},
+    Position,
+};
+
+/// The ID of a sequence: its index in a [`Genome`]'s order (see the [module
+/// documentation](self#interned-sequence-names)).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SeqId(pub u32);
+
+/// A record whose sequence name is interned as a [`SeqId`] of a [`Genome`] (see
+/// [`Genome::intern()`]). Records are ordered by sequence (in the genome's order),
+/// start, and end position.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct InternedRecord<U> {
+    pub seq_id: SeqId,
+    pub start: Position,
+    pub end: Position,
+    pub data: U,
+}
+
+impl<U> GenericRange for InternedRecord<U> {
+    fn start(&self) -> Position {
+        self.start
+    }
+    fn end(&self) -> Position {
+        self.end
+    }
+    fn index(&self) -> Option<usize> {
+        None
+    }
+}
+
+/// Parsed records that can be interned with [`Genome::intern()`], keeping their data.
+pub trait InternableRecord: GenomicRecord {
+    type Data;
+    /// Consume the record, returning its data.
+    fn into_data(self) -> Self::Data;
+}
+
+impl<U: Clone> InternableRecord for GenomicRangeRecord<U> {
+    type Data = U;
+    fn into_data(self) -> U {
+        self.data
+    }
+}
+
+impl InternableRecord for GenomicRangeRecordEmpty {
+    type Data = ();
+    fn into_data(self) {}
+}
+
+/// The sequences of a genome assembly, in order, with their lengths (see the [module
+/// documentation](self)).
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Genome {
+    /// The sequence names and their lengths, in order.
+    seqlens: IndexMap<String, Position>,
+    /// Alternative names, each with the sequence name it refers to.
+    aliases: IndexMap<String, String>,
+    /// The name of the assembly, e.g. `GRCh38`.
+    assembly: Option<String>,
+}
+
+impl Genome {
+    /// Create a new [`Genome`] from sequence names and their lengths, in order.
+    pub fn new(seqlens: IndexMap<String, Position>) -> Self {
+        Self {
+            seqlens,
+            ..Default::default()
+        }
+    }
+
+    /// Read a genome file (possibly gzip-compressed, or remote).
+    pub fn from_file(filepath: impl Into<PathBuf>) -> Result<Self, GRangesError> {
+        let input_file = InputStream::new(filepath);
+        Self::parse(input_file.reader()?)
+    }
+
+    /// Read the contents of a genome file from memory, e.g. for use without a
+    /// filesystem (such as in WebAssembly).
+    pub fn from_bytes(bytes: impl Into<Vec<u8>>) -> Result<Self, GRangesError> {
+        Self::parse(BufReader::new(bytes_reader(bytes)))
+    }
+
+    fn parse(reader: impl BufRead) -> Result<Self, GRangesError> {
+        let mut genome = Genome::default();
+        // the positions of the name, length, and aliases columns
+        let mut columns: Option<(usize, usize, Option<usize>)> = None;
+        for (i, result) in reader.lines().enumerate() {
+            let line = result?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            if let Some(comment) = line.strip_prefix('#') {
+                if let Some(assembly) = comment.trim().strip_prefix("assembly=") {
+                    genome.assembly = Some(assembly.trim().to_string());
+                }
+                continue;
+            }
+            let fields: Vec<&str> = line.split('\t').collect();
+            let (name, length, aliases) = match columns {
+                Some(columns) => columns,
+                None => {
+                    let header = parse_header(&fields);
+                    let parsed = header.unwrap_or((0, 1, Some(2)));
+                    columns = Some(parsed);
+                    if header.is_some() {
+                        continue;
+                    }
+                    parsed
+                }
+            };
+
+            let field = |column: usize| {
+                fields.get(column).copied().ok_or_else(|| {
+                    GRangesError::InvalidGenomeFile(format!(
+                        "line {} has too few columns: '{}'",
+                        i + 1,
+                        line
+                    ))
+                })
+            };
+            let seqname = field(name)?;
+            let length: Position = field(length)?.trim().parse().map_err(|_| {
+                GRangesError::InvalidGenomeFile(format!(
+                    "line {} has an invalid length: '{}'",
+                    i + 1,
+                    line
+                ))
+            })?;
+            genome.push(seqname, length)?;
+            let aliases = aliases.and_then(|column| fields.get(column));
+            for alias in aliases.into_iter().flat_map(|aliases| aliases.split(',')) {
+                if !alias.trim().is_empty() {
+                    genome.add_alias(alias.trim(), seqname)?;
+                }
+            }
+        }
+        Ok(genome)
+    }
+
+    /// Add the sequence `seqname`, with its length, after the existing sequences.
+    pub fn push(&mut self, seqname: &str, length: Position) -> Result<(), GRangesError> {
+        if self.seqlens.contains_key(seqname) || self.aliases.contains_key(seqname) {
+            return Err(GRangesError::InvalidGenomeFile(format!(
+                "sequence '{}' is duplicated",
+                seqname
+            )));
+        }
+        self.seqlens.insert(seqname.to_string(), length);
+        Ok(())
+    }
+
+    /// Add `alias` as an alternative name of the sequence `seqname`.
+    pub fn add_alias(&mut self, alias: &str, seqname: &str) -> Result<(), GRangesError> {
+        if !self.seqlens.contains_key(seqname) {
+            return Err(GRangesError::missing_sequence(seqname));
+        }
+        if alias == seqname {
+            return Ok(());
+        }
+        if self.seqlens.contains_key(alias) || self.aliases.contains_key(alias) {
+            return Err(GRangesError::InvalidGenomeFile(format!(
+                "alias '{}' is duplicated",
+                alias
+            )));
+        }
+        self.aliases.insert(alias.to_string(), seqname.to_string());
+        Ok(())
+    }
+
+    /// Set the name of the assembly.
+    pub fn with_assembly(mut self, assembly: impl Into<String>) -> Self {
+        self.assembly = Some(assembly.into());
+        self
+    }
+
+    /// The name of the assembly, if known.
+    pub fn assembly(&self) -> Option<&str> {
+        self.assembly.as_deref()
+    }
+
+    /// The sequence names and their lengths, in order.
+    pub fn seqlens(&self) -> &IndexMap<String, Position> {
+        &self.seqlens
+    }
+
+    /// Consume this [`Genome`], returning its sequence names and their lengths.
+    pub fn into_seqlens(self) -> IndexMap<String, Position> {
+        self.seqlens
+    }
+
+    /// The aliases, each with the sequence name it refers to.
+    pub fn aliases(&self) -> &IndexMap<String, String> {
+        &self.aliases
+    }
+
+    /// Resolve a sequence name or alias to its sequence name, or `None` if it is
+    /// neither.
+    pub fn resolve<'a>(&'a self, name: &'a str) -> Option<&'a str> {
+        if self.seqlens.contains_key(name) {
+            return Some(name);
+        }
+        self.aliases.get(name).map(|seqname| seqname.as_str())
+    }
+
+    /// The length of the sequence with this name or alias.
+    pub fn length(&self, name: &str) -> Option<Position> {
+        self.resolve(name)
+            .and_then(|seqname| self.seqlens.get(seqname))
+            .copied()
+    }
+
+    /// The [`SeqId`] of the sequence with this name or alias, or `None` if there is no
+    /// such sequence.
+    pub fn seq_id(&self, name: &str) -> Option<SeqId> {
+        let index = self.seqlens.get_index_of(self.resolve(name)?)?;
+        let seq_id = u32::try_from(index).expect("more than u32::MAX sequences");
+        Some(SeqId(seq_id))
+    }
+
+    /// The name of the sequence with this [`SeqId`], if it is in this genome.
+    pub fn seqname(&self, seq_id: SeqId) -> Option<&str> {
+        self.seqlens
+            .get_index(seq_id.0 as usize)
+            .map(|(seqname, _)| seqname.as_str())
+    }
+
+    /// Intern a parsed record's sequence name, returning an [`InternedRecord`] with
+    /// its [`SeqId`] and data. This is a [`GRangesError::MissingSequence`] error if the
+    /// sequence is not in this genome. Since the record was already parsed with a
+    /// [`String`] sequence name, prefer [`Genome::intern_bed3()`] and
+    /// [`Genome::intern_bed5()`] where possible.
+    pub fn intern<R: InternableRecord>(
+        &self,
+        record: R,
+    ) -> Result<InternedRecord<R::Data>, GRangesError> {
+        let seq_id = self
+            .seq_id(record.seqname())
+            .ok_or_else(|| GRangesError::missing_sequence(record.seqname()))?;
+        Ok(InternedRecord {
+            seq_id,
+            start: record.start(),
+            end: record.end(),
+            data: record.into_data(),
+        })
+    }
+
+    /// Intern the records of a parsing iterator as they are parsed (see
+    /// [`Genome::intern()`]).
+    pub fn intern_iter<'a, I, R>(
+        &'a self,
+        iter: I,
+    ) -> impl Iterator<Item = Result<InternedRecord<R::Data>, GRangesError>> + 'a
+    where
+        I: IntoIterator<Item = Result<R, GRangesError>>,
+        I::IntoIter: 'a,
+        R: InternableRecord + 'a,
+    {
+        iter.into_iter()
+            .map(move |result| result.and_then(|record| self.intern(record)))
+    }
+
+    /// Parse a BED3 file (which may be gzip-compressed), interning each line's
+    /// sequence name as it is parsed (see the [module
+    /// documentation](self#interned-sequence-names)). Sequences not in this genome
+    /// are parse errors.
+    pub fn intern_bed3(
+        &self,
+        filepath: impl Into<PathBuf>,
+    ) -> Result<impl Iterator<Item = Result<InternedRecord<()>, GRangesError>> + '_, GRangesError>
+    {
+        let iter = FastBedIterator::with_parser(filepath, |line: &[u8]| {
+            parse_bed3_line_interned(line, self)
+        })?;
+        Ok(iter.filter_map(Result::transpose))
+    }
+
+    /// Parse a BED5 file (which may be gzip-compressed), interning each line's
+    /// sequence name as it is parsed (see [`Genome::intern_bed3()`]).
+    pub fn intern_bed5(
+        &self,
+        filepath: impl Into<PathBuf>,
+    ) -> Result<
+        impl Iterator<Item = Result<InternedRecord<Bed5Addition>, GRangesError>> + '_,
+        GRangesError,
+    > {
+        let iter = FastBedIterator::with_parser(filepath, |line: &[u8]| {
+            parse_bed5_line_interned(line, self)
+        })?;
+        Ok(iter.filter_map(Result::transpose))
+    }
+}
+
+/// The positions of the name, length, and (optional) aliases columns, if `fields`
+/// is a header line.
+fn parse_header(fields: &[&str]) -> Option<(usize, usize, Option<usize>)> {
+    let position = |names: &[&str]| {
+        fields
+            .iter()
+            .position(|field| names.contains(&field.trim().to_lowercase().as_str()))
+    };
+    let name = position(&["name", "chrom", "seqname", "sequence"])?;
+    let length = position(&["length", "size", "len"])?;
+    Some((name, length, position(&["aliases", "alias"])))
+}
+
+impl Deref for Genome {
+    type Target = IndexMap<String, Position>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.seqlens
+    }
+}
+
+impl From<IndexMap<String, Position>> for Genome {
+    fn from(seqlens: IndexMap<String, Position>) -> Self {
+        Self::new(seqlens)
+    }
+}
+
+impl From<Genome> for IndexMap<String, Position> {
+    fn from(genome: Genome) -> Self {
+        genome.seqlens
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::seqlens;
+
+    #[test]
+    fn test_genome_plain() {
+        let genome = Genome::from_file("tests_data/hg38_seqlens.tsv").unwrap();
+        assert_eq!(genome.assembly(), None);
+        assert_eq!(genome.keys().next().unwrap(), "chr1");
+        assert_eq!(genome.length("chr1"), Some(248956422));
+
+        let genome = Genome::from_bytes("chr2\t10\nchr1\t20\t1,NC_1\n").unwrap();
+        assert_eq!(genome.seqlens(), &seqlens! { "chr2" => 10, "chr1" => 20 });
+        assert_eq!(genome.resolve("NC_1"), Some("chr1"));
+        assert_eq!(genome.length("chr3"), None);
+    }
+
+    #[test]
+    fn test_genome_named_columns() {
+        let genome =
+            Genome::from_bytes("# a comment\n#assembly=toy\nsize\tchrom\n10\tchr2\n20\tchr1\n")
+                .unwrap();
+        assert_eq!(genome.assembly(), Some("toy"));
+        assert_eq!(genome.seqlens(), &seqlens! { "chr2" => 10, "chr1" => 20 });
+        assert!(genome.aliases().is_empty());
+    }
+
+    #[test]
+    fn test_intern() {
+        let genome = Genome::from_bytes("chr2\t10\nchr1\t20\t1\n").unwrap();
+        assert_eq!(genome.seq_id("chr2"), Some(SeqId(0)));
+        assert_eq!(genome.seq_id("1"), Some(SeqId(1)));
+        assert_eq!(genome.seq_id("chr3"), None);
+        assert_eq!(genome.seqname(SeqId(1)), Some("chr1"));
+        assert_eq!(genome.seqname(SeqId(2)), None);
+
+        let record = GenomicRangeRecord::new("1".to_string(), 0, 5, 1.5);
+        let interned = genome.intern(record).unwrap();
+        assert_eq!(
+            (interned.seq_id, interned.start, interned.data),
+            (SeqId(1), 0, 1.5)
+        );
+
+        let records =
+            [("chr1", 0, 5), ("chr2", 3, 4), ("chr3", 0, 1)].map(|(seqname, start, end)| {
+                let record = GenomicRangeRecordEmpty::new(seqname.to_string(), start, end);
+                Ok::<_, GRangesError>(record)
+            });
+        let mut interned = genome.intern_iter(records);
+        let chr1 = interned.next().unwrap().unwrap();
+        let chr2 = interned.next().unwrap().unwrap();
+        assert_eq!((chr1.seq_id, chr2.seq_id), (SeqId(1), SeqId(0)));
+        // ordered by the genome's sequence order
+        assert!(chr2 < chr1);
+        assert!(matches!(
+            interned.next().unwrap(),
+            Err(GRangesError::MissingSequence { .. })
+        ));
+    }
+
+    #[test]
+    fn test_intern_bed() {
+        let genome = Genome::from_bytes("chr2\t100\nchr1\t100\n").unwrap();
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        std::io::Write::write_all(&mut file, b"chr1\t0\t10\ta\t1\nchr2\t5\t8\tb\t.\n").unwrap();
+
+        let records = genome
+            .intern_bed5(file.path())
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!((records[0].seq_id, records[1].seq_id), (SeqId(1), SeqId(0)));
+        assert_eq!(records[1].data.score, None);
+
+        let genome = Genome::from_bytes("chr1\t100\n").unwrap();
+        let mut iter = genome.intern_bed3(file.path()).unwrap();
+        assert!(iter.next().unwrap().is_ok());
+        assert!(matches!(
+            iter.next().unwrap(),
+            Err(GRangesError::ParseError { line: 2, .. })
+        ));
+    }
+
+    #[test]
+    fn test_genome_invalid() {
+        for contents in [
+            "chr1\t10\nchr1\t20\n",
+            "chr1\t10\tchr2\nchr2\t20\n",
+            "chr1\tten\n",
+            "chr1\n",
+        ] {
+            assert!(matches!(
+                Genome::from_bytes(contents),
+                Err(GRangesError::InvalidGenomeFile(_))
+            ));
+        }
+    }
+}
@@ -37,32 +37,50 @@
 //! [`BedlikeIterator`]: crate::io::parsers::BedlikeIterator
 //! [`GRanges::into_coitrees`]: crate::granges::GRanges::into_coitrees
 
-use std::{collections::HashSet, hash::Hash, path::PathBuf};
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Write as _,
+    hash::Hash,
+    ops::ControlFlow,
+    path::PathBuf,
+};
 
+use clap::ValueEnum;
 use genomap::GenomeMap;
 use indexmap::IndexMap;
-use serde::Serialize;
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+use serde::{Deserialize, Serialize};
 
 use crate::{
     commands::build_tsv_writer_with_config,
+    data::columns::{column_position, datum_into, FromDatumType},
     ensure_eq,
-    io::tsv::TsvConfig,
+    io::{
+        tsv::{TsvConfig, BED_TSV},
+        Bed5Addition,
+    },
     iterators::{GRangesIterator, GRangesRecordIterator},
     join::{
         CombinedJoinData, CombinedJoinDataBothEmpty, CombinedJoinDataLeftEmpty,
         CombinedJoinDataRightEmpty, JoinData, JoinDataBothEmpty, JoinDataLeftEmpty,
-        JoinDataRightEmpty, LeftGroupedJoin,
+        JoinDataRightEmpty, JoinFilter, LeftGroupedJoin, RangeTuple,
     },
+    parallel::{par_count_overlaps, par_has_overlaps, par_left_grouped_joins},
     prelude::GRangesError,
     ranges::{
+        check_genomic_range,
         coitrees::{COITrees, COITreesEmpty, COITreesIndexed},
+        lapper::{Lapper, LapperEmpty, LapperIndexed},
+        nclist::{NCList, NCListEmpty, NCListIndexed},
+        soa::{SoaRanges, SoaRangesEmpty, SoaRangesIndexed},
         vec::{VecRanges, VecRangesEmpty, VecRangesIndexed},
-        GenomicRangeRecord, GenomicRangeRecordEmpty, RangeEmpty, RangeIndexed,
+        GenomicRangeRecord, GenomicRangeRecordBorrowed, GenomicRangeRecordEmpty,
+        GenomicRangeRecordEmptyBorrowed, RangeEmpty, RangeIndexed,
     },
     traits::{
         AdjustableGenericRange, AsGRangesRef, GenericRange, GenericRangeOperations,
         GenomicRangesTsvSerialize, IndexedDataContainer, IterableRangeContainer, LeftOverlaps,
-        RangeContainer,
+        GenomicRecord, OverlapQuery, RangeContainer, Selection, TsvFormat,
     },
     unique_id::UniqueIdentifier,
     Position, PositionOffset,
@@ -282,6 +300,91 @@ impl<'a, R: IterableRangeContainer> GenomicRangesTsvSerialize<'a, R> for GRanges
     }
 }
 
+impl<C, T> GRanges<C, T>
+where
+    C: IterableRangeContainer,
+{
+    /// Format the ranges of this [`GRanges`] object as BED3 lines (ignoring any data).
+    pub fn to_bed3(&self) -> String {
+        let seqnames = &self.ranges.sorted_keys;
+        let mut bed = String::new();
+        for range in self.iter_ranges() {
+            let seqname = range.seqname(seqnames);
+            let _ = writeln!(bed, "{}\t{}\t{}", seqname, range.start, range.end);
+        }
+        bed
+    }
+}
+
+impl<C, T> GRanges<C, T>
+where
+    C: IterableRangeContainer,
+    T: IndexedDataContainer,
+{
+    /// Format this [`GRanges`] object as BED-like lines, with each range's data
+    /// element as the extra columns. Missing values and floats are formatted with
+    /// the `no_value_string` and `float_precision` of `config`, which is also used
+    /// for any metadata and header lines. Use [`BEDTOOLS_TSV`] to format floats like
+    /// bedtools.
+    ///
+    /// [`BEDTOOLS_TSV`]: crate::io::tsv::BEDTOOLS_TSV
+    pub fn to_bedlike<'a>(&'a self, config: &TsvConfig) -> Result<String, GRangesError>
+    where
+        <T as IndexedDataContainer>::Item<'a>: TsvFormat,
+    {
+        let data = self.data.as_ref().ok_or(GRangesError::NoDataContainer)?;
+        let seqnames = &self.ranges.sorted_keys;
+        let mut bed = String::new();
+        if let Some(metadata_rows) = &config.metadata {
+            for metadata_row in metadata_rows {
+                let _ = writeln!(bed, "#{}", metadata_row);
+            }
+        }
+        if let Some(headers) = &config.headers {
+            let _ = writeln!(bed, "{}", headers.join("\t"));
+        }
+
+        let mut columns = Vec::new();
+        for range in self.iter_ranges() {
+            columns.clear();
+            columns.push(range.seqname(seqnames).clone());
+            columns.push(range.start.to_string());
+            columns.push(range.end.to_string());
+            // unwrap should be safe, since this GRanges has a data container
+            let value = data.get_value(range.index().unwrap());
+            value.write_columns(&mut columns, config);
+            let _ = writeln!(bed, "{}", columns.join("\t"));
+        }
+        Ok(bed)
+    }
+
+    /// Format this [`GRanges`] object as BED-like lines, with the standard BED
+    /// configuration ([`BED_TSV`]). See [`GRanges::to_bedlike()`].
+    ///
+    /// [`BED_TSV`]: crate::io::tsv::BED_TSV
+    pub fn to_bed_string<'a>(&'a self) -> Result<String, GRangesError>
+    where
+        <T as IndexedDataContainer>::Item<'a>: TsvFormat,
+    {
+        self.to_bedlike(&BED_TSV)
+    }
+}
+
+impl<C> GRanges<C, Vec<Bed5Addition>>
+where
+    C: IterableRangeContainer,
+{
+    /// Format this [`GRanges`] object as BED6 lines, with each range's name and
+    /// score, and a missing strand (`.`, since BED5 data has no strand).
+    pub fn to_bed6(&self, config: &TsvConfig) -> Result<String, GRangesError> {
+        let mut bed = String::new();
+        for line in self.to_bedlike(config)?.lines() {
+            let _ = writeln!(bed, "{}\t.", line);
+        }
+        Ok(bed)
+    }
+}
+
 impl<R: GenericRange, T> GRanges<VecRanges<R>, T> {
     /// Create a new [`GRanges`] object, with vector storage for ranges and data.
     ///
@@ -351,7 +454,7 @@ impl<K: Clone + std::cmp::Eq + Hash> GRanges<VecRangesIndexed, UniqueIdentifier<
         let range_container = self
             .ranges
             .get_mut(seqname)
-            .ok_or(GRangesError::MissingSequence(seqname.to_string()))?;
+            .ok_or(GRangesError::missing_sequence(seqname))?;
         range_container.push_range(range);
         Ok(())
     }
@@ -367,6 +470,70 @@ impl<R: AdjustableGenericRange, T> GRanges<VecRanges<R>, T> {
     }
 }
 
+impl<U> GRanges<VecRangesIndexed, Vec<U>> {
+    /// Adjust all the ranges in place, by adding `start_delta` to their starts and
+    /// `end_delta` to their ends (see [`GRanges::clamp_to_genome()`] for how ranges
+    /// extending past the sequence ends are handled). Ranges are re-sorted, and the
+    /// data container is kept aligned with the remaining ranges.
+    pub fn adjust_ranges_in_place(
+        &mut self,
+        start_delta: PositionOffset,
+        end_delta: PositionOffset,
+    ) {
+        self.ranges.values_mut().for_each(|ranges| {
+            ranges.adjust_ranges(start_delta, end_delta);
+            ranges.sort();
+        });
+        self.drop_unused_data();
+    }
+
+    /// Truncate ranges to their sequence's bounds, dropping (with their data) any
+    /// ranges that are entirely past the sequence end.
+    pub fn clamp_to_genome(&mut self) {
+        self.adjust_ranges_in_place(0, 0);
+    }
+
+    /// Drop the data elements of any dropped ranges, re-indexing the remaining ranges
+    /// so that the data container is in genome order.
+    fn drop_unused_data(&mut self) {
+        let Some(data) = self.data.take() else {
+            return;
+        };
+        let mut data: Vec<Option<U>> = data.into_iter().map(Some).collect();
+        let mut kept_data = Vec::with_capacity(self.len());
+        for ranges in self.ranges.values_mut() {
+            for range in ranges.ranges.iter_mut() {
+                let element = data[range.index].take();
+                kept_data.push(element.expect("Internal error: please report"));
+                range.index = kept_data.len() - 1;
+            }
+        }
+        self.data = Some(kept_data);
+    }
+}
+
+impl GRangesEmpty<VecRangesEmpty> {
+    /// Adjust all the ranges in place, by adding `start_delta` to their starts and
+    /// `end_delta` to their ends, and re-sort them (see
+    /// [`GRanges::adjust_ranges_in_place()`]).
+    pub fn adjust_ranges_in_place(
+        &mut self,
+        start_delta: PositionOffset,
+        end_delta: PositionOffset,
+    ) {
+        self.0.ranges.values_mut().for_each(|ranges| {
+            ranges.adjust_ranges(start_delta, end_delta);
+            ranges.sort();
+        });
+    }
+
+    /// Truncate ranges to their sequence's bounds, dropping any ranges that are
+    /// entirely past the sequence end.
+    pub fn clamp_to_genome(&mut self) {
+        self.adjust_ranges_in_place(0, 0);
+    }
+}
+
 impl<C: IterableRangeContainer> GRangesEmpty<C>
 where
     C: IterableRangeContainer<RangeType = RangeEmpty>,
@@ -522,6 +689,39 @@ where
     }
 }
 
+impl<C, U> GRanges<C, Vec<U>>
+where
+    C: IterableRangeContainer<RangeType = RangeIndexed>,
+    U: Selection,
+{
+    /// Extract the data column `name` as a [`Vec<Option<T>>`], in the order
+    /// ranges are iterated over (i.e. by sequence, then by range order). Missing
+    /// values are `None`.
+    ///
+    /// This returns [`GRangesError::NoSuchColumn`] if the column is not in the data
+    /// type's schema (see [`Selection`]), and [`GRangesError::ColumnTypeMismatch`]
+    /// if its values cannot be converted to `T`.
+    pub fn column<T: FromDatumType>(&self, name: &str) -> Result<Vec<Option<T>>, GRangesError> {
+        if column_position(U::schema(), name).is_none() {
+            return Err(GRangesError::NoSuchColumn(name.to_string()));
+        }
+        let data = self.data().ok_or(GRangesError::NoDataContainer)?;
+        let mut values = Vec::with_capacity(self.len());
+        for ranges in self.ranges.values() {
+            for range in ranges.iter_ranges() {
+                let row = data.get(range.index).expect(
+                    "Invalid index in GRanges::column(). This indicates invalid data indices (developer error).",
+                );
+                let datum = row
+                    .select_by_name(name)
+                    .ok_or_else(|| GRangesError::NoSuchColumn(name.to_string()))?;
+                values.push(datum_into(name, datum)?);
+            }
+        }
+        Ok(values)
+    }
+}
+
 impl<T> GRanges<VecRangesIndexed, T>
 where
     VecRangesIndexed: IterableRangeContainer,
@@ -748,7 +948,7 @@ impl<U> GRanges<VecRangesIndexed, Vec<U>> {
         let range_container = self
             .ranges
             .get_mut(seqname)
-            .ok_or(GRangesError::MissingSequence(seqname.to_string()))?;
+            .ok_or(GRangesError::missing_sequence(seqname))?;
         range_container.push_range(range);
         Ok(())
     }
@@ -804,7 +1004,7 @@ impl<'a, DL, DR> GRanges<VecRangesIndexed, JoinData<'a, DL, DR>> {
         let range_container = self
             .ranges
             .get_mut(seqname)
-            .ok_or(GRangesError::MissingSequence(seqname.to_string()))?;
+            .ok_or(GRangesError::missing_sequence(seqname))?;
         range_container.push_range(range);
         Ok(())
     }
@@ -836,24 +1036,27 @@ impl<'a, DL, DR> GRanges<VecRangesIndexed, JoinData<'a, DL, DR>> {
 //}
 
 /// [`GRanges::left_overlaps()`] for the left with data, right with data case.
-impl<'a, DL: 'a, DR: 'a> LeftOverlaps<'a, GRanges<COITreesIndexed, DR>>
+impl<'a, CR: 'a, DL: 'a, DR: 'a> LeftOverlaps<'a, GRanges<CR, DR>>
     for GRanges<VecRangesIndexed, DL>
 where
+    CR: OverlapQuery + Sync,
     DL: IndexedDataContainer + 'a,
     DR: IndexedDataContainer + 'a,
 {
     type Output = GRanges<VecRanges<RangeIndexed>, JoinData<'a, DL, DR>>;
 
     /// Conduct a left overlap join, consuming self and returning a new
-    /// [`GRanges<VecRangesIndexed, JoinData>`].
+    /// [`GRanges<VecRangesIndexed, JoinData>`], joining the right ranges whose
+    /// overlap passes `filter`.
     ///
     /// The [`JoinData`] container contains the owned left data container and has
     /// a reference to the right data container, as as well as a [`Vec<LeftGroupedJoin>`]
     /// that contains information about each overlap between a left and zero or more right
     /// ranges.
-    fn left_overlaps(
+    fn left_overlaps_with(
         mut self,
-        right: &'a GRanges<COITreesIndexed, DR>,
+        right: &'a GRanges<CR, DR>,
+        filter: &JoinFilter,
     ) -> Result<Self::Output, GRangesError> {
         let mut gr: GRanges<VecRangesIndexed, JoinData<'a, DL, DR>> =
             GRanges::new_vec(&self.seqlens());
@@ -862,17 +1065,11 @@ where
         let right_data = right.data.as_ref().ok_or(GRangesError::NoDataContainer)?;
         gr.data = Some(JoinData::new(left_data, right_data));
 
-        for (seqname, left_ranges) in self.ranges.iter() {
-            for left_range in left_ranges.iter_ranges() {
-                // Left join: every left range gets a JoinData.
-                let mut join_data = LeftGroupedJoin::new(&left_range);
-                if let Some(right_ranges) = right.ranges.get(seqname) {
-                    right_ranges.query(left_range.start(), left_range.end(), |right_range| {
-                        // NOTE: right_range is a coitrees::IntervalNode.
-                        join_data.add_right(right_range);
-                    });
-                }
-                gr.push_range_with_join(seqname, left_range.start, left_range.end, join_data)?;
+        // Left join: every left range gets a JoinData.
+        for (seqname, joins) in par_left_grouped_joins(&self.ranges, &right.ranges, filter) {
+            for join_data in joins {
+                let (start, end) = (join_data.left.start(), join_data.left.end());
+                gr.push_range_with_join(seqname, start, end, join_data)?;
             }
         }
         Ok(gr)
@@ -880,22 +1077,25 @@ where
 }
 
 /// [`GRanges::left_overlaps()`] for the left with data, right empty case.
-impl<'a, DL: 'a> LeftOverlaps<'a, GRangesEmpty<COITreesEmpty>> for GRanges<VecRangesIndexed, DL>
+impl<'a, CR: 'a, DL: 'a> LeftOverlaps<'a, GRangesEmpty<CR>> for GRanges<VecRangesIndexed, DL>
 where
+    CR: OverlapQuery + Sync,
     DL: IndexedDataContainer + 'a,
 {
     type Output = GRanges<VecRanges<RangeIndexed>, JoinDataRightEmpty<DL>>;
 
     /// Conduct a left overlap join, consuming self and returning a new
-    /// [`GRanges<VecRangesIndexed, JoinDataRightEmpty>`].
+    /// [`GRanges<VecRangesIndexed, JoinDataRightEmpty>`], joining the right ranges whose
+    /// overlap passes `filter`.
     ///
     /// The [`JoinData`] container contains the left data container and has
     /// a reference to the right data container, as as well as a [`Vec<LeftGroupedJoin>`]
     /// that contains information about each overlap between a left and zero or more right
     /// ranges.
-    fn left_overlaps(
+    fn left_overlaps_with(
         mut self,
-        right: &'a GRangesEmpty<COITreesEmpty>,
+        right: &'a GRangesEmpty<CR>,
+        filter: &JoinFilter,
     ) -> Result<Self::Output, GRangesError> {
         // this is a temporary GRanges object; we just use it to build up results
         let mut gr: GRanges<VecRangesIndexed, JoinData<DL, ()>> = GRanges::new_vec(&self.seqlens());
@@ -903,17 +1103,11 @@ where
         let left_data = self.take_data()?;
         gr.data = Some(JoinData::new(left_data, &()));
 
-        for (seqname, left_ranges) in self.ranges.iter() {
-            for left_range in left_ranges.iter_ranges() {
-                // Left join: every left range gets a JoinData.
-                let mut join_data = LeftGroupedJoin::new(&left_range);
-                if let Some(right_ranges) = right.0.ranges.get(seqname) {
-                    right_ranges.query(left_range.start(), left_range.end(), |right_range| {
-                        // NOTE: right_range is a coitrees::IntervalNode.
-                        join_data.add_right(right_range);
-                    });
-                }
-                gr.push_range_with_join(seqname, left_range.start, left_range.end, join_data)?;
+        // Left join: every left range gets a JoinData.
+        for (seqname, joins) in par_left_grouped_joins(&self.ranges, &right.0.ranges, filter) {
+            for join_data in joins {
+                let (start, end) = (join_data.left.start(), join_data.left.end());
+                gr.push_range_with_join(seqname, start, end, join_data)?;
             }
         }
 
@@ -932,22 +1126,25 @@ where
 }
 
 /// [`GRanges::left_overlaps()`] for the left empty, right with data case.
-impl<'a, DR: 'a> LeftOverlaps<'a, GRanges<COITreesIndexed, DR>> for GRangesEmpty<VecRangesEmpty>
+impl<'a, CR: 'a, DR: 'a> LeftOverlaps<'a, GRanges<CR, DR>> for GRangesEmpty<VecRangesEmpty>
 where
+    CR: OverlapQuery + Sync,
     DR: IndexedDataContainer + 'a,
 {
     type Output = GRanges<VecRanges<RangeIndexed>, JoinDataLeftEmpty<'a, DR>>;
 
     /// Conduct a left overlap join, consuming self and returning a new
-    /// [`GRanges<VecRangesIndexed, JoinDataLeftEmpty>`].
+    /// [`GRanges<VecRangesIndexed, JoinDataLeftEmpty>`], joining the right ranges whose
+    /// overlap passes `filter`.
     ///
     /// The [`JoinDataLeftEmpty`] contains no left data, and a reference to the
     /// right data container, as as well as a [`Vec<LeftGroupedJoin>`]
     /// that contains information about each overlap between a left and zero or more right
     /// ranges.
-    fn left_overlaps(
+    fn left_overlaps_with(
         self,
-        right: &'a GRanges<COITreesIndexed, DR>,
+        right: &'a GRanges<CR, DR>,
+        filter: &JoinFilter,
     ) -> Result<Self::Output, GRangesError> {
         let mut gr: GRanges<VecRangesIndexed, JoinData<(), DR>> =
             GRanges::new_vec(&self.0.seqlens());
@@ -955,17 +1152,11 @@ where
         let right_data = right.data.as_ref().ok_or(GRangesError::NoDataContainer)?;
         gr.data = Some(JoinData::new((), right_data));
 
-        for (seqname, left_ranges) in self.0.ranges.iter() {
-            for left_range in left_ranges.iter_ranges() {
-                // Left join: every left range gets a JoinData.
-                let mut join_data = LeftGroupedJoin::new(&left_range);
-                if let Some(right_ranges) = right.ranges.get(seqname) {
-                    right_ranges.query(left_range.start(), left_range.end(), |right_range| {
-                        // NOTE: right_range is a coitrees::IntervalNode.
-                        join_data.add_right(right_range);
-                    });
-                }
-                gr.push_range_with_join(seqname, left_range.start(), left_range.end(), join_data)?;
+        // Left join: every left range gets a JoinData.
+        for (seqname, joins) in par_left_grouped_joins(&self.0.ranges, &right.ranges, filter) {
+            for join_data in joins {
+                let (start, end) = (join_data.left.start(), join_data.left.end());
+                gr.push_range_with_join(seqname, start, end, join_data)?;
             }
         }
 
@@ -984,39 +1175,37 @@ where
 }
 
 /// [`GRanges::left_overlaps()`] for the left empty, right empty case.
-impl<'a, C> LeftOverlaps<'a, GRangesEmpty<COITreesEmpty>> for GRangesEmpty<C>
+impl<'a, C, CR: 'a> LeftOverlaps<'a, GRangesEmpty<CR>> for GRangesEmpty<C>
 where
-    C: IterableRangeContainer,
+    C: IterableRangeContainer + Sync,
+    C::RangeType: Send + Sync,
+    CR: OverlapQuery + Sync,
 {
     type Output = GRanges<VecRanges<RangeIndexed>, JoinDataBothEmpty>;
 
     /// Conduct a left overlap join, consuming self and returning a new
-    /// [`GRanges<VecRangesIndexed, JoinDataBothEmpty>`].
+    /// [`GRanges<VecRangesIndexed, JoinDataBothEmpty>`], joining the right ranges whose
+    /// overlap passes `filter`.
     ///
     /// The [`JoinDataBothEmpty`] contains no data, since neither left of right
     /// [`GRanges`] objects had data. However, it does contain a [`Vec<LeftGroupedJoin>`],
     /// and each [`LeftGroupedJoin`] contains information about the number of overlapping
     /// ranges and their lengths. This can be used to summarize, e.g. the number
     /// of overlapping basepairs, the overlap fraction, etc.
-    fn left_overlaps(
+    fn left_overlaps_with(
         self,
-        right: &'a GRangesEmpty<COITreesEmpty>,
+        right: &'a GRangesEmpty<CR>,
+        filter: &JoinFilter,
     ) -> Result<Self::Output, GRangesError> {
         let mut gr: GRanges<VecRangesIndexed, JoinData<(), ()>> =
             GRanges::new_vec(&self.0.seqlens());
         gr.data = Some(JoinData::new((), &()));
 
-        for (seqname, left_ranges) in self.0.ranges.iter() {
-            for left_range in left_ranges.iter_ranges() {
-                // Left join: every left range gets a JoinData.
-                let mut join_data = LeftGroupedJoin::new(&left_range);
-                if let Some(right_ranges) = right.0.ranges.get(seqname) {
-                    right_ranges.query(left_range.start(), left_range.end(), |right_range| {
-                        // NOTE: right_range is a coitrees::IntervalNode.
-                        join_data.add_right(right_range);
-                    });
-                }
-                gr.push_range_with_join(seqname, left_range.start(), left_range.end(), join_data)?;
+        // Left join: every left range gets a JoinData.
+        for (seqname, joins) in par_left_grouped_joins(&self.0.ranges, &right.0.ranges, filter) {
+            for join_data in joins {
+                let (start, end) = (join_data.left.start(), join_data.left.end());
+                gr.push_range_with_join(seqname, start, end, join_data)?;
             }
         }
 
@@ -1222,7 +1411,7 @@ impl GRangesEmpty<VecRanges<RangeEmpty>> {
             .0
             .ranges
             .get_mut(seqname)
-            .ok_or(GRangesError::MissingSequence(seqname.to_string()))?;
+            .ok_or(GRangesError::missing_sequence(seqname))?;
         range_container.push_range(range);
         Ok(())
     }
@@ -1242,7 +1431,7 @@ impl<T> GRanges<VecRanges<RangeIndexed>, T> {
         let range_container = self
             .ranges
             .get_mut(seqname)
-            .ok_or(GRangesError::MissingSequence(seqname.to_string()))?;
+            .ok_or(GRangesError::missing_sequence(seqname))?;
         range_container.push_range(range);
         Ok(())
     }
@@ -1284,6 +1473,68 @@ impl GRangesEmpty<VecRangesEmpty> {
     }
 }
 
+/// Check that a column has as many values as the sequence names column.
+fn check_column_length(column: &str, found: usize, expected: usize) -> Result<(), GRangesError> {
+    if found != expected {
+        return Err(GRangesError::ColumnLengthMismatch {
+            column: column.to_string(),
+            found,
+            expected_column: "seqnames".to_string(),
+            expected,
+        });
+    }
+    Ok(())
+}
+
+impl<U> GRanges<VecRangesIndexed, Vec<U>> {
+    /// Create a new [`GRanges<VecRangesIndexed, Vec<U>>`] object from parallel
+    /// columns of sequence names, start and end positions, and data, e.g. ranges
+    /// held in another program's memory. The `i`-th range is
+    /// `seqnames[i]:starts[i]-ends[i]`, with data `data[i]`.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`GRangesError::ColumnLengthMismatch`] if the columns have
+    /// different lengths, or a [`GRangesError::MissingSequence`] if a sequence
+    /// name is not in `seqlens`.
+    pub fn from_vectors<S: AsRef<str>>(
+        seqnames: &[S],
+        starts: &[Position],
+        ends: &[Position],
+        data: Vec<U>,
+        seqlens: &IndexMap<String, Position>,
+    ) -> Result<GRanges<VecRangesIndexed, Vec<U>>, GRangesError> {
+        check_column_length("starts", starts.len(), seqnames.len())?;
+        check_column_length("ends", ends.len(), seqnames.len())?;
+        check_column_length("data", data.len(), seqnames.len())?;
+        let mut gr = GRanges::new_vec(seqlens);
+        let ranges = seqnames.iter().zip(starts).zip(ends);
+        for (((seqname, &start), &end), data) in ranges.zip(data) {
+            gr.push_range(seqname.as_ref(), start, end, data)?;
+        }
+        Ok(gr)
+    }
+}
+
+impl GRangesEmpty<VecRangesEmpty> {
+    /// Create a new [`GRangesEmpty`] object from parallel columns of sequence
+    /// names, and start and end positions (see [`GRanges::from_vectors()`]).
+    pub fn from_vectors<S: AsRef<str>>(
+        seqnames: &[S],
+        starts: &[Position],
+        ends: &[Position],
+        seqlens: &IndexMap<String, Position>,
+    ) -> Result<GRangesEmpty<VecRangesEmpty>, GRangesError> {
+        check_column_length("starts", starts.len(), seqnames.len())?;
+        check_column_length("ends", ends.len(), seqnames.len())?;
+        let mut gr = GRangesEmpty::new_vec(seqlens);
+        for ((seqname, &start), &end) in seqnames.iter().zip(starts).zip(ends) {
+            gr.push_range(seqname.as_ref(), start, end)?;
+        }
+        Ok(gr)
+    }
+}
+
 impl<U> GRanges<VecRangesIndexed, Vec<U>> {
     /// Create a new [`GRanges<VecRangesIndexed, Vec<U>>`] object from a parsing iterator over
     /// [`Result<GenomicRangeRecord<U>, GRangesError>`] records.
@@ -1309,22 +1560,27 @@ impl<U> GRanges<VecRangesIndexed, Vec<U>> {
 
 impl GRangesEmpty<VecRangesEmpty> {
     /// Create a new [`GRanges<VecRangesEmpty, Vec<U>>`] object from a parsing iterator over
-    /// [`Result<GenomicRangeRecord, GRangesError>`] records.
+    /// [`Result<R, GRangesError>`] records, where `R` is any genomic record type (e.g.
+    /// [`GenomicRangeRecordEmpty`], or the zero-copy [`BorrowedBedRecord`]). Any data
+    /// in the records is ignored.
     ///
     /// # ⚠️ Stability
     ///
     /// This may be renamed.
-    pub fn from_iter<I>(
+    ///
+    /// [`BorrowedBedRecord`]: crate::io::parsers::mmap::BorrowedBedRecord
+    pub fn from_iter<I, R>(
         iter: I,
         seqlens: &IndexMap<String, Position>,
     ) -> Result<GRangesEmpty<VecRangesEmpty>, GRangesError>
     where
-        I: Iterator<Item = Result<GenomicRangeRecordEmpty, GRangesError>>,
+        I: Iterator<Item = Result<R, GRangesError>>,
+        R: GenomicRecord,
     {
         let mut gr = GRangesEmpty::new_vec(seqlens);
         for possible_entry in iter {
             let entry = possible_entry?;
-            gr.push_range(&entry.seqname, entry.start, entry.end)?;
+            gr.push_range(entry.seqname(), entry.start(), entry.end())?;
         }
         Ok(gr)
     }
@@ -1401,9 +1657,105 @@ impl<T> GRanges<VecRanges<RangeIndexed>, T> {
     }
 }
 
+impl GRangesEmpty<VecRangesEmpty> {
+    /// Convert the [`VecRangesEmpty`] range containers in this [`GRangesEmpty`] to
+    /// sorted-vector range containers, [`LapperEmpty`], an alternative overlap-query
+    /// backend to [`COITreesEmpty`] (see [`OverlapQuery`]).
+    pub fn into_lapper(self) -> Result<GRangesEmpty<LapperEmpty>, GRangesError> {
+        let old_ranges = self.0.ranges;
+        let mut new_ranges = GenomeMap::new();
+        for (seqname, vec_ranges) in old_ranges.into_iter() {
+            new_ranges.insert(&seqname, Lapper::from(vec_ranges))?;
+        }
+        Ok(GRangesEmpty(GRanges {
+            ranges: new_ranges,
+            data: None,
+        }))
+    }
+
+    /// Convert the [`VecRangesEmpty`] range containers in this [`GRangesEmpty`] to
+    /// nested containment list range containers, [`NCListEmpty`], an alternative
+    /// overlap-query backend to [`COITreesEmpty`] (see [`OverlapQuery`]).
+    pub fn into_nclist(self) -> Result<GRangesEmpty<NCListEmpty>, GRangesError> {
+        let old_ranges = self.0.ranges;
+        let mut new_ranges = GenomeMap::new();
+        for (seqname, vec_ranges) in old_ranges.into_iter() {
+            new_ranges.insert(&seqname, NCList::from(vec_ranges))?;
+        }
+        Ok(GRangesEmpty(GRanges {
+            ranges: new_ranges,
+            data: None,
+        }))
+    }
+
+    /// Convert the [`VecRangesEmpty`] range containers in this [`GRangesEmpty`] to
+    /// compact, struct-of-arrays range containers, [`SoaRangesEmpty`], which also
+    /// are an overlap-query backend (see [`OverlapQuery`]).
+    pub fn into_soa(self) -> Result<GRangesEmpty<SoaRangesEmpty>, GRangesError> {
+        let old_ranges = self.0.ranges;
+        let mut new_ranges = GenomeMap::new();
+        for (seqname, vec_ranges) in old_ranges.into_iter() {
+            new_ranges.insert(&seqname, SoaRanges::try_from(vec_ranges)?)?;
+        }
+        Ok(GRangesEmpty(GRanges {
+            ranges: new_ranges,
+            data: None,
+        }))
+    }
+}
+
+impl<T> GRanges<VecRanges<RangeIndexed>, T> {
+    /// Convert the [`VecRangesIndexed`] range containers in this [`GRanges`] to
+    /// sorted-vector range containers, [`LapperIndexed`], an alternative overlap-query
+    /// backend to [`COITreesIndexed`] (see [`OverlapQuery`]).
+    pub fn into_lapper(self) -> Result<GRanges<LapperIndexed, T>, GRangesError> {
+        let old_ranges = self.ranges;
+        let mut new_ranges = GenomeMap::new();
+        for (seqname, vec_ranges) in old_ranges.into_iter() {
+            new_ranges.insert(&seqname, Lapper::from(vec_ranges))?;
+        }
+        Ok(GRanges {
+            ranges: new_ranges,
+            data: self.data,
+        })
+    }
+
+    /// Convert the [`VecRangesIndexed`] range containers in this [`GRanges`] to
+    /// nested containment list range containers, [`NCListIndexed`], an alternative
+    /// overlap-query backend to [`COITreesIndexed`] (see [`OverlapQuery`]).
+    pub fn into_nclist(self) -> Result<GRanges<NCListIndexed, T>, GRangesError> {
+        let old_ranges = self.ranges;
+        let mut new_ranges = GenomeMap::new();
+        for (seqname, vec_ranges) in old_ranges.into_iter() {
+            new_ranges.insert(&seqname, NCList::from(vec_ranges))?;
+        }
+        Ok(GRanges {
+            ranges: new_ranges,
+            data: self.data,
+        })
+    }
+
+    /// Convert the [`VecRangesIndexed`] range containers in this [`GRanges`] to
+    /// compact, struct-of-arrays range containers, [`SoaRangesIndexed`], which also
+    /// are an overlap-query backend (see [`OverlapQuery`]). This fails if a data
+    /// index does not fit in 32 bits.
+    pub fn into_soa(self) -> Result<GRanges<SoaRangesIndexed, T>, GRangesError> {
+        let old_ranges = self.ranges;
+        let mut new_ranges = GenomeMap::new();
+        for (seqname, vec_ranges) in old_ranges.into_iter() {
+            new_ranges.insert(&seqname, SoaRanges::try_from(vec_ranges)?)?;
+        }
+        Ok(GRanges {
+            ranges: new_ranges,
+            data: self.data,
+        })
+    }
+}
+
 impl<CL: RangeContainer> GRangesEmpty<CL>
 where
-    CL: IterableRangeContainer,
+    CL: IterableRangeContainer + Sync,
+    CL::RangeType: Send + Sync,
 {
     /// Exclude genomic ranges in this object that have any overlaps
     /// with the `right` set of genomic ranges.
@@ -1413,26 +1765,30 @@ where
     ///
     /// This is a type of *filtering join*, in particular a *anti-join*.
     /// See Hadley Wickham's [R for Data Science](https://r4ds.hadley.nz/joins.html#filtering-joins) for more information.
-    pub fn antifilter_overlaps<'a, M: Clone + 'a, DR: 'a>(
+    pub fn antifilter_overlaps<'a, CR: OverlapQuery + Sync + 'a, DR: 'a>(
+        self,
+        right: &'a impl AsGRangesRef<'a, CR, DR>,
+    ) -> Result<GRangesEmpty<VecRangesEmpty>, GRangesError> {
+        self.antifilter_overlaps_with(right, &JoinFilter::default())
+    }
+
+    /// Exclude genomic ranges in this object that have any overlaps with the `right`
+    /// set of genomic ranges that pass `filter` (see
+    /// [`GRangesEmpty::antifilter_overlaps()`]).
+    pub fn antifilter_overlaps_with<'a, CR: OverlapQuery + Sync + 'a, DR: 'a>(
         self,
-        // right: &GRanges<COITrees<M>, DR>,
-        right: &'a impl AsGRangesRef<'a, COITrees<M>, DR>,
+        right: &'a impl AsGRangesRef<'a, CR, DR>,
+        filter: &JoinFilter,
     ) -> Result<GRangesEmpty<VecRangesEmpty>, GRangesError> {
         let mut gr = GRangesEmpty::new_vec(&self.seqlens());
 
         let right_ref = right.as_granges_ref();
 
-        for (seqname, left_ranges) in self.0.ranges.iter() {
-            for left_range in left_ranges.iter_ranges() {
-                if let Some(right_ranges) = right_ref.ranges.get(seqname) {
-                    let has_overlaps =
-                        right_ranges.count_overlaps(left_range.start(), left_range.end()) > 0;
-                    if !has_overlaps {
-                        gr.push_range(seqname, left_range.start(), left_range.end())?;
-                    }
-                } else {
-                    // if this left range's chrom doesn't exist in right, it doesn't have
-                    // overlaps, so we push
+        // note: if a left range's chrom doesn't exist in right, it doesn't have
+        // overlaps, so it is pushed
+        for (seqname, ranges) in par_has_overlaps(&self.0.ranges, &right_ref.ranges, filter) {
+            for (left_range, has_overlaps) in ranges {
+                if !has_overlaps {
                     gr.push_range(seqname, left_range.start(), left_range.end())?;
                 }
             }
@@ -1482,35 +1838,72 @@ where
     /// This is a type of *filtering join*, in particular a *semi-join*.
     /// See Hadley Wickham's [R for Data
     /// Science](https://r4ds.hadley.nz/joins.html#filtering-joins) for more information.
-    pub fn filter_overlaps<'a, M: Clone + 'a, DR: 'a>(
+    pub fn filter_overlaps<'a, CR: OverlapQuery + Sync + 'a, DR: 'a>(
+        self,
+        right: &'a impl AsGRangesRef<'a, CR, DR>,
+    ) -> Result<GRangesEmpty<VecRangesEmpty>, GRangesError> {
+        self.filter_overlaps_with(right, &JoinFilter::default())
+    }
+
+    /// Retain only genomic ranges that have at least one overlap with the `right`
+    /// set of genomic ranges that passes `filter`, e.g. one that covers at least half
+    /// of the range (see [`JoinFilter`]).
+    pub fn filter_overlaps_with<'a, CR: OverlapQuery + Sync + 'a, DR: 'a>(
         self,
-        // right: &GRanges<COITrees<M>, DR>,
-        right: &'a impl AsGRangesRef<'a, COITrees<M>, DR>,
+        right: &'a impl AsGRangesRef<'a, CR, DR>,
+        filter: &JoinFilter,
     ) -> Result<GRangesEmpty<VecRangesEmpty>, GRangesError> {
         let mut gr = GRangesEmpty::new_vec(&self.seqlens());
 
         let right_ref = right.as_granges_ref();
 
-        for (seqname, left_ranges) in self.0.ranges.iter() {
-            for left_range in left_ranges.iter_ranges() {
-                if let Some(right_ranges) = right_ref.ranges.get(seqname) {
-                    let num_overlaps =
-                        right_ranges.count_overlaps(left_range.start(), left_range.end());
-                    if num_overlaps == 0 {
-                        // no overlaps -- skip
-                    } else {
-                        gr.push_range(seqname, left_range.start(), left_range.end())?;
-                    }
+        for (seqname, ranges) in par_has_overlaps(&self.0.ranges, &right_ref.ranges, filter) {
+            for (left_range, has_overlaps) in ranges {
+                if has_overlaps {
+                    gr.push_range(seqname, left_range.start(), left_range.end())?;
                 }
             }
         }
         Ok(gr)
     }
+
+    /// Count the number of `right` genomic ranges that overlap each range in this
+    /// object, returning a [`GRanges`] with the counts as data.
+    ///
+    /// Unlike [`LeftOverlaps::left_overlaps()`], this does not build the join data
+    /// for each left range, or use any data of the `right` ranges, so it is much
+    /// faster when only the counts are needed (e.g. read or peak counting).
+    pub fn count_overlaps<'a, CR: OverlapQuery + Sync + 'a, DR: 'a>(
+        &self,
+        right: &'a impl AsGRangesRef<'a, CR, DR>,
+    ) -> Result<GRanges<VecRangesIndexed, Vec<usize>>, GRangesError> {
+        self.count_overlaps_with(right, &JoinFilter::default())
+    }
+
+    /// Count the number of `right` genomic ranges whose overlap with each range in
+    /// this object passes `filter` (see [`GRangesEmpty::count_overlaps()`]).
+    pub fn count_overlaps_with<'a, CR: OverlapQuery + Sync + 'a, DR: 'a>(
+        &self,
+        right: &'a impl AsGRangesRef<'a, CR, DR>,
+        filter: &JoinFilter,
+    ) -> Result<GRanges<VecRangesIndexed, Vec<usize>>, GRangesError> {
+        let mut gr = GRanges::new_vec(&self.seqlens());
+
+        let right_ref = right.as_granges_ref();
+
+        for (seqname, ranges) in par_count_overlaps(&self.0.ranges, &right_ref.ranges, filter) {
+            for (left_range, num_overlaps) in ranges {
+                gr.push_range(seqname, left_range.start(), left_range.end(), num_overlaps)?;
+            }
+        }
+        Ok(gr)
+    }
 }
 
 impl<CL, U> GRanges<CL, Vec<U>>
 where
-    CL: IterableRangeContainer,
+    CL: IterableRangeContainer + Sync,
+    CL::RangeType: Send + Sync,
 {
     /// Retain only genomic ranges that have at least one overlap with the `right`
     /// set of genomic ranges. The whole range will be retained.
@@ -1521,11 +1914,22 @@ where
     /// This is a type of *filtering join*, in particular a *semi-join*.
     /// See Hadley Wickham's [R for Data
     /// Science](https://r4ds.hadley.nz/joins.html#filtering-joins) for more information.
-    pub fn filter_overlaps<'a, M: Clone + 'a, DR: 'a>(
+    pub fn filter_overlaps<'a, CR: OverlapQuery + Sync + 'a, DR: 'a>(
         self,
-        right: &'a impl AsGRangesRef<'a, COITrees<M>, DR>,
+        right: &'a impl AsGRangesRef<'a, CR, DR>,
     ) -> Result<GRanges<VecRangesIndexed, Vec<U>>, GRangesError> {
-        self._filter_overlaps_base(right, false)
+        self._filter_overlaps_base(right, false, &JoinFilter::default())
+    }
+
+    /// Retain only genomic ranges that have at least one overlap with the `right`
+    /// set of genomic ranges that passes `filter`, e.g. one that covers at least half
+    /// of the range (see [`JoinFilter`]).
+    pub fn filter_overlaps_with<'a, CR: OverlapQuery + Sync + 'a, DR: 'a>(
+        self,
+        right: &'a impl AsGRangesRef<'a, CR, DR>,
+        filter: &JoinFilter,
+    ) -> Result<GRanges<VecRangesIndexed, Vec<U>>, GRangesError> {
+        self._filter_overlaps_base(right, false, filter)
     }
 
     /// Exclude genomic ranges in this object that have any overlaps
@@ -1536,76 +1940,134 @@ where
     ///
     /// This is a type of *filtering join*, in particular a *anti-join*.
     /// See Hadley Wickham's [R for Data Science](https://r4ds.hadley.nz/joins.html#filtering-joins) for more information.
-    pub fn antifilter_overlaps<'a, M: Clone + 'a, DR: 'a>(
+    pub fn antifilter_overlaps<'a, CR: OverlapQuery + Sync + 'a, DR: 'a>(
+        self,
+        right: &'a impl AsGRangesRef<'a, CR, DR>,
+    ) -> Result<GRanges<VecRangesIndexed, Vec<U>>, GRangesError> {
+        self._filter_overlaps_base(right, true, &JoinFilter::default())
+    }
+
+    /// Exclude genomic ranges in this object that have any overlaps with the `right`
+    /// set of genomic ranges that pass `filter` (see
+    /// [`GRanges::antifilter_overlaps()`]).
+    pub fn antifilter_overlaps_with<'a, CR: OverlapQuery + Sync + 'a, DR: 'a>(
         self,
-        right: &'a impl AsGRangesRef<'a, COITrees<M>, DR>,
+        right: &'a impl AsGRangesRef<'a, CR, DR>,
+        filter: &JoinFilter,
     ) -> Result<GRanges<VecRangesIndexed, Vec<U>>, GRangesError> {
-        self._filter_overlaps_base(right, true)
+        self._filter_overlaps_base(right, true, filter)
+    }
+
+    /// Count the number of `right` genomic ranges overlapping each range in this
+    /// object (like `bedtools intersect -c`). Each range's data is kept, paired with
+    /// its count, as `(data, count)`.
+    pub fn count_overlaps<'a, CR: OverlapQuery + Sync + 'a, DR: 'a>(
+        self,
+        right: &'a impl AsGRangesRef<'a, CR, DR>,
+    ) -> Result<GRanges<VecRangesIndexed, Vec<(U, usize)>>, GRangesError> {
+        self.count_overlaps_with(right, &JoinFilter::default())
+    }
+
+    /// Count the number of `right` genomic ranges whose overlap with each range in
+    /// this object passes `filter` (see [`GRanges::count_overlaps()`]).
+    pub fn count_overlaps_with<'a, CR: OverlapQuery + Sync + 'a, DR: 'a>(
+        mut self,
+        right: &'a impl AsGRangesRef<'a, CR, DR>,
+        filter: &JoinFilter,
+    ) -> Result<GRanges<VecRangesIndexed, Vec<(U, usize)>>, GRangesError> {
+        let mut data: Vec<Option<U>> = self.take_data()?.into_iter().map(Some).collect();
+        let mut gr = GRanges::new_vec(&self.seqlens());
+
+        let right_ref = right.as_granges_ref();
+
+        for (seqname, ranges) in par_count_overlaps(&self.ranges, &right_ref.ranges, filter) {
+            for (left_range, num_overlaps) in ranges {
+                let datum = left_range
+                    .index()
+                    .and_then(|index| data[index].take())
+                    .expect("Internal error: please report");
+                gr.push_range(
+                    seqname,
+                    left_range.start(),
+                    left_range.end(),
+                    (datum, num_overlaps),
+                )?;
+            }
+        }
+        Ok(gr)
     }
 
     // internal base function for handling the cases above
-    fn _filter_overlaps_base<'a, M: Clone + 'a, DR: 'a>(
+    fn _filter_overlaps_base<'a, CR: OverlapQuery + Sync + 'a, DR: 'a>(
         mut self,
-        right: &'a impl AsGRangesRef<'a, COITrees<M>, DR>,
+        right: &'a impl AsGRangesRef<'a, CR, DR>,
         anti: bool,
+        filter: &JoinFilter,
     ) -> Result<GRanges<VecRangesIndexed, Vec<U>>, GRangesError> {
-        let mut gr: GRanges<VecRangesIndexed, Vec<U>> = GRanges::new_vec(&self.seqlens());
+        let (mut gr, kept_indices) = self._filter_overlaps_indices(right, anti, filter)?;
+        let data = self.take_data()?;
+
+        // Now, we reconstruct the right data, in the order of the new
+        // indices. Note that we do not use the standard push_range()
+        // method here, which would double the memory usage essentially.
+        let mut data: Vec<Option<U>> = data.into_iter().map(Some).collect();
+        let new_data: Vec<U> = kept_indices
+            .iter()
+            .map(|&old_index| {
+                data[old_index]
+                    .take()
+                    .expect("Invalid index in GRanges::filter_overlaps(). This indicates invalid data indices (developer error).")
+            })
+            .collect();
+        ensure_eq!(new_data.len(), gr.len());
+        gr.data = Some(new_data);
+        Ok(gr)
+    }
+}
+
+impl<CL, T> GRanges<CL, T>
+where
+    CL: IterableRangeContainer + Sync,
+    CL::RangeType: Send + Sync,
+{
+    /// Internal base function for filtering joins over any data container.
+    ///
+    /// This returns the filtered ranges in a new [`GRanges<VecRangesIndexed, D>`] *without*
+    /// a data container, where new indices are `0..n` in range order, and the old data
+    /// indices each new index corresponds to. Data containers (e.g. [`Vec<U>`], or
+    /// `ndarray` arrays) then select these rows, keeping data in sync with the ranges.
+    pub(crate) fn _filter_overlaps_indices<'a, CR: OverlapQuery + Sync + 'a, DR: 'a, D>(
+        &self,
+        right: &'a impl AsGRangesRef<'a, CR, DR>,
+        anti: bool,
+        filter: &JoinFilter,
+    ) -> Result<(GRanges<VecRangesIndexed, D>, Vec<usize>), GRangesError> {
+        let mut gr: GRanges<VecRangesIndexed, D> = GRanges::new_vec(&self.seqlens());
 
         let right_ref = right.as_granges_ref();
-        let data = self.take_data()?;
 
-        let mut old_indices = HashSet::new(); // the old indices to *keep*
-        let mut new_indices = Vec::new();
-        let mut current_index = 0;
-
-        for (seqname, left_ranges) in self.ranges.iter() {
-            for left_range in left_ranges.iter_ranges() {
-                if let Some(right_ranges) = right_ref.ranges.get(seqname) {
-                    let has_overlaps =
-                        right_ranges.count_overlaps(left_range.start(), left_range.end()) > 0;
-                    // XOR with anti
-                    let passes_filter = has_overlaps != anti;
-                    if passes_filter {
-                        gr.push_range_with_index(
-                            seqname,
-                            left_range.start(),
-                            left_range.end(),
-                            current_index,
-                        )?;
-                        // unwrap should be safe, since this is an indexed GRanges
-                        old_indices.insert(left_range.index().unwrap());
-                        new_indices.push(current_index);
-                        current_index += 1;
-                    }
-                } else if anti {
-                    // if this left range's chrom doesn't exist in right, it doesn't have
-                    // overlaps, so we push
+        // the old indices to *keep*, in the order of the new indices
+        let mut kept_indices = Vec::new();
+
+        // note: if a left range's chrom doesn't exist in right, it doesn't have
+        // overlaps, so it is pushed only if this is an anti-join
+        for (seqname, ranges) in par_has_overlaps(&self.ranges, &right_ref.ranges, filter) {
+            for (left_range, has_overlaps) in ranges {
+                // XOR with anti
+                let passes_filter = has_overlaps != anti;
+                if passes_filter {
                     gr.push_range_with_index(
                         seqname,
                         left_range.start(),
                         left_range.end(),
-                        current_index,
+                        kept_indices.len(),
                     )?;
                     // unwrap should be safe, since this is an indexed GRanges
-                    old_indices.insert(left_range.index().unwrap());
-                    new_indices.push(current_index);
-                    current_index += 1;
+                    kept_indices.push(left_range.index().unwrap());
                 }
             }
         }
-
-        // Now, we reconstruct the right data. Note that we do not use the
-        // standard push_range() method here, which would double the memory
-        // usage essentially.
-        let mut new_data = Vec::new();
-        for (old_index, data_value) in data.into_iter().enumerate() {
-            if old_indices.contains(&old_index) {
-                new_data.push(data_value)
-            }
-        }
-        ensure_eq!(new_data.len(), new_indices.len());
-        gr.data = Some(new_data);
-        Ok(gr)
+        Ok((gr, kept_indices))
     }
 }
 
@@ -1640,25 +2102,518 @@ where
     }
 }
 
-/// [`PartialEq`] for [`GRanges`] objects.
-///
-/// This is a more powerful comparison operator than [`GRanges.is_equal_to()`], since it will first
-/// convert one type of ranges to another.
-///
-/// A [`GRanges`] object is considered equal to another if:
-/// 1. Same number or ranges (this is checked first for efficiency).
-/// 2. Same metadata.
-/// 3. Same ranges. This requires converting ranges from different range container map types to a
-///    [`VecRanges`]. **Warning**: this is currently memory intensive.
-///
-/// # Developer Notes
-/// This uses type conversion in the range container types; see
-/// [`crate::granges::ranges::comparison.rs`].
-impl<CL, CR, DL, DR> PartialEq<GRanges<CR, DR>> for GRanges<CL, DL>
+impl<R, T> GRanges<R, T>
 where
-    CL: IterableRangeContainer + PartialEq<CL>,
-    CR: IterableRangeContainer + PartialEq<CR>,
-    DL: PartialEq<DR>,
+    R: IterableRangeContainer,
+{
+    /// Iterate through the ranges in genome order, as borrowed records with their
+    /// sequence names (unlike [`GRanges::iter_ranges()`], which yields indices).
+    pub fn iter_named_ranges(&self) -> impl Iterator<Item = GenomicRangeRecordEmptyBorrowed<'_>> {
+        self.ranges.iter().flat_map(|(seqname, ranges)| {
+            ranges
+                .iter_ranges()
+                .map(move |range| GenomicRangeRecordEmptyBorrowed {
+                    seqname: seqname.as_str(),
+                    start: range.start(),
+                    end: range.end(),
+                })
+        })
+    }
+}
+
+impl<R> GRangesEmpty<R>
+where
+    R: IterableRangeContainer,
+{
+    /// Iterate through the ranges in genome order, as borrowed records with their
+    /// sequence names.
+    pub fn iter_named_ranges(&self) -> impl Iterator<Item = GenomicRangeRecordEmptyBorrowed<'_>> {
+        self.0.iter_named_ranges()
+    }
+}
+
+impl<R, T> GRanges<R, T>
+where
+    R: IterableRangeContainer<RangeType = RangeIndexed>,
+    T: IndexedDataContainer,
+{
+    /// Iterate through the ranges in genome order, as borrowed records with their
+    /// sequence names and references to their data. Unlike
+    /// [`GRanges::iter_records()`], this does not clone any data.
+    pub fn iter_with_data(
+        &self,
+    ) -> impl Iterator<Item = GenomicRangeRecordBorrowed<'_, <T as IndexedDataContainer>::Item<'_>>>
+    {
+        self.ranges.iter().flat_map(move |(seqname, ranges)| {
+            ranges
+                .iter_ranges()
+                .map(move |range| GenomicRangeRecordBorrowed {
+                    seqname: seqname.as_str(),
+                    start: range.start,
+                    end: range.end,
+                    data: self.get_data_value(range.index),
+                })
+        })
+    }
+}
+
+impl<R, U> GRanges<R, Vec<U>>
+where
+    R: IterableRangeContainer<RangeType = RangeIndexed> + Sync,
+    U: Sync,
+{
+    /// A parallel version of [`GRanges::iter_with_data()`], for per-range
+    /// computations with [rayon](https://docs.rs/rayon). Sequences are processed in
+    /// parallel, and `collect()` returns the results in genome order.
+    pub fn par_iter_with_data(
+        &self,
+    ) -> impl ParallelIterator<Item = GenomicRangeRecordBorrowed<'_, &'_ U>> {
+        let data = self.data.as_ref().expect("data container was None");
+        let sequences: Vec<(&String, &R)> = self.ranges.iter().collect();
+        sequences
+            .into_par_iter()
+            .flat_map_iter(move |(seqname, ranges)| {
+                ranges
+                    .iter_ranges()
+                    .map(move |range| GenomicRangeRecordBorrowed {
+                        seqname: seqname.as_str(),
+                        start: range.start,
+                        end: range.end,
+                        data: &data[range.index],
+                    })
+            })
+    }
+}
+
+/// A lightweight view of the ranges (and data) on one sequence of a [`GRanges`]
+/// object, from [`GRanges::on()`].
+#[derive(Debug)]
+pub struct SequenceView<'a, C, T> {
+    seqname: &'a str,
+    ranges: &'a C,
+    data: Option<&'a T>,
+}
+
+impl<C, T> Clone for SequenceView<'_, C, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<C, T> Copy for SequenceView<'_, C, T> {}
+
+impl<'a, C, T> SequenceView<'a, C, T>
+where
+    C: RangeContainer,
+{
+    /// The sequence name.
+    pub fn seqname(&self) -> &'a str {
+        self.seqname
+    }
+
+    /// The sequence's range container.
+    pub fn ranges(&self) -> &'a C {
+        self.ranges
+    }
+
+    /// The (whole) data container of the [`GRanges`] object.
+    pub fn data(&self) -> Option<&'a T> {
+        self.data
+    }
+
+    /// The number of ranges on this sequence.
+    pub fn len(&self) -> usize {
+        self.ranges.len()
+    }
+
+    /// Whether there are no ranges on this sequence.
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    /// The length of this sequence.
+    pub fn sequence_length(&self) -> Position {
+        self.ranges.sequence_length()
+    }
+}
+
+impl<'a, C, T> SequenceView<'a, C, T>
+where
+    C: OverlapQuery,
+{
+    /// Return the number of ranges on this sequence that overlap `[start, end)`.
+    pub fn count_overlaps(&self, start: Position, end: Position) -> usize {
+        self.ranges.count_overlaps(start, end)
+    }
+}
+
+impl<'a, C, T> SequenceView<'a, C, T>
+where
+    C: IterableRangeContainer<RangeType = RangeIndexed>,
+    T: IndexedDataContainer,
+{
+    /// Iterate through the ranges on this sequence, with references to their data
+    /// (see [`GRanges::iter_with_data()`]).
+    pub fn iter_with_data(
+        &self,
+    ) -> impl Iterator<Item = GenomicRangeRecordBorrowed<'a, <T as IndexedDataContainer>::Item<'a>>>
+    {
+        let (seqname, data) = (self.seqname, self.data.expect("data container was None"));
+        self.ranges
+            .iter_ranges()
+            .map(move |range| GenomicRangeRecordBorrowed {
+                seqname,
+                start: range.start,
+                end: range.end,
+                data: data.get_value(range.index),
+            })
+    }
+}
+
+impl<C, T> GRanges<C, T>
+where
+    C: OverlapQuery,
+{
+    /// Visit the ranges overlapping each of a stream of `queries` (e.g. a parsing
+    /// iterator), without building a join: `visit` is called with each query and the
+    /// ranges in this object that overlap it (unsorted, with their data indices), and
+    /// only one query's overlaps are held in memory at a time. Queries on sequences
+    /// not in this object have no overlaps.
+    ///
+    /// Returning [`ControlFlow::Break`] from `visit` stops the stream early, and its
+    /// value is returned (otherwise, `None` is returned after the last query).
+    pub fn query_stream<I, Q, F, B>(
+        &self,
+        queries: I,
+        mut visit: F,
+    ) -> Result<Option<B>, GRangesError>
+    where
+        I: IntoIterator<Item = Result<Q, GRangesError>>,
+        Q: GenomicRecord,
+        F: FnMut(&Q, &[RangeTuple]) -> ControlFlow<B>,
+    {
+        let mut overlaps = Vec::new();
+        for result in queries {
+            let query = result?;
+            overlaps.clear();
+            if let Some(ranges) = self.ranges.get(query.seqname()) {
+                ranges.query_overlaps(query.start(), query.end(), |range| {
+                    overlaps.push(RangeTuple(range.as_tuple()))
+                });
+            }
+            if let ControlFlow::Break(value) = visit(&query, &overlaps) {
+                return Ok(Some(value));
+            }
+        }
+        Ok(None)
+    }
+}
+
+impl<C> GRangesEmpty<C>
+where
+    C: OverlapQuery,
+{
+    /// Visit the ranges overlapping each of a stream of `queries`, without building a
+    /// join (see [`GRanges::query_stream()`]).
+    pub fn query_stream<I, Q, F, B>(&self, queries: I, visit: F) -> Result<Option<B>, GRangesError>
+    where
+        I: IntoIterator<Item = Result<Q, GRangesError>>,
+        Q: GenomicRecord,
+        F: FnMut(&Q, &[RangeTuple]) -> ControlFlow<B>,
+    {
+        self.0.query_stream(queries, visit)
+    }
+}
+
+impl<C, T> GRanges<C, T>
+where
+    C: RangeContainer,
+{
+    /// Get a [`SequenceView`] of the ranges on the sequence `seqname`, or `None` if
+    /// this sequence is not in this [`GRanges`] object.
+    pub fn on(&self, seqname: &str) -> Option<SequenceView<'_, C, T>> {
+        let (seqname, ranges) = self.ranges.iter().find(|(name, _)| *name == seqname)?;
+        Some(SequenceView {
+            seqname: seqname.as_str(),
+            ranges,
+            data: self.data.as_ref(),
+        })
+    }
+}
+
+impl<R, U> GRanges<R, Vec<U>>
+where
+    R: IterableRangeContainer<RangeType = RangeIndexed>,
+    U: Clone,
+{
+    /// Split this [`GRanges`] object into one [`GRanges`] object per sequence (each
+    /// with only that sequence), e.g. to process sequences independently in parallel.
+    pub fn split_by_chrom(&self) -> IndexMap<String, GRanges<VecRangesIndexed, Vec<U>>> {
+        let mut split = IndexMap::new();
+        for (seqname, ranges) in self.ranges.iter() {
+            let seqlens = IndexMap::from([(seqname.clone(), ranges.sequence_length())]);
+            split.insert(seqname.clone(), GRanges::new_vec(&seqlens));
+        }
+        for record in self.iter_with_data() {
+            split[record.seqname]
+                .push_range(
+                    record.seqname,
+                    record.start,
+                    record.end,
+                    record.data.clone(),
+                )
+                .expect("Internal error: please report");
+        }
+        split
+    }
+}
+
+impl<R> GRangesEmpty<R>
+where
+    R: IterableRangeContainer,
+{
+    /// Get a [`SequenceView`] of the ranges on the sequence `seqname`, or `None` if
+    /// this sequence is not in this [`GRangesEmpty`] object.
+    pub fn on(&self, seqname: &str) -> Option<SequenceView<'_, R, ()>> {
+        self.0.on(seqname)
+    }
+
+    /// Split this [`GRangesEmpty`] object into one [`GRangesEmpty`] object per
+    /// sequence (see [`GRanges::split_by_chrom()`]).
+    pub fn split_by_chrom(&self) -> IndexMap<String, GRangesEmpty<VecRangesEmpty>> {
+        let mut split = IndexMap::new();
+        for (seqname, ranges) in self.0.ranges.iter() {
+            let seqlens = IndexMap::from([(seqname.clone(), ranges.sequence_length())]);
+            split.insert(seqname.clone(), GRangesEmpty::new_vec(&seqlens));
+        }
+        for range in self.iter_named_ranges() {
+            split[range.seqname]
+                .push_range(range.seqname, range.start, range.end)
+                .expect("Internal error: please report");
+        }
+        split
+    }
+}
+
+/// Convert positions (in genome order) into a mask of `len` ranges.
+fn index_mask(indices: &[usize], len: usize) -> Result<Vec<bool>, GRangesError> {
+    let mut mask = vec![false; len];
+    for &index in indices {
+        *mask
+            .get_mut(index)
+            .ok_or(GRangesError::IndexOutOfBounds { index, len })? = true;
+    }
+    Ok(mask)
+}
+
+/// Check that a mask has one entry per range.
+fn check_mask_length(mask: &[bool], len: usize) -> Result<(), GRangesError> {
+    if mask.len() != len {
+        return Err(GRangesError::ColumnLengthMismatch {
+            column: "mask".to_string(),
+            found: mask.len(),
+            expected_column: "ranges".to_string(),
+            expected: len,
+        });
+    }
+    Ok(())
+}
+
+impl<R, U> GRanges<R, Vec<U>>
+where
+    R: IterableRangeContainer<RangeType = RangeIndexed>,
+    U: Clone,
+{
+    /// Create a new [`GRanges`] with the ranges (and their data) for which
+    /// `predicate` returns `true`.
+    pub fn filter_ranges<F>(&self, mut predicate: F) -> GRanges<VecRangesIndexed, Vec<U>>
+    where
+        F: FnMut(&GenomicRangeRecordBorrowed<'_, &U>) -> bool,
+    {
+        let mut gr = GRanges::new_vec(&self.seqlens());
+        for record in self.iter_with_data() {
+            if predicate(&record) {
+                gr.push_range(
+                    record.seqname,
+                    record.start,
+                    record.end,
+                    record.data.clone(),
+                )
+                .expect("Internal error: please report");
+            }
+        }
+        gr
+    }
+
+    /// Create a new [`GRanges`] with the ranges (and their data) where `mask` is
+    /// `true`. The mask has an entry for each range, in genome order (the order
+    /// of [`GRanges::iter_with_data()`]).
+    pub fn select_by_mask(
+        &self,
+        mask: &[bool],
+    ) -> Result<GRanges<VecRangesIndexed, Vec<U>>, GRangesError> {
+        check_mask_length(mask, self.len())?;
+        let mut keep = mask.iter();
+        Ok(self.filter_ranges(|_| *keep.next().unwrap()))
+    }
+
+    /// Create a new [`GRanges`] with the ranges (and their data) at the positions
+    /// `indices`, in genome order (the order of [`GRanges::iter_with_data()`]). The
+    /// ranges stay in genome order, and repeated indices are only included once.
+    pub fn subset_by_index(
+        &self,
+        indices: &[usize],
+    ) -> Result<GRanges<VecRangesIndexed, Vec<U>>, GRangesError> {
+        self.select_by_mask(&index_mask(indices, self.len())?)
+    }
+}
+
+impl<R> GRangesEmpty<R>
+where
+    R: IterableRangeContainer,
+{
+    /// Create a new [`GRangesEmpty`] with the ranges for which `predicate` returns
+    /// `true`.
+    pub fn filter_ranges<F>(&self, mut predicate: F) -> GRangesEmpty<VecRangesEmpty>
+    where
+        F: FnMut(&GenomicRangeRecordEmptyBorrowed<'_>) -> bool,
+    {
+        let mut gr = GRangesEmpty::new_vec(&self.seqlens());
+        for range in self.iter_named_ranges() {
+            if predicate(&range) {
+                gr.push_range(range.seqname, range.start, range.end)
+                    .expect("Internal error: please report");
+            }
+        }
+        gr
+    }
+
+    /// Create a new [`GRangesEmpty`] with the ranges where `mask` is `true` (see
+    /// [`GRanges::select_by_mask()`]).
+    pub fn select_by_mask(
+        &self,
+        mask: &[bool],
+    ) -> Result<GRangesEmpty<VecRangesEmpty>, GRangesError> {
+        check_mask_length(mask, self.len())?;
+        let mut keep = mask.iter();
+        Ok(self.filter_ranges(|_| *keep.next().unwrap()))
+    }
+
+    /// Create a new [`GRangesEmpty`] with the ranges at the positions `indices` (see
+    /// [`GRanges::subset_by_index()`]).
+    pub fn subset_by_index(
+        &self,
+        indices: &[usize],
+    ) -> Result<GRangesEmpty<VecRangesEmpty>, GRangesError> {
+        self.select_by_mask(&index_mask(indices, self.len())?)
+    }
+}
+
+/// Which parts of ranges are compared to find duplicates, with
+/// [`GRanges::duplicated()`] and [`GRanges::dedup()`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum DuplicateKey {
+    /// Ranges are duplicates if their ranges and data are equal.
+    #[default]
+    RangesAndData,
+    /// Ranges are duplicates if their ranges are equal, whatever their data.
+    Ranges,
+}
+
+impl<R, U> GRanges<R, Vec<U>>
+where
+    R: IterableRangeContainer<RangeType = RangeIndexed>,
+    U: Clone + PartialEq,
+{
+    /// A mask with an entry for each range, in genome order (the order of
+    /// [`GRanges::iter_with_data()`]), that is `true` if the range duplicates an
+    /// earlier range (according to `key`).
+    pub fn duplicated(&self, key: DuplicateKey) -> Vec<bool> {
+        // the data of the earlier distinct records of each range
+        let mut seen: HashMap<(&str, Position, Position), Vec<&U>> = HashMap::new();
+        self.iter_with_data()
+            .map(|record| {
+                let earlier = seen
+                    .entry((record.seqname, record.start, record.end))
+                    .or_default();
+                let duplicated = match key {
+                    DuplicateKey::RangesAndData => earlier.contains(&record.data),
+                    DuplicateKey::Ranges => !earlier.is_empty(),
+                };
+                if !duplicated {
+                    earlier.push(record.data);
+                }
+                duplicated
+            })
+            .collect()
+    }
+
+    /// The positions (in genome order) of the ranges that duplicate an earlier range
+    /// (see [`GRanges::duplicated()`]).
+    pub fn duplicated_indices(&self, key: DuplicateKey) -> Vec<usize> {
+        duplicated_indices(&self.duplicated(key))
+    }
+
+    /// Create a new [`GRanges`] without the ranges (and their data) that duplicate an
+    /// earlier range (see [`GRanges::duplicated()`]).
+    pub fn dedup(&self, key: DuplicateKey) -> GRanges<VecRangesIndexed, Vec<U>> {
+        let mut duplicated = self.duplicated(key).into_iter();
+        self.filter_ranges(|_| !duplicated.next().unwrap())
+    }
+}
+
+impl<R> GRangesEmpty<R>
+where
+    R: IterableRangeContainer,
+{
+    /// A mask with an entry for each range, in genome order, that is `true` if the
+    /// range equals an earlier range.
+    pub fn duplicated(&self) -> Vec<bool> {
+        let mut seen = HashSet::new();
+        self.iter_named_ranges()
+            .map(|range| !seen.insert((range.seqname, range.start, range.end)))
+            .collect()
+    }
+
+    /// The positions (in genome order) of the ranges that equal an earlier range.
+    pub fn duplicated_indices(&self) -> Vec<usize> {
+        duplicated_indices(&self.duplicated())
+    }
+
+    /// Create a new [`GRangesEmpty`] without the ranges that equal an earlier range.
+    pub fn dedup(&self) -> GRangesEmpty<VecRangesEmpty> {
+        let mut duplicated = self.duplicated().into_iter();
+        self.filter_ranges(|_| !duplicated.next().unwrap())
+    }
+}
+
+/// The positions where a duplicates mask is `true`.
+fn duplicated_indices(mask: &[bool]) -> Vec<usize> {
+    mask.iter()
+        .enumerate()
+        .filter_map(|(index, &duplicated)| duplicated.then_some(index))
+        .collect()
+}
+
+/// [`PartialEq`] for [`GRanges`] objects.
+///
+/// This is a more powerful comparison operator than [`GRanges.is_equal_to()`], since it will first
+/// convert one type of ranges to another.
+///
+/// A [`GRanges`] object is considered equal to another if:
+/// 1. Same number or ranges (this is checked first for efficiency).
+/// 2. Same metadata.
+/// 3. Same ranges. This requires converting ranges from different range container map types to a
+///    [`VecRanges`]. **Warning**: this is currently memory intensive.
+///
+/// # Developer Notes
+/// This uses type conversion in the range container types; see
+/// [`crate::granges::ranges::comparison.rs`].
+impl<CL, CR, DL, DR> PartialEq<GRanges<CR, DR>> for GRanges<CL, DL>
+where
+    CL: IterableRangeContainer + PartialEq<CL>,
+    CR: IterableRangeContainer + PartialEq<CR>,
+    DL: PartialEq<DR>,
 {
     fn eq(&self, other: &GRanges<CR, DR>) -> bool {
         if self.len() != other.len() {
@@ -1678,12 +2633,119 @@ where
     }
 }
 
+/// [`PartialEq`] for [`GRangesEmpty`] objects, which are equal if they have the same
+/// ranges (see the [`PartialEq`] implementation for [`GRanges`]).
+impl<CL, CR> PartialEq<GRangesEmpty<CR>> for GRangesEmpty<CL>
+where
+    CL: IterableRangeContainer + PartialEq<CL>,
+    CR: IterableRangeContainer + PartialEq<CR>,
+{
+    fn eq(&self, other: &GRangesEmpty<CR>) -> bool {
+        self.0 == other.0
+    }
+}
+
+/// A serializable view of a [`GRanges`] object: the sequence names with their
+/// range containers (in order), and the optional data container.
+#[derive(Serialize)]
+struct GRangesSerializeRef<'a, C, T> {
+    sequences: Vec<(&'a String, &'a C)>,
+    data: Option<&'a T>,
+}
+
+/// The owned counterpart to [`GRangesSerializeRef`], used for deserialization.
+#[derive(Deserialize)]
+struct GRangesDeserialize<C, T> {
+    sequences: Vec<(String, C)>,
+    data: Option<T>,
+}
+
+/// [`Serialize`] for [`GRanges`] objects with serializable range and data containers
+/// (e.g. [`VecRanges`] and [`Vec<U>`]).
+///
+/// [`GenomeMap`] does not implement [`Serialize`], so this serializes the sequences
+/// and their range containers as an ordered list.
+impl<C, T> Serialize for GRanges<C, T>
+where
+    C: Serialize,
+    T: Serialize,
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        GRangesSerializeRef {
+            sequences: self.ranges.iter().collect(),
+            data: self.data.as_ref(),
+        }
+        .serialize(serializer)
+    }
+}
+
+/// [`Deserialize`] for [`GRanges`] objects, which validates each range like ranges
+/// are validated when parsed (see [`check_genomic_range()`]).
+impl<'de, C, T> Deserialize<'de> for GRanges<C, T>
+where
+    C: Deserialize<'de> + IterableRangeContainer,
+    T: Deserialize<'de>,
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let deserialized = GRangesDeserialize::deserialize(deserializer)?;
+        let mut ranges = GenomeMap::new();
+        for (seqname, container) in deserialized.sequences {
+            let length = container.sequence_length();
+            for range in container.iter_ranges() {
+                check_genomic_range(&seqname, range.start(), range.end(), length)
+                    .map_err(serde::de::Error::custom)?;
+            }
+            ranges
+                .insert(&seqname, container)
+                .map_err(serde::de::Error::custom)?;
+        }
+        Ok(GRanges {
+            ranges,
+            data: deserialized.data,
+        })
+    }
+}
+
+impl<C: Serialize> Serialize for GRangesEmpty<C> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        self.0.serialize(serializer)
+    }
+}
+
+impl<'de, C> Deserialize<'de> for GRangesEmpty<C>
+where
+    C: Deserialize<'de> + IterableRangeContainer,
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        Ok(GRangesEmpty(GRanges::deserialize(deserializer)?))
+    }
+}
+
 #[cfg(test)]
 mod tests {
+    use std::ops::ControlFlow;
+
+    use rayon::iter::ParallelIterator;
+
     use crate::{
+        io::TsvConfig,
         iterators::GRangesRecordIterator,
+        join::{JoinDataBothEmpty, JoinFilter},
         prelude::*,
-        test_utilities::{granges_test_case_01, granges_test_case_02, random_vecranges},
+        ranges::GenomicRangeRecordEmpty,
+        testing::{granges_test_case_01, granges_test_case_02, random_granges, random_vecranges},
         Position,
     };
 
@@ -1695,6 +2757,233 @@ mod tests {
         assert_eq!(gr.len(), 1);
     }
 
+    #[test]
+    fn test_from_vectors() {
+        let seqlens = seqlens! { "chr1" => 100, "chr2" => 100 };
+        let gr = GRanges::from_vectors(
+            &["chr2", "chr1", "chr1"],
+            &[0, 0, 20],
+            &[10, 10, 30],
+            vec![3.0, 1.0, 2.0],
+            &seqlens,
+        )
+        .unwrap();
+        assert_eq!(gr.len(), 3);
+        assert_eq!(
+            gr.data_by_seqname().unwrap().get("chr1").unwrap(),
+            &[1.0, 2.0]
+        );
+
+        let gr = GRangesEmpty::from_vectors(&["chr1"], &[0], &[10], &seqlens).unwrap();
+        assert_eq!(gr.len(), 1);
+
+        assert!(matches!(
+            GRangesEmpty::from_vectors(&["chr1", "chr2"], &[0, 5], &[10], &seqlens),
+            Err(GRangesError::ColumnLengthMismatch { found: 1, .. })
+        ));
+        assert!(matches!(
+            GRangesEmpty::from_vectors(&["chr3"], &[0], &[10], &seqlens),
+            Err(GRangesError::MissingSequence { .. })
+        ));
+    }
+
+    #[test]
+    fn test_iter_with_data() {
+        let seqlens = seqlens! { "chr1" => 100, "chr2" => 100 };
+        let gr = GRanges::from_vectors(
+            &["chr2", "chr1", "chr1"],
+            &[0, 20, 0],
+            &[10, 30, 10],
+            vec![3.0, 2.0, 1.0],
+            &seqlens,
+        )
+        .unwrap()
+        .sort();
+
+        let records: Vec<_> = gr
+            .iter_with_data()
+            .map(|record| (record.seqname, record.start, *record.data))
+            .collect();
+        assert_eq!(
+            records,
+            vec![("chr1", 0, 1.0), ("chr1", 20, 2.0), ("chr2", 0, 3.0)]
+        );
+
+        let widths: Vec<_> = gr
+            .par_iter_with_data()
+            .map(|record| (record.end - record.start) as f64 * record.data)
+            .collect();
+        assert_eq!(widths, vec![10.0, 20.0, 30.0]);
+
+        let seqnames: Vec<_> = gr.iter_named_ranges().map(|range| range.seqname).collect();
+        assert_eq!(seqnames, vec!["chr1", "chr1", "chr2"]);
+    }
+
+    #[test]
+    fn test_adjust_ranges_in_place() {
+        let seqlens = seqlens! { "chr1" => 100 };
+        let mut gr = GRanges::from_vectors(
+            &["chr1", "chr1", "chr1"],
+            &[95, 0, 50],
+            &[120, 10, 60],
+            vec!["past end", "first", "middle"],
+            &seqlens,
+        )
+        .unwrap();
+
+        gr.clamp_to_genome();
+        let ranges: Vec<_> = gr
+            .iter_with_data()
+            .map(|record| (record.start, record.end, *record.data))
+            .collect();
+        assert_eq!(
+            ranges,
+            vec![(0, 10, "first"), (50, 60, "middle"), (95, 100, "past end")]
+        );
+
+        // the range shifted past the end is dropped, along with its data
+        gr.adjust_ranges_in_place(10, 10);
+        assert_eq!(gr.len(), 2);
+        assert_eq!(gr.data().unwrap(), &vec!["first", "middle"]);
+    }
+
+    #[test]
+    fn test_subsetting() {
+        let seqlens = seqlens! { "chr1" => 100, "chr2" => 100 };
+        let gr = GRanges::from_vectors(
+            &["chr1", "chr1", "chr2"],
+            &[0, 20, 0],
+            &[10, 30, 50],
+            vec![1.0, 2.0, 3.0],
+            &seqlens,
+        )
+        .unwrap();
+
+        let wide = gr.filter_ranges(|record| record.end - record.start > 10);
+        assert_eq!(wide.data().unwrap(), &vec![3.0]);
+
+        let selected = gr.select_by_mask(&[true, false, true]).unwrap();
+        assert_eq!(selected.data().unwrap(), &vec![1.0, 3.0]);
+        assert!(gr.select_by_mask(&[true]).is_err());
+
+        let subset = gr.subset_by_index(&[2, 1]).unwrap();
+        assert_eq!(subset.data().unwrap(), &vec![2.0, 3.0]);
+        assert!(matches!(
+            gr.subset_by_index(&[3]),
+            Err(GRangesError::IndexOutOfBounds { index: 3, len: 3 })
+        ));
+
+        let empty = gr.into_granges_empty().unwrap();
+        assert_eq!(empty.subset_by_index(&[0]).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_duplicates() {
+        let seqlens = seqlens! { "chr1" => 100, "chr2" => 100 };
+        let gr = GRanges::from_vectors(
+            &["chr1", "chr2", "chr1", "chr1", "chr1"],
+            &[0, 0, 0, 20, 0],
+            &[10, 10, 10, 30, 10],
+            vec![1.0, 1.0, 2.0, 3.0, 1.0],
+            &seqlens,
+        )
+        .unwrap();
+
+        // genome order: chr1 ranges (data 1.0, 2.0, 3.0, 1.0), then chr2
+        let duplicated = gr.duplicated(DuplicateKey::RangesAndData);
+        assert_eq!(duplicated, vec![false, false, false, true, false]);
+        assert_eq!(gr.duplicated_indices(DuplicateKey::Ranges), vec![1, 3]);
+
+        let deduped = gr.dedup(DuplicateKey::RangesAndData);
+        assert_eq!(deduped.data().unwrap(), &vec![1.0, 2.0, 3.0, 1.0]);
+        let deduped = gr.dedup(DuplicateKey::Ranges);
+        assert_eq!(deduped.data().unwrap(), &vec![1.0, 3.0, 1.0]);
+
+        let empty = gr.into_granges_empty().unwrap();
+        assert_eq!(empty.duplicated_indices(), vec![1, 3]);
+        assert_eq!(empty.dedup().len(), 3);
+    }
+
+    #[test]
+    fn test_on_and_split_by_chrom() {
+        let seqlens = seqlens! { "chr1" => 100, "chr2" => 200 };
+        let gr = GRanges::from_vectors(
+            &["chr1", "chr2", "chr1"],
+            &[0, 5, 20],
+            &[10, 15, 30],
+            vec![1.0, 2.0, 3.0],
+            &seqlens,
+        )
+        .unwrap();
+
+        let chr1 = gr.on("chr1").unwrap();
+        assert_eq!(chr1.len(), 2);
+        assert_eq!(chr1.sequence_length(), 100);
+        let data: Vec<_> = chr1.iter_with_data().map(|record| *record.data).collect();
+        assert_eq!(data, vec![1.0, 3.0]);
+        assert!(gr.on("chr3").is_none());
+
+        let split = gr.split_by_chrom();
+        assert_eq!(split.len(), 2);
+        assert_eq!(split["chr2"].seqlens(), seqlens! { "chr2" => 200 });
+        assert_eq!(split["chr2"].data().unwrap(), &vec![2.0]);
+        assert_eq!(split["chr1"].len(), 2);
+    }
+
+    #[test]
+    fn test_column_by_name() {
+        let seqlens = seqlens! { "chr1" => 100, "chr2" => 100 };
+        let mut gr = GRanges::new_vec(&seqlens);
+        let row = |name: &str, score| Bed5Addition {
+            name: name.to_string(),
+            score,
+        };
+        gr.push_range("chr2", 0, 10, row("c", Some(3.0))).unwrap();
+        gr.push_range("chr1", 0, 10, row("a", Some(1.0))).unwrap();
+        gr.push_range("chr1", 20, 30, row("b", None)).unwrap();
+
+        // values come out in genome order
+        let scores = gr.column::<f64>("score").unwrap();
+        assert_eq!(scores, vec![Some(1.0), None, Some(3.0)]);
+        let names = gr.column::<String>("name").unwrap();
+        assert_eq!(names[2], Some("c".to_string()));
+
+        assert!(matches!(
+            gr.column::<f64>("strand"),
+            Err(GRangesError::NoSuchColumn(_))
+        ));
+        assert!(matches!(
+            gr.column::<f64>("name"),
+            Err(GRangesError::ColumnTypeMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn test_to_bedlike() {
+        let seqlens = seqlens! { "chr1" => 100, "chr2" => 100 };
+        let mut gr = GRanges::new_vec(&seqlens);
+        let row = |name: &str, score| Bed5Addition {
+            name: name.to_string(),
+            score,
+        };
+        gr.push_range("chr2", 0, 10, row("b", Some(123456.7))).unwrap();
+        gr.push_range("chr1", 5, 10, row("a", None)).unwrap();
+
+        assert_eq!(gr.to_bed3(), "chr1\t5\t10\nchr2\t0\t10\n");
+        assert_eq!(
+            gr.to_bed_string().unwrap(),
+            "chr1\t5\t10\ta\t.\nchr2\t0\t10\tb\t123456.7\n"
+        );
+        let config = TsvConfig {
+            no_value_string: "NA".to_string(),
+            ..BEDTOOLS_TSV.clone()
+        };
+        assert_eq!(
+            gr.to_bed6(&config).unwrap(),
+            "chr1\t5\t10\ta\tNA\t.\nchr2\t0\t10\tb\t1.2346e+05\t.\n"
+        );
+    }
+
     #[test]
     fn test_random_vecranges() {
         let vr = random_vecranges(100);
@@ -1752,6 +3041,30 @@ mod tests {
         assert_eq!(gr_filtered.len(), 3);
     }
 
+    #[test]
+    fn granges_filter_overlaps_data_in_sync() {
+        // data is pushed out of range order, so data indices and
+        // range order differ after sorting
+        let seqlens = seqlens! { "chr1" => 100 };
+        let mut gr = GRanges::new_vec(&seqlens);
+        gr.push_range("chr1", 50, 60, 50).unwrap();
+        gr.push_range("chr1", 0, 10, 0).unwrap();
+        gr.push_range("chr1", 20, 30, 20).unwrap();
+        let gr = gr.sort();
+
+        let mut right: GRangesEmpty<VecRangesEmpty> = GRangesEmpty::new_vec(&seqlens);
+        right.push_range("chr1", 0, 25).unwrap();
+        let right = right.into_coitrees().unwrap();
+
+        let kept = gr.clone().filter_overlaps(&right).unwrap();
+        let excluded = gr.antifilter_overlaps(&right).unwrap();
+        for filtered in [kept, excluded] {
+            for range in filtered.iter_records() {
+                assert_eq!(range.start, range.data);
+            }
+        }
+    }
+
     #[test]
     fn granges_antifilter_overlaps() {
         // ANTI version of above
@@ -1938,6 +3251,190 @@ mod tests {
         // rest are empty TODO should check
     }
 
+    #[test]
+    fn test_overlap_backends_agree() {
+        let sl = seqlens!("chr1" => 100_000, "chr2" => 50_000);
+        let left = random_granges(&sl, 500).unwrap();
+        let right = random_granges(&sl, 500).unwrap();
+
+        let num_overlaps = |joined: GRanges<VecRangesIndexed, JoinDataBothEmpty>| {
+            joined
+                .data
+                .unwrap()
+                .iter()
+                .map(|join| join.num_overlaps())
+                .collect::<Vec<_>>()
+        };
+        let coitrees = right.clone().into_coitrees().unwrap();
+        let lapper = right.clone().into_lapper().unwrap();
+        let nclist = right.clone().into_nclist().unwrap();
+        let soa = right.into_soa().unwrap();
+        let expected = num_overlaps(left.clone().left_overlaps(&coitrees).unwrap());
+        assert_eq!(
+            num_overlaps(left.clone().left_overlaps(&lapper).unwrap()),
+            expected
+        );
+        assert_eq!(
+            num_overlaps(left.clone().left_overlaps(&nclist).unwrap()),
+            expected
+        );
+        assert_eq!(
+            num_overlaps(left.clone().left_overlaps(&soa).unwrap()),
+            expected
+        );
+
+        let expected_kept = left.clone().filter_overlaps(&coitrees).unwrap().len();
+        assert_eq!(
+            left.clone().filter_overlaps(&lapper).unwrap().len(),
+            expected_kept
+        );
+        assert_eq!(
+            left.clone().filter_overlaps(&nclist).unwrap().len(),
+            expected_kept
+        );
+        assert_eq!(
+            left.clone().filter_overlaps(&soa).unwrap().len(),
+            expected_kept
+        );
+
+        let counts = left.count_overlaps(&nclist).unwrap();
+        assert_eq!(counts.data.unwrap(), expected);
+    }
+
+    #[test]
+    fn test_join_filter() {
+        let sl = seqlens!("chr1" => 100);
+        let mut left = GRanges::new_vec(&sl);
+        left.push_range("chr1", 0, 10, "a").unwrap();
+        left.push_range("chr1", 20, 60, "b").unwrap();
+        let mut right = GRangesEmpty::new_vec(&sl);
+        right.push_range("chr1", 5, 40).unwrap();
+        right.push_range("chr1", 50, 52).unwrap();
+        let right = right.into_coitrees().unwrap();
+
+        // a: 5bp overlap (half of a); b: 20bp and 2bp overlaps (half and 1/20 of b)
+        let filter = JoinFilter::new().min_overlap(10);
+        let kept = left.clone().filter_overlaps_with(&right, &filter).unwrap();
+        assert_eq!(kept.data.unwrap(), vec!["b"]);
+        let dropped = left
+            .clone()
+            .antifilter_overlaps_with(&right, &filter)
+            .unwrap();
+        assert_eq!(dropped.data.unwrap(), vec!["a"]);
+
+        let filter = JoinFilter::new().min_left_fraction(0.5);
+        let joined = left.left_overlaps_with(&right, &filter).unwrap();
+        let num_joined: Vec<_> = joined
+            .data
+            .unwrap()
+            .joins
+            .iter()
+            .map(|join| join.num_overlaps())
+            .collect();
+        assert_eq!(num_joined, vec![1, 1]);
+
+        let filter = JoinFilter::new().reciprocal(0.5);
+        let counts = GRangesEmpty::from_windows(&sl, 50, None, false)
+            .unwrap()
+            .count_overlaps_with(&right, &filter)
+            .unwrap();
+        assert_eq!(counts.data.unwrap(), vec![1, 0]);
+    }
+
+    #[test]
+    fn test_count_overlaps_with_data() {
+        let sl = seqlens!("chr1" => 100);
+        let mut left = GRanges::new_vec(&sl);
+        left.push_range("chr1", 0, 10, "a").unwrap();
+        left.push_range("chr1", 20, 60, "b").unwrap();
+        left.push_range("chr1", 70, 80, "c").unwrap();
+        let mut right = GRangesEmpty::new_vec(&sl);
+        right.push_range("chr1", 5, 40).unwrap();
+        right.push_range("chr1", 50, 52).unwrap();
+        let right = right.into_coitrees().unwrap();
+
+        let counts = left.clone().count_overlaps(&right).unwrap();
+        assert_eq!(counts.data.unwrap(), vec![("a", 1), ("b", 2), ("c", 0)]);
+
+        let filter = JoinFilter::new().min_overlap(5);
+        let counts = left.count_overlaps_with(&right, &filter).unwrap();
+        assert_eq!(counts.data.unwrap(), vec![("a", 1), ("b", 1), ("c", 0)]);
+    }
+
+    #[test]
+    fn test_query_stream() {
+        let sl = seqlens!("chr1" => 100, "chr2" => 100);
+        let mut gr = GRangesEmpty::new_vec(&sl);
+        gr.push_range("chr1", 5, 40).unwrap();
+        gr.push_range("chr1", 30, 52).unwrap();
+        gr.push_range("chr2", 0, 10).unwrap();
+        let gr = gr.into_coitrees().unwrap();
+
+        let queries = || {
+            [
+                ("chr1", 0, 10),
+                ("chr1", 35, 45),
+                ("chr2", 50, 60),
+                ("chr3", 0, 10),
+            ]
+            .map(|(seqname, start, end)| {
+                let range = GenomicRangeRecordEmpty::new(seqname.to_string(), start, end);
+                Ok::<_, GRangesError>(range)
+            })
+        };
+        let mut counts = Vec::new();
+        let result = gr
+            .query_stream(queries(), |_, overlaps| {
+                counts.push(overlaps.len());
+                ControlFlow::<()>::Continue(())
+            })
+            .unwrap();
+        assert!(result.is_none());
+        assert_eq!(counts, vec![1, 2, 0, 0]);
+
+        // stop at the first query with no overlaps
+        let first_empty = gr
+            .query_stream(queries(), |query, overlaps| {
+                if overlaps.is_empty() {
+                    ControlFlow::Break(query.seqname.clone())
+                } else {
+                    ControlFlow::Continue(())
+                }
+            })
+            .unwrap();
+        assert_eq!(first_empty.as_deref(), Some("chr2"));
+    }
+
+    #[test]
+    fn test_join_filter_window() {
+        let sl = seqlens!("chr1" => 100);
+        let mut left = GRangesEmpty::new_vec(&sl);
+        left.push_range("chr1", 10, 20).unwrap();
+        left.push_range("chr1", 60, 70).unwrap();
+        let mut right = GRangesEmpty::new_vec(&sl);
+        right.push_range("chr1", 0, 5).unwrap();
+        right.push_range("chr1", 25, 30).unwrap();
+        right.push_range("chr1", 80, 90).unwrap();
+        let right = right.into_coitrees().unwrap();
+
+        let counts = left
+            .clone()
+            .count_overlaps_with(&right, &JoinFilter::new())
+            .unwrap();
+        assert_eq!(counts.data.unwrap(), vec![0, 0]);
+
+        let counts = left
+            .clone()
+            .count_overlaps_with(&right, &JoinFilter::new().window(6, 6))
+            .unwrap();
+        assert_eq!(counts.data.unwrap(), vec![2, 0]);
+
+        let counts = left
+            .count_overlaps_with(&right, &JoinFilter::new().window(0, 11))
+            .unwrap();
+        assert_eq!(counts.data.unwrap(), vec![1, 1]);
+    }
+
     #[test]
     fn test_left_with_data_both_empty() {
         let sl = seqlens!("chr1" => 50);
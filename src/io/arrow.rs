@@ -0,0 +1,123 @@
+//! Reading and writing genomic ranges as [Apache Arrow](https://arrow.apache.org)
+//! IPC files, through polars.
+//!
+//! # Schema
+//!
+//! Range tables have the columns:
+//!
+//!  1. `chrom` (`Utf8`): the sequence name.
+//!  2. `start` (`UInt64`): the 0-indexed start position.
+//!  3. `end` (`UInt64`): the exclusive end position.
+//!
+//! followed by any data columns. When reading, `start` and `end` may be any
+//! integer type, and are validated against the sequence lengths of the genome.
+//! This is the same layout as [`GRanges::to_dataframe()`] and [`GRanges::from_dataframe()`].
+//!
+//! Parquet files with this layout are supported with the `parquet` feature (see
+//! `io::parquet`), and command output is written as either format by `io::columnar`.
+
+use std::{fs::File, path::PathBuf};
+
+use indexmap::IndexMap;
+use polars::prelude::*;
+
+use crate::{
+    error::GRangesError,
+    granges::{GRanges, GRangesEmpty},
+    ranges::{
+        vec::{VecRangesEmpty, VecRangesIndexed},
+        RangeIndexed,
+    },
+    traits::{IterableRangeContainer, Selection},
+    Position,
+};
+
+/// Write a range [`DataFrame`] to an Arrow IPC file.
+pub fn write_dataframe_ipc(
+    df: &mut DataFrame,
+    path: impl Into<PathBuf>,
+) -> Result<(), GRangesError> {
+    let mut file = File::create(path.into())?;
+    IpcWriter::new(&mut file).finish(df)?;
+    Ok(())
+}
+
+/// Read a range [`DataFrame`] from an Arrow IPC file.
+pub fn read_dataframe_ipc(path: impl Into<PathBuf>) -> Result<DataFrame, GRangesError> {
+    let file = File::open(path.into())?;
+    Ok(IpcReader::new(file).finish()?)
+}
+
+impl GRanges<VecRangesIndexed, DataFrame> {
+    /// Read a new [`GRanges<VecRangesIndexed, DataFrame>`] from an Arrow IPC file,
+    /// with any non-range columns in the [`DataFrame`] data container.
+    pub fn from_arrow_ipc(
+        path: impl Into<PathBuf>,
+        seqlens: &IndexMap<String, Position>,
+    ) -> Result<Self, GRangesError> {
+        GRanges::from_dataframe(&read_dataframe_ipc(path)?, seqlens)
+    }
+}
+
+impl GRangesEmpty<VecRangesEmpty> {
+    /// Read a new [`GRangesEmpty`] from an Arrow IPC file, ignoring any data columns.
+    pub fn from_arrow_ipc(
+        path: impl Into<PathBuf>,
+        seqlens: &IndexMap<String, Position>,
+    ) -> Result<Self, GRangesError> {
+        GRangesEmpty::from_dataframe(&read_dataframe_ipc(path)?, seqlens)
+    }
+}
+
+impl<C> GRangesEmpty<C>
+where
+    C: IterableRangeContainer,
+{
+    /// Write these ranges to an Arrow IPC file.
+    pub fn write_to_arrow_ipc(&self, path: impl Into<PathBuf>) -> Result<(), GRangesError> {
+        write_dataframe_ipc(&mut self.to_dataframe()?, path)
+    }
+}
+
+impl<C> GRanges<C, DataFrame>
+where
+    C: IterableRangeContainer<RangeType = RangeIndexed>,
+{
+    /// Write these ranges and their [`DataFrame`] data to an Arrow IPC file.
+    pub fn write_to_arrow_ipc(&self, path: impl Into<PathBuf>) -> Result<(), GRangesError> {
+        write_dataframe_ipc(&mut self.to_dataframe()?, path)
+    }
+}
+
+impl<C, U> GRanges<C, Vec<U>>
+where
+    C: IterableRangeContainer<RangeType = RangeIndexed>,
+    U: Selection,
+{
+    /// Write these ranges and their data to an Arrow IPC file, with a column
+    /// for each column in the data type's schema (see [`Selection`]).
+    pub fn write_to_arrow_ipc(&self, path: impl Into<PathBuf>) -> Result<(), GRangesError> {
+        write_dataframe_ipc(&mut self.to_dataframe()?, path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::prelude::*;
+
+    #[test]
+    fn test_arrow_ipc_roundtrip() {
+        let seqlens = seqlens! { "chr1" => 100, "chr2" => 100 };
+        let gr = GRangesEmpty::from_windows(&seqlens, 30, None, false).unwrap();
+
+        let file = tempfile::Builder::new().suffix(".arrow").tempfile().unwrap();
+        gr.write_to_arrow_ipc(file.path()).unwrap();
+
+        let gr_read = GRangesEmpty::from_arrow_ipc(file.path(), &seqlens).unwrap();
+        assert_eq!(gr_read.len(), gr.len());
+        assert!(gr_read
+            .to_dataframe()
+            .unwrap()
+            .equals(&gr.to_dataframe().unwrap()));
+    }
+}
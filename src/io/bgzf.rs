@@ -0,0 +1,338 @@
+//! Parallel compression and decompression of BGZF (blocked gzip) data.
+//!
+//! BGZF files (as written by `bgzip`, and used by samtools, tabix, etc.) are
+//! a series of gzip members of at most 64 KiB each, so they are valid gzip files, but
+//! unlike a single gzip stream, their blocks can be compressed and decompressed
+//! independently. [`BgzfWriter`] compresses batches of blocks in parallel, and
+//! [`BgzfReader`] decompresses them in parallel, using rayon's global thread pool
+//! (whose size is set with [`set_num_threads()`](crate::parallel::set_num_threads),
+//! or `granges --threads`).
+//!
+//! Output files ending in `.gz` or `.bgz` are written as BGZF (see
+//! [`create_output()`](crate::io::file::create_output)), and BGZF inputs are detected
+//! by their header and read with a [`BgzfReader`] (other gzip inputs are decompressed
+//! on a single thread).
+
+use flate2::{read::DeflateDecoder, write::DeflateEncoder, Compression, Crc};
+use rayon::prelude::*;
+use std::io::{self, Read, Write};
+
+/// The maximum uncompressed size of a block, as in htslib, so that even
+/// incompressible data fits in a block of at most 64 KiB.
+pub const BLOCK_SIZE: usize = 0xff00;
+
+/// The maximum size of a compressed block.
+const MAX_BLOCK_SIZE: usize = 0x10000;
+
+/// The length of a BGZF block header, up to the compressed data.
+const HEADER_LENGTH: usize = 18;
+
+/// The length of a block footer (the CRC32 and uncompressed size).
+const FOOTER_LENGTH: usize = 8;
+
+/// The empty block that marks the end of a BGZF file.
+pub const EOF_BLOCK: [u8; 28] = [
+    0x1f, 0x8b, 0x08, 0x04, 0x00, 0x00, 0x00, 0x00, 0x00, 0xff, 0x06, 0x00, 0x42, 0x43, 0x02, 0x00,
+    0x1b, 0x00, 0x03, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+];
+
+/// The number of blocks compressed or decompressed in each parallel batch.
+fn batch_length() -> usize {
+    4 * rayon::current_num_threads()
+}
+
+/// Returns whether `bytes` starts with a BGZF block header (a gzip header with a
+/// `BC` extra subfield).
+pub fn is_bgzf(bytes: &[u8]) -> bool {
+    bytes.len() >= HEADER_LENGTH
+        && bytes.starts_with(&[0x1f, 0x8b, 0x08])
+        && bytes[3] & 0x04 != 0
+        && bytes[12..14] == *b"BC"
+}
+
+/// Compress `data` (at most [`BLOCK_SIZE`] bytes) into a BGZF block.
+fn compress_block(data: &[u8], level: Compression) -> io::Result<Vec<u8>> {
+    let mut encoder = DeflateEncoder::new(Vec::with_capacity(data.len()), level);
+    encoder.write_all(data)?;
+    let compressed = encoder.finish()?;
+    let block_size = HEADER_LENGTH + compressed.len() + FOOTER_LENGTH;
+    if block_size > MAX_BLOCK_SIZE {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "BGZF block exceeds the maximum block size",
+        ));
+    }
+    let mut crc = Crc::new();
+    crc.update(data);
+
+    let mut block = Vec::with_capacity(block_size);
+    block.extend_from_slice(&EOF_BLOCK[..16]);
+    block.extend_from_slice(&((block_size - 1) as u16).to_le_bytes());
+    block.extend_from_slice(&compressed);
+    block.extend_from_slice(&crc.sum().to_le_bytes());
+    block.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    Ok(block)
+}
+
+/// Decompress a whole BGZF `block`, checking its CRC32 and size.
+fn decompress_block(block: &[u8]) -> io::Result<Vec<u8>> {
+    let footer = &block[block.len() - FOOTER_LENGTH..];
+    let crc_sum = u32::from_le_bytes(footer[..4].try_into().unwrap());
+    let size = u32::from_le_bytes(footer[4..].try_into().unwrap()) as usize;
+    if size > MAX_BLOCK_SIZE {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "BGZF block exceeds the maximum block size",
+        ));
+    }
+
+    // read at most one byte more than the size, so corrupt data can't use unbounded memory
+    let mut data = Vec::with_capacity(size);
+    DeflateDecoder::new(&block[HEADER_LENGTH..block.len() - FOOTER_LENGTH])
+        .take(size as u64 + 1)
+        .read_to_end(&mut data)?;
+    let mut crc = Crc::new();
+    crc.update(&data);
+    if data.len() != size || crc.sum() != crc_sum {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "BGZF block is corrupt (CRC32 or size mismatch)",
+        ));
+    }
+    Ok(data)
+}
+
+/// A writer that compresses its output as BGZF, in parallel batches of blocks.
+///
+/// The remaining data and the end-of-file block are written by
+/// [`BgzfWriter::finish()`], or when the writer is dropped (ignoring any errors).
+pub struct BgzfWriter<W: Write> {
+    inner: Option<W>,
+    level: Compression,
+    /// Full blocks of uncompressed data, waiting to be compressed.
+    blocks: Vec<Vec<u8>>,
+    /// The current, partial block.
+    buffer: Vec<u8>,
+}
+
+impl<W: Write> BgzfWriter<W> {
+    /// Create a new [`BgzfWriter`] with the default compression level.
+    pub fn new(inner: W) -> Self {
+        Self::with_level(inner, Compression::default())
+    }
+
+    /// Create a new [`BgzfWriter`] with the compression `level`.
+    pub fn with_level(inner: W, level: Compression) -> Self {
+        Self {
+            inner: Some(inner),
+            level,
+            blocks: Vec::new(),
+            buffer: Vec::with_capacity(BLOCK_SIZE),
+        }
+    }
+
+    /// Compress the pending blocks in parallel, and write them in order.
+    fn write_blocks(&mut self) -> io::Result<()> {
+        if !self.buffer.is_empty() {
+            let buffer = std::mem::replace(&mut self.buffer, Vec::with_capacity(BLOCK_SIZE));
+            self.blocks.push(buffer);
+        }
+        let level = self.level;
+        let compressed = self
+            .blocks
+            .par_iter()
+            .map(|block| compress_block(block, level))
+            .collect::<io::Result<Vec<_>>>()?;
+        self.blocks.clear();
+        let inner = self.inner.as_mut().expect("Internal error: please report");
+        for block in compressed {
+            inner.write_all(&block)?;
+        }
+        Ok(())
+    }
+
+    /// Write the remaining data and the end-of-file block, and return the inner writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        self.try_finish()?;
+        Ok(self.inner.take().expect("Internal error: please report"))
+    }
+
+    fn try_finish(&mut self) -> io::Result<()> {
+        if self.inner.is_none() {
+            return Ok(());
+        }
+        self.write_blocks()?;
+        let inner = self.inner.as_mut().expect("Internal error: please report");
+        inner.write_all(&EOF_BLOCK)?;
+        inner.flush()
+    }
+}
+
+impl<W: Write> Write for BgzfWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let length = buf.len().min(BLOCK_SIZE - self.buffer.len());
+        self.buffer.extend_from_slice(&buf[..length]);
+        if self.buffer.len() == BLOCK_SIZE {
+            let buffer = std::mem::replace(&mut self.buffer, Vec::with_capacity(BLOCK_SIZE));
+            self.blocks.push(buffer);
+            if self.blocks.len() >= batch_length() {
+                self.write_blocks()?;
+            }
+        }
+        Ok(length)
+    }
+
+    /// Compress and write all the data so far (a partial block is written as a
+    /// smaller block).
+    fn flush(&mut self) -> io::Result<()> {
+        self.write_blocks()?;
+        self.inner
+            .as_mut()
+            .expect("Internal error: please report")
+            .flush()
+    }
+}
+
+impl<W: Write> Drop for BgzfWriter<W> {
+    fn drop(&mut self) {
+        let _ = self.try_finish();
+    }
+}
+
+/// A reader that decompresses BGZF data, in parallel batches of blocks.
+pub struct BgzfReader<R: Read> {
+    inner: R,
+    /// Decompressed data, and the position read up to.
+    buffer: Vec<u8>,
+    position: usize,
+    done: bool,
+}
+
+impl<R: Read> BgzfReader<R> {
+    /// Create a new [`BgzfReader`].
+    pub fn new(inner: R) -> Self {
+        Self {
+            inner,
+            buffer: Vec::new(),
+            position: 0,
+            done: false,
+        }
+    }
+
+    /// Read the next whole (compressed) block, or `None` at the end of the input.
+    fn read_block(&mut self) -> io::Result<Option<Vec<u8>>> {
+        let mut block = vec![0; HEADER_LENGTH];
+        let mut length = 0;
+        while length < HEADER_LENGTH {
+            match self.inner.read(&mut block[length..])? {
+                0 if length == 0 => return Ok(None),
+                0 => return Err(io::ErrorKind::UnexpectedEof.into()),
+                n => length += n,
+            }
+        }
+        if !is_bgzf(&block) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "invalid BGZF block header",
+            ));
+        }
+        let block_size = u16::from_le_bytes([block[16], block[17]]) as usize + 1;
+        if block_size < HEADER_LENGTH + FOOTER_LENGTH {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "invalid BGZF block size",
+            ));
+        }
+        block.resize(block_size, 0);
+        self.inner.read_exact(&mut block[HEADER_LENGTH..])?;
+        Ok(Some(block))
+    }
+
+    /// Read and decompress the next batch of blocks into the buffer.
+    fn fill_buffer(&mut self) -> io::Result<()> {
+        let mut blocks = Vec::new();
+        while blocks.len() < batch_length() {
+            match self.read_block()? {
+                Some(block) => blocks.push(block),
+                None => {
+                    self.done = true;
+                    break;
+                }
+            }
+        }
+        let data = blocks
+            .par_iter()
+            .map(|block| decompress_block(block))
+            .collect::<io::Result<Vec<_>>>()?;
+        self.buffer = data.concat();
+        self.position = 0;
+        Ok(())
+    }
+}
+
+impl<R: Read> Read for BgzfReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        // empty blocks (e.g. the end-of-file block) may give empty batches
+        while self.position == self.buffer.len() {
+            if self.done {
+                return Ok(0);
+            }
+            self.fill_buffer()?;
+        }
+        let length = buf.len().min(self.buffer.len() - self.position);
+        buf[..length].copy_from_slice(&self.buffer[self.position..self.position + length]);
+        self.position += length;
+        Ok(length)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::read::MultiGzDecoder;
+
+    fn test_data() -> Vec<u8> {
+        (0..20_000)
+            .map(|i| format!("chr1\t{}\t{}\n", i * 10, i * 10 + 5))
+            .collect::<String>()
+            .into_bytes()
+    }
+
+    #[test]
+    fn test_bgzf_roundtrip() {
+        let data = test_data();
+        assert!(data.len() > 2 * BLOCK_SIZE);
+        let mut writer = BgzfWriter::new(Vec::new());
+        writer.write_all(&data).unwrap();
+        let compressed = writer.finish().unwrap();
+        assert!(is_bgzf(&compressed));
+        assert!(compressed.ends_with(&EOF_BLOCK));
+
+        let mut decompressed = Vec::new();
+        BgzfReader::new(compressed.as_slice())
+            .read_to_end(&mut decompressed)
+            .unwrap();
+        assert_eq!(decompressed, data);
+
+        // BGZF is also valid (multi-member) gzip
+        let mut decompressed = Vec::new();
+        MultiGzDecoder::new(compressed.as_slice())
+            .read_to_end(&mut decompressed)
+            .unwrap();
+        assert_eq!(decompressed, data);
+    }
+
+    #[test]
+    fn test_bgzf_corrupt() {
+        let mut writer = BgzfWriter::new(Vec::new());
+        writer.write_all(&test_data()).unwrap();
+        let mut compressed = writer.finish().unwrap();
+        // flip a bit in the first block's CRC32
+        let block_size = u16::from_le_bytes([compressed[16], compressed[17]]) as usize + 1;
+        compressed[block_size - FOOTER_LENGTH] ^= 1;
+        let mut decompressed = Vec::new();
+        assert!(BgzfReader::new(compressed.as_slice())
+            .read_to_end(&mut decompressed)
+            .is_err());
+    }
+}
@@ -0,0 +1,160 @@
+//! The binary `.granges` format, for persisting [`GRanges`] objects.
+//!
+//! Constructing a large [`GRanges`] object from a text file requires parsing and
+//! validating every range. Saving the constructed object with [`GRanges::save()`] allows
+//! it to be loaded back quickly with [`GRanges::load()`].
+//!
+//! # Format
+//!
+//! A `.granges` file is the 8-byte magic string `GRANGES\0`, a little-endian `u32` format
+//! version, and then the [CBOR](https://cbor.io) encoding of the [`GRanges`] object (see
+//! its [`Serialize`] implementation). Only range and data containers that implement
+//! [`Serialize`] and [`Deserialize`] can be saved, e.g. [`VecRanges`] with
+//! [`Vec<U>`] data.
+//!
+//! Loading a `.granges` file deserializes it in full: the CBOR encoding can't be read
+//! in place, so there is no memory-mapped load (as `MmapBedFile` provides for BED
+//! files with the `mmap` feature). A memory-mappable layout would need a fixed-width
+//! encoding of the range and data containers, and is left for a future format version.
+//!
+//! [`VecRanges`]: crate::ranges::vec::VecRanges
+
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter, Read, Write},
+    path::PathBuf,
+};
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::{
+    error::GRangesError,
+    granges::{GRanges, GRangesEmpty},
+    traits::IterableRangeContainer,
+};
+
+/// The magic string at the start of every `.granges` file.
+pub const GRANGES_MAGIC: &[u8; 8] = b"GRANGES\0";
+
+/// The current `.granges` format version.
+pub const GRANGES_FORMAT_VERSION: u32 = 1;
+
+/// Write `value` in the binary `.granges` format to `path`.
+pub fn write_granges_binary<V: Serialize>(
+    value: &V,
+    path: impl Into<PathBuf>,
+) -> Result<(), GRangesError> {
+    let mut writer = BufWriter::new(File::create(path.into())?);
+    writer.write_all(GRANGES_MAGIC)?;
+    writer.write_all(&GRANGES_FORMAT_VERSION.to_le_bytes())?;
+    ciborium::into_writer(value, &mut writer)
+        .map_err(|e| GRangesError::BinaryFormatError(e.to_string()))?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// Read a value in the binary `.granges` format from `path`.
+pub fn read_granges_binary<V: DeserializeOwned>(
+    path: impl Into<PathBuf>,
+) -> Result<V, GRangesError> {
+    let mut reader = BufReader::new(File::open(path.into())?);
+    let mut magic = [0; 8];
+    reader
+        .read_exact(&mut magic)
+        .map_err(|_| GRangesError::BinaryFormatError("file is too short".to_string()))?;
+    if &magic != GRANGES_MAGIC {
+        return Err(GRangesError::BinaryFormatError(
+            "file is not a .granges file".to_string(),
+        ));
+    }
+    let mut version = [0; 4];
+    reader.read_exact(&mut version)?;
+    let version = u32::from_le_bytes(version);
+    if version != GRANGES_FORMAT_VERSION {
+        return Err(GRangesError::BinaryFormatError(format!(
+            "unsupported format version {} (expected {})",
+            version, GRANGES_FORMAT_VERSION
+        )));
+    }
+    ciborium::from_reader(reader).map_err(|e| GRangesError::BinaryFormatError(e.to_string()))
+}
+
+impl<C, T> GRanges<C, T>
+where
+    C: Serialize + for<'de> Deserialize<'de> + IterableRangeContainer,
+    T: Serialize + for<'de> Deserialize<'de>,
+{
+    /// Save this [`GRanges`] object to `path` in the binary `.granges` format.
+    pub fn save(&self, path: impl Into<PathBuf>) -> Result<(), GRangesError> {
+        write_granges_binary(self, path)
+    }
+
+    /// Load a [`GRanges`] object from a binary `.granges` file. The whole file is
+    /// deserialized; it is not memory-mapped (see the [module docs](self)).
+    pub fn load(path: impl Into<PathBuf>) -> Result<Self, GRangesError> {
+        read_granges_binary(path)
+    }
+}
+
+impl<C> GRangesEmpty<C>
+where
+    C: Serialize + for<'de> Deserialize<'de> + IterableRangeContainer,
+{
+    /// Save this [`GRangesEmpty`] object to `path` in the binary `.granges` format.
+    pub fn save(&self, path: impl Into<PathBuf>) -> Result<(), GRangesError> {
+        write_granges_binary(self, path)
+    }
+
+    /// Load a [`GRangesEmpty`] object from a binary `.granges` file. The whole file
+    /// is deserialized; it is not memory-mapped (see the [module docs](self)).
+    pub fn load(path: impl Into<PathBuf>) -> Result<Self, GRangesError> {
+        read_granges_binary(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{prelude::*, testing::granges_test_case_01};
+
+    #[test]
+    fn test_save_load_roundtrip() {
+        let gr = granges_test_case_01();
+        let file = tempfile::NamedTempFile::new().unwrap();
+        gr.save(file.path()).unwrap();
+        let gr_loaded: GRanges<VecRangesIndexed, Vec<f64>> = GRanges::load(file.path()).unwrap();
+        assert_eq!(gr, gr_loaded);
+        assert_eq!(gr.seqlens(), gr_loaded.seqlens());
+    }
+
+    #[test]
+    fn test_save_load_empty() {
+        let seqlens = seqlens! { "chr1" => 100, "chr2" => 50 };
+        let gr = GRangesEmpty::from_windows(&seqlens, 20, None, false).unwrap();
+        let file = tempfile::NamedTempFile::new().unwrap();
+        gr.save(file.path()).unwrap();
+        let gr_loaded: GRangesEmpty<VecRangesEmpty> = GRangesEmpty::load(file.path()).unwrap();
+        assert_eq!(gr_loaded.len(), gr.len());
+        assert_eq!(gr_loaded.seqnames(), vec!["chr1", "chr2"]);
+    }
+
+    #[test]
+    fn test_load_invalid_range() {
+        // ranges are validated when loaded, like when they are parsed
+        let seqlens = seqlens! { "chr1" => 100 };
+        let mut gr = GRanges::new_vec(&seqlens);
+        gr.push_range("chr1", 90, 110, 1.0).unwrap();
+        let file = tempfile::NamedTempFile::new().unwrap();
+        gr.save(file.path()).unwrap();
+        let result: Result<GRanges<VecRangesIndexed, Vec<f64>>, _> = GRanges::load(file.path());
+        assert!(matches!(result, Err(GRangesError::BinaryFormatError(_))));
+    }
+
+    #[test]
+    fn test_load_invalid() {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(file.path(), b"chr1\t0\t10\n").unwrap();
+        let result: Result<GRangesEmpty<VecRangesEmpty>, _> = GRangesEmpty::load(file.path());
+        assert!(matches!(result, Err(GRangesError::BinaryFormatError(_))));
+    }
+}
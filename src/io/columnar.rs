@@ -0,0 +1,357 @@
+//! Writing command output as columnar range tables, i.e. [Apache Arrow](https://arrow.apache.org)
+//! IPC or [Apache Parquet](https://parquet.apache.org) files.
+//!
+//! Commands write their ranges as TSV rows. When the output path has an Arrow IPC
+//! or Parquet extension (see [`ColumnarFormat::from_path()`]), these rows are
+//! buffered by a [`ColumnarWriter`], which converts them to a range [`DataFrame`]
+//! (see [`tsv_to_dataframe()`]) and writes the file when flushed.
+//!
+//! # Schema
+//!
+//! Tables have the same layout as [`crate::io::arrow`] range tables:
+//!
+//!  1. `chrom` (`Utf8`): the sequence name.
+//!  2. `start` (`UInt64`): the 0-indexed start position.
+//!  3. `end` (`UInt64`): the exclusive end position.
+//!
+//! followed by the data columns, named by the output's header (if any), or
+//! otherwise `column_4`, `column_5`, etc. (their 1-based TSV column number). Each
+//! data column is `Int64` if all its values are integers, `Float64` if they are all
+//! numbers, and `Utf8` otherwise. Missing values (see [`crate::io::tsv::is_na_value()`])
+//! are null.
+
+use std::path::Path;
+#[cfg(feature = "polars")]
+use std::{
+    io::{self, Write},
+    path::PathBuf,
+};
+
+#[cfg(feature = "polars")]
+use polars::prelude::*;
+
+#[cfg(feature = "polars")]
+use crate::{
+    data::polars::{CHROM_COLUMN, END_COLUMN, START_COLUMN},
+    io::tsv::is_na_value,
+};
+use crate::{
+    error::GRangesError,
+    io::file::{is_arrow_ipc_path, is_parquet_path},
+};
+
+/// A columnar output file format.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ColumnarFormat {
+    /// An Arrow IPC file (requires the `arrow` feature).
+    ArrowIpc,
+    /// A Parquet file (requires the `parquet` feature).
+    Parquet,
+}
+
+impl ColumnarFormat {
+    /// The columnar format of `path`, by its file extension, or `None` if it is not a
+    /// columnar file.
+    pub fn from_path(path: impl AsRef<Path>) -> Option<Self> {
+        let path = path.as_ref();
+        if is_arrow_ipc_path(path) {
+            Some(ColumnarFormat::ArrowIpc)
+        } else if is_parquet_path(path) {
+            Some(ColumnarFormat::Parquet)
+        } else {
+            None
+        }
+    }
+
+    /// The feature granges must be compiled with to write this format.
+    pub fn feature(&self) -> &'static str {
+        match self {
+            ColumnarFormat::ArrowIpc => "arrow",
+            ColumnarFormat::Parquet => "parquet",
+        }
+    }
+
+    /// Whether granges was compiled with support for writing this format.
+    pub fn is_enabled(&self) -> bool {
+        match self {
+            ColumnarFormat::ArrowIpc => cfg!(feature = "arrow"),
+            ColumnarFormat::Parquet => cfg!(feature = "parquet"),
+        }
+    }
+
+    /// The error for writing this format to `path` when it isn't enabled.
+    pub fn requires_feature(&self, path: impl AsRef<Path>) -> GRangesError {
+        GRangesError::OutputFormatRequiresFeature(
+            path.as_ref().display().to_string(),
+            self.feature().to_string(),
+        )
+    }
+}
+
+/// A data column's values, typed as narrowly as all its values allow.
+#[cfg(feature = "polars")]
+enum DataColumn {
+    Integer(Vec<Option<i64>>),
+    Float(Vec<Option<f64>>),
+    String(Vec<Option<String>>),
+}
+
+#[cfg(feature = "polars")]
+impl DataColumn {
+    fn infer(values: Vec<Option<String>>) -> Self {
+        let present = || values.iter().flatten();
+        if present().all(|value| value.parse::<i64>().is_ok()) {
+            DataColumn::Integer(
+                values
+                    .iter()
+                    .map(|value| value.as_ref().map(|value| value.parse().unwrap()))
+                    .collect(),
+            )
+        } else if present().all(|value| value.parse::<f64>().is_ok()) {
+            DataColumn::Float(
+                values
+                    .iter()
+                    .map(|value| value.as_ref().map(|value| value.parse().unwrap()))
+                    .collect(),
+            )
+        } else {
+            DataColumn::String(values)
+        }
+    }
+
+    fn into_series(self, name: &str) -> Series {
+        match self {
+            DataColumn::Integer(values) => Series::new(name, values),
+            DataColumn::Float(values) => Series::new(name, values),
+            DataColumn::String(values) => Series::new(name, values),
+        }
+    }
+}
+
+/// Convert TSV range output (e.g. BED) to a range [`DataFrame`] (see the module
+/// documentation for its schema). Metadata lines (starting with `#`) and empty lines
+/// are skipped, and if `has_header` is set, the first remaining line names the columns.
+#[cfg(feature = "polars")]
+pub fn tsv_to_dataframe(tsv: &str, has_header: bool) -> Result<DataFrame, GRangesError> {
+    let mut lines = tsv
+        .lines()
+        .filter(|line| !line.is_empty() && !line.starts_with('#'));
+    let header: Vec<&str> = match has_header {
+        true => lines
+            .next()
+            .map(|line| line.split('\t').collect())
+            .unwrap_or_default(),
+        false => Vec::new(),
+    };
+
+    let mut chroms = Vec::new();
+    let mut starts = Vec::new();
+    let mut ends = Vec::new();
+    let mut data: Vec<Vec<Option<String>>> = Vec::new();
+    for line in lines {
+        let columns: Vec<&str> = line.split('\t').collect();
+        if columns.len() < 3 {
+            return Err(GRangesError::Bed3TooFewColumns(
+                columns.len(),
+                line.to_string(),
+            ));
+        }
+        let parse_position = |value: &str| {
+            value
+                .parse::<u64>()
+                .map_err(|_| GRangesError::InvalidColumnType {
+                    expected_type: "u64".to_string(),
+                    found_value: value.to_string(),
+                    line: line.to_string(),
+                })
+        };
+        chroms.push(columns[0].to_string());
+        starts.push(parse_position(columns[1])?);
+        ends.push(parse_position(columns[2])?);
+
+        // Pad ragged rows (with missing values), so all columns have a row per range.
+        let row = chroms.len() - 1;
+        while data.len() < columns.len() - 3 {
+            data.push(vec![None; row]);
+        }
+        for (i, column) in data.iter_mut().enumerate() {
+            let value = columns
+                .get(i + 3)
+                .filter(|value| !is_na_value(value))
+                .map(|value| value.to_string());
+            column.push(value);
+        }
+    }
+
+    let mut series = vec![
+        Series::new(CHROM_COLUMN, chroms),
+        Series::new(START_COLUMN, starts),
+        Series::new(END_COLUMN, ends),
+    ];
+    for (i, values) in data.into_iter().enumerate() {
+        let name = header
+            .get(i + 3)
+            .map(|name| name.to_string())
+            .unwrap_or_else(|| format!("column_{}", i + 4));
+        series.push(DataColumn::infer(values).into_series(&name));
+    }
+    Ok(DataFrame::new(series)?)
+}
+
+/// A [`Write`] that buffers TSV range output, and writes it as a columnar file
+/// (see [`ColumnarFormat`]) when flushed.
+///
+/// Since a columnar file can't be appended to, the whole table is kept in memory,
+/// and each flush (after new output was written) rewrites the file. The file is
+/// also written when the writer is dropped, if it wasn't flushed.
+#[cfg(feature = "polars")]
+pub struct ColumnarWriter {
+    path: PathBuf,
+    format: ColumnarFormat,
+    has_header: bool,
+    buffer: Vec<u8>,
+    /// The length of the buffer when the file was last written.
+    written: Option<usize>,
+}
+
+#[cfg(feature = "polars")]
+impl ColumnarWriter {
+    /// Create a new [`ColumnarWriter`], which writes TSV output to `path` as `format`.
+    /// If `has_header` is set, the first (non-metadata) line of output is the header.
+    pub fn new(path: impl Into<PathBuf>, format: ColumnarFormat, has_header: bool) -> Self {
+        Self {
+            path: path.into(),
+            format,
+            has_header,
+            buffer: Vec::new(),
+            written: None,
+        }
+    }
+
+    fn write_table(&self) -> Result<(), GRangesError> {
+        let tsv = std::str::from_utf8(&self.buffer)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+        #[allow(unused_mut, unused_variables)]
+        let mut df = tsv_to_dataframe(tsv, self.has_header)?;
+        match self.format {
+            #[cfg(feature = "arrow")]
+            ColumnarFormat::ArrowIpc => crate::io::arrow::write_dataframe_ipc(&mut df, &self.path),
+            #[cfg(feature = "parquet")]
+            ColumnarFormat::Parquet => {
+                crate::io::parquet::write_dataframe_parquet(&mut df, &self.path)
+            }
+            #[cfg(not(all(feature = "arrow", feature = "parquet")))]
+            format => Err(format.requires_feature(&self.path)),
+        }
+    }
+}
+
+#[cfg(feature = "polars")]
+impl Write for ColumnarWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.buffer.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if self.written == Some(self.buffer.len()) {
+            return Ok(());
+        }
+        self.write_table()
+            .map_err(|err| io::Error::other(err.to_string()))?;
+        self.written = Some(self.buffer.len());
+        Ok(())
+    }
+}
+
+#[cfg(feature = "polars")]
+impl Drop for ColumnarWriter {
+    fn drop(&mut self) {
+        if let Err(err) = self.flush() {
+            tracing::error!("could not write {}: {}", self.path.display(), err);
+        }
+    }
+}
+
+/// Create a [`Write`] for TSV range output that is written to `path` as a columnar
+/// file of `format`. This errors if granges wasn't compiled with support for `format`.
+#[cfg(feature = "polars")]
+pub fn create_columnar_output(
+    path: impl Into<PathBuf>,
+    format: ColumnarFormat,
+    has_header: bool,
+) -> Result<Box<dyn Write + Send>, GRangesError> {
+    let path = path.into();
+    if !format.is_enabled() {
+        return Err(format.requires_feature(&path));
+    }
+    Ok(Box::new(ColumnarWriter::new(path, format, has_header)))
+}
+
+/// Create a [`Write`] for TSV range output that is written to `path` as a columnar
+/// file of `format`. This errors if granges wasn't compiled with support for `format`.
+#[cfg(not(feature = "polars"))]
+pub fn create_columnar_output(
+    path: impl AsRef<Path>,
+    format: ColumnarFormat,
+    _has_header: bool,
+) -> Result<Box<dyn std::io::Write + Send>, GRangesError> {
+    Err(format.requires_feature(path))
+}
+
+#[cfg(all(test, feature = "polars"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tsv_to_dataframe() {
+        let tsv = "#metadata\nchrom\tstart\tend\tname\tscore\n\
+                   chr1\t0\t10\ta\t1\n\
+                   chr1\t10\t20\tb\t.\n\
+                   chr2\t5\t8\tc\t2.5\n";
+        let df = tsv_to_dataframe(tsv, true).unwrap();
+        assert_eq!(df.shape(), (3, 5));
+        assert_eq!(
+            df.get_column_names(),
+            vec!["chrom", "start", "end", "name", "score"]
+        );
+        assert_eq!(df.column("start").unwrap().dtype(), &DataType::UInt64);
+        assert_eq!(df.column("score").unwrap().dtype(), &DataType::Float64);
+        assert_eq!(df.column("score").unwrap().null_count(), 1);
+
+        // Without a header, data columns are named by their column number, and ragged
+        // rows are padded.
+        let df = tsv_to_dataframe("chr1\t0\t10\nchr1\t10\t20\t3\n", false).unwrap();
+        assert_eq!(
+            df.get_column_names(),
+            vec!["chrom", "start", "end", "column_4"]
+        );
+        assert_eq!(df.column("column_4").unwrap().dtype(), &DataType::Int64);
+        assert_eq!(df.column("column_4").unwrap().null_count(), 1);
+
+        assert!(matches!(
+            tsv_to_dataframe("chr1\tzero\t10\n", false),
+            Err(GRangesError::InvalidColumnType { .. })
+        ));
+    }
+
+    #[cfg(feature = "parquet")]
+    #[test]
+    fn test_columnar_writer_parquet() {
+        let file = tempfile::Builder::new()
+            .suffix(".parquet")
+            .tempfile()
+            .unwrap();
+        let format = ColumnarFormat::from_path(file.path()).unwrap();
+        assert_eq!(format, ColumnarFormat::Parquet);
+
+        let mut writer = create_columnar_output(file.path(), format, false).unwrap();
+        writer
+            .write_all(b"chr1\t0\t10\t1.5\nchr2\t3\t4\t2\n")
+            .unwrap();
+        writer.flush().unwrap();
+
+        let df = crate::io::parquet::read_dataframe_parquet(file.path()).unwrap();
+        assert_eq!(df.shape(), (2, 4));
+    }
+}
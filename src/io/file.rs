@@ -1,52 +1,140 @@
 //! Input/Output file handling with [`InputStream`] and [`OutputStream`].
 //!
 //! These types abstract over reading/writing both plaintext and gzip-compressed
-//! input/output.
+//! input/output. Compressed output is written as BGZF, which is compressed (and
+//! read back) in parallel (see [`crate::io::bgzf`]).
 
-use flate2::read::GzDecoder;
-use flate2::write::GzEncoder;
-use flate2::Compression;
+use flate2::read::MultiGzDecoder;
 use indexmap::IndexMap;
 use std::fs::File;
 use std::io::Write;
 use std::io::{self, BufWriter};
-use std::io::{BufRead, BufReader, Read};
+use std::io::{BufRead, BufReader, Cursor, Read};
 use std::path::PathBuf;
 
 use crate::error::GRangesError;
+use crate::genome::Genome;
+use crate::io::bgzf::{is_bgzf, BgzfReader, BgzfWriter};
+use crate::io::parsers::filters::in_restricted_seqnames;
+use crate::io::remote::{is_remote, open_remote};
 use crate::Position;
 
-/// Read a tab-delimited *genome file* of sequence (i.e. chromosome) names and their lengths.
+/// Read a tab-delimited *genome file* of sequence (i.e. chromosome) names and their lengths,
+/// in the order of the file. This also reads genome files with named columns (see
+/// [`Genome`], which keeps their aliases and assembly name too).
+///
+/// Sequences excluded by the global [`Restriction`](crate::io::parsers::Restriction)
+/// (e.g. from `--chroms`), if one is set, are left out.
 pub fn read_seqlens(
     filepath: impl Into<PathBuf>,
 ) -> Result<IndexMap<String, Position>, GRangesError> {
-    let input_file = InputStream::new(filepath);
-    let reader = input_file.reader()?;
-
-    let mut seqlens = IndexMap::new();
-    for result in reader.lines() {
-        let line = result?;
-        let mut columns = line.split('\t');
-        let seqname = columns.next().unwrap();
-        let length: Position = columns.next().unwrap().parse()?;
-        if seqlens.contains_key(seqname) {
-            return Err(GRangesError::InvalidGenomeFile(format!(
-                "sequence '{}' is duplicated",
-                seqname
-            )));
-        }
-        seqlens.insert(seqname.to_string(), length);
-    }
+    let mut seqlens = Genome::from_file(filepath)?.into_seqlens();
+    seqlens.retain(|seqname, _| in_restricted_seqnames(seqname));
     Ok(seqlens)
 }
 
-/// Check if a file is a gzipped by looking for the magic numbers
-fn is_gzipped_file(file_path: impl Into<PathBuf>) -> io::Result<bool> {
-    let mut file = File::open(file_path.into())?;
-    let mut buffer = [0; 2];
-    file.read_exact(&mut buffer)?;
+/// Read the contents of a *genome file* (see [`read_seqlens()`]) from memory, e.g.
+/// for use without a filesystem (such as in WebAssembly).
+pub fn read_seqlens_from_bytes(
+    bytes: impl Into<Vec<u8>>,
+) -> Result<IndexMap<String, Position>, GRangesError> {
+    let mut seqlens = Genome::from_bytes(bytes)?.into_seqlens();
+    seqlens.retain(|seqname, _| in_restricted_seqnames(seqname));
+    Ok(seqlens)
+}
 
-    Ok(buffer == [0x1f, 0x8b])
+/// Wrap in-memory data in a reader, decompressing it if it is gzip-compressed (which
+/// is detected by its magic number).
+pub fn bytes_reader(bytes: impl Into<Vec<u8>>) -> Box<dyn Read> {
+    let bytes = bytes.into();
+    if is_bgzf(&bytes) {
+        Box::new(BgzfReader::new(Cursor::new(bytes)))
+    } else if bytes.starts_with(&[0x1f, 0x8b]) {
+        Box::new(MultiGzDecoder::new(Cursor::new(bytes)))
+    } else {
+        Box::new(Cursor::new(bytes))
+    }
+}
+
+/// File extensions of gzip-compressed output.
+pub const GZIP_EXTENSIONS: &[&str] = &["gz", "bgz"];
+
+/// Returns whether `path` has a gzip file extension, e.g. `ranges.bed.gz`.
+pub fn is_gzip_path(path: impl Into<PathBuf>) -> bool {
+    path.into()
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| GZIP_EXTENSIONS.contains(&ext))
+}
+
+/// Create an output file, which is compressed as BGZF (in parallel) if it has a gzip
+/// file extension (see [`is_gzip_path()`]).
+pub fn create_output(path: impl Into<PathBuf>) -> io::Result<Box<dyn Write + Send>> {
+    let path = path.into();
+    let file = File::create(&path)?;
+    if is_gzip_path(&path) {
+        Ok(Box::new(BgzfWriter::new(file)))
+    } else {
+        Ok(Box::new(file))
+    }
+}
+
+/// File extensions recognized as Arrow IPC files.
+pub const ARROW_IPC_EXTENSIONS: &[&str] = &["arrow", "ipc", "feather"];
+
+/// Returns whether `path` has an Arrow IPC file extension, e.g. `ranges.arrow`.
+pub fn is_arrow_ipc_path(path: impl Into<PathBuf>) -> bool {
+    path.into()
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| ARROW_IPC_EXTENSIONS.contains(&ext))
+}
+
+/// File extensions recognized as Parquet files.
+pub const PARQUET_EXTENSIONS: &[&str] = &["parquet", "pq"];
+
+/// Returns whether `path` has a Parquet file extension, e.g. `matrix.parquet`.
+pub fn is_parquet_path(path: impl Into<PathBuf>) -> bool {
+    path.into()
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| PARQUET_EXTENSIONS.contains(&ext))
+}
+
+/// File extensions recognized as FASTA files.
+pub const FASTA_EXTENSIONS: &[&str] = &["fa", "fasta", "fna", "fas"];
+
+/// Returns whether `path` has a FASTA file extension, which may be followed by
+/// `.gz`, e.g. `genome.fa` or `genome.fa.gz`.
+pub fn is_fasta_path(path: impl Into<PathBuf>) -> bool {
+    let mut path = path.into();
+    if path.extension().is_some_and(|ext| ext == "gz") {
+        path.set_extension("");
+    }
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| FASTA_EXTENSIONS.contains(&ext))
+}
+
+/// Open a local file, or a remote URL (see [`crate::io::remote`]), decompressing it
+/// if it is gzip-compressed (which is detected by its magic number). BGZF input is
+/// decompressed in parallel.
+pub fn open_input(filepath: impl Into<PathBuf>) -> io::Result<Box<dyn Read>> {
+    let filepath = filepath.into();
+    let stream: Box<dyn Read> = if is_remote(&filepath) {
+        open_remote(&filepath.to_string_lossy())?
+    } else {
+        Box::new(File::open(filepath)?)
+    };
+    let mut reader = BufReader::new(stream);
+    let start = reader.fill_buf()?;
+    if is_bgzf(start) {
+        Ok(Box::new(BgzfReader::new(reader)))
+    } else if start.starts_with(&[0x1f, 0x8b]) {
+        Ok(Box::new(MultiGzDecoder::new(reader)))
+    } else {
+        Ok(Box::new(reader))
+    }
 }
 
 /// Represents an input file.
@@ -80,27 +168,28 @@ impl InputStream {
 
     /// Opens the file and returns a buffered reader.
     ///
-    /// If the file is gzip-compressed (indicated by a ".gz" extension), this method will
-    /// automatically handle the decompression.
+    /// If the file is gzip-compressed, this method will automatically handle the
+    /// decompression. URLs are read remotely, with the `remote` feature.
     ///
     /// # Returns
     ///
-    /// A result containing a `BufReader<Box<dyn Read>>` on success, or a `FileError` on failure.
+    /// A result containing a `BufReader<Box<dyn Read>>` on success, or a
+    /// [`GRangesError::FileError`] with the file's path on failure.
     ///
-    pub fn reader(&self) -> io::Result<BufReader<Box<dyn Read>>> {
-        let file = File::open(self.filepath.clone())?;
-        //let is_gzipped_name = self.filepath.ends_with(".gz");
-        let is_gzipped = is_gzipped_file(&self.filepath)?;
-        let reader: Box<dyn Read> = if is_gzipped {
-            Box::new(GzDecoder::new(file))
-        } else {
-            Box::new(file)
-        };
-        Ok(BufReader::new(reader))
+    pub fn reader(&self) -> Result<BufReader<Box<dyn Read>>, GRangesError> {
+        let stream = open_input(&self.filepath).map_err(|source| GRangesError::FileError {
+            path: self.filepath.clone(),
+            source,
+        })?;
+        Ok(BufReader::new(stream))
     }
 
     /// Collects comment lines and/or a line at the start of the file.
-    pub fn collect_metadata(&mut self, comment: &str, header: Option<&str>) -> io::Result<bool> {
+    pub fn collect_metadata(
+        &mut self,
+        comment: &str,
+        header: Option<&str>,
+    ) -> Result<bool, GRangesError> {
         let mut buf_reader = self.reader()?;
         let mut comments = Vec::new();
         let mut line = String::new();
@@ -144,7 +233,7 @@ impl InputStream {
     }
 
     /// Method to continue reading after skipping the comment and header lines.
-    pub fn continue_reading(&self) -> io::Result<BufReader<Box<dyn Read>>> {
+    pub fn continue_reading(&self) -> Result<BufReader<Box<dyn Read>>, GRangesError> {
         let mut buf_reader = self.reader()?;
         let mut skipped_lines = 0;
         let mut line = String::new();
@@ -198,26 +287,16 @@ impl OutputStream {
 
     /// Opens the file and returns a writer.
     ///
-    /// If the file path ends with ".gz", the file is treated as gzip-compressed, and the
-    /// function will handle compression automatically. If a header is set, it will be written
-    /// to the file.
+    /// If the file path ends with ".gz" (or ".bgz"), the file is treated as
+    /// gzip-compressed, and the function will handle compression automatically (see
+    /// [`create_output()`]). If a header is set, it will be written to the file.
     ///
     /// # Returns
     ///
     /// A result containing a `Box<dyn Write>` on success, or an `io::Error` on failure.
     pub fn writer(&self) -> io::Result<Box<dyn Write>> {
         let mut writer: Box<dyn Write> = match &self.destination {
-            OutputDestination::File(path) => {
-                let is_gzip = path.ends_with(".gz");
-                if is_gzip {
-                    Box::new(BufWriter::new(GzEncoder::new(
-                        File::create(path)?,
-                        Compression::default(),
-                    )))
-                } else {
-                    Box::new(BufWriter::new(File::create(path)?))
-                }
-            }
+            OutputDestination::File(path) => Box::new(BufWriter::new(create_output(path)?)),
             OutputDestination::Stdout => Box::new(BufWriter::new(io::stdout())),
         };
         // write header if one is set
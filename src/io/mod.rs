@@ -1,13 +1,25 @@
 //! Types and methods for reading and parsing input and writing output.
 
+#[cfg(feature = "arrow")]
+pub mod arrow;
+pub mod bgzf;
+pub mod binary;
+pub mod columnar;
 pub mod file;
+#[cfg(feature = "parquet")]
+pub mod parquet;
 pub mod parsers;
+pub mod remote;
+pub mod track;
 pub mod tsv;
 
 pub use file::{InputStream, OutputStream};
 pub use parsers::{
-    bed::{Bed3Iterator, Bed4Iterator, Bed5Iterator, BedlikeIterator},
+    bed::{
+        Bed3Iterator, Bed4Addition, Bed4Iterator, Bed5Addition, Bed5Iterator, Bed6Addition,
+        Bed6Iterator, BedlikeIterator, NarrowPeakAddition, NarrowPeakIterator,
+    },
     tsv::TsvRecordIterator,
     GenomicRangesFile, GenomicRangesParser,
 };
-pub use tsv::{TsvConfig, BED_TSV};
+pub use tsv::{TsvConfig, BEDTOOLS_TSV, BED_TSV};
@@ -0,0 +1,114 @@
+//! Reading and writing genomic ranges as [Apache Parquet](https://parquet.apache.org)
+//! files, through polars.
+//!
+//! Tables have the same layout as Arrow IPC range tables (see [`crate::io::arrow`]):
+//! `chrom`, `start`, and `end` columns, followed by any data columns.
+
+use std::{fs::File, path::PathBuf};
+
+use indexmap::IndexMap;
+use polars::prelude::*;
+
+use crate::{
+    error::GRangesError,
+    granges::{GRanges, GRangesEmpty},
+    ranges::{
+        vec::{VecRangesEmpty, VecRangesIndexed},
+        RangeIndexed,
+    },
+    traits::{IterableRangeContainer, Selection},
+    Position,
+};
+
+/// Write a range [`DataFrame`] to a Parquet file.
+pub fn write_dataframe_parquet(
+    df: &mut DataFrame,
+    path: impl Into<PathBuf>,
+) -> Result<(), GRangesError> {
+    let file = File::create(path.into())?;
+    ParquetWriter::new(file).finish(df)?;
+    Ok(())
+}
+
+/// Read a range [`DataFrame`] from a Parquet file.
+pub fn read_dataframe_parquet(path: impl Into<PathBuf>) -> Result<DataFrame, GRangesError> {
+    let file = File::open(path.into())?;
+    Ok(ParquetReader::new(file).finish()?)
+}
+
+impl GRanges<VecRangesIndexed, DataFrame> {
+    /// Read a new [`GRanges<VecRangesIndexed, DataFrame>`] from a Parquet file,
+    /// with any non-range columns in the [`DataFrame`] data container.
+    pub fn from_parquet(
+        path: impl Into<PathBuf>,
+        seqlens: &IndexMap<String, Position>,
+    ) -> Result<Self, GRangesError> {
+        GRanges::from_dataframe(&read_dataframe_parquet(path)?, seqlens)
+    }
+}
+
+impl GRangesEmpty<VecRangesEmpty> {
+    /// Read a new [`GRangesEmpty`] from a Parquet file, ignoring any data columns.
+    pub fn from_parquet(
+        path: impl Into<PathBuf>,
+        seqlens: &IndexMap<String, Position>,
+    ) -> Result<Self, GRangesError> {
+        GRangesEmpty::from_dataframe(&read_dataframe_parquet(path)?, seqlens)
+    }
+}
+
+impl<C> GRangesEmpty<C>
+where
+    C: IterableRangeContainer,
+{
+    /// Write these ranges to a Parquet file.
+    pub fn write_to_parquet(&self, path: impl Into<PathBuf>) -> Result<(), GRangesError> {
+        write_dataframe_parquet(&mut self.to_dataframe()?, path)
+    }
+}
+
+impl<C> GRanges<C, DataFrame>
+where
+    C: IterableRangeContainer<RangeType = RangeIndexed>,
+{
+    /// Write these ranges and their [`DataFrame`] data to a Parquet file.
+    pub fn write_to_parquet(&self, path: impl Into<PathBuf>) -> Result<(), GRangesError> {
+        write_dataframe_parquet(&mut self.to_dataframe()?, path)
+    }
+}
+
+impl<C, U> GRanges<C, Vec<U>>
+where
+    C: IterableRangeContainer<RangeType = RangeIndexed>,
+    U: Selection,
+{
+    /// Write these ranges and their data to a Parquet file, with a column
+    /// for each column in the data type's schema (see [`Selection`]).
+    pub fn write_to_parquet(&self, path: impl Into<PathBuf>) -> Result<(), GRangesError> {
+        write_dataframe_parquet(&mut self.to_dataframe()?, path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::prelude::*;
+
+    #[test]
+    fn test_parquet_roundtrip() {
+        let seqlens = seqlens! { "chr1" => 100, "chr2" => 100 };
+        let gr = GRangesEmpty::from_windows(&seqlens, 30, None, false).unwrap();
+
+        let file = tempfile::Builder::new()
+            .suffix(".parquet")
+            .tempfile()
+            .unwrap();
+        gr.write_to_parquet(file.path()).unwrap();
+
+        let gr_read = GRangesEmpty::from_parquet(file.path(), &seqlens).unwrap();
+        assert_eq!(gr_read.len(), gr.len());
+        assert!(gr_read
+            .to_dataframe()
+            .unwrap()
+            .equals(&gr.to_dataframe().unwrap()));
+    }
+}
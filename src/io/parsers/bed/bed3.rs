@@ -1,26 +1,34 @@
 //! BED3 Parsers, which are built off of the [`GenomicRangeRecordEmpty`].
 //!
 
-use crate::{io::TsvRecordIterator, ranges::GenomicRangeRecordEmpty, GRangesError};
+use super::fast::{parse_bed3_line, FastBedIterator};
+use crate::{io::parsers::filters::keep_parsed, ranges::GenomicRangeRecordEmpty, GRangesError};
 use std::path::PathBuf;
 
 /// An iterator over BED3 entries (which just contain ranges no data).
 #[derive(Debug)]
 pub struct Bed3Iterator {
-    iter: TsvRecordIterator<GenomicRangeRecordEmpty>,
+    iter: FastBedIterator<GenomicRangeRecordEmpty>,
 }
 
 impl Bed3Iterator {
     /// Creates a parsing iterator over a BED5 file.
     pub fn new(filepath: impl Into<PathBuf>) -> Result<Self, GRangesError> {
-        let iter = TsvRecordIterator::new(filepath)?;
+        let iter = FastBedIterator::new(filepath, parse_bed3_line)?;
         Ok(Self { iter })
     }
+
+    /// Creates a parsing iterator over in-memory BED3 data (which may be
+    /// gzip-compressed).
+    pub fn from_bytes(bytes: impl Into<Vec<u8>>) -> Self {
+        let iter = FastBedIterator::from_bytes(bytes, parse_bed3_line);
+        Self { iter }
+    }
 }
 
 impl Iterator for Bed3Iterator {
     type Item = Result<GenomicRangeRecordEmpty, GRangesError>;
     fn next(&mut self) -> Option<Self::Item> {
-        self.iter.next()
+        self.iter.find(keep_parsed)
     }
 }
@@ -1,7 +1,12 @@
 //! BED4 Parsers, which are built off of the [`GenomicRangeRecordEmpty`]
 //! and [`Bed4Addition`].
 
-use crate::{io::TsvRecordIterator, ranges::GenomicRangeRecord, GRangesError};
+use crate::{
+    io::{parsers::filters::keep_parsed, TsvConfig, TsvRecordIterator},
+    ranges::GenomicRangeRecord,
+    traits::TsvFormat,
+    GRangesError,
+};
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
 
@@ -15,6 +20,12 @@ pub struct Bed4Addition {
     pub name: String,
 }
 
+impl TsvFormat for Bed4Addition {
+    fn write_columns(&self, columns: &mut Vec<String>, _config: &TsvConfig) {
+        columns.push(self.name.clone());
+    }
+}
+
 /// An iterator over BED4 entries, which contain the three
 /// range entries (sequence name, start and end positions),
 /// and a feature name
@@ -35,12 +46,19 @@ impl Bed4Iterator {
 
         Ok(Self { iter })
     }
+
+    /// Creates a parsing iterator over in-memory BED4 data (which may be
+    /// gzip-compressed).
+    pub fn from_bytes(bytes: impl Into<Vec<u8>>) -> Self {
+        let iter = TsvRecordIterator::from_bytes(bytes);
+        Self { iter }
+    }
 }
 
 impl Iterator for Bed4Iterator {
     type Item = Result<GenomicRangeRecord<Bed4Addition>, GRangesError>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        self.iter.next()
+        self.iter.find(keep_parsed)
     }
 }
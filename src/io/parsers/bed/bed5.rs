@@ -1,8 +1,16 @@
 //! BED5 Parsers, which are built off of the [`GenomicRangeRecordEmpty`]
 //! and [`Bed5Addition`].
 
-use super::bed_missing;
-use crate::{io::TsvRecordIterator, ranges::GenomicRangeRecord, GRangesError};
+use super::{
+    bed_missing,
+    fast::{parse_bed5_line, FastBedIterator},
+};
+use crate::{
+    io::{parsers::filters::keep_parsed, TsvConfig},
+    ranges::GenomicRangeRecord,
+    traits::TsvFormat,
+    GRangesError,
+};
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
 
@@ -21,6 +29,13 @@ pub struct Bed5Addition {
     pub score: Option<f64>,
 }
 
+impl TsvFormat for Bed5Addition {
+    fn write_columns(&self, columns: &mut Vec<String>, config: &TsvConfig) {
+        columns.push(self.name.clone());
+        self.score.write_columns(columns, config);
+    }
+}
+
 /// An iterator over BED5 entries, which contain the three
 /// range entries (sequence name, start and end positions),
 /// a feature name, and a score.
@@ -31,22 +46,29 @@ pub struct Bed5Addition {
 // TODO strict type?
 #[derive(Debug)]
 pub struct Bed5Iterator {
-    iter: TsvRecordIterator<GenomicRangeRecord<Bed5Addition>>,
+    iter: FastBedIterator<GenomicRangeRecord<Bed5Addition>>,
 }
 
 impl Bed5Iterator {
     /// Creates a parsing iterator over a BED5 file.
     pub fn new(filepath: impl Into<PathBuf>) -> Result<Self, GRangesError> {
-        let iter = TsvRecordIterator::new(filepath)?;
+        let iter = FastBedIterator::new(filepath, parse_bed5_line)?;
 
         Ok(Self { iter })
     }
+
+    /// Creates a parsing iterator over in-memory BED5 data (which may be
+    /// gzip-compressed).
+    pub fn from_bytes(bytes: impl Into<Vec<u8>>) -> Self {
+        let iter = FastBedIterator::from_bytes(bytes, parse_bed5_line);
+        Self { iter }
+    }
 }
 
 impl Iterator for Bed5Iterator {
     type Item = Result<GenomicRangeRecord<Bed5Addition>, GRangesError>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        self.iter.next()
+        self.iter.find(keep_parsed)
     }
 }
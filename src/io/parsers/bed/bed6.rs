@@ -0,0 +1,71 @@
+//! BED6 Parsers, which are built off of the [`GenomicRangeRecord`]
+//! and [`Bed6Addition`].
+
+use super::{bed_missing, bed_strand, Strand};
+use crate::{
+    io::{parsers::filters::keep_parsed, TsvConfig, TsvRecordIterator},
+    ranges::GenomicRangeRecord,
+    traits::TsvFormat,
+    GRangesError,
+};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+/// The additional three BED6 columns.
+///
+/// # Fields
+/// * `name`: the feature name.
+/// * `score`: a score.
+/// * `strand`: the strand, or `None` for features with no strand (`.`).
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Bed6Addition {
+    pub name: String,
+    #[serde(deserialize_with = "bed_missing")]
+    pub score: Option<f64>,
+    #[serde(deserialize_with = "bed_strand")]
+    pub strand: Option<Strand>,
+}
+
+impl TsvFormat for Bed6Addition {
+    fn write_columns(&self, columns: &mut Vec<String>, config: &TsvConfig) {
+        columns.push(self.name.clone());
+        self.score.write_columns(columns, config);
+        self.strand.write_columns(columns, config);
+    }
+}
+
+/// An iterator over BED6 entries, which contain the three
+/// range entries (sequence name, start and end positions),
+/// a feature name, a score, and a strand.
+///
+/// Like the [`Bed5Addition`](super::Bed5Addition), the [`Bed6Addition`]
+/// is *permissive*, allowing missing scores.
+#[derive(Debug)]
+pub struct Bed6Iterator {
+    iter: TsvRecordIterator<GenomicRangeRecord<Bed6Addition>>,
+}
+
+impl Bed6Iterator {
+    /// Creates a parsing iterator over a BED6 file.
+    pub fn new(filepath: impl Into<PathBuf>) -> Result<Self, GRangesError> {
+        let iter = TsvRecordIterator::new(filepath)?;
+
+        Ok(Self { iter })
+    }
+
+    /// Creates a parsing iterator over in-memory BED6 data (which may be
+    /// gzip-compressed).
+    pub fn from_bytes(bytes: impl Into<Vec<u8>>) -> Self {
+        let iter = TsvRecordIterator::from_bytes(bytes);
+        Self { iter }
+    }
+}
+
+impl Iterator for Bed6Iterator {
+    type Item = Result<GenomicRangeRecord<Bed6Addition>, GRangesError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.iter.find(keep_parsed)
+    }
+}
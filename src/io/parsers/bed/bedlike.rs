@@ -7,7 +7,12 @@ use std::{
 
 use crate::{
     io::{
-        parsers::{tsv::build_tsv_reader, utils::parse_column},
+        file::bytes_reader,
+        parsers::{
+            filters::in_restriction,
+            tsv::{build_tsv_reader, IN_MEMORY_SOURCE},
+            utils::{is_header_line, parse_column, skip_malformed},
+        },
         InputStream,
     },
     ranges::GenomicRangeRecord,
@@ -17,13 +22,27 @@ use crate::{
 // for BedlikeIterator only, TODO needed?
 pub const PARSE_CAPACITY: usize = 512;
 
+/// A function parsing a line into a range, with the line's other columns as data.
+type LineParser = fn(&str) -> Result<GenomicRangeRecord<Option<String>>, GRangesError>;
+
 /// A lazy parser for BED-like files.
 /// yields [`GenomicRangeRecord<Option<Vec<String>>>`] entries. If the file is a BED3 file,
 /// the data in the [`GenomicRangeRecord`] will be set to `None`, since there are no remaining
 /// string columns to parse.
+///
+/// GFF/GTF, VCF, and SAF files can also be read as BED-like files (with
+/// [`BedlikeIterator::new_gff()`], [`BedlikeIterator::new_vcf()`], and
+/// [`BedlikeIterator::new_saf()`]), with their 1-based positions converted to 0-based,
+/// right-exclusive ranges.
 pub struct BedlikeIterator {
     reader: BufReader<Box<dyn std::io::Read>>,
     line_buffer: String,
+    parse_line: LineParser,
+    /// The start of a header line to skip (e.g. SAF's column names), if any.
+    header: Option<&'static str>,
+    /// The input's name, and the number of lines read, for errors.
+    source: String,
+    line_number: u64,
 }
 
 impl std::fmt::Debug for BedlikeIterator {
@@ -37,6 +56,8 @@ impl BedlikeIterator {
     /// assumes the first three columns are the sequence name, start (0-indexed and inclusive),
     /// and end (0-indeed and exclusive) positions.
     pub fn new(filepath: impl Into<PathBuf>) -> Result<Self, GRangesError> {
+        let filepath = filepath.into();
+        let source = filepath.display().to_string();
         let input_file = InputStream::new(filepath);
         // let _has_metadata = input_file.collect_metadata("#", None);
         // let reader = input_file.continue_reading()?;
@@ -45,8 +66,56 @@ impl BedlikeIterator {
         Ok(Self {
             reader,
             line_buffer,
+            parse_line: parse_bed_lazy,
+            header: None,
+            source,
+            line_number: 0,
+        })
+    }
+
+    /// Create a new lazy-parsing iterator over a GFF or GTF file, yielding each
+    /// feature's range, with the other columns (source, type, score, strand, phase,
+    /// and attributes) as data.
+    pub fn new_gff(filepath: impl Into<PathBuf>) -> Result<Self, GRangesError> {
+        Ok(Self {
+            parse_line: parse_gff_lazy,
+            ..Self::new(filepath)?
+        })
+    }
+
+    /// Create a new lazy-parsing iterator over a VCF file, yielding each variant's
+    /// range (covering its reference allele), with the other columns (from ID
+    /// onwards) as data.
+    pub fn new_vcf(filepath: impl Into<PathBuf>) -> Result<Self, GRangesError> {
+        Ok(Self {
+            parse_line: parse_vcf_lazy,
+            ..Self::new(filepath)?
         })
     }
+
+    /// Create a new lazy-parsing iterator over a SAF (featureCounts' Simplified
+    /// Annotation Format) file, yielding each feature's range, with BED6-like data: the
+    /// gene ID, a missing score (`.`), and the strand (followed by any other columns).
+    pub fn new_saf(filepath: impl Into<PathBuf>) -> Result<Self, GRangesError> {
+        Ok(Self {
+            parse_line: parse_saf_lazy,
+            header: Some(SAF_HEADER),
+            ..Self::new(filepath)?
+        })
+    }
+
+    /// Create a new lazy-parsing iterator over in-memory Bed-like TSV data (which may
+    /// be gzip-compressed).
+    pub fn from_bytes(bytes: impl Into<Vec<u8>>) -> Self {
+        Self {
+            reader: BufReader::new(bytes_reader(bytes)),
+            line_buffer: String::with_capacity(PARSE_CAPACITY),
+            parse_line: parse_bed_lazy,
+            header: None,
+            source: IN_MEMORY_SOURCE.to_string(),
+            line_number: 0,
+        }
+    }
 }
 
 impl Iterator for BedlikeIterator {
@@ -60,11 +129,34 @@ impl Iterator for BedlikeIterator {
             match self.reader.read_line(&mut self.line_buffer) {
                 Ok(0) => return None,
                 Ok(_) => {
-                    if !self.line_buffer.starts_with('#') {
+                    self.line_number += 1;
+                    // a GFF file's sequences follow its features
+                    if self.line_buffer.starts_with("##FASTA") {
+                        return None;
+                    }
+                    // skip the metadata/comment lines, and track and browser lines
+                    let is_header = self
+                        .header
+                        .is_some_and(|header| self.line_buffer.starts_with(header));
+                    if !is_header_line(&self.line_buffer) && !is_header {
                         let line = self.line_buffer.trim_end();
-                        return Some(parse_bed_lazy(line));
+                        let error = match (self.parse_line)(line) {
+                            Ok(record)
+                                if !in_restriction(&record.seqname, record.start, record.end) =>
+                            {
+                                continue;
+                            }
+                            Ok(record) => return Some(Ok(record)),
+                            Err(error) => GRangesError::ParseError {
+                                file: self.source.clone(),
+                                line: self.line_number,
+                                message: error.to_string(),
+                            },
+                        };
+                        if let Some(error) = skip_malformed(error) {
+                            return Some(Err(error));
+                        }
                     }
-                    // skip the metadata/comment character
                 }
                 Err(e) => return Some(Err(GRangesError::IOError(e))),
             }
@@ -104,6 +196,119 @@ pub fn parse_bed_lazy(line: &str) -> Result<GenomicRangeRecord<Option<String>>,
     })
 }
 
+/// Lazily parses a GFF or GTF line into its feature's range (converting the 1-based,
+/// inclusive start and end positions), storing the other columns as a `String`.
+pub fn parse_gff_lazy(line: &str) -> Result<GenomicRangeRecord<Option<String>>, GRangesError> {
+    let columns: Vec<&str> = line.splitn(6, '\t').collect();
+    if columns.len() < 5 {
+        return Err(GRangesError::BedTooFewColumns(
+            columns.len(),
+            9,
+            line.to_string(),
+        ));
+    }
+
+    let start: Position = parse_column(columns[3], line)?;
+    let end: Position = parse_column(columns[4], line)?;
+    if start == 0 || start > end {
+        return Err(GRangesError::InvalidGenomicRange {
+            seqname: Some(columns[0].to_string()),
+            start,
+            end,
+        });
+    }
+    let mut data = format!("{}\t{}", columns[1], columns[2]);
+    if let Some(rest) = columns.get(5) {
+        data.push('\t');
+        data.push_str(rest);
+    }
+
+    Ok(GenomicRangeRecord {
+        seqname: columns[0].to_string(),
+        start: start - 1,
+        end,
+        data: Some(data),
+    })
+}
+
+/// Lazily parses a VCF line into the range of its reference allele (converting the
+/// 1-based position), storing the columns from ID onwards as a `String`.
+pub fn parse_vcf_lazy(line: &str) -> Result<GenomicRangeRecord<Option<String>>, GRangesError> {
+    let columns: Vec<&str> = line.splitn(3, '\t').collect();
+    let reference = columns.get(2).and_then(|rest| rest.split('\t').nth(1));
+    let (Some(rest), Some(reference)) = (columns.get(2), reference) else {
+        return Err(GRangesError::BedTooFewColumns(
+            line.split('\t').count(),
+            5,
+            line.to_string(),
+        ));
+    };
+
+    let position: Position = parse_column(columns[1], line)?;
+    if position == 0 {
+        return Err(GRangesError::InvalidGenomicRange {
+            seqname: Some(columns[0].to_string()),
+            start: position,
+            end: position,
+        });
+    }
+    let start = position - 1;
+    let end = Position::try_from(reference.len().max(1))
+        .ok()
+        .and_then(|length| start.checked_add(length))
+        .ok_or_else(|| GRangesError::InvalidGenomicRange {
+            seqname: Some(columns[0].to_string()),
+            start,
+            end: Position::MAX,
+        })?;
+
+    Ok(GenomicRangeRecord {
+        seqname: columns[0].to_string(),
+        start,
+        end,
+        data: Some(rest.to_string()),
+    })
+}
+
+/// The column names of a SAF file, which its first line may have.
+pub const SAF_HEADER: &str = "GeneID\tChr\tStart\tEnd\tStrand";
+
+/// Lazily parses a SAF line into its feature's range (converting the 1-based,
+/// inclusive start and end positions), storing its gene ID, a missing score, and its
+/// strand (and any other columns) as a BED6-like `String`.
+pub fn parse_saf_lazy(line: &str) -> Result<GenomicRangeRecord<Option<String>>, GRangesError> {
+    let columns: Vec<&str> = line.splitn(6, '\t').collect();
+    if columns.len() < 5 {
+        return Err(GRangesError::BedTooFewColumns(
+            columns.len(),
+            5,
+            line.to_string(),
+        ));
+    }
+
+    let start: Position = parse_column(columns[2], line)?;
+    let end: Position = parse_column(columns[3], line)?;
+    if start == 0 || start > end {
+        return Err(GRangesError::InvalidGenomicRange {
+            seqname: Some(columns[1].to_string()),
+            start,
+            end,
+        });
+    }
+    let mut data = format!("{}\t.\t{}", columns[0], columns[4]);
+    if let Some(rest) = columns.get(5) {
+        data.push('\t');
+        data.push_str(rest);
+    }
+
+    Ok(GenomicRangeRecord {
+        seqname: columns[1].to_string(),
+        start: start - 1,
+        end,
+        data: Some(data),
+    })
+}
+
 /// Inspect the first line to check that it looks like a valid BED-like
 /// file, i.e. the first column is there (there are no reasonable checks
 /// for sequence names other than presence), and the next to columns can
@@ -140,7 +345,13 @@ pub fn valid_bedlike(filepath: impl Into<PathBuf>) -> Result<bool, GRangesError>
 
 #[cfg(test)]
 mod tests {
-    use super::valid_bedlike;
+    use super::{parse_gff_lazy, parse_saf_lazy, parse_vcf_lazy, valid_bedlike, BedlikeIterator};
+    use crate::{
+        io::{Bed3Iterator, Bed5Iterator},
+        GRangesError, Position,
+    };
+    use flate2::{write::GzEncoder, Compression};
+    use std::io::Write;
 
     #[test]
     fn test_valid_bedlike() {
@@ -158,4 +369,103 @@ mod tests {
             true
         );
     }
+
+    #[test]
+    fn test_from_bytes() {
+        let bed = "chr1\t10\t20\tname\t1.5\n# comment\nchr2\t0\t5\tother\t.\n";
+        let records: Vec<_> = Bed5Iterator::from_bytes(bed)
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].data.score, Some(1.5));
+        assert_eq!(records[1].data.score, None);
+
+        let records: Vec<_> = BedlikeIterator::from_bytes(bed)
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(records[1].data.as_deref(), Some("other\t."));
+
+        // gzip-compressed data is detected
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(b"chr1\t10\t20\nchr2\t0\t5\n").unwrap();
+        let records: Vec<_> = Bed3Iterator::from_bytes(encoder.finish().unwrap())
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!((records[1].start, records[1].end), (0, 5));
+    }
+
+    #[test]
+    fn test_parse_gff_vcf_lazy() {
+        let gff = "chr1\tsrc\tgene\t11\t20\t.\t+\t.\tID=g1";
+        let record = parse_gff_lazy(gff).unwrap();
+        assert_eq!((record.start, record.end), (10, 20));
+        assert_eq!(record.data.as_deref(), Some("src\tgene\t.\t+\t.\tID=g1"));
+        assert!(parse_gff_lazy("chr1\tsrc\tgene\t0\t20").is_err());
+
+        let vcf = "chr1\t100\trs1\tAC\tA\t50\tPASS\t.";
+        let record = parse_vcf_lazy(vcf).unwrap();
+        assert_eq!((record.start, record.end), (99, 101));
+        assert_eq!(record.data.as_deref(), Some("rs1\tAC\tA\t50\tPASS\t."));
+        assert!(parse_vcf_lazy("chr1\t100").is_err());
+        // a range past the maximum position is an error, not an overflow
+        let vcf = format!("chr1\t{}\t.\tACGT\tA", Position::MAX);
+        assert!(parse_vcf_lazy(&vcf).is_err());
+    }
+
+    #[test]
+    fn test_parse_saf_lazy() {
+        let record = parse_saf_lazy("gene1\tchr1\t11\t20\t-").unwrap();
+        assert_eq!(record.seqname, "chr1");
+        assert_eq!((record.start, record.end), (10, 20));
+        assert_eq!(record.data.as_deref(), Some("gene1\t.\t-"));
+        assert!(parse_saf_lazy("gene1\tchr1\t0\t20\t+").is_err());
+        assert!(parse_saf_lazy("gene1\tchr1\t11\t20").is_err());
+
+        // the header line is skipped
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("genes.saf");
+        std::fs::write(
+            &path,
+            "GeneID\tChr\tStart\tEnd\tStrand\ngene1\tchr1\t11\t20\t+\n",
+        )
+        .unwrap();
+        let records: Vec<_> = BedlikeIterator::new_saf(&path)
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].data.as_deref(), Some("gene1\t.\t+"));
+    }
+
+    #[test]
+    fn test_parse_error_line_number() {
+        let bed = "chr1\t10\t20\tname\t1.5\n# comment\nchr1\tten\t20\tname\tbad\n";
+        let error = BedlikeIterator::from_bytes(bed)
+            .nth(1)
+            .unwrap()
+            .unwrap_err();
+        assert!(
+            matches!(error, GRangesError::ParseError { line: 3, .. }),
+            "{:?}",
+            error
+        );
+        assert!(error.to_string().contains("'ten'"));
+
+        let error = Bed5Iterator::from_bytes(bed).nth(1).unwrap().unwrap_err();
+        assert!(
+            matches!(error, GRangesError::ParseError { line: 3, .. }),
+            "{:?}",
+            error
+        );
+    }
+
+    #[test]
+    fn test_missing_file() {
+        let error = BedlikeIterator::new("tests_data/no_such_file.bed").unwrap_err();
+        let GRangesError::FileError { path, .. } = &error else {
+            panic!("expected a FileError, got {:?}", error);
+        };
+        assert!(path.ends_with("no_such_file.bed"));
+        assert!(std::error::Error::source(&error).is_some());
+    }
 }
@@ -0,0 +1,388 @@
+//! A fast, bytewise parser for the common BED3 and BED5 formats.
+//!
+//! The general TSV parsing iterators ([`TsvRecordIterator`]) deserialize each row
+//! with [`serde`] through the [`csv`] crate, which is flexible but has a
+//! per-field overhead. BED3 and BED5 rows are simple enough to parse directly: the
+//! tab-delimited fields are split with [`memchr`] (which uses SIMD instructions where
+//! available), and positions are parsed from their digits without going through a
+//! string. [`Bed3Iterator`] and [`Bed5Iterator`] use this parser.
+//!
+//! Like the general TSV parser, empty lines and lines starting with `'#'` are skipped,
+//! columns after those of the format are ignored, and malformed lines are reported as
+//! [`GRangesError::ParseError`] (or skipped, see
+//! [`set_skip_errors()`](crate::io::parsers::utils::set_skip_errors)).
+//!
+//! The `_interned` parsers intern each line's sequence name in a [`Genome`] directly
+//! from the line, so that no [`String`] is allocated per record (see
+//! [`Genome::intern_bed3()`]).
+//!
+//! [`TsvRecordIterator`]: crate::io::TsvRecordIterator
+//! [`Bed3Iterator`]: crate::io::Bed3Iterator
+//! [`Bed5Iterator`]: crate::io::Bed5Iterator
+
+use memchr::memchr;
+use std::io::{BufRead, BufReader, Read};
+use std::path::PathBuf;
+
+use super::Bed5Addition;
+use crate::{
+    error::GRangesError,
+    genome::{Genome, InternedRecord, SeqId},
+    io::{
+        file::{bytes_reader, open_input},
+        parsers::{filters::in_restriction, tsv::IN_MEMORY_SOURCE, utils::skip_malformed},
+        tsv::is_na_value,
+    },
+    ranges::{GenomicRangeRecord, GenomicRangeRecordEmpty},
+    Position,
+};
+
+/// The tab-delimited fields of a line, split lazily.
+struct Fields<'a> {
+    rest: Option<&'a [u8]>,
+    /// The 1-based column number of the last field returned.
+    column: usize,
+}
+
+impl<'a> Fields<'a> {
+    fn new(line: &'a [u8]) -> Self {
+        Self {
+            rest: Some(line),
+            column: 0,
+        }
+    }
+
+    /// The next field, or an error message if there are no more.
+    fn next_field(&mut self) -> Result<&'a [u8], String> {
+        let rest = self.rest.ok_or_else(|| {
+            format!(
+                "expected at least {} columns, found {}",
+                self.column + 1,
+                self.column
+            )
+        })?;
+        self.column += 1;
+        match memchr(b'\t', rest) {
+            Some(tab) => {
+                self.rest = Some(&rest[tab + 1..]);
+                Ok(&rest[..tab])
+            }
+            None => {
+                self.rest = None;
+                Ok(rest)
+            }
+        }
+    }
+
+    /// The next field, as a string.
+    fn next_str(&mut self) -> Result<&'a str, String> {
+        let field = self.next_field()?;
+        std::str::from_utf8(field).map_err(|_| format!("column {}: invalid UTF-8", self.column))
+    }
+
+    /// The next field, as a position.
+    fn next_position(&mut self) -> Result<Position, String> {
+        let field = self.next_field()?;
+        parse_position(field).ok_or_else(|| {
+            format!(
+                "column {}: invalid position '{}'",
+                self.column,
+                String::from_utf8_lossy(field)
+            )
+        })
+    }
+}
+
+/// Parse a position from its decimal digits, or `None` if it is empty, has other
+/// characters, or overflows.
+pub fn parse_position(bytes: &[u8]) -> Option<Position> {
+    if bytes.is_empty() {
+        return None;
+    }
+    bytes.iter().try_fold(0 as Position, |value, &byte| {
+        let digit = byte.wrapping_sub(b'0');
+        if digit > 9 {
+            return None;
+        }
+        value.checked_mul(10)?.checked_add(digit as Position)
+    })
+}
+
+/// Parse the first three columns of a BED line.
+fn parse_ranges<'a>(fields: &mut Fields<'a>) -> Result<(&'a str, Position, Position), String> {
+    Ok((
+        fields.next_str()?,
+        fields.next_position()?,
+        fields.next_position()?,
+    ))
+}
+
+/// Parse a BED3 line (ignoring any further columns).
+pub fn parse_bed3_line(line: &[u8]) -> Result<GenomicRangeRecordEmpty, String> {
+    let (seqname, start, end) = parse_ranges(&mut Fields::new(line))?;
+    Ok(GenomicRangeRecordEmpty {
+        seqname: seqname.to_string(),
+        start,
+        end,
+    })
+}
+
+/// Parse the first three columns of a BED line, interning the sequence name in
+/// `genome`. Ranges outside the current restriction (see [`in_restriction()`]) are
+/// `None`, and sequences not in `genome` are an error.
+fn parse_interned_ranges(
+    fields: &mut Fields,
+    genome: &Genome,
+) -> Result<Option<(SeqId, Position, Position)>, String> {
+    let (seqname, start, end) = parse_ranges(fields)?;
+    if !in_restriction(seqname, start, end) {
+        return Ok(None);
+    }
+    let seq_id = genome
+        .seq_id(seqname)
+        .ok_or_else(|| format!("sequence '{}' is not in the genome", seqname))?;
+    Ok(Some((seq_id, start, end)))
+}
+
+/// Parse a BED3 line like [`parse_bed3_line()`], but interning its sequence name in
+/// `genome` (see [`parse_interned_ranges()`]).
+pub fn parse_bed3_line_interned(
+    line: &[u8],
+    genome: &Genome,
+) -> Result<Option<InternedRecord<()>>, String> {
+    let ranges = parse_interned_ranges(&mut Fields::new(line), genome)?;
+    Ok(ranges.map(|(seq_id, start, end)| InternedRecord {
+        seq_id,
+        start,
+        end,
+        data: (),
+    }))
+}
+
+/// Parse the name and score columns of a BED5 line. A missing score (see
+/// [`is_na_value()`]) is `None`.
+fn parse_bed5_addition(fields: &mut Fields) -> Result<Bed5Addition, String> {
+    let name = fields.next_str()?.to_string();
+    let score = fields.next_str()?;
+    let score = if is_na_value(score) {
+        None
+    } else {
+        let column = fields.column;
+        let score = score
+            .parse()
+            .map_err(|err| format!("column {}: parsing error: {}", column, err))?;
+        Some(score)
+    };
+    Ok(Bed5Addition { name, score })
+}
+
+/// Parse a BED5 line (ignoring any further columns). A missing score (see
+/// [`is_na_value()`]) is `None`.
+pub fn parse_bed5_line(line: &[u8]) -> Result<GenomicRangeRecord<Bed5Addition>, String> {
+    let mut fields = Fields::new(line);
+    let (seqname, start, end) = parse_ranges(&mut fields)?;
+    Ok(GenomicRangeRecord {
+        seqname: seqname.to_string(),
+        start,
+        end,
+        data: parse_bed5_addition(&mut fields)?,
+    })
+}
+
+/// Parse a BED5 line like [`parse_bed5_line()`], but interning its sequence name in
+/// `genome` (see [`parse_interned_ranges()`]).
+pub fn parse_bed5_line_interned(
+    line: &[u8],
+    genome: &Genome,
+) -> Result<Option<InternedRecord<Bed5Addition>>, String> {
+    let mut fields = Fields::new(line);
+    let Some((seq_id, start, end)) = parse_interned_ranges(&mut fields, genome)? else {
+        return Ok(None);
+    };
+    Ok(Some(InternedRecord {
+        seq_id,
+        start,
+        end,
+        data: parse_bed5_addition(&mut fields)?,
+    }))
+}
+
+/// A parsing iterator over the lines of a BED file, with a bytewise `parse` function
+/// for each line (e.g. [`parse_bed3_line()`], or a closure).
+pub struct FastBedIterator<T, P = fn(&[u8]) -> Result<T, String>> {
+    reader: BufReader<Box<dyn Read>>,
+    parse: P,
+    line: Vec<u8>,
+    line_number: u64,
+    source: String,
+}
+
+impl<T, P> std::fmt::Debug for FastBedIterator<T, P> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FastBedIterator")
+            .field("source", &self.source)
+            .field("line_number", &self.line_number)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<T> FastBedIterator<T> {
+    /// Create a new parsing iterator over a (possibly gzip-compressed) file, parsing
+    /// each line with `parse`.
+    pub fn new(
+        filepath: impl Into<PathBuf>,
+        parse: fn(&[u8]) -> Result<T, String>,
+    ) -> Result<Self, GRangesError> {
+        Self::with_parser(filepath, parse)
+    }
+
+    /// Create a new parsing iterator over in-memory data (which may be
+    /// gzip-compressed), parsing each line with `parse`.
+    pub fn from_bytes(bytes: impl Into<Vec<u8>>, parse: fn(&[u8]) -> Result<T, String>) -> Self {
+        Self::from_bytes_with_parser(bytes, parse)
+    }
+}
+
+impl<T, P> FastBedIterator<T, P>
+where
+    P: FnMut(&[u8]) -> Result<T, String>,
+{
+    /// Create a new parsing iterator over a (possibly gzip-compressed) file, parsing
+    /// each line with `parse`, which may be a closure (e.g. one that borrows a
+    /// [`Genome`] to intern sequence names).
+    pub fn with_parser(filepath: impl Into<PathBuf>, parse: P) -> Result<Self, GRangesError> {
+        let filepath = filepath.into();
+        let stream = open_input(&filepath).map_err(|source| GRangesError::FileError {
+            path: filepath.clone(),
+            source,
+        })?;
+        Ok(Self::from_reader(
+            stream,
+            parse,
+            filepath.display().to_string(),
+        ))
+    }
+
+    /// Create a new parsing iterator over in-memory data (which may be
+    /// gzip-compressed), parsing each line with `parse`.
+    pub fn from_bytes_with_parser(bytes: impl Into<Vec<u8>>, parse: P) -> Self {
+        Self::from_reader(bytes_reader(bytes), parse, IN_MEMORY_SOURCE.to_string())
+    }
+
+    fn from_reader(stream: Box<dyn Read>, parse: P, source: String) -> Self {
+        Self {
+            reader: BufReader::with_capacity(1 << 16, stream),
+            parse,
+            line: Vec::new(),
+            line_number: 0,
+            source,
+        }
+    }
+}
+
+impl<T, P> Iterator for FastBedIterator<T, P>
+where
+    P: FnMut(&[u8]) -> Result<T, String>,
+{
+    type Item = Result<T, GRangesError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            self.line.clear();
+            match self.reader.read_until(b'\n', &mut self.line) {
+                Ok(0) => return None,
+                Ok(_) => {}
+                Err(err) => return Some(Err(err.into())),
+            }
+            self.line_number += 1;
+            let mut line = self.line.as_slice();
+            while let Some((&(b'\n' | b'\r'), rest)) = line.split_last() {
+                line = rest;
+            }
+            if line.is_empty() || line[0] == b'#' {
+                continue;
+            }
+            let error = match (self.parse)(line) {
+                Ok(record) => return Some(Ok(record)),
+                Err(message) => GRangesError::ParseError {
+                    file: self.source.clone(),
+                    line: self.line_number,
+                    message,
+                },
+            };
+            if let Some(error) = skip_malformed(error) {
+                return Some(Err(error));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_position() {
+        assert_eq!(parse_position(b"0"), Some(0));
+        assert_eq!(parse_position(b"123456"), Some(123456));
+        assert_eq!(parse_position(b""), None);
+        assert_eq!(parse_position(b"-1"), None);
+        assert_eq!(parse_position(b"12a"), None);
+        assert_eq!(parse_position(b"99999999999999999999999"), None);
+    }
+
+    #[test]
+    fn test_parse_lines() {
+        let record = parse_bed3_line(b"chr1\t10\t20\textra").unwrap();
+        assert_eq!(
+            (record.seqname.as_str(), record.start, record.end),
+            ("chr1", 10, 20)
+        );
+        assert!(parse_bed3_line(b"chr1\t10").is_err());
+        assert!(parse_bed3_line(b"chr1\tten\t20").is_err());
+
+        let record = parse_bed5_line(b"chr2\t0\t5\tname\t1.5").unwrap();
+        assert_eq!(record.data.name, "name");
+        assert_eq!(record.data.score, Some(1.5));
+        let record = parse_bed5_line(b"chr2\t0\t5\tname\t.").unwrap();
+        assert_eq!(record.data.score, None);
+        assert!(parse_bed5_line(b"chr2\t0\t5\tname").is_err());
+        assert!(parse_bed5_line(b"chr2\t0\t5\tname\tx").is_err());
+    }
+
+    #[test]
+    fn test_parse_lines_interned() {
+        let genome = Genome::from_bytes("chr1\t100\nchr2\t50\n").unwrap();
+        let record = parse_bed3_line_interned(b"chr2\t10\t20", &genome)
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            (record.seq_id, record.start, record.end),
+            (SeqId(1), 10, 20)
+        );
+        assert!(parse_bed3_line_interned(b"chr3\t10\t20", &genome).is_err());
+
+        let record = parse_bed5_line_interned(b"chr1\t0\t5\tname\t.", &genome)
+            .unwrap()
+            .unwrap();
+        assert_eq!(record.seq_id, SeqId(0));
+        assert_eq!(record.data.score, None);
+    }
+
+    #[test]
+    fn test_fast_bed_iterator() {
+        let bed = "# comment\nchr1\t10\t20\r\n\nchr2\t0\t5\n";
+        let records: Vec<_> = FastBedIterator::from_bytes(bed, parse_bed3_line)
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!((records[1].start, records[1].end), (0, 5));
+
+        let mut iter = FastBedIterator::from_bytes("chr1\t10\t20\nchr1\tx\t20\n", parse_bed3_line);
+        assert!(iter.next().unwrap().is_ok());
+        assert!(matches!(
+            iter.next().unwrap(),
+            Err(GRangesError::ParseError { line: 2, .. })
+        ));
+    }
+}
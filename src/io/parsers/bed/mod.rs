@@ -19,43 +19,93 @@
 pub mod bed3;
 pub mod bed4;
 pub mod bed5;
+pub mod bed6;
 pub mod bedlike;
+pub mod fast;
+pub mod narrowpeak;
 
 pub use bed3::Bed3Iterator;
 pub use bed4::{Bed4Addition, Bed4Iterator};
 pub use bed5::{Bed5Addition, Bed5Iterator};
+pub use bed6::{Bed6Addition, Bed6Iterator};
 pub use bedlike::{valid_bedlike, BedlikeIterator};
+pub use narrowpeak::{NarrowPeakAddition, NarrowPeakIterator};
 
 use serde::de::Error as DeError;
-use serde::{Deserialize, Deserializer};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::str::FromStr;
 
+use crate::io::tsv::{is_na_value, na_value, TsvConfig, DEFAULT_NA_VALUE};
+use crate::traits::TsvFormat;
+
 /// [`serde`] deserializer for a BED column with a possibly missing value. Note that the [BED
 /// specification](https://samtools.github.io/hts-specs/BEDv1.pdf) only technically allows `'.'` to
 /// be used for missing strands, but in practice it can be found to represent
-/// missing scores, etc too.
+/// missing scores, etc too. The missing value string set with
+/// [`set_na_value()`](crate::io::tsv::set_na_value) is also treated as missing.
 pub fn bed_missing<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
 where
     D: Deserializer<'de>,
     T: Deserialize<'de> + FromStr,
     <T as FromStr>::Err: std::fmt::Display,
 {
-    let missing_chars = &["."];
+    let missing_chars = &[DEFAULT_NA_VALUE, na_value()];
     deserialize_option_generic(deserializer, missing_chars) // Use the generic deserializer with specific placeholders
 }
 
 /// Nucleotide strand enum type.
-#[derive(Clone, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum Strand {
     Forward,
     Reverse,
 }
 
+impl Strand {
+    /// The BED strand column of this strand, `+` or `-`.
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            Strand::Forward => "+",
+            Strand::Reverse => "-",
+        }
+    }
+}
+
+/// [`serde`] serializer for a BED strand column, as `+` or `-`.
+impl Serialize for Strand {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Strand::Forward => serializer.serialize_str("+"),
+            Strand::Reverse => serializer.serialize_str("-"),
+        }
+    }
+}
+
+impl TsvFormat for Strand {
+    fn write_columns(&self, columns: &mut Vec<String>, _config: &TsvConfig) {
+        columns.push(self.symbol().to_string());
+    }
+}
+
+/// [`serde`] deserializer for a BED strand column, `+` or `-`, or a missing value
+/// (e.g. `.`) for features with no strand.
+pub fn bed_strand<'de, D>(deserializer: D) -> Result<Option<Strand>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let s: String = Deserialize::deserialize(deserializer)?;
+    match s.as_str() {
+        "+" => Ok(Some(Strand::Forward)),
+        "-" => Ok(Some(Strand::Reverse)),
+        s if is_na_value(s) => Ok(None),
+        s => Err(DeError::custom(format!("invalid strand '{}'", s))),
+    }
+}
+
 /// Deserializes some value of type `t` with some possible missing
 /// character `missing_chars` into [`Option<T>`].
 pub fn deserialize_option_generic<'de, D, T>(
     deserializer: D,
-    missing_chars: &'de [&'de str],
+    missing_chars: &[&str],
 ) -> Result<Option<T>, D::Error>
 where
     D: Deserializer<'de>,
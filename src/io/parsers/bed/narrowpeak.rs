@@ -0,0 +1,81 @@
+//! ENCODE narrowPeak (BED6+4) parsers, which are built off of the
+//! [`GenomicRangeRecord`] and [`NarrowPeakAddition`].
+
+use super::{bed_missing, bed_strand, Strand};
+use crate::{
+    io::{parsers::filters::keep_parsed, TsvConfig, TsvRecordIterator},
+    ranges::GenomicRangeRecord,
+    traits::TsvFormat,
+    GRangesError,
+};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+/// The additional seven columns of a [narrowPeak
+/// file](https://genome.ucsc.edu/FAQ/FAQformat.html#format12).
+///
+/// # Fields
+/// * `name`: the peak name.
+/// * `score`: a score.
+/// * `strand`: the strand, or `None` for peaks with no strand (`.`).
+/// * `signal_value`: the overall enrichment of the peak.
+/// * `p_value`: the peak's -log10 p-value, or -1 if none was assigned.
+/// * `q_value`: the peak's -log10 q-value, or -1 if none was assigned.
+/// * `peak`: the summit's offset from the peak start, or -1 if no summit was called.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct NarrowPeakAddition {
+    pub name: String,
+    #[serde(deserialize_with = "bed_missing")]
+    pub score: Option<f64>,
+    #[serde(deserialize_with = "bed_strand")]
+    pub strand: Option<Strand>,
+    pub signal_value: f64,
+    pub p_value: f64,
+    pub q_value: f64,
+    pub peak: i64,
+}
+
+impl TsvFormat for NarrowPeakAddition {
+    fn write_columns(&self, columns: &mut Vec<String>, config: &TsvConfig) {
+        columns.push(self.name.clone());
+        self.score.write_columns(columns, config);
+        self.strand.write_columns(columns, config);
+        self.signal_value.write_columns(columns, config);
+        self.p_value.write_columns(columns, config);
+        self.q_value.write_columns(columns, config);
+        self.peak.write_columns(columns, config);
+    }
+}
+
+/// An iterator over narrowPeak entries, which contain the three
+/// range entries (sequence name, start and end positions), and
+/// the seven [`NarrowPeakAddition`] columns.
+#[derive(Debug)]
+pub struct NarrowPeakIterator {
+    iter: TsvRecordIterator<GenomicRangeRecord<NarrowPeakAddition>>,
+}
+
+impl NarrowPeakIterator {
+    /// Creates a parsing iterator over a narrowPeak file.
+    pub fn new(filepath: impl Into<PathBuf>) -> Result<Self, GRangesError> {
+        let iter = TsvRecordIterator::new(filepath)?;
+
+        Ok(Self { iter })
+    }
+
+    /// Creates a parsing iterator over in-memory narrowPeak data (which may be
+    /// gzip-compressed).
+    pub fn from_bytes(bytes: impl Into<Vec<u8>>) -> Self {
+        let iter = TsvRecordIterator::from_bytes(bytes);
+        Self { iter }
+    }
+}
+
+impl Iterator for NarrowPeakIterator {
+    type Item = Result<GenomicRangeRecord<NarrowPeakAddition>, GRangesError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.iter.find(keep_parsed)
+    }
+}
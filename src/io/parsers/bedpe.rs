@@ -0,0 +1,233 @@
+//! A lazy parser for BEDPE (paired-range) files.
+//!
+//! [BEDPE](https://bedtools.readthedocs.io/en/latest/content/general-usage.html#bedpe-format)
+//! files describe pairs of ranges, e.g. the two anchors of a Hi-C contact or the two
+//! breakpoints of a structural variant. The first six columns are the sequence name,
+//! start, and end of each of the two ends, and the other columns (e.g. name, score,
+//! and the strands of the ends) are kept unparsed, like the data of a
+//! [`BedlikeIterator`](super::bed::BedlikeIterator).
+//!
+//! Both ends of each pair must be on a sequence; the `.` and `-1` used by some
+//! tools for unmapped ends are parsing errors.
+
+use std::{
+    fmt,
+    io::{BufRead, BufReader},
+    path::PathBuf,
+};
+
+use crate::{
+    io::{
+        parsers::{
+            bed::bedlike::PARSE_CAPACITY,
+            filters::in_restriction,
+            utils::{is_header_line, parse_column, skip_malformed},
+        },
+        InputStream,
+    },
+    ranges::GenomicRangeRecordEmpty,
+    GRangesError, Position,
+};
+
+/// A pair of ranges from a BEDPE file, with its other columns, if any.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BedpeRecord {
+    pub first: GenomicRangeRecordEmpty,
+    pub second: GenomicRangeRecordEmpty,
+    pub data: Option<String>,
+}
+
+impl BedpeRecord {
+    /// The span from the start of the first end to the end of the second, if both
+    /// are on the same sequence (the "outer span" of `bedtools pairtobed -type
+    /// ospan`).
+    pub fn outer_span(&self) -> Option<GenomicRangeRecordEmpty> {
+        let (first, second) = (&self.first, &self.second);
+        (first.seqname == second.seqname).then(|| GenomicRangeRecordEmpty {
+            seqname: first.seqname.clone(),
+            start: first.start.min(second.start),
+            end: first.end.max(second.end),
+        })
+    }
+
+    /// The span between the end of the first end and the start of the second, if
+    /// both are on the same sequence and they don't overlap (the "inner span" of
+    /// `bedtools pairtobed -type ispan`).
+    pub fn inner_span(&self) -> Option<GenomicRangeRecordEmpty> {
+        let (first, second) = (&self.first, &self.second);
+        let start = first.end.min(second.end);
+        let end = first.start.max(second.start);
+        (first.seqname == second.seqname && start < end).then(|| GenomicRangeRecordEmpty {
+            seqname: first.seqname.clone(),
+            start,
+            end,
+        })
+    }
+}
+
+/// Formats the record as a BEDPE line (without a newline).
+impl fmt::Display for BedpeRecord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (first, second) = (&self.first, &self.second);
+        write!(
+            f,
+            "{}\t{}\t{}\t{}\t{}\t{}",
+            first.seqname, first.start, first.end, second.seqname, second.start, second.end
+        )?;
+        if let Some(data) = &self.data {
+            write!(f, "\t{}", data)?;
+        }
+        Ok(())
+    }
+}
+
+/// Parse one end of a BEDPE line, from its sequence name, start, and end columns.
+fn parse_end(columns: &[&str], line: &str) -> Result<GenomicRangeRecordEmpty, GRangesError> {
+    let start: Position = parse_column(columns[1], line)?;
+    let end: Position = parse_column(columns[2], line)?;
+    if start > end {
+        return Err(GRangesError::InvalidGenomicRange {
+            seqname: Some(columns[0].to_string()),
+            start,
+            end,
+        });
+    }
+    Ok(GenomicRangeRecordEmpty {
+        seqname: columns[0].to_string(),
+        start,
+        end,
+    })
+}
+
+/// Parses a BEDPE line into its two ranges, storing the other columns as a `String`.
+pub fn parse_bedpe(line: &str) -> Result<BedpeRecord, GRangesError> {
+    let columns: Vec<&str> = line.splitn(7, '\t').collect();
+    if columns.len() < 6 {
+        return Err(GRangesError::BedTooFewColumns(
+            columns.len(),
+            6,
+            line.to_string(),
+        ));
+    }
+    Ok(BedpeRecord {
+        first: parse_end(&columns[0..3], line)?,
+        second: parse_end(&columns[3..6], line)?,
+        data: columns.get(6).map(|data| data.to_string()),
+    })
+}
+
+/// A lazy parser for BEDPE files, yielding [`BedpeRecord`] entries.
+pub struct BedpeIterator {
+    reader: BufReader<Box<dyn std::io::Read>>,
+    line_buffer: String,
+    /// The input's name, and the number of lines read, for errors.
+    source: String,
+    line_number: u64,
+}
+
+impl std::fmt::Debug for BedpeIterator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BedpeIterator").finish_non_exhaustive()
+    }
+}
+
+impl BedpeIterator {
+    /// Create a new lazy-parsing iterator over a BEDPE file (which may be
+    /// gzip-compressed).
+    pub fn new(filepath: impl Into<PathBuf>) -> Result<Self, GRangesError> {
+        let filepath = filepath.into();
+        let source = filepath.display().to_string();
+        let reader = InputStream::new(filepath).reader()?;
+        Ok(Self {
+            reader,
+            line_buffer: String::with_capacity(PARSE_CAPACITY),
+            source,
+            line_number: 0,
+        })
+    }
+}
+
+impl Iterator for BedpeIterator {
+    type Item = Result<BedpeRecord, GRangesError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            self.line_buffer.clear();
+            match self.reader.read_line(&mut self.line_buffer) {
+                Ok(0) => return None,
+                Ok(_) => {
+                    self.line_number += 1;
+                    // skip the metadata/comment lines, and track and browser lines
+                    if is_header_line(&self.line_buffer) {
+                        continue;
+                    }
+                    let line = self.line_buffer.trim_end();
+                    let error = match parse_bedpe(line) {
+                        // both ends must be within the restriction
+                        Ok(BedpeRecord { first, second, .. })
+                            if !in_restriction(&first.seqname, first.start, first.end)
+                                || !in_restriction(&second.seqname, second.start, second.end) =>
+                        {
+                            continue;
+                        }
+                        Ok(record) => return Some(Ok(record)),
+                        Err(error) => GRangesError::ParseError {
+                            file: self.source.clone(),
+                            line: self.line_number,
+                            message: error.to_string(),
+                        },
+                    };
+                    if let Some(error) = skip_malformed(error) {
+                        return Some(Err(error));
+                    }
+                }
+                Err(e) => return Some(Err(GRangesError::IOError(e))),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_bedpe, BedpeIterator};
+    use crate::GRangesError;
+
+    #[test]
+    fn test_parse_bedpe() {
+        let record = parse_bedpe("chr1\t10\t20\tchr2\t30\t40\tpair1\t5\t+\t-").unwrap();
+        assert_eq!(record.first.seqname, "chr1");
+        assert_eq!((record.first.start, record.first.end), (10, 20));
+        assert_eq!(record.second.seqname, "chr2");
+        assert_eq!((record.second.start, record.second.end), (30, 40));
+        assert_eq!(record.data.as_deref(), Some("pair1\t5\t+\t-"));
+        assert_eq!(
+            record.to_string(),
+            "chr1\t10\t20\tchr2\t30\t40\tpair1\t5\t+\t-"
+        );
+
+        let record = parse_bedpe("chr1\t10\t20\tchr1\t30\t40").unwrap();
+        assert_eq!(record.data, None);
+        let outer = record.outer_span().unwrap();
+        assert_eq!((outer.start, outer.end), (10, 40));
+        let inner = record.inner_span().unwrap();
+        assert_eq!((inner.start, inner.end), (20, 30));
+
+        assert!(matches!(
+            parse_bedpe("chr1\t10\t20\tchr2\t30"),
+            Err(GRangesError::BedTooFewColumns(5, 6, _))
+        ));
+        assert!(parse_bedpe("chr1\t10\t20\t.\t-1\t-1").is_err());
+        assert!(parse_bedpe("chr1\t20\t10\tchr2\t30\t40").is_err());
+    }
+
+    #[test]
+    fn test_bedpe_iterator() {
+        let records: Vec<_> = BedpeIterator::new("tests_data/example.bedpe")
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(records.len(), 4);
+        assert_eq!(records[0].first.seqname, "chr1");
+        assert_eq!(records[3].second.seqname, "chr2");
+    }
+}
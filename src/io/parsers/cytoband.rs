@@ -0,0 +1,299 @@
+//! A lazy parser for cytogenetic bands, from the UCSC `cytoBand` table, and helpers
+//! to assign ranges to chromosome arms and bands.
+//!
+//! Dumps of the UCSC Genome Browser's `cytoBand` table (e.g. `cytoBand.txt.gz`) have
+//! five tab-separated columns: the sequence name, the 0-based start and end of the
+//! band, its name (e.g. `p36.33`), and its Giemsa stain (e.g. `gneg`, `gpos50`, or
+//! `acen` for centromeric bands). The arm of a band is the first letter of its name,
+//! `p` (the short arm) or `q` (the long arm); bands of unplaced sequences often
+//! have empty names, and so no arm.
+//!
+//! [`Cytobands`] indexes the bands of a genome, to find the band or arm (e.g.
+//! `chr1p`) containing a position, e.g. to stratify an analysis by arm.
+
+use indexmap::IndexMap;
+use serde::Serialize;
+use std::{
+    io::{BufRead, BufReader},
+    path::PathBuf,
+};
+
+use crate::{
+    io::{
+        parsers::{
+            bed::bedlike::PARSE_CAPACITY,
+            filters::in_restriction,
+            utils::{parse_column, skip_malformed},
+        },
+        InputStream,
+    },
+    ranges::GenomicRangeRecord,
+    traits::GenomicRecord,
+    GRangesError, Position,
+};
+
+/// A cytogenetic band.
+///
+/// # Fields
+/// * `name`: the band name (e.g. `p36.33`), which may be empty.
+/// * `stain`: the Giemsa stain of the band (e.g. `gneg`, `gpos50`, or `acen`).
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Cytoband {
+    pub name: String,
+    pub stain: String,
+}
+
+impl Cytoband {
+    /// The arm of this band, `'p'` or `'q'`, if its name starts with one.
+    pub fn arm(&self) -> Option<char> {
+        self.name
+            .chars()
+            .next()
+            .filter(|arm| matches!(arm, 'p' | 'q'))
+    }
+
+    /// Whether this band is (part of) a centromere.
+    pub fn is_centromere(&self) -> bool {
+        self.stain == "acen"
+    }
+}
+
+/// Parses a line of a UCSC `cytoBand` table dump.
+pub fn parse_cytoband(line: &str) -> Result<GenomicRangeRecord<Cytoband>, GRangesError> {
+    let columns: Vec<&str> = line.split('\t').collect();
+    if columns.len() < 5 {
+        return Err(GRangesError::BedTooFewColumns(
+            columns.len(),
+            5,
+            line.to_string(),
+        ));
+    }
+    let start: Position = parse_column(columns[1], line)?;
+    let end: Position = parse_column(columns[2], line)?;
+    if start > end {
+        return Err(GRangesError::InvalidGenomicRange {
+            seqname: Some(columns[0].to_string()),
+            start,
+            end,
+        });
+    }
+    Ok(GenomicRangeRecord {
+        seqname: columns[0].to_string(),
+        start,
+        end,
+        data: Cytoband {
+            name: columns[3].to_string(),
+            stain: columns[4].to_string(),
+        },
+    })
+}
+
+/// A lazy parser for UCSC `cytoBand` table dumps, yielding
+/// [`GenomicRangeRecord<Cytoband>`] entries.
+pub struct CytobandIterator {
+    reader: BufReader<Box<dyn std::io::Read>>,
+    line_buffer: String,
+    /// The input's name, and the number of lines read, for errors.
+    source: String,
+    line_number: u64,
+}
+
+impl std::fmt::Debug for CytobandIterator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CytobandIterator").finish_non_exhaustive()
+    }
+}
+
+impl CytobandIterator {
+    /// Create a new lazy-parsing iterator over a UCSC `cytoBand` table dump (which
+    /// may be gzip-compressed).
+    pub fn new(filepath: impl Into<PathBuf>) -> Result<Self, GRangesError> {
+        let filepath = filepath.into();
+        let source = filepath.display().to_string();
+        let reader = InputStream::new(filepath).reader()?;
+        Ok(Self {
+            reader,
+            line_buffer: String::with_capacity(PARSE_CAPACITY),
+            source,
+            line_number: 0,
+        })
+    }
+}
+
+impl Iterator for CytobandIterator {
+    type Item = Result<GenomicRangeRecord<Cytoband>, GRangesError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            self.line_buffer.clear();
+            match self.reader.read_line(&mut self.line_buffer) {
+                Ok(0) => return None,
+                Ok(_) => {
+                    self.line_number += 1;
+                    // skip the header (in dumps with one) and empty lines
+                    let line = self.line_buffer.trim_end();
+                    if line.starts_with('#') || line.is_empty() {
+                        continue;
+                    }
+                    let error = match parse_cytoband(line) {
+                        Ok(record)
+                            if !in_restriction(&record.seqname, record.start, record.end) =>
+                        {
+                            continue;
+                        }
+                        Ok(record) => return Some(Ok(record)),
+                        Err(error) => GRangesError::ParseError {
+                            file: self.source.clone(),
+                            line: self.line_number,
+                            message: error.to_string(),
+                        },
+                    };
+                    if let Some(error) = skip_malformed(error) {
+                        return Some(Err(error));
+                    }
+                }
+                Err(e) => return Some(Err(GRangesError::IOError(e))),
+            }
+        }
+    }
+}
+
+/// The cytogenetic bands of a genome, indexed to find the band or chromosome arm
+/// containing a position.
+#[derive(Clone, Debug, Default)]
+pub struct Cytobands {
+    /// The bands of each sequence, sorted by start position.
+    bands: IndexMap<String, Vec<GenomicRangeRecord<Cytoband>>>,
+}
+
+impl Cytobands {
+    /// Read the bands of a UCSC `cytoBand` table dump.
+    pub fn from_file(filepath: impl Into<PathBuf>) -> Result<Self, GRangesError> {
+        Self::from_iter(CytobandIterator::new(filepath)?)
+    }
+
+    /// Index the bands from a parsing iterator, which need not be sorted.
+    pub fn from_iter<I>(iter: I) -> Result<Self, GRangesError>
+    where
+        I: IntoIterator<Item = Result<GenomicRangeRecord<Cytoband>, GRangesError>>,
+    {
+        let mut cytobands = Self::default();
+        for result in iter {
+            let band = result?;
+            cytobands
+                .bands
+                .entry(band.seqname.clone())
+                .or_default()
+                .push(band);
+        }
+        for bands in cytobands.bands.values_mut() {
+            bands.sort_unstable_by_key(|band| (band.start, band.end));
+        }
+        Ok(cytobands)
+    }
+
+    /// The band containing `position` on `seqname`, if any.
+    pub fn band(&self, seqname: &str, position: Position) -> Option<&Cytoband> {
+        let bands = self.bands.get(seqname)?;
+        let index = bands.partition_point(|band| band.start <= position);
+        let band = bands.get(index.checked_sub(1)?)?;
+        (position < band.end).then_some(&band.data)
+    }
+
+    /// The name of the chromosome arm containing `position` on `seqname`, e.g.
+    /// `chr1p`, if it's in a band with an arm.
+    pub fn arm(&self, seqname: &str, position: Position) -> Option<String> {
+        let arm = self.band(seqname, position)?.arm()?;
+        Some(format!("{}{}", seqname, arm))
+    }
+
+    /// The name of the chromosome arm containing the midpoint of `range` (see
+    /// [`Cytobands::arm()`]), so that a range spanning a centromere is assigned to
+    /// the arm with most of it.
+    pub fn range_arm<R: GenomicRecord>(&self, range: &R) -> Option<String> {
+        self.arm(range.seqname(), range.midpoint())
+    }
+
+    /// The extent of each chromosome arm, from the first to the last of its bands,
+    /// in the order of the sequences and then of the arms.
+    pub fn arms(&self) -> Vec<GenomicRangeRecord<String>> {
+        let mut arms: Vec<GenomicRangeRecord<String>> = Vec::new();
+        for (seqname, bands) in &self.bands {
+            let first = arms.len();
+            for band in bands {
+                let Some(arm) = band.data.arm() else {
+                    continue;
+                };
+                let name = format!("{}{}", seqname, arm);
+                match arms[first..].iter_mut().find(|range| range.data == name) {
+                    Some(range) => {
+                        range.start = range.start.min(band.start);
+                        range.end = range.end.max(band.end);
+                    }
+                    None => arms.push(GenomicRangeRecord::new(
+                        seqname.clone(),
+                        band.start,
+                        band.end,
+                        name,
+                    )),
+                }
+            }
+        }
+        arms
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_cytoband, CytobandIterator, Cytobands};
+    use crate::{ranges::GenomicRangeRecordEmpty, GRangesError};
+
+    #[test]
+    fn test_parse_cytoband() {
+        let record = parse_cytoband("chr1\t0\t2300000\tp36.33\tgneg").unwrap();
+        assert_eq!(record.seqname, "chr1");
+        assert_eq!((record.start, record.end), (0, 2300000));
+        assert_eq!(record.data.arm(), Some('p'));
+        assert!(!record.data.is_centromere());
+
+        let record = parse_cytoband("chr1_KI270706v1_random\t0\t175055\t\tgneg").unwrap();
+        assert_eq!(record.data.arm(), None);
+
+        assert!(matches!(
+            parse_cytoband("chr1\t0\t2300000"),
+            Err(GRangesError::BedTooFewColumns(3, 5, _))
+        ));
+        assert!(parse_cytoband("chr1\t10\t0\tp36.33\tgneg").is_err());
+    }
+
+    #[test]
+    fn test_cytobands() {
+        let iter = CytobandIterator::new("tests_data/golden/cytoband.txt").unwrap();
+        let cytobands = Cytobands::from_iter(iter).unwrap();
+        assert_eq!(cytobands.band("chr1", 0).unwrap().name, "p2");
+        assert_eq!(cytobands.band("chr1", 45).unwrap().name, "p11");
+        assert!(cytobands.band("chr1", 45).unwrap().is_centromere());
+        assert_eq!(cytobands.arm("chr1", 60).as_deref(), Some("chr1q"));
+        assert_eq!(cytobands.arm("chr1", 100), None);
+        assert_eq!(cytobands.arm("chr3", 0), None);
+
+        // the midpoint of 30-70 is on the q arm
+        let range = GenomicRangeRecordEmpty::new("chr1".to_string(), 30, 70);
+        assert_eq!(cytobands.range_arm(&range).as_deref(), Some("chr1q"));
+
+        let arms: Vec<_> = cytobands
+            .arms()
+            .into_iter()
+            .map(|arm| (arm.data, arm.start, arm.end))
+            .collect();
+        assert_eq!(
+            arms,
+            vec![
+                ("chr1p".to_string(), 0, 50),
+                ("chr1q".to_string(), 50, 100),
+                ("chr2p".to_string(), 0, 20),
+                ("chr2q".to_string(), 20, 50),
+            ]
+        );
+    }
+}
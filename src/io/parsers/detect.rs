@@ -1,20 +1,140 @@
 //! Filetype detection functionality.
 //!
+//! Input formats are detected from their header lines (e.g. `##gff-version`,
+//! `##fileformat=VCF`, or SAF's column names), their extensions, and their first
+//! record. Since detection can misfire on ambiguous files, the format can be set
+//! explicitly with [`set_input_format()`] (the `--input-format` option of the
+//! `granges` command line tool), which is checked against any header or extension
+//! that indicates another format.
 
+use clap::ValueEnum;
 use serde::Deserialize;
-use std::path::PathBuf;
+use std::{fmt, io::BufRead, path::PathBuf, sync::OnceLock};
 
 use super::{
-    bed::{valid_bedlike, Bed4Addition, Bed4Iterator},
+    bed::{bedlike::SAF_HEADER, valid_bedlike, Bed4Addition, Bed4Iterator},
     tsv::build_tsv_reader,
-    utils::get_base_extension,
+    utils::{get_base_extension, is_header_line},
     Bed3Iterator, Bed5Addition, Bed5Iterator, BedlikeIterator,
 };
 use crate::{
+    io::InputStream,
     ranges::{GenomicRangeRecord, GenomicRangeRecordEmpty},
     GRangesError,
 };
 
+/// The number of lines read to look for format-specific header lines.
+const HEADER_LINES: usize = 64;
+
+/// An explicitly specified input format.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum InputFormat {
+    Bed3,
+    Bed4,
+    Bed5,
+    Bed6,
+    Bed12,
+    Bedgraph,
+    Gff,
+    Gtf,
+    Vcf,
+    /// featureCounts' Simplified Annotation Format.
+    Saf,
+}
+
+impl InputFormat {
+    /// Whether this is a BED format (including bedGraph), with 0-based ranges in the
+    /// first three columns.
+    pub fn is_bed(&self) -> bool {
+        !matches!(
+            self,
+            InputFormat::Gff | InputFormat::Gtf | InputFormat::Vcf | InputFormat::Saf
+        )
+    }
+
+    /// The format indicated by a (base) file extension, if it is specific to one format.
+    /// Note that `.bed` files are not, since BED has several variants.
+    pub fn from_extension(extension: &str) -> Option<Self> {
+        match extension.to_lowercase().as_str() {
+            "bedgraph" | "bdg" | "bg" => Some(InputFormat::Bedgraph),
+            "gff" | "gff3" => Some(InputFormat::Gff),
+            "gtf" => Some(InputFormat::Gtf),
+            "vcf" => Some(InputFormat::Vcf),
+            "saf" => Some(InputFormat::Saf),
+            _ => None,
+        }
+    }
+
+    /// The format indicated by a header line, if any.
+    pub fn from_header(line: &str) -> Option<Self> {
+        if line.starts_with("##gff-version") {
+            Some(InputFormat::Gff)
+        } else if line.starts_with("##fileformat=VCF") {
+            Some(InputFormat::Vcf)
+        } else if line.starts_with("track") && line.contains("type=bedGraph") {
+            Some(InputFormat::Bedgraph)
+        } else if line.starts_with(SAF_HEADER) {
+            Some(InputFormat::Saf)
+        } else {
+            None
+        }
+    }
+}
+
+impl fmt::Display for InputFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            InputFormat::Bed3 => "bed3",
+            InputFormat::Bed4 => "bed4",
+            InputFormat::Bed5 => "bed5",
+            InputFormat::Bed6 => "bed6",
+            InputFormat::Bed12 => "bed12",
+            InputFormat::Bedgraph => "bedgraph",
+            InputFormat::Gff => "gff",
+            InputFormat::Gtf => "gtf",
+            InputFormat::Vcf => "vcf",
+            InputFormat::Saf => "saf",
+        };
+        f.write_str(name)
+    }
+}
+
+static INPUT_FORMAT: OnceLock<Option<InputFormat>> = OnceLock::new();
+
+/// Set the format of detected inputs globally, overriding detection.
+///
+/// Like [`set_num_threads()`](crate::parallel::set_num_threads), this must be called
+/// before any input is read, and can only be called once.
+pub fn set_input_format(format: InputFormat) -> Result<(), GRangesError> {
+    INPUT_FORMAT
+        .set(Some(format))
+        .map_err(|_| GRangesError::InputFormatAlreadySet(format.to_string()))
+}
+
+/// The input format set with [`set_input_format()`], if any.
+pub fn input_format() -> Option<InputFormat> {
+    *INPUT_FORMAT.get_or_init(|| None)
+}
+
+/// Detect the input format from its header lines (within the first lines of the file),
+/// or failing that, its extension. This returns `None` for formats that can only be
+/// detected from their records, e.g. the BED variants.
+pub fn sniff_format(filepath: impl Into<PathBuf>) -> Result<Option<InputFormat>, GRangesError> {
+    let filepath = filepath.into();
+    let reader = InputStream::new(&filepath).reader()?;
+    for result in reader.lines().take(HEADER_LINES) {
+        let line = result?;
+        if let Some(format) = InputFormat::from_header(&line) {
+            return Ok(Some(format));
+        }
+        // header lines come first
+        if !is_header_line(&line) {
+            break;
+        }
+    }
+    Ok(get_base_extension(&filepath).and_then(|extension| InputFormat::from_extension(&extension)))
+}
+
 /// Enum that connects a genomic ranges file type to its specific parser.
 #[derive(Debug)]
 pub enum GenomicRangesParser {
@@ -32,6 +152,12 @@ pub enum GenomicRangesFile {
     Bed4(PathBuf),
     Bed5(PathBuf),
     Bedlike(PathBuf),
+    /// A GFF or GTF file, read as a BED-like file.
+    Gff(PathBuf),
+    /// A VCF file, read as a BED-like file.
+    Vcf(PathBuf),
+    /// A SAF file, read as a BED6-like file.
+    Saf(PathBuf),
     Unsupported,
 }
 
@@ -96,13 +222,26 @@ impl GenomicRangesFile {
     ///  5. If the file type does not satisfy any of the rules above, it is
     ///     [`GenomicRangesFile::Unsupported`].
     ///
-    /// See the `match` statement in the source code for the exact rules. Additional
-    /// genomic range file formats like GTF/GFF can easily be added later.
+    /// Before these rules, GFF/GTF and VCF files (and bedGraph files, which are
+    /// [`GenomicRangesFile::Bedlike`]) are detected by their header lines or
+    /// extensions (see [`sniff_format()`]).
+    ///
+    /// See the `match` statement in the source code for the exact rules.
     ///
     /// [`GRanges`]: crate::granges::GRanges
     pub fn detect(filepath: impl Into<PathBuf>) -> Result<Self, GRangesError> {
         let filepath: PathBuf = filepath.into();
 
+        match sniff_format(&filepath)? {
+            Some(InputFormat::Gff | InputFormat::Gtf) => {
+                return Ok(GenomicRangesFile::Gff(filepath))
+            }
+            Some(InputFormat::Vcf) => return Ok(GenomicRangesFile::Vcf(filepath)),
+            Some(InputFormat::Saf) => return Ok(GenomicRangesFile::Saf(filepath)),
+            Some(InputFormat::Bedgraph) => return Ok(GenomicRangesFile::Bedlike(filepath)),
+            _ => {}
+        }
+
         let is_valid_bedlike = valid_bedlike(&filepath)?;
 
         // get the extension, as a hint
@@ -127,6 +266,49 @@ impl GenomicRangesFile {
         Ok(GenomicRangesFile::Unsupported)
     }
 
+    /// Get the [`GenomicRangesFile`] of an input in the explicitly specified `format`,
+    /// raising an error if its header lines, extension, or first record indicate
+    /// another format. BED6, BED12, and bedGraph files are
+    /// [`GenomicRangesFile::Bedlike`].
+    pub fn with_format(
+        filepath: impl Into<PathBuf>,
+        format: InputFormat,
+    ) -> Result<Self, GRangesError> {
+        let filepath: PathBuf = filepath.into();
+        let conflict = |detected: &str| {
+            GRangesError::InputFormatConflict(
+                filepath.to_string_lossy().to_string(),
+                format.to_string(),
+                detected.to_string(),
+            )
+        };
+
+        let sniffed = sniff_format(&filepath)?;
+        if let Some(sniffed) = sniffed {
+            // bedGraph files are BED-like, so may be read as any BED format
+            let compatible = sniffed == format
+                || (sniffed == InputFormat::Bedgraph && format.is_bed())
+                || (sniffed == InputFormat::Gtf && format == InputFormat::Gff);
+            if !compatible {
+                return Err(conflict(&sniffed.to_string()));
+            }
+        } else if format.is_bed() && !valid_bedlike(&filepath)? {
+            return Err(conflict("a non-BED file"));
+        }
+
+        Ok(match format {
+            InputFormat::Bed3 => GenomicRangesFile::Bed3(filepath),
+            InputFormat::Bed4 => GenomicRangesFile::Bed4(filepath),
+            InputFormat::Bed5 => GenomicRangesFile::Bed5(filepath),
+            InputFormat::Bed6 | InputFormat::Bed12 | InputFormat::Bedgraph => {
+                GenomicRangesFile::Bedlike(filepath)
+            }
+            InputFormat::Gff | InputFormat::Gtf => GenomicRangesFile::Gff(filepath),
+            InputFormat::Vcf => GenomicRangesFile::Vcf(filepath),
+            InputFormat::Saf => GenomicRangesFile::Saf(filepath),
+        })
+    }
+
     /// Detect the genomic range filetype and link it to its parsing iterator, or raise an error
     /// if the filetype is not supported.
     ///
@@ -136,7 +318,11 @@ impl GenomicRangesFile {
         filepath: impl Clone + Into<PathBuf>,
     ) -> Result<GenomicRangesParser, GRangesError> {
         let path = filepath.into();
-        match Self::detect(path)? {
+        let filetype = match input_format() {
+            Some(format) => Self::with_format(path, format)?,
+            None => Self::detect(path)?,
+        };
+        match filetype {
             GenomicRangesFile::Bed3(path) => {
                 Ok(GenomicRangesParser::Bed3(Bed3Iterator::new(path)?))
             }
@@ -149,6 +335,15 @@ impl GenomicRangesFile {
             GenomicRangesFile::Bedlike(path) => {
                 Ok(GenomicRangesParser::Bedlike(BedlikeIterator::new(path)?))
             }
+            GenomicRangesFile::Gff(path) => Ok(GenomicRangesParser::Bedlike(
+                BedlikeIterator::new_gff(path)?,
+            )),
+            GenomicRangesFile::Vcf(path) => Ok(GenomicRangesParser::Bedlike(
+                BedlikeIterator::new_vcf(path)?,
+            )),
+            GenomicRangesFile::Saf(path) => Ok(GenomicRangesParser::Bedlike(
+                BedlikeIterator::new_saf(path)?,
+            )),
             GenomicRangesFile::Unsupported => Err(GRangesError::UnsupportedGenomicRangesFileFormat),
         }
     }
@@ -156,7 +351,8 @@ impl GenomicRangesFile {
 
 #[cfg(test)]
 mod tests {
-    use super::GenomicRangesFile;
+    use super::{GenomicRangesFile, InputFormat};
+    use crate::GRangesError;
 
     #[test]
     fn test_rangefiletype_detect() {
@@ -178,4 +374,45 @@ mod tests {
             GenomicRangesFile::Bed5(_)
         ));
     }
+
+    #[test]
+    fn test_with_format() {
+        let dir = tempfile::tempdir().unwrap();
+        let gff = dir.path().join("genes.txt");
+        std::fs::write(
+            &gff,
+            "##gff-version 3\nchr1\tsrc\tgene\t1\t10\t.\t+\t.\tID=g1\n",
+        )
+        .unwrap();
+        assert_eq!(
+            GenomicRangesFile::detect(&gff).unwrap(),
+            GenomicRangesFile::Gff(gff.clone())
+        );
+        assert!(matches!(
+            GenomicRangesFile::with_format(&gff, InputFormat::Bed3),
+            Err(GRangesError::InputFormatConflict(..))
+        ));
+
+        let bed = "tests_data/test_case_03.bed";
+        assert_eq!(
+            GenomicRangesFile::with_format(bed, InputFormat::Bed6).unwrap(),
+            GenomicRangesFile::Bedlike(bed.into())
+        );
+        assert!(matches!(
+            GenomicRangesFile::with_format("tests_data/invalid_format.bed", InputFormat::Bed3),
+            Err(GRangesError::InputFormatConflict(..))
+        ));
+
+        // SAF files are detected by their header, whatever their extension
+        let saf = dir.path().join("annotation.txt");
+        std::fs::write(
+            &saf,
+            "GeneID\tChr\tStart\tEnd\tStrand\ng1\tchr1\t1\t10\t+\n",
+        )
+        .unwrap();
+        assert_eq!(
+            GenomicRangesFile::detect(&saf).unwrap(),
+            GenomicRangesFile::Saf(saf)
+        );
+    }
 }
@@ -2,11 +2,23 @@
 
 use crate::error::GRangesError;
 use crate::io::TsvRecordIterator;
+use crate::ranges::operations::merge_intervals;
 use crate::ranges::{GenomicRangeRecord, GenomicRangeRecordEmpty};
-use crate::traits::{GeneralRangeRecordIterator, GenomicRangeRecordUnwrappable};
-use std::collections::HashSet;
+use crate::traits::{
+    AdjustableGenericRange, GeneralRangeRecordIterator, GenomicRangeRecordUnwrappable,
+    GenomicRecord,
+};
+use crate::Position;
+use clap::ValueEnum;
+use indexmap::IndexMap;
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::sync::OnceLock;
+use tracing::warn;
 
 use super::bed::{Bed4Addition, Bed4Iterator};
+use super::mmap::BorrowedBedRecord;
+use super::rmsk::{RepeatMaskerIterator, RepeatRecord};
 use super::{Bed3Iterator, Bed5Addition, Bed5Iterator, BedlikeIterator};
 
 /// An iterator over a generic "genomic range like " item type `R`, that filters based on sequence name.
@@ -134,6 +146,40 @@ where
     }
 }
 
+/// Range-filtering iterator implementation for [`BorrowedBedRecord`].
+impl<'a, I> Iterator for FilteredRanges<I, BorrowedBedRecord<'a>>
+where
+    I: Iterator<Item = Result<BorrowedBedRecord<'a>, GRangesError>>,
+{
+    type Item = Result<BorrowedBedRecord<'a>, GRangesError>;
+
+    /// Get the next filtered entry, prioritizing exclude over retain.
+    fn next(&mut self) -> Option<Self::Item> {
+        for item in self.inner.by_ref() {
+            match &item {
+                Ok(entry) => {
+                    if self
+                        .exclude_seqnames
+                        .as_ref()
+                        .is_some_and(|ex| ex.contains(entry.seqname))
+                    {
+                        continue;
+                    }
+                    if self
+                        .retain_seqnames
+                        .as_ref()
+                        .is_none_or(|rt| rt.contains(entry.seqname))
+                    {
+                        return Some(item);
+                    }
+                }
+                Err(_) => return Some(item),
+            }
+        }
+        None
+    }
+}
+
 impl<U> GeneralRangeRecordIterator<GenomicRangeRecord<U>>
     for TsvRecordIterator<GenomicRangeRecord<U>>
 where
@@ -204,6 +250,21 @@ impl GeneralRangeRecordIterator<GenomicRangeRecord<Bed5Addition>> for Bed5Iterat
     }
 }
 
+impl GeneralRangeRecordIterator<GenomicRangeRecord<RepeatRecord>> for RepeatMaskerIterator {
+    fn retain_seqnames(
+        self,
+        seqnames: &[String],
+    ) -> FilteredRanges<Self, GenomicRangeRecord<RepeatRecord>> {
+        FilteredRanges::new(self, Some(&seqnames.to_vec()), None)
+    }
+    fn exclude_seqnames(
+        self,
+        seqnames: &[String],
+    ) -> FilteredRanges<Self, GenomicRangeRecord<RepeatRecord>> {
+        FilteredRanges::new(self, None, Some(&seqnames.to_vec()))
+    }
+}
+
 impl<I> GeneralRangeRecordIterator<GenomicRangeRecord<String>> for UnwrappedRanges<I>
 where
     I: Iterator<Item = Result<GenomicRangeRecord<Option<String>>, GRangesError>>,
@@ -266,3 +327,325 @@ impl GenomicRangeRecordUnwrappable for BedlikeIterator {
         UnwrappedRanges::new(self)
     }
 }
+
+/// A restriction of the ranges read to some sequences (e.g. to exclude alt contigs),
+/// and/or to those overlapping some regions.
+///
+/// Once set globally with [`set_restriction()`], this is applied by the parsing
+/// iterators as they read each record, and to the sequences read with
+/// [`read_seqlens()`](crate::io::file::read_seqlens).
+#[derive(Clone, Debug, Default)]
+pub struct Restriction {
+    seqnames: Option<HashSet<String>>,
+    /// Sorted, non-overlapping regions on each sequence.
+    regions: Option<HashMap<String, Vec<(Position, Position)>>>,
+}
+
+impl Restriction {
+    /// Create a new [`Restriction`], which allows all ranges.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Restrict ranges to these sequences.
+    pub fn with_seqnames(mut self, seqnames: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.seqnames = Some(seqnames.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Restrict ranges to those overlapping the `(seqname, start, end)` regions.
+    pub fn with_regions(
+        mut self,
+        regions: impl IntoIterator<Item = (String, Position, Position)>,
+    ) -> Self {
+        let mut by_seqname: HashMap<String, Vec<(Position, Position)>> = HashMap::new();
+        for (seqname, start, end) in regions {
+            by_seqname.entry(seqname).or_default().push((start, end));
+        }
+        for intervals in by_seqname.values_mut() {
+            *intervals = merge_intervals(std::mem::take(intervals));
+        }
+        self.regions = Some(by_seqname);
+        self
+    }
+
+    /// Restrict ranges to those overlapping the regions of a BED-like file.
+    pub fn with_regions_file(self, filepath: impl Into<PathBuf>) -> Result<Self, GRangesError> {
+        let regions = BedlikeIterator::new(filepath)?
+            .map(|result| result.map(|range| (range.seqname, range.start, range.end)))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(self.with_regions(regions))
+    }
+
+    /// Whether ranges on this sequence may be allowed, i.e. it is one of the
+    /// sequences (if restricted to some), and has regions (if restricted to them).
+    pub fn contains_seqname(&self, seqname: &str) -> bool {
+        self.seqnames
+            .as_ref()
+            .is_none_or(|seqnames| seqnames.contains(seqname))
+            && self
+                .regions
+                .as_ref()
+                .is_none_or(|regions| regions.contains_key(seqname))
+    }
+
+    /// Whether the range is allowed, i.e. it is on one of the sequences, and overlaps
+    /// a region. Zero-width ranges overlap the regions that contain their position.
+    pub fn contains(&self, seqname: &str, start: Position, end: Position) -> bool {
+        if !self.contains_seqname(seqname) {
+            return false;
+        }
+        let Some(regions) = &self.regions else {
+            return true;
+        };
+        let intervals = &regions[seqname];
+        let end = end.max(start.saturating_add(1));
+        let first = intervals.partition_point(|&(_, region_end)| region_end <= start);
+        intervals
+            .get(first)
+            .is_some_and(|&(region_start, _)| region_start < end)
+    }
+}
+
+static RESTRICTION: OnceLock<Restriction> = OnceLock::new();
+
+/// Set the [`Restriction`] of all the ranges read, globally.
+///
+/// Like [`set_input_format()`](super::set_input_format), this must be called before
+/// any input is read, and can only be called once.
+pub fn set_restriction(restriction: Restriction) -> Result<(), GRangesError> {
+    RESTRICTION
+        .set(restriction)
+        .map_err(|_| GRangesError::RestrictionAlreadySet)
+}
+
+/// Whether the range is allowed by the [`Restriction`] set with [`set_restriction()`]
+/// (all ranges are, if none has been set).
+pub fn in_restriction(seqname: &str, start: Position, end: Position) -> bool {
+    RESTRICTION
+        .get()
+        .is_none_or(|restriction| restriction.contains(seqname, start, end))
+}
+
+/// Whether ranges on the sequence may be allowed by the [`Restriction`] set with
+/// [`set_restriction()`].
+pub fn in_restricted_seqnames(seqname: &str) -> bool {
+    RESTRICTION
+        .get()
+        .is_none_or(|restriction| restriction.contains_seqname(seqname))
+}
+
+/// Whether a parsing iterator should yield this item: parse errors are always
+/// yielded, and records only if they are [`in_restriction()`].
+pub(crate) fn keep_parsed<R: GenomicRecord>(result: &Result<R, GRangesError>) -> bool {
+    match result {
+        Ok(record) => in_restriction(record.seqname(), record.start(), record.end()),
+        Err(_) => true,
+    }
+}
+
+/// How ranges that fall outside the genome are handled, i.e. ranges on sequences
+/// not in the genome, or ranges that extend past the end of their sequence.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum BoundsPolicy {
+    /// Return an error.
+    #[default]
+    Error,
+    /// Truncate ranges at the end of their sequence. Ranges that start past the end
+    /// of their sequence, or are on unknown sequences, are dropped.
+    Truncate,
+    /// Drop the range.
+    Drop,
+    /// Keep ranges that extend past the end of their sequence, logging a warning.
+    /// Ranges on unknown sequences cannot be kept, and are dropped with a warning.
+    Warn,
+}
+
+/// An iterator over genomic range records that applies a [`BoundsPolicy`] to
+/// ranges that fall outside the genome described by `seqlens`.
+///
+/// # Example
+///
+/// ```
+/// use granges::prelude::*;
+/// use granges::io::parsers::filters::{BoundsPolicy, BoundsPolicyExt};
+///
+/// let seqlens = seqlens! { "chr1" => 15, "chr2" => 10 };
+/// let iter = Bed3Iterator::new("tests_data/example.bed")
+///            .expect("error reading file")
+///            .with_bounds_policy(&seqlens, BoundsPolicy::Truncate);
+///
+/// // the chr1 ranges are truncated at 15, and the chr4 range is dropped
+/// let gr = GRangesEmpty::from_iter(iter, &seqlens).expect("parsing error");
+/// assert_eq!(gr.iter_ranges().next().unwrap().end, 15);
+/// assert_eq!(gr.len(), 4);
+/// ```
+#[derive(Debug)]
+pub struct BoundedRanges<I> {
+    inner: I,
+    seqlens: IndexMap<String, Position>,
+    policy: BoundsPolicy,
+    num_truncated: usize,
+    num_dropped: usize,
+}
+
+impl<I> BoundedRanges<I> {
+    pub fn new(inner: I, seqlens: &IndexMap<String, Position>, policy: BoundsPolicy) -> Self {
+        Self {
+            inner,
+            seqlens: seqlens.clone(),
+            policy,
+            num_truncated: 0,
+            num_dropped: 0,
+        }
+    }
+
+    /// The number of ranges truncated at the end of their sequence so far.
+    pub fn num_truncated(&self) -> usize {
+        self.num_truncated
+    }
+
+    /// The number of ranges dropped so far.
+    pub fn num_dropped(&self) -> usize {
+        self.num_dropped
+    }
+}
+
+impl<I, R> Iterator for BoundedRanges<I>
+where
+    I: Iterator<Item = Result<R, GRangesError>>,
+    R: GenomicRecord + AdjustableGenericRange,
+{
+    type Item = Result<R, GRangesError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        for item in self.inner.by_ref() {
+            let mut range = match item {
+                Ok(range) => range,
+                Err(e) => return Some(Err(e)),
+            };
+            let Some(&length) = self.seqlens.get(range.seqname()) else {
+                match self.policy {
+                    BoundsPolicy::Error => {
+                        return Some(Err(GRangesError::missing_sequence(range.seqname())))
+                    }
+                    BoundsPolicy::Warn => warn!(
+                        "dropping range {}:{}-{} on a sequence not in the genome",
+                        range.seqname(),
+                        range.start(),
+                        range.end()
+                    ),
+                    BoundsPolicy::Truncate | BoundsPolicy::Drop => {}
+                }
+                self.num_dropped += 1;
+                continue;
+            };
+            if range.end() <= length {
+                return Some(Ok(range));
+            }
+            match self.policy {
+                BoundsPolicy::Error => {
+                    return Some(Err(GRangesError::InvalidGenomicRangeForSequence {
+                        seqname: Some(range.seqname().to_string()),
+                        start: range.start(),
+                        end: range.end(),
+                        length,
+                    }))
+                }
+                BoundsPolicy::Truncate if range.start() < length => {
+                    range.set_end(length);
+                    self.num_truncated += 1;
+                    return Some(Ok(range));
+                }
+                BoundsPolicy::Truncate | BoundsPolicy::Drop => self.num_dropped += 1,
+                BoundsPolicy::Warn => {
+                    warn!(
+                        "range {}:{}-{} extends past the end of its sequence (length {})",
+                        range.seqname(),
+                        range.start(),
+                        range.end(),
+                        length
+                    );
+                    return Some(Ok(range));
+                }
+            }
+        }
+        None
+    }
+}
+
+/// Extends parsing iterators with the [`BoundsPolicyExt::with_bounds_policy()`] adapter.
+pub trait BoundsPolicyExt: Iterator + Sized {
+    /// Wrap this iterator in a [`BoundedRanges`], applying `policy` to ranges
+    /// that fall outside the genome described by `seqlens`.
+    fn with_bounds_policy(
+        self,
+        seqlens: &IndexMap<String, Position>,
+        policy: BoundsPolicy,
+    ) -> BoundedRanges<Self> {
+        BoundedRanges::new(self, seqlens, policy)
+    }
+}
+
+impl<I: Iterator> BoundsPolicyExt for I {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::seqlens;
+
+    fn ranges() -> Vec<Result<GenomicRangeRecordEmpty, GRangesError>> {
+        vec![
+            Ok(GenomicRangeRecordEmpty::new("chr1".to_string(), 0, 10)),
+            Ok(GenomicRangeRecordEmpty::new("chr1".to_string(), 5, 30)),
+            Ok(GenomicRangeRecordEmpty::new("chr1".to_string(), 25, 30)),
+            Ok(GenomicRangeRecordEmpty::new("chrX".to_string(), 0, 10)),
+        ]
+    }
+
+    #[test]
+    fn test_bounds_policy() {
+        let seqlens = seqlens! { "chr1" => 20 };
+        let ends = |policy| {
+            ranges()
+                .into_iter()
+                .with_bounds_policy(&seqlens, policy)
+                .map(|range| range.map(|range| range.end))
+                .collect::<Result<Vec<_>, _>>()
+        };
+
+        assert!(ends(BoundsPolicy::Error).is_err());
+        assert_eq!(ends(BoundsPolicy::Truncate).unwrap(), vec![10, 20]);
+        assert_eq!(ends(BoundsPolicy::Drop).unwrap(), vec![10]);
+        assert_eq!(ends(BoundsPolicy::Warn).unwrap(), vec![10, 30, 30]);
+
+        let mut iter = ranges()
+            .into_iter()
+            .with_bounds_policy(&seqlens, BoundsPolicy::Truncate);
+        iter.by_ref().for_each(drop);
+        assert_eq!(iter.num_truncated(), 1);
+        assert_eq!(iter.num_dropped(), 2);
+    }
+
+    #[test]
+    fn test_restriction() {
+        let restriction = Restriction::new().with_seqnames(["chr1", "chr2"]);
+        assert!(restriction.contains("chr1", 0, 10));
+        assert!(!restriction.contains("chr1_alt", 0, 10));
+
+        let restriction = restriction.with_regions([
+            ("chr1".to_string(), 20, 30),
+            ("chr1".to_string(), 25, 40),
+            ("chr3".to_string(), 0, 10),
+        ]);
+        assert!(restriction.contains("chr1", 35, 50));
+        assert!(restriction.contains("chr1", 10, 21));
+        assert!(!restriction.contains("chr1", 10, 20));
+        assert!(!restriction.contains("chr1", 40, 50));
+        // a zero-width range, within a region
+        assert!(restriction.contains("chr1", 30, 30));
+        // chr2 has no regions, and chr3 is not one of the sequences
+        assert!(!restriction.contains_seqname("chr2"));
+        assert!(!restriction.contains("chr3", 0, 10));
+    }
+}
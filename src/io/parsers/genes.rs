@@ -0,0 +1,758 @@
+//! Parsers for gene annotations, in GFF3, GTF, or BED12 format.
+//!
+//! Gene annotations are read into [`GeneModel`]s, which store each gene's range,
+//! strand, name, and exons (merged across its transcripts), for classifying
+//! where ranges fall relative to genes (see [`crate::annotation`]).
+//!
+//!  - GFF3 and GTF files are read from their `gene` and `exon` features. Exons are
+//!    linked to their gene either directly (the GTF `gene_id` attribute, or a GFF3
+//!    `Parent` that is a gene), or through a transcript (e.g. an `mRNA` feature
+//!    whose `Parent` is a gene). Genes without a `gene` feature (common in GTF
+//!    files) span their exons.
+//!  - BED12 files have one transcript per line, with exons as blocks. Each line
+//!    is a [`GeneModel`] named by its fourth column.
+//!
+//! Annotations can also be read into [`TranscriptModel`]s (with
+//! [`read_transcript_models()`]), which keep each transcript's exons and coding
+//! region, for deriving per-transcript features like introns and UTRs (see
+//! [`TranscriptFeature`]). GFF3 and GTF transcripts are read from their `exon` and
+//! `CDS` features (and GTF `start_codon` and `stop_codon` features), which are linked
+//! to their transcript by the GTF `transcript_id` attribute or their GFF3 `Parent`.
+//! BED12 lines are transcripts, with their coding region from the `thickStart` and
+//! `thickEnd` columns.
+//!
+//! Coordinates are converted from the 1-based, right-inclusive GFF/GTF convention
+//! to the 0-based, right-exclusive convention GRanges uses.
+
+use std::collections::HashMap;
+use std::io::BufRead;
+use std::path::PathBuf;
+
+use clap::ValueEnum;
+use indexmap::IndexMap;
+
+use super::bed::Strand;
+use super::filters::in_restriction;
+use super::utils::{get_base_extension, is_header_line, parse_column};
+use crate::{error::GRangesError, io::InputStream, ranges::operations::merge_intervals, Position};
+
+/// A gene, with its exons merged across transcripts.
+#[derive(Clone, Debug)]
+pub struct GeneModel {
+    pub seqname: String,
+    pub start: Position,
+    pub end: Position,
+    pub strand: Option<Strand>,
+    pub name: String,
+    /// Sorted, non-overlapping exons.
+    pub exons: Vec<(Position, Position)>,
+}
+
+impl GeneModel {
+    /// The 0-based position of the transcription start site, i.e. the first
+    /// basepair of the gene on its strand (genes without a strand are treated as
+    /// being on the forward strand).
+    pub fn tss(&self) -> Position {
+        match self.strand {
+            Some(Strand::Reverse) => self.end.saturating_sub(1),
+            _ => self.start,
+        }
+    }
+}
+
+/// Read the [`GeneModel`]s of a GFF3, GTF, or BED12 file (which may be
+/// gzip-compressed), with the format determined by the file extension.
+pub fn read_gene_models(filepath: impl Into<PathBuf>) -> Result<Vec<GeneModel>, GRangesError> {
+    let filepath = filepath.into();
+    let mut genes = match get_base_extension(&filepath).as_deref() {
+        Some("gff" | "gff3" | "gtf") => read_gff(filepath),
+        Some("bed") => read_bed12(filepath),
+        _ => Err(GRangesError::UnsupportedGenomicRangesFileFormat),
+    }?;
+    genes.retain(|gene| in_restriction(&gene.seqname, gene.start, gene.end));
+    Ok(genes)
+}
+
+pub(crate) fn parse_strand(column: &str) -> Option<Strand> {
+    match column {
+        "+" => Some(Strand::Forward),
+        "-" => Some(Strand::Reverse),
+        _ => None,
+    }
+}
+
+/// Parse a GFF3 (`key=value;...`) or GTF (`key "value"; ...`) attributes column.
+fn parse_attributes(column: &str) -> HashMap<&str, &str> {
+    column
+        .split(';')
+        .filter_map(|attribute| {
+            let attribute = attribute.trim();
+            let (key, value) = attribute
+                .split_once('=')
+                .or_else(|| attribute.split_once(' '))?;
+            Some((key.trim(), value.trim().trim_matches('"')))
+        })
+        .collect()
+}
+
+/// An exon, with the ID of its parent gene or transcript.
+struct ParsedExon {
+    parent: String,
+    seqname: String,
+    start: Position,
+    end: Position,
+    strand: Option<Strand>,
+    gene_name: Option<String>,
+}
+
+/// Read the [`GeneModel`]s of a GFF3 or GTF file.
+fn read_gff(filepath: PathBuf) -> Result<Vec<GeneModel>, GRangesError> {
+    let reader = InputStream::new(filepath).reader()?;
+
+    let mut genes: IndexMap<String, GeneModel> = IndexMap::new();
+    let mut transcript_genes: HashMap<String, String> = HashMap::new();
+    let mut exons = Vec::new();
+    for result in reader.lines() {
+        let line = result?;
+        if line.starts_with("##FASTA") {
+            // the rest of a GFF3 file is sequences
+            break;
+        }
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let columns: Vec<&str> = line.split('\t').collect();
+        if columns.len() < 9 {
+            return Err(GRangesError::InvalidGeneAnnotation(line));
+        }
+        let seqname = columns[0].to_string();
+        let start = parse_column::<Position>(columns[3], &line)?.saturating_sub(1);
+        let end: Position = parse_column(columns[4], &line)?;
+        if start >= end {
+            return Err(GRangesError::InvalidGenomicRange {
+                seqname: Some(columns[0].to_string()),
+                start,
+                end,
+            });
+        }
+        let strand = parse_strand(columns[6]);
+        let attributes = parse_attributes(columns[8]);
+        let id = attributes.get("ID").or(attributes.get("gene_id"));
+        let name = ["Name", "gene_name", "gene_id", "ID"]
+            .iter()
+            .find_map(|key| attributes.get(key))
+            .map(|name| name.to_string());
+
+        match columns[2] {
+            "gene" => {
+                let Some(id) = id else {
+                    continue;
+                };
+                let gene = GeneModel {
+                    seqname,
+                    start,
+                    end,
+                    strand,
+                    name: name.unwrap_or_else(|| id.to_string()),
+                    exons: Vec::new(),
+                };
+                genes.insert(id.to_string(), gene);
+            }
+            "exon" => {
+                // GTF exons name their gene, GFF3 exons their parent(s)
+                let parents = match attributes.get("gene_id") {
+                    Some(gene_id) => vec![*gene_id],
+                    None => attributes
+                        .get("Parent")
+                        .map(|parents| parents.split(',').collect())
+                        .unwrap_or_default(),
+                };
+                let gene_name = attributes.get("gene_name").map(|name| name.to_string());
+                for parent in parents {
+                    exons.push(ParsedExon {
+                        parent: parent.to_string(),
+                        seqname: seqname.clone(),
+                        start,
+                        end,
+                        strand,
+                        gene_name: gene_name.clone(),
+                    });
+                }
+            }
+            _ => {
+                // transcript-like features link exons to genes
+                if let (Some(id), Some(parent)) = (attributes.get("ID"), attributes.get("Parent")) {
+                    transcript_genes.insert(id.to_string(), parent.to_string());
+                }
+            }
+        }
+    }
+
+    for exon in exons {
+        let gene_id = match transcript_genes.get(&exon.parent) {
+            Some(gene_id) if !genes.contains_key(&exon.parent) => gene_id.clone(),
+            _ => exon.parent,
+        };
+        let gene = genes.entry(gene_id.clone()).or_insert_with(|| GeneModel {
+            seqname: exon.seqname,
+            start: exon.start,
+            end: exon.end,
+            strand: exon.strand,
+            name: exon.gene_name.unwrap_or(gene_id),
+            exons: Vec::new(),
+        });
+        gene.start = gene.start.min(exon.start);
+        gene.end = gene.end.max(exon.end);
+        gene.exons.push((exon.start, exon.end));
+    }
+
+    Ok(genes
+        .into_values()
+        .map(|mut gene| {
+            gene.exons = merge_intervals(std::mem::take(&mut gene.exons));
+            gene
+        })
+        .collect())
+}
+
+/// Read the [`GeneModel`]s of a BED12 file.
+fn read_bed12(filepath: PathBuf) -> Result<Vec<GeneModel>, GRangesError> {
+    Ok(read_bed12_transcripts(filepath)?
+        .into_iter()
+        .map(|transcript| GeneModel {
+            seqname: transcript.seqname,
+            start: transcript.start,
+            end: transcript.end,
+            strand: transcript.strand,
+            name: transcript.name,
+            exons: transcript.exons,
+        })
+        .collect())
+}
+
+/// A transcript, with its exons and coding region.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TranscriptModel {
+    pub seqname: String,
+    pub start: Position,
+    pub end: Position,
+    pub strand: Option<Strand>,
+    pub name: String,
+    /// The name of the transcript's gene.
+    pub gene: String,
+    /// Sorted, non-overlapping exons.
+    pub exons: Vec<(Position, Position)>,
+    /// The start and end of the coding region (from the start codon to the stop
+    /// codon), if the transcript is coding.
+    pub coding: Option<(Position, Position)>,
+}
+
+/// A feature of a [`TranscriptModel`] (see [`TranscriptModel::features()`]).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum TranscriptFeature {
+    /// The exons.
+    Exons,
+    /// The gaps between consecutive exons.
+    Introns,
+    /// The exonic parts of the coding region.
+    Cds,
+    /// The exonic parts upstream of the coding region (on the transcript's strand).
+    FivePrimeUtr,
+    /// The exonic parts downstream of the coding region (on the transcript's strand).
+    ThreePrimeUtr,
+    /// The first exon (on the transcript's strand).
+    FirstExon,
+    /// The transcription start site, i.e. the first basepair (on the transcript's
+    /// strand).
+    Tss,
+}
+
+impl TranscriptModel {
+    /// The 0-based position of the transcription start site (see
+    /// [`GeneModel::tss()`]).
+    pub fn tss(&self) -> Position {
+        match self.strand {
+            Some(Strand::Reverse) => self.end.saturating_sub(1),
+            _ => self.start,
+        }
+    }
+
+    /// The gaps between consecutive exons.
+    pub fn introns(&self) -> Vec<(Position, Position)> {
+        self.exons
+            .windows(2)
+            .map(|exons| (exons[0].1, exons[1].0))
+            .collect()
+    }
+
+    /// The exonic parts of the coding region, or none for non-coding transcripts.
+    pub fn cds(&self) -> Vec<(Position, Position)> {
+        match self.coding {
+            Some((start, end)) => clip_intervals(&self.exons, start, end),
+            None => Vec::new(),
+        }
+    }
+
+    /// The exonic parts of the coding region (see [`TranscriptModel::cds()`]), each
+    /// with its GFF3 phase: the number of bases before the first complete codon that
+    /// starts in it, on the transcript's strand.
+    pub fn cds_with_phases(&self) -> Vec<((Position, Position), Position)> {
+        let reverse = self.strand == Some(Strand::Reverse);
+        let mut cds = self.cds();
+        if reverse {
+            cds.reverse();
+        }
+        let mut length = 0;
+        let mut phased: Vec<_> = cds
+            .into_iter()
+            .map(|(start, end)| {
+                let phase = (3 - length % 3) % 3;
+                length += end - start;
+                ((start, end), phase)
+            })
+            .collect();
+        if reverse {
+            phased.reverse();
+        }
+        phased
+    }
+
+    /// The exonic parts upstream of the coding region, on the transcript's strand
+    /// (transcripts without a strand are treated as being on the forward strand).
+    /// Non-coding transcripts have no UTRs.
+    pub fn five_prime_utrs(&self) -> Vec<(Position, Position)> {
+        match (self.coding, self.strand) {
+            (None, _) => Vec::new(),
+            (Some((_, end)), Some(Strand::Reverse)) => {
+                clip_intervals(&self.exons, end, Position::MAX)
+            }
+            (Some((start, _)), _) => clip_intervals(&self.exons, 0, start),
+        }
+    }
+
+    /// The exonic parts downstream of the coding region, on the transcript's strand.
+    pub fn three_prime_utrs(&self) -> Vec<(Position, Position)> {
+        match (self.coding, self.strand) {
+            (None, _) => Vec::new(),
+            (Some((start, _)), Some(Strand::Reverse)) => clip_intervals(&self.exons, 0, start),
+            (Some((_, end)), _) => clip_intervals(&self.exons, end, Position::MAX),
+        }
+    }
+
+    /// The first exon, on the transcript's strand.
+    pub fn first_exon(&self) -> Option<(Position, Position)> {
+        match self.strand {
+            Some(Strand::Reverse) => self.exons.last().copied(),
+            _ => self.exons.first().copied(),
+        }
+    }
+
+    /// The length of the transcript, i.e. the total length of its exons.
+    pub fn length(&self) -> Position {
+        self.exons.iter().map(|(start, end)| end - start).sum()
+    }
+
+    /// The exons in transcript order (5' to 3' on the transcript's strand), with
+    /// their offsets in transcript coordinates.
+    fn exons_with_offsets(&self) -> Vec<(Position, (Position, Position))> {
+        let mut exons = self.exons.clone();
+        if self.strand == Some(Strand::Reverse) {
+            exons.reverse();
+        }
+        let mut offset = 0;
+        exons
+            .into_iter()
+            .map(|(start, end)| {
+                offset += end - start;
+                (offset - (end - start), (start, end))
+            })
+            .collect()
+    }
+
+    /// Map the range `[start, end)` in transcript coordinates (0-based, from the 5'
+    /// end of the transcript) to the genome, as the (sorted) genomic blocks it
+    /// spans across exons, or `None` if it is not within the transcript.
+    pub fn to_genome(&self, start: Position, end: Position) -> Option<Vec<(Position, Position)>> {
+        if start >= end || end > self.length() {
+            return None;
+        }
+        let reverse = self.strand == Some(Strand::Reverse);
+        let mut blocks: Vec<_> = self
+            .exons_with_offsets()
+            .into_iter()
+            .filter_map(|(offset, (exon_start, exon_end))| {
+                let overlap_start = start.max(offset) - offset;
+                let overlap_end = end.min(offset + exon_end - exon_start);
+                let overlap_end = overlap_end.checked_sub(offset)?;
+                if overlap_start >= overlap_end {
+                    return None;
+                }
+                Some(if reverse {
+                    (exon_end - overlap_end, exon_end - overlap_start)
+                } else {
+                    (exon_start + overlap_start, exon_start + overlap_end)
+                })
+            })
+            .collect();
+        blocks.sort_unstable();
+        Some(blocks)
+    }
+
+    /// Map the genomic range `[start, end)` to transcript coordinates (0-based, from
+    /// the 5' end of the transcript), as the range spanning its exonic parts, or
+    /// `None` if it does not overlap an exon.
+    pub fn to_transcript(&self, start: Position, end: Position) -> Option<(Position, Position)> {
+        let reverse = self.strand == Some(Strand::Reverse);
+        self.exons_with_offsets()
+            .into_iter()
+            .filter_map(|(offset, (exon_start, exon_end))| {
+                let (overlap_start, overlap_end) = (start.max(exon_start), end.min(exon_end));
+                if overlap_start >= overlap_end {
+                    return None;
+                }
+                Some(if reverse {
+                    (
+                        offset + exon_end - overlap_end,
+                        offset + exon_end - overlap_start,
+                    )
+                } else {
+                    (
+                        offset + overlap_start - exon_start,
+                        offset + overlap_end - exon_start,
+                    )
+                })
+            })
+            .reduce(|(start, end), (other_start, other_end)| {
+                (start.min(other_start), end.max(other_end))
+            })
+    }
+
+    /// The ranges of a feature of this transcript, sorted by start position.
+    pub fn features(&self, feature: TranscriptFeature) -> Vec<(Position, Position)> {
+        match feature {
+            TranscriptFeature::Exons => self.exons.clone(),
+            TranscriptFeature::Introns => self.introns(),
+            TranscriptFeature::Cds => self.cds(),
+            TranscriptFeature::FivePrimeUtr => self.five_prime_utrs(),
+            TranscriptFeature::ThreePrimeUtr => self.three_prime_utrs(),
+            TranscriptFeature::FirstExon => self.first_exon().into_iter().collect(),
+            TranscriptFeature::Tss => vec![(self.tss(), self.tss() + 1)],
+        }
+    }
+}
+
+/// The parts of the sorted `intervals` within `[start, end)`.
+fn clip_intervals(
+    intervals: &[(Position, Position)],
+    start: Position,
+    end: Position,
+) -> Vec<(Position, Position)> {
+    intervals
+        .iter()
+        .filter_map(|&(interval_start, interval_end)| {
+            let clipped = (interval_start.max(start), interval_end.min(end));
+            (clipped.0 < clipped.1).then_some(clipped)
+        })
+        .collect()
+}
+
+/// Read the [`TranscriptModel`]s of a GFF3, GTF, or BED12 file (which may be
+/// gzip-compressed), with the format determined by the file extension.
+pub fn read_transcript_models(
+    filepath: impl Into<PathBuf>,
+) -> Result<Vec<TranscriptModel>, GRangesError> {
+    let filepath = filepath.into();
+    let mut transcripts = match get_base_extension(&filepath).as_deref() {
+        Some("gff" | "gff3" | "gtf") => read_gff_transcripts(filepath),
+        Some("bed") => read_bed12_transcripts(filepath),
+        _ => Err(GRangesError::UnsupportedGenomicRangesFileFormat),
+    }?;
+    transcripts
+        .retain(|transcript| in_restriction(&transcript.seqname, transcript.start, transcript.end));
+    Ok(transcripts)
+}
+
+/// Read the [`TranscriptModel`]s of a GFF3 or GTF file.
+fn read_gff_transcripts(filepath: PathBuf) -> Result<Vec<TranscriptModel>, GRangesError> {
+    let reader = InputStream::new(filepath).reader()?;
+
+    let mut transcripts: IndexMap<String, TranscriptModel> = IndexMap::new();
+    // the gene IDs of transcripts, and the names of genes
+    let mut transcript_genes: HashMap<String, String> = HashMap::new();
+    let mut gene_names: HashMap<String, String> = HashMap::new();
+    for result in reader.lines() {
+        let line = result?;
+        if line.starts_with("##FASTA") {
+            break;
+        }
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let columns: Vec<&str> = line.split('\t').collect();
+        if columns.len() < 9 {
+            return Err(GRangesError::InvalidGeneAnnotation(line));
+        }
+        let start = parse_column::<Position>(columns[3], &line)?.saturating_sub(1);
+        let end: Position = parse_column(columns[4], &line)?;
+        if start >= end {
+            return Err(GRangesError::InvalidGenomicRange {
+                seqname: Some(columns[0].to_string()),
+                start,
+                end,
+            });
+        }
+        let attributes = parse_attributes(columns[8]);
+        let gene_id = attributes.get("gene_id");
+        if let (Some(gene_id), Some(gene_name)) = (gene_id, attributes.get("gene_name")) {
+            gene_names.insert(gene_id.to_string(), gene_name.to_string());
+        }
+
+        let is_exon = columns[2] == "exon";
+        match columns[2] {
+            "gene" => {
+                let id = attributes.get("ID").or(gene_id);
+                let name = attributes.get("Name").or(attributes.get("gene_name"));
+                if let (Some(id), Some(name)) = (id, name) {
+                    gene_names.insert(id.to_string(), name.to_string());
+                }
+            }
+            "exon" | "CDS" | "start_codon" | "stop_codon" => {
+                // GTF features name their transcript, GFF3 features their parent(s)
+                let parents: Vec<&str> = match attributes.get("transcript_id") {
+                    Some(transcript_id) => vec![*transcript_id],
+                    None => attributes
+                        .get("Parent")
+                        .map(|parents| parents.split(',').collect())
+                        .unwrap_or_default(),
+                };
+                for parent in parents {
+                    if let Some(gene_id) = gene_id {
+                        transcript_genes.insert(parent.to_string(), gene_id.to_string());
+                    }
+                    let transcript =
+                        transcripts
+                            .entry(parent.to_string())
+                            .or_insert_with(|| TranscriptModel {
+                                seqname: columns[0].to_string(),
+                                start,
+                                end,
+                                strand: parse_strand(columns[6]),
+                                name: parent.to_string(),
+                                gene: parent.to_string(),
+                                exons: Vec::new(),
+                                coding: None,
+                            });
+                    if is_exon {
+                        transcript.start = transcript.start.min(start);
+                        transcript.end = transcript.end.max(end);
+                        transcript.exons.push((start, end));
+                    } else {
+                        let coding = transcript.coding.get_or_insert((start, end));
+                        *coding = (coding.0.min(start), coding.1.max(end));
+                    }
+                }
+            }
+            _ => {
+                // transcript-like features link transcripts to genes
+                let parent = attributes.get("Parent").or(gene_id);
+                if let (Some(id), Some(parent)) = (attributes.get("ID"), parent) {
+                    transcript_genes.insert(id.to_string(), parent.to_string());
+                }
+            }
+        }
+    }
+
+    Ok(transcripts
+        .into_values()
+        .filter(|transcript| !transcript.exons.is_empty())
+        .map(|mut transcript| {
+            // exons may be linked directly to a gene, rather than a transcript
+            let gene_id = transcript_genes
+                .get(&transcript.name)
+                .unwrap_or(&transcript.name);
+            transcript.gene = gene_names.get(gene_id).unwrap_or(gene_id).clone();
+            transcript.exons = merge_intervals(std::mem::take(&mut transcript.exons));
+            transcript
+        })
+        .collect())
+}
+
+/// Read the [`TranscriptModel`]s of a BED12 file, named (along with their gene) by
+/// their fourth column.
+fn read_bed12_transcripts(filepath: PathBuf) -> Result<Vec<TranscriptModel>, GRangesError> {
+    let reader = InputStream::new(filepath).reader()?;
+
+    let mut transcripts = Vec::new();
+    for result in reader.lines() {
+        let line = result?;
+        if is_header_line(&line) {
+            continue;
+        }
+        let columns: Vec<&str> = line.split('\t').collect();
+        if columns.len() < 12 {
+            return Err(GRangesError::InvalidGeneAnnotation(line));
+        }
+        let start: Position = parse_column(columns[1], &line)?;
+        let end: Position = parse_column(columns[2], &line)?;
+        let thick_start: Position = parse_column(columns[6], &line)?;
+        let thick_end: Position = parse_column(columns[7], &line)?;
+        let block_sizes = columns[10].trim_end_matches(',').split(',');
+        let block_starts = columns[11].trim_end_matches(',').split(',');
+        let exons = block_sizes
+            .zip(block_starts)
+            .map(|(size, block_start)| {
+                let size: Position = parse_column(size, &line)?;
+                let block_start: Position = parse_column(block_start, &line)?;
+                let exon_start = start.checked_add(block_start);
+                exon_start
+                    .zip(exon_start.and_then(|exon_start| exon_start.checked_add(size)))
+                    .ok_or_else(|| GRangesError::InvalidGenomicRange {
+                        seqname: Some(columns[0].to_string()),
+                        start,
+                        end: Position::MAX,
+                    })
+            })
+            .collect::<Result<Vec<_>, GRangesError>>()?;
+        transcripts.push(TranscriptModel {
+            seqname: columns[0].to_string(),
+            start,
+            end,
+            strand: parse_strand(columns[5]),
+            name: columns[3].to_string(),
+            gene: columns[3].to_string(),
+            exons: merge_intervals(exons),
+            coding: (thick_start < thick_end).then_some((thick_start, thick_end)),
+        });
+    }
+    Ok(transcripts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use tempfile::Builder;
+
+    fn temp_file(suffix: &str, contents: &str) -> tempfile::NamedTempFile {
+        let mut file = Builder::new().suffix(suffix).tempfile().unwrap();
+        file.write_all(contents.as_bytes()).unwrap();
+        file
+    }
+
+    #[test]
+    fn test_read_gff3() {
+        let file = temp_file(
+            ".gff3",
+            "##gff-version 3\n\
+             chr1\t.\tgene\t101\t500\t.\t-\t.\tID=gene1;Name=ABC\n\
+             chr1\t.\tmRNA\t101\t500\t.\t-\t.\tID=tx1;Parent=gene1\n\
+             chr1\t.\texon\t101\t200\t.\t-\t.\tParent=tx1\n\
+             chr1\t.\texon\t401\t500\t.\t-\t.\tParent=tx1\n",
+        );
+        let genes = read_gene_models(file.path()).unwrap();
+        assert_eq!(genes.len(), 1);
+        assert_eq!(genes[0].name, "ABC");
+        assert_eq!((genes[0].start, genes[0].end), (100, 500));
+        assert_eq!(genes[0].exons, vec![(100, 200), (400, 500)]);
+        assert_eq!(genes[0].tss(), 499);
+    }
+
+    #[test]
+    fn test_read_gtf() {
+        // GTF files often lack gene features
+        let file = temp_file(
+            ".gtf",
+            "chr2\t.\texon\t11\t20\t.\t+\t.\tgene_id \"g1\"; gene_name \"XYZ\";\n\
+             chr2\t.\texon\t31\t40\t.\t+\t.\tgene_id \"g1\"; gene_name \"XYZ\";\n",
+        );
+        let genes = read_gene_models(file.path()).unwrap();
+        assert_eq!(genes[0].name, "XYZ");
+        assert_eq!((genes[0].start, genes[0].end), (10, 40));
+        assert_eq!(genes[0].tss(), 10);
+    }
+
+    #[test]
+    fn test_read_bed12() {
+        let file = temp_file(
+            ".bed",
+            "chr1\t100\t500\tABC\t0\t+\t100\t500\t0\t2\t100,50,\t0,350,\n",
+        );
+        let genes = read_gene_models(file.path()).unwrap();
+        assert_eq!(genes[0].exons, vec![(100, 200), (450, 500)]);
+
+        let transcripts = read_transcript_models(file.path()).unwrap();
+        assert_eq!(transcripts[0].coding, Some((100, 500)));
+        assert_eq!(transcripts[0].introns(), vec![(200, 450)]);
+    }
+
+    #[test]
+    fn test_read_invalid_ranges() {
+        // an end before the start
+        let file = temp_file(".gff3", "chr1\t.\texon\t200\t101\t.\t+\t.\tParent=tx1\n");
+        assert!(read_gene_models(file.path()).is_err());
+        assert!(read_transcript_models(file.path()).is_err());
+
+        // an exon past the maximum position
+        let bed12 = format!(
+            "chr1\t{}\t{}\ttx1\t0\t+\t0\t0\t0\t1\t10,\t0,\n",
+            Position::MAX - 5,
+            Position::MAX
+        );
+        let file = temp_file(".bed", &bed12);
+        assert!(read_gene_models(file.path()).is_err());
+    }
+
+    #[test]
+    fn test_read_transcript_models() {
+        let file = temp_file(
+            ".gff3",
+            "##gff-version 3\n\
+             chr1\t.\tgene\t101\t500\t.\t-\t.\tID=gene1;Name=ABC\n\
+             chr1\t.\tmRNA\t101\t500\t.\t-\t.\tID=tx1;Parent=gene1\n\
+             chr1\t.\texon\t101\t200\t.\t-\t.\tParent=tx1\n\
+             chr1\t.\texon\t301\t350\t.\t-\t.\tParent=tx1\n\
+             chr1\t.\texon\t401\t500\t.\t-\t.\tParent=tx1\n\
+             chr1\t.\tCDS\t151\t200\t.\t-\t.\tParent=tx1\n\
+             chr1\t.\tCDS\t301\t350\t.\t-\t.\tParent=tx1\n\
+             chr1\t.\tCDS\t401\t420\t.\t-\t.\tParent=tx1\n",
+        );
+        let transcripts = read_transcript_models(file.path()).unwrap();
+        assert_eq!(transcripts.len(), 1);
+        let transcript = &transcripts[0];
+        assert_eq!(
+            (transcript.name.as_str(), transcript.gene.as_str()),
+            ("tx1", "ABC")
+        );
+        assert_eq!(transcript.coding, Some((150, 420)));
+
+        let features = |feature| transcript.features(feature);
+        assert_eq!(
+            features(TranscriptFeature::Introns),
+            vec![(200, 300), (350, 400)]
+        );
+        assert_eq!(
+            features(TranscriptFeature::Cds),
+            vec![(150, 200), (300, 350), (400, 420)]
+        );
+        // phases count from the 5' end of the coding region
+        assert_eq!(
+            transcript.cds_with_phases(),
+            vec![((150, 200), 2), ((300, 350), 1), ((400, 420), 0)]
+        );
+        // on the reverse strand, the 5' UTR is at the end
+        assert_eq!(features(TranscriptFeature::FivePrimeUtr), vec![(420, 500)]);
+        assert_eq!(features(TranscriptFeature::ThreePrimeUtr), vec![(100, 150)]);
+        assert_eq!(features(TranscriptFeature::FirstExon), vec![(400, 500)]);
+        assert_eq!(features(TranscriptFeature::Tss), vec![(499, 500)]);
+
+        // coordinate mapping, from the 5' end of the transcript
+        assert_eq!(transcript.length(), 250);
+        assert_eq!(
+            transcript.to_genome(90, 120),
+            Some(vec![(330, 350), (490, 500)])
+        );
+        assert_eq!(transcript.to_genome(240, 251), None);
+        assert_eq!(transcript.to_transcript(330, 500), Some((0, 120)));
+        assert_eq!(transcript.to_transcript(200, 300), None);
+    }
+}
@@ -0,0 +1,266 @@
+//! Zero-copy parsing of BED-like files.
+//!
+//! The standard parsing iterators (e.g. [`Bed3Iterator`]) read each row into owned
+//! types, which allocates a new [`String`] for the sequence name (and any remaining
+//! columns) of every row. For large files, these allocations dominate parsing time.
+//!
+//! The [`BorrowedBedIterator`] parses a BED-like file from a byte slice lazily, and
+//! yields [`BorrowedBedRecord`] items whose fields are `&str` slices into the input,
+//! so no per-row allocation is needed. With the `mmap` feature, [`MmapBedFile`]
+//! memory-maps a file, so that the operating system pages it in as it is parsed:
+//!
+//! ```
+//! use granges::{io::parsers::mmap::BorrowedBedIterator, prelude::*};
+//!
+//! let seqlens = seqlens! { "chr1" => 22, "chr2" => 10, "chr3" => 10, "chr4" => 15 };
+//! // with the 'mmap' feature, this could be MmapBedFile::open(...) and file.iter()
+//! let bytes = std::fs::read("tests_data/example.bed").unwrap();
+//! let gr = GRangesEmpty::from_iter(BorrowedBedIterator::new(&bytes), &seqlens).unwrap();
+//! assert_eq!(gr.len(), 5);
+//! ```
+//!
+//! Since [`BorrowedBedRecord`] implements [`GenomicRecord`], these iterators can be
+//! used anywhere the standard parsing iterators over ranges can be, e.g. to build a
+//! [`GRangesEmpty`] or in a [`StreamingLeftOverlaps`] join. Like the standard parsing
+//! iterators, empty lines and lines starting with `'#'` are skipped.
+//!
+//! [`Bed3Iterator`]: crate::io::parsers::Bed3Iterator
+//! [`GRangesEmpty`]: crate::granges::GRangesEmpty
+//! [`StreamingLeftOverlaps`]: crate::streaming::StreamingLeftOverlaps
+
+use super::{filters::keep_parsed, utils::parse_column, FilteredRanges};
+use crate::{
+    error::GRangesError,
+    ranges::{GenomicRangeRecord, GenomicRangeRecordEmpty},
+    traits::{GeneralRangeRecordIterator, GenericRange, GenomicRecord},
+    Position,
+};
+
+/// A BED-like record borrowed from the input it was parsed from.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BorrowedBedRecord<'a> {
+    pub seqname: &'a str,
+    pub start: Position,
+    pub end: Position,
+    /// The unparsed columns after the first three, if any.
+    pub data: Option<&'a str>,
+}
+
+impl<'a> BorrowedBedRecord<'a> {
+    /// Get the additional column at `index` (where zero is the first column
+    /// after the end position), if it exists.
+    pub fn column(&self, index: usize) -> Option<&'a str> {
+        self.data?.split('\t').nth(index)
+    }
+
+    /// Convert this record into an owned [`GenomicRangeRecordEmpty`], dropping any
+    /// additional columns.
+    pub fn to_record_empty(&self) -> GenomicRangeRecordEmpty {
+        GenomicRangeRecordEmpty {
+            seqname: self.seqname.to_string(),
+            start: self.start,
+            end: self.end,
+        }
+    }
+
+    /// Convert this record into an owned [`GenomicRangeRecord<Option<String>>`], as
+    /// yielded by the [`BedlikeIterator`].
+    ///
+    /// [`BedlikeIterator`]: crate::io::parsers::BedlikeIterator
+    pub fn to_record(&self) -> GenomicRangeRecord<Option<String>> {
+        GenomicRangeRecord::new(
+            self.seqname.to_string(),
+            self.start,
+            self.end,
+            self.data.map(|data| data.to_string()),
+        )
+    }
+}
+
+impl<'a> GenericRange for BorrowedBedRecord<'a> {
+    fn start(&self) -> Position {
+        self.start
+    }
+    fn end(&self) -> Position {
+        self.end
+    }
+    fn index(&self) -> Option<usize> {
+        None
+    }
+}
+
+impl<'a> GenomicRecord for BorrowedBedRecord<'a> {
+    fn seqname(&self) -> &str {
+        self.seqname
+    }
+}
+
+/// Parse a single line (without its line terminator) into a [`BorrowedBedRecord`].
+fn parse_borrowed_line(line: &str) -> Result<BorrowedBedRecord<'_>, GRangesError> {
+    let mut columns = line.splitn(4, '\t');
+    let (Some(seqname), Some(start), Some(end)) = (columns.next(), columns.next(), columns.next())
+    else {
+        let num_columns = line.split('\t').count();
+        return Err(GRangesError::Bed3TooFewColumns(
+            num_columns,
+            line.to_string(),
+        ));
+    };
+    Ok(BorrowedBedRecord {
+        seqname,
+        start: parse_column(start, line)?,
+        end: parse_column(end, line)?,
+        data: columns.next(),
+    })
+}
+
+/// A lazy, zero-copy parsing iterator over the BED-like records in a byte slice.
+#[derive(Clone, Debug)]
+pub struct BorrowedBedIterator<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> BorrowedBedIterator<'a> {
+    /// Create a new [`BorrowedBedIterator`] over the (uncompressed) BED-like
+    /// contents in `bytes`.
+    pub fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes, pos: 0 }
+    }
+
+    /// Get the next line, without its line terminator.
+    fn next_line(&mut self) -> Option<&'a [u8]> {
+        if self.pos >= self.bytes.len() {
+            return None;
+        }
+        let rest = &self.bytes[self.pos..];
+        let (mut line, consumed) = match rest.iter().position(|&byte| byte == b'\n') {
+            Some(newline) => (&rest[..newline], newline + 1),
+            None => (rest, rest.len()),
+        };
+        self.pos += consumed;
+        if let Some(stripped) = line.strip_suffix(b"\r") {
+            line = stripped;
+        }
+        Some(line)
+    }
+}
+
+impl<'a> Iterator for BorrowedBedIterator<'a> {
+    type Item = Result<BorrowedBedRecord<'a>, GRangesError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let line = self.next_line()?;
+            if line.is_empty() || line[0] == b'#' {
+                continue;
+            }
+            let result = std::str::from_utf8(line)
+                .map_err(GRangesError::from)
+                .and_then(parse_borrowed_line);
+            if keep_parsed(&result) {
+                return Some(result);
+            }
+        }
+    }
+}
+
+impl<'a> GeneralRangeRecordIterator<BorrowedBedRecord<'a>> for BorrowedBedIterator<'a> {
+    fn retain_seqnames(self, seqnames: &[String]) -> FilteredRanges<Self, BorrowedBedRecord<'a>> {
+        FilteredRanges::new(self, Some(&seqnames.to_vec()), None)
+    }
+    fn exclude_seqnames(self, seqnames: &[String]) -> FilteredRanges<Self, BorrowedBedRecord<'a>> {
+        FilteredRanges::new(self, None, Some(&seqnames.to_vec()))
+    }
+}
+
+/// A memory-mapped, uncompressed BED-like file, which can be parsed with
+/// a zero-copy [`BorrowedBedIterator`].
+///
+/// # Safety
+///
+/// As with any memory-mapped file, the file must not be modified (e.g. by another
+/// process) while it is mapped; doing so is undefined behavior.
+#[cfg(feature = "mmap")]
+#[derive(Debug)]
+pub struct MmapBedFile {
+    mmap: memmap2::Mmap,
+}
+
+#[cfg(feature = "mmap")]
+impl MmapBedFile {
+    /// Memory-map the BED-like file at `filepath`. Gzip-compressed files cannot be
+    /// memory-mapped, and will raise a [`GRangesError::CompressedMmapInput`] error.
+    pub fn open(filepath: impl Into<std::path::PathBuf>) -> Result<Self, GRangesError> {
+        let filepath = filepath.into();
+        let file = std::fs::File::open(&filepath).map_err(|source| GRangesError::FileError {
+            path: filepath.clone(),
+            source,
+        })?;
+        // SAFETY: see the safety note on [`MmapBedFile`]; the file is only read.
+        let mmap = unsafe { memmap2::Mmap::map(&file)? };
+        if mmap.starts_with(&[0x1f, 0x8b]) {
+            return Err(GRangesError::CompressedMmapInput(
+                filepath.display().to_string(),
+            ));
+        }
+        Ok(Self { mmap })
+    }
+
+    /// Get a zero-copy parsing iterator over the records of this file.
+    pub fn iter(&self) -> BorrowedBedIterator<'_> {
+        BorrowedBedIterator::new(&self.mmap)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{io::parsers::Bed3Iterator, prelude::*};
+
+    #[test]
+    fn test_borrowed_bed_iterator() {
+        let bytes = b"# a comment\nchr1\t0\t10\tname\t3.4\r\n\nchr2\t5\t8\n";
+        let records: Vec<_> = BorrowedBedIterator::new(bytes)
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].seqname, "chr1");
+        assert_eq!(records[0].data, Some("name\t3.4"));
+        assert_eq!(records[0].column(1), Some("3.4"));
+        assert_eq!(records[1].data, None);
+        assert_eq!(records[1].width(), 3);
+
+        let mut iter = BorrowedBedIterator::new(b"chr1\t0\nchr1\tzero\t10");
+        assert!(matches!(
+            iter.next().unwrap(),
+            Err(GRangesError::Bed3TooFewColumns(2, _))
+        ));
+        assert!(matches!(
+            iter.next().unwrap(),
+            Err(GRangesError::InvalidColumnType { .. })
+        ));
+    }
+
+    #[test]
+    fn test_borrowed_matches_bed3() {
+        let seqlens = seqlens! { "chr1" => 22, "chr2" => 10, "chr3" => 10, "chr4" => 15 };
+        let bytes = std::fs::read("tests_data/example.bed").unwrap();
+        let borrowed = GRangesEmpty::from_iter(
+            BorrowedBedIterator::new(&bytes).exclude_seqnames(&["chr1".to_string()]),
+            &seqlens,
+        )
+        .unwrap();
+        let owned = GRangesEmpty::from_iter(
+            Bed3Iterator::new("tests_data/example.bed")
+                .unwrap()
+                .exclude_seqnames(&["chr1".to_string()]),
+            &seqlens,
+        )
+        .unwrap();
+        assert_eq!(
+            borrowed.iter_ranges().collect::<Vec<_>>(),
+            owned.iter_ranges().collect::<Vec<_>>()
+        );
+    }
+}
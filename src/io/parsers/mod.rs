@@ -62,12 +62,26 @@
 //!
 
 pub mod bed;
+pub mod bedpe;
+pub mod cytoband;
 pub mod detect;
 pub mod filters;
+pub mod genes;
+pub mod mmap;
+pub mod rmsk;
 pub mod tsv;
 pub mod utils;
 
 pub use bed::{Bed3Iterator, Bed5Addition, Bed5Iterator, BedlikeIterator};
-pub use detect::{GenomicRangesFile, GenomicRangesParser};
+pub use bedpe::{BedpeIterator, BedpeRecord};
+pub use cytoband::{Cytoband, CytobandIterator, Cytobands};
+pub use detect::{set_input_format, GenomicRangesFile, GenomicRangesParser, InputFormat};
+pub use rmsk::{RepeatMaskerIterator, RepeatRecord};
 
-pub use filters::{FilteredRanges, UnwrappedRanges};
+pub use filters::{
+    set_restriction, BoundedRanges, BoundsPolicy, BoundsPolicyExt, FilteredRanges, Restriction,
+    UnwrappedRanges,
+};
+pub use genes::{
+    read_gene_models, read_transcript_models, GeneModel, TranscriptFeature, TranscriptModel,
+};
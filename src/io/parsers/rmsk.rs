@@ -0,0 +1,311 @@
+//! A lazy parser for repeat annotations, from RepeatMasker or the UCSC `rmsk` table.
+//!
+//! Two formats are supported, and are distinguished line by line:
+//!
+//!  - [RepeatMasker](https://www.repeatmasker.org/webrepeatmaskerhelp.html) `.out`
+//!    files, which have whitespace-separated columns (after a three-line header),
+//!    1-based, right-inclusive positions, a `C` strand for repeats on the reverse
+//!    strand, and the repeat class and family in one `class/family` column.
+//!  - Dumps of the UCSC Genome Browser's `rmsk` table (e.g. `rmsk.txt.gz`), which
+//!    have tab-separated columns (with or without the leading `bin` column), 0-based
+//!    positions, and divergence in parts per thousand.
+//!
+//! Each repeat is read as a [`GenomicRangeRecord<RepeatRecord>`], whose typed columns
+//! can be selected by name (see [`crate::data::columns`]).
+
+use serde::Serialize;
+use std::{
+    io::{BufRead, BufReader},
+    path::PathBuf,
+};
+
+use crate::{
+    io::{
+        parsers::{
+            bed::{bedlike::PARSE_CAPACITY, Strand},
+            filters::in_restriction,
+            utils::{parse_column, skip_malformed},
+        },
+        InputStream, TsvConfig,
+    },
+    ranges::GenomicRangeRecord,
+    traits::TsvFormat,
+    GRangesError, Position,
+};
+
+/// A repeat's alignment to its consensus sequence.
+///
+/// # Fields
+/// * `name`: the repeat name (e.g. `AluY` or `(TAACCC)n`).
+/// * `class`: the repeat class (e.g. `SINE` or `Simple_repeat`).
+/// * `family`: the repeat family (e.g. `Alu`), which is the class for repeats
+///   without one (following the UCSC table).
+/// * `score`: the Smith-Waterman alignment score.
+/// * `strand`: the strand of the repeat.
+/// * `divergence`: the percent of substitutions from the consensus.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct RepeatRecord {
+    pub name: String,
+    pub class: String,
+    pub family: String,
+    pub score: u32,
+    pub strand: Strand,
+    pub divergence: f64,
+}
+
+/// Written as BED6+3 columns: the name, score, strand, class, family, and
+/// divergence.
+impl TsvFormat for RepeatRecord {
+    fn write_columns(&self, columns: &mut Vec<String>, config: &TsvConfig) {
+        columns.push(self.name.clone());
+        columns.push(self.score.to_string());
+        columns.push(self.strand.symbol().to_string());
+        columns.push(self.class.clone());
+        columns.push(self.family.clone());
+        self.divergence.write_columns(columns, config);
+    }
+}
+
+/// Parse a RepeatMasker or UCSC strand column.
+fn parse_repeat_strand(column: &str, line: &str) -> Result<Strand, GRangesError> {
+    match column {
+        "+" => Ok(Strand::Forward),
+        "-" | "C" => Ok(Strand::Reverse),
+        _ => Err(GRangesError::InvalidColumnType {
+            expected_type: "strand (+, -, or C)".to_string(),
+            found_value: column.to_string(),
+            line: line.to_string(),
+        }),
+    }
+}
+
+/// Parses a line of a RepeatMasker `.out` file (converting its 1-based, inclusive
+/// positions).
+pub fn parse_repeatmasker_out(
+    line: &str,
+) -> Result<GenomicRangeRecord<RepeatRecord>, GRangesError> {
+    let columns: Vec<&str> = line.split_whitespace().collect();
+    if columns.len() < 11 {
+        return Err(GRangesError::BedTooFewColumns(
+            columns.len(),
+            11,
+            line.to_string(),
+        ));
+    }
+    let start: Position = parse_column(columns[5], line)?;
+    let end: Position = parse_column(columns[6], line)?;
+    if start == 0 || start > end {
+        return Err(GRangesError::InvalidGenomicRange {
+            seqname: Some(columns[4].to_string()),
+            start,
+            end,
+        });
+    }
+    let (class, family) = columns[10]
+        .split_once('/')
+        .unwrap_or((columns[10], columns[10]));
+    Ok(GenomicRangeRecord {
+        seqname: columns[4].to_string(),
+        start: start - 1,
+        end,
+        data: RepeatRecord {
+            name: columns[9].to_string(),
+            class: class.to_string(),
+            family: family.to_string(),
+            score: parse_column(columns[0], line)?,
+            strand: parse_repeat_strand(columns[8], line)?,
+            divergence: parse_column(columns[1], line)?,
+        },
+    })
+}
+
+/// Parses a line of a UCSC `rmsk` table dump, with or without its leading `bin`
+/// column.
+pub fn parse_rmsk(line: &str) -> Result<GenomicRangeRecord<RepeatRecord>, GRangesError> {
+    let columns: Vec<&str> = line.split('\t').collect();
+    let columns = match columns.len() {
+        16 => &columns[..],
+        n if n > 16 => &columns[1..],
+        n => return Err(GRangesError::BedTooFewColumns(n, 16, line.to_string())),
+    };
+    let start: Position = parse_column(columns[5], line)?;
+    let end: Position = parse_column(columns[6], line)?;
+    if start > end {
+        return Err(GRangesError::InvalidGenomicRange {
+            seqname: Some(columns[4].to_string()),
+            start,
+            end,
+        });
+    }
+    let milli_divergence: u32 = parse_column(columns[1], line)?;
+    Ok(GenomicRangeRecord {
+        seqname: columns[4].to_string(),
+        start,
+        end,
+        data: RepeatRecord {
+            name: columns[9].to_string(),
+            class: columns[10].to_string(),
+            family: columns[11].to_string(),
+            score: parse_column(columns[0], line)?,
+            strand: parse_repeat_strand(columns[8], line)?,
+            divergence: milli_divergence as f64 / 10.0,
+        },
+    })
+}
+
+/// Whether a line of a RepeatMasker `.out` file is part of its header (the column
+/// names, or the message written when no repeats were found).
+fn is_repeatmasker_header(line: &str) -> bool {
+    let line = line.trim_start();
+    line.is_empty()
+        || line.starts_with("SW")
+        || line.starts_with("score")
+        || line.starts_with("There were no repetitive sequences")
+}
+
+/// A lazy parser for RepeatMasker `.out` files and UCSC `rmsk` table dumps, yielding
+/// [`GenomicRangeRecord<RepeatRecord>`] entries.
+pub struct RepeatMaskerIterator {
+    reader: BufReader<Box<dyn std::io::Read>>,
+    line_buffer: String,
+    /// The input's name, and the number of lines read, for errors.
+    source: String,
+    line_number: u64,
+}
+
+impl std::fmt::Debug for RepeatMaskerIterator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RepeatMaskerIterator")
+            .finish_non_exhaustive()
+    }
+}
+
+impl RepeatMaskerIterator {
+    /// Create a new lazy-parsing iterator over a RepeatMasker `.out` file or UCSC
+    /// `rmsk` table dump (which may be gzip-compressed).
+    pub fn new(filepath: impl Into<PathBuf>) -> Result<Self, GRangesError> {
+        let filepath = filepath.into();
+        let source = filepath.display().to_string();
+        let reader = InputStream::new(filepath).reader()?;
+        Ok(Self {
+            reader,
+            line_buffer: String::with_capacity(PARSE_CAPACITY),
+            source,
+            line_number: 0,
+        })
+    }
+}
+
+impl Iterator for RepeatMaskerIterator {
+    type Item = Result<GenomicRangeRecord<RepeatRecord>, GRangesError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            self.line_buffer.clear();
+            match self.reader.read_line(&mut self.line_buffer) {
+                Ok(0) => return None,
+                Ok(_) => {
+                    self.line_number += 1;
+                    let line = self.line_buffer.trim_end();
+                    // skip the header lines of both formats
+                    if line.starts_with('#') || is_repeatmasker_header(line) {
+                        continue;
+                    }
+                    let parsed = if line.contains('\t') {
+                        parse_rmsk(line)
+                    } else {
+                        parse_repeatmasker_out(line)
+                    };
+                    let error = match parsed {
+                        Ok(record)
+                            if !in_restriction(&record.seqname, record.start, record.end) =>
+                        {
+                            continue;
+                        }
+                        Ok(record) => return Some(Ok(record)),
+                        Err(error) => GRangesError::ParseError {
+                            file: self.source.clone(),
+                            line: self.line_number,
+                            message: error.to_string(),
+                        },
+                    };
+                    if let Some(error) = skip_malformed(error) {
+                        return Some(Err(error));
+                    }
+                }
+                Err(e) => return Some(Err(GRangesError::IOError(e))),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_repeatmasker_out, parse_rmsk, RepeatMaskerIterator};
+    use crate::{io::parsers::bed::Strand, prelude::*};
+
+    #[test]
+    fn test_parse_repeatmasker_out() {
+        let line = " 3612  11.4 21.5  1.3  chr1        10469   11447 (248944975) C  \
+                    TAR1           Satellite/telo       (399) 1712    483      2";
+        let record = parse_repeatmasker_out(line).unwrap();
+        assert_eq!(record.seqname, "chr1");
+        assert_eq!((record.start, record.end), (10468, 11447));
+        assert_eq!(record.data.name, "TAR1");
+        assert_eq!(record.data.class, "Satellite");
+        assert_eq!(record.data.family, "telo");
+        assert_eq!(record.data.score, 3612);
+        assert_eq!(record.data.strand, Strand::Reverse);
+        assert_eq!(record.data.divergence, 11.4);
+
+        let line = "  463   1.3  0.6  1.7  chr1  10001  10468 (248945954) +  (TAACCC)n  \
+                    Simple_repeat  1  463  (0)  1";
+        let record = parse_repeatmasker_out(line).unwrap();
+        assert_eq!(record.data.class, "Simple_repeat");
+        assert_eq!(record.data.family, "Simple_repeat");
+        assert_eq!(record.data.strand, Strand::Forward);
+
+        assert!(parse_repeatmasker_out("463 1.3 0.6 1.7 chr1 10001").is_err());
+        assert!(
+            parse_repeatmasker_out("463 1.3 0.6 1.7 chr1 0 10 (0) + (TAACCC)n Simple_repeat")
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_parse_rmsk() {
+        let line = "585\t3612\t114\t215\t13\tchr1\t10468\t11447\t-248944975\t-\tTAR1\t\
+                    Satellite\ttelo\t-399\t1712\t483\t2";
+        let record = parse_rmsk(line).unwrap();
+        assert_eq!((record.start, record.end), (10468, 11447));
+        assert_eq!(record.data.family, "telo");
+        assert_eq!(record.data.divergence, 11.4);
+        assert_eq!(record.data.strand, Strand::Reverse);
+
+        // without the bin column
+        let record = parse_rmsk(line.split_once('\t').unwrap().1).unwrap();
+        assert_eq!(record.data.name, "TAR1");
+        assert!(parse_rmsk("chr1\t10\t20").is_err());
+    }
+
+    #[test]
+    fn test_repeatmasker_iterator() {
+        let seqlens = seqlens! { "chr1" => 20000 };
+        for path in ["tests_data/example.out", "tests_data/example_rmsk.txt"] {
+            let iter = RepeatMaskerIterator::new(path).unwrap();
+            let gr = GRanges::from_iter(iter, &seqlens).unwrap();
+            assert_eq!(gr.len(), 3);
+            let classes = gr.column::<String>("class").unwrap();
+            assert_eq!(
+                classes,
+                vec![
+                    Some("Simple_repeat".to_string()),
+                    Some("Satellite".to_string()),
+                    Some("LINE".to_string())
+                ]
+            );
+            let divergence = gr.column::<f64>("divergence").unwrap();
+            assert_eq!(divergence[2], Some(25.1));
+        }
+    }
+}
@@ -2,7 +2,6 @@
 //! deserialization method using [`serde`].
 
 use csv::{DeserializeRecordsIntoIter, Reader, ReaderBuilder};
-use flate2::read::GzDecoder;
 use serde::de::Error as DeError;
 use serde::{Deserialize, Deserializer};
 use std::fs::File;
@@ -10,7 +9,16 @@ use std::io::{self, Read};
 use std::path::PathBuf;
 use std::str::FromStr;
 
-use crate::error::GRangesError;
+use crate::{
+    error::GRangesError,
+    io::{
+        file::{bytes_reader, open_input},
+        parsers::utils::skip_malformed,
+    },
+};
+
+/// The name of in-memory inputs in errors.
+pub(crate) const IN_MEMORY_SOURCE: &str = "in-memory input";
 
 /// Build a TSV reader which ignores comment lines, works on gzip-compressed
 /// files, etc.
@@ -28,27 +36,26 @@ pub fn build_tsv_reader(
     filepath: impl Into<PathBuf>,
 ) -> Result<Reader<Box<dyn Read>>, GRangesError> {
     let filepath = filepath.into();
-    let file = File::open(&filepath)?;
-    let is_gzipped = is_gzipped_file(&filepath)?;
-    let stream: Box<dyn Read> = if is_gzipped {
-        Box::new(GzDecoder::new(file))
-    } else {
-        Box::new(file)
-    };
+    let stream = open_input(&filepath).map_err(|source| GRangesError::FileError {
+        path: filepath,
+        source,
+    })?;
+    Ok(tsv_reader(stream))
+}
 
-    let reader = ReaderBuilder::new()
+fn tsv_reader(stream: Box<dyn Read>) -> Reader<Box<dyn Read>> {
+    ReaderBuilder::new()
         .delimiter(b'\t')
         .has_headers(false)
         .comment(Some(b'#'))
-        .from_reader(stream);
-    Ok(reader)
+        .from_reader(stream)
 }
 
 /// Deserializes some value of type `t` with some possible missing
 /// character `missing_chars` into [`Option<T>`].
 pub fn deserialize_option_generic<'de, D, T>(
     deserializer: D,
-    missing_chars: &'de [&'de str],
+    missing_chars: &[&str],
 ) -> Result<Option<T>, D::Error>
 where
     D: Deserializer<'de>,
@@ -70,6 +77,7 @@ where
 /// data.
 pub struct TsvRecordIterator<T> {
     inner: DeserializeRecordsIntoIter<Box<dyn std::io::Read>, T>,
+    source: String,
 }
 
 impl<T> std::fmt::Debug for TsvRecordIterator<T> {
@@ -100,10 +108,21 @@ where
     /// E.g. for VCF, it would need to be parsed.
     pub fn new(filepath: impl Into<PathBuf>) -> Result<Self, GRangesError> {
         let filepath = filepath.into();
+        let source = filepath.display().to_string();
         let reader = build_tsv_reader(filepath)?;
         let inner = reader.into_deserialize();
 
-        Ok(Self { inner })
+        Ok(Self { inner, source })
+    }
+
+    /// Create a new TSV reader over in-memory data (which may be gzip-compressed),
+    /// e.g. for use without a filesystem (such as in WebAssembly).
+    pub fn from_bytes(bytes: impl Into<Vec<u8>>) -> Self {
+        let inner = tsv_reader(bytes_reader(bytes)).into_deserialize();
+        Self {
+            inner,
+            source: IN_MEMORY_SOURCE.to_string(),
+        }
     }
 }
 
@@ -114,8 +133,35 @@ where
     type Item = Result<T, GRangesError>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        self.inner
-            .next()
-            .map(|res| res.map_err(|e| GRangesError::IOError(e.into())))
+        loop {
+            let error = match self.inner.next()? {
+                Ok(record) => return Some(Ok(record)),
+                Err(error) => parse_error(&self.source, error),
+            };
+            if let Some(error) = skip_malformed(error) {
+                return Some(Err(error));
+            }
+        }
+    }
+}
+
+/// Convert a [`csv`] error reading `source` into a [`GRangesError::ParseError`] with
+/// its line number and the offending column, or a [`GRangesError::IOError`].
+fn parse_error(source: &str, error: csv::Error) -> GRangesError {
+    if error.is_io_error() {
+        return GRangesError::IOError(error.into());
+    }
+    let line = error.position().map_or(0, |position| position.line());
+    let message = match error.kind() {
+        csv::ErrorKind::Deserialize { err, .. } => match err.field() {
+            Some(field) => format!("column {}: {}", field + 1, err.kind()),
+            None => err.kind().to_string(),
+        },
+        _ => error.to_string(),
+    };
+    GRangesError::ParseError {
+        file: source.to_string(),
+        line,
+        message,
     }
 }
@@ -1,7 +1,46 @@
 use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use tracing::warn;
 
 use crate::{GRangesError, Position};
 
+static SKIP_ERRORS: AtomicUsize = AtomicUsize::new(0);
+static ERRORS_SKIPPED: AtomicUsize = AtomicUsize::new(0);
+
+/// Set the number of malformed lines (across all inputs) that are skipped with a
+/// warning, rather than raising a [`GRangesError::ParseError`]. By default, none are.
+pub fn set_skip_errors(limit: usize) {
+    SKIP_ERRORS.store(limit, Ordering::Relaxed);
+}
+
+/// The number of malformed lines that have been skipped.
+pub fn errors_skipped() -> usize {
+    ERRORS_SKIPPED.load(Ordering::Relaxed)
+}
+
+/// Skip the malformed line of a [`GRangesError::ParseError`] (returning `None`) if
+/// fewer than the limit set with [`set_skip_errors()`] have been skipped, or otherwise
+/// return the error.
+pub(crate) fn skip_malformed(error: GRangesError) -> Option<GRangesError> {
+    let is_parse_error = matches!(error, GRangesError::ParseError { .. });
+    if !is_parse_error || errors_skipped() >= SKIP_ERRORS.load(Ordering::Relaxed) {
+        return Some(error);
+    }
+    ERRORS_SKIPPED.fetch_add(1, Ordering::Relaxed);
+    warn!("skipping malformed line: {}", error);
+    None
+}
+
+/// Whether `line` of a BED-like file should be skipped rather than parsed: an empty
+/// line, or a comment (`#`), track, or browser line.
+pub fn is_header_line(line: &str) -> bool {
+    line.is_empty()
+        || line.starts_with('#')
+        || line.starts_with("track")
+        || line.starts_with("browser")
+}
+
 /// Parses a single column from a string slice into a specified type.
 ///
 /// This function is particularly useful for converting columns in genomic data files
@@ -100,7 +139,16 @@ pub fn get_base_extension<P: AsRef<Path>>(filepath: P) -> Option<String> {
 
 #[cfg(test)]
 mod tests {
-    use super::get_base_extension;
+    use super::{get_base_extension, is_header_line};
+
+    #[test]
+    fn test_is_header_line() {
+        assert!(is_header_line(""));
+        assert!(is_header_line("# comment"));
+        assert!(is_header_line("track name=peaks"));
+        assert!(is_header_line("browser position chr1:1-100"));
+        assert!(!is_header_line("chr1\t0\t10"));
+    }
 
     #[test]
     fn test_get_base_extension() {
@@ -0,0 +1,81 @@
+//! Remote inputs, streamed over HTTP(S) or from S3.
+//!
+//! Any input path that is a `http://`, `https://`, or `s3://` URL is downloaded as it
+//! is read (like local inputs, gzip-compressed data is decompressed on the fly), when
+//! GRanges is compiled with the `remote` feature. S3 URLs are read from public
+//! buckets over HTTPS, either from AWS, or from the S3-compatible endpoint set in the
+//! `AWS_ENDPOINT_URL` environment variable (with path-style URLs). Requests are not
+//! signed, so private objects need a presigned `https://` URL.
+//!
+//! Note that input type detection reads the start of an input (see
+//! [`GenomicRangesFile::detect()`](crate::io::GenomicRangesFile::detect)), so
+//! the first lines of a remote input may be requested more than once.
+
+use std::io;
+use std::path::Path;
+
+/// URL schemes that are read remotely.
+pub const REMOTE_SCHEMES: &[&str] = &["http://", "https://", "s3://"];
+
+/// Returns whether `path` is a URL to be read remotely, e.g. `https://example.com/a.bed`.
+pub fn is_remote(path: &Path) -> bool {
+    path.to_str()
+        .is_some_and(|path| REMOTE_SCHEMES.iter().any(|scheme| path.starts_with(scheme)))
+}
+
+/// Resolve a remote input's URL to the HTTP(S) URL it is downloaded from, with S3
+/// objects read from the S3-compatible `endpoint` if set, or AWS if not.
+pub fn resolve_url(url: &str, endpoint: Option<&str>) -> String {
+    let Some(object) = url.strip_prefix("s3://") else {
+        return url.to_string();
+    };
+    let (bucket, key) = object.split_once('/').unwrap_or((object, ""));
+    match endpoint {
+        Some(endpoint) => format!("{}/{}/{}", endpoint.trim_end_matches('/'), bucket, key),
+        None => format!("https://{}.s3.amazonaws.com/{}", bucket, key),
+    }
+}
+
+/// Open a streaming reader over the remote input at `url`.
+#[cfg(feature = "remote")]
+pub fn open_remote(url: &str) -> io::Result<Box<dyn io::Read>> {
+    let endpoint = std::env::var("AWS_ENDPOINT_URL").ok();
+    let url = resolve_url(url, endpoint.as_deref());
+    let response = ureq::get(&url)
+        .call()
+        .map_err(|err| io::Error::other(format!("{}: {}", url, err)))?;
+    Ok(Box::new(response.into_reader()))
+}
+
+#[cfg(not(feature = "remote"))]
+pub fn open_remote(url: &str) -> io::Result<Box<dyn io::Read>> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        crate::error::GRangesError::RequiresFeature(
+            format!("reading {}", url),
+            "remote".to_string(),
+        ),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_url() {
+        assert!(is_remote(Path::new("s3://bucket/a.bed")));
+        assert!(!is_remote(Path::new("tests_data/example.bed")));
+
+        let url = "https://example.com/a.bed.gz";
+        assert_eq!(resolve_url(url, None), url);
+        assert_eq!(
+            resolve_url("s3://bucket/dir/a.bed", None),
+            "https://bucket.s3.amazonaws.com/dir/a.bed"
+        );
+        assert_eq!(
+            resolve_url("s3://bucket/a.bed", Some("http://localhost:9000/")),
+            "http://localhost:9000/bucket/a.bed"
+        );
+    }
+}
@@ -0,0 +1,226 @@
+//! Browser-ready track output: BED `track` lines, and UCSC track hubs.
+//!
+//! A [`TrackLine`] is written as the first line of a BED file, so that the UCSC
+//! Genome Browser or IGV display it with a name, description, and color. A track
+//! hub (written with [`write_track_hub()`]) is a directory that the UCSC browser
+//! loads from a URL:
+//!
+//! ```text
+//! hub/
+//!   hub.txt
+//!   genomes.txt
+//!   <assembly>/trackDb.txt
+//!   <assembly>/chrom.sizes
+//!   <assembly>/<track>.bed
+//! ```
+//!
+//! Hub tracks are bigBed files, which are built from each track's sorted BED file
+//! with UCSC's `bedToBigBed` (e.g. `bedToBigBed <track>.bed chrom.sizes
+//! <track>.bb`), since GRanges does not write bigBed files itself.
+
+use indexmap::IndexMap;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use crate::{error::GRangesError, io::parsers::utils::parse_column, Position};
+
+/// A track's display settings, written as a BED `track` line or a hub's
+/// `trackDb.txt` stanza.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TrackLine {
+    pub name: String,
+    pub description: Option<String>,
+    /// An RGB color.
+    pub color: Option<(u8, u8, u8)>,
+}
+
+impl TrackLine {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            description: None,
+            color: None,
+        }
+    }
+
+    fn description(&self) -> &str {
+        self.description.as_deref().unwrap_or(&self.name)
+    }
+
+    /// The `trackDb.txt` stanza of this track in a hub, as a bigBed file with
+    /// `bed_columns` standard BED columns.
+    pub fn hub_stanza(&self, bed_columns: usize) -> String {
+        let mut stanza = format!(
+            "track {name}\nbigDataUrl {name}.bb\nshortLabel {name}\nlongLabel {}\n\
+             type bigBed {}\nvisibility dense\n",
+            self.description(),
+            bed_columns,
+            name = self.name,
+        );
+        if let Some((r, g, b)) = self.color {
+            stanza.push_str(&format!("color {},{},{}\n", r, g, b));
+        }
+        stanza
+    }
+}
+
+impl fmt::Display for TrackLine {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "track name=\"{}\" description=\"{}\"",
+            self.name,
+            self.description()
+        )?;
+        if let Some((r, g, b)) = self.color {
+            write!(f, " color={},{},{}", r, g, b)?;
+        }
+        Ok(())
+    }
+}
+
+/// Parse an RGB color, e.g. `255,0,0`.
+pub fn parse_color(color: &str) -> Result<(u8, u8, u8), GRangesError> {
+    let invalid = || GRangesError::InvalidColor(color.to_string());
+    let components = color
+        .split(',')
+        .map(|component| component.trim().parse::<u8>().map_err(|_| invalid()))
+        .collect::<Result<Vec<_>, _>>()?;
+    match components[..] {
+        [r, g, b] => Ok((r, g, b)),
+        _ => Err(invalid()),
+    }
+}
+
+/// A hub track: its display settings, and its BED lines.
+#[derive(Clone, Debug)]
+pub struct HubTrack {
+    pub track: TrackLine,
+    pub lines: Vec<String>,
+}
+
+/// Write a UCSC track hub for the genome `assembly` (e.g. `hg38`) to the directory
+/// `dir` (see the [module documentation](self)), with each track's BED lines sorted
+/// as `bedToBigBed` requires. The hub is named `name`, and `email` is its contact
+/// address, which the UCSC browser requires.
+pub fn write_track_hub(
+    dir: &Path,
+    name: &str,
+    email: &str,
+    assembly: &str,
+    seqlens: &IndexMap<String, Position>,
+    tracks: &[HubTrack],
+) -> Result<(), GRangesError> {
+    let genome_dir = dir.join(assembly);
+    fs::create_dir_all(&genome_dir)?;
+
+    fs::write(
+        dir.join("hub.txt"),
+        format!(
+            "hub {name}\nshortLabel {name}\nlongLabel {name}\ngenomesFile genomes.txt\n\
+             email {}\n",
+            email,
+            name = name
+        ),
+    )?;
+    fs::write(
+        dir.join("genomes.txt"),
+        format!("genome {0}\ntrackDb {0}/trackDb.txt\n", assembly),
+    )?;
+
+    let mut chrom_sizes = BufWriter::new(File::create(genome_dir.join("chrom.sizes"))?);
+    for (seqname, length) in seqlens {
+        writeln!(chrom_sizes, "{}\t{}", seqname, length)?;
+    }
+    chrom_sizes.flush()?;
+
+    let mut stanzas = Vec::with_capacity(tracks.len());
+    for hub_track in tracks {
+        let bed_columns = sorted_bed(&hub_track.lines, seqlens)?;
+        let mut bed = BufWriter::new(File::create(
+            genome_dir.join(format!("{}.bed", hub_track.track.name)),
+        )?);
+        let mut num_columns = usize::MAX;
+        for (_, _, line) in &bed_columns {
+            writeln!(bed, "{}", line)?;
+            num_columns = num_columns.min(line.split('\t').count());
+        }
+        bed.flush()?;
+        stanzas.push(hub_track.track.hub_stanza(num_columns.clamp(3, 12)));
+    }
+    fs::write(genome_dir.join("trackDb.txt"), stanzas.join("\n"))?;
+    Ok(())
+}
+
+/// Sort BED lines by sequence name (in byte order, like `sort -k1,1`) and start,
+/// checking that each sequence is in `seqlens`.
+fn sorted_bed<'a>(
+    lines: &'a [String],
+    seqlens: &IndexMap<String, Position>,
+) -> Result<Vec<(&'a str, Position, &'a str)>, GRangesError> {
+    let mut records = Vec::with_capacity(lines.len());
+    for line in lines {
+        let mut columns = line.split('\t');
+        let seqname = columns.next().unwrap_or_default();
+        if !seqlens.contains_key(seqname) {
+            return Err(GRangesError::missing_sequence(seqname));
+        }
+        let Some(start) = columns.next() else {
+            return Err(GRangesError::Bed3TooFewColumns(1, line.to_string()));
+        };
+        let start = parse_column(start, line)?;
+        records.push((seqname, start, line.as_str()));
+    }
+    records.sort_by(|a, b| a.0.as_bytes().cmp(b.0.as_bytes()).then(a.1.cmp(&b.1)));
+    Ok(records)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::seqlens;
+
+    #[test]
+    fn test_track_line() {
+        let mut track = TrackLine::new("peaks");
+        assert_eq!(
+            track.to_string(),
+            "track name=\"peaks\" description=\"peaks\""
+        );
+        track.color = Some(parse_color("255,0,0").unwrap());
+        assert!(track.to_string().ends_with(" color=255,0,0"));
+        assert!(track.hub_stanza(6).contains("type bigBed 6\n"));
+        assert!(parse_color("255,0").is_err());
+    }
+
+    #[test]
+    fn test_write_track_hub() {
+        let dir = tempfile::tempdir().unwrap();
+        let seqlens = seqlens! { "chr2" => 100, "chr10" => 100 };
+        let track = HubTrack {
+            track: TrackLine::new("peaks"),
+            lines: vec![
+                "chr2\t5\t10\ta".to_string(),
+                "chr10\t20\t30\tb".to_string(),
+                "chr2\t1\t3\tc".to_string(),
+            ],
+        };
+        write_track_hub(
+            dir.path(),
+            "test",
+            "me@example.com",
+            "hg38",
+            &seqlens,
+            &[track],
+        )
+        .unwrap();
+
+        let bed = fs::read_to_string(dir.path().join("hg38/peaks.bed")).unwrap();
+        assert_eq!(bed, "chr10\t20\t30\tb\nchr2\t1\t3\tc\nchr2\t5\t10\ta\n");
+        let track_db = fs::read_to_string(dir.path().join("hg38/trackDb.txt")).unwrap();
+        assert!(track_db.contains("type bigBed 4\n"));
+        assert!(dir.path().join("hub.txt").exists());
+    }
+}
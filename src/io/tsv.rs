@@ -1,22 +1,233 @@
 //! TSV Serializing helpers, functionality, etc.
 
+use csv::QuoteStyle;
 use lazy_static::lazy_static;
+use std::sync::OnceLock;
+
+use crate::{error::GRangesError, traits::TsvFormat};
+
+/// The float precision (in significant digits) bedtools uses by default, e.g. for
+/// `bedtools map` (its `-prec` option).
+pub const BEDTOOLS_PRECISION: usize = 5;
+
+/// The default string for missing values, in both input and output.
+pub const DEFAULT_NA_VALUE: &str = ".";
+
+static NA_VALUE: OnceLock<String> = OnceLock::new();
+
+/// Set the string for missing values globally, e.g. `NA`, or an empty string for
+/// empty fields. This is written for missing values by [`BED_TSV`], and is parsed as
+/// a missing value (as is the default, `.`).
+///
+/// Like [`set_num_threads()`](crate::parallel::set_num_threads), this must be called
+/// before any input is read or output written, and can only be called once.
+pub fn set_na_value(value: impl Into<String>) -> Result<(), GRangesError> {
+    NA_VALUE
+        .set(value.into())
+        .map_err(GRangesError::NaValueAlreadySet)
+}
+
+/// The string for missing values (see [`set_na_value()`]).
+pub fn na_value() -> &'static str {
+    NA_VALUE.get_or_init(|| DEFAULT_NA_VALUE.to_string())
+}
+
+/// Whether `value` represents a missing value: either the default (`.`), or the
+/// string set with [`set_na_value()`].
+pub fn is_na_value(value: &str) -> bool {
+    value == DEFAULT_NA_VALUE || value == na_value()
+}
 
 lazy_static! {
-    /// The standard BED format TSV configuration.
+    /// The standard BED format TSV configuration, with missing values written as
+    /// [`na_value()`]. BED-like formats have no quoting, and their data columns may
+    /// contain tabs (e.g. the unparsed remainder of a BED-like line, whose tabs
+    /// separate its columns), so fields are never quoted.
     pub static ref BED_TSV: TsvConfig = TsvConfig {
-        no_value_string: ".".to_string(),
+        no_value_string: na_value().to_string(),
         headers: None,
         metadata: None,
+        float_precision: None,
+        quote_style: QuoteStyle::Never,
+    };
+
+    /// The BED format TSV configuration, with floats formatted like bedtools.
+    pub static ref BEDTOOLS_TSV: TsvConfig = TsvConfig {
+        float_precision: Some(BEDTOOLS_PRECISION),
+        ..BED_TSV.clone()
     };
 }
 
 /// This is an extensible type to handle common
 /// TSV output configurations, e.g. what to print
-/// for `None` or [`DatumType::NoValue`].
+/// for `None` or [`DatumType::NoValue`], and how
+/// many significant digits floats are written with.
+///
+/// [`DatumType::NoValue`]: crate::data::DatumType::NoValue
 #[derive(Debug, Clone)]
 pub struct TsvConfig {
     pub no_value_string: String,
     pub headers: Option<Vec<String>>,
-    pub metadata: Option<Vec<String>>
+    pub metadata: Option<Vec<String>>,
+    /// The number of significant digits floats are written with (see
+    /// [`format_float_general()`]), or `None` for the shortest representation
+    /// that reads back as the same float.
+    pub float_precision: Option<usize>,
+    /// When fields are quoted, e.g. [`QuoteStyle::Necessary`] to quote fields
+    /// containing tabs or quotes.
+    pub quote_style: QuoteStyle,
+}
+
+impl TsvConfig {
+    /// Format a float with this configuration's float precision.
+    pub fn format_float(&self, value: f64) -> String {
+        match self.float_precision {
+            Some(precision) => format_float_general(value, precision),
+            None => value.to_string(),
+        }
+    }
+
+    /// Format a single-precision float with this configuration's float precision.
+    pub fn format_float32(&self, value: f32) -> String {
+        match self.float_precision {
+            Some(precision) => format_float_general(value as f64, precision),
+            None => value.to_string(),
+        }
+    }
+}
+
+/// Format a float with `precision` significant digits like C's `%g` (and C++
+/// streams, which bedtools uses): in fixed notation unless the exponent is less
+/// than -4 or at least `precision`, and without trailing zeros, e.g. `2.3333`,
+/// `1e-05`, or `1.2346e+05` with a precision of 5.
+pub fn format_float_general(value: f64, precision: usize) -> String {
+    if value.is_nan() {
+        return "nan".to_string();
+    }
+    if value.is_infinite() {
+        return if value > 0.0 { "inf" } else { "-inf" }.to_string();
+    }
+    if value == 0.0 {
+        return value.to_string();
+    }
+    let precision = precision.max(1);
+    // the exponent after rounding to the precision, e.g. 99999.5 rounds to 1.0000e5
+    let scientific = format!("{:.*e}", precision - 1, value);
+    let (mantissa, exponent) = scientific
+        .split_once('e')
+        .expect("scientific notation has an exponent");
+    let exponent: i32 = exponent.parse().expect("the exponent is an integer");
+    if exponent < -4 || exponent >= precision as i32 {
+        let sign = if exponent < 0 { '-' } else { '+' };
+        format!("{}e{}{:02}", trim_zeros(mantissa), sign, exponent.abs())
+    } else {
+        let decimals = (precision as i32 - 1 - exponent) as usize;
+        trim_zeros(&format!("{:.*}", decimals, value)).to_string()
+    }
+}
+
+/// Remove the trailing zeros after a decimal point (and the point, if nothing is
+/// left after it).
+fn trim_zeros(number: &str) -> &str {
+    if number.contains('.') {
+        number.trim_end_matches('0').trim_end_matches('.')
+    } else {
+        number
+    }
+}
+
+impl TsvFormat for f64 {
+    fn write_columns(&self, columns: &mut Vec<String>, config: &TsvConfig) {
+        columns.push(config.format_float(*self));
+    }
+}
+
+impl TsvFormat for f32 {
+    fn write_columns(&self, columns: &mut Vec<String>, config: &TsvConfig) {
+        columns.push(config.format_float32(*self));
+    }
+}
+
+macro_rules! impl_tsv_format_display {
+    ($($type:ty),*) => {
+        $(
+            impl TsvFormat for $type {
+                fn write_columns(&self, columns: &mut Vec<String>, _config: &TsvConfig) {
+                    columns.push(self.to_string());
+                }
+            }
+        )*
+    };
+}
+
+impl_tsv_format_display!(i32, i64, u32, u64, usize, String, str);
+
+impl<T: TsvFormat> TsvFormat for Option<T> {
+    /// Write the value, or the [`TsvConfig`]'s `no_value_string` if missing.
+    fn write_columns(&self, columns: &mut Vec<String>, config: &TsvConfig) {
+        match self {
+            Some(value) => value.write_columns(columns, config),
+            None => columns.push(config.no_value_string.clone()),
+        }
+    }
+}
+
+impl<T: TsvFormat> TsvFormat for Vec<T> {
+    /// Write each value as its own column(s).
+    fn write_columns(&self, columns: &mut Vec<String>, config: &TsvConfig) {
+        for value in self {
+            value.write_columns(columns, config);
+        }
+    }
+}
+
+impl<T: TsvFormat + ?Sized> TsvFormat for &T {
+    fn write_columns(&self, columns: &mut Vec<String>, config: &TsvConfig) {
+        (**self).write_columns(columns, config)
+    }
+}
+
+impl TsvFormat for () {
+    fn write_columns(&self, _columns: &mut Vec<String>, _config: &TsvConfig) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_float_general() {
+        let format = |value| format_float_general(value, BEDTOOLS_PRECISION);
+        assert_eq!(format(0.0), "0");
+        assert_eq!(format(7.0), "7");
+        assert_eq!(format(2.0 / 3.0), "0.66667");
+        assert_eq!(format(-12.5), "-12.5");
+        assert_eq!(format(12345.0), "12345");
+        assert_eq!(format(123456.7), "1.2346e+05");
+        assert_eq!(format(99999.5), "1e+05");
+        assert_eq!(format(0.0001), "0.0001");
+        assert_eq!(format(0.00001234), "1.234e-05");
+        assert_eq!(format(f64::NAN), "nan");
+        assert_eq!(format_float_general(3.14159, 1), "3");
+    }
+
+    #[test]
+    fn test_na_value() {
+        assert!(is_na_value("."));
+        assert!(!is_na_value("0"));
+        // the missing value string is fixed once it is used
+        assert_eq!(na_value(), DEFAULT_NA_VALUE);
+        assert!(set_na_value("NA").is_err());
+    }
+
+    #[test]
+    fn test_tsv_format_missing() {
+        let config = TsvConfig {
+            no_value_string: "NA".to_string(),
+            ..BEDTOOLS_TSV.clone()
+        };
+        let mut columns = Vec::new();
+        vec![Some(1.0 / 3.0), None].write_columns(&mut columns, &config);
+        assert_eq!(columns, vec!["0.33333", "NA"]);
+    }
 }
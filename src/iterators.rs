@@ -147,7 +147,7 @@ mod tests {
     use crate::{
         iterators::GRangesRecordIterator,
         ranges::{GenomicRangeIndexedRecord, GenomicRangeRecord},
-        test_utilities::granges_test_case_01,
+        testing::granges_test_case_01,
     };
 
     use super::GRangesIterator;
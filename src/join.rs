@@ -1,12 +1,18 @@
-//! [`LeftGroupedJoin`], [`JoinData`], and [`JoinDataIterator`] types for overlaps.
+//! [`LeftGroupedJoin`], [`JoinData`], and [`JoinDataIterator`] types for overlaps,
+//! and the [`JoinFilter`] overlap requirements for joins. Records can also be joined
+//! by key (e.g. the names of ranges), rather than by overlap, with a [`KeyJoin`].
 //!
 #![allow(clippy::all)]
 
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
+
+use clap::ValueEnum;
 
 use crate::{
+    io::parsers::bed::Strand,
+    ranges::RangeEmpty,
     traits::{GenericRange, IndexedDataContainer, JoinDataOperations},
-    Position,
+    GRangesError, Position,
 };
 
 /// This is a generic range used just in join logic, to avoid
@@ -173,6 +179,177 @@ impl LeftGroupedJoin {
     }
 }
 
+/// How an overlapping left (query) range must relate to a right (subject) range,
+/// beyond just overlapping. These are the Allen-style interval relations.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum OverlapMode {
+    /// Any overlap.
+    #[default]
+    Any,
+    /// The left range is entirely within the right range.
+    Within,
+    /// The left range entirely contains the right range.
+    Contains,
+    /// The left and right ranges start at the same position.
+    StartsWith,
+    /// The left and right ranges end at the same position.
+    EndsWith,
+    /// The left and right ranges are exactly equal.
+    Equal,
+}
+
+impl OverlapMode {
+    /// Whether the `left` and `right` ranges have this relation. This does not check
+    /// that they overlap.
+    pub fn relates<L: GenericRange, R: GenericRange>(&self, left: &L, right: &R) -> bool {
+        match self {
+            OverlapMode::Any => true,
+            OverlapMode::Within => right.start() <= left.start() && left.end() <= right.end(),
+            OverlapMode::Contains => left.start() <= right.start() && right.end() <= left.end(),
+            OverlapMode::StartsWith => left.start() == right.start(),
+            OverlapMode::EndsWith => left.end() == right.end(),
+            OverlapMode::Equal => left.start() == right.start() && left.end() == right.end(),
+        }
+    }
+}
+
+/// Overlap requirements for a left range and a right range to be joined: a minimum
+/// number of overlapping basepairs, minimum fractions of the left and right ranges'
+/// widths that overlap (both fractions give a reciprocal overlap, like
+/// `bedtools intersect -f 0.5 -r`), and an [`OverlapMode`] relation between them.
+/// By default, any overlap joins two ranges.
+///
+/// A filter can also pad the left range by a window of basepairs on either side
+/// (like `bedtools window -l -r`), so that right ranges *near* it are joined too.
+/// The padding is only applied while finding overlaps, and all the requirements are
+/// then checked against the padded left range.
+///
+/// The same [`JoinFilter`] applies to filtering joins (e.g.
+/// [`GRanges::filter_overlaps_with()`]), overlap counts, and left joins (with
+/// [`LeftOverlaps::left_overlaps_with()`], e.g. before
+/// [`GRanges::map_joins()`]), where right ranges that do not pass are not joined.
+///
+/// # Example
+///
+/// ```
+/// use granges::join::JoinFilter;
+/// use granges::prelude::*;
+///
+/// let seqlens = seqlens! { "chr1" => 100 };
+/// let mut left = GRangesEmpty::new_vec(&seqlens);
+/// left.push_range("chr1", 0, 10).unwrap();
+/// left.push_range("chr1", 20, 60).unwrap();
+/// let mut right = GRangesEmpty::new_vec(&seqlens);
+/// right.push_range("chr1", 5, 40).unwrap();
+/// let right = right.into_coitrees().unwrap();
+///
+/// // half of each left range must overlap
+/// let filter = JoinFilter::new().min_left_fraction(0.5);
+/// let filtered = left.filter_overlaps_with(&right, &filter).unwrap();
+/// assert_eq!(filtered.len(), 1);
+/// ```
+///
+/// [`GRanges::filter_overlaps_with()`]: crate::granges::GRanges::filter_overlaps_with
+/// [`GRanges::map_joins()`]: crate::granges::GRanges::map_joins
+/// [`LeftOverlaps::left_overlaps_with()`]: crate::traits::LeftOverlaps::left_overlaps_with
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct JoinFilter {
+    /// The minimum number of overlapping basepairs.
+    pub min_overlap: Position,
+    /// The minimum fraction of the left range's width that overlaps.
+    pub min_left_fraction: f64,
+    /// The minimum fraction of the right range's width that overlaps.
+    pub min_right_fraction: f64,
+    /// How the left range must relate to the right range.
+    pub mode: OverlapMode,
+    /// The number of basepairs the left range is padded by before its start.
+    pub left_window: Position,
+    /// The number of basepairs the left range is padded by after its end.
+    pub right_window: Position,
+}
+
+impl JoinFilter {
+    /// Create a new [`JoinFilter`] that any overlap passes.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Require at least `min_overlap` overlapping basepairs.
+    pub fn min_overlap(mut self, min_overlap: Position) -> Self {
+        self.min_overlap = min_overlap;
+        self
+    }
+
+    /// Require at least the fraction `fraction` of the left range to overlap.
+    pub fn min_left_fraction(mut self, fraction: f64) -> Self {
+        self.min_left_fraction = fraction;
+        self
+    }
+
+    /// Require at least the fraction `fraction` of the right range to overlap.
+    pub fn min_right_fraction(mut self, fraction: f64) -> Self {
+        self.min_right_fraction = fraction;
+        self
+    }
+
+    /// Require at least the fraction `fraction` of both the left and right ranges to
+    /// overlap (a reciprocal overlap).
+    pub fn reciprocal(self, fraction: f64) -> Self {
+        self.min_left_fraction(fraction)
+            .min_right_fraction(fraction)
+    }
+
+    /// Require the left range to have the relation `mode` to the right range.
+    pub fn mode(mut self, mode: OverlapMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Pad the left range by `left` basepairs before its start and `right`
+    /// basepairs after its end while finding overlaps.
+    pub fn window(mut self, left: Position, right: Position) -> Self {
+        self.left_window = left;
+        self.right_window = right;
+        self
+    }
+
+    /// Orient the window by the left range's strand: for reverse-strand ranges,
+    /// the left and right windows are swapped, so that they are upstream and
+    /// downstream of the range.
+    pub fn for_strand(self, strand: Option<Strand>) -> Self {
+        match strand {
+            Some(Strand::Reverse) => self.window(self.right_window, self.left_window),
+            _ => self,
+        }
+    }
+
+    /// Whether any overlap passes this filter, i.e. it has no requirements.
+    pub fn is_any(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// The `[start, end)` range to find overlaps with for the left range `[start,
+    /// end)`, i.e. padded by the window.
+    pub fn query_range(&self, start: Position, end: Position) -> (Position, Position) {
+        (
+            start.saturating_sub(self.left_window),
+            end.saturating_add(self.right_window),
+        )
+    }
+
+    /// Whether the overlap between the (padded) `left` and `right` ranges passes this
+    /// filter.
+    pub fn passes<L: GenericRange, R: GenericRange>(&self, left: &L, right: &R) -> bool {
+        let (start, end) = self.query_range(left.start(), left.end());
+        let left = RangeEmpty { start, end };
+        let overlap = left.overlap_width(right);
+        overlap >= self.min_overlap
+            && overlap as f64 >= self.min_left_fraction * left.width() as f64
+            && overlap as f64 >= self.min_right_fraction * right.width() as f64
+            && self.mode.relates(&left, right)
+    }
+}
+
 /// [`JoinData`] contains a [`Vec<LeftGroupedJoin>`] of all overlap joins,
 /// and owns the left data container from the join. It stores a reference
 /// to the right data container.
@@ -531,6 +708,67 @@ impl<'a, DL, DR> Iterator for JoinDataIterator<'a, DL, DR> {
     }
 }
 
+/// How a [`KeyJoin`] handles right records that share a key.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum DuplicateKeyPolicy {
+    /// Join each left record with every right record with its key.
+    #[default]
+    All,
+    /// Join each left record with only the first right record with its key.
+    First,
+    /// Raise an error if right records share a key.
+    Error,
+}
+
+/// A hash join of records by a key (e.g. the names of ranges), rather than by their
+/// coordinates. The right records are indexed by key in a hash table, so that each
+/// left record can then be joined with the right records with its key, without
+/// either set of records being sorted.
+#[derive(Clone, Debug)]
+pub struct KeyJoin<T> {
+    index: HashMap<String, Vec<T>>,
+}
+
+impl<T> KeyJoin<T> {
+    /// Index the `(key, record)` pairs of the right records, handling records that
+    /// share a key according to `duplicates`. The records with each key are kept in
+    /// their input order.
+    pub fn new(
+        right: impl IntoIterator<Item = (String, T)>,
+        duplicates: DuplicateKeyPolicy,
+    ) -> Result<Self, GRangesError> {
+        let mut index: HashMap<String, Vec<T>> = HashMap::new();
+        for (key, record) in right {
+            match index.get_mut(&key) {
+                None => {
+                    index.insert(key, vec![record]);
+                }
+                Some(records) => match duplicates {
+                    DuplicateKeyPolicy::All => records.push(record),
+                    DuplicateKeyPolicy::First => {}
+                    DuplicateKeyPolicy::Error => return Err(GRangesError::DuplicateJoinKey(key)),
+                },
+            }
+        }
+        Ok(Self { index })
+    }
+
+    /// The right records with the key `key`, which are empty if there are none.
+    pub fn get(&self, key: &str) -> &[T] {
+        self.index.get(key).map_or(&[], Vec::as_slice)
+    }
+
+    /// The number of distinct keys of the right records.
+    pub fn len(&self) -> usize {
+        self.index.len()
+    }
+
+    /// Whether there are no right records.
+    pub fn is_empty(&self) -> bool {
+        self.index.is_empty()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -551,6 +789,66 @@ mod tests {
         assert_eq!(jd.len(), 1);
     }
 
+    #[test]
+    fn test_join_filter() {
+        let left = RangeIndexed::new(0, 100, 0);
+        let right = RangeIndexed::new(80, 120, 1);
+        assert!(JoinFilter::new().is_any());
+        assert!(JoinFilter::new().passes(&left, &right));
+        assert!(JoinFilter::new().min_overlap(20).passes(&left, &right));
+        assert!(!JoinFilter::new().min_overlap(21).passes(&left, &right));
+        assert!(JoinFilter::new()
+            .min_left_fraction(0.2)
+            .passes(&left, &right));
+        assert!(JoinFilter::new()
+            .min_right_fraction(0.5)
+            .passes(&left, &right));
+        assert!(!JoinFilter::new().reciprocal(0.5).passes(&left, &right));
+    }
+
+    #[test]
+    fn test_overlap_mode() {
+        let left = RangeIndexed::new(10, 20, 0);
+        let outer = RangeIndexed::new(10, 30, 1);
+        let equal = RangeIndexed::new(10, 20, 2);
+        let inner = RangeIndexed::new(12, 20, 3);
+
+        assert!(OverlapMode::Within.relates(&left, &outer));
+        assert!(!OverlapMode::Contains.relates(&left, &outer));
+        assert!(OverlapMode::Contains.relates(&left, &inner));
+        assert!(!OverlapMode::Within.relates(&left, &inner));
+        assert!(OverlapMode::StartsWith.relates(&left, &outer));
+        assert!(!OverlapMode::EndsWith.relates(&left, &outer));
+        assert!(OverlapMode::EndsWith.relates(&left, &inner));
+        assert!(OverlapMode::Equal.relates(&left, &equal));
+        assert!(!OverlapMode::Equal.relates(&left, &inner));
+
+        let filter = JoinFilter::new().mode(OverlapMode::Within);
+        assert!(!filter.is_any());
+        assert!(filter.passes(&left, &outer));
+        assert!(!filter.passes(&outer, &left));
+    }
+
+    #[test]
+    fn test_join_filter_window() {
+        let filter = JoinFilter::new().window(10, 5);
+        assert!(!filter.is_any());
+        assert_eq!(filter.query_range(100, 110), (90, 115));
+        assert_eq!(filter.query_range(5, 10), (0, 15));
+
+        // on the reverse strand, the 10bp left window is after the end
+        let reverse = filter.for_strand(Some(Strand::Reverse));
+        assert_eq!(reverse.query_range(100, 110), (95, 120));
+        assert_eq!(filter.for_strand(Some(Strand::Forward)), filter);
+
+        // requirements are checked against the padded left range
+        let left = RangeIndexed::new(100, 110, 0);
+        let right = RangeIndexed::new(112, 120, 1);
+        assert!(filter.min_overlap(3).passes(&left, &right));
+        assert!(!filter.min_overlap(4).passes(&left, &right));
+        assert!(reverse.min_overlap(8).passes(&left, &right));
+    }
+
     #[test]
     fn test_single_range_indexed() {
         let ranges = vec![RangeIndexed {
@@ -636,4 +934,28 @@ mod tests {
         assert_eq!(second_range.indices().len(), 1);
         assert!(second_range.indices().contains(&Some(1)));
     }
+
+    #[test]
+    fn test_key_join() {
+        let right = || {
+            vec![
+                ("tx1".to_string(), 1),
+                ("tx2".to_string(), 2),
+                ("tx1".to_string(), 3),
+            ]
+        };
+        let join = KeyJoin::new(right(), DuplicateKeyPolicy::All).unwrap();
+        assert_eq!(join.len(), 2);
+        assert_eq!(join.get("tx1"), &[1, 3]);
+        assert_eq!(join.get("tx2"), &[2]);
+        assert!(join.get("tx3").is_empty());
+
+        let join = KeyJoin::new(right(), DuplicateKeyPolicy::First).unwrap();
+        assert_eq!(join.get("tx1"), &[1]);
+
+        assert!(matches!(
+            KeyJoin::new(right(), DuplicateKeyPolicy::Error),
+            Err(GRangesError::DuplicateJoinKey(key)) if key == "tx1"
+        ));
+    }
 }
@@ -9,7 +9,8 @@
 //! as a test and benchmark of the library. In benchmarks, this command line tool is reliably
 //! 30-40% faster than bedtools. Internally, GRanges uses the very fast
 //! [coitrees](https://github.com/dcjones/coitrees) library written by Daniel C. Jones for overlap
-//! operations.
+//! operations by default; other overlap-query backends can be chosen at construction (see
+//! [`OverlapQuery`](crate::traits::OverlapQuery)).
 //!
 //! The GRanges library aims to simplify the creation of powerful, performant genomics tools in
 //! Rust. GRanges is inspired by the design and ease of use of Bioconductor's
@@ -256,7 +257,8 @@
 //!
 //! ## Manipulating GRanges objects
 //!
-//! 1. *Creation*: [`GRanges::new_vec()`], [`GRanges::from_iter()`], [`GRangesEmpty::from_windows()`].
+//! 1. *Creation*: [`GRanges::new_vec()`], [`GRanges::from_iter()`], [`GRangesEmpty::from_windows()`],
+//!    and [`GRangesBuilder`], which collects unsorted ranges and builds an indexed [`GRanges`].
 //!
 //! 2. *Range-modifying* functions: [`GRanges::into_coitrees()`], [`GRanges::adjust_ranges()`], [`GRanges::sort()`],
 //!        [`GRanges::flanking_ranges()`], [`GRanges::filter_overlaps()`].
@@ -350,19 +352,37 @@
 //! [`GRanges::sort()`]: crate::granges::GRanges::sort
 //! [`GRanges::from_iter()`]: crate::granges::GRanges::from_iter
 //! [`GRangesEmpty::from_windows()`]: crate::granges::GRangesEmpty::from_windows
+//! [`GRangesBuilder`]: crate::builder::GRangesBuilder
 
 pub use indexmap;
 
+pub mod annotation;
+pub mod binning;
+pub mod bootstrap;
+pub mod builder;
+pub mod comparison;
 pub mod data;
+pub mod enrichment;
 pub mod error;
+pub mod expr;
+pub mod gaps;
+pub mod genome;
 pub mod granges;
 pub mod io;
 pub mod iterators;
 pub mod join;
 pub mod merging_iterators;
+pub mod paired;
+pub mod parallel;
+pub mod pipeline;
+pub mod random;
 pub mod ranges;
 pub mod sequences;
-pub mod test_utilities;
+pub mod stabbing;
+pub mod stats;
+pub mod streaming;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
 pub mod traits;
 pub mod unique_id;
 
@@ -406,35 +426,42 @@ pub type PositionOffset = i64;
 /// The main exports of the GRanges library.
 pub mod prelude {
     pub use crate::{Position, PositionOffset};
+    pub use crate::builder::GRangesBuilder;
     pub use crate::error::GRangesError;
-    pub use crate::granges::{GRanges, GRangesEmpty};
-    pub use crate::io::file::read_seqlens;
-    pub use crate::io::tsv::BED_TSV;
+    pub use crate::genome::Genome;
+    pub use crate::granges::{DuplicateKey, GRanges, GRangesEmpty};
+    pub use crate::io::file::{read_seqlens, read_seqlens_from_bytes};
+    pub use crate::io::tsv::{BEDTOOLS_TSV, BED_TSV};
     pub use crate::io::{
-        Bed3Iterator, Bed4Iterator, Bed5Iterator, BedlikeIterator, GenomicRangesFile,
-        GenomicRangesParser, TsvRecordIterator,
+        Bed3Iterator, Bed4Addition, Bed4Iterator, Bed5Addition, Bed5Iterator, Bed6Addition,
+        Bed6Iterator, BedlikeIterator, GenomicRangesFile, GenomicRangesParser, NarrowPeakAddition,
+        NarrowPeakIterator, TsvRecordIterator,
     };
     pub use crate::join::{
         CombinedJoinData, CombinedJoinDataBothEmpty, CombinedJoinDataLeftEmpty,
-        CombinedJoinDataRightEmpty,
+        CombinedJoinDataRightEmpty, JoinFilter, OverlapMode,
     };
 
-    pub use crate::data::DatumType;
+    pub use crate::data::{columns::FromDatumType, DatumType};
     pub use crate::ranges::{
         coitrees::{COITreesEmpty, COITreesIndexed},
+        lapper::{LapperEmpty, LapperIndexed},
+        nclist::{NCListEmpty, NCListIndexed},
+        soa::{SoaRangesEmpty, SoaRangesIndexed},
         try_range,
         vec::{VecRangesEmpty, VecRangesIndexed},
     };
 
     pub use crate::merging_iterators::{
-        ConditionalMergingIterator, ConditionalMergingResultIterator, MergingEmptyIterator,
-        MergingEmptyResultIterator, MergingResultIterator,
+        ConditionalMergingIterator, ConditionalMergingResultIterator,
+        GroupedMergingResultIterator, MergingEmptyIterator, MergingEmptyResultIterator,
+        MergingResultIterator,
     };
     pub use crate::traits::{
         AsGRangesRef, GeneralRangeRecordIterator, GenericRange, GenericRangeOperations,
         GenomicRangeRecordUnwrappable, GenomicRangesTsvSerialize, IndexedDataContainer,
         IntoDatumType, IntoIterableRangesContainer, IterableRangeContainer, JoinDataOperations,
-        LeftOverlaps,
+        LeftOverlaps, OverlapQuery, Selection, TsvFormat,
     };
 
     pub use crate::seqlens;
@@ -1,18 +1,43 @@
-use std::path::PathBuf;
+use std::{num::NonZeroUsize, path::PathBuf};
 
 use clap::{Parser, Subcommand};
 use granges::{
     commands::{
-        granges_adjust, granges_filter, granges_flank, granges_map, granges_windows, FilterChroms,
-        FeatureDensity, Merge, ProcessingMode,
+        granges_adjust, granges_filter, granges_flank, granges_map, granges_windows,
+        set_output_buffering, Bin, Bootstrap, Cat, Center, Compare, Complement, ConvertAnnotation,
+        Eval, Expand, FeatureDensity, FilterChroms, FilterOutput, Fisher, GeneFeatures, Hist,
+        JoinByName, Load, MapToGenome, MapToTranscript, Math, Merge, OutputBuffering, PairToBed,
+        PairToPair, Pairwise, ProcessingMode, Random, Save, Shift, Shuffle, Significance, Slide,
+        Split, Stats, SummaryPerChrom, Tag, Track, Validate, Window, WindowName,
+        DEFAULT_OUTPUT_BUFFER_SIZE,
     },
-    data::operations::FloatOperation,
+    data::operations::{Aggregate, EmptyPolicy, FloatOperation},
+    io::{
+        parsers::{
+            set_input_format, set_restriction,
+            utils::{errors_skipped, set_skip_errors},
+            BoundsPolicy, InputFormat, Restriction,
+        },
+        tsv::{set_na_value, BEDTOOLS_PRECISION},
+    },
+    join::{JoinFilter, OverlapMode},
+    parallel::set_num_threads,
     prelude::GRangesError,
+    random::set_seed,
+    ranges::operations::InvertedRangePolicy,
+    reporting::{set_progress, ReportFormat},
+    stats::Normalization,
     Position, PositionOffset,
 };
+use tracing::{info_span, warn};
+use tracing_subscriber::{
+    filter::LevelFilter,
+    fmt::{format::FmtSpan, time::Uptime},
+    EnvFilter,
+};
 
 #[cfg(feature = "dev-commands")]
-use granges::commands::granges_random_bed;
+use granges::{commands::granges_random_bed, random::seeded_rng};
 
 const INFO: &str = r#"
 granges: genomic range operations built off of the GRanges library
@@ -22,9 +47,53 @@ Subcommands:
   
   adjust:             Adjust each genomic range, e.g. to add a kilobase to each end.
 
+  bin:                Aggregate the scores of several BED5 files into fixed-width
+                      genome-wide bins, as a bins × files matrix (TSV, Arrow IPC,
+                      or Parquet).
+
+  bootstrap:          Estimate confidence intervals of overlap statistics (e.g. the
+                      Jaccard index) between the left and right ranges with a
+                      block bootstrap, resampling large blocks of the genome.
+
+  cat:                Concatenate several BED-like files, padding their columns to the
+                      same number, optionally tagging records with their source
+                      file, sorting them, and removing exact duplicates.
+
+  center:             Reduce ranges to their midpoints, or the summits of narrowPeak
+                      peaks, optionally expanded by some basepairs on each side
+                      (e.g. fixed-width windows for motif analysis).
+
+  compare:            Compare two BED-like files, like a range-aware 'diff': write the
+                      records only in either file, and those with the same range
+                      but different data columns, and summarize their counts.
+
+  complement:         Write the regions of the genome not covered by any range, or
+                      with --restrict, those within some regions. With --stranded,
+                      the complement of each strand is found separately. With
+                      --gaps, assembly gaps are left out.
+
+  convert-annotation: Convert a GFF3, GTF, or BED12 gene annotation to BED12 (one
+                      line per transcript), GFF3 (genes, transcripts, exons, and
+                      CDS), or SAF (for featureCounts).
+
+  eval:               Filter rows or add columns with expressions, e.g.
+                      --expr "width() > 1000 && score > 5" or
+                      --add-col "log2(score + 1)".
+
+  expand:             Expand ranges into per-basepair lines (sequence name, position,
+                      and the range's other columns), or with --depth, write the
+                      depth at every position of the genome (like 'bedtools
+                      genomecov -d'), streaming.
+
   filter:             Filter the left ranges based on whether they have at least one
                       overlap with a right range. This is equivalent to a filtering
-                      "semi-join" in SQL terminology. 
+                      "semi-join" in SQL terminology. With --invert, keep those with
+                      no overlaps instead (an "anti-join"), or with --count, keep
+                      all of them with their numbers of overlaps. With
+                      --assume-sorted (or --detect-sorted, if they are), sorted
+                      inputs are joined in a single streaming pass. With
+                      --exclude, right ranges in excluded regions (e.g. a
+                      blacklist) are ignored.
 
   feature-density     Calculate the density of features per window, e.g. how many 
                       basepairs are "exon", "CDS", etc. With --exclusive, this will assign
@@ -33,17 +102,131 @@ Subcommands:
                       number of basepairs will be added to a new composite "CDS,exon" 
                       feature set.
 
+  fisher:             Test for enrichment of overlap between the left and right ranges,
+                      with Fisher's exact test on covered basepairs, and optionally,
+                      a permutation test (--permutations). With --gaps, assembly
+                      gaps (from a gap track, or the Ns of a FASTA file) are
+                      excluded from both tests.
+
   map:                Compute the left grouped overlaps between the left genomic ranges
                       and right genomic ranges, and apply one or more operations to the 
-                      score column of the right BED5 file.
+                      score column of the right BED5 file. With --assume-sorted
+                      (or --detect-sorted, if they are), sorted inputs are joined
+                      in a single streaming pass. With --chunk-size,
+                      the left ranges are processed in bounded batches. With --lua,
+                      a custom aggregation function is applied too. With
+                      --normalize, counts are normalized as CPM, RPKM, or TPM.
+                      With --exclude, right ranges in excluded regions (e.g. a
+                      blacklist) are ignored.
+
+  gene-features:      Extract transcript features from a GFF3, GTF, or BED12 gene
+                      annotation (exons, introns, CDS, 5' and 3' UTRs, first
+                      exons, and TSSs), strand-aware, each to its own BED file.
+
+  hist:               Calculate a histogram of range widths, or of a score column,
+                      as a TSV or a quick terminal plot (--plot).
+
+  join-by-name:       Join two BED-like files by the names of their ranges, rather
+                      than by their coordinates (e.g. to attach transcript
+                      metadata by ID), with a hash join.
+
+  map-to-genome:      Map ranges in transcript coordinates (e.g. CLIP peaks) to the
+                      genome, as spliced BED12 lines, using the exons of a GFF3,
+                      GTF, or BED12 gene annotation.
+
+  map-to-transcript:  Map genomic ranges to the coordinates of each transcript whose
+                      exons they overlap.
+
+  math:               Do arithmetic between two score tracks (bedGraphs): add, subtract,
+                      log2 ratio, min, max, etc., re-segmented at the breakpoints
+                      of both tracks.
 
   merge:              Merge ranges that are within a minimum distance of each other.
+
+  pair-to-bed:        Report the pairs of a BEDPE file (e.g. Hi-C contacts) by how
+                      their ends overlap the ranges of a BED-like file, like
+                      'bedtools pairtobed' (alias: pairtobed).
+
+  pair-to-pair:       Report the pairs of a BEDPE file by how their ends overlap the
+                      ends of the pairs of another BEDPE file, like 'bedtools
+                      pairtopair' (alias: pairtopair).
+
+  pairwise:           Calculate the Jaccard index (or the intersecting basepairs, or
+                      the number of overlapping ranges) between all pairs of several
+                      BED-like files, as a symmetric matrix, in parallel.
+
+  random:             Generate random ranges on a genome (e.g. as a null model), with
+                      fixed, uniform, or template-matched widths, avoiding excluded
+                      regions (and assembly gaps, with --gaps), and optionally with
+                      random scores and strands.
+
+  save:               Save a BED-like file as a binary .granges file, which can be
+                      loaded back quickly (see 'load').
+
+  load:               Load a binary .granges file, writing it as a BED-like file.
+
+  shift:              Shift ranges by some number of basepairs (or a fraction of their
+                      widths) without changing their widths, optionally by
+                      different amounts per strand, like 'bedtools shift'.
+
+  shuffle:            Shuffle ranges to random positions on a genome, keeping their
+                      widths (e.g. as a null set), optionally matching the GC
+                      content (--fasta) and mappability (--mappability) of each
+                      original range within a tolerance.
+
+  significance:       Test the significance of the overlap between the left and right
+                      ranges with a Monte Carlo test, shuffling the left ranges in
+                      parallel (avoiding excluded regions and gaps), and report
+                      an empirical p-value and z-score.
+
+  slide:              Compute a rolling statistic of a score column in sliding windows
+                      across the genome (e.g. smoothed coverage), as a bedGraph, in
+                      one streaming pass over sorted input.
+
+  split:              Split a BED-like file into some number of files, balanced by
+                      their total basepairs (or numbers of records), optionally
+                      keeping each chromosome in one file, e.g. for cluster jobs.
+
+  stats:              Report summary statistics of a BED-like file: the number of
+                      ranges, covered basepairs, range widths, and overlaps. With
+                      --by-arm, sequences are split into chromosome arms.
+
+  summary-per-chrom:  Summarize a BED-like file by sequence in a single pass: the
+                      number of ranges, covered basepairs, and mean score of each.
+                      With --by-arm, summarize by chromosome arm (from a UCSC
+                      cytoBand file) instead.
+
+  tag:                Tag each range with its nearest gene in a GFF3, GTF, or BED12
+                      annotation, the distance to its TSS, and its genomic context
+                      (promoter, exon, intron, or intergenic). With
+                      --closest-features, tag it with its nearest upstream and
+                      downstream genes and their signed distances instead, or
+                      with --k-nearest, its k nearest genes (optionally within
+                      --max-distance), one row per pair or --collapse'd.
+
+  track:              Write a BED-like file as a browser track, with a track line,
+                      or as a UCSC track hub directory (--hub).
+
+  validate:           Check a BED-like file against a genome file for malformed
+                      lines, invalid or out of bounds ranges, unknown sequences,
+                      and unsorted records. With --fix, write a repaired copy.
+
+  window:             Join each left range with the right ranges within a window of
+                      it (e.g. -w 5000 for features within 5kb), like 'bedtools
+                      window', optionally upstream and downstream by strand, and
+                      as BEDPE or UCSC interact pairs (--format).
           
   windows:            Create a set of genomic windows of the specified width (in 
                       basepairs), stepping the specified step size (the width, by 
-                      default).
+                      default), across the genome or within each range of a BED
+                      file (--per-range), optionally named (--name).
           
 
+Ranges written to an --output file with an Arrow IPC (.arrow, .ipc, .feather)
+or Parquet (.parquet, .pq) extension are written in that format, with the
+columns chrom, start, end, and then any data columns. This requires granges to
+be compiled with the 'arrow' or 'parquet' feature.
+
 NOTE: granges is under active development. It is not currently meant to be
 a full replacement for other genomic ranges software, such as bedtools. The
 command line functionality currently used for testing and benchmarking.
@@ -57,9 +240,74 @@ https://github.com/vsbuffalo/granges/issues
 #[clap(name = "granges")]
 #[clap(about = INFO)]
 struct Cli {
-    #[arg(short, long, action = clap::ArgAction::Count)]
+    /// Log debugging details to standard error (repeat, e.g. --debug --debug, for
+    /// more). The RUST_LOG environment variable, if set, overrides the log level.
+    #[arg(long, global = true, action = clap::ArgAction::Count)]
     debug: u8,
 
+    /// Log information, including the time spent in each processing phase
+    #[arg(long, global = true)]
+    verbose: bool,
+
+    /// Only log errors
+    #[arg(long, global = true, conflicts_with_all = ["verbose", "debug"])]
+    quiet: bool,
+
+    /// The number of threads to use for parallel processing, including compressing
+    /// gzip (BGZF) output and decompressing BGZF input (by default, one per CPU)
+    #[arg(long, global = true)]
+    threads: Option<usize>,
+
+    /// Print a report of the run (records read, written, skipped, etc.) to standard
+    /// error, as human-readable text or JSON
+    #[arg(long, global = true, value_enum)]
+    report: Option<ReportFormat>,
+
+    /// Show the progress of reading large inputs on standard error
+    #[arg(long, global = true)]
+    progress: bool,
+
+    /// The format of inputs, overriding format detection (for commands that detect
+    /// their input's format, e.g. adjust, filter, flank, and merge)
+    #[arg(long, global = true, value_enum)]
+    input_format: Option<InputFormat>,
+
+    /// Only read ranges on these sequences (comma-separated, e.g. 'chr1,chr2'), and
+    /// only these sequences of genome files
+    #[arg(long, global = true, value_delimiter = ',')]
+    chroms: Vec<String>,
+
+    /// Only read ranges overlapping the regions of this BED file
+    #[arg(long, global = true)]
+    regions: Option<PathBuf>,
+
+    /// Skip up to this many malformed input lines (with a warning for each), rather
+    /// than raising an error on the first one
+    #[arg(long, global = true, value_name = "N")]
+    skip_errors: Option<usize>,
+
+    /// Raise an error on the first malformed input line (the default)
+    #[arg(long, global = true, conflicts_with = "skip_errors")]
+    strict: bool,
+
+    /// The string for missing values in output, which is also read as missing in
+    /// input (as is '.'), e.g. 'NA', or '' for empty fields
+    #[arg(long, global = true)]
+    na_value: Option<String>,
+
+    /// The random seed, for reproducible output from commands that use randomness
+    /// (e.g. random and fisher --permutations); by default, a random seed
+    #[arg(long, global = true)]
+    seed: Option<u64>,
+
+    /// The size (in bytes) of the batches TSV output is written in
+    #[arg(long, global = true, default_value_t = DEFAULT_OUTPUT_BUFFER_SIZE)]
+    output_buffer_size: usize,
+
+    /// Write TSV output on a background thread, while processing continues
+    #[arg(long, global = true)]
+    async_output: bool,
+
     #[command(subcommand)]
     command: Option<Commands>,
 }
@@ -78,8 +326,30 @@ enum Commands {
         bedfile: PathBuf,
 
         /// Number of basepairs to expand the range start and end positions by
-        #[arg(short, long)]
-        both: PositionOffset,
+        /// (negative values shrink the ranges)
+        #[arg(
+            short,
+            long,
+            allow_negative_numbers = true,
+            required_unless_present_any = ["left", "right"],
+            conflicts_with_all = ["left", "right"]
+        )]
+        both: Option<PositionOffset>,
+
+        /// Number of basepairs to move the range start positions leftwards by
+        /// (negative values shrink the ranges)
+        #[arg(short, long, allow_negative_numbers = true)]
+        left: Option<PositionOffset>,
+
+        /// Number of basepairs to move the range end positions rightwards by
+        /// (negative values shrink the ranges)
+        #[arg(short, long, allow_negative_numbers = true)]
+        right: Option<PositionOffset>,
+
+        /// What to do with ranges that shrinking would invert, i.e. whose start would
+        /// be at or past their end. `bedtools slop` keeps these unadjusted ('original').
+        #[arg(long, value_enum, default_value_t)]
+        inverted: InvertedRangePolicy,
 
         /// An optional output file (standard output will be used if not specified)
         #[arg(short, long)]
@@ -114,6 +384,44 @@ enum Commands {
         /// By default, ranges with sequence names not in the genome file will raise an error.
         #[arg(short, long)]
         skip_missing: bool,
+
+        /// How to handle ranges that fall outside the genome: on sequences not in the
+        /// genome file, or extending past the end of their sequence
+        #[arg(long, value_enum, default_value_t)]
+        bounds: BoundsPolicy,
+
+        /// How a left range must relate to an overlapping right range to be kept:
+        /// any overlap, within it, containing it, starting or ending at the same
+        /// position, or exactly equal to it
+        #[arg(long, value_enum, default_value_t)]
+        mode: OverlapMode,
+
+        /// Keep only the left ranges with no overlaps with the right ranges instead
+        /// (an "anti-join", like 'bedtools intersect -v')
+        #[arg(long)]
+        invert: bool,
+
+        /// Write every left range, with the number of right ranges it overlaps
+        /// appended (like 'bedtools intersect -c')
+        #[arg(short, long, conflicts_with = "invert")]
+        count: bool,
+
+        /// A BED file of regions to exclude (e.g. the ENCODE blacklist): right ranges
+        /// that overlap them are ignored
+        #[arg(long)]
+        exclude: Option<PathBuf>,
+
+        /// Assume that both inputs are sorted (by sequence, in the order of the genome
+        /// file, and then by start position), and use a streaming join, in
+        /// which neither input is loaded into memory. Unsorted input raises an error.
+        #[arg(long = "assume-sorted", alias = "sorted")]
+        sorted: bool,
+
+        /// Check whether both inputs are sorted first, with a quick streaming pass
+        /// over each, and use a streaming join if so. Otherwise, the inputs are
+        /// joined in memory, which sorts them.
+        #[arg(long, conflicts_with = "sorted")]
+        detect_sorted: bool,
     },
     /// Compute the flanking regions for each range.
     Flank {
@@ -146,16 +454,27 @@ enum Commands {
         #[arg(long)]
         skip_missing: bool,
 
+        /// How to handle ranges that fall outside the genome: on sequences not in the
+        /// genome file, or extending past the end of their sequence
+        #[arg(long, value_enum, default_value_t)]
+        bounds: BoundsPolicy,
+
         /// Processing mode
         #[arg(long)]
         in_mem: bool,
+
+        /// Write each flank with the other columns (e.g. name, score, and strand) of
+        /// its range
+        #[arg(long, conflicts_with = "in_mem")]
+        carry: bool,
     },
     FeatureDensity(FeatureDensity),
     /// Do a "left grouped join", on the specified left and right genomic ranges,
     /// and apply one or more functions to the BED5 scores for all right genomic
     /// ranges.
     ///
-    /// This is analogous to 'bedtools map'.
+    /// This is analogous to 'bedtools map', and like it, writes results with five
+    /// significant digits by default (e.g. 0.33333 or 1.2346e+05; see --precision).
     Map {
         /// A TSV genome file of chromosome names and their lengths
         #[arg(short, long, required = true)]
@@ -173,6 +492,38 @@ enum Commands {
         #[clap(short, long, value_parser = clap::value_parser!(FloatOperation), use_value_delimiter = true, value_delimiter = ',')]
         func: Vec<FloatOperation>,
 
+        /// A Lua script defining an `aggregate(values)` function, which is called with
+        /// the overlapping values of each left range, and whose result is added as a
+        /// final column (requires the 'lua' feature)
+        #[arg(long)]
+        lua: Option<PathBuf>,
+
+        /// A BED file of regions to exclude (e.g. the ENCODE blacklist): right ranges
+        /// that overlap them are ignored
+        #[arg(long)]
+        exclude: Option<PathBuf>,
+
+        /// Normalize the counts of right ranges over each left range, by the total
+        /// number of right ranges (the library size) and the left range's width.
+        /// Requires 'count' to be the only operation.
+        #[arg(long, value_enum)]
+        normalize: Option<Normalization>,
+
+        /// What to report for left ranges with no overlapping right ranges: each
+        /// operation's own result (e.g. 0 for sum), a missing value ('.', like
+        /// bedtools), 'NA', zero, or to drop the range. Counts are always zero.
+        #[arg(long, value_enum, default_value_t)]
+        empty: EmptyPolicy,
+
+        /// The name of the right BED5 file's column to apply the operations to
+        #[arg(short, long, default_value = "score")]
+        column: String,
+
+        /// The number of significant digits of results, formatted like C's %g (like
+        /// bedtools' -prec)
+        #[arg(long, default_value_t = BEDTOOLS_PRECISION)]
+        precision: usize,
+
         /// An optional output file (standard output will be used if not specified)
         #[arg(short, long)]
         output: Option<PathBuf>,
@@ -181,8 +532,66 @@ enum Commands {
         /// By default, ranges with sequence names not in the genome file will raise an error.
         #[arg(short, long)]
         skip_missing: bool,
+
+        /// How to handle ranges that fall outside the genome: on sequences not in the
+        /// genome file, or extending past the end of their sequence
+        #[arg(long, value_enum, default_value_t)]
+        bounds: BoundsPolicy,
+
+        /// Assume that both inputs are sorted (by sequence, in the order of the genome
+        /// file, and then by start position), and use a streaming join, in
+        /// which neither input is loaded into memory. Unsorted input raises an error.
+        #[arg(long = "assume-sorted", alias = "sorted")]
+        sorted: bool,
+
+        /// Check whether both inputs are sorted first, with a quick streaming pass
+        /// over each, and use a streaming join if so. Otherwise, the inputs are
+        /// joined in memory, which sorts them.
+        #[arg(long, conflicts_with = "sorted")]
+        detect_sorted: bool,
+
+        /// Process the left ranges in batches of at most this many ranges (each on a
+        /// single sequence), writing each batch's results before reading the next. Only
+        /// the right ranges are loaded into memory. Output is in the left input's order.
+        #[arg(long, conflicts_with_all = ["sorted", "detect_sorted"])]
+        chunk_size: Option<NonZeroUsize>,
     },
+    Bin(Bin),
+    Bootstrap(Bootstrap),
+    Cat(Cat),
+    Center(Center),
+    Compare(Compare),
+    Complement(Complement),
+    ConvertAnnotation(ConvertAnnotation),
+    Eval(Eval),
+    Expand(Expand),
+    GeneFeatures(GeneFeatures),
+    JoinByName(JoinByName),
+    MapToGenome(MapToGenome),
+    MapToTranscript(MapToTranscript),
+    Math(Math),
     Merge(Merge),
+    #[command(visible_alias = "pairtobed")]
+    PairToBed(PairToBed),
+    #[command(visible_alias = "pairtopair")]
+    PairToPair(PairToPair),
+    Pairwise(Pairwise),
+    Random(Random),
+    Save(Save),
+    Shift(Shift),
+    Shuffle(Shuffle),
+    Significance(Significance),
+    Load(Load),
+    Stats(Stats),
+    SummaryPerChrom(SummaryPerChrom),
+    Hist(Hist),
+    Fisher(Fisher),
+    Slide(Slide),
+    Split(Split),
+    Tag(Tag),
+    Track(Track),
+    Validate(Validate),
+    Window(Window),
     /// Create a set of genomic windows ranges using the specified width
     /// and step size, and output to BED3.
     ///
@@ -191,6 +600,11 @@ enum Commands {
     /// off.
     ///
     /// This is analogous to 'bedtools makewindows'.
+    ///
+    /// As with other commands, if the output file has an Arrow IPC extension
+    /// (.arrow, .ipc, or .feather) or a Parquet extension (.parquet or .pq), the
+    /// windows are written as an Arrow IPC or Parquet file. This requires granges
+    /// to be compiled with the 'arrow' or 'parquet' feature.
     Windows {
         /// A TSV genome file of chromosome names and their lengths
         #[arg(short, long, required = true)]
@@ -208,6 +622,22 @@ enum Commands {
         #[arg(short, long)]
         chop: bool,
 
+        /// Make the windows of each range of this BED-like file (e.g. genes or
+        /// peaks), rather than of the whole genome
+        #[arg(long, value_name = "BEDFILE")]
+        per_range: Option<PathBuf>,
+
+        /// Name each window in a fourth column: by its number ('window_1', ...), its
+        /// coordinates ('chr1:0-1000'), or the name of its range (or its sequence) and
+        /// its number within it ('geneA_1', ...)
+        #[arg(long, alias = "name-style", value_enum)]
+        name: Option<WindowName>,
+
+        /// Write each window with the other columns (e.g. name, score, and strand) of
+        /// its range, after its name (if any)
+        #[arg(long, requires = "per_range")]
+        carry: bool,
+
         /// An optional output file (standard output will be used if not specified)
         #[arg(short, long)]
         output: Option<PathBuf>,
@@ -237,23 +667,128 @@ enum Commands {
     },
 }
 
+/// The most verbose log level, from the --quiet, --verbose, and --debug flags.
+fn log_level(cli: &Cli) -> LevelFilter {
+    match cli.debug {
+        _ if cli.quiet => LevelFilter::ERROR,
+        0 if cli.verbose => LevelFilter::INFO,
+        0 => LevelFilter::WARN,
+        1 => LevelFilter::DEBUG,
+        _ => LevelFilter::TRACE,
+    }
+}
+
+/// Log to standard error, at the level set by the command line flags, unless
+/// overridden by RUST_LOG. Each message is prefixed with the time since the
+/// program started, and the time spent in each processing phase (a span) is
+/// logged when it closes.
+fn init_logging(cli: &Cli) {
+    let filter = EnvFilter::builder()
+        .with_default_directive(log_level(cli).into())
+        .from_env_lossy();
+    tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(std::io::stderr)
+        .with_timer(Uptime::default())
+        .with_target(false)
+        .with_span_events(FmtSpan::CLOSE)
+        .init();
+}
+
+/// Joins use the streaming algorithm only if the inputs are declared sorted, or
+/// are detected to be sorted.
+fn processing_mode(sorted: bool, detect_sorted: bool) -> ProcessingMode {
+    if sorted {
+        ProcessingMode::Streaming
+    } else if detect_sorted {
+        ProcessingMode::Auto
+    } else {
+        ProcessingMode::InMemory
+    }
+}
+
 fn run() -> Result<(), GRangesError> {
     let cli = Cli::parse();
+    if let Some(threads) = cli.threads {
+        set_num_threads(threads)?;
+    }
+    if let Some(format) = cli.input_format {
+        set_input_format(format)?;
+    }
+    if !cli.chroms.is_empty() || cli.regions.is_some() {
+        let mut restriction = Restriction::new();
+        if !cli.chroms.is_empty() {
+            restriction = restriction.with_seqnames(cli.chroms.iter());
+        }
+        if let Some(regions) = &cli.regions {
+            restriction = restriction.with_regions_file(regions)?;
+        }
+        set_restriction(restriction)?;
+    }
+    if let Some(limit) = cli.skip_errors {
+        set_skip_errors(limit);
+    }
+    if let Some(na_value) = &cli.na_value {
+        set_na_value(na_value.clone())?;
+    }
+    if let Some(seed) = cli.seed {
+        set_seed(seed)?;
+    }
+    set_output_buffering(OutputBuffering {
+        size: cli.output_buffer_size,
+        background: cli.async_output,
+    })?;
+    set_progress(cli.progress);
+    init_logging(&cli);
+    let total = info_span!("total").entered();
     let result = match &cli.command {
         Some(Commands::Adjust {
             bedfile,
             genome,
             both,
+            left,
+            right,
+            inverted,
             output,
             sort,
-        }) => granges_adjust(bedfile, genome, *both, output.as_ref(), *sort),
+        }) => granges_adjust(
+            bedfile,
+            genome,
+            left.or(*both).unwrap_or(0),
+            right.or(*both).unwrap_or(0),
+            *inverted,
+            output.as_ref(),
+            *sort,
+        ),
         Some(Commands::Filter {
             genome,
             left,
             right,
             output,
             skip_missing,
-        }) => granges_filter(genome, left, right, output.as_ref(), *skip_missing),
+            bounds,
+            mode,
+            invert,
+            count,
+            exclude,
+            sorted,
+            detect_sorted,
+        }) => granges_filter(
+            genome,
+            left,
+            right,
+            output.as_ref(),
+            *skip_missing,
+            *bounds,
+            &JoinFilter::new().mode(*mode),
+            match (invert, count) {
+                (true, _) => FilterOutput::NonOverlapping,
+                (_, true) => FilterOutput::Count,
+                _ => FilterOutput::Overlapping,
+            },
+            processing_mode(*sorted, *detect_sorted),
+            exclude.as_ref(),
+        ),
         Some(Commands::FilterChroms(filter_chroms)) => filter_chroms.run(),
         Some(Commands::Flank {
             genome,
@@ -263,7 +798,9 @@ fn run() -> Result<(), GRangesError> {
             right,
             output,
             skip_missing,
+            bounds,
             in_mem,
+            carry,
         }) => {
             if both.is_some() && (left.is_some() || right.is_some()) {
                 let error = clap::Error::raw(
@@ -293,7 +830,9 @@ fn run() -> Result<(), GRangesError> {
                 right,
                 output.as_ref(),
                 *skip_missing,
+                *bounds,
                 mode,
+                *carry,
             )
         }
         Some(Commands::Map {
@@ -301,31 +840,98 @@ fn run() -> Result<(), GRangesError> {
             left,
             right,
             func,
+            lua,
+            exclude,
+            normalize,
+            empty,
+            column,
+            precision,
             output,
             skip_missing,
+            bounds,
+            sorted,
+            detect_sorted,
+            chunk_size,
         }) => {
-            if func.is_empty() {
+            if func.is_empty() && lua.is_none() {
                 return Err(GRangesError::NoOperationSpecified);
             }
+            let script = lua.as_ref().map(load_lua_script).transpose()?;
             granges_map(
                 genome,
                 left,
                 right,
                 func.to_vec(),
+                script.as_deref(),
+                *normalize,
+                *empty,
+                column,
                 output.as_ref(),
                 *skip_missing,
+                *bounds,
+                match chunk_size {
+                    Some(chunk_size) => ProcessingMode::Chunked(chunk_size.get()),
+                    None => processing_mode(*sorted, *detect_sorted),
+                },
+                *precision,
+                exclude.as_ref(),
             )
         }
         // NOTE: this is the new API, so clean!
         Some(Commands::FeatureDensity(density)) => density.run(),
+        Some(Commands::Bin(bin)) => bin.run(),
+        Some(Commands::Bootstrap(bootstrap)) => bootstrap.run(),
+        Some(Commands::Cat(cat)) => cat.run(),
+        Some(Commands::Center(center)) => center.run(),
+        Some(Commands::Compare(compare)) => compare.run(),
+        Some(Commands::Complement(complement)) => complement.run(),
+        Some(Commands::ConvertAnnotation(convert)) => convert.run(),
+        Some(Commands::Eval(eval)) => eval.run(),
+        Some(Commands::Expand(expand)) => expand.run(),
+        Some(Commands::GeneFeatures(features)) => features.run(),
+        Some(Commands::JoinByName(join)) => join.run(),
+        Some(Commands::MapToGenome(map)) => map.run(),
+        Some(Commands::MapToTranscript(map)) => map.run(),
+        Some(Commands::Math(math)) => math.run(),
         Some(Commands::Merge(merge)) => merge.run(),
+        Some(Commands::PairToBed(pair_to_bed)) => pair_to_bed.run(),
+        Some(Commands::PairToPair(pair_to_pair)) => pair_to_pair.run(),
+        Some(Commands::Pairwise(pairwise)) => pairwise.run(),
+        Some(Commands::Random(random)) => random.run(),
+        Some(Commands::Save(save)) => save.run(),
+        Some(Commands::Shift(shift)) => shift.run(),
+        Some(Commands::Shuffle(shuffle)) => shuffle.run(),
+        Some(Commands::Significance(significance)) => significance.run(),
+        Some(Commands::Load(load)) => load.run(),
+        Some(Commands::Stats(stats)) => stats.run(),
+        Some(Commands::SummaryPerChrom(summary)) => summary.run(),
+        Some(Commands::Hist(hist)) => hist.run(),
+        Some(Commands::Fisher(fisher)) => fisher.run(),
+        Some(Commands::Slide(slide)) => slide.run(),
+        Some(Commands::Split(split)) => split.run(),
+        Some(Commands::Tag(tag)) => tag.run(),
+        Some(Commands::Track(track)) => track.run(),
+        Some(Commands::Validate(validate)) => validate.run(),
+        Some(Commands::Window(window)) => window.run(),
         Some(Commands::Windows {
             genome,
             width,
             step,
             chop,
+            per_range,
+            name,
+            carry,
             output,
-        }) => granges_windows(genome, *width, *step, *chop, output.as_ref()),
+        }) => granges_windows(
+            genome,
+            *width,
+            *step,
+            *chop,
+            per_range.as_ref(),
+            *name,
+            *carry,
+            output.as_ref(),
+        ),
         #[cfg(feature = "dev-commands")]
         Some(Commands::RandomBed {
             genome,
@@ -333,17 +939,49 @@ fn run() -> Result<(), GRangesError> {
             output,
             sort,
             scores,
-        }) => granges_random_bed(genome, *num, output.as_ref(), *sort, *scores),
+        }) => granges_random_bed(
+            genome,
+            *num,
+            output.as_ref(),
+            *sort,
+            *scores,
+            &mut seeded_rng(),
+        ),
         None => {
             println!("{}\n", INFO);
             std::process::exit(1);
         }
     };
-    let _output = result?;
-    // TODO do something with reporting, etc.
+    let output = result?;
+    drop(total);
+    let num_skipped = errors_skipped();
+    if num_skipped > 0 {
+        warn!("skipped {} malformed lines", num_skipped);
+    }
+    if let (Some(format), Some(report)) = (&cli.report, output.report()) {
+        let mut report = report.clone();
+        if num_skipped > 0 {
+            report.add_issue(format!("{} malformed lines were skipped", num_skipped));
+        }
+        report.write(std::io::stderr(), format)?;
+    }
     Ok(())
 }
 
+/// Load a Lua script's aggregation function for `granges map --lua`.
+#[cfg(feature = "lua")]
+fn load_lua_script(path: &PathBuf) -> Result<Box<dyn Aggregate>, GRangesError> {
+    Ok(Box::new(granges::data::lua::LuaAggregation::from_file(path)?))
+}
+
+#[cfg(not(feature = "lua"))]
+fn load_lua_script(path: &PathBuf) -> Result<Box<dyn Aggregate>, GRangesError> {
+    Err(GRangesError::RequiresFeature(
+        format!("--lua {}", path.display()),
+        "lua".to_string(),
+    ))
+}
+
 fn main() {
     match run() {
         Ok(_) => {}
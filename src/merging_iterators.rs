@@ -2,7 +2,10 @@
 //!
 // TODO: these probably could use better names?
 // TODO: we should support weighting by overlaps.
-use std::cmp::max;
+use std::{
+    cmp::{max, min},
+    collections::VecDeque,
+};
 
 use crate::{
     error::GRangesError,
@@ -11,6 +14,28 @@ use crate::{
     PositionOffset,
 };
 
+/// Whether the range `next` is close enough to the range `last` to be merged with
+/// it: within `minimum_distance`, or, if `fraction` is set, within `fraction` times
+/// the width of the narrower of the two ranges (whichever is larger). Scaling the
+/// distance by width is useful for merging ranges of varying width, such as peaks.
+pub fn within_merge_distance<R, S>(
+    last: &R,
+    next: &S,
+    minimum_distance: PositionOffset,
+    fraction: Option<f64>,
+) -> bool
+where
+    R: GenericRange,
+    S: GenericRange,
+{
+    let mut distance = minimum_distance;
+    if let Some(fraction) = fraction {
+        let width = min(last.width(), next.width()) as f64;
+        distance = max(distance, (fraction * width).floor() as PositionOffset);
+    }
+    last.distance_or_overlap(next) <= distance
+}
+
 // Create a new [`MergingEmptyIterator`], which work over data-less
 // "empty" ranges ([`GenomicRangeRecordEmpty`] and merge them based on their
 // distance or degree of overlap.
@@ -21,6 +46,7 @@ where
     last_range: Option<GenomicRangeRecordEmpty>,
     inner: <I as IntoIterator>::IntoIter,
     minimum_distance: PositionOffset,
+    fraction: Option<f64>,
 }
 
 impl<I> MergingEmptyIterator<I>
@@ -32,8 +58,16 @@ where
             last_range: None,
             inner: inner.into_iter(),
             minimum_distance,
+            fraction: None,
         }
     }
+
+    /// If set, also merge ranges that are within `fraction` times the width of the
+    /// narrower of the two ranges (see [`within_merge_distance()`]).
+    pub fn fraction(mut self, fraction: Option<f64>) -> Self {
+        self.fraction = fraction;
+        self
+    }
 }
 
 impl<I> Iterator for MergingEmptyIterator<I>
@@ -47,7 +81,12 @@ where
             if let Some(last_range) = &mut self.last_range {
                 let on_same_chrom = last_range.seqname == next_range.seqname;
                 if on_same_chrom
-                    && last_range.distance_or_overlap(&next_range) <= self.minimum_distance
+                    && within_merge_distance(
+                        last_range,
+                        &next_range,
+                        self.minimum_distance,
+                        self.fraction,
+                    )
                 {
                     last_range.end = max(last_range.end, next_range.end);
                 } else {
@@ -78,6 +117,7 @@ where
     last_range: Option<GenomicRangeRecordEmpty>,
     inner: <I as IntoIterator>::IntoIter,
     minimum_distance: PositionOffset,
+    fraction: Option<f64>,
 }
 
 impl<I> MergingEmptyResultIterator<I>
@@ -89,8 +129,16 @@ where
             last_range: None,
             inner: inner.into_iter(),
             minimum_distance,
+            fraction: None,
         }
     }
+
+    /// If set, also merge ranges that are within `fraction` times the width of the
+    /// narrower of the two ranges (see [`within_merge_distance()`]).
+    pub fn fraction(mut self, fraction: Option<f64>) -> Self {
+        self.fraction = fraction;
+        self
+    }
 }
 
 impl<I> Iterator for MergingEmptyResultIterator<I>
@@ -109,7 +157,12 @@ where
             if let Some(last_range) = &mut self.last_range {
                 let on_same_chrom = last_range.seqname == next_range.seqname;
                 if on_same_chrom
-                    && last_range.distance_or_overlap(&next_range) <= self.minimum_distance
+                    && within_merge_distance(
+                        last_range,
+                        &next_range,
+                        self.minimum_distance,
+                        self.fraction,
+                    )
                 {
                     last_range.end = max(last_range.end, next_range.end);
                 } else {
@@ -144,6 +197,7 @@ where
     last_range: Option<GenomicRangeRecord<U>>,
     inner: <I as IntoIterator>::IntoIter,
     minimum_distance: PositionOffset,
+    fraction: Option<f64>,
     func: F,
     accumulated_data: Vec<U>,
 }
@@ -160,10 +214,18 @@ where
             last_range: None,
             inner: inner.into_iter(),
             minimum_distance,
+            fraction: None,
             func,
             accumulated_data: Vec::new(),
         }
     }
+
+    /// If set, also merge ranges that are within `fraction` times the width of the
+    /// narrower of the two ranges (see [`within_merge_distance()`]).
+    pub fn fraction(mut self, fraction: Option<f64>) -> Self {
+        self.fraction = fraction;
+        self
+    }
 }
 
 impl<I, U, V, F> Iterator for MergingResultIterator<I, U, V, F>
@@ -184,7 +246,12 @@ where
                     if let Some(ref mut last_range) = self.last_range {
                         let on_same_chrom = last_range.seqname == next_range.seqname;
                         if on_same_chrom
-                            && last_range.distance_or_overlap(&next_range) <= self.minimum_distance
+                            && within_merge_distance(
+                                last_range,
+                                &next_range,
+                                self.minimum_distance,
+                                self.fraction,
+                            )
                         {
                             // this range overlaps the last range, so we keep accumulating data
                             last_range.end = max(last_range.end, next_range.end);
@@ -254,6 +321,7 @@ where
     last_range: Option<GenomicRangeRecord<U>>,
     inner: <I as IntoIterator>::IntoIter,
     minimum_distance: PositionOffset,
+    fraction: Option<f64>,
     func: F,
     group: G,
     accumulated_data: Vec<U>,
@@ -272,11 +340,19 @@ where
             last_range: None,
             inner: inner.into_iter(),
             minimum_distance,
+            fraction: None,
             func,
             group,
             accumulated_data: Vec::new(),
         }
     }
+
+    /// If set, also merge ranges that are within `fraction` times the width of the
+    /// narrower of the two ranges (see [`within_merge_distance()`]).
+    pub fn fraction(mut self, fraction: Option<f64>) -> Self {
+        self.fraction = fraction;
+        self
+    }
 }
 
 impl<I, U, V, F, G> Iterator for ConditionalMergingIterator<I, U, V, F, G>
@@ -301,7 +377,12 @@ where
                 let on_same_chrom = last_range.seqname == next_range.seqname;
                 if on_same_chrom
                     && satifies_groupby
-                    && last_range.distance_or_overlap(&next_range) <= self.minimum_distance
+                    && within_merge_distance(
+                        last_range,
+                        &next_range,
+                        self.minimum_distance,
+                        self.fraction,
+                    )
                 {
                     // this range overlaps the last range, so we keep accumulating data
                     last_range.end = max(last_range.end, next_range.end);
@@ -367,6 +448,7 @@ where
     last_range: Option<GenomicRangeRecord<U>>,
     inner: <I as IntoIterator>::IntoIter,
     minimum_distance: PositionOffset,
+    fraction: Option<f64>,
     func: F,
     group: G,
     accumulated_data: Vec<U>,
@@ -385,11 +467,19 @@ where
             last_range: None,
             inner: inner.into_iter(),
             minimum_distance,
+            fraction: None,
             func,
             group,
             accumulated_data: Vec::new(),
         }
     }
+
+    /// If set, also merge ranges that are within `fraction` times the width of the
+    /// narrower of the two ranges (see [`within_merge_distance()`]).
+    pub fn fraction(mut self, fraction: Option<f64>) -> Self {
+        self.fraction = fraction;
+        self
+    }
 }
 
 impl<I, U, V, F, G> Iterator for ConditionalMergingResultIterator<I, U, V, F, G>
@@ -416,7 +506,12 @@ where
                         let on_same_chrom = last_range.seqname == next_range.seqname;
                         if on_same_chrom
                             && satifies_groupby
-                            && last_range.distance_or_overlap(&next_range) <= self.minimum_distance
+                            && within_merge_distance(
+                                last_range,
+                                &next_range,
+                                self.minimum_distance,
+                                self.fraction,
+                            )
                         {
                             // this range overlaps the last range, so we keep accumulating data
                             last_range.end = max(last_range.end, next_range.end);
@@ -472,12 +567,166 @@ where
     }
 }
 
+/// A merging iterator (over [`Result<GenomicRangeRecord<U>, GRangesError`] items,
+/// i.e. from a parsing iterator) that only merges ranges in the same group, given
+/// by the key function `G` (e.g. the strand). Unlike [`ConditionalMergingResultIterator`],
+/// ranges in different groups may be interleaved in the input (as the ranges on
+/// each strand are in a sorted BED file); the merged ranges are yielded in sorted
+/// order.
+pub struct GroupedMergingResultIterator<I, U, V, F, G, K>
+where
+    I: IntoIterator<Item = Result<GenomicRangeRecord<U>, GRangesError>>,
+    F: Fn(Vec<U>) -> V,
+    G: Fn(&GenomicRangeRecord<U>) -> K,
+    K: PartialEq,
+{
+    inner: <I as IntoIterator>::IntoIter,
+    minimum_distance: PositionOffset,
+    fraction: Option<f64>,
+    func: F,
+    group: G,
+    /// The ranges still being merged, one per group, with their accumulated data.
+    open: Vec<(K, GenomicRangeRecordEmpty, Vec<U>)>,
+    /// Merged ranges, sorted by start, not yet yielded.
+    merged: VecDeque<GenomicRangeRecord<V>>,
+    exhausted: bool,
+}
+
+impl<I, U, V, F, G, K> GroupedMergingResultIterator<I, U, V, F, G, K>
+where
+    I: IntoIterator<Item = Result<GenomicRangeRecord<U>, GRangesError>>,
+    F: Fn(Vec<U>) -> V,
+    G: Fn(&GenomicRangeRecord<U>) -> K,
+    K: PartialEq,
+{
+    pub fn new(inner: I, minimum_distance: PositionOffset, func: F, group: G) -> Self {
+        Self {
+            inner: inner.into_iter(),
+            minimum_distance,
+            fraction: None,
+            func,
+            group,
+            open: Vec::new(),
+            merged: VecDeque::new(),
+            exhausted: false,
+        }
+    }
+
+    /// If set, also merge ranges that are within `fraction` times the width of the
+    /// narrower of the two ranges (see [`within_merge_distance()`]).
+    pub fn fraction(mut self, fraction: Option<f64>) -> Self {
+        self.fraction = fraction;
+        self
+    }
+
+    /// Finish merging the open range at `index`, running the function on its data.
+    fn finalize(&mut self, index: usize) {
+        let (_, range, data) = self.open.remove(index);
+        let record = GenomicRangeRecord {
+            seqname: range.seqname,
+            start: range.start,
+            end: range.end,
+            data: (self.func)(data),
+        };
+        let position = self
+            .merged
+            .partition_point(|other| other.start <= record.start);
+        self.merged.insert(position, record);
+    }
+
+    /// Whether the first merged range can be yielded, i.e. no open range (which
+    /// could end up before it) starts before it on the same sequence.
+    fn first_is_ready(&self) -> bool {
+        let Some(first) = self.merged.front() else {
+            return false;
+        };
+        self.open
+            .iter()
+            .all(|(_, range, _)| range.seqname != first.seqname || first.start <= range.start)
+    }
+}
+
+impl<I, U, V, F, G, K> Iterator for GroupedMergingResultIterator<I, U, V, F, G, K>
+where
+    GenomicRangeRecord<U>: GenericRange,
+    I: IntoIterator<Item = Result<GenomicRangeRecord<U>, GRangesError>>,
+    F: Fn(Vec<U>) -> V,
+    G: Fn(&GenomicRangeRecord<U>) -> K,
+    K: PartialEq,
+{
+    type Item = Result<GenomicRangeRecord<V>, GRangesError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if self.first_is_ready() {
+                return self.merged.pop_front().map(Ok);
+            }
+            if self.exhausted {
+                return None;
+            }
+            let next_range = match self.inner.next() {
+                Some(Ok(range)) => range,
+                Some(Err(e)) => return Some(Err(e)),
+                None => {
+                    // Finalize all the open ranges.
+                    self.exhausted = true;
+                    while !self.open.is_empty() {
+                        self.finalize(0);
+                    }
+                    continue;
+                }
+            };
+
+            // On a new sequence, none of the open ranges can be merged further.
+            if self
+                .open
+                .first()
+                .is_some_and(|(_, range, _)| range.seqname != next_range.seqname)
+            {
+                while !self.open.is_empty() {
+                    self.finalize(0);
+                }
+            }
+
+            let key = (self.group)(&next_range);
+            if let Some(index) = self.open.iter().position(|(other, _, _)| *other == key) {
+                let last_range = &mut self.open[index].1;
+                if within_merge_distance(
+                    last_range,
+                    &next_range,
+                    self.minimum_distance,
+                    self.fraction,
+                ) {
+                    last_range.end = max(last_range.end, next_range.end);
+                    self.open[index].2.push(next_range.data);
+                    continue;
+                }
+                self.finalize(index);
+            }
+            let range = GenomicRangeRecordEmpty {
+                seqname: next_range.seqname,
+                start: next_range.start,
+                end: next_range.end,
+            };
+            self.open.push((key, range, vec![next_range.data]));
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use crate::{
-        io::{parsers::Bed5Addition, Bed3Iterator, Bed5Iterator},
-        merging_iterators::{ConditionalMergingResultIterator, MergingEmptyResultIterator},
+        error::GRangesError,
+        io::{
+            parsers::{bed::Strand, Bed5Addition},
+            Bed3Iterator, Bed5Iterator,
+        },
+        merging_iterators::{
+            ConditionalMergingResultIterator, GroupedMergingResultIterator,
+            MergingEmptyResultIterator,
+        },
         ranges::{GenomicRangeRecord, GenomicRangeRecordEmpty},
+        Position,
     };
 
     // TODO/TEST we need non-result iterator test.
@@ -644,4 +893,74 @@ mod tests {
             )
         );
     }
+
+    #[test]
+    fn test_merging_empty_iterators_fraction() {
+        // chr1 ranges [0, 7) and [10, 17) are three basepairs apart
+        let iter = Bed3Iterator::new("tests_data/test_case_03.bed").unwrap();
+        let merged_iter = MergingEmptyResultIterator::new(iter, 0).fraction(Some(0.4));
+        let results: Vec<_> = Result::from_iter(merged_iter).unwrap();
+        assert_eq!(
+            results[1],
+            GenomicRangeRecordEmpty::new("chr1".to_string(), 10, 17)
+        );
+
+        // with a fraction of 1.0 (of the narrower range's width, 7), they are merged
+        let iter = Bed3Iterator::new("tests_data/test_case_03.bed").unwrap();
+        let merged_iter = MergingEmptyResultIterator::new(iter, 0).fraction(Some(1.0));
+        let results: Vec<_> = Result::from_iter(merged_iter).unwrap();
+        assert_eq!(
+            results,
+            vec![
+                GenomicRangeRecordEmpty::new("chr1".to_string(), 0, 17),
+                GenomicRangeRecordEmpty::new("chr2".to_string(), 10, 32),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_grouped_merging_iterators() {
+        fn record(
+            seqname: &str,
+            start: Position,
+            end: Position,
+            strand: Strand,
+        ) -> Result<GenomicRangeRecord<Strand>, GRangesError> {
+            Ok(GenomicRangeRecord::new(
+                seqname.to_string(),
+                start,
+                end,
+                strand,
+            ))
+        }
+        let ranges = vec![
+            record("chr1", 0, 10, Strand::Forward),
+            record("chr1", 5, 8, Strand::Reverse),
+            record("chr1", 9, 20, Strand::Forward),
+            record("chr1", 12, 15, Strand::Reverse),
+            record("chr1", 30, 40, Strand::Reverse),
+            record("chr2", 0, 5, Strand::Forward),
+        ];
+
+        let merged_iter = GroupedMergingResultIterator::new(
+            ranges,
+            0,
+            |strands: Vec<Strand>| strands[0],
+            |range: &GenomicRangeRecord<Strand>| range.data,
+        );
+        let results: Vec<_> = Result::from_iter(merged_iter).unwrap();
+
+        // the forward strand ranges are merged, even though a reverse strand
+        // range is between them; the results are still sorted
+        assert_eq!(
+            results,
+            vec![
+                GenomicRangeRecord::new("chr1".to_string(), 0, 20, Strand::Forward),
+                GenomicRangeRecord::new("chr1".to_string(), 5, 8, Strand::Reverse),
+                GenomicRangeRecord::new("chr1".to_string(), 12, 15, Strand::Reverse),
+                GenomicRangeRecord::new("chr1".to_string(), 30, 40, Strand::Reverse),
+                GenomicRangeRecord::new("chr2".to_string(), 0, 5, Strand::Forward),
+            ]
+        );
+    }
 }
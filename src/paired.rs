@@ -0,0 +1,492 @@
+//! Paired ranges, e.g. the anchors of Hi-C contacts or the breakpoints of structural
+//! variants, read from BEDPE files (see [`BedpeIterator`]).
+//!
+//! A [`GRangesPaired`] container indexes both ends of each pair, for finding the
+//! pairs that overlap another pair ([`GRangesPaired::overlapping_pairs()`], like
+//! `bedtools pairtopair`). Pairs can also be compared against a [`GRanges`] object's
+//! ranges ([`PairToBedType::overlapping_ranges()`], like `bedtools pairtobed`). In
+//! both, pairs' strands are ignored.
+//!
+//! Commands that produce pairs of ranges (e.g. a range and a nearby range) can
+//! write them as BEDPE or UCSC interact lines (see [`PairFormat`] and
+//! [`RangePair`]), to view them as arcs in genome browsers.
+//!
+//! # Example
+//!
+//! ```
+//! use granges::{
+//!     io::parsers::bedpe::{parse_bedpe, BedpeIterator},
+//!     paired::{GRangesPaired, PairToPairType},
+//!     prelude::*,
+//! };
+//!
+//! let seqlens = seqlens! { "chr1" => 100, "chr2" => 50 };
+//! let iter = BedpeIterator::new("tests_data/example.bedpe").unwrap();
+//! let pairs = GRangesPaired::from_iter(iter, &seqlens)
+//!     .unwrap()
+//!     .into_coitrees()
+//!     .unwrap();
+//!
+//! // both ends overlap those of the first pair, chr1:10-20 and chr1:60-70
+//! let query = parse_bedpe("chr1\t15\t16\tchr1\t65\t66").unwrap();
+//! let overlaps = pairs.overlapping_pairs(&query, PairToPairType::Both);
+//! assert_eq!(overlaps, Some(vec![0]));
+//! ```
+//!
+//! [`BedpeIterator`]: crate::io::parsers::bedpe::BedpeIterator
+
+use std::collections::BTreeSet;
+
+use clap::ValueEnum;
+use indexmap::IndexMap;
+
+use crate::{
+    error::GRangesError,
+    granges::GRanges,
+    io::parsers::{bed::Strand, bedpe::BedpeRecord},
+    ranges::{coitrees::COITreesIndexed, vec::VecRangesIndexed, GenomicRangeRecordEmpty},
+    traits::{GenericRange, OverlapQuery},
+    Position,
+};
+
+/// The sorted (data) indices of the ranges of `ranges` that overlap `range`.
+fn overlapping<C: OverlapQuery, T>(
+    ranges: &GRanges<C, T>,
+    range: &GenomicRangeRecordEmpty,
+) -> Vec<usize> {
+    let mut indices = Vec::new();
+    if let Some(container) = ranges.ranges.get(&range.seqname) {
+        container.query_overlaps(range.start, range.end, |overlap| {
+            indices.extend(overlap.index())
+        });
+    }
+    indices.sort_unstable();
+    indices.dedup();
+    indices
+}
+
+/// Which pairs are reported by `pairtobed`, by how their ends (or spans) overlap
+/// the ranges of a BED file, like `bedtools pairtobed -type`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum PairToBedType {
+    /// Pairs with either end overlapping a range
+    #[default]
+    Either,
+    /// Pairs with both ends overlapping a range (not necessarily the same one)
+    Both,
+    /// Pairs with neither end overlapping a range
+    Neither,
+    /// Pairs with exactly one end overlapping a range
+    Xor,
+    /// Pairs without both ends overlapping a range
+    #[value(name = "notboth")]
+    NotBoth,
+    /// Pairs on one sequence with their outer span (from the start of the first end
+    /// to the end of the second) overlapping a range
+    Ospan,
+    /// Pairs on one sequence with their inner span (between the ends) overlapping a
+    /// range
+    Ispan,
+    /// Pairs on one sequence with their outer span not overlapping a range
+    #[value(name = "notospan")]
+    NotOspan,
+    /// Pairs on one sequence with their inner span not overlapping a range
+    #[value(name = "notispan")]
+    NotIspan,
+}
+
+impl PairToBedType {
+    /// Whether `pair` is reported, and if so, the (data) indices of the ranges of
+    /// `ranges` it overlaps: those overlapping either end (or with the span types,
+    /// the span). These are empty for the types reporting pairs without overlaps
+    /// (e.g. [`PairToBedType::Neither`]).
+    pub fn overlapping_ranges<C: OverlapQuery, T>(
+        &self,
+        pair: &BedpeRecord,
+        ranges: &GRanges<C, T>,
+    ) -> Option<Vec<usize>> {
+        use PairToBedType::*;
+        let span = match self {
+            Ospan | NotOspan => Some(pair.outer_span()),
+            Ispan | NotIspan => Some(pair.inner_span()),
+            _ => None,
+        };
+        if let Some(span) = span {
+            let overlaps = overlapping(ranges, &span?);
+            let reported = matches!(self, Ospan | Ispan) != overlaps.is_empty();
+            return reported.then_some(overlaps);
+        }
+
+        let first = overlapping(ranges, &pair.first);
+        let second = overlapping(ranges, &pair.second);
+        let (first_overlaps, second_overlaps) = (!first.is_empty(), !second.is_empty());
+        let reported = match self {
+            Either => first_overlaps || second_overlaps,
+            Both => first_overlaps && second_overlaps,
+            Neither => !first_overlaps && !second_overlaps,
+            Xor => first_overlaps != second_overlaps,
+            // NotBoth (the span types are handled above)
+            _ => !(first_overlaps && second_overlaps),
+        };
+        let overlaps: BTreeSet<usize> = first.into_iter().chain(second).collect();
+        reported.then(|| overlaps.into_iter().collect())
+    }
+}
+
+/// Which pairs are reported by `pairtopair`, by how their ends overlap the ends of
+/// the other pairs, like `bedtools pairtopair -type`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum PairToPairType {
+    /// Pairs with both ends overlapping the two ends of another pair (in either
+    /// orientation)
+    #[default]
+    Both,
+    /// Pairs with either end overlapping an end of another pair
+    Either,
+    /// Pairs with neither end overlapping an end of another pair
+    Neither,
+    /// Pairs without both ends overlapping the two ends of another pair
+    #[value(name = "notboth")]
+    NotBoth,
+}
+
+/// Pairs of ranges, with both ends indexed by the pair's index, for overlap queries.
+#[derive(Clone, Debug)]
+pub struct GRangesPaired<C> {
+    /// The first ends of the pairs, with the pairs as data.
+    first: GRanges<C, Vec<BedpeRecord>>,
+    /// The second ends of the pairs, with their pairs' indices as data.
+    second: GRanges<C, Vec<usize>>,
+}
+
+impl GRangesPaired<VecRangesIndexed> {
+    /// Create a new empty [`GRangesPaired`] object, with vector storage.
+    pub fn new_vec(seqlens: &IndexMap<String, Position>) -> Self {
+        Self {
+            first: GRanges::new_vec(seqlens),
+            second: GRanges::new_vec(seqlens),
+        }
+    }
+
+    /// Add a pair. Both of its ends must be on sequences of this object.
+    pub fn push_pair(&mut self, pair: BedpeRecord) -> Result<(), GRangesError> {
+        for end in [&pair.first, &pair.second] {
+            if self.first.ranges.get(&end.seqname).is_none() {
+                return Err(GRangesError::missing_sequence(&end.seqname));
+            }
+        }
+        let index = self.len();
+        let (first, second) = (pair.first.clone(), &pair.second);
+        self.second
+            .push_range(&second.seqname, second.start, second.end, index)?;
+        self.first
+            .push_range(&first.seqname, first.start, first.end, pair)
+    }
+
+    /// Create a new [`GRangesPaired`] object from a parsing iterator over
+    /// [`BedpeRecord`]s (e.g. a [`BedpeIterator`]).
+    ///
+    /// [`BedpeIterator`]: crate::io::parsers::bedpe::BedpeIterator
+    pub fn from_iter<I>(iter: I, seqlens: &IndexMap<String, Position>) -> Result<Self, GRangesError>
+    where
+        I: Iterator<Item = Result<BedpeRecord, GRangesError>>,
+    {
+        let mut paired = Self::new_vec(seqlens);
+        for pair in iter {
+            paired.push_pair(pair?)?;
+        }
+        Ok(paired)
+    }
+
+    /// Convert the range containers of both ends to interval trees (see
+    /// [`GRanges::into_coitrees()`]), for overlap queries.
+    pub fn into_coitrees(self) -> Result<GRangesPaired<COITreesIndexed>, GRangesError> {
+        Ok(GRangesPaired {
+            first: self.first.into_coitrees()?,
+            second: self.second.into_coitrees()?,
+        })
+    }
+}
+
+impl<C> GRangesPaired<C> {
+    /// The pairs, in the order they were added.
+    pub fn pairs(&self) -> &[BedpeRecord] {
+        self.first.data.as_deref().unwrap_or_default()
+    }
+
+    /// The number of pairs.
+    pub fn len(&self) -> usize {
+        self.pairs().len()
+    }
+
+    /// Whether there are no pairs.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl<C: OverlapQuery> GRangesPaired<C> {
+    /// Whether `pair` is reported by the overlap type `overlap`, and if so, the
+    /// indices of the pairs (see [`GRangesPaired::pairs()`]) it overlaps: those
+    /// overlapping both of its ends with [`PairToPairType::Both`], or either end
+    /// with [`PairToPairType::Either`]. These are empty for the types reporting
+    /// pairs without overlaps.
+    pub fn overlapping_pairs(
+        &self,
+        pair: &BedpeRecord,
+        overlap: PairToPairType,
+    ) -> Option<Vec<usize>> {
+        let first_first = overlapping(&self.first, &pair.first);
+        let first_second = overlapping(&self.second, &pair.first);
+        let second_first = overlapping(&self.first, &pair.second);
+        let second_second = overlapping(&self.second, &pair.second);
+
+        let contains = |indices: &[usize], index: &usize| indices.binary_search(index).is_ok();
+        let ends = [&first_first, &first_second, &second_first, &second_second];
+        let either: BTreeSet<usize> = ends.into_iter().flatten().copied().collect();
+        let both: Vec<usize> = either
+            .iter()
+            .filter(|index| {
+                (contains(&first_first, index) && contains(&second_second, index))
+                    || (contains(&first_second, index) && contains(&second_first, index))
+            })
+            .copied()
+            .collect();
+
+        match overlap {
+            PairToPairType::Both => (!both.is_empty()).then_some(both),
+            PairToPairType::Either => (!either.is_empty()).then(|| either.into_iter().collect()),
+            PairToPairType::Neither => either.is_empty().then(Vec::new),
+            PairToPairType::NotBoth => both.is_empty().then(Vec::new),
+        }
+    }
+}
+
+/// The output format of pairs of ranges.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum PairFormat {
+    /// The first range's BED-like columns, followed by the second range's
+    #[default]
+    Tsv,
+    /// BEDPE: the ranges' sequence names, starts, and ends, then the pair's name and
+    /// score, and the ranges' strands
+    Bedpe,
+    /// UCSC interact (BED5+13), for viewing the pairs as arcs in genome browsers
+    Interact,
+}
+
+/// A pair of ranges to write as BEDPE or interact lines (see [`PairFormat`]), with
+/// the ranges' names and strands, and a value for the pair (e.g. their distance),
+/// if known.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RangePair {
+    pub first: GenomicRangeRecordEmpty,
+    pub second: GenomicRangeRecordEmpty,
+    pub names: (Option<String>, Option<String>),
+    pub strands: (Option<Strand>, Option<Strand>),
+    pub value: Option<f64>,
+}
+
+/// The column of an optional value, with `.` if missing.
+fn column_or_missing(value: Option<impl ToString>) -> String {
+    value.map_or_else(|| ".".to_string(), |value| value.to_string())
+}
+
+fn strand_column(strand: Option<Strand>) -> String {
+    let symbol = match strand {
+        Some(Strand::Forward) => "+",
+        Some(Strand::Reverse) => "-",
+        None => ".",
+    };
+    symbol.to_string()
+}
+
+impl RangePair {
+    /// Create a new [`RangePair`], without names, strands, or a value.
+    pub fn new(first: GenomicRangeRecordEmpty, second: GenomicRangeRecordEmpty) -> Self {
+        Self {
+            first,
+            second,
+            names: (None, None),
+            strands: (None, None),
+            value: None,
+        }
+    }
+
+    /// The pair's name, from the names of both ranges, if known.
+    fn name(&self) -> Option<String> {
+        match &self.names {
+            (Some(first), Some(second)) => Some(format!("{}-{}", first, second)),
+            _ => None,
+        }
+    }
+
+    /// The pair's BEDPE columns: the ranges' sequence names, starts, and ends, the
+    /// pair's name and score (its value), and the ranges' strands, with `.` for
+    /// missing values.
+    pub fn bedpe_columns(&self) -> Vec<String> {
+        let (first, second) = (&self.first, &self.second);
+        vec![
+            first.seqname.clone(),
+            first.start.to_string(),
+            first.end.to_string(),
+            second.seqname.clone(),
+            second.start.to_string(),
+            second.end.to_string(),
+            column_or_missing(self.name()),
+            column_or_missing(self.value),
+            strand_column(self.strands.0),
+            strand_column(self.strands.1),
+        ]
+    }
+
+    /// The pair's [UCSC interact](https://genome.ucsc.edu/goldenPath/help/interact.html)
+    /// columns, with the first range as the source and the second as the target.
+    /// The interaction's region spans both ranges if they are on the same sequence,
+    /// and is the first range otherwise. The score is zero, and the value is the
+    /// pair's value (or zero).
+    pub fn interact_columns(&self) -> Vec<String> {
+        let (first, second) = (&self.first, &self.second);
+        let (start, end) = if first.seqname == second.seqname {
+            (first.start.min(second.start), first.end.max(second.end))
+        } else {
+            (first.start, first.end)
+        };
+        let (first_name, second_name) = &self.names;
+        vec![
+            first.seqname.clone(),
+            start.to_string(),
+            end.to_string(),
+            column_or_missing(self.name()),
+            "0".to_string(),
+            self.value.unwrap_or(0.0).to_string(),
+            ".".to_string(),
+            "0".to_string(),
+            first.seqname.clone(),
+            first.start.to_string(),
+            first.end.to_string(),
+            column_or_missing(first_name.as_ref()),
+            strand_column(self.strands.0),
+            second.seqname.clone(),
+            second.start.to_string(),
+            second.end.to_string(),
+            column_or_missing(second_name.as_ref()),
+            strand_column(self.strands.1),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{io::parsers::bedpe::parse_bedpe, seqlens};
+
+    fn example_pairs() -> GRangesPaired<COITreesIndexed> {
+        let seqlens = seqlens! { "chr1" => 100, "chr2" => 50 };
+        let pairs = [
+            "chr1\t10\t20\tchr1\t60\t70",
+            "chr1\t0\t5\tchr2\t20\t25",
+            "chr2\t0\t10\tchr2\t30\t40",
+        ];
+        let iter = pairs.into_iter().map(parse_bedpe);
+        GRangesPaired::from_iter(iter, &seqlens)
+            .unwrap()
+            .into_coitrees()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_push_pair_missing_sequence() {
+        let mut pairs = GRangesPaired::new_vec(&seqlens! { "chr1" => 100 });
+        let pair = parse_bedpe("chr1\t0\t10\tchr2\t0\t10").unwrap();
+        assert!(pairs.push_pair(pair).is_err());
+        assert!(pairs.is_empty());
+    }
+
+    #[test]
+    fn test_overlapping_pairs() {
+        let pairs = example_pairs();
+        assert_eq!(pairs.len(), 3);
+
+        // both ends overlap, in the swapped orientation
+        let query = parse_bedpe("chr2\t22\t23\tchr1\t3\t4").unwrap();
+        assert_eq!(
+            pairs.overlapping_pairs(&query, PairToPairType::Both),
+            Some(vec![1])
+        );
+        // one end overlaps pair 0, the other pair 2
+        let query = parse_bedpe("chr1\t15\t16\tchr2\t35\t36").unwrap();
+        assert_eq!(pairs.overlapping_pairs(&query, PairToPairType::Both), None);
+        assert_eq!(
+            pairs.overlapping_pairs(&query, PairToPairType::Either),
+            Some(vec![0, 2])
+        );
+        assert_eq!(
+            pairs.overlapping_pairs(&query, PairToPairType::NotBoth),
+            Some(vec![])
+        );
+        assert_eq!(
+            pairs.overlapping_pairs(&query, PairToPairType::Neither),
+            None
+        );
+    }
+
+    #[test]
+    fn test_pair_to_bed_types() {
+        let seqlens = seqlens! { "chr1" => 100, "chr2" => 50 };
+        let mut ranges = GRanges::new_vec(&seqlens);
+        ranges.push_range("chr1", 12, 14, "a").unwrap();
+        ranges.push_range("chr1", 40, 45, "b").unwrap();
+        let ranges = ranges.into_coitrees().unwrap();
+
+        let pair = parse_bedpe("chr1\t10\t20\tchr1\t60\t70").unwrap();
+        let select = |overlap: PairToBedType| overlap.overlapping_ranges(&pair, &ranges);
+        assert_eq!(select(PairToBedType::Either), Some(vec![0]));
+        assert_eq!(select(PairToBedType::Both), None);
+        assert_eq!(select(PairToBedType::Neither), None);
+        assert_eq!(select(PairToBedType::Xor), Some(vec![0]));
+        assert_eq!(select(PairToBedType::NotBoth), Some(vec![0]));
+        assert_eq!(select(PairToBedType::Ospan), Some(vec![0, 1]));
+        assert_eq!(select(PairToBedType::Ispan), Some(vec![1]));
+        assert_eq!(select(PairToBedType::NotIspan), None);
+
+        // spans are only defined for pairs on one sequence
+        let pair = parse_bedpe("chr1\t10\t20\tchr2\t0\t5").unwrap();
+        assert_eq!(
+            PairToBedType::Ospan.overlapping_ranges(&pair, &ranges),
+            None
+        );
+        assert_eq!(
+            PairToBedType::NotOspan.overlapping_ranges(&pair, &ranges),
+            None
+        );
+    }
+
+    #[test]
+    fn test_range_pair_columns() {
+        let range =
+            |seqname: &str, start, end| GenomicRangeRecordEmpty::new(seqname.into(), start, end);
+        let mut pair = RangePair::new(range("chr1", 100, 200), range("chr1", 500, 600));
+        assert_eq!(
+            pair.bedpe_columns().join("\t"),
+            "chr1\t100\t200\tchr1\t500\t600\t.\t.\t.\t."
+        );
+
+        pair.names = (Some("peak1".into()), Some("geneA".into()));
+        pair.strands = (None, Some(Strand::Reverse));
+        pair.value = Some(300.0);
+        assert_eq!(
+            pair.bedpe_columns().join("\t"),
+            "chr1\t100\t200\tchr1\t500\t600\tpeak1-geneA\t300\t.\t-"
+        );
+        assert_eq!(
+            pair.interact_columns().join("\t"),
+            "chr1\t100\t600\tpeak1-geneA\t0\t300\t.\t0\t\
+             chr1\t100\t200\tpeak1\t.\tchr1\t500\t600\tgeneA\t-"
+        );
+
+        // interchromosomal pairs' regions are their first ranges
+        let pair = RangePair::new(range("chr1", 100, 200), range("chr2", 5, 10));
+        assert_eq!(pair.interact_columns()[..3], ["chr1", "100", "200"]);
+    }
+}
@@ -0,0 +1,195 @@
+//! Parallel processing of overlap operations.
+//!
+//! GRanges partitions overlap work (e.g. [`LeftOverlaps::left_overlaps()`] and the
+//! filtering joins [`GRanges::filter_overlaps()`] and [`GRanges::antifilter_overlaps()`])
+//! by sequence, and then into chunks of sorted ranges within each sequence, so that
+//! large chromosomes are also split across threads. Chunks are processed in parallel
+//! with [rayon](https://docs.rs/rayon), and results are always returned in the same
+//! order as they would be by single-threaded processing.
+//!
+//! By default, rayon uses one thread per CPU. Use [`set_num_threads()`] (or the
+//! `granges --threads` option) to change this.
+//!
+//! [`LeftOverlaps::left_overlaps()`]: crate::traits::LeftOverlaps::left_overlaps
+//! [`GRanges::filter_overlaps()`]: crate::granges::GRanges::filter_overlaps
+//! [`GRanges::antifilter_overlaps()`]: crate::granges::GRanges::antifilter_overlaps
+
+use genomap::GenomeMap;
+use rayon::prelude::*;
+
+use crate::{
+    error::GRangesError,
+    join::{JoinFilter, LeftGroupedJoin},
+    traits::{GenericRange, IterableRangeContainer, OverlapQuery},
+};
+
+/// The minimum number of ranges processed per task, to keep scheduling
+/// overhead low.
+pub(crate) const MIN_CHUNK_LENGTH: usize = 1024;
+
+/// Per-range results, grouped by sequence name.
+pub(crate) type SequenceResults<'a, T> = Vec<(&'a String, Vec<T>)>;
+
+/// Set the number of threads used for parallel processing.
+///
+/// This configures rayon's global thread pool, so it must be called before any
+/// parallel processing, and can only be called once.
+pub fn set_num_threads(num_threads: usize) -> Result<(), GRangesError> {
+    rayon::ThreadPoolBuilder::new()
+        .num_threads(num_threads)
+        .build_global()?;
+    Ok(())
+}
+
+/// Apply `func` to every left range and the right ranges on the same sequence (if any),
+/// in parallel, returning the results grouped by sequence in the order of `left`.
+fn par_map_ranges<'a, CL, CR, T, F>(
+    left: &'a GenomeMap<CL>,
+    right: &GenomeMap<CR>,
+    func: F,
+) -> SequenceResults<'a, T>
+where
+    CL: IterableRangeContainer + Sync,
+    CL::RangeType: Send + Sync,
+    CR: Sync,
+    T: Send,
+    F: Fn(&CL::RangeType, Option<&CR>) -> T + Sync,
+{
+    let sequences: Vec<(&String, &CL)> = left.iter().collect();
+    sequences
+        .into_par_iter()
+        .map(|(seqname, left_ranges)| {
+            let right_ranges = right.get(seqname);
+            let ranges: Vec<CL::RangeType> = left_ranges.iter_ranges().collect();
+            let results = ranges
+                .par_iter()
+                .with_min_len(MIN_CHUNK_LENGTH)
+                .map(|left_range| func(left_range, right_ranges))
+                .collect();
+            (seqname, results)
+        })
+        .collect()
+}
+
+/// Compute the [`LeftGroupedJoin`] of every left range, with the right ranges whose
+/// overlap passes `filter`, in parallel.
+pub(crate) fn par_left_grouped_joins<'a, CL, CR>(
+    left: &'a GenomeMap<CL>,
+    right: &GenomeMap<CR>,
+    filter: &JoinFilter,
+) -> SequenceResults<'a, LeftGroupedJoin>
+where
+    CL: IterableRangeContainer + Sync,
+    CL::RangeType: Send + Sync,
+    CR: OverlapQuery + Sync,
+{
+    par_map_ranges(left, right, |left_range, right_ranges| {
+        let mut join_data = LeftGroupedJoin::new(left_range);
+        if let Some(right_ranges) = right_ranges {
+            let (start, end) = filter.query_range(left_range.start(), left_range.end());
+            right_ranges.query_overlaps(start, end, |right_range| {
+                if filter.passes(left_range, right_range) {
+                    join_data.add_right(right_range);
+                }
+            });
+        }
+        join_data
+    })
+}
+
+/// Determine whether every left range has at least one overlapping right range
+/// whose overlap passes `filter`, in parallel.
+pub(crate) fn par_has_overlaps<'a, CL, CR>(
+    left: &'a GenomeMap<CL>,
+    right: &GenomeMap<CR>,
+    filter: &JoinFilter,
+) -> SequenceResults<'a, (CL::RangeType, bool)>
+where
+    CL: IterableRangeContainer + Sync,
+    CL::RangeType: Send + Sync,
+    CR: OverlapQuery + Sync,
+{
+    par_map_ranges(left, right, |left_range, right_ranges| {
+        let has_overlaps = right_ranges
+            .is_some_and(|right_ranges| count_passing(left_range, right_ranges, filter) > 0);
+        (left_range.clone(), has_overlaps)
+    })
+}
+
+/// Count the overlapping right ranges of every left range whose overlap passes
+/// `filter`, in parallel.
+pub(crate) fn par_count_overlaps<'a, CL, CR>(
+    left: &'a GenomeMap<CL>,
+    right: &GenomeMap<CR>,
+    filter: &JoinFilter,
+) -> SequenceResults<'a, (CL::RangeType, usize)>
+where
+    CL: IterableRangeContainer + Sync,
+    CL::RangeType: Send + Sync,
+    CR: OverlapQuery + Sync,
+{
+    par_map_ranges(left, right, |left_range, right_ranges| {
+        let num_overlaps = right_ranges.map_or(0, |right_ranges| {
+            count_passing(left_range, right_ranges, filter)
+        });
+        (left_range.clone(), num_overlaps)
+    })
+}
+
+/// Count the right ranges overlapping `left_range` (padded by any window) whose
+/// overlap passes `filter` (with a faster count if it has no requirements).
+fn count_passing<L: GenericRange, CR: OverlapQuery>(
+    left_range: &L,
+    right_ranges: &CR,
+    filter: &JoinFilter,
+) -> usize {
+    let (start, end) = filter.query_range(left_range.start(), left_range.end());
+    if filter.is_any() {
+        return right_ranges.count_overlaps(start, end);
+    }
+    let mut count = 0;
+    right_ranges.query_overlaps(start, end, |right_range| {
+        if filter.passes(left_range, right_range) {
+            count += 1;
+        }
+    });
+    count
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{prelude::*, testing::random_granges};
+
+    #[test]
+    fn test_parallel_filter_in_order() {
+        let seqlens = seqlens! { "chr1" => 1_000_000, "chr2" => 100_000 };
+        // enough ranges to be split into several chunks
+        let left = random_granges(&seqlens, 5_000).unwrap();
+        let right = random_granges(&seqlens, 5_000).unwrap();
+
+        // brute force, single-threaded
+        let right_ranges: Vec<_> = right.iter_ranges().collect();
+        let expected: Vec<_> = left
+            .iter_ranges()
+            .filter(|left_range| {
+                right_ranges.iter().any(|right_range| {
+                    right_range.seqname_index == left_range.seqname_index
+                        && right_range.has_overlap_with(left_range)
+                })
+            })
+            .map(|range| (range.seqname_index, range.start, range.end))
+            .collect();
+
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(4)
+            .build()
+            .unwrap();
+        let right = right.into_coitrees().unwrap();
+        let filtered = pool.install(|| left.filter_overlaps(&right).unwrap());
+        let filtered: Vec<_> = filtered
+            .iter_ranges()
+            .map(|range| (range.seqname_index, range.start, range.end))
+            .collect();
+        assert_eq!(filtered, expected);
+    }
+}
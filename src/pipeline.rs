@@ -0,0 +1,338 @@
+//! Lazy pipelines of range operations.
+//!
+//! Each method on a [`GRanges`] (e.g. [`GRanges::adjust_ranges()`] or
+//! [`GRanges::filter_overlaps()`]) builds a new in-memory [`GRanges`] object.
+//! A chain of such operations thus materializes every intermediate result. A
+//! [`GRangesPipeline`] instead composes operations over a stream of input ranges
+//! (e.g. a parsing iterator), and runs nothing until it is consumed with
+//! [`GRangesPipeline::collect()`] or [`GRangesPipeline::write()`]. Then, all
+//! operations are fused into a single pass over the input, and each range flows
+//! through every step before the next range is read.
+//!
+//! Only the *right* ranges of overlap operations (which must be queried) are held
+//! in memory, in a [`GRanges`] with an overlap-query backend.
+//!
+//! # Example
+//!
+//! ```
+//! use granges::{pipeline::GRangesPipeline, prelude::*};
+//!
+//! let seqlens = seqlens! { "chr1" => 100, "chr2" => 100 };
+//! let mut right = GRangesEmpty::new_vec(&seqlens);
+//! right.push_range("chr1", 40, 50).unwrap();
+//! let right = right.into_coitrees().unwrap();
+//!
+//! let left = Bed3Iterator::new("tests_data/example.bed").unwrap();
+//! let gr = GRangesPipeline::new(left, &seqlens)
+//!     .retain_seqnames(&["chr1".to_string()])
+//!     .adjust(-10, 25)
+//!     .filter(&right)
+//!     .merge(0)
+//!     .collect()
+//!     .unwrap();
+//!
+//! // chr1:10-20 and chr1:14-18 are adjusted to chr1:0-45 and chr1:4-43,
+//! // which both overlap chr1:40-50 and then are merged.
+//! assert_eq!(gr.len(), 1);
+//! ```
+//!
+//! [`GRanges`]: crate::granges::GRanges
+//! [`GRanges::adjust_ranges()`]: crate::granges::GRanges::adjust_ranges
+//! [`GRanges::filter_overlaps()`]: crate::granges::GRanges::filter_overlaps
+
+use std::path::PathBuf;
+
+use indexmap::IndexMap;
+
+use crate::{
+    commands::build_bed_writer,
+    data::{operations::FloatOperation, DatumType},
+    error::GRangesError,
+    granges::{GRanges, GRangesEmpty},
+    io::tsv::BED_TSV,
+    merging_iterators::MergingEmptyResultIterator,
+    ranges::{
+        operations::adjust_range, vec::VecRangesEmpty, vec::VecRangesIndexed, GenomicRangeRecord,
+        GenomicRangeRecordEmpty,
+    },
+    traits::{AsGRangesRef, GenericRange, GenomicRecord, OverlapQuery},
+    Position, PositionOffset,
+};
+
+type RangeStream<'a, R> = Box<dyn Iterator<Item = Result<R, GRangesError>> + 'a>;
+
+/// A lazy pipeline of operations over a stream of ranges, which are run in a single
+/// pass when the pipeline is consumed with [`GRangesPipeline::collect()`] or
+/// [`GRangesPipeline::write()`].
+pub struct GRangesPipeline<'a> {
+    ranges: RangeStream<'a, GenomicRangeRecordEmpty>,
+    seqlens: IndexMap<String, Position>,
+}
+
+impl<'a> GRangesPipeline<'a> {
+    /// Create a new [`GRangesPipeline`] over the ranges of a parsing iterator. Any
+    /// data in the records is dropped.
+    pub fn new<I, R>(iter: I, seqlens: &IndexMap<String, Position>) -> Self
+    where
+        I: Iterator<Item = Result<R, GRangesError>> + 'a,
+        R: GenomicRecord,
+    {
+        let ranges = iter.map(|result| {
+            result.map(|range| GenomicRangeRecordEmpty {
+                seqname: range.seqname().to_string(),
+                start: range.start(),
+                end: range.end(),
+            })
+        });
+        Self {
+            ranges: Box::new(ranges),
+            seqlens: seqlens.clone(),
+        }
+    }
+
+    /// Only keep ranges on the specified sequences.
+    pub fn retain_seqnames(mut self, seqnames: &[String]) -> Self {
+        let seqnames = seqnames.to_vec();
+        self.ranges = Box::new(self.ranges.filter(move |result| match result {
+            Ok(range) => seqnames.contains(&range.seqname),
+            Err(_) => true,
+        }));
+        self
+    }
+
+    /// Adjust the start and end positions of each range, as with
+    /// [`GRanges::adjust_ranges()`]. Adjusted ranges are clipped to the sequence
+    /// bounds, and ranges with a width of zero after adjustment are dropped.
+    ///
+    /// [`GRanges::adjust_ranges()`]: crate::granges::GRanges::adjust_ranges
+    pub fn adjust(mut self, start_delta: PositionOffset, end_delta: PositionOffset) -> Self {
+        let seqlens = self.seqlens.clone();
+        self.ranges = Box::new(self.ranges.filter_map(move |result| {
+            let range = match result {
+                Ok(range) => range,
+                Err(e) => return Some(Err(e)),
+            };
+            match seqlens.get(&range.seqname) {
+                Some(&length) => adjust_range(range, start_delta, end_delta, length).map(Ok),
+                None => Some(Err(GRangesError::missing_sequence(range.seqname))),
+            }
+        }));
+        self
+    }
+
+    /// Only keep ranges that overlap at least one of the `right` ranges, as with
+    /// [`GRanges::filter_overlaps()`].
+    ///
+    /// [`GRanges::filter_overlaps()`]: crate::granges::GRanges::filter_overlaps
+    pub fn filter<C, T: 'a>(mut self, right: &'a impl AsGRangesRef<'a, C, T>) -> Self
+    where
+        C: OverlapQuery + 'a,
+    {
+        let right = right.as_granges_ref();
+        self.ranges = Box::new(self.ranges.filter(move |result| {
+            match result {
+                Ok(range) => right
+                    .get_ranges(&range.seqname)
+                    .is_some_and(|ranges| ranges.count_overlaps(range.start, range.end) > 0),
+                Err(_) => true,
+            }
+        }));
+        self
+    }
+
+    /// Merge ranges that are within `minimum_distance` of each other (or overlap by
+    /// at least `-minimum_distance`, if negative), as with `granges merge`. The
+    /// ranges at this step must be sorted.
+    pub fn merge(mut self, minimum_distance: PositionOffset) -> Self {
+        self.ranges = Box::new(MergingEmptyResultIterator::new(
+            self.ranges,
+            minimum_distance,
+        ));
+        self
+    }
+
+    /// Summarize the values of the `right` ranges that overlap each range with one
+    /// or more `operations`, as with `granges map`. This ends the pipeline, returning a
+    /// [`MappedPipeline`].
+    pub fn map<C>(
+        self,
+        right: &'a GRanges<C, Vec<Option<f64>>>,
+        operations: Vec<FloatOperation>,
+    ) -> MappedPipeline<'a>
+    where
+        C: OverlapQuery,
+    {
+        let values: &'a [Option<f64>] = right.data.as_deref().unwrap_or_default();
+        let ranges = self.ranges.map(move |result| {
+            let range = result?;
+            let mut overlap_scores = Vec::new();
+            let mut num_overlaps = 0;
+            if let Some(ranges) = right.get_ranges(&range.seqname) {
+                ranges.query_overlaps(range.start, range.end, |overlap| {
+                    num_overlaps += 1;
+                    if let Some(value) = overlap.index().and_then(|index| values[index]) {
+                        overlap_scores.push(value);
+                    }
+                });
+            }
+            let data = operations
+                .iter()
+                .map(|operation| match operation {
+                    // count all overlaps, including those with missing values
+                    FloatOperation::Count => DatumType::Unsigned64(num_overlaps as u64),
+                    _ => operation.run(&mut overlap_scores),
+                })
+                .collect();
+            Ok(GenomicRangeRecord::new(
+                range.seqname,
+                range.start,
+                range.end,
+                data,
+            ))
+        });
+        MappedPipeline {
+            ranges: Box::new(ranges),
+            seqlens: self.seqlens,
+        }
+    }
+
+    /// Run the pipeline, collecting the resulting ranges into a [`GRangesEmpty`].
+    pub fn collect(self) -> Result<GRangesEmpty<VecRangesEmpty>, GRangesError> {
+        GRangesEmpty::from_iter(self.ranges, &self.seqlens)
+    }
+
+    /// Run the pipeline, writing the resulting ranges to a BED3 file (or standard
+    /// output if `output` is `None`) as they are processed.
+    pub fn write(self, output: Option<impl Into<PathBuf>>) -> Result<(), GRangesError> {
+        let mut writer = build_bed_writer(output)?;
+        for result in self.ranges {
+            writer.serialize(result?)?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+/// A lazy pipeline ending in a `map` step (see [`GRangesPipeline::map()`]), such
+/// that each range has the results of the map operations as its data.
+pub struct MappedPipeline<'a> {
+    ranges: RangeStream<'a, GenomicRangeRecord<Vec<DatumType>>>,
+    seqlens: IndexMap<String, Position>,
+}
+
+impl<'a> MappedPipeline<'a> {
+    /// Run the pipeline, collecting the resulting ranges and their map operation
+    /// results into a [`GRanges`].
+    pub fn collect(self) -> Result<GRanges<VecRangesIndexed, Vec<Vec<DatumType>>>, GRangesError> {
+        GRanges::from_iter(self.ranges, &self.seqlens)
+    }
+
+    /// Run the pipeline, writing the resulting ranges and their map operation results
+    /// to a BED-like file (or standard output if `output` is `None`) as they are processed.
+    pub fn write(self, output: Option<impl Into<PathBuf>>) -> Result<(), GRangesError> {
+        let mut writer = build_bed_writer(output)?;
+        for result in self.ranges {
+            let range = result?;
+            let data: Vec<_> = range
+                .data
+                .into_iter()
+                .map(|datum| datum.into_serializable(&BED_TSV))
+                .collect();
+            writer.serialize(GenomicRangeRecord::new(
+                range.seqname,
+                range.start,
+                range.end,
+                data,
+            ))?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        prelude::*,
+        testing::{random_granges, random_granges_mock_bed5},
+    };
+
+    fn records(gr: &GRangesEmpty<VecRangesEmpty>) -> Vec<GenomicRangeRecordEmpty> {
+        let seqnames = gr.seqnames();
+        gr.iter_ranges()
+            .map(|range| GenomicRangeRecordEmpty {
+                seqname: seqnames[range.seqname_index].clone(),
+                start: range.start,
+                end: range.end,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_pipeline_matches_granges() {
+        let seqlens = seqlens! { "chr1" => 10_000, "chr2" => 5_000 };
+        let left = random_granges(&seqlens, 200).unwrap();
+        let right = random_granges(&seqlens, 200)
+            .unwrap()
+            .into_coitrees()
+            .unwrap();
+
+        let expected = left
+            .clone()
+            .adjust_ranges(-10, 10)
+            .filter_overlaps(&right)
+            .unwrap();
+
+        let gr = GRangesPipeline::new(records(&left).into_iter().map(Ok), &seqlens)
+            .adjust(-10, 10)
+            .filter(&right)
+            .collect()
+            .unwrap();
+        assert_eq!(
+            gr.iter_ranges().collect::<Vec<_>>(),
+            expected.iter_ranges().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_pipeline_map() {
+        let seqlens = seqlens! { "chr1" => 10_000, "chr2" => 5_000 };
+        let left = random_granges(&seqlens, 100).unwrap();
+        let mut right = random_granges_mock_bed5(&seqlens, 200).unwrap();
+        let scores: Vec<Option<f64>> = right
+            .take_data()
+            .unwrap()
+            .into_iter()
+            .map(|bed5| bed5.score)
+            .collect();
+        let right = right.clone_with_data(Some(scores)).into_coitrees().unwrap();
+
+        let mapped = GRangesPipeline::new(records(&left).into_iter().map(Ok), &seqlens)
+            .map(&right, vec![FloatOperation::Sum, FloatOperation::Max])
+            .collect()
+            .unwrap();
+
+        let expected = left
+            .left_overlaps(&right)
+            .unwrap()
+            .map_joins(|join_data| {
+                let mut scores: Vec<f64> = join_data.right_data.into_iter().flatten().collect();
+                FloatOperation::Sum.run(&mut scores)
+            })
+            .unwrap();
+        let sums: Vec<String> = mapped
+            .data
+            .unwrap()
+            .into_iter()
+            .map(|data| format!("{:?}", data[0]))
+            .collect();
+        let expected: Vec<String> = expected
+            .data
+            .unwrap()
+            .iter()
+            .map(|datum| format!("{:?}", datum))
+            .collect();
+        assert_eq!(sums, expected);
+    }
+}
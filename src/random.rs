@@ -0,0 +1,614 @@
+//! Random genomic ranges, e.g. for null models, simulations, and benchmarks.
+//!
+//! A [`RandomRanges`] generator places ranges at random on the sequences of a
+//! genome. Each range's sequence is chosen with probability proportional to its
+//! length, and its width is drawn from a [`WidthDistribution`]: a fixed width,
+//! widths uniform between a minimum and maximum, or the empirical widths of a
+//! template set of ranges (e.g. to build a null set matching a set of peaks).
+//! Ranges can be kept out of excluded regions (e.g. assembly gaps or blacklisted
+//! regions), in which case a range that overlaps one is placed again.
+//!
+//! Existing ranges can also be shuffled into a matched null set with
+//! [`RandomRanges::shuffle_matched()`]: each is placed again at random, keeping its
+//! width, until features of its new position (e.g. GC content, or mappability; see
+//! [`MatchedFeature`]) are within a tolerance of those of its original position.
+//! These are the ranges written by `granges shuffle`.
+//!
+//! All generation takes a random number generator (any [`Rng`]), so output is
+//! reproducible with a seeded generator. These are the ranges written by `granges
+//! random`. The command line tool seeds its random number generators from a global
+//! seed (set with `--seed`; see [`set_seed()`] and [`seeded_rng()`]).
+//!
+//! # Example
+//!
+//! ```
+//! use granges::prelude::*;
+//! use granges::random::{RandomRanges, WidthDistribution};
+//! use rand::{rngs::StdRng, SeedableRng};
+//!
+//! let seqlens = seqlens! { "chr1" => 10_000, "chr2" => 5_000 };
+//! let generator = RandomRanges::new(&seqlens, WidthDistribution::Fixed(100)).unwrap();
+//! let mut rng = StdRng::seed_from_u64(1);
+//! let gr = generator.generate(50, &mut rng).unwrap();
+//! assert_eq!(gr.len(), 50);
+//! assert!(gr.iter_ranges().all(|range| range.end - range.start == 100));
+//! ```
+
+use std::{path::PathBuf, sync::OnceLock};
+
+use indexmap::IndexMap;
+use rand::{
+    distributions::{Distribution, Uniform, WeightedIndex},
+    rngs::StdRng,
+    seq::SliceRandom,
+    Rng, SeedableRng,
+};
+use serde::Serialize;
+
+use crate::{
+    error::GRangesError,
+    granges::{GRanges, GRangesEmpty},
+    io::parsers::{bed::Strand, Bed5Addition, BedlikeIterator},
+    ranges::{
+        coitrees::COITreesEmpty,
+        vec::{VecRangesEmpty, VecRangesIndexed},
+        GenomicRangeRecord,
+    },
+    Position,
+};
+
+/// The default number of times a range is placed before giving up, if it is too
+/// wide for the chosen sequence, or overlaps an excluded region.
+pub const DEFAULT_MAX_ATTEMPTS: usize = 1000;
+
+static SEED: OnceLock<u64> = OnceLock::new();
+
+/// Set the random seed globally, so that all randomness derived from [`seed()`] is
+/// reproducible.
+///
+/// Like [`set_na_value()`](crate::io::tsv::set_na_value), this must be called
+/// before any random numbers are generated, and can only be called once.
+pub fn set_seed(seed: u64) -> Result<(), GRangesError> {
+    SEED.set(seed).map_err(GRangesError::SeedAlreadySet)
+}
+
+/// The global random seed: the one set with [`set_seed()`], or otherwise a random
+/// seed, chosen once.
+pub fn seed() -> u64 {
+    *SEED.get_or_init(rand::random)
+}
+
+/// A random number generator seeded with the global seed (see [`seed()`]).
+pub fn seeded_rng() -> StdRng {
+    StdRng::seed_from_u64(seed())
+}
+
+/// The distribution of random range widths.
+#[derive(Clone, Debug, PartialEq)]
+pub enum WidthDistribution {
+    /// Every range has the same width.
+    Fixed(Position),
+    /// Widths are uniform between the minimum and maximum (inclusive).
+    Uniform(Position, Position),
+    /// Widths are drawn (with replacement) from these widths.
+    Empirical(Vec<Position>),
+}
+
+impl WidthDistribution {
+    /// Build an empirical [`WidthDistribution`] from the widths of the ranges in
+    /// the BED-like file at `path`.
+    pub fn from_template(path: impl Into<PathBuf>) -> Result<Self, GRangesError> {
+        let widths = BedlikeIterator::new(path)?
+            .map(|record| record.map(|record| record.end - record.start))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(WidthDistribution::Empirical(widths))
+    }
+
+    /// Check that all widths of this distribution are positive.
+    fn validate(&self) -> Result<(), GRangesError> {
+        let invalid = |message: &str| Err(GRangesError::InvalidWidthDistribution(message.into()));
+        match self {
+            WidthDistribution::Fixed(0) => invalid("the width must be greater than zero"),
+            WidthDistribution::Uniform(min, _) if *min == 0 => {
+                invalid("the minimum width must be greater than zero")
+            }
+            WidthDistribution::Uniform(min, max) if min > max => {
+                invalid("the minimum width is greater than the maximum width")
+            }
+            WidthDistribution::Empirical(widths) if widths.is_empty() => {
+                invalid("there are no template widths")
+            }
+            WidthDistribution::Empirical(widths) if widths.contains(&0) => {
+                invalid("the template has a zero-width range")
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Draw a random width.
+    fn sample(&self, rng: &mut impl Rng) -> Position {
+        match self {
+            WidthDistribution::Fixed(width) => *width,
+            WidthDistribution::Uniform(min, max) => rng.gen_range(*min..=*max),
+            WidthDistribution::Empirical(widths) => {
+                *widths.choose(rng).expect("Internal error: please report")
+            }
+        }
+    }
+}
+
+/// The name, score, and strand columns of a random BED6 range, from
+/// [`RandomRanges::generate_bed6()`].
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct RandomBed6Addition {
+    pub name: String,
+    pub score: f64,
+    pub strand: Strand,
+}
+
+/// The function of a [`MatchedFeature`].
+type FeatureFn<'a> = dyn Fn(&str, Position, Position) -> Result<Option<f64>, GRangesError> + 'a;
+
+/// A feature of ranges to match when shuffling them (see
+/// [`RandomRanges::shuffle_matched()`]), e.g. GC content or mappability: a function
+/// of a range's sequence name, start, and end, which is `None` where the feature is
+/// undefined (e.g. the GC content of a run of `N`s), and the largest allowed
+/// difference between the feature of a shuffled range and that of the original.
+pub struct MatchedFeature<'a> {
+    func: Box<FeatureFn<'a>>,
+    tolerance: f64,
+}
+
+impl<'a> MatchedFeature<'a> {
+    /// Create a new [`MatchedFeature`] from its function, matched within `tolerance`.
+    pub fn new<F>(func: F, tolerance: f64) -> Self
+    where
+        F: Fn(&str, Position, Position) -> Result<Option<f64>, GRangesError> + 'a,
+    {
+        Self {
+            func: Box::new(func),
+            tolerance,
+        }
+    }
+
+    /// The value of this feature for a range.
+    pub fn value(
+        &self,
+        seqname: &str,
+        start: Position,
+        end: Position,
+    ) -> Result<Option<f64>, GRangesError> {
+        (self.func)(seqname, start, end)
+    }
+}
+
+impl std::fmt::Debug for MatchedFeature<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MatchedFeature")
+            .field("tolerance", &self.tolerance)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Whether each of the `features` of a range is within its tolerance of the
+/// feature's `target` value (if it is defined).
+fn matches_features(
+    features: &[MatchedFeature],
+    targets: &[Option<f64>],
+    seqname: &str,
+    start: Position,
+    end: Position,
+) -> Result<bool, GRangesError> {
+    for (feature, target) in features.iter().zip(targets) {
+        let Some(target) = target else {
+            continue;
+        };
+        match feature.value(seqname, start, end)? {
+            Some(value) if (value - target).abs() <= feature.tolerance => {}
+            _ => return Ok(false),
+        }
+    }
+    Ok(true)
+}
+
+/// A generator of random ranges on a genome (see the [module documentation](self)).
+#[derive(Clone, Debug)]
+pub struct RandomRanges {
+    seqlens: IndexMap<String, Position>,
+    /// The sequence weights, proportional to their lengths.
+    weights: WeightedIndex<u64>,
+    widths: WidthDistribution,
+    exclude: Option<GRangesEmpty<COITreesEmpty>>,
+    max_attempts: usize,
+}
+
+impl RandomRanges {
+    /// Create a new [`RandomRanges`] generator of ranges on the sequences in
+    /// `seqlens`, with widths drawn from `widths`.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`GRangesError::InvalidWidthDistribution`] if any width could be
+    /// zero, or a [`GRangesError::InvalidGenomeFile`] if no sequence has a positive
+    /// length.
+    pub fn new(
+        seqlens: &IndexMap<String, Position>,
+        widths: WidthDistribution,
+    ) -> Result<Self, GRangesError> {
+        widths.validate()?;
+        let weights = WeightedIndex::new(seqlens.values().map(|length| u64::from(*length)))
+            .map_err(|_| {
+                GRangesError::InvalidGenomeFile("no sequence has a positive length".to_string())
+            })?;
+        Ok(Self {
+            seqlens: seqlens.clone(),
+            weights,
+            widths,
+            exclude: None,
+            max_attempts: DEFAULT_MAX_ATTEMPTS,
+        })
+    }
+
+    /// Keep the random ranges from overlapping any of the `exclude` ranges.
+    pub fn exclude(mut self, exclude: GRangesEmpty<COITreesEmpty>) -> Self {
+        self.exclude = Some(exclude);
+        self
+    }
+
+    /// Set the number of times each range is placed before giving up (by default,
+    /// [`DEFAULT_MAX_ATTEMPTS`]).
+    pub fn max_attempts(mut self, max_attempts: usize) -> Self {
+        self.max_attempts = max_attempts;
+        self
+    }
+
+    /// Place one random range, as (sequence index, start, end), of `width` (or of a
+    /// width drawn from the width distribution, if `None`) and accepted by `accept`,
+    /// or `None` if no range is placed in the maximum number of attempts.
+    fn place<F>(
+        &self,
+        width: Option<Position>,
+        mut accept: F,
+        rng: &mut impl Rng,
+    ) -> Result<Option<(usize, Position, Position)>, GRangesError>
+    where
+        F: FnMut(&str, Position, Position) -> Result<bool, GRangesError>,
+    {
+        for _ in 0..self.max_attempts {
+            let index = self.weights.sample(rng);
+            let (seqname, length) = self
+                .seqlens
+                .get_index(index)
+                .expect("Internal error: please report");
+            let width = width.unwrap_or_else(|| self.widths.sample(rng));
+            if width > *length {
+                continue;
+            }
+            let start = rng.gen_range(0..=length - width);
+            let end = start + width;
+            let excluded = self.exclude.as_ref().is_some_and(|exclude| {
+                exclude
+                    .on(seqname)
+                    .is_some_and(|view| view.count_overlaps(start, end) > 0)
+            });
+            if !excluded && accept(seqname, start, end)? {
+                return Ok(Some((index, start, end)));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Place one random range, as (sequence index, start, end).
+    fn sample_range(
+        &self,
+        rng: &mut impl Rng,
+    ) -> Result<(usize, Position, Position), GRangesError> {
+        self.place(None, |_, _, _| Ok(true), rng)?
+            .ok_or(GRangesError::RandomPlacementFailed(self.max_attempts))
+    }
+
+    /// Generate `num` random ranges, sorted.
+    pub fn generate(
+        &self,
+        num: usize,
+        rng: &mut impl Rng,
+    ) -> Result<GRangesEmpty<VecRangesEmpty>, GRangesError> {
+        let mut gr = GRangesEmpty::new_vec(&self.seqlens);
+        for _ in 0..num {
+            let (index, start, end) = self.sample_range(rng)?;
+            gr.push_range(self.seqname(index), start, end)?;
+        }
+        Ok(gr.sort())
+    }
+
+    /// Generate `num` random ranges, sorted, with BED5 columns: a name (`range1`,
+    /// `range2`, etc., in the order generated) and a score uniform on `[0, 1)`.
+    pub fn generate_bed5(
+        &self,
+        num: usize,
+        rng: &mut impl Rng,
+    ) -> Result<GRanges<VecRangesIndexed, Vec<Bed5Addition>>, GRangesError> {
+        let mut gr = GRanges::new_vec(&self.seqlens);
+        let scores = Uniform::new(0.0, 1.0);
+        for i in 0..num {
+            let (index, start, end) = self.sample_range(rng)?;
+            let data = Bed5Addition {
+                name: format!("range{}", i + 1),
+                score: Some(scores.sample(rng)),
+            };
+            gr.push_range(self.seqname(index), start, end, data)?;
+        }
+        Ok(gr.sort())
+    }
+
+    /// Generate `num` random ranges, sorted, with BED6 columns: the BED5 columns of
+    /// [`RandomRanges::generate_bed5()`], and a strand that is equally likely to be
+    /// forward or reverse.
+    pub fn generate_bed6(
+        &self,
+        num: usize,
+        rng: &mut impl Rng,
+    ) -> Result<GRanges<VecRangesIndexed, Vec<RandomBed6Addition>>, GRangesError> {
+        let mut gr = GRanges::new_vec(&self.seqlens);
+        let scores = Uniform::new(0.0, 1.0);
+        for i in 0..num {
+            let (index, start, end) = self.sample_range(rng)?;
+            let data = RandomBed6Addition {
+                name: format!("range{}", i + 1),
+                score: scores.sample(rng),
+                strand: if rng.gen() {
+                    Strand::Forward
+                } else {
+                    Strand::Reverse
+                },
+            };
+            gr.push_range(self.seqname(index), start, end, data)?;
+        }
+        Ok(gr.sort())
+    }
+
+    /// Shuffle `ranges`: place each at random, keeping its width and data, such that
+    /// each of the `features` of its new position is within the feature's tolerance
+    /// of that of its original position (e.g. a GC-matched null set of peaks). A
+    /// range whose feature is undefined isn't matched on it, but a position whose
+    /// feature is undefined never matches. The shuffled ranges are in the order of
+    /// `ranges`. The width distribution of this generator is not used.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`GRangesError::MatchedPlacementFailed`] if a range can't be placed
+    /// in the maximum number of attempts (see [`RandomRanges::max_attempts()`]).
+    pub fn shuffle_matched<T: Clone>(
+        &self,
+        ranges: &[GenomicRangeRecord<T>],
+        features: &[MatchedFeature],
+        rng: &mut impl Rng,
+    ) -> Result<Vec<GenomicRangeRecord<T>>, GRangesError> {
+        let mut shuffled = Vec::with_capacity(ranges.len());
+        for range in ranges {
+            let targets = features
+                .iter()
+                .map(|feature| feature.value(&range.seqname, range.start, range.end))
+                .collect::<Result<Vec<_>, _>>()?;
+            let (index, start, end) = self
+                .place(
+                    Some(range.end - range.start),
+                    |seqname, start, end| matches_features(features, &targets, seqname, start, end),
+                    rng,
+                )?
+                .ok_or(GRangesError::MatchedPlacementFailed(self.max_attempts))?;
+            shuffled.push(GenomicRangeRecord::new(
+                self.seqname(index).to_string(),
+                start,
+                end,
+                range.data.clone(),
+            ));
+        }
+        Ok(shuffled)
+    }
+
+    fn seqname(&self, index: usize) -> &str {
+        self.seqlens
+            .get_index(index)
+            .map(|(seqname, _)| seqname.as_str())
+            .expect("Internal error: please report")
+    }
+}
+
+// The widths of the ranges of the simple generators below, which choose sequences
+// uniformly and are used for mock data (e.g. in tests and benchmarks).
+pub const MIN_LEN: Position = 1;
+pub const MAX_LEN: Position = 1000;
+
+/// Build a random range start/end on a sequence of `max_len`.
+/// 0-indexed, right exclusive
+pub fn random_range(chrom_len: Position, rng: &mut impl Rng) -> (Position, Position) {
+    let len = rng.gen_range(MIN_LEN..MAX_LEN);
+    let start = rng.gen_range(0..chrom_len - len + 1);
+    (start, start + len)
+}
+
+/// Build a random [`GRangesEmpty`] using a set of sequence lengths, and the random
+/// number generator `rng`.
+pub fn random_granges_with_rng(
+    seqlens: &IndexMap<String, Position>,
+    num: usize,
+    rng: &mut impl Rng,
+) -> Result<GRangesEmpty<VecRangesEmpty>, GRangesError> {
+    let mut gr = GRangesEmpty::new_vec(seqlens);
+
+    let seqnames: Vec<String> = seqlens.keys().cloned().collect();
+    for _ in 0..num {
+        let seqname = seqnames.choose(rng).unwrap();
+        let chrom_len = *seqlens
+            .get(seqname)
+            .ok_or_else(|| GRangesError::missing_sequence(seqname.clone()))?;
+        let (start, end) = random_range(chrom_len, rng);
+        gr.push_range(seqname, start, end)?;
+    }
+    Ok(gr)
+}
+
+/// Generate random strings, e.g. for mock feature names.
+fn generate_random_string(n: usize, rng: &mut impl Rng) -> String {
+    let letters: Vec<char> = ('a'..='z').collect();
+    let letters_dist = Uniform::from(0..letters.len());
+
+    (0..n).map(|_| letters[rng.sample(letters_dist)]).collect()
+}
+
+/// Generate a random float value, e.g. for a mock BED "score".
+fn generate_random_uniform(start: f64, end: f64, rng: &mut impl Rng) -> f64 {
+    let uniform = Uniform::new(start, end); // Specify the range
+    rng.sample(uniform)
+}
+
+/// Build a random [`GRanges`] using a set of sequence lengths, with BED5 like data,
+/// and the random number generator `rng`.
+pub fn random_granges_mock_bed5_with_rng(
+    seqlens: &IndexMap<String, Position>,
+    num: usize,
+    rng: &mut impl Rng,
+) -> Result<GRanges<VecRangesIndexed, Vec<Bed5Addition>>, GRangesError> {
+    let mut gr = GRanges::new_vec(seqlens);
+
+    let seqnames: Vec<String> = seqlens.keys().cloned().collect();
+    for _ in 0..num {
+        let seqname = seqnames.choose(rng).unwrap();
+        let chrom_len = *seqlens
+            .get(seqname)
+            .ok_or_else(|| GRangesError::missing_sequence(seqname.clone()))?;
+        let (start, end) = random_range(chrom_len, rng);
+        let bed5_cols = Bed5Addition {
+            name: generate_random_string(8, rng),
+            score: Some(generate_random_uniform(0.0, 1.0, rng)),
+        };
+        gr.push_range(seqname, start, end, bed5_cols)?;
+    }
+    Ok(gr)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{prelude::*, testing::temp_bedfile};
+    use std::io::Write;
+
+    #[test]
+    fn test_seed() {
+        let seed = seed();
+        assert!(set_seed(seed.wrapping_add(1)).is_err());
+        assert_eq!(seeded_rng().gen::<u64>(), seeded_rng().gen::<u64>());
+    }
+
+    #[test]
+    fn test_random_seeded() {
+        let seqlens = seqlens! { "chr1" => 10_000, "chr2" => 100 };
+        let generator = RandomRanges::new(&seqlens, WidthDistribution::Uniform(10, 50)).unwrap();
+        let gr = generator
+            .generate(100, &mut StdRng::seed_from_u64(1))
+            .unwrap();
+        let same = generator
+            .generate(100, &mut StdRng::seed_from_u64(1))
+            .unwrap();
+        assert_eq!(
+            gr.iter_ranges().collect::<Vec<_>>(),
+            same.iter_ranges().collect::<Vec<_>>()
+        );
+
+        for range in gr.iter_named_ranges() {
+            let width = range.end - range.start;
+            assert!((10..=50).contains(&width));
+            assert!(range.end <= seqlens[range.seqname]);
+        }
+    }
+
+    #[test]
+    fn test_random_exclude() {
+        let seqlens = seqlens! { "chr1" => 1000 };
+        let mut exclude = GRangesEmpty::new_vec(&seqlens);
+        exclude.push_range("chr1", 100, 900).unwrap();
+        let generator = RandomRanges::new(&seqlens, WidthDistribution::Fixed(50))
+            .unwrap()
+            .exclude(exclude.into_coitrees().unwrap());
+
+        let gr = generator
+            .generate(100, &mut StdRng::seed_from_u64(2))
+            .unwrap();
+        assert!(gr
+            .iter_ranges()
+            .all(|range| range.end <= 100 || range.start >= 900));
+
+        let too_wide = generator.clone().max_attempts(10);
+        let too_wide = RandomRanges {
+            widths: WidthDistribution::Fixed(200),
+            ..too_wide
+        };
+        assert!(matches!(
+            too_wide.generate(1, &mut StdRng::seed_from_u64(2)),
+            Err(GRangesError::RandomPlacementFailed(10))
+        ));
+    }
+
+    #[test]
+    fn test_random_widths() {
+        let seqlens = seqlens! { "chr1" => 1000 };
+        assert!(RandomRanges::new(&seqlens, WidthDistribution::Fixed(0)).is_err());
+        assert!(RandomRanges::new(&seqlens, WidthDistribution::Uniform(10, 5)).is_err());
+        assert!(RandomRanges::new(&seqlens, WidthDistribution::Empirical(vec![])).is_err());
+
+        let mut template = temp_bedfile();
+        writeln!(template, "chr1\t0\t10\nchr1\t5\t25").unwrap();
+        let widths = WidthDistribution::from_template(template.path()).unwrap();
+        assert_eq!(widths, WidthDistribution::Empirical(vec![10, 20]));
+
+        let generator = RandomRanges::new(&seqlens, widths).unwrap();
+        let gr = generator
+            .generate_bed6(20, &mut StdRng::seed_from_u64(3))
+            .unwrap();
+        assert_eq!(gr.len(), 20);
+        assert!(gr
+            .iter_ranges()
+            .all(|range| [10, 20].contains(&(range.end - range.start))));
+    }
+
+    #[test]
+    fn test_shuffle_matched() {
+        let seqlens = seqlens! { "chr1" => 1000 };
+        let ranges = vec![
+            GenomicRangeRecord::new("chr1".to_string(), 100, 150, "a"),
+            GenomicRangeRecord::new("chr1".to_string(), 800, 820, "b"),
+        ];
+        let generator = RandomRanges::new(&seqlens, WidthDistribution::Fixed(1)).unwrap();
+        // match the relative position of each range within 5%
+        let position = MatchedFeature::new(|_, start, _| Ok(Some(start as f64 / 1000.0)), 0.05);
+        let shuffled = generator
+            .shuffle_matched(&ranges, &[position], &mut StdRng::seed_from_u64(4))
+            .unwrap();
+        assert_eq!(shuffled.len(), 2);
+        for (range, original) in shuffled.iter().zip(&ranges) {
+            assert_eq!(range.data, original.data);
+            assert_eq!(range.end - range.start, original.end - original.start);
+            assert!(range.start.abs_diff(original.start) <= 50);
+        }
+
+        // an undefined feature of an original range isn't matched
+        let undefined = MatchedFeature::new(|_, _, _| Ok(None), 0.0);
+        let shuffled = generator
+            .shuffle_matched(&ranges, &[undefined], &mut StdRng::seed_from_u64(4))
+            .unwrap();
+        assert_eq!(shuffled.len(), 2);
+
+        // the only matching position is excluded
+        let mut exclude = GRangesEmpty::new_vec(&seqlens);
+        exclude.push_range("chr1", 50, 200).unwrap();
+        let generator = generator
+            .exclude(exclude.into_coitrees().unwrap())
+            .max_attempts(10);
+        let position = MatchedFeature::new(|_, start, _| Ok(Some(start as f64)), 0.0);
+        assert!(matches!(
+            generator.shuffle_matched(&ranges[..1], &[position], &mut StdRng::seed_from_u64(4)),
+            Err(GRangesError::MatchedPlacementFailed(10))
+        ));
+    }
+}
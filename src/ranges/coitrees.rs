@@ -7,7 +7,7 @@ use coitrees::{BasicCOITree, GenericInterval, Interval, IntervalNode, IntervalTr
 use crate::{
     error::GRangesError,
     traits::IterableRangeContainer,
-    traits::{GenericRange, RangeContainer},
+    traits::{GenericRange, OverlapQuery, RangeContainer},
     Position,
 };
 
@@ -120,6 +120,25 @@ impl<M: Clone> RangeContainer for COITrees<M> {
     }
 }
 
+/// The [`COITrees`] overlap-query backend.
+impl<M: Clone> OverlapQuery for COITrees<M>
+where
+    IntervalNode<M, usize>: GenericRange,
+{
+    type OverlapRangeType = IntervalNode<M, usize>;
+
+    fn query_overlaps<F>(&self, start: Position, end: Position, visit: F)
+    where
+        F: FnMut(&Self::OverlapRangeType),
+    {
+        self.query(start, end, visit)
+    }
+
+    fn count_overlaps(&self, start: Position, end: Position) -> usize {
+        COITrees::count_overlaps(self, start, end)
+    }
+}
+
 /// Convert between [`coitrees::Interval`] with index metadata to a [`RangeEmpty`].
 impl From<Interval<&()>> for RangeEmpty {
     fn from(value: Interval<&()>) -> Self {
@@ -245,7 +264,7 @@ mod tests {
 
     use crate::prelude::*;
     use crate::ranges::{RangeEmpty, RangeIndexed};
-    use crate::test_utilities::granges_test_case_01;
+    use crate::testing::granges_test_case_01;
 
     #[test]
     fn test_ranges_iterable_coitrees() {
@@ -0,0 +1,163 @@
+//! The [`Lapper<R>`] overlap-query backend, and the [`LapperIndexed`] and
+//! [`LapperEmpty`] type aliases.
+//!
+//! This is an implementation of the algorithm used by
+//! [rust-lapper](https://github.com/sstadick/rust-lapper): ranges are stored
+//! sorted by start position, along with the maximum range width. An overlap
+//! query binary searches for the first range that could overlap the query
+//! (i.e. one starting less than the maximum width before the query start) and
+//! scans forward. This is very fast when ranges have similar widths, but
+//! degrades when a few ranges are very wide.
+
+use super::{validate_range, vec::VecRanges, RangeEmpty, RangeIndexed};
+use crate::{
+    error::GRangesError,
+    traits::{GenericRange, IterableRangeContainer, OverlapQuery, RangeContainer},
+    Position,
+};
+
+pub type LapperIndexed = Lapper<RangeIndexed>;
+pub type LapperEmpty = Lapper<RangeEmpty>;
+
+/// A sorted-vector overlap-query range container for a single sequence's ranges.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Lapper<R: Clone> {
+    /// The ranges, sorted by start position.
+    pub(crate) ranges: Vec<R>,
+    /// The width of the widest range.
+    max_width: Position,
+    /// The sequence length, used to validate new ranges.
+    pub length: Position,
+}
+
+impl<R: Clone + GenericRange> Lapper<R> {
+    /// Validate a range, raising an error if it is invalid for some reason.
+    pub fn validate_range(&self, start: Position, end: Position) -> Result<(), GRangesError> {
+        validate_range(start, end, self.length)
+    }
+
+    /// Return the number of ranges in this [`Lapper`] container.
+    pub fn len(&self) -> usize {
+        self.ranges.len()
+    }
+
+    /// Return whether the [`Lapper`] object is empty (contains no ranges).
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Convert a [`VecRanges`] range container to a [`Lapper`] range container.
+impl<R: Clone + GenericRange> From<VecRanges<R>> for Lapper<R> {
+    fn from(value: VecRanges<R>) -> Self {
+        let mut ranges = value.ranges;
+        ranges.sort_by(|a, b| {
+            a.start()
+                .cmp(&b.start())
+                .then_with(|| a.end().cmp(&b.end()))
+                .then_with(|| a.index().cmp(&b.index()))
+        });
+        let max_width = ranges.iter().map(|range| range.width()).max().unwrap_or(0);
+        Self {
+            ranges,
+            max_width,
+            length: value.length,
+        }
+    }
+}
+
+/// Convert a [`Lapper`] range container to a (sorted) [`VecRanges`] range container.
+impl<R: Clone + GenericRange> From<Lapper<R>> for VecRanges<R> {
+    fn from(value: Lapper<R>) -> Self {
+        let mut ranges = VecRanges::new(value.length);
+        ranges.ranges = value.ranges;
+        ranges
+    }
+}
+
+impl<R: Clone + GenericRange> RangeContainer for Lapper<R> {
+    type InternalRangeType = R;
+    fn len(&self) -> usize {
+        self.ranges.len()
+    }
+    fn sequence_length(&self) -> Position {
+        self.length
+    }
+}
+
+impl<R: Clone + GenericRange> IterableRangeContainer for Lapper<R> {
+    type RangeType = R;
+    fn iter_ranges(&self) -> Box<dyn Iterator<Item = R> + '_> {
+        Box::new(self.ranges.iter().cloned())
+    }
+}
+
+impl<R: Clone + GenericRange> OverlapQuery for Lapper<R> {
+    type OverlapRangeType = R;
+
+    fn query_overlaps<F>(&self, start: Position, end: Position, mut visit: F)
+    where
+        F: FnMut(&Self::OverlapRangeType),
+    {
+        // No range starting at or before start - max_width can reach the query.
+        let first = self
+            .ranges
+            .partition_point(|range| range.start() + self.max_width <= start);
+        for range in &self.ranges[first..] {
+            if range.start() >= end {
+                break;
+            }
+            if range.end() > start {
+                visit(range);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::random_vecranges_indexed;
+
+    fn brute_force_overlaps(
+        ranges: &VecRanges<RangeIndexed>,
+        start: Position,
+        end: Position,
+    ) -> Vec<usize> {
+        let mut indices: Vec<usize> = ranges
+            .ranges
+            .iter()
+            .filter(|range| range.start < end && range.end > start)
+            .map(|range| range.index)
+            .collect();
+        indices.sort();
+        indices
+    }
+
+    #[test]
+    fn test_lapper_query() {
+        let mut ranges = VecRanges::new(100);
+        ranges.push_range(RangeIndexed::new(0, 50, 0));
+        ranges.push_range(RangeIndexed::new(10, 12, 1));
+        ranges.push_range(RangeIndexed::new(60, 70, 2));
+        let lapper = Lapper::from(ranges);
+
+        let mut hits = Vec::new();
+        lapper.query_overlaps(45, 61, |range| hits.push(range.index));
+        assert_eq!(hits, vec![0, 2]);
+        assert_eq!(lapper.count_overlaps(11, 12), 2);
+        assert_eq!(lapper.count_overlaps(50, 60), 0);
+    }
+
+    #[test]
+    fn test_lapper_random() {
+        let ranges = random_vecranges_indexed(500);
+        let lapper = Lapper::from(ranges.clone());
+        for (start, end) in [(0, 10), (1000, 5000), (40_000, 40_001), (0, ranges.length)] {
+            let mut hits = Vec::new();
+            lapper.query_overlaps(start, end, |range| hits.push(range.index));
+            hits.sort();
+            assert_eq!(hits, brute_force_overlaps(&ranges, start, end));
+        }
+    }
+}
@@ -7,12 +7,18 @@ use std::ops::Range;
 
 use crate::{
     error::GRangesError,
-    traits::{AdjustableGenericRange, GenericRange, GenericRangeOperations, IndexedDataContainer},
+    traits::{
+        AdjustableGenericRange, GenericRange, GenericRangeOperations, GenomicRecord,
+        IndexedDataContainer,
+    },
     Position,
 };
 
 pub mod coitrees;
+pub mod lapper;
+pub mod nclist;
 pub mod operations;
+pub mod soa;
 pub mod vec;
 
 #[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
@@ -93,7 +99,7 @@ impl From<RangeIndexed> for RangeEmpty {
 
 /// [`RangeIndexed`] is a range with a valid
 /// index to a data element in the data container.
-#[derive(Clone, Debug, Default, PartialEq)]
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
 pub struct RangeIndexed {
     pub start: Position,
     pub end: Position,
@@ -243,6 +249,12 @@ impl<U: Clone> GenericRange for GenomicRangeRecord<U> {
     }
 }
 
+impl<U: Clone> GenomicRecord for GenomicRangeRecord<U> {
+    fn seqname(&self) -> &str {
+        &self.seqname
+    }
+}
+
 impl<U: Clone> AdjustableGenericRange for GenomicRangeRecord<U> {
     fn set_start(&mut self, start: Position) {
         self.start = start
@@ -323,6 +335,12 @@ impl GenericRange for GenomicRangeRecordEmpty {
     }
 }
 
+impl GenomicRecord for GenomicRangeRecordEmpty {
+    fn seqname(&self) -> &str {
+        &self.seqname
+    }
+}
+
 impl AdjustableGenericRange for GenomicRangeRecordEmpty {
     fn set_start(&mut self, start: Position) {
         self.start = start
@@ -449,17 +467,69 @@ pub fn validate_range(
     length: Position,
 ) -> Result<(), GRangesError> {
     if start > end {
-        return Err(GRangesError::InvalidGenomicRange(start, end));
+        return Err(GRangesError::InvalidGenomicRange {
+            seqname: None,
+            start,
+            end,
+        });
     }
 
     if end >= length {
-        return Err(GRangesError::InvalidGenomicRangeForSequence(
-            start, end, length,
-        ));
+        return Err(GRangesError::InvalidGenomicRangeForSequence {
+            seqname: None,
+            start,
+            end,
+            length,
+        });
+    }
+    Ok(())
+}
+
+/// Check that a genomic range on sequence `seqname` is well-formed (its start is not
+/// after its end) and within the sequence's `length`, raising the same errors as
+/// ranges that fail these checks when parsed (see
+/// [`BoundsPolicy::Error`](crate::io::parsers::BoundsPolicy::Error)).
+pub fn check_genomic_range(
+    seqname: &str,
+    start: Position,
+    end: Position,
+    length: Position,
+) -> Result<(), GRangesError> {
+    if start > end {
+        return Err(GRangesError::InvalidGenomicRange {
+            seqname: Some(seqname.to_string()),
+            start,
+            end,
+        });
+    }
+    if end > length {
+        return Err(GRangesError::InvalidGenomicRangeForSequence {
+            seqname: Some(seqname.to_string()),
+            start,
+            end,
+            length,
+        });
     }
     Ok(())
 }
 
+/// The width of a parsed genomic record, or a [`GRangesError::InvalidGenomicRange`]
+/// error if its start is after its end. Parsing iterators (e.g.
+/// [`BedlikeIterator`](crate::io::BedlikeIterator)) don't check this, so code that
+/// uses the widths of unvalidated records should use this rather than
+/// [`GenericRange::width()`], which would underflow.
+pub fn checked_width<R: GenomicRecord>(record: &R) -> Result<Position, GRangesError> {
+    let (start, end) = (record.start(), record.end());
+    if start > end {
+        return Err(GRangesError::InvalidGenomicRange {
+            seqname: Some(record.seqname().to_string()),
+            start,
+            end,
+        });
+    }
+    Ok(end - start)
+}
+
 /// Try converting genome positions to an right-exclusive [`std::ops::Range`], with
 /// checking that the range is valid. This is predominantly used for building [`std::ops::Range`]
 /// items that are used to slice (e.g. nucleotide) sequences.
@@ -478,12 +548,19 @@ pub fn try_range(
     length: Position,
 ) -> Result<Range<usize>, GRangesError> {
     if start >= end {
-        return Err(GRangesError::InvalidGenomicRange(start, end));
+        return Err(GRangesError::InvalidGenomicRange {
+            seqname: None,
+            start,
+            end,
+        });
     }
     if end > length {
-        return Err(GRangesError::InvalidGenomicRangeForSequence(
-            start, end, length,
-        ));
+        return Err(GRangesError::InvalidGenomicRangeForSequence {
+            seqname: None,
+            start,
+            end,
+            length,
+        });
     }
     let start_usize: usize = start.try_into().unwrap();
     let end_usize: usize = end.try_into().unwrap();
@@ -492,7 +569,7 @@ pub fn try_range(
 
 #[cfg(test)]
 mod tests {
-    use super::{validate_range, RangeEmpty};
+    use super::{check_genomic_range, try_range, validate_range, RangeEmpty};
     use crate::prelude::*;
 
     #[test]
@@ -500,7 +577,52 @@ mod tests {
         let result = validate_range(5, 1, 10);
         assert!(matches!(
             result,
-            Err(GRangesError::InvalidGenomicRange(5, 1))
+            Err(GRangesError::InvalidGenomicRange {
+                seqname: None,
+                start: 5,
+                end: 1
+            })
+        ));
+    }
+
+    #[test]
+    fn test_check_genomic_range() {
+        assert!(check_genomic_range("chr1", 0, 10, 10).is_ok());
+        assert!(check_genomic_range("chr1", 5, 5, 10).is_ok());
+        assert!(matches!(
+            check_genomic_range("chr1", 5, 1, 10),
+            Err(GRangesError::InvalidGenomicRange {
+                start: 5,
+                end: 1,
+                ..
+            })
+        ));
+        assert!(matches!(
+            check_genomic_range("chr1", 5, 11, 10),
+            Err(GRangesError::InvalidGenomicRangeForSequence { length: 10, .. })
+        ));
+    }
+
+    #[test]
+    fn test_checked_width() {
+        let record = GenomicRangeRecordEmpty {
+            seqname: "chr1".to_string(),
+            start: 5,
+            end: 10,
+        };
+        assert_eq!(checked_width(&record).unwrap(), 5);
+        let record = GenomicRangeRecordEmpty {
+            seqname: "chr1".to_string(),
+            start: 10,
+            end: 5,
+        };
+        assert!(matches!(
+            checked_width(&record),
+            Err(GRangesError::InvalidGenomicRange {
+                start: 10,
+                end: 5,
+                ..
+            })
         ));
     }
 
@@ -515,10 +637,22 @@ mod tests {
         let result = validate_range(1, 10, 10);
         assert!(matches!(
             result,
-            Err(GRangesError::InvalidGenomicRangeForSequence(1, 10, 10))
+            Err(GRangesError::InvalidGenomicRangeForSequence {
+                seqname: None,
+                start: 1,
+                end: 10,
+                length: 10
+            })
         ));
     }
 
+    #[test]
+    fn test_invalid_range_context() {
+        let error = try_range(1, 20, 10).unwrap_err().with_seqname("chr1");
+        assert_eq!(error.seqname(), Some("chr1"));
+        assert!(error.to_string().contains(" on sequence 'chr1' "));
+    }
+
     #[test]
     fn test_overlap_range() {
         let range_a = RangeEmpty::new(5, 8);
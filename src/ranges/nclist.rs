@@ -0,0 +1,197 @@
+//! The [`NCList<R>`] overlap-query backend, and the [`NCListIndexed`] and
+//! [`NCListEmpty`] type aliases.
+//!
+//! A *nested containment list* ([Alekseyenko and Lee,
+//! 2007](https://doi.org/10.1093/bioinformatics/btl647)) splits ranges into
+//! nested sublists: each range that is contained by another range is stored in
+//! its container's sublist. Within each sublist no range contains another, so
+//! both start and end positions are sorted, and an overlap query can binary
+//! search each sublist. Unlike [`Lapper`], query time does not degrade when a
+//! few ranges are very wide.
+//!
+//! [`Lapper`]: crate::ranges::lapper::Lapper
+
+use super::{validate_range, vec::VecRanges, RangeEmpty, RangeIndexed};
+use crate::{
+    error::GRangesError,
+    traits::{GenericRange, IterableRangeContainer, OverlapQuery, RangeContainer},
+    Position,
+};
+
+pub type NCListIndexed = NCList<RangeIndexed>;
+pub type NCListEmpty = NCList<RangeEmpty>;
+
+/// A nested containment list range container for a single sequence's ranges.
+#[derive(Clone, Debug, PartialEq)]
+pub struct NCList<R: Clone> {
+    /// The ranges, sorted by start position and then by descending end position.
+    pub(crate) ranges: Vec<R>,
+    /// The sublists, as indices into `ranges`. The first sublist is the top-level list.
+    sublists: Vec<Vec<usize>>,
+    /// For each range, the index of the sublist of ranges it contains, if any.
+    children: Vec<Option<usize>>,
+    /// The sequence length, used to validate new ranges.
+    pub length: Position,
+}
+
+impl<R: Clone + GenericRange> NCList<R> {
+    /// Validate a range, raising an error if it is invalid for some reason.
+    pub fn validate_range(&self, start: Position, end: Position) -> Result<(), GRangesError> {
+        validate_range(start, end, self.length)
+    }
+
+    /// Return the number of ranges in this [`NCList`] container.
+    pub fn len(&self) -> usize {
+        self.ranges.len()
+    }
+
+    /// Return whether the [`NCList`] object is empty (contains no ranges).
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn query_sublist<F>(&self, sublist: usize, start: Position, end: Position, visit: &mut F)
+    where
+        F: FnMut(&R),
+    {
+        let list = &self.sublists[sublist];
+        // ends are sorted within a sublist, so skip the ranges ending before the query
+        let first = list.partition_point(|&i| self.ranges[i].end() <= start);
+        for &i in &list[first..] {
+            let range = &self.ranges[i];
+            if range.start() >= end {
+                break;
+            }
+            visit(range);
+            if let Some(child) = self.children[i] {
+                self.query_sublist(child, start, end, visit);
+            }
+        }
+    }
+}
+
+/// Convert a [`VecRanges`] range container to a [`NCList`] range container.
+impl<R: Clone + GenericRange> From<VecRanges<R>> for NCList<R> {
+    fn from(value: VecRanges<R>) -> Self {
+        let mut ranges = value.ranges;
+        ranges.sort_by(|a, b| {
+            a.start()
+                .cmp(&b.start())
+                .then_with(|| b.end().cmp(&a.end()))
+                .then_with(|| a.index().cmp(&b.index()))
+        });
+
+        let mut sublists = vec![Vec::new()];
+        let mut children = vec![None; ranges.len()];
+        // the stack of ranges containing the current range
+        let mut stack: Vec<usize> = Vec::new();
+        for i in 0..ranges.len() {
+            while let Some(&top) = stack.last() {
+                if ranges[i].end() <= ranges[top].end() {
+                    break;
+                }
+                stack.pop();
+            }
+            let sublist = match stack.last() {
+                None => 0,
+                Some(&parent) => *children[parent].get_or_insert_with(|| {
+                    sublists.push(Vec::new());
+                    sublists.len() - 1
+                }),
+            };
+            sublists[sublist].push(i);
+            stack.push(i);
+        }
+
+        Self {
+            ranges,
+            sublists,
+            children,
+            length: value.length,
+        }
+    }
+}
+
+/// Convert a [`NCList`] range container to a (sorted) [`VecRanges`] range container.
+impl<R: Clone + GenericRange> From<NCList<R>> for VecRanges<R> {
+    fn from(value: NCList<R>) -> Self {
+        let mut ranges = VecRanges::new(value.length);
+        ranges.ranges = value.ranges;
+        ranges.sort();
+        ranges
+    }
+}
+
+impl<R: Clone + GenericRange> RangeContainer for NCList<R> {
+    type InternalRangeType = R;
+    fn len(&self) -> usize {
+        self.ranges.len()
+    }
+    fn sequence_length(&self) -> Position {
+        self.length
+    }
+}
+
+impl<R: Clone + GenericRange> IterableRangeContainer for NCList<R> {
+    type RangeType = R;
+    fn iter_ranges(&self) -> Box<dyn Iterator<Item = R> + '_> {
+        Box::new(self.ranges.iter().cloned())
+    }
+}
+
+impl<R: Clone + GenericRange> OverlapQuery for NCList<R> {
+    type OverlapRangeType = R;
+
+    fn query_overlaps<F>(&self, start: Position, end: Position, mut visit: F)
+    where
+        F: FnMut(&Self::OverlapRangeType),
+    {
+        if !self.ranges.is_empty() {
+            self.query_sublist(0, start, end, &mut visit);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::random_vecranges_indexed;
+
+    #[test]
+    fn test_nclist_nested_query() {
+        let mut ranges = VecRanges::new(100);
+        ranges.push_range(RangeIndexed::new(0, 90, 0));
+        ranges.push_range(RangeIndexed::new(10, 20, 1));
+        ranges.push_range(RangeIndexed::new(12, 15, 2));
+        ranges.push_range(RangeIndexed::new(30, 40, 3));
+        ranges.push_range(RangeIndexed::new(85, 95, 4));
+        let nclist = NCList::from(ranges);
+        assert_eq!(nclist.sublists[0].len(), 2);
+
+        let mut hits = Vec::new();
+        nclist.query_overlaps(14, 31, |range| hits.push(range.index));
+        hits.sort();
+        assert_eq!(hits, vec![0, 1, 2, 3]);
+        assert_eq!(nclist.count_overlaps(89, 90), 2);
+        assert_eq!(nclist.count_overlaps(95, 100), 0);
+    }
+
+    #[test]
+    fn test_nclist_random() {
+        let ranges = random_vecranges_indexed(500);
+        let nclist = NCList::from(ranges.clone());
+        for (start, end) in [(0, 10), (1000, 5000), (40_000, 40_001), (0, ranges.length)] {
+            let mut hits = Vec::new();
+            nclist.query_overlaps(start, end, |range| hits.push(range.index));
+            hits.sort();
+            let mut expected: Vec<usize> = ranges
+                .ranges
+                .iter()
+                .filter(|range| range.start < end && range.end > start)
+                .map(|range| range.index)
+                .collect();
+            expected.sort();
+            assert_eq!(hits, expected);
+        }
+    }
+}
@@ -1,6 +1,8 @@
 //! Range operations.
 //!
-use crate::{traits::AdjustableGenericRange, Position, PositionOffset};
+use clap::ValueEnum;
+
+use crate::{error::GRangesError, traits::AdjustableGenericRange, Position, PositionOffset};
 
 /// Adjusts the start and end coordinates of a range, ensuring the adjusted range is
 /// within [0, length] and returning `None` if the range has zero width after adjustment.
@@ -30,10 +32,149 @@ pub fn adjust_range<R: AdjustableGenericRange>(
     }
 }
 
+/// What to do with a range that shrinking (i.e. adjusting with a negative offset)
+/// would invert, i.e. whose adjusted start would be at or past its adjusted end.
+///
+/// `bedtools slop` with negative `-l`/`-r` values never drops ranges: it outputs such
+/// ranges unadjusted, which corresponds to [`InvertedRangePolicy::Original`]. The
+/// default here, [`InvertedRangePolicy::Drop`], instead removes them, as zero-width
+/// ranges cannot be represented. Note that both tools clamp adjusted ranges to the
+/// sequence bounds first, so only ranges that shrink past themselves are affected.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum InvertedRangePolicy {
+    /// Remove the range.
+    #[default]
+    Drop,
+    /// Keep the range unadjusted (like `bedtools slop`).
+    Original,
+    /// Replace the range by the single basepair at its (original) midpoint. For a
+    /// zero-width range at the end of its sequence, this is the last basepair.
+    Midpoint,
+    /// Return a [`GRangesError::InvertedRange`] error.
+    Error,
+}
+
+/// Adjusts the start and end coordinates of a range like [`adjust_range()`], but handles
+/// ranges that would be inverted (or have zero width) according to `policy`. Returns
+/// `Ok(None)` if the range is dropped.
+pub fn adjust_range_with_policy<R: AdjustableGenericRange>(
+    mut range: R,
+    start_delta: PositionOffset,
+    end_delta: PositionOffset,
+    length: Position,
+    policy: InvertedRangePolicy,
+) -> Result<Option<R>, GRangesError> {
+    let (start, end) = (range.start(), range.end());
+    let new_start = (start as PositionOffset + start_delta).min(length as PositionOffset);
+    let new_end = (end as PositionOffset + end_delta).max(0);
+    if new_start < new_end {
+        return Ok(adjust_range(range, start_delta, end_delta, length));
+    }
+    match policy {
+        InvertedRangePolicy::Drop => Ok(None),
+        InvertedRangePolicy::Original => Ok(Some(range)),
+        InvertedRangePolicy::Midpoint => {
+            // keep the basepair within the sequence, e.g. for zero-width ranges at its end
+            let midpoint = (start + (end - start) / 2).min(length.saturating_sub(1));
+            range.set_start(midpoint);
+            range.set_end(midpoint + 1);
+            Ok(Some(range))
+        }
+        InvertedRangePolicy::Error => Err(GRangesError::InvertedRange {
+            seqname: None,
+            start,
+            end,
+            start_delta,
+            end_delta,
+        }),
+    }
+}
+
+/// Shifts a range by `shift` basepairs (leftwards if negative), without changing its
+/// width. Like `bedtools shift`, a range shifted past a sequence end is truncated at
+/// it, though at least one basepair of the range is kept at the boundary.
+pub fn shift_range<R: AdjustableGenericRange>(
+    mut range: R,
+    shift: PositionOffset,
+    length: Position,
+) -> R {
+    let length = length as PositionOffset;
+    let start = (range.start() as PositionOffset + shift)
+        .min(length - 1)
+        .max(0);
+    let end = (range.end() as PositionOffset + shift)
+        .min(length)
+        .max(start + 1);
+    range.set_start(start as Position);
+    range.set_end(end as Position);
+    range
+}
+
+/// Reduces a range to the single basepair at `center` (e.g. its midpoint, or a peak
+/// summit), expanded by `flank` basepairs on each side, and truncated at the sequence
+/// bounds.
+pub fn center_range<R: AdjustableGenericRange>(
+    mut range: R,
+    center: Position,
+    flank: Position,
+    length: Position,
+) -> R {
+    let start = center.saturating_sub(flank).min(length.saturating_sub(1));
+    let end = center.saturating_add(flank).saturating_add(1).min(length);
+    range.set_start(start);
+    range.set_end(end.max(start + 1));
+    range
+}
+
+/// Sort the `(start, end)` ranges, and merge overlapping and book-ended ranges.
+pub fn merge_intervals(mut ranges: Vec<(Position, Position)>) -> Vec<(Position, Position)> {
+    ranges.sort_unstable();
+    let mut merged: Vec<(Position, Position)> = Vec::with_capacity(ranges.len());
+    for (start, end) in ranges {
+        match merged.last_mut() {
+            Some((_, last_end)) if start <= *last_end => *last_end = (*last_end).max(end),
+            _ => merged.push((start, end)),
+        }
+    }
+    merged
+}
+
+/// The parts of the `universe` ranges not covered by any of the `ranges`, like
+/// `bedtools complement`. The `ranges` need not be sorted or disjoint, but the
+/// `universe` must be both (e.g. from [`merge_intervals()`]).
+pub fn complement_intervals(
+    ranges: Vec<(Position, Position)>,
+    universe: &[(Position, Position)],
+) -> Vec<(Position, Position)> {
+    let covered = merge_intervals(ranges);
+    let mut complement = Vec::new();
+    let mut first = 0;
+    for &(start, end) in universe {
+        // skip the covered ranges before this region; the rest may overlap it
+        while first < covered.len() && covered[first].1 <= start {
+            first += 1;
+        }
+        let mut position = start;
+        for &(covered_start, covered_end) in &covered[first..] {
+            if covered_start >= end {
+                break;
+            }
+            if covered_start > position {
+                complement.push((position, covered_start));
+            }
+            position = position.max(covered_end);
+        }
+        if position < end {
+            complement.push((position, end));
+        }
+    }
+    complement
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::ranges::RangeIndexed;
+    use crate::ranges::{GenomicRangeRecordEmpty, RangeIndexed};
 
     #[test]
     fn test_normal_adjustment() {
@@ -49,9 +190,110 @@ mod tests {
         assert_eq!(adjusted, RangeIndexed::new(5, 15, 2));
     }
 
+    #[test]
+    fn test_shift_range() {
+        let range = RangeIndexed::new(5, 10, 1);
+        assert_eq!(
+            shift_range(range.clone(), 3, 15),
+            RangeIndexed::new(8, 13, 1)
+        );
+        assert_eq!(
+            shift_range(range.clone(), -3, 15),
+            RangeIndexed::new(2, 7, 1)
+        );
+        // truncated at the sequence bounds
+        assert_eq!(
+            shift_range(range.clone(), 8, 15),
+            RangeIndexed::new(13, 15, 1)
+        );
+        assert_eq!(
+            shift_range(range.clone(), -7, 15),
+            RangeIndexed::new(0, 3, 1)
+        );
+        // shifted entirely past the bounds, keeping one basepair
+        assert_eq!(
+            shift_range(range.clone(), 20, 15),
+            RangeIndexed::new(14, 15, 1)
+        );
+        assert_eq!(shift_range(range, -20, 15), RangeIndexed::new(0, 1, 1));
+    }
+
+    #[test]
+    fn test_merge_intervals() {
+        let merged = merge_intervals(vec![(10, 20), (0, 5), (15, 30), (30, 35), (40, 45)]);
+        assert_eq!(merged, vec![(0, 5), (10, 35), (40, 45)]);
+    }
+
+    #[test]
+    fn test_complement_intervals() {
+        let ranges = vec![(30, 40), (10, 20), (15, 18)];
+        assert_eq!(
+            complement_intervals(ranges.clone(), &[(0, 50)]),
+            vec![(0, 10), (20, 30), (40, 50)]
+        );
+        assert_eq!(
+            complement_intervals(ranges.clone(), &[(15, 35), (45, 60)]),
+            vec![(20, 30), (45, 60)]
+        );
+        assert_eq!(complement_intervals(ranges, &[(10, 20)]), vec![]);
+        assert_eq!(complement_intervals(vec![], &[(0, 5)]), vec![(0, 5)]);
+    }
+
     #[test]
     fn test_zero_width_result() {
         let range = RangeIndexed::new(5, 10, 3);
         assert!(adjust_range(range, 5, -5, 15).is_none());
     }
+
+    #[test]
+    fn test_inverted_range_policy() {
+        let range = RangeIndexed::new(5, 10, 3);
+        let adjust = |policy| adjust_range_with_policy(range.clone(), 3, -3, 15, policy);
+        assert_eq!(adjust(InvertedRangePolicy::Drop).unwrap(), None);
+        assert_eq!(
+            adjust(InvertedRangePolicy::Original).unwrap(),
+            Some(range.clone())
+        );
+        assert_eq!(
+            adjust(InvertedRangePolicy::Midpoint).unwrap(),
+            Some(RangeIndexed::new(7, 8, 3))
+        );
+        assert!(matches!(
+            adjust(InvertedRangePolicy::Error),
+            Err(GRangesError::InvertedRange {
+                start: 5,
+                end: 10,
+                ..
+            })
+        ));
+
+        // shrinking that does not invert the range is unaffected by the policy
+        let adjusted = adjust_range_with_policy(range, 2, -2, 15, InvertedRangePolicy::Error);
+        assert_eq!(adjusted.unwrap(), Some(RangeIndexed::new(7, 8, 3)));
+
+        // the midpoint of a zero-width range at the sequence end is its last basepair
+        let range = GenomicRangeRecordEmpty {
+            seqname: "chr1".to_string(),
+            start: 15,
+            end: 15,
+        };
+        let adjusted =
+            adjust_range_with_policy(range, 0, 0, 15, InvertedRangePolicy::Midpoint).unwrap();
+        assert_eq!(
+            adjusted.map(|range| (range.start, range.end)),
+            Some((14, 15))
+        );
+    }
+
+    #[test]
+    fn test_center_range() {
+        let range = RangeIndexed::new(10, 21, 0);
+        let centered = center_range(range.clone(), 15, 0, 100);
+        assert_eq!(centered, RangeIndexed::new(15, 16, 0));
+        let centered = center_range(range.clone(), 12, 5, 100);
+        assert_eq!(centered, RangeIndexed::new(7, 18, 0));
+        // truncated at the sequence bounds
+        let centered = center_range(range, 12, 50, 30);
+        assert_eq!(centered, RangeIndexed::new(0, 30, 0));
+    }
 }
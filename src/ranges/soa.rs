@@ -0,0 +1,237 @@
+//! The [`SoaRanges<R>`] compact range container, and the [`SoaRangesIndexed`] and
+//! [`SoaRangesEmpty`] type aliases.
+//!
+//! [`VecRanges`] stores an array of range structs, and for [`RangeIndexed`] each
+//! range is padded to the alignment of its `usize` index: with the default 32-bit
+//! [`Position`], a range takes 16 bytes. [`SoaRanges`] instead stores a
+//! *struct of arrays*: one column of starts, one of ends, and one of 32-bit data
+//! indices, so a range takes 12 bytes (8 bytes without indices, for
+//! [`SoaRangesEmpty`]). The columns are public through [`SoaRanges::starts()`],
+//! [`SoaRanges::ends()`], and [`SoaRanges::indices()`], for direct column access
+//! (e.g. to compute widths, or pass them to other libraries without copying).
+//!
+//! The ranges are sorted by start position, and [`SoaRanges`] is also an
+//! overlap-query backend (see [`OverlapQuery`]), using the same algorithm as
+//! [`Lapper`](super::lapper::Lapper) on the start column.
+
+use std::marker::PhantomData;
+
+use super::{validate_range, vec::VecRanges, RangeEmpty, RangeIndexed};
+use crate::{
+    error::GRangesError,
+    traits::{GenericRange, IterableRangeContainer, OverlapQuery, RangeContainer},
+    Position,
+};
+
+pub type SoaRangesIndexed = SoaRanges<RangeIndexed>;
+pub type SoaRangesEmpty = SoaRanges<RangeEmpty>;
+
+/// The range types that can be stored in a [`SoaRanges`] container, and rebuilt from
+/// its columns.
+pub trait SoaRange: GenericRange + Clone {
+    /// Build a range from its start, end, and index (if this range type has one).
+    fn from_columns(start: Position, end: Position, index: Option<u32>) -> Self;
+}
+
+impl SoaRange for RangeIndexed {
+    fn from_columns(start: Position, end: Position, index: Option<u32>) -> Self {
+        let index = index.expect("Internal error: please report") as usize;
+        RangeIndexed { start, end, index }
+    }
+}
+
+impl SoaRange for RangeEmpty {
+    fn from_columns(start: Position, end: Position, _index: Option<u32>) -> Self {
+        RangeEmpty { start, end }
+    }
+}
+
+/// A compact, struct-of-arrays range container for a single sequence's ranges.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SoaRanges<R> {
+    starts: Vec<Position>,
+    ends: Vec<Position>,
+    /// The data indices, which are empty for range types without them.
+    indices: Vec<u32>,
+    /// The width of the widest range.
+    max_width: Position,
+    /// The sequence length, used to validate new ranges.
+    pub length: Position,
+    phantom: PhantomData<R>,
+}
+
+impl<R: SoaRange> SoaRanges<R> {
+    /// Validate a range, raising an error if it is invalid for some reason.
+    pub fn validate_range(&self, start: Position, end: Position) -> Result<(), GRangesError> {
+        validate_range(start, end, self.length)
+    }
+
+    /// Return the number of ranges in this [`SoaRanges`] container.
+    pub fn len(&self) -> usize {
+        self.starts.len()
+    }
+
+    /// Return whether the [`SoaRanges`] object is empty (contains no ranges).
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The start positions of the ranges, in sorted order.
+    pub fn starts(&self) -> &[Position] {
+        &self.starts
+    }
+
+    /// The end positions of the ranges, in the same order as [`SoaRanges::starts()`].
+    pub fn ends(&self) -> &[Position] {
+        &self.ends
+    }
+
+    /// The data indices of the ranges, in the same order as [`SoaRanges::starts()`].
+    /// This is empty for [`SoaRangesEmpty`].
+    pub fn indices(&self) -> &[u32] {
+        &self.indices
+    }
+
+    /// Return the `i`-th range, or `None` if it is out of bounds.
+    pub fn get(&self, i: usize) -> Option<R> {
+        let start = *self.starts.get(i)?;
+        Some(R::from_columns(
+            start,
+            self.ends[i],
+            self.indices.get(i).copied(),
+        ))
+    }
+}
+
+/// Convert a [`VecRanges`] range container to a (sorted) [`SoaRanges`] range
+/// container. This fails if a data index does not fit in 32 bits.
+impl<R: SoaRange> TryFrom<VecRanges<R>> for SoaRanges<R> {
+    type Error = GRangesError;
+
+    fn try_from(mut value: VecRanges<R>) -> Result<Self, Self::Error> {
+        value.sort();
+        let num_ranges = value.len();
+        let mut ranges = SoaRanges {
+            starts: Vec::with_capacity(num_ranges),
+            ends: Vec::with_capacity(num_ranges),
+            indices: Vec::new(),
+            max_width: 0,
+            length: value.length,
+            phantom: PhantomData,
+        };
+        for range in value.ranges {
+            if let Some(index) = range.index() {
+                let index =
+                    u32::try_from(index).map_err(|_| GRangesError::CompactIndexOverflow(index))?;
+                ranges.indices.push(index);
+            }
+            ranges.max_width = ranges.max_width.max(range.width());
+            ranges.starts.push(range.start());
+            ranges.ends.push(range.end());
+        }
+        Ok(ranges)
+    }
+}
+
+/// Convert a [`SoaRanges`] range container to a (sorted) [`VecRanges`] range container.
+impl<R: SoaRange> From<SoaRanges<R>> for VecRanges<R> {
+    fn from(value: SoaRanges<R>) -> Self {
+        let mut ranges = VecRanges::new(value.length);
+        ranges.ranges = (0..value.len())
+            .map(|i| value.get(i).expect("Internal error: please report"))
+            .collect();
+        ranges
+    }
+}
+
+impl<R: SoaRange> RangeContainer for SoaRanges<R> {
+    type InternalRangeType = R;
+    fn len(&self) -> usize {
+        self.starts.len()
+    }
+    fn sequence_length(&self) -> Position {
+        self.length
+    }
+}
+
+impl<R: SoaRange> IterableRangeContainer for SoaRanges<R> {
+    type RangeType = R;
+    fn iter_ranges(&self) -> Box<dyn Iterator<Item = R> + '_> {
+        Box::new((0..self.len()).map(|i| self.get(i).expect("Internal error: please report")))
+    }
+}
+
+impl<R: SoaRange> OverlapQuery for SoaRanges<R> {
+    type OverlapRangeType = R;
+
+    fn query_overlaps<F>(&self, start: Position, end: Position, mut visit: F)
+    where
+        F: FnMut(&Self::OverlapRangeType),
+    {
+        // No range starting at or before start - max_width can reach the query.
+        let first = self
+            .starts
+            .partition_point(|&range_start| range_start + self.max_width <= start);
+        for (i, &range_start) in self.starts.iter().enumerate().skip(first) {
+            if range_start >= end {
+                break;
+            }
+            if self.ends[i] > start {
+                visit(&R::from_columns(
+                    range_start,
+                    self.ends[i],
+                    self.indices.get(i).copied(),
+                ));
+            }
+        }
+    }
+
+    fn count_overlaps(&self, start: Position, end: Position) -> usize {
+        let first = self
+            .starts
+            .partition_point(|&range_start| range_start + self.max_width <= start);
+        self.starts[first..]
+            .iter()
+            .zip(&self.ends[first..])
+            .take_while(|(&range_start, _)| range_start < end)
+            .filter(|(_, &range_end)| range_end > start)
+            .count()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_soa_ranges() {
+        let mut ranges = VecRanges::new(100);
+        ranges.push_range(RangeIndexed::new(60, 70, 2));
+        ranges.push_range(RangeIndexed::new(0, 50, 0));
+        ranges.push_range(RangeIndexed::new(10, 12, 1));
+        let soa = SoaRanges::try_from(ranges.clone()).unwrap();
+        assert_eq!(soa.starts(), &[0, 10, 60]);
+        assert_eq!(soa.ends(), &[50, 12, 70]);
+        assert_eq!(soa.indices(), &[0, 1, 2]);
+        assert_eq!(soa.get(1), Some(RangeIndexed::new(10, 12, 1)));
+        assert_eq!(soa.get(3), None);
+
+        let mut hits = Vec::new();
+        soa.query_overlaps(45, 61, |range| hits.push(range.index));
+        assert_eq!(hits, vec![0, 2]);
+        assert_eq!(soa.count_overlaps(11, 12), 2);
+        assert_eq!(soa.count_overlaps(50, 60), 0);
+
+        ranges.sort();
+        assert_eq!(VecRanges::from(soa), ranges);
+
+        let mut empty = VecRanges::new(100);
+        empty.push_range(RangeEmpty::new(5, 10));
+        let soa = SoaRangesEmpty::try_from(empty).unwrap();
+        assert!(soa.indices().is_empty());
+        assert_eq!(
+            soa.iter_ranges().collect::<Vec<_>>(),
+            vec![RangeEmpty::new(5, 10)]
+        );
+    }
+}
@@ -7,11 +7,12 @@ use crate::traits::{
 };
 use crate::PositionOffset;
 use crate::{error::GRangesError, traits::RangeContainer, Position};
+use serde::{Deserialize, Serialize};
 
 pub type VecRangesIndexed = VecRanges<RangeIndexed>;
 pub type VecRangesEmpty = VecRanges<RangeEmpty>;
 
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
 pub struct VecRanges<R: Clone> {
     pub(crate) ranges: Vec<R>,
     pub length: Position,
@@ -6,10 +6,27 @@
 //! tools to report information about potentially fragile operations, or inform
 //! them of e.g. how many ranges were filtered out by some operation.
 //!
+//! A [`Report`] collects processing statistics (the number of records read,
+//! written, skipped, and truncated at sequence ends) and warnings for a command.
+//! The `granges` command line tool prints this report with `--report text` or
+//! `--report json`. For long-running commands, a [`ProgressIterator`] can also
+//! report how many records have been processed so far, when enabled with
+//! [`set_progress()`] (or `granges --progress`).
+
+use std::{
+    collections::BTreeMap,
+    io::{IsTerminal, Write},
+    sync::atomic::{AtomicBool, Ordering},
+    time::Instant,
+};
+
+use clap::ValueEnum;
+use serde::Serialize;
+
+use crate::error::GRangesError;
 
 /// The [`CommandOutput<U>`] type output is generic over some data output
 /// from a command, and a [`Report`] that reports information to the user.
-#[allow(unused)]
 pub struct CommandOutput<U> {
     value: U,
     report: Option<Report>,
@@ -19,11 +36,34 @@ impl<U> CommandOutput<U> {
     pub fn new(value: U, report: Option<Report>) -> Self {
         Self { value, report }
     }
+
+    /// Get the value output by the command.
+    pub fn value(&self) -> &U {
+        &self.value
+    }
+
+    /// Get the [`Report`] of the command, if there is one.
+    pub fn report(&self) -> Option<&Report> {
+        self.report.as_ref()
+    }
 }
 
 /// A type to (semi) standardize reporting to the user.
-#[derive(Default)]
+#[derive(Clone, Debug, Default, Serialize)]
 pub struct Report {
+    /// The number of records (e.g. ranges) read from input files.
+    pub records_read: usize,
+    /// The number of records written to the output.
+    pub records_written: usize,
+    /// The number of records that were skipped (e.g. removed by an operation).
+    pub records_skipped: usize,
+    /// The number of ranges that were truncated at sequence ends.
+    pub ranges_truncated: usize,
+    /// Statistics computed by the operation (e.g. a p-value), by name.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    statistics: BTreeMap<String, f64>,
+    /// Warnings about potential issues with the operation.
+    #[serde(rename = "warnings")]
     entries: Vec<String>,
 }
 
@@ -32,7 +72,179 @@ impl Report {
         Self::default()
     }
 
+    /// Create a new [`Report`] for an operation that read `records_read` records, and
+    /// wrote `records_written` of them (the rest are counted as skipped).
+    pub fn with_counts(records_read: usize, records_written: usize) -> Self {
+        Self {
+            records_read,
+            records_written,
+            records_skipped: records_read.saturating_sub(records_written),
+            ..Self::default()
+        }
+    }
+
     pub fn add_issue(&mut self, message: String) {
         self.entries.push(message)
     }
+
+    /// Add a statistic computed by the operation (replacing any with the same name).
+    pub fn add_statistic(&mut self, name: impl Into<String>, value: f64) {
+        self.statistics.insert(name.into(), value);
+    }
+
+    /// Get the statistics computed by the operation, by name.
+    pub fn statistics(&self) -> &BTreeMap<String, f64> {
+        &self.statistics
+    }
+
+    /// Get the warnings about potential issues with the operation.
+    pub fn issues(&self) -> &[String] {
+        &self.entries
+    }
+
+    /// Write this report in the specified format.
+    pub fn write(&self, mut writer: impl Write, format: &ReportFormat) -> Result<(), GRangesError> {
+        match format {
+            ReportFormat::Text => {
+                writeln!(writer, "records read:      {}", self.records_read)?;
+                writeln!(writer, "records written:   {}", self.records_written)?;
+                writeln!(writer, "records skipped:   {}", self.records_skipped)?;
+                writeln!(writer, "ranges truncated:  {}", self.ranges_truncated)?;
+                for (name, value) in &self.statistics {
+                    writeln!(writer, "{:<19}{}", format!("{}:", name), value)?;
+                }
+                for issue in &self.entries {
+                    writeln!(writer, "warning: {}", issue)?;
+                }
+            }
+            ReportFormat::Json => {
+                serde_json::to_writer_pretty(&mut writer, self)
+                    .map_err(|e| GRangesError::ReportError(e.to_string()))?;
+                writeln!(writer)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The formats a [`Report`] can be written in.
+#[derive(Clone, Debug, ValueEnum)]
+pub enum ReportFormat {
+    /// A human-readable summary.
+    Text,
+    /// A machine-readable JSON object.
+    Json,
+}
+
+static PROGRESS: AtomicBool = AtomicBool::new(false);
+
+/// Enable or disable progress reporting by [`ProgressIterator`]s. Progress is only
+/// ever shown when standard error is a terminal.
+pub fn set_progress(enabled: bool) {
+    PROGRESS.store(enabled, Ordering::Relaxed);
+}
+
+/// How often (in records) progress is updated.
+const PROGRESS_INTERVAL: usize = 1 << 16;
+
+/// An iterator adapter that counts the items of the inner iterator, and (if enabled
+/// with [`set_progress()`]) shows a running count on standard error.
+pub struct ProgressIterator<I> {
+    inner: I,
+    label: &'static str,
+    count: usize,
+    show: bool,
+    started: Instant,
+}
+
+impl<I> ProgressIterator<I> {
+    pub fn new(inner: I, label: &'static str) -> Self {
+        Self {
+            inner,
+            label,
+            count: 0,
+            show: PROGRESS.load(Ordering::Relaxed) && std::io::stderr().is_terminal(),
+            started: Instant::now(),
+        }
+    }
+
+    /// The number of items that have been iterated over.
+    pub fn num_items(&self) -> usize {
+        self.count
+    }
+
+    fn show_progress(&self) {
+        let elapsed = self.started.elapsed().as_secs_f64();
+        eprint!(
+            "\r{}: {} records ({:.0} records/s)",
+            self.label,
+            self.count,
+            self.count as f64 / elapsed.max(f64::EPSILON)
+        );
+    }
+}
+
+impl<I: Iterator> Iterator for ProgressIterator<I> {
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let item = self.inner.next();
+        if item.is_some() {
+            self.count += 1;
+            if self.show && self.count.is_multiple_of(PROGRESS_INTERVAL) {
+                self.show_progress();
+            }
+        }
+        item
+    }
+}
+
+impl<I> Drop for ProgressIterator<I> {
+    fn drop(&mut self) {
+        if self.show && self.count >= PROGRESS_INTERVAL {
+            self.show_progress();
+            eprintln!();
+        }
+    }
+}
+
+/// Extends iterators with the [`ProgressExt::progress()`] adapter.
+pub trait ProgressExt: Iterator + Sized {
+    /// Wrap this iterator in a [`ProgressIterator`], labeling its progress with `label`.
+    fn progress(self, label: &'static str) -> ProgressIterator<Self> {
+        ProgressIterator::new(self, label)
+    }
+}
+
+impl<I: Iterator> ProgressExt for I {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_report_json() {
+        let mut report = Report::new();
+        report.records_read = 10;
+        report.records_written = 8;
+        report.add_issue("2 ranges were removed".to_string());
+
+        let mut json = Vec::new();
+        report.write(&mut json, &ReportFormat::Json).unwrap();
+        let value: serde_json::Value = serde_json::from_slice(&json).unwrap();
+        assert_eq!(value["records_read"], 10);
+        assert_eq!(value["records_written"], 8);
+        assert_eq!(value["warnings"][0], "2 ranges were removed");
+        assert!(value.get("statistics").is_none());
+
+        report.add_statistic("p_value", 0.01);
+        let mut json = Vec::new();
+        report.write(&mut json, &ReportFormat::Json).unwrap();
+        let value: serde_json::Value = serde_json::from_slice(&json).unwrap();
+        assert_eq!(value["statistics"]["p_value"], 0.01);
+
+        let mut iter = (0..5).progress("test");
+        iter.by_ref().for_each(drop);
+        assert_eq!(iter.num_items(), 5);
+    }
 }
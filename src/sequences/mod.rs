@@ -17,6 +17,9 @@
 //!    (`LazyNumericSequences1` will be added soon, please file a [GitHub
 //!    issue](https://github.com]/vsbuffalo/granges/issues) if you need it).
 //!
+//!  - Run-length encoded per-basepair data (e.g. coverage), with arithmetic between
+//!    tracks and summaries over ranges, with [`rle::Rle`] and [`rle::RleList`].
+//!
 //!
 //! [`Sequences`]: crate::traits::Sequences
 //! [`LazyLoader`]: crate::sequences::lazy
@@ -26,3 +29,6 @@ pub mod lazy;
 pub mod nucleotide;
 #[cfg(feature = "ndarray")]
 pub mod numeric;
+#[cfg(feature = "remote")]
+pub mod refget;
+pub mod rle;
@@ -57,6 +57,12 @@ impl From<String> for Nucleotides {
     }
 }
 
+impl From<Vec<u8>> for Nucleotides {
+    fn from(seq: Vec<u8>) -> Self {
+        Nucleotides(Bytes::from(seq))
+    }
+}
+
 impl<'a> From<&'a str> for Nucleotides {
     fn from(s: &'a str) -> Self {
         let bytes = Bytes::from(s.as_bytes().to_vec());
@@ -151,7 +157,7 @@ impl Sequences for NucleotideSequences {
     fn get_sequence(&self, seqname: &str) -> Result<Self::Container<'_>, GRangesError> {
         self.data
             .get(seqname)
-            .ok_or(GRangesError::MissingSequenceName(seqname.to_string()))
+            .ok_or(GRangesError::missing_sequence(seqname))
     }
 
     /// Apply an arbitrary function to the specified region.
@@ -172,7 +178,8 @@ impl Sequences for NucleotideSequences {
         F: Fn(Self::Slice<'_>, (&str, Position, Position)) -> V,
     {
         let seq = self.get_sequence(seqname)?;
-        let range = try_range(start, end, seq.len().try_into().unwrap())?;
+        let range = try_range(start, end, seq.len().try_into().unwrap())
+            .map_err(|error| error.with_seqname(seqname))?;
         let data = &seq[range];
         Ok(func(data, (&seqname, start, end)))
     }
@@ -243,7 +250,7 @@ impl LazyNucleotideSequences {
                 let nucs: Nucleotides = seq.into();
                 Ok(nucs)
             } else {
-                Err(GRangesError::MissingSequenceName(seqname.to_string()))
+                Err(GRangesError::missing_sequence(seqname))
             }
         });
 
@@ -306,7 +313,8 @@ impl Sequences for LazyNucleotideSequences {
         F: for<'b> Fn(&'b [u8], (&str, Position, Position)) -> V,
     {
         let seq = self.get_sequence(seqname)?;
-        let range = try_range(start, end, seq.len().try_into().unwrap())?;
+        let range = try_range(start, end, seq.len().try_into().unwrap())
+            .map_err(|error| error.with_seqname(seqname))?;
         Ok(func(&seq[range], (seqname, start, end)))
     }
 
@@ -314,7 +322,7 @@ impl Sequences for LazyNucleotideSequences {
     fn get_sequence_length(&self, seqname: &str) -> Result<Position, GRangesError> {
         self.seqlens
             .get(seqname)
-            .ok_or(GRangesError::MissingSequenceName(seqname.to_string()))
+            .ok_or(GRangesError::missing_sequence(seqname))
             .copied()
     }
 }
@@ -26,7 +26,7 @@ use crate::Position;
 ///
 /// ```
 /// use granges::sequences::numeric::NumericSequences1;
-/// use granges::test_utilities::random_array1_sequences;
+/// use granges::testing::random_array1_sequences;
 ///
 /// let mut data = random_array1_sequences(100);
 /// let numeric_seq = NumericSequences1::new(data);
@@ -47,7 +47,7 @@ impl<T> NumericSequences1<T> {
     /// ```
     /// use ndarray::Array1;
     /// use granges::sequences::numeric::NumericSequences1;
-    /// use granges::test_utilities::random_array1_sequences;
+    /// use granges::testing::random_array1_sequences;
     ///
     /// let mut data = random_array1_sequences(100);
     /// let numeric_seq = NumericSequences1::new(data);
@@ -73,7 +73,7 @@ where
         let seq = self
             .data
             .get(seqname)
-            .ok_or(GRangesError::MissingSequence(seqname.to_string()))?;
+            .ok_or(GRangesError::missing_sequence(seqname))?;
         Ok(seq)
     }
 
@@ -92,7 +92,8 @@ where
         F: for<'b> Fn(Self::Slice<'b>, (&str, Position, Position)) -> V,
     {
         let seq = self.get_sequence(seqname)?;
-        let range = try_range(start, end, seq.len().try_into().unwrap())?;
+        let range = try_range(start, end, seq.len().try_into().unwrap())
+            .map_err(|error| error.with_seqname(seqname))?;
         let view = seq.slice(s![range]);
         Ok(func(view, (seqname, start, end)))
     }
@@ -119,7 +120,7 @@ impl<T> NumericSequences2<T> {
     ///
     /// ```
     /// use granges::sequences::numeric::NumericSequences2;
-    /// use granges::test_utilities::random_array2_sequences;
+    /// use granges::testing::random_array2_sequences;
     ///
     /// let data = random_array2_sequences(20);
     /// let numeric_seq = NumericSequences2::new(data);
@@ -148,7 +149,7 @@ where
     /// ```
     /// use crate::granges::traits::Sequences;
     /// use granges::sequences::numeric::NumericSequences1;
-    /// use granges::test_utilities::random_array1_sequences;
+    /// use granges::testing::random_array1_sequences;
     ///
     /// let data = random_array1_sequences(20);
     /// let numeric_seq = NumericSequences1::new(data);
@@ -174,7 +175,7 @@ where
     /// ```
     /// use granges::sequences::numeric::NumericSequences1;
     /// use crate::granges::traits::Sequences;
-    /// use granges::test_utilities::random_array1_sequences;
+    /// use granges::testing::random_array1_sequences;
     ///
     /// let mut data = random_array1_sequences(100);
     /// let numeric_seq = NumericSequences1::new(data);
@@ -184,7 +185,7 @@ where
         let seq = self
             .data
             .get(seqname)
-            .ok_or(GRangesError::MissingSequence(seqname.to_string()))?;
+            .ok_or(GRangesError::missing_sequence(seqname))?;
         Ok(seq)
     }
 
@@ -207,7 +208,7 @@ where
     /// use crate::granges::prelude::Position;
     /// use crate::granges::traits::Sequences;
     /// use granges::sequences::numeric::NumericSequences1;
-    /// use granges::test_utilities::random_array1_sequences;
+    /// use granges::testing::random_array1_sequences;
     /// use ndarray::ArrayView1;
     ///
     ///
@@ -231,7 +232,8 @@ where
         F: for<'b> Fn(Self::Slice<'_>, (&str, Position, Position)) -> V,
     {
         let seq = self.get_sequence(seqname)?;
-        let range = try_range(start, end, seq.len().try_into().unwrap())?;
+        let range = try_range(start, end, seq.len().try_into().unwrap())
+            .map_err(|error| error.with_seqname(seqname))?;
         let seq = self.get_sequence(seqname)?;
         let view = seq.slice(s![range, ..]);
         Ok(func(view, (seqname, start, end)))
@@ -253,7 +255,7 @@ where
     /// ```
     /// use granges::sequences::numeric::NumericSequences1;
     /// use crate::granges::traits::Sequences;
-    /// use granges::test_utilities::random_array1_sequences;
+    /// use granges::testing::random_array1_sequences;
     ///
     /// let data = random_array1_sequences(20);
     /// let numeric_seq = NumericSequences1::new(data);
@@ -359,7 +361,8 @@ where
         F: for<'b> Fn(ArrayView2<'b, T>, (&str, Position, Position)) -> V,
     {
         let seq = self.get_sequence(seqname)?;
-        let range = try_range(start, end, seq.len().try_into().unwrap())?;
+        let range = try_range(start, end, seq.len().try_into().unwrap())
+            .map_err(|error| error.with_seqname(seqname))?;
         let view = seq.slice(s![range, ..]);
         let value = func(view, (seqname, start, end));
         Ok(value)
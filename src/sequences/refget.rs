@@ -0,0 +1,203 @@
+//! Reference sequences resolved remotely by their checksums, from a GA4GH
+//! [refget](https://samtools.github.io/hts-specs/refget.html) server.
+//!
+//! [`RefgetSequences`] implements [`Sequences`], like the local
+//! [`NucleotideSequences`](super::nucleotide::NucleotideSequences), but only
+//! downloads the regions that are accessed (through [`Sequences::region_map()`]),
+//! so no local FASTA copy is needed. Sequences are identified by a checksum (e.g.
+//! an MD5 or `ga4gh:SQ.` digest) that the server recognizes.
+//!
+//! This requires the `remote` feature.
+
+use indexmap::IndexMap;
+use std::io::{self, BufRead, Read};
+use std::path::PathBuf;
+
+use super::nucleotide::Nucleotides;
+use crate::{error::GRangesError, io::InputStream, ranges::try_range, traits::Sequences, Position};
+
+/// Nucleotide sequences downloaded by their checksums from a refget server.
+#[derive(Clone, Debug)]
+pub struct RefgetSequences {
+    server: String,
+    checksums: IndexMap<String, String>,
+    seqlens: IndexMap<String, Position>,
+}
+
+impl RefgetSequences {
+    /// Create a new [`RefgetSequences`] from the refget `server` URL (e.g.
+    /// `https://www.ebi.ac.uk/ena/cram`), and the checksums of each sequence
+    /// name. This requests each sequence's length from the server.
+    pub fn new(
+        server: impl Into<String>,
+        checksums: IndexMap<String, String>,
+    ) -> Result<Self, GRangesError> {
+        let server = server.into().trim_end_matches('/').to_string();
+        let mut seqlens = IndexMap::new();
+        for (seqname, checksum) in &checksums {
+            let url = format!("{}/sequence/{}/metadata", server, checksum);
+            let length = parse_metadata_length(&get(&url)?)
+                .ok_or_else(|| GRangesError::RefgetError(url, "no length".to_string()))?;
+            seqlens.insert(seqname.clone(), length);
+        }
+        Ok(Self {
+            server,
+            checksums,
+            seqlens,
+        })
+    }
+
+    /// Create a new [`RefgetSequences`] from the refget `server` URL, and a TSV file
+    /// of sequence names and their checksums.
+    pub fn from_file(
+        server: impl Into<String>,
+        filepath: impl Into<PathBuf>,
+    ) -> Result<Self, GRangesError> {
+        let reader = InputStream::new(filepath).reader()?;
+        let mut checksums = IndexMap::new();
+        for result in reader.lines() {
+            let line = result?;
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (seqname, checksum) = line
+                .split_once('\t')
+                .ok_or_else(|| GRangesError::InvalidGenomeFile(line.clone()))?;
+            checksums.insert(seqname.to_string(), checksum.trim().to_string());
+        }
+        Self::new(server, checksums)
+    }
+
+    /// Download the region `[start, end)` of sequence `seqname`, or the whole
+    /// sequence if no region is given.
+    fn fetch(
+        &self,
+        seqname: &str,
+        region: Option<(Position, Position)>,
+    ) -> Result<Nucleotides, GRangesError> {
+        let checksum = self
+            .checksums
+            .get(seqname)
+            .ok_or(GRangesError::missing_sequence(seqname))?;
+        let mut url = format!("{}/sequence/{}", self.server, checksum);
+        if let Some((start, end)) = region {
+            url.push_str(&format!("?start={}&end={}", start, end));
+        }
+        Ok(Nucleotides::from(get(&url)?))
+    }
+}
+
+/// Make a GET request, returning the response body.
+fn get(url: &str) -> Result<Vec<u8>, GRangesError> {
+    let error =
+        |err: &dyn std::fmt::Display| GRangesError::RefgetError(url.to_string(), err.to_string());
+    let response = ureq::get(url).call().map_err(|err| error(&err))?;
+    read_body(response.into_reader()).map_err(|err| error(&err))
+}
+
+/// Read an entire response body. Unlike ureq's `into_string()`, which fails on
+/// bodies over 10 MB, this has no size limit, so whole chromosomes can be downloaded.
+fn read_body(mut reader: impl Read) -> io::Result<Vec<u8>> {
+    let mut body = Vec::new();
+    reader.read_to_end(&mut body)?;
+    Ok(body)
+}
+
+/// Parse the sequence length from a refget metadata response, e.g.
+/// `{"metadata": {"md5": "...", "length": 248956422, ...}}`.
+fn parse_metadata_length(response: &[u8]) -> Option<Position> {
+    let json: serde_json::Value = serde_json::from_slice(response).ok()?;
+    let length = json.get("metadata")?.get("length")?.as_u64()?;
+    length.try_into().ok()
+}
+
+impl Sequences for RefgetSequences {
+    type Container<'a> = Nucleotides;
+    type Slice<'a> = &'a [u8];
+
+    /// Retrieve all sequence names.
+    fn seqnames(&self) -> Vec<String> {
+        self.seqlens.keys().cloned().collect()
+    }
+
+    /// Download the [`Nucleotides`] of an entire sequence.
+    fn get_sequence(&self, seqname: &str) -> Result<Self::Container<'_>, GRangesError> {
+        self.fetch(seqname, None)
+    }
+
+    /// Apply an arbitrary function to the specified region, downloading only
+    /// this region.
+    fn region_map<V, F>(
+        &self,
+        func: &F,
+        seqname: &str,
+        start: Position,
+        end: Position,
+    ) -> Result<V, GRangesError>
+    where
+        F: Fn(Self::Slice<'_>, (&str, Position, Position)) -> V,
+    {
+        // validate the range before making a request
+        try_range(start, end, self.get_sequence_length(seqname)?)
+            .map_err(|error| error.with_seqname(seqname))?;
+        let seq = self.fetch(seqname, Some((start, end)))?;
+        Ok(func(&seq, (seqname, start, end)))
+    }
+
+    /// Get the length of a particular sequence.
+    fn get_sequence_length(&self, seqname: &str) -> Result<Position, GRangesError> {
+        self.seqlens
+            .get(seqname)
+            .copied()
+            .ok_or(GRangesError::missing_sequence(seqname))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufReader, Write};
+    use std::net::TcpListener;
+    use std::thread;
+
+    #[test]
+    fn test_parse_metadata_length() {
+        let response = br#"{"metadata": {"md5": "6aef897c3d6ff0c78aff06ac189178dd",
+                           "length": 248956422, "aliases": []}}"#;
+        assert_eq!(parse_metadata_length(response), Some(248956422));
+        assert_eq!(parse_metadata_length(br#"{"metadata": {}}"#), None);
+        assert_eq!(parse_metadata_length(b"not found"), None);
+    }
+
+    #[test]
+    fn test_get_large_chunked_body() {
+        // a local server that sends a body over ureq's 10 MB into_string() limit, in
+        // chunks (as refget servers send whole chromosomes)
+        let chunk = vec![b'A'; 1 << 20];
+        let num_chunks = 12;
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let server = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = BufReader::new(stream.try_clone().unwrap());
+            let mut line = String::new();
+            while request.read_line(&mut line).unwrap() > 0 && line != "\r\n" {
+                line.clear();
+            }
+            stream
+                .write_all(b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n")
+                .unwrap();
+            for _ in 0..num_chunks {
+                write!(stream, "{:x}\r\n", chunk.len()).unwrap();
+                stream.write_all(&chunk).unwrap();
+                stream.write_all(b"\r\n").unwrap();
+            }
+            stream.write_all(b"0\r\n\r\n").unwrap();
+        });
+
+        let body = get(&format!("http://{}/sequence/abc", address)).unwrap();
+        server.join().unwrap();
+        assert_eq!(body.len(), num_chunks << 20);
+        assert!(body.iter().all(|&base| base == b'A'));
+    }
+}
@@ -0,0 +1,597 @@
+//! Run-length encoded per-basepair data.
+//!
+//! Per-basepair data like coverage is often constant over long runs of basepairs.
+//! An [`Rle`] stores such a vector as runs of equal values, and an [`RleList`] stores
+//! one [`Rle`] per sequence of a genome (like Bioconductor's `Rle` and `RleList`).
+//! Arithmetic between two [`Rle`]s (e.g. two coverage tracks) works run by run, with
+//! the result re-segmented at the breakpoints of both, so it never expands the runs
+//! into per-basepair values.
+//!
+//! An [`RleList`] implements [`Sequences`], so the values in ranges ("views") can be
+//! summarized with [`Sequences::region_map()`] and
+//! [`Sequences::region_map_into_granges()`].
+//!
+//! # Example
+//!
+//! ```
+//! use granges::{prelude::*, sequences::rle::RleList, traits::Sequences};
+//!
+//! let seqlens = seqlens! { "chr1" => 10 };
+//! let mut gr = GRangesEmpty::new_vec(&seqlens);
+//! gr.push_range("chr1", 0, 5).unwrap();
+//! gr.push_range("chr1", 2, 8).unwrap();
+//!
+//! let coverage = RleList::coverage(&gr).unwrap();
+//! let chr1 = coverage.get_sequence("chr1").unwrap();
+//! assert_eq!(chr1.num_runs(), 4);
+//! assert_eq!(chr1.get(3), Some(&2));
+//!
+//! // the mean coverage in a view, chr1:0-4
+//! let mean = coverage
+//!     .region_map(&|view, _| view.map(|depth| *depth as f64).mean(), "chr1", 0, 4)
+//!     .unwrap();
+//! assert_eq!(mean, Some(1.5));
+//! ```
+//!
+//! [`Sequences`]: crate::traits::Sequences
+//! [`Sequences::region_map()`]: crate::traits::Sequences::region_map
+//! [`Sequences::region_map_into_granges()`]: crate::traits::Sequences::region_map_into_granges
+
+use genomap::GenomeMap;
+use indexmap::IndexMap;
+use std::ops::{Add, Div, Mul, Sub};
+
+use crate::{
+    error::GRangesError,
+    granges::GRanges,
+    ranges::{try_range, GenomicRangeRecord},
+    traits::{AsGRangesRef, IterableRangeContainer, Sequences},
+    Position,
+};
+
+/// A run-length encoded vector of per-basepair values.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Rle<T> {
+    values: Vec<T>,
+    /// The (exclusive) end position of each run.
+    ends: Vec<Position>,
+}
+
+impl<T> Default for Rle<T> {
+    fn default() -> Self {
+        Self {
+            values: Vec::new(),
+            ends: Vec::new(),
+        }
+    }
+}
+
+impl<T: Clone + PartialEq> Rle<T> {
+    /// Create a new empty [`Rle`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a new [`Rle`] of `length` basepairs, all with `value`.
+    pub fn from_value(value: T, length: Position) -> Self {
+        let mut rle = Self::new();
+        rle.push(value, length);
+        rle
+    }
+
+    /// Add a run of `length` basepairs with `value` to the end. The run is joined
+    /// with the last run if they have the same value.
+    pub fn push(&mut self, value: T, length: Position) {
+        if length == 0 {
+            return;
+        }
+        let end = self.len() + length;
+        if self.values.last() == Some(&value) {
+            *self.ends.last_mut().unwrap() = end;
+        } else {
+            self.values.push(value);
+            self.ends.push(end);
+        }
+    }
+
+    /// The length of this [`Rle`], in basepairs.
+    pub fn len(&self) -> Position {
+        self.ends.last().copied().unwrap_or(0)
+    }
+
+    /// Whether this [`Rle`] has zero length.
+    pub fn is_empty(&self) -> bool {
+        self.ends.is_empty()
+    }
+
+    /// The number of runs.
+    pub fn num_runs(&self) -> usize {
+        self.values.len()
+    }
+
+    /// Iterate over the runs, as `(start, end, value)` tuples.
+    pub fn runs(&self) -> impl Iterator<Item = (Position, Position, &T)> + '_ {
+        let starts = std::iter::once(0).chain(self.ends.iter().copied());
+        starts
+            .zip(self.ends.iter().copied())
+            .zip(self.values.iter())
+            .map(|((start, end), value)| (start, end, value))
+    }
+
+    /// The index of the run containing `position`.
+    fn run_index(&self, position: Position) -> usize {
+        self.ends.partition_point(|end| *end <= position)
+    }
+
+    /// Get the value at `position`, or `None` if it is past the end.
+    pub fn get(&self, position: Position) -> Option<&T> {
+        self.values.get(self.run_index(position))
+    }
+
+    /// Get the [`Rle`] of the basepairs from `start` to `end` (exclusive).
+    pub fn slice(&self, start: Position, end: Position) -> Result<Self, GRangesError> {
+        try_range(start, end, self.len())?;
+        let mut slice = Self::new();
+        for index in self.run_index(start)..self.num_runs() {
+            let run_start = if index == 0 { 0 } else { self.ends[index - 1] };
+            if run_start >= end {
+                break;
+            }
+            let length = self.ends[index].min(end) - run_start.max(start);
+            slice.push(self.values[index].clone(), length);
+        }
+        Ok(slice)
+    }
+
+    /// Create a new [`Rle`] by applying `func` to the value of each run.
+    pub fn map<V, F>(&self, func: F) -> Rle<V>
+    where
+        V: Clone + PartialEq,
+        F: Fn(&T) -> V,
+    {
+        let mut rle = Rle::new();
+        for (start, end, value) in self.runs() {
+            rle.push(func(value), end - start);
+        }
+        rle
+    }
+
+    /// Create a new [`Rle`] by applying `func` to the values of this and `other` at
+    /// each basepair. The result has a run boundary wherever either has one (and
+    /// adjacent runs with equal results are joined).
+    pub fn zip_with<U, V, F>(&self, other: &Rle<U>, func: F) -> Result<Rle<V>, GRangesError>
+    where
+        U: Clone + PartialEq,
+        V: Clone + PartialEq,
+        F: Fn(&T, &U) -> V,
+    {
+        if self.len() != other.len() {
+            return Err(GRangesError::RleLengthMismatch(self.len(), other.len()));
+        }
+        let mut rle = Rle::new();
+        let (mut i, mut j, mut start) = (0, 0, 0);
+        while i < self.num_runs() && j < other.num_runs() {
+            let end = self.ends[i].min(other.ends[j]);
+            rle.push(func(&self.values[i], &other.values[j]), end - start);
+            if self.ends[i] == end {
+                i += 1;
+            }
+            if other.ends[j] == end {
+                j += 1;
+            }
+            start = end;
+        }
+        Ok(rle)
+    }
+
+    /// Expand this [`Rle`] into a vector of per-basepair values.
+    pub fn to_vec(&self) -> Vec<T> {
+        let mut values = Vec::with_capacity(self.len() as usize);
+        for (start, end, value) in self.runs() {
+            values.resize(values.len() + (end - start) as usize, value.clone());
+        }
+        values
+    }
+}
+
+impl<T: Clone + PartialEq> FromIterator<T> for Rle<T> {
+    /// Create an [`Rle`] from per-basepair values.
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut rle = Self::new();
+        for value in iter {
+            rle.push(value, 1);
+        }
+        rle
+    }
+}
+
+impl Rle<f64> {
+    /// The sum of the values of all basepairs.
+    pub fn sum(&self) -> f64 {
+        self.runs()
+            .map(|(start, end, value)| value * (end - start) as f64)
+            .sum()
+    }
+
+    /// The mean value per basepair, or `None` if this [`Rle`] is empty.
+    pub fn mean(&self) -> Option<f64> {
+        (!self.is_empty()).then(|| self.sum() / self.len() as f64)
+    }
+
+    /// The minimum value, or `None` if this [`Rle`] is empty.
+    pub fn min(&self) -> Option<f64> {
+        self.values.iter().copied().reduce(f64::min)
+    }
+
+    /// The maximum value, or `None` if this [`Rle`] is empty.
+    pub fn max(&self) -> Option<f64> {
+        self.values.iter().copied().reduce(f64::max)
+    }
+}
+
+/// Implement a binary operator between two [`Rle`]s, with [`Rle::zip_with()`].
+macro_rules! impl_rle_op {
+    ($op:ident, $method:ident) => {
+        /// Apply this operation basepair by basepair.
+        ///
+        /// # Panics
+        /// Panics if the two [`Rle`]s have different lengths; use [`Rle::zip_with()`]
+        /// to handle this as an error.
+        impl<'a, T> $op<&'a Rle<T>> for &'a Rle<T>
+        where
+            T: Clone + PartialEq + $op<Output = T>,
+        {
+            type Output = Rle<T>;
+
+            fn $method(self, other: &'a Rle<T>) -> Rle<T> {
+                self.zip_with(other, |x, y| x.clone().$method(y.clone()))
+                    .unwrap_or_else(|error| panic!("{}", error))
+            }
+        }
+    };
+}
+
+impl_rle_op!(Add, add);
+impl_rle_op!(Sub, sub);
+impl_rle_op!(Mul, mul);
+impl_rle_op!(Div, div);
+
+/// A set of [`Rle`]s, one per sequence (e.g. chromosome) of a genome.
+#[derive(Clone, Debug)]
+pub struct RleList<T> {
+    data: GenomeMap<Rle<T>>,
+}
+
+impl<T: Clone + PartialEq> Default for RleList<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Clone + PartialEq> RleList<T> {
+    /// Create a new empty [`RleList`].
+    pub fn new() -> Self {
+        Self {
+            data: GenomeMap::new(),
+        }
+    }
+
+    /// Create a new [`RleList`] with the sequences in `seqlens`, all with `value`.
+    pub fn from_value(
+        value: T,
+        seqlens: &IndexMap<String, Position>,
+    ) -> Result<Self, GRangesError> {
+        let mut rle_list = Self::new();
+        for (seqname, length) in seqlens {
+            rle_list.insert(seqname, Rle::from_value(value.clone(), *length))?;
+        }
+        Ok(rle_list)
+    }
+
+    /// Add the [`Rle`] of a sequence.
+    pub fn insert(&mut self, seqname: &str, rle: Rle<T>) -> Result<(), GRangesError> {
+        self.data.insert(seqname, rle)?;
+        Ok(())
+    }
+
+    /// Iterate over the sequence names and their [`Rle`]s.
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &Rle<T>)> {
+        self.data.iter()
+    }
+
+    /// Create a new [`RleList`] by applying `func` to the value of each run.
+    pub fn map<V, F>(&self, func: F) -> Result<RleList<V>, GRangesError>
+    where
+        V: Clone + PartialEq,
+        F: Fn(&T) -> V,
+    {
+        let mut rle_list = RleList::new();
+        for (seqname, rle) in self.iter() {
+            rle_list.insert(seqname, rle.map(&func))?;
+        }
+        Ok(rle_list)
+    }
+
+    /// Create a new [`RleList`] by applying `func` to the values of this and `other`
+    /// at each basepair, with [`Rle::zip_with()`]. Both must have the same sequences,
+    /// with the same lengths.
+    pub fn zip_with<U, V, F>(&self, other: &RleList<U>, func: F) -> Result<RleList<V>, GRangesError>
+    where
+        U: Clone + PartialEq,
+        V: Clone + PartialEq,
+        F: Fn(&T, &U) -> V,
+    {
+        let mut rle_list = RleList::new();
+        for (seqname, rle) in self.iter() {
+            let other_rle = other.get_sequence(seqname)?;
+            rle_list.insert(seqname, rle.zip_with(other_rle, &func)?)?;
+        }
+        Ok(rle_list)
+    }
+}
+
+impl<T: Clone + PartialEq> RleList<Option<T>> {
+    /// Create a new [`RleList`] from ranges with values (e.g. a bedGraph), on the
+    /// sequences in `seqlens`. Basepairs not in any range are `None`. Ranges need
+    /// not be sorted; where they overlap, the range that starts first is used.
+    pub fn from_ranges<I>(
+        ranges: I,
+        seqlens: &IndexMap<String, Position>,
+    ) -> Result<Self, GRangesError>
+    where
+        I: IntoIterator<Item = Result<GenomicRangeRecord<T>, GRangesError>>,
+    {
+        let mut by_sequence: IndexMap<&str, Vec<(Position, Position, T)>> = seqlens
+            .keys()
+            .map(|seqname| (seqname.as_str(), Vec::new()))
+            .collect();
+        for result in ranges {
+            let range = result?;
+            by_sequence
+                .get_mut(range.seqname.as_str())
+                .ok_or_else(|| GRangesError::missing_sequence(&range.seqname))?
+                .push((range.start, range.end, range.data));
+        }
+
+        let mut rle_list = Self::new();
+        for (seqname, mut ranges) in by_sequence {
+            ranges.sort_by_key(|&(start, _, _)| start);
+            let mut rle = Rle::new();
+            for (start, end, value) in ranges {
+                let start = start.max(rle.len());
+                if end > start {
+                    rle.push(None, start - rle.len());
+                    rle.push(Some(value), end - start);
+                }
+            }
+            rle.push(None, seqlens[seqname].saturating_sub(rle.len()));
+            rle_list.insert(seqname, rle)?;
+        }
+        Ok(rle_list)
+    }
+}
+
+impl RleList<usize> {
+    /// The coverage of `granges`, i.e. the number of ranges overlapping each
+    /// basepair of each of its sequences. A range extending past the end of its
+    /// sequence is a [`GRangesError::InvalidGenomicRangeForSequence`] error.
+    pub fn coverage<'a, C, T>(
+        granges: &'a impl AsGRangesRef<'a, C, T>,
+    ) -> Result<Self, GRangesError>
+    where
+        C: IterableRangeContainer + 'a,
+        T: 'a,
+    {
+        let granges: &GRanges<C, T> = granges.as_granges_ref();
+        let seqlens = granges.seqlens();
+        // the positions where the coverage changes, and by how much
+        let mut changes: IndexMap<&str, Vec<(Position, isize)>> = seqlens
+            .keys()
+            .map(|seqname| (seqname.as_str(), Vec::new()))
+            .collect();
+        for range in granges.iter_named_ranges() {
+            if let Some(changes) = changes.get_mut(range.seqname) {
+                let length = seqlens[range.seqname];
+                if range.end > length {
+                    return Err(GRangesError::InvalidGenomicRangeForSequence {
+                        seqname: Some(range.seqname.to_string()),
+                        start: range.start,
+                        end: range.end,
+                        length,
+                    });
+                }
+                changes.extend([(range.start, 1), (range.end, -1)]);
+            }
+        }
+
+        let mut coverage = Self::new();
+        for (seqname, mut changes) in changes {
+            changes.sort_unstable();
+            let mut rle = Rle::new();
+            let (mut depth, mut position) = (0_isize, 0);
+            for (change_position, change) in changes {
+                rle.push(depth as usize, change_position - position);
+                depth += change;
+                position = change_position;
+            }
+            rle.push(0, seqlens[seqname] - position);
+            coverage.insert(seqname, rle)?;
+        }
+        Ok(coverage)
+    }
+}
+
+impl<T: Clone + PartialEq> Sequences for RleList<T> {
+    type Container<'a>
+        = &'a Rle<T>
+    where
+        Self: 'a;
+    type Slice<'a> = Rle<T>;
+
+    /// Retrieve all sequence names.
+    fn seqnames(&self) -> Vec<String> {
+        self.data.names()
+    }
+
+    /// Retrieve the [`Rle`] for a particular sequence name.
+    fn get_sequence(&self, seqname: &str) -> Result<Self::Container<'_>, GRangesError> {
+        self.data
+            .get(seqname)
+            .ok_or(GRangesError::missing_sequence(seqname))
+    }
+
+    /// Apply a function to the [`Rle`] of the specified region (a "view").
+    fn region_map<V, F>(
+        &self,
+        func: &F,
+        seqname: &str,
+        start: Position,
+        end: Position,
+    ) -> Result<V, GRangesError>
+    where
+        F: Fn(Self::Slice<'_>, (&str, Position, Position)) -> V,
+    {
+        let rle = self.get_sequence(seqname)?;
+        let view = rle
+            .slice(start, end)
+            .map_err(|error| error.with_seqname(seqname))?;
+        Ok(func(view, (seqname, start, end)))
+    }
+
+    /// Get the length of a particular sequence.
+    fn get_sequence_length(&self, seqname: &str) -> Result<Position, GRangesError> {
+        Ok(self.get_sequence(seqname)?.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::*;
+
+    #[test]
+    fn test_rle_push_and_get() {
+        let rle: Rle<u8> = [1, 1, 2, 2, 2, 1].into_iter().collect();
+        assert_eq!(rle.len(), 6);
+        assert_eq!(rle.num_runs(), 3);
+        assert_eq!(rle.get(0), Some(&1));
+        assert_eq!(rle.get(4), Some(&2));
+        assert_eq!(rle.get(5), Some(&1));
+        assert_eq!(rle.get(6), None);
+        assert_eq!(rle.to_vec(), vec![1, 1, 2, 2, 2, 1]);
+        let runs: Vec<_> = rle
+            .runs()
+            .map(|(start, end, value)| (start, end, *value))
+            .collect();
+        assert_eq!(runs, vec![(0, 2, 1), (2, 5, 2), (5, 6, 1)]);
+    }
+
+    #[test]
+    fn test_rle_slice() {
+        let rle: Rle<u8> = [1, 1, 2, 2, 2, 1].into_iter().collect();
+        assert_eq!(rle.slice(1, 4).unwrap().to_vec(), vec![1, 2, 2]);
+        assert_eq!(rle.slice(2, 5).unwrap().num_runs(), 1);
+        assert_eq!(rle.slice(0, 6).unwrap(), rle);
+        assert!(rle.slice(4, 7).is_err());
+    }
+
+    #[test]
+    fn test_rle_arithmetic() {
+        let x = Rle::from_value(1.0, 4);
+        let y: Rle<f64> = [0.0, 2.0, 2.0, 0.0].into_iter().collect();
+        let sum = &x + &y;
+        assert_eq!(sum.to_vec(), vec![1.0, 3.0, 3.0, 1.0]);
+        assert_eq!(sum.num_runs(), 3);
+        assert_eq!((&sum - &y), x);
+        assert_eq!(sum.sum(), 8.0);
+        assert_eq!(sum.mean(), Some(2.0));
+        assert_eq!(sum.max(), Some(3.0));
+
+        let log2_ratio = x.zip_with(&sum, |x, y| (y / x).log2()).unwrap();
+        assert_eq!(log2_ratio.get(1), Some(&3.0_f64.log2()));
+        assert!(matches!(
+            x.zip_with(&Rle::from_value(1.0, 5), |x, y| x + y),
+            Err(GRangesError::RleLengthMismatch(4, 5))
+        ));
+    }
+
+    #[test]
+    fn test_rle_list_coverage() {
+        let seqlens = seqlens! { "chr1" => 10, "chr2" => 5 };
+        let mut gr = GRangesEmpty::new_vec(&seqlens);
+        gr.push_range("chr1", 2, 5).unwrap();
+        gr.push_range("chr1", 3, 8).unwrap();
+        gr.push_range("chr1", 3, 4).unwrap();
+
+        let coverage = RleList::coverage(&gr).unwrap();
+        let chr1 = coverage.get_sequence("chr1").unwrap();
+        assert_eq!(chr1.to_vec(), vec![0, 0, 1, 3, 2, 1, 1, 1, 0, 0]);
+        assert_eq!(coverage.get_sequence_length("chr2").unwrap(), 5);
+        assert_eq!(coverage.get_sequence("chr2").unwrap().num_runs(), 1);
+
+        // summarize views with the Sequences trait
+        let max_depth = gr
+            .iter_ranges()
+            .map(|range| {
+                coverage
+                    .region_map(
+                        &|view, _| view.runs().map(|run| *run.2).max(),
+                        "chr1",
+                        range.start,
+                        range.end,
+                    )
+                    .unwrap()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(max_depth, vec![Some(3), Some(3), Some(3)]);
+
+        // arithmetic between RleLists
+        let doubled = coverage.zip_with(&coverage, |x, y| x + y).unwrap();
+        assert_eq!(doubled.get_sequence("chr1").unwrap().get(3), Some(&6));
+        let flat = RleList::from_value(1_usize, &seqlens).unwrap();
+        assert!(coverage.zip_with(&flat, |x, y| x * y).is_ok());
+    }
+
+    #[test]
+    fn test_rle_list_coverage_out_of_bounds() {
+        let seqlens = seqlens! { "chr1" => 10 };
+        let mut gr = GRangesEmpty::new_vec(&seqlens);
+        gr.push_range("chr1", 5, 12).unwrap();
+        assert!(matches!(
+            RleList::coverage(&gr),
+            Err(GRangesError::InvalidGenomicRangeForSequence {
+                end: 12,
+                length: 10,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_rle_list_from_ranges() {
+        let seqlens = seqlens! { "chr1" => 10 };
+        let ranges = vec![
+            GenomicRangeRecord::new("chr1".to_string(), 6, 8, 2.0),
+            GenomicRangeRecord::new("chr1".to_string(), 1, 3, 1.0),
+            GenomicRangeRecord::new("chr1".to_string(), 2, 4, 5.0),
+        ];
+        let track = RleList::from_ranges(ranges.into_iter().map(Ok), &seqlens).unwrap();
+        let chr1 = track.get_sequence("chr1").unwrap();
+        let expected = vec![
+            None,
+            Some(1.0),
+            Some(1.0),
+            Some(5.0),
+            None,
+            None,
+            Some(2.0),
+            Some(2.0),
+            None,
+            None,
+        ];
+        assert_eq!(chr1.to_vec(), expected);
+
+        let missing = vec![Ok(GenomicRangeRecord::new("chr2".to_string(), 0, 1, 1.0))];
+        assert!(RleList::from_ranges(missing, &seqlens).is_err());
+    }
+}
@@ -0,0 +1,323 @@
+//! Stabbing queries: which ranges contain a position.
+//!
+//! Overlap-query backends (e.g. [`COITrees`]) answer range queries, and can answer
+//! position queries as ranges of one basepair, but a [`StabbingIndex`] is optimized for
+//! many (e.g. millions of) position queries. It splits each sequence into elementary
+//! segments at every range start and end, and stores the ranges covering each
+//! segment, so a query is a binary search for the position's segment
+//! ([`StabbingIndex::query_position()`]), and a stream of sorted positions just walks
+//! forward through the segments ([`StabbingIndex::query_sorted()`]). The cost is
+//! memory proportional to the total number of ranges covering each segment, which is
+//! greater than the number of ranges when they overlap heavily.
+//!
+//! # Example
+//!
+//! ```
+//! use granges::{prelude::*, stabbing::StabbingIndex};
+//!
+//! let iter = Bed3Iterator::new("tests_data/example.bed").unwrap();
+//! let index = StabbingIndex::from_ranges(iter).unwrap();
+//! // chr1:10-20 contains chr1:14-18
+//! assert_eq!(index.query_position("chr1", 15).count(), 2);
+//! assert_eq!(index.query_position("chr1", 19).count(), 1);
+//! assert_eq!(index.query_position("chr3", 0).count(), 0);
+//! ```
+//!
+//! [`COITrees`]: crate::ranges::coitrees::COITreesIndexed
+
+use std::collections::{BTreeSet, HashMap};
+
+use crate::{error::GRangesError, traits::GenomicRecord, Position};
+
+/// The elementary segments of one sequence, between consecutive range boundaries,
+/// with the ranges covering each.
+#[derive(Debug)]
+struct SequenceSegments {
+    /// The sorted, unique starts and ends of the ranges; segment `i` is
+    /// `[boundaries[i], boundaries[i + 1])`.
+    boundaries: Vec<Position>,
+    /// The ranges covering segment `i` are `covering[offsets[i]..offsets[i + 1]]`.
+    offsets: Vec<usize>,
+    /// The indices of the ranges covering each segment, in input order.
+    covering: Vec<usize>,
+}
+
+impl SequenceSegments {
+    /// Build the segments of the `ranges` with these `indices`, which must all be on
+    /// the same sequence and have non-zero widths.
+    fn new<R: GenomicRecord>(ranges: &[R], indices: Vec<usize>) -> Self {
+        let mut boundaries: Vec<Position> = indices
+            .iter()
+            .flat_map(|&i| [ranges[i].start(), ranges[i].end()])
+            .collect();
+        boundaries.sort_unstable();
+        boundaries.dedup();
+
+        let mut starts = indices;
+        starts.sort_by_key(|&i| ranges[i].start());
+        let mut ends = starts.clone();
+        ends.sort_by_key(|&i| ranges[i].end());
+
+        // sweep the boundaries, tracking the ranges covering each segment
+        let (mut next_start, mut next_end) = (0, 0);
+        let mut active = BTreeSet::new();
+        let mut offsets = vec![0];
+        let mut covering = Vec::new();
+        for &boundary in boundaries.iter().take(boundaries.len().saturating_sub(1)) {
+            while next_end < ends.len() && ranges[ends[next_end]].end() <= boundary {
+                active.remove(&ends[next_end]);
+                next_end += 1;
+            }
+            while next_start < starts.len() && ranges[starts[next_start]].start() <= boundary {
+                active.insert(starts[next_start]);
+                next_start += 1;
+            }
+            covering.extend(active.iter().copied());
+            offsets.push(covering.len());
+        }
+        Self {
+            boundaries,
+            offsets,
+            covering,
+        }
+    }
+
+    /// The indices of the ranges covering the segment containing `position`, given
+    /// the number of boundaries at or before it.
+    fn covering(&self, num_before: usize) -> &[usize] {
+        if num_before == 0 || num_before >= self.boundaries.len() {
+            return &[];
+        }
+        let segment = num_before - 1;
+        &self.covering[self.offsets[segment]..self.offsets[segment + 1]]
+    }
+
+    /// The indices of the ranges containing `position`.
+    fn query(&self, position: Position) -> &[usize] {
+        self.covering(
+            self.boundaries
+                .partition_point(|&boundary| boundary <= position),
+        )
+    }
+}
+
+/// An index of ranges for position ("stabbing") queries (see the [module
+/// documentation](self)).
+#[derive(Debug)]
+pub struct StabbingIndex<R> {
+    ranges: Vec<R>,
+    sequences: HashMap<String, SequenceSegments>,
+}
+
+impl<R: GenomicRecord> StabbingIndex<R> {
+    /// Create a new [`StabbingIndex`] of these ranges. Zero-width ranges contain no
+    /// positions, so are never returned by queries.
+    pub fn new(ranges: Vec<R>) -> Self {
+        let mut by_sequence: HashMap<&str, Vec<usize>> = HashMap::new();
+        for (i, range) in ranges.iter().enumerate() {
+            if range.width() > 0 {
+                by_sequence.entry(range.seqname()).or_default().push(i);
+            }
+        }
+        let sequences = by_sequence
+            .into_iter()
+            .map(|(seqname, indices)| {
+                (seqname.to_string(), SequenceSegments::new(&ranges, indices))
+            })
+            .collect();
+        Self { ranges, sequences }
+    }
+
+    /// Create a new [`StabbingIndex`] of the ranges from a parsing iterator.
+    pub fn from_ranges<I>(iter: I) -> Result<Self, GRangesError>
+    where
+        I: IntoIterator<Item = Result<R, GRangesError>>,
+    {
+        Ok(Self::new(iter.into_iter().collect::<Result<_, _>>()?))
+    }
+
+    /// The indexed ranges, in input order.
+    pub fn ranges(&self) -> &[R] {
+        &self.ranges
+    }
+
+    /// The ranges on sequence `seqname` that contain the 0-based `position`, in
+    /// input order.
+    pub fn query_position(&self, seqname: &str, position: Position) -> Stabbed<'_, R> {
+        let indices = self
+            .sequences
+            .get(seqname)
+            .map(|segments| segments.query(position))
+            .unwrap_or_default();
+        Stabbed {
+            ranges: &self.ranges,
+            indices: indices.iter(),
+        }
+    }
+
+    /// Query a stream of `(seqname, position)` pairs, sorted by position within each
+    /// run of the same sequence, returning an iterator over each position with the
+    /// ranges containing it (as from [`StabbingIndex::query_position()`]). Rather
+    /// than a binary search per position, this walks forward through the segments of
+    /// each sequence. A position before the previous one on the same sequence returns
+    /// a [`GRangesError::UnsortedInput`] error.
+    pub fn query_sorted<I, S>(&self, positions: I) -> SortedStabbing<'_, R, I::IntoIter>
+    where
+        I: IntoIterator<Item = (S, Position)>,
+        S: AsRef<str>,
+    {
+        SortedStabbing {
+            index: self,
+            positions: positions.into_iter(),
+            seqname: None,
+            segments: None,
+            position: 0,
+            num_before: 0,
+        }
+    }
+}
+
+/// An iterator over the ranges containing a position, from
+/// [`StabbingIndex::query_position()`].
+pub struct Stabbed<'a, R> {
+    ranges: &'a [R],
+    indices: std::slice::Iter<'a, usize>,
+}
+
+impl<'a, R> Iterator for Stabbed<'a, R> {
+    type Item = &'a R;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let ranges = self.ranges;
+        self.indices.next().map(|&i| &ranges[i])
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.indices.size_hint()
+    }
+}
+
+impl<R> ExactSizeIterator for Stabbed<'_, R> {}
+
+/// An iterator over sorted position queries, from [`StabbingIndex::query_sorted()`].
+pub struct SortedStabbing<'a, R, I> {
+    index: &'a StabbingIndex<R>,
+    positions: I,
+    /// The sequence of the previous position, and its segments.
+    seqname: Option<String>,
+    segments: Option<&'a SequenceSegments>,
+    position: Position,
+    /// The number of boundaries of the current sequence at or before `position`.
+    num_before: usize,
+}
+
+impl<'a, R, I, S> Iterator for SortedStabbing<'a, R, I>
+where
+    I: Iterator<Item = (S, Position)>,
+    S: AsRef<str>,
+{
+    type Item = Result<(S, Position, Stabbed<'a, R>), GRangesError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (seqname, position) = self.positions.next()?;
+        let index = self.index;
+        if self.seqname.as_deref() == Some(seqname.as_ref()) {
+            if position < self.position {
+                return Some(Err(GRangesError::UnsortedInput {
+                    seqname: seqname.as_ref().to_string(),
+                    start: position,
+                    previous_seqname: seqname.as_ref().to_string(),
+                    previous_start: self.position,
+                }));
+            }
+        } else {
+            self.seqname = Some(seqname.as_ref().to_string());
+            self.segments = index.sequences.get(seqname.as_ref());
+            self.num_before = 0;
+        }
+        self.position = position;
+
+        let indices = match self.segments {
+            Some(segments) => {
+                let boundaries = &segments.boundaries;
+                while self.num_before < boundaries.len() && boundaries[self.num_before] <= position
+                {
+                    self.num_before += 1;
+                }
+                segments.covering(self.num_before)
+            }
+            None => &[],
+        };
+        let stabbed = Stabbed {
+            ranges: &index.ranges,
+            indices: indices.iter(),
+        };
+        Some(Ok((seqname, position, stabbed)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ranges::GenomicRangeRecordEmpty;
+
+    fn test_index() -> StabbingIndex<GenomicRangeRecordEmpty> {
+        let ranges = [
+            ("chr1", 10, 20),
+            ("chr1", 14, 18),
+            ("chr1", 18, 30),
+            ("chr2", 5, 6),
+        ];
+        StabbingIndex::new(
+            ranges
+                .iter()
+                .map(|&(seqname, start, end)| {
+                    GenomicRangeRecordEmpty::new(seqname.to_string(), start, end)
+                })
+                .collect(),
+        )
+    }
+
+    fn starts(stabbed: Stabbed<'_, GenomicRangeRecordEmpty>) -> Vec<Position> {
+        stabbed.map(|range| range.start).collect()
+    }
+
+    #[test]
+    fn test_query_position() {
+        let index = test_index();
+        assert_eq!(starts(index.query_position("chr1", 15)), vec![10, 14]);
+        assert_eq!(starts(index.query_position("chr1", 18)), vec![10, 18]);
+        assert_eq!(starts(index.query_position("chr1", 29)), vec![18]);
+        assert!(starts(index.query_position("chr1", 9)).is_empty());
+        assert!(starts(index.query_position("chr1", 30)).is_empty());
+        assert_eq!(starts(index.query_position("chr2", 5)), vec![5]);
+        assert!(starts(index.query_position("chr3", 5)).is_empty());
+    }
+
+    #[test]
+    fn test_query_sorted() {
+        let index = test_index();
+        let positions = [
+            ("chr1", 9),
+            ("chr1", 15),
+            ("chr1", 15),
+            ("chr1", 25),
+            ("chr3", 0),
+            ("chr2", 5),
+            ("chr2", 6),
+        ];
+        let counts: Vec<usize> = index
+            .query_sorted(positions)
+            .map(|result| result.unwrap().2.len())
+            .collect();
+        assert_eq!(counts, vec![0, 2, 2, 1, 0, 1, 0]);
+
+        // a position before the previous one on the same sequence
+        let mut queries = index.query_sorted([("chr1", 20), ("chr1", 10)]);
+        assert!(queries.next().unwrap().is_ok());
+        assert!(matches!(
+            queries.next().unwrap(),
+            Err(GRangesError::UnsortedInput { .. })
+        ));
+    }
+}
@@ -0,0 +1,755 @@
+//! Summary statistics of a set of genomic ranges.
+//!
+//! [`RangeStats`] summarizes ranges read from a parsing iterator: the number of
+//! ranges, the number of basepairs covered by at least one range (overall and per
+//! sequence), the distribution of range widths (including the N50), and the fraction
+//! of ranges that overlap another range in the same set. These are the statistics
+//! reported by `granges stats`.
+//!
+//! A [`Histogram`] bins values, e.g. range widths or scores, as reported by
+//! `granges hist`.
+//!
+//! Read counts over features (e.g. from `granges map --func count`) can be
+//! normalized by library size and feature length, as CPM, RPKM/FPKM, or TPM (see
+//! [`Normalization`]).
+//!
+//! # Example
+//!
+//! ```
+//! use granges::prelude::*;
+//! use granges::stats::RangeStats;
+//!
+//! let iter = Bed3Iterator::new("tests_data/example.bed").expect("error reading file");
+//! let stats = RangeStats::from_ranges(iter).expect("parsing error");
+//! assert_eq!(stats.num_ranges, 5);
+//! // chr1:10-20 contains chr1:14-18
+//! assert_eq!(stats.covered_bases, 10 + 1 + 1 + 6);
+//! assert_eq!(stats.overlap_fraction, 0.4);
+//! ```
+
+use std::{io::Write, num::NonZeroUsize};
+
+use clap::ValueEnum;
+use indexmap::IndexMap;
+use serde::Serialize;
+
+use crate::{error::GRangesError, ranges::checked_width, traits::GenomicRecord, Position};
+
+/// The quantiles of range widths reported in [`WidthStats::quantiles`].
+pub const WIDTH_QUANTILES: &[f64] = &[0.05, 0.25, 0.5, 0.75, 0.95];
+
+/// Fold the ranges from a parsing iterator by sequence, in a single pass: each
+/// sequence's value starts as `init()`, and is updated by `fold` with each of its
+/// ranges. The values are returned in the order sequences are first seen, and the
+/// ranges do not need to be sorted. Ranges whose start is after their end are a
+/// [`GRangesError::InvalidGenomicRange`] error (see [`checked_width()`]).
+///
+/// # Example
+///
+/// ```
+/// use granges::prelude::*;
+/// use granges::stats::group_by_chrom;
+///
+/// let iter = Bed3Iterator::new("tests_data/example.bed").expect("error reading file");
+/// let widths = group_by_chrom(iter, || 0, |total, range| *total += range.width()).unwrap();
+/// assert_eq!(widths["chr1"], 10 + 4);
+/// ```
+pub fn group_by_chrom<I, R, A>(
+    iter: I,
+    mut init: impl FnMut() -> A,
+    mut fold: impl FnMut(&mut A, R),
+) -> Result<IndexMap<String, A>, GRangesError>
+where
+    I: Iterator<Item = Result<R, GRangesError>>,
+    R: GenomicRecord,
+{
+    let mut groups: IndexMap<String, A> = IndexMap::new();
+    for result in iter {
+        let range = result?;
+        checked_width(&range)?;
+        // avoid allocating the sequence name for every range
+        let index = match groups.get_index_of(range.seqname()) {
+            Some(index) => index,
+            None => groups.insert_full(range.seqname().to_string(), init()).0,
+        };
+        let (_, value) = groups
+            .get_index_mut(index)
+            .expect("Internal error: please report");
+        fold(value, range);
+    }
+    Ok(groups)
+}
+
+/// Collect the start and end positions of the ranges from a parsing iterator,
+/// grouped by sequence (in the order sequences are first seen).
+pub fn ranges_by_sequence<I, R>(
+    iter: I,
+) -> Result<IndexMap<String, Vec<(Position, Position)>>, GRangesError>
+where
+    I: Iterator<Item = Result<R, GRangesError>>,
+    R: GenomicRecord,
+{
+    group_by_chrom(iter, Vec::new, |ranges, range| {
+        ranges.push((range.start(), range.end()))
+    })
+}
+
+/// Summary statistics of the widths of a set of ranges.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct WidthStats {
+    pub min: Position,
+    pub mean: f64,
+    pub median: Position,
+    pub max: Position,
+    /// The widths at each of the [`WIDTH_QUANTILES`], by the nearest-rank method.
+    pub quantiles: Vec<(f64, Position)>,
+    /// The width such that ranges at least this wide make up half of the total width.
+    pub n50: Position,
+}
+
+impl WidthStats {
+    /// Calculate the width statistics of `widths`, which must not be empty.
+    fn new(mut widths: Vec<Position>) -> Self {
+        assert!(!widths.is_empty());
+        widths.sort_unstable();
+        let total: u64 = widths.iter().map(|&width| width as u64).sum();
+
+        // the nearest-rank quantile of sorted widths
+        let quantile = |q: f64| {
+            let rank = (q * widths.len() as f64).ceil() as usize;
+            widths[rank.clamp(1, widths.len()) - 1]
+        };
+
+        let mut cumulative = 0;
+        let n50 = widths
+            .iter()
+            .rev()
+            .find(|&&width| {
+                cumulative += width as u64;
+                2 * cumulative >= total
+            })
+            .copied()
+            .unwrap_or_default();
+
+        Self {
+            min: widths[0],
+            mean: total as f64 / widths.len() as f64,
+            median: quantile(0.5),
+            max: widths[widths.len() - 1],
+            quantiles: WIDTH_QUANTILES.iter().map(|&q| (q, quantile(q))).collect(),
+            n50,
+        }
+    }
+}
+
+/// Summary statistics of the ranges on one sequence.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct SequenceStats {
+    pub seqname: String,
+    pub num_ranges: usize,
+    /// The number of basepairs covered by at least one range.
+    pub covered_bases: u64,
+}
+
+/// Summary statistics of a set of genomic ranges.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct RangeStats {
+    pub num_ranges: usize,
+    /// The total width of all ranges, counting overlapping basepairs multiple times.
+    pub total_width: u64,
+    /// The number of basepairs covered by at least one range.
+    pub covered_bases: u64,
+    /// The fraction of ranges that overlap at least one other range.
+    pub overlap_fraction: f64,
+    pub widths: WidthStats,
+    /// Statistics for each sequence, in the order they were first seen.
+    pub sequences: Vec<SequenceStats>,
+}
+
+impl RangeStats {
+    /// Calculate the summary statistics of the ranges from a parsing iterator.
+    /// The ranges do not need to be sorted.
+    ///
+    /// # Errors
+    /// Returns [`GRangesError::NoRows`] if there are no ranges.
+    pub fn from_ranges<I, R>(iter: I) -> Result<Self, GRangesError>
+    where
+        I: Iterator<Item = Result<R, GRangesError>>,
+        R: GenomicRecord,
+    {
+        let ranges = ranges_by_sequence(iter)?;
+        let mut widths = Vec::new();
+        let mut num_overlapping = 0;
+        let mut sequences = Vec::with_capacity(ranges.len());
+        for (seqname, mut seq_ranges) in ranges {
+            seq_ranges.sort_unstable();
+            widths.extend(seq_ranges.iter().map(|(start, end)| end - start));
+            num_overlapping += count_overlapping(&seq_ranges);
+            sequences.push(SequenceStats {
+                seqname,
+                num_ranges: seq_ranges.len(),
+                covered_bases: covered_bases(&seq_ranges),
+            });
+        }
+        if widths.is_empty() {
+            return Err(GRangesError::NoRows);
+        }
+
+        let num_ranges = widths.len();
+        Ok(Self {
+            num_ranges,
+            total_width: widths.iter().map(|&width| width as u64).sum(),
+            covered_bases: sequences.iter().map(|seq| seq.covered_bases).sum(),
+            overlap_fraction: num_overlapping as f64 / num_ranges as f64,
+            widths: WidthStats::new(widths),
+            sequences,
+        })
+    }
+
+    /// Write these statistics in the specified format.
+    pub fn write(&self, mut writer: impl Write, format: &StatsFormat) -> Result<(), GRangesError> {
+        match format {
+            StatsFormat::Text => {
+                writeln!(writer, "ranges:            {}", self.num_ranges)?;
+                writeln!(writer, "total width:       {}", self.total_width)?;
+                writeln!(writer, "covered bases:     {}", self.covered_bases)?;
+                writeln!(writer, "overlap fraction:  {:.4}", self.overlap_fraction)?;
+                writeln!(writer, "width min:         {}", self.widths.min)?;
+                writeln!(writer, "width mean:        {:.2}", self.widths.mean)?;
+                writeln!(writer, "width median:      {}", self.widths.median)?;
+                writeln!(writer, "width max:         {}", self.widths.max)?;
+                for (q, width) in &self.widths.quantiles {
+                    writeln!(writer, "width {:<12} {}", format!("{}%:", q * 100.0), width)?;
+                }
+                writeln!(writer, "width N50:         {}", self.widths.n50)?;
+                writeln!(writer, "\nsequence\tranges\tcovered bases")?;
+                for seq in &self.sequences {
+                    writeln!(writer, "{}\t{}\t{}", seq.seqname, seq.num_ranges, seq.covered_bases)?;
+                }
+            }
+            StatsFormat::Json => {
+                serde_json::to_writer_pretty(&mut writer, self)
+                    .map_err(|e| GRangesError::ReportError(e.to_string()))?;
+                writeln!(writer)?;
+            }
+            StatsFormat::Tsv => {
+                // a long format table, with "all" for statistics over all sequences
+                writeln!(writer, "sequence\tstatistic\tvalue")?;
+                writeln!(writer, "all\tranges\t{}", self.num_ranges)?;
+                writeln!(writer, "all\ttotal_width\t{}", self.total_width)?;
+                writeln!(writer, "all\tcovered_bases\t{}", self.covered_bases)?;
+                writeln!(writer, "all\toverlap_fraction\t{}", self.overlap_fraction)?;
+                writeln!(writer, "all\twidth_min\t{}", self.widths.min)?;
+                writeln!(writer, "all\twidth_mean\t{}", self.widths.mean)?;
+                writeln!(writer, "all\twidth_median\t{}", self.widths.median)?;
+                writeln!(writer, "all\twidth_max\t{}", self.widths.max)?;
+                for (q, width) in &self.widths.quantiles {
+                    writeln!(writer, "all\twidth_q{}\t{}", q * 100.0, width)?;
+                }
+                writeln!(writer, "all\twidth_n50\t{}", self.widths.n50)?;
+                for seq in &self.sequences {
+                    writeln!(writer, "{}\tranges\t{}", seq.seqname, seq.num_ranges)?;
+                    writeln!(writer, "{}\tcovered_bases\t{}", seq.seqname, seq.covered_bases)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Aggregates of the ranges on one sequence, as reported by `granges
+/// summary-per-chrom`.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct SequenceSummary {
+    pub num_ranges: usize,
+    /// The number of basepairs covered by at least one range.
+    pub covered_bases: u64,
+    /// The mean score of the ranges that have one, if any do.
+    pub mean_score: Option<f64>,
+}
+
+/// The running totals of a [`SequenceSummary`].
+#[derive(Default)]
+struct SummaryAccumulator {
+    num_ranges: usize,
+    // the ranges, with ranges overlapping the last range merged into it, so that
+    // sorted input takes little memory
+    intervals: Vec<(Position, Position)>,
+    score_sum: f64,
+    num_scores: usize,
+}
+
+impl SequenceSummary {
+    /// Summarize the ranges from a parsing iterator by sequence (see
+    /// [`group_by_chrom()`]), where `score` gives each range's score, if it has one.
+    pub fn from_ranges<I, R>(
+        iter: I,
+        score: impl Fn(&R) -> Option<f64>,
+    ) -> Result<IndexMap<String, Self>, GRangesError>
+    where
+        I: Iterator<Item = Result<R, GRangesError>>,
+        R: GenomicRecord,
+    {
+        let groups = group_by_chrom(iter, SummaryAccumulator::default, |totals, range| {
+            totals.num_ranges += 1;
+            if let Some(score) = score(&range) {
+                totals.score_sum += score;
+                totals.num_scores += 1;
+            }
+            let (start, end) = (range.start(), range.end());
+            match totals.intervals.last_mut() {
+                Some((last_start, last_end)) if *last_start <= start && start <= *last_end => {
+                    *last_end = (*last_end).max(end);
+                }
+                _ => totals.intervals.push((start, end)),
+            }
+        })?;
+        Ok(groups
+            .into_iter()
+            .map(|(seqname, mut totals)| {
+                totals.intervals.sort_unstable();
+                let summary = SequenceSummary {
+                    num_ranges: totals.num_ranges,
+                    covered_bases: covered_bases(&totals.intervals),
+                    mean_score: (totals.num_scores > 0)
+                        .then(|| totals.score_sum / totals.num_scores as f64),
+                };
+                (seqname, summary)
+            })
+            .collect())
+    }
+}
+
+/// The number of basepairs covered by at least one of the `ranges`, which must be
+/// sorted by start position.
+fn covered_bases(ranges: &[(Position, Position)]) -> u64 {
+    let mut covered = 0;
+    let mut current: Option<(Position, Position)> = None;
+    for &(start, end) in ranges {
+        match current {
+            Some((current_start, current_end)) if start <= current_end => {
+                current = Some((current_start, current_end.max(end)));
+            }
+            _ => {
+                if let Some((current_start, current_end)) = current {
+                    covered += (current_end - current_start) as u64;
+                }
+                current = Some((start, end));
+            }
+        }
+    }
+    if let Some((current_start, current_end)) = current {
+        covered += (current_end - current_start) as u64;
+    }
+    covered
+}
+
+/// The number of the `ranges` that overlap at least one other range, where `ranges`
+/// must be sorted by start position.
+fn count_overlapping(ranges: &[(Position, Position)]) -> usize {
+    let mut max_end = 0;
+    let mut count = 0;
+    for (i, &(start, end)) in ranges.iter().enumerate() {
+        // a range overlaps an earlier range if it starts before one ends, and a later
+        // range if the next range starts before it ends
+        let overlaps_earlier = start < max_end;
+        let overlaps_later = ranges
+            .get(i + 1)
+            .is_some_and(|&(next_start, _)| next_start < end);
+        if overlaps_earlier || overlaps_later {
+            count += 1;
+        }
+        max_end = max_end.max(end);
+    }
+    count
+}
+
+/// The number of pairs of a left and a right range that overlap, where the ranges
+/// are grouped by sequence (e.g. by [`ranges_by_sequence()`]) and need not be sorted.
+pub fn count_overlapping_pairs(
+    left: &IndexMap<String, Vec<(Position, Position)>>,
+    right: &IndexMap<String, Vec<(Position, Position)>>,
+) -> u64 {
+    left.iter()
+        .filter_map(|(seqname, left_ranges)| {
+            let right_ranges = right.get(seqname)?;
+            let mut starts: Vec<Position> = right_ranges.iter().map(|range| range.0).collect();
+            let mut ends: Vec<Position> = right_ranges.iter().map(|range| range.1).collect();
+            starts.sort_unstable();
+            ends.sort_unstable();
+            let count: usize = left_ranges
+                .iter()
+                .map(|&(start, end)| {
+                    // the right ranges starting before this range ends, less those
+                    // ending before it starts
+                    starts.partition_point(|&right_start| right_start < end)
+                        - ends.partition_point(|&right_end| right_end <= start)
+                })
+                .sum();
+            Some(count as u64)
+        })
+        .sum()
+}
+
+/// A normalization of read counts over features, by library size (the total
+/// number of reads) and feature length (see [`Normalization::normalize()`]).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Normalization {
+    /// Counts per million reads (see [`cpm()`]).
+    Cpm,
+    /// Reads (or fragments, for FPKM) per kilobase of feature per million reads
+    /// (see [`rpkm()`]).
+    #[value(alias = "fpkm")]
+    Rpkm,
+    /// Transcripts per million (see [`tpm()`]).
+    Tpm,
+}
+
+impl Normalization {
+    /// Normalize the `counts` of reads over features with these `lengths`, out of
+    /// `library_size` reads. Values that depend on the length are `None` for
+    /// zero-length features.
+    pub fn normalize(
+        &self,
+        counts: &[u64],
+        lengths: &[Position],
+        library_size: u64,
+    ) -> Vec<Option<f64>> {
+        match self {
+            Normalization::Cpm => cpm(counts, library_size).into_iter().map(Some).collect(),
+            Normalization::Rpkm => rpkm(counts, lengths, library_size),
+            Normalization::Tpm => tpm(counts, lengths),
+        }
+    }
+}
+
+/// Counts per million: each count divided by the library size (the total number
+/// of reads), times a million.
+pub fn cpm(counts: &[u64], library_size: u64) -> Vec<f64> {
+    counts
+        .iter()
+        .map(|&count| count as f64 * 1e6 / library_size as f64)
+        .collect()
+}
+
+/// Reads per kilobase per million (RPKM, or FPKM for fragments): each count divided
+/// by its feature's length in kilobases and the library size in millions of reads.
+/// This is `None` for zero-length features.
+pub fn rpkm(counts: &[u64], lengths: &[Position], library_size: u64) -> Vec<Option<f64>> {
+    counts
+        .iter()
+        .zip(lengths)
+        .map(|(&count, &length)| {
+            (length > 0).then(|| count as f64 * 1e9 / (length as f64 * library_size as f64))
+        })
+        .collect()
+}
+
+/// Transcripts per million: each count divided by its feature's length (a rate of
+/// reads per basepair), as a proportion of the total rate of all features, times a
+/// million. Unlike [`cpm()`] and [`rpkm()`], this only depends on the reads over
+/// these features, and always sums to a million (if any are non-zero). Zero-length
+/// features have no rate, so they are `None`, and are left out of the total.
+pub fn tpm(counts: &[u64], lengths: &[Position]) -> Vec<Option<f64>> {
+    let rates: Vec<Option<f64>> = counts
+        .iter()
+        .zip(lengths)
+        .map(|(&count, &length)| (length > 0).then(|| count as f64 / length as f64))
+        .collect();
+    let total: f64 = rates.iter().flatten().sum();
+    rates
+        .iter()
+        .map(|rate| rate.map(|rate| rate * 1e6 / total))
+        .collect()
+}
+
+/// The formats statistics (e.g. [`RangeStats`]) can be written in.
+#[derive(Clone, Debug, ValueEnum)]
+pub enum StatsFormat {
+    /// A human-readable summary.
+    Text,
+    /// A machine-readable JSON object.
+    Json,
+    /// A long-format TSV table of sequence, statistic, and value.
+    Tsv,
+}
+
+/// The width of the bars of the largest bin in [`Histogram::write_plot()`].
+const PLOT_WIDTH: usize = 50;
+
+/// A bin of a [`Histogram`], counting the values in `[start, end)`. The last bin
+/// also counts values equal to its end.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct HistogramBin {
+    pub start: f64,
+    pub end: f64,
+    pub count: usize,
+}
+
+/// A histogram of values, e.g. range widths or scores.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Histogram {
+    pub bins: Vec<HistogramBin>,
+    /// The number of values that could not be binned: NaN values, and values that
+    /// are not positive on a log scale.
+    pub num_skipped: usize,
+}
+
+impl Histogram {
+    /// Bin `values` into `num_bins` bins of equal width spanning the range of the
+    /// values, or of equal width on a log10 scale if `log` is set. If all values
+    /// are equal, there is a single bin.
+    ///
+    /// # Errors
+    /// Returns [`GRangesError::NoRows`] if there are no values that can be binned.
+    pub fn new(values: &[f64], num_bins: NonZeroUsize, log: bool) -> Result<Self, GRangesError> {
+        let transform = |value: f64| if log { value.log10() } else { value };
+        let binnable = |value: &f64| !value.is_nan() && (!log || *value > 0.0);
+
+        let (lower, upper) = values
+            .iter()
+            .filter(|value| binnable(value))
+            .map(|&value| transform(value))
+            .fold(None, |bounds: Option<(f64, f64)>, value| match bounds {
+                Some((lower, upper)) => Some((lower.min(value), upper.max(value))),
+                None => Some((value, value)),
+            })
+            .ok_or(GRangesError::NoRows)?;
+
+        let num_bins = if lower == upper { 1 } else { num_bins.get() };
+        let bin_width = (upper - lower) / num_bins as f64;
+        let untransform = |value: f64| if log { 10f64.powf(value) } else { value };
+        let mut bins: Vec<HistogramBin> = (0..num_bins)
+            .map(|i| HistogramBin {
+                start: untransform(lower + i as f64 * bin_width),
+                end: untransform(lower + (i + 1) as f64 * bin_width),
+                count: 0,
+            })
+            .collect();
+
+        let mut num_skipped = 0;
+        for value in values {
+            if !binnable(value) {
+                num_skipped += 1;
+                continue;
+            }
+            let index = if bin_width > 0.0 {
+                ((transform(*value) - lower) / bin_width) as usize
+            } else {
+                0
+            };
+            bins[index.min(num_bins - 1)].count += 1;
+        }
+        Ok(Self { bins, num_skipped })
+    }
+
+    /// Write this histogram as a TSV of the start, end, and count of each bin,
+    /// with a header.
+    pub fn write_tsv(&self, mut writer: impl Write) -> Result<(), GRangesError> {
+        writeln!(writer, "start\tend\tcount")?;
+        for bin in &self.bins {
+            writeln!(writer, "{}\t{}\t{}", bin.start, bin.end, bin.count)?;
+        }
+        Ok(())
+    }
+
+    /// Write a quick plot of this histogram for viewing in a terminal, with a bar
+    /// for each bin.
+    pub fn write_plot(&self, mut writer: impl Write) -> Result<(), GRangesError> {
+        let max_count = self.bins.iter().map(|bin| bin.count).max().unwrap_or(0);
+        let labels: Vec<String> = self
+            .bins
+            .iter()
+            .map(|bin| format!("[{:.2}, {:.2})", bin.start, bin.end))
+            .collect();
+        let label_width = labels.iter().map(|label| label.len()).max().unwrap_or(0);
+        for (bin, label) in self.bins.iter().zip(labels) {
+            let bar_width = if max_count > 0 {
+                (bin.count * PLOT_WIDTH).div_ceil(max_count)
+            } else {
+                0
+            };
+            writeln!(
+                writer,
+                "{:>label_width$}  {:<PLOT_WIDTH$}  {}",
+                label,
+                "#".repeat(bar_width),
+                bin.count
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        io::BedlikeIterator,
+        ranges::{GenomicRangeRecord, GenomicRangeRecordEmpty},
+    };
+
+    fn stats(ranges: &[(&str, Position, Position)]) -> RangeStats {
+        let iter = ranges.iter().map(|&(seqname, start, end)| {
+            Ok(GenomicRangeRecordEmpty::new(seqname.to_string(), start, end))
+        });
+        RangeStats::from_ranges(iter).unwrap()
+    }
+
+    #[test]
+    fn test_range_stats() {
+        let stats = stats(&[
+            ("chr2", 0, 10),
+            ("chr1", 30, 40),
+            ("chr1", 0, 20),
+            ("chr1", 15, 25),
+        ]);
+        assert_eq!(stats.num_ranges, 4);
+        assert_eq!(stats.total_width, 50);
+        assert_eq!(stats.covered_bases, 45);
+        assert_eq!(stats.overlap_fraction, 0.5);
+        assert_eq!(stats.sequences[0].seqname, "chr2");
+        assert_eq!(stats.sequences[1].covered_bases, 35);
+
+        assert_eq!(stats.widths.min, 10);
+        assert_eq!(stats.widths.max, 20);
+        assert_eq!(stats.widths.mean, 12.5);
+        assert_eq!(stats.widths.median, 10);
+        // 20 is less than half of 50, but 20 + 10 is more
+        assert_eq!(stats.widths.n50, 10);
+    }
+
+    #[test]
+    fn test_sequence_summary() {
+        let ranges = [
+            ("chr2", 0, 10, Some(1.0)),
+            ("chr1", 30, 40, None),
+            ("chr1", 0, 20, Some(2.0)),
+            ("chr1", 15, 25, Some(4.0)),
+        ];
+        let iter = ranges.iter().map(|&(seqname, start, end, score)| {
+            Ok(GenomicRangeRecord::new(
+                seqname.to_string(),
+                start,
+                end,
+                score,
+            ))
+        });
+        let summaries = SequenceSummary::from_ranges(iter, |range| range.data).unwrap();
+        assert_eq!(summaries.keys().collect::<Vec<_>>(), vec!["chr2", "chr1"]);
+        assert_eq!(
+            summaries["chr1"],
+            SequenceSummary {
+                num_ranges: 3,
+                covered_bases: 35,
+                mean_score: Some(3.0),
+            }
+        );
+        assert_eq!(summaries["chr2"].covered_bases, 10);
+    }
+
+    #[test]
+    fn test_count_overlapping() {
+        // the first range contains both later ranges, which do not overlap each other
+        assert_eq!(count_overlapping(&[(0, 100), (10, 20), (30, 40)]), 3);
+        // book-ended ranges do not overlap
+        assert_eq!(count_overlapping(&[(0, 10), (10, 20)]), 0);
+    }
+
+    #[test]
+    fn test_count_overlapping_pairs() {
+        let grouped = |ranges: Vec<(Position, Position)>| {
+            IndexMap::from([("chr1".to_string(), ranges), ("chr2".to_string(), vec![(0, 10)])])
+        };
+        let left = grouped(vec![(0, 10), (20, 30)]);
+        let right = grouped(vec![(25, 40), (5, 25), (10, 20)]);
+        // on chr1, 0-10 overlaps 5-25, and 20-30 overlaps 25-40 and 5-25, but not
+        // the book-ended 10-20
+        assert_eq!(count_overlapping_pairs(&left, &right), 3 + 1);
+        assert_eq!(count_overlapping_pairs(&right, &left), 3 + 1);
+    }
+
+    #[test]
+    fn test_histogram() {
+        let bins = NonZeroUsize::new(4).unwrap();
+        let values = [0.0, 1.0, 2.5, 3.9, 4.0, f64::NAN];
+        let hist = Histogram::new(&values, bins, false).unwrap();
+        let counts: Vec<_> = hist.bins.iter().map(|bin| bin.count).collect();
+        // the maximum value is counted in the last bin
+        assert_eq!(counts, vec![1, 1, 1, 2]);
+        assert_eq!(hist.bins[1].start, 1.0);
+        assert_eq!(hist.num_skipped, 1);
+
+        let hist = Histogram::new(&[1.0, 10.0, 50.0, 100.0, 0.0], bins, true).unwrap();
+        let counts: Vec<_> = hist.bins.iter().map(|bin| bin.count).collect();
+        assert_eq!(counts, vec![1, 0, 1, 2]);
+        assert_eq!(hist.num_skipped, 1);
+
+        // equal values are put in a single bin
+        let hist = Histogram::new(&[5.0, 5.0], bins, false).unwrap();
+        assert_eq!(hist.bins.len(), 1);
+        assert_eq!(hist.bins[0].count, 2);
+    }
+
+    #[test]
+    fn test_normalization() {
+        let counts = [10, 20, 70];
+        let lengths = [1000, 2000, 7000];
+        assert_eq!(cpm(&counts, 100), vec![1e5, 2e5, 7e5]);
+        // the counts are proportional to the lengths
+        assert_eq!(
+            rpkm(&counts, &lengths, 100),
+            vec![Some(1e5), Some(1e5), Some(1e5)]
+        );
+        let assert_close = |values: Vec<Option<f64>>, expected: &[Option<f64>]| {
+            assert_eq!(values.len(), expected.len());
+            for (value, expected) in values.iter().zip(expected) {
+                match (value, expected) {
+                    (Some(value), Some(expected)) => {
+                        assert!((value - expected).abs() < 1e-6, "{} ≠ {}", value, expected)
+                    }
+                    _ => assert_eq!(value, expected),
+                }
+            }
+        };
+        assert_close(tpm(&counts, &lengths), &[Some(1e6 / 3.0); 3]);
+
+        let values = Normalization::Tpm.normalize(&[5, 5, 0], &[500, 1000, 100], 10);
+        assert_close(values, &[Some(2e6 / 3.0), Some(1e6 / 3.0), Some(0.0)]);
+        assert_eq!(
+            Normalization::Rpkm.normalize(&[5], &[500], 10),
+            vec![Some(1e6)]
+        );
+        assert_eq!(
+            Normalization::Cpm.normalize(&[5], &[500], 10),
+            vec![Some(5e5)]
+        );
+
+        // zero-length features have no RPKM or TPM, and don't count towards the total
+        assert_eq!(
+            Normalization::Rpkm.normalize(&[5, 2], &[500, 0], 10),
+            vec![Some(1e6), None]
+        );
+        let values = Normalization::Tpm.normalize(&[5, 2], &[500, 0], 10);
+        assert_close(values, &[Some(1e6), None]);
+    }
+
+    #[test]
+    fn test_inverted_range() {
+        let iter = BedlikeIterator::from_bytes("chr1\t0\t10\nchr1\t20\t15\n");
+        assert!(matches!(
+            RangeStats::from_ranges(iter),
+            Err(GRangesError::InvalidGenomicRange {
+                start: 20,
+                end: 15,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_no_rows() {
+        let iter = std::iter::empty::<Result<GenomicRangeRecordEmpty, GRangesError>>();
+        assert!(matches!(RangeStats::from_ranges(iter), Err(GRangesError::NoRows)));
+    }
+}
@@ -0,0 +1,574 @@
+//! Streaming joins over sorted inputs.
+//!
+//! In-memory joins (e.g. [`GRanges::left_overlaps()`]) load the right ranges into an
+//! overlap-query backend, which requires memory proportional to the number of right
+//! ranges. When both the left and right ranges are *sorted*, a join can instead be done
+//! with a single linear-time sweep over two streams of ranges (e.g. parsing iterators),
+//! holding in memory only those right ranges that could overlap the current left range.
+//!
+//! Inputs must be sorted by sequence, in the order of the sequences in the genome file
+//! (i.e. the order of the `seqlens` keys), and then by start position. This need not
+//! be lexicographic or natural chromosome order: e.g. input sorted with
+//! `sort -k1,1 -k2,2n` is only sorted if the genome file also lists its sequences in
+//! lexicographic order. Sort order is verified as ranges are read, and a
+//! [`GRangesError::UnsortedInput`] is returned if a range is out of order. Whether an
+//! input is sorted can also be checked beforehand with [`is_sorted()`], in a single
+//! pass that holds no ranges in memory.
+//!
+//! # Example
+//!
+//! ```
+//! use granges::{prelude::*, streaming::StreamingLeftOverlaps};
+//!
+//! let seqlens = seqlens! { "chr1" => 100, "chr2" => 100 };
+//! let left = Bed3Iterator::new("tests_data/bedtools/map_a.txt").unwrap();
+//! let right = Bed5Iterator::new("tests_data/bedtools/map_b.txt").unwrap();
+//!
+//! for result in StreamingLeftOverlaps::new(left, right, &seqlens).unwrap() {
+//!     let (left_range, overlaps) = result.unwrap();
+//!     // every overlapping right range is on the same sequence
+//!     assert!(overlaps.iter().all(|right| right.seqname == left_range.seqname));
+//! }
+//! ```
+//!
+//! [`GRanges::left_overlaps()`]: crate::traits::LeftOverlaps::left_overlaps
+
+use genomap::GenomeMap;
+use indexmap::IndexMap;
+use std::{cmp::Reverse, collections::BinaryHeap};
+
+use crate::{
+    error::GRangesError,
+    ranges::{GenomicRangeRecord, GenomicRangeRecordEmpty},
+    traits::GenomicRecord,
+    Position,
+};
+
+/// The order of sequences (i.e. chromosomes) of sorted input, which is the order of
+/// `seqlens` (i.e. the genome file).
+pub(crate) fn sequence_order(
+    seqlens: &IndexMap<String, Position>,
+) -> Result<GenomeMap<()>, GRangesError> {
+    let mut order = GenomeMap::new();
+    for seqname in seqlens.keys() {
+        order.insert(seqname, ())?;
+    }
+    Ok(order)
+}
+
+/// An iterator over [`Result<R, GRangesError>`] that verifies that ranges are
+/// sorted by sequence and then start position, returning a
+/// [`GRangesError::UnsortedInput`] error on the first out of order range.
+pub struct SortedRanges<I, R>
+where
+    I: Iterator<Item = Result<R, GRangesError>>,
+{
+    inner: I,
+    order: GenomeMap<()>,
+    // the sequence index and start position of the last range
+    last: Option<(usize, Position)>,
+}
+
+impl<I, R> SortedRanges<I, R>
+where
+    I: Iterator<Item = Result<R, GRangesError>>,
+    R: GenomicRecord,
+{
+    /// Create a new [`SortedRanges`] iterator, with the order of sequences
+    /// that of `seqlens`. Ranges on sequences not in `seqlens` will
+    /// raise a [`GRangesError::MissingSequence`] error.
+    pub fn new(inner: I, seqlens: &IndexMap<String, Position>) -> Result<Self, GRangesError> {
+        Ok(Self {
+            inner,
+            order: sequence_order(seqlens)?,
+            last: None,
+        })
+    }
+
+    /// Get the next range, with its sequence index.
+    fn next_indexed(&mut self) -> Option<Result<(usize, R), GRangesError>> {
+        let range = match self.inner.next()? {
+            Ok(range) => range,
+            Err(e) => return Some(Err(e)),
+        };
+        let Some(index) = self.order.get_index_by_name(range.seqname()) else {
+            return Some(Err(GRangesError::missing_sequence(range.seqname())));
+        };
+        if let Some(last) = self.last {
+            if (index, range.start()) < last {
+                let last_seqname = self.order.get_name_by_index(last.0).unwrap_or_default();
+                return Some(Err(GRangesError::UnsortedInput {
+                    seqname: range.seqname().to_string(),
+                    start: range.start(),
+                    previous_seqname: last_seqname.to_string(),
+                    previous_start: last.1,
+                }));
+            }
+        }
+        self.last = Some((index, range.start()));
+        Some(Ok((index, range)))
+    }
+}
+
+impl<I, R> Iterator for SortedRanges<I, R>
+where
+    I: Iterator<Item = Result<R, GRangesError>>,
+    R: GenomicRecord,
+{
+    type Item = Result<R, GRangesError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_indexed()
+            .map(|result| result.map(|(_, range)| range))
+    }
+}
+
+/// Check whether the ranges of `iter` are sorted (see the [module
+/// documentation](self)), with the order of sequences determined by `seqlens`. This
+/// reads every range, but holds none of them in memory. Errors other than unsorted
+/// ranges, e.g. ranges on sequences not in `seqlens`, are returned.
+pub fn is_sorted<I, R>(iter: I, seqlens: &IndexMap<String, Position>) -> Result<bool, GRangesError>
+where
+    I: Iterator<Item = Result<R, GRangesError>>,
+    R: GenomicRecord,
+{
+    for result in SortedRanges::new(iter, seqlens)? {
+        match result {
+            Ok(_) => {}
+            Err(GRangesError::UnsortedInput { .. }) => return Ok(false),
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(true)
+}
+
+/// A streaming left overlap join between two sorted iterators of ranges.
+///
+/// Each item is a left range, and all the right ranges that overlap it (in sorted
+/// order). Like [`LeftOverlaps`], every left range is returned, even if it has no
+/// overlapping right ranges. Memory usage is proportional to the largest number of
+/// right ranges that overlap a left range, rather than the total number of right ranges.
+///
+/// [`LeftOverlaps`]: crate::traits::LeftOverlaps
+pub struct StreamingLeftOverlaps<IL, IR, L, R>
+where
+    IL: Iterator<Item = Result<L, GRangesError>>,
+    IR: Iterator<Item = Result<R, GRangesError>>,
+{
+    left: SortedRanges<IL, L>,
+    right: SortedRanges<IR, R>,
+    // the next right range that is not yet in the window
+    next_right: Option<(usize, R)>,
+    // right ranges on the current sequence that may overlap the current or later left ranges
+    window: Vec<R>,
+    window_seqname: Option<usize>,
+}
+
+impl<IL, IR, L, R> StreamingLeftOverlaps<IL, IR, L, R>
+where
+    IL: Iterator<Item = Result<L, GRangesError>>,
+    IR: Iterator<Item = Result<R, GRangesError>>,
+    L: GenomicRecord,
+    R: GenomicRecord,
+{
+    /// Create a new [`StreamingLeftOverlaps`] join of the `left` and `right` ranges,
+    /// both of which must be sorted in the order of sequences determined by `seqlens`.
+    pub fn new(
+        left: IL,
+        right: IR,
+        seqlens: &IndexMap<String, Position>,
+    ) -> Result<Self, GRangesError> {
+        Ok(Self {
+            left: SortedRanges::new(left, seqlens)?,
+            right: SortedRanges::new(right, seqlens)?,
+            next_right: None,
+            window: Vec::new(),
+            window_seqname: None,
+        })
+    }
+}
+
+impl<IL, IR, L, R> Iterator for StreamingLeftOverlaps<IL, IR, L, R>
+where
+    IL: Iterator<Item = Result<L, GRangesError>>,
+    IR: Iterator<Item = Result<R, GRangesError>>,
+    L: GenomicRecord,
+    R: GenomicRecord,
+{
+    type Item = Result<(L, Vec<R>), GRangesError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (seqname_index, left) = match self.left.next_indexed()? {
+            Ok(indexed) => indexed,
+            Err(e) => return Some(Err(e)),
+        };
+
+        if self.window_seqname != Some(seqname_index) {
+            self.window.clear();
+            self.window_seqname = Some(seqname_index);
+        }
+        // Since left ranges are sorted by start, right ranges that end before
+        // this left range starts cannot overlap any later left range.
+        self.window.retain(|right| right.end() > left.start());
+
+        // Advance the right ranges, until one starts after this left range ends.
+        loop {
+            if self.next_right.is_none() {
+                match self.right.next_indexed() {
+                    None => break,
+                    Some(Ok(indexed)) => self.next_right = Some(indexed),
+                    Some(Err(e)) => return Some(Err(e)),
+                }
+            }
+            let (right_index, right) = self.next_right.as_ref().unwrap();
+            if *right_index > seqname_index
+                || (*right_index == seqname_index && right.start() >= left.end())
+            {
+                // this right range is ahead of the left range
+                break;
+            }
+            let (right_index, right) = self.next_right.take().unwrap();
+            if right_index == seqname_index && right.end() > left.start() {
+                self.window.push(right);
+            }
+        }
+
+        let overlaps = self
+            .window
+            .iter()
+            .filter(|right| right.start() < left.end())
+            .cloned()
+            .collect();
+        Some(Ok((left, overlaps)))
+    }
+}
+
+/// An iterator over genomic windows of `width` basepairs, every `step` basepairs,
+/// in sorted order (i.e. the order [`StreamingLeftOverlaps`] requires). Unlike
+/// [`GRangesEmpty::from_windows()`], the windows are created as they are needed,
+/// rather than all at once.
+///
+/// The last window on each sequence is truncated at the end of the sequence,
+/// or with `chop`, dropped if it would extend past the end.
+///
+/// [`GRangesEmpty::from_windows()`]: crate::granges::GRangesEmpty::from_windows
+pub struct GenomicWindows {
+    sequences: std::vec::IntoIter<(String, Position)>,
+    current: Option<(String, Position)>,
+    start: Position,
+    width: Position,
+    step: Position,
+    chop: bool,
+}
+
+impl GenomicWindows {
+    /// Create a new [`GenomicWindows`] iterator over the sequences in `seqlens`. If
+    /// `step` is `None`, windows do not overlap (i.e. the step is the `width`).
+    pub fn new(
+        seqlens: &IndexMap<String, Position>,
+        width: Position,
+        step: Option<Position>,
+        chop: bool,
+    ) -> Result<Self, GRangesError> {
+        let step = step.unwrap_or(width);
+        if width == 0 || step == 0 {
+            return Err(GRangesError::ZeroWindowSize);
+        }
+        let sequences: Vec<_> = sequence_order(seqlens)?
+            .names()
+            .into_iter()
+            .map(|seqname| {
+                let length = seqlens[&seqname];
+                (seqname, length)
+            })
+            .collect();
+        Ok(Self {
+            sequences: sequences.into_iter(),
+            current: None,
+            start: 0,
+            width,
+            step,
+            chop,
+        })
+    }
+}
+
+impl Iterator for GenomicWindows {
+    type Item = GenomicRangeRecordEmpty;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some((seqname, length)) = &self.current {
+                let end = self.start + self.width;
+                if self.start < *length && (end <= *length || !self.chop) {
+                    let window =
+                        GenomicRangeRecordEmpty::new(seqname.clone(), self.start, end.min(*length));
+                    // the window truncated at the sequence end is the last one
+                    self.start = if end >= *length {
+                        *length
+                    } else {
+                        self.start + self.step
+                    };
+                    return Some(window);
+                }
+            }
+            self.current = Some(self.sequences.next()?);
+            self.start = 0;
+        }
+    }
+}
+
+/// An iterator over the *depth* (the number of ranges overlapping each position)
+/// along every sequence of a genome, from a sorted iterator of ranges, as runs of
+/// constant depth. The runs cover every sequence, in sorted order, including
+/// regions of zero depth. Only the ends of the ranges overlapping the current
+/// position are held in memory, so memory is proportional to the maximum depth,
+/// not the size of the genome.
+pub struct StreamingDepth<I, R>
+where
+    I: Iterator<Item = Result<R, GRangesError>>,
+{
+    ranges: SortedRanges<I, R>,
+    sequences: Vec<(String, Position)>,
+    // the index of the current sequence, and the start of the next run on it
+    seq_index: usize,
+    position: Position,
+    // the ends of the ranges overlapping the current position
+    ends: BinaryHeap<Reverse<Position>>,
+    // the next range (with its sequence index) not yet added to the ends
+    pending: Option<(usize, R)>,
+    exhausted: bool,
+}
+
+impl<I, R> StreamingDepth<I, R>
+where
+    I: Iterator<Item = Result<R, GRangesError>>,
+    R: GenomicRecord,
+{
+    /// Create a new [`StreamingDepth`] iterator over the sequences in `seqlens`.
+    pub fn new(ranges: I, seqlens: &IndexMap<String, Position>) -> Result<Self, GRangesError> {
+        let ranges = SortedRanges::new(ranges, seqlens)?;
+        let sequences = ranges
+            .order
+            .names()
+            .into_iter()
+            .map(|seqname| {
+                let length = seqlens[&seqname];
+                (seqname, length)
+            })
+            .collect();
+        Ok(Self {
+            ranges,
+            sequences,
+            seq_index: 0,
+            position: 0,
+            ends: BinaryHeap::new(),
+            pending: None,
+            exhausted: false,
+        })
+    }
+}
+
+impl<I, R> Iterator for StreamingDepth<I, R>
+where
+    I: Iterator<Item = Result<R, GRangesError>>,
+    R: GenomicRecord,
+{
+    type Item = Result<GenomicRangeRecord<usize>, GRangesError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (seqname, length) = self.sequences.get(self.seq_index)?;
+            if self.pending.is_none() && !self.exhausted {
+                match self.ranges.next_indexed() {
+                    Some(Ok(indexed)) => self.pending = Some(indexed),
+                    Some(Err(e)) => return Some(Err(e)),
+                    None => self.exhausted = true,
+                }
+            }
+
+            // add the ranges starting at the current position
+            if let Some((index, range)) = &self.pending {
+                if *index < self.seq_index
+                    || (*index == self.seq_index && range.start() <= self.position)
+                {
+                    if *index == self.seq_index && range.end() > self.position {
+                        self.ends.push(Reverse(range.end().min(*length)));
+                    }
+                    self.pending = None;
+                    continue;
+                }
+            }
+            while self
+                .ends
+                .peek()
+                .is_some_and(|Reverse(end)| *end <= self.position)
+            {
+                self.ends.pop();
+            }
+
+            if self.position >= *length {
+                self.seq_index += 1;
+                self.position = 0;
+                self.ends.clear();
+                continue;
+            }
+
+            // the run ends where the next range starts or ends, or the sequence ends
+            let mut end = *length;
+            if let Some(Reverse(next_end)) = self.ends.peek() {
+                end = end.min(*next_end);
+            }
+            if let Some((index, range)) = &self.pending {
+                if *index == self.seq_index {
+                    end = end.min(range.start());
+                }
+            }
+            let run = GenomicRangeRecord {
+                seqname: seqname.clone(),
+                start: self.position,
+                end,
+                data: self.ends.len(),
+            };
+            self.position = end;
+            return Some(Ok(run));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{prelude::*, ranges::GenomicRangeRecordEmpty, testing::random_granges};
+
+    fn records(gr: &GRangesEmpty<VecRangesEmpty>) -> Vec<GenomicRangeRecordEmpty> {
+        let seqnames = gr.seqnames();
+        gr.iter_ranges()
+            .map(|range| {
+                GenomicRangeRecordEmpty::new(
+                    seqnames[range.seqname_index].clone(),
+                    range.start,
+                    range.end,
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_streaming_matches_in_memory() {
+        let seqlens = seqlens! { "chr1" => 10_000, "chr2" => 5_000, "chr10" => 5_000 };
+        let left = random_granges(&seqlens, 300).unwrap().sort();
+        let right = random_granges(&seqlens, 300).unwrap().sort();
+
+        let left_records = records(&left);
+        let right_records = records(&right);
+        let streaming = StreamingLeftOverlaps::new(
+            left_records.into_iter().map(Ok),
+            right_records.into_iter().map(Ok),
+            &seqlens,
+        )
+        .unwrap();
+        let streaming_counts: Vec<usize> =
+            streaming.map(|result| result.unwrap().1.len()).collect();
+
+        let joined = left.left_overlaps(&right.into_coitrees().unwrap()).unwrap();
+        let counts: Vec<usize> = joined
+            .data
+            .unwrap()
+            .iter()
+            .map(|join| join.num_overlaps())
+            .collect();
+        assert_eq!(streaming_counts, counts);
+    }
+
+    #[test]
+    fn test_streaming_unsorted() {
+        let seqlens = seqlens! { "chr1" => 100, "chr2" => 100 };
+        let left = vec![
+            GenomicRangeRecordEmpty::new("chr2".to_string(), 0, 10),
+            GenomicRangeRecordEmpty::new("chr1".to_string(), 0, 10),
+        ];
+        let mut join = StreamingLeftOverlaps::new(
+            left.into_iter().map(Ok),
+            std::iter::empty::<Result<GenomicRangeRecordEmpty, GRangesError>>(),
+            &seqlens,
+        )
+        .unwrap();
+        assert!(join.next().unwrap().is_ok());
+        assert!(matches!(
+            join.next().unwrap(),
+            Err(GRangesError::UnsortedInput { .. })
+        ));
+    }
+
+    #[test]
+    fn test_is_sorted() {
+        let seqlens = seqlens! { "chr1" => 100, "chr2" => 100, "chr10" => 100 };
+        let ranges = |ranges: &[(&str, Position)]| -> Vec<Result<_, GRangesError>> {
+            ranges
+                .iter()
+                .map(|&(seqname, start)| {
+                    Ok(GenomicRangeRecordEmpty::new(
+                        seqname.to_string(),
+                        start,
+                        start + 1,
+                    ))
+                })
+                .collect()
+        };
+        let sorted = ranges(&[("chr1", 0), ("chr1", 5), ("chr2", 0), ("chr10", 0)]);
+        assert!(is_sorted(sorted.into_iter(), &seqlens).unwrap());
+        let unsorted = ranges(&[("chr1", 5), ("chr1", 0)]);
+        assert!(!is_sorted(unsorted.into_iter(), &seqlens).unwrap());
+        let missing = ranges(&[("chr1", 0), ("chr3", 0)]);
+        assert!(is_sorted(missing.into_iter(), &seqlens).is_err());
+    }
+
+    #[test]
+    fn test_genomic_windows() {
+        let seqlens = seqlens! { "chr2" => 10, "chr1" => 12 };
+        let windows: Vec<_> = GenomicWindows::new(&seqlens, 5, Some(4), false)
+            .unwrap()
+            .map(|window| (window.seqname, window.start, window.end))
+            .collect();
+        let expected = vec![
+            ("chr1".to_string(), 0, 5),
+            ("chr1".to_string(), 4, 9),
+            ("chr1".to_string(), 8, 12),
+            ("chr2".to_string(), 0, 5),
+            ("chr2".to_string(), 4, 9),
+            ("chr2".to_string(), 8, 10),
+        ];
+        assert_eq!(windows, expected);
+
+        let chopped = GenomicWindows::new(&seqlens, 5, None, true).unwrap();
+        assert_eq!(chopped.count(), 4);
+    }
+
+    #[test]
+    fn test_streaming_depth() {
+        let seqlens = seqlens! { "chr2" => 5, "chr1" => 10 };
+        let ranges = vec![
+            GenomicRangeRecordEmpty::new("chr1".to_string(), 2, 5),
+            GenomicRangeRecordEmpty::new("chr1".to_string(), 3, 8),
+            GenomicRangeRecordEmpty::new("chr1".to_string(), 3, 4),
+        ];
+        let runs: Vec<_> = StreamingDepth::new(ranges.into_iter().map(Ok), &seqlens)
+            .unwrap()
+            .map(|run| {
+                let run = run.unwrap();
+                (run.seqname, run.start, run.end, run.data)
+            })
+            .collect();
+        let expected = vec![
+            ("chr1".to_string(), 0, 2, 0),
+            ("chr1".to_string(), 2, 3, 1),
+            ("chr1".to_string(), 3, 4, 3),
+            ("chr1".to_string(), 4, 5, 2),
+            ("chr1".to_string(), 5, 8, 1),
+            ("chr1".to_string(), 8, 10, 0),
+            ("chr2".to_string(), 0, 5, 0),
+        ];
+        assert_eq!(runs, expected);
+    }
+}
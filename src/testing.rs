@@ -1,4 +1,16 @@
-//! Test cases and test utility functions.
+//! Test cases and test utility functions, for the tests of this crate and of
+//! downstream crates building on it. This module requires the `testing` feature,
+//! e.g. with a dev-dependency on `granges = { version = "...", features = ["testing"] }`.
+//!
+//! It includes:
+//!
+//!  - Random data generators, e.g. [`random_granges()`] and [`random_bed3file()`],
+//!    which are reproducible: they use [`get_rng()`], seeded from the `TEST_SEED`
+//!    environment variable (or a fixed default).
+//!  - Temporary file helpers, e.g. [`temp_bedfile()`].
+//!  - Small test cases with known contents, e.g. [`granges_test_case_01()`].
+//!  - The [`assert_granges_eq!`](crate::assert_granges_eq) assertion, which reports
+//!    the first difference between two [`GRanges`] objects.
 //!
 
 use std::{
@@ -10,17 +22,17 @@ use std::{
 use tempfile::{Builder, NamedTempFile};
 
 use crate::{
-    commands::granges_random_bed,
     create_granges_with_seqlens,
     error::GRangesError,
     granges::GRangesEmpty,
-    io::{parsers::bed::Bed5Addition, InputStream},
+    io::{file::read_seqlens_from_bytes, parsers::bed::Bed5Addition, InputStream, BED_TSV},
     prelude::{GRanges, VecRangesIndexed},
     ranges::{
         coitrees::COITrees,
         vec::{VecRanges, VecRangesEmpty},
-        RangeEmpty,
+        RangeEmpty, RangeIndexed,
     },
+    traits::GenomicRangesTsvSerialize,
     Position,
 };
 #[cfg(feature = "ndarray")]
@@ -28,10 +40,47 @@ use genomap::GenomeMap;
 use indexmap::IndexMap;
 #[cfg(feature = "ndarray")]
 use ndarray::{Array1, Array2};
-use rand::{distributions::Uniform, rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+pub use crate::random::{
+    random_granges_mock_bed5_with_rng, random_granges_with_rng, random_range, MAX_LEN, MIN_LEN,
+};
+
+/// The human genome (hg38) sequence lengths, of `tests_data/hg38_seqlens.tsv`.
+const HG38_SEQLENS: &[u8] = include_bytes!("../tests_data/hg38_seqlens.tsv");
 
-// Use a default random seed, unless one is in the environment.
-fn get_rng() -> StdRng {
+/// Assert that two [`GRanges`] (or [`GRangesEmpty`]) objects have the same ranges
+/// and data, comparing float data columns within an optional
+/// [`Tolerance`](crate::comparison::Tolerance) (by default, exactly). On failure,
+/// this panics with the first difference (see [`GRanges::first_difference()`]).
+///
+/// ```
+/// use granges::{assert_granges_eq, comparison::Tolerance, testing::granges_test_case_01};
+///
+/// let gr = granges_test_case_01();
+/// assert_granges_eq!(gr, gr.clone());
+/// assert_granges_eq!(gr, gr.clone(), &Tolerance::new(1e-6));
+/// ```
+#[macro_export]
+macro_rules! assert_granges_eq {
+    ($left:expr, $right:expr $(,)?) => {
+        $crate::assert_granges_eq!($left, $right, &$crate::comparison::Tolerance::exact())
+    };
+    ($left:expr, $right:expr, $tolerance:expr $(,)?) => {
+        if let Some(difference) = $left.first_difference(&$right, $tolerance) {
+            panic!(
+                "assertion failed: `{} == {}`: {}",
+                stringify!($left),
+                stringify!($right),
+                difference
+            );
+        }
+    };
+}
+
+/// Get a random number generator for tests, with a default random seed, unless one
+/// is in the environment (`TEST_SEED`).
+pub fn get_rng() -> StdRng {
     let seed: u64 = env::var("TEST_SEED")
         .unwrap_or_else(|_| "13".to_string())
         .parse()
@@ -59,10 +108,6 @@ pub fn granges_binary_path() -> PathBuf {
 // The tradeoff is catching stochastic errors vs test time.
 pub const NRANDOM_RANGES: usize = 10000;
 
-// range length
-pub const MIN_LEN: Position = 1;
-pub const MAX_LEN: Position = 1000;
-
 // number of chromosome sequences
 pub const NCHROM: usize = 22;
 
@@ -70,21 +115,13 @@ pub const NCHROM: usize = 22;
 pub const MIN_CHROM_LEN: Position = 50_000_000;
 pub const MAX_CHROM_LEN: Position = 250_000_000;
 
-/// Build a random range start/end on a sequence of `max_len`.
-/// 0-indexed, right exclusive
-pub fn random_range(chrom_len: Position, rng: &mut StdRng) -> (Position, Position) {
-    let len = rng.gen_range(MIN_LEN..MAX_LEN);
-    let start = rng.gen_range(0..chrom_len - len + 1);
-    (start, start + len)
-}
-
 /// Build random sequence lengths
-pub fn random_seqlen(rng: &mut StdRng) -> Position {
+pub fn random_seqlen(rng: &mut impl Rng) -> Position {
     rng.gen_range(MIN_CHROM_LEN..=MAX_CHROM_LEN)
 }
 
 /// Sample a random chromosome
-pub fn random_chrom(rng: &mut StdRng) -> String {
+pub fn random_chrom(rng: &mut impl Rng) -> String {
     format!("chr{}", rng.gen_range(1..NCHROM + 1))
 }
 
@@ -101,40 +138,23 @@ pub fn random_vecranges(n: usize) -> VecRanges<RangeEmpty> {
     vr
 }
 
+/// Build random [`VecRanges`] of [`RangeIndexed`] ranges, with indices `0..n`.
+pub fn random_vecranges_indexed(n: usize) -> VecRanges<RangeIndexed> {
+    let ranges = random_vecranges(n);
+    let mut vr = VecRanges::new(ranges.length);
+    for (index, range) in ranges.ranges.into_iter().enumerate() {
+        vr.push_range(RangeIndexed::new(range.start, range.end, index));
+    }
+    vr
+}
+
 /// Build a random [`GRangesEmpty`] using a set of
 /// sequence lengths.
 pub fn random_granges(
     seqlens: &IndexMap<String, Position>,
     num: usize,
 ) -> Result<GRangesEmpty<VecRangesEmpty>, GRangesError> {
-    let mut rng = get_rng();
-
-    let mut gr = GRangesEmpty::new_vec(seqlens);
-
-    let seqnames: Vec<String> = seqlens.keys().cloned().collect();
-    for _ in 0..num {
-        let seqname = seqnames.choose(&mut rng).unwrap();
-        let chrom_len = *seqlens
-            .get(seqname)
-            .ok_or_else(|| GRangesError::MissingSequence(seqname.clone()))?;
-        let (start, end) = random_range(chrom_len, &mut rng);
-        gr.push_range(seqname, start, end)?;
-    }
-    Ok(gr)
-}
-
-/// Generate random strings, e.g. for mock feature names.
-fn generate_random_string(n: usize, rng: &mut StdRng) -> String {
-    let letters: Vec<char> = ('a'..='z').collect();
-    let letters_dist = Uniform::from(0..letters.len());
-
-    (0..n).map(|_| letters[rng.sample(letters_dist)]).collect()
-}
-
-/// Generate a random float value, e.g. for a mock BED "score".
-fn generate_random_uniform(start: f64, end: f64, rng: &mut StdRng) -> f64 {
-    let uniform = Uniform::new(start, end); // Specify the range
-    rng.sample(uniform)
+    random_granges_with_rng(seqlens, num, &mut get_rng())
 }
 
 /// Build a random [`GRanges`] using a set of sequence lengths,
@@ -143,24 +163,7 @@ pub fn random_granges_mock_bed5(
     seqlens: &IndexMap<String, Position>,
     num: usize,
 ) -> Result<GRanges<VecRangesIndexed, Vec<Bed5Addition>>, GRangesError> {
-    let mut rng = get_rng();
-
-    let mut gr = GRanges::new_vec(seqlens);
-
-    let seqnames: Vec<String> = seqlens.keys().cloned().collect();
-    for _ in 0..num {
-        let seqname = seqnames.choose(&mut rng).unwrap();
-        let chrom_len = *seqlens
-            .get(seqname)
-            .ok_or_else(|| GRangesError::MissingSequence(seqname.clone()))?;
-        let (start, end) = random_range(chrom_len, &mut rng);
-        let bed5_cols = Bed5Addition {
-            name: generate_random_string(8, &mut rng),
-            score: Some(generate_random_uniform(0.0, 1.0, &mut rng)),
-        };
-        gr.push_range(seqname, start, end, bed5_cols)?;
-    }
-    Ok(gr)
+    random_granges_mock_bed5_with_rng(seqlens, num, &mut get_rng())
 }
 
 /// Build random [`COITrees`] from a random [`VecRanges`].
@@ -199,34 +202,32 @@ pub fn copy_tempfile_for_inspection(tempfile: impl Into<PathBuf>, new_file_name:
     }
 }
 
+/// The human genome (hg38) sequence lengths, used by [`random_bed3file()`] and
+/// [`random_bed5file()`].
+pub fn hg38_seqlens() -> IndexMap<String, Position> {
+    read_seqlens_from_bytes(HG38_SEQLENS).expect("Internal error: please report")
+}
+
 /// Create a random BED3 file based on the hg38 sequence lengths, and write to disk.
+/// The ranges are sorted.
 pub fn random_bed3file(length: usize) -> NamedTempFile {
     let temp_bedfile = temp_bedfile();
-    granges_random_bed(
-        "tests_data/hg38_seqlens.tsv",
-        length,
-        Some(temp_bedfile.path()),
-        true,
-        false,
-    )
-    .expect("could not generate random BED file");
+    random_granges_with_rng(&hg38_seqlens(), length, &mut get_rng())
+        .and_then(|gr| gr.sort().write_to_tsv(Some(temp_bedfile.path()), &BED_TSV))
+        .expect("could not generate random BED file");
     temp_bedfile
 }
 
 /// Create a random BED5 file based on the hg38 sequence lengths, and write to disk.
+/// The ranges are sorted.
 ///
 /// The feature names are random lowercase characters, and the scores are
 /// random floats.
 pub fn random_bed5file(length: usize) -> NamedTempFile {
     let temp_bedfile = temp_bedfile();
-    granges_random_bed(
-        "tests_data/hg38_seqlens.tsv",
-        length,
-        Some(temp_bedfile.path()),
-        true,
-        true,
-    )
-    .expect("could not generate random BED file");
+    random_granges_mock_bed5_with_rng(&hg38_seqlens(), length, &mut get_rng())
+        .and_then(|gr| gr.sort().write_to_tsv(Some(temp_bedfile.path()), &BED_TSV))
+        .expect("could not generate random BED file");
     temp_bedfile
 }
 
@@ -348,9 +349,7 @@ pub fn random_array2_sequences(n: usize) -> GenomeMap<Array2<f64>> {
 
 #[cfg(test)]
 mod tests {
-    use crate::{
-        granges::GRanges, io::Bed5Iterator, seqlens, test_utilities::granges_test_case_01,
-    };
+    use crate::{granges::GRanges, io::Bed5Iterator, seqlens, testing::granges_test_case_01};
 
     #[test]
     fn test_test_case_01() {
@@ -6,14 +6,14 @@ use std::path::PathBuf;
 use indexmap::IndexMap;
 
 use crate::{
-    data::DatumType,
+    data::{columns::Column, DatumType},
     error::GRangesError,
     granges::GRanges,
     io::{
         parsers::{FilteredRanges, UnwrappedRanges},
         tsv::TsvConfig,
     },
-    join::LeftGroupedJoin,
+    join::{JoinFilter, LeftGroupedJoin},
     prelude::VecRangesIndexed,
     ranges::GenomicRangeRecord,
     Position, PositionOffset,
@@ -44,7 +44,23 @@ pub trait AsGRangesRef<'a, C, T> {
 pub trait LeftOverlaps<'a, Right> {
     type Output;
 
-    fn left_overlaps(self, right: &'a Right) -> Result<Self::Output, GRangesError>;
+    /// Conduct a left overlap join, joining each left range with every right range
+    /// that overlaps it.
+    fn left_overlaps(self, right: &'a Right) -> Result<Self::Output, GRangesError>
+    where
+        Self: Sized,
+    {
+        self.left_overlaps_with(right, &JoinFilter::default())
+    }
+
+    /// Conduct a left overlap join, joining each left range with every right range
+    /// whose overlap with it passes `filter`. Left ranges without any such right
+    /// ranges are still kept, with no joined ranges.
+    fn left_overlaps_with(
+        self,
+        right: &'a Right,
+        filter: &JoinFilter,
+    ) -> Result<Self::Output, GRangesError>;
 }
 
 /// The [`GenomicRangesTsvSerialize`] trait defines how to convert a [`GRanges<R, T>`]
@@ -58,6 +74,16 @@ pub trait GenomicRangesTsvSerialize<'a, C: RangeContainer> {
     ) -> Result<(), GRangesError>;
 }
 
+/// The [`TsvFormat`] trait defines how a data element is formatted as the extra
+/// columns of a BED-like TSV record, with the missing value string and float
+/// precision of a [`TsvConfig`]. This is used by [`GRanges::to_bedlike()`].
+///
+/// [`GRanges::to_bedlike()`]: crate::granges::GRanges::to_bedlike
+pub trait TsvFormat {
+    /// Append the formatted column(s) of this element to `columns`.
+    fn write_columns(&self, columns: &mut Vec<String>, config: &TsvConfig);
+}
+
 /// The [`GenericRange`] trait defines common functionality for all range types.
 pub trait GenericRange: Clone {
     fn start(&self) -> Position;
@@ -124,6 +150,16 @@ pub trait GenericRange: Clone {
     }
 }
 
+/// The [`GenomicRecord`] trait defines access to the sequence name of parsed range
+/// records, i.e. [`GenomicRangeRecord`] and [`GenomicRangeRecordEmpty`], which store
+/// their sequence name rather than a sequence name index.
+///
+/// [`GenomicRangeRecordEmpty`]: crate::ranges::GenomicRangeRecordEmpty
+pub trait GenomicRecord: GenericRange {
+    /// Returns the sequence name of this record.
+    fn seqname(&self) -> &str;
+}
+
 /// The [`GenericGenomicRange`] extends sequence name comparison and related
 /// functionality to [`GenericRange`].
 // TODO: to do this right and have it be useful we need to push
@@ -191,10 +227,15 @@ pub trait IntoDatumType {
     fn into_data_type(self) -> DatumType;
 }
 
-// TODO
+/// The [`Selection`] trait gives data container elements (e.g. the additional
+/// columns of a BED5 file) a column schema, so individual columns can be
+/// addressed by name. See [`crate::data::columns`].
 pub trait Selection {
-    fn select_by_name(&self, name: &str) -> DatumType;
-    fn select(&self, names: &[String]) -> Vec<DatumType> {
+    /// The ordered, named and typed columns of this type.
+    fn schema() -> &'static [Column];
+    /// Select the column `name`, returning `None` if there is no such column.
+    fn select_by_name(&self, name: &str) -> Option<DatumType>;
+    fn select(&self, names: &[String]) -> Vec<Option<DatumType>> {
         names.iter().map(|name| self.select_by_name(name)).collect()
     }
 }
@@ -241,6 +282,45 @@ pub trait RangeContainer {
     fn sequence_length(&self) -> Position;
 }
 
+/// The [`OverlapQuery`] trait defines the *overlap-query backend* interface: range
+/// containers that can efficiently find all the ranges that overlap a query range.
+/// Overlap operations (e.g. [`GRanges::filter_overlaps()`] and [`LeftOverlaps`]) are
+/// generic over this trait, so the backend can be chosen at construction, since
+/// different workloads favor different data structures.
+///
+/// GRanges provides the following backends:
+///
+///  - [`COITrees`], cache-oblivious interval trees (the default, see [`GRanges::into_coitrees()`]).
+///  - [`Lapper`], a sorted vector with a maximum range width, based on
+///    [rust-lapper](https://github.com/sstadick/rust-lapper), which is fast for
+///    ranges of similar widths (see [`GRanges::into_lapper()`]).
+///  - [`NCList`], a nested containment list (see [`GRanges::into_nclist()`]).
+///  - [`SoaRanges`], a compact struct-of-arrays sorted vector, using the same algorithm
+///    as [`Lapper`] (see [`GRanges::into_soa()`]).
+///
+/// [`COITrees`]: crate::ranges::coitrees::COITrees
+/// [`Lapper`]: crate::ranges::lapper::Lapper
+/// [`NCList`]: crate::ranges::nclist::NCList
+/// [`SoaRanges`]: crate::ranges::soa::SoaRanges
+pub trait OverlapQuery: RangeContainer {
+    /// The type of the overlapping ranges passed to the visit function.
+    type OverlapRangeType: GenericRange;
+
+    /// Call `visit` on every range that overlaps the right-exclusive range
+    /// `[start, end)`.
+    fn query_overlaps<F>(&self, start: Position, end: Position, visit: F)
+    where
+        F: FnMut(&Self::OverlapRangeType);
+
+    /// Return the number of ranges that overlap the right-exclusive range
+    /// `[start, end)`.
+    fn count_overlaps(&self, start: Position, end: Position) -> usize {
+        let mut count = 0;
+        self.query_overlaps(start, end, |_| count += 1);
+        count
+    }
+}
+
 /// Marker trait for data container.
 /// This is presently not used for much, but is useful to indicate certain
 /// type combinations are data containers.
@@ -5,7 +5,7 @@ use granges::{
     io::parsers::bed::bed_missing,
     prelude::{read_seqlens, BedlikeIterator, GRanges, GenomicRangesFile, TsvRecordIterator},
     ranges::GenomicRangeRecord,
-    test_utilities::{granges_binary_path, random_bed3file, random_bed5file, temp_bedfile},
+    testing::{get_rng, granges_binary_path, random_bed3file, random_bed5file, temp_bedfile},
     Position,
 };
 use indexmap::IndexMap;
@@ -148,6 +148,7 @@ fn test_random_bed3file_filetype_detect() {
         Some(&random_bedfile_path),
         true,
         false,
+        &mut get_rng(),
     )
     .expect("could not generate random BED file");
 
@@ -169,6 +170,7 @@ fn test_against_bedtools_slop() {
         Some(&random_bedfile_path),
         true,
         false,
+        &mut get_rng(),
     )
     .expect("could not generate random BED file");
 
@@ -224,6 +226,7 @@ fn test_against_bedtools_intersect_wa() {
         Some(&random_bedfile_right),
         true,
         false,
+        &mut get_rng(),
     )
     .expect("could not generate random BED file");
 
@@ -255,6 +258,106 @@ fn test_against_bedtools_intersect_wa() {
     assert_stdout_eq!(bedtools_output, granges_output);
 }
 
+/// Test bedtools intersect -a <left> -b <right> -v
+/// against
+/// granges filter --genome <genome> --left <left> --right <right> --invert
+#[test]
+fn test_against_bedtools_intersect_v() {
+    let num_ranges = 100_000;
+
+    let random_bedfile_left_tempfile = random_bed3file(num_ranges);
+    let random_bedfile_right_tempfile = random_bed3file(num_ranges);
+    let random_bedfile_left = random_bedfile_left_tempfile.path();
+    let random_bedfile_right = random_bedfile_right_tempfile.path();
+
+    granges_random_bed(
+        "tests_data/hg38_seqlens.tsv",
+        num_ranges,
+        Some(&random_bedfile_right),
+        true,
+        false,
+        &mut get_rng(),
+    )
+    .expect("could not generate random BED file");
+
+    let bedtools_output = Command::new("bedtools")
+        .arg("intersect")
+        .arg("-a")
+        .arg(&random_bedfile_left)
+        .arg("-b")
+        .arg(&random_bedfile_right)
+        .arg("-v")
+        .output()
+        .expect("bedtools intersect failed");
+
+    let granges_output = Command::new(granges_binary_path())
+        .arg("filter")
+        .arg("--genome")
+        .arg("tests_data/hg38_seqlens.tsv")
+        .arg("--left")
+        .arg(&random_bedfile_left)
+        .arg("--right")
+        .arg(&random_bedfile_right)
+        .arg("--invert")
+        .output()
+        .expect("granges filter failed");
+
+    assert!(bedtools_output.status.success(), "{:?}", bedtools_output);
+    assert!(granges_output.status.success(), "{:?}", granges_output);
+
+    assert_stdout_eq!(bedtools_output, granges_output);
+}
+
+/// Test bedtools intersect -a <left> -b <right> -c
+/// against
+/// granges filter --genome <genome> --left <left> --right <right> --count
+#[test]
+fn test_against_bedtools_intersect_c() {
+    let num_ranges = 100_000;
+
+    let random_bedfile_left_tempfile = random_bed3file(num_ranges);
+    let random_bedfile_right_tempfile = random_bed3file(num_ranges);
+    let random_bedfile_left = random_bedfile_left_tempfile.path();
+    let random_bedfile_right = random_bedfile_right_tempfile.path();
+
+    granges_random_bed(
+        "tests_data/hg38_seqlens.tsv",
+        num_ranges,
+        Some(&random_bedfile_right),
+        true,
+        false,
+        &mut get_rng(),
+    )
+    .expect("could not generate random BED file");
+
+    let bedtools_output = Command::new("bedtools")
+        .arg("intersect")
+        .arg("-a")
+        .arg(&random_bedfile_left)
+        .arg("-b")
+        .arg(&random_bedfile_right)
+        .arg("-c")
+        .output()
+        .expect("bedtools intersect failed");
+
+    let granges_output = Command::new(granges_binary_path())
+        .arg("filter")
+        .arg("--genome")
+        .arg("tests_data/hg38_seqlens.tsv")
+        .arg("--left")
+        .arg(&random_bedfile_left)
+        .arg("--right")
+        .arg(&random_bedfile_right)
+        .arg("--count")
+        .output()
+        .expect("granges filter failed");
+
+    assert!(bedtools_output.status.success(), "{:?}", bedtools_output);
+    assert!(granges_output.status.success(), "{:?}", granges_output);
+
+    assert_stdout_eq!(bedtools_output, granges_output);
+}
+
 /// Test bedtools flank -g <genome> -i <input> -l 10 -r 20
 /// against
 /// granges filter --genome <genome> --left 10 --right 20 <input>
@@ -271,6 +374,7 @@ fn test_against_bedtools_flank() {
         Some(&random_bedfile),
         true,
         false,
+        &mut get_rng(),
     )
     .expect("could not generate random BED file");
 
@@ -564,6 +668,131 @@ fn test_against_bedtools_map_multiple() {
         });
 }
 
+#[test]
+fn test_against_bedtools_map_empty_missing() {
+    // like bedtools, write plain sum (etc.) as missing for windows with no overlaps
+    let num_ranges = BED_LENGTH;
+    let width = 1_000_000;
+    // #[allow(unused_variables)]
+    // let step = 10_000; // can uncomment lines below to test this
+
+    // make windows
+    let windows_file = temp_bedfile();
+    let granges_windows_output = Command::new(granges_binary_path())
+        .arg("windows")
+        .arg("--genome")
+        .arg("tests_data/hg38_seqlens.tsv")
+        .arg("--width")
+        .arg(width.to_string())
+        // .arg("--step")
+        // .arg(step.to_string())
+        .arg("--output")
+        .arg(windows_file.path())
+        .output()
+        .expect("granges windows failed");
+    assert!(
+        granges_windows_output.status.success(),
+        "{:?}",
+        granges_windows_output
+    );
+
+    // copy_tempfile_for_inspection(&windows_file.path(), "windows.bed");
+
+    // create the random data BED5
+    let bedscores_file = random_bed5file(num_ranges);
+    // copy_tempfile_for_inspection(&bedscores_file.path(), "scores.bed");
+
+    let bedtools_path = temp_bedfile();
+    let bedtools_output_file = File::create(&bedtools_path).unwrap();
+
+    // compare map commands
+    let bedtools_output = Command::new("bedtools")
+        .arg("map")
+        .arg("-a")
+        .arg(windows_file.path())
+        .arg("-b")
+        .arg(&bedscores_file.path())
+        .arg("-c")
+        .arg("5")
+        .arg("-o")
+        .arg("min,max,mean,sum,median")
+        .stdout(Stdio::from(bedtools_output_file))
+        .output()
+        .expect("bedtools map failed");
+
+    // copy_tempfile_for_inspection(&bedtools_path.path(), "bedtools.bed");
+
+    let granges_output_file = temp_bedfile();
+    let granges_output = Command::new(granges_binary_path())
+        .arg("map")
+        .arg("--genome")
+        .arg("tests_data/hg38_seqlens.tsv")
+        .arg("--left")
+        .arg(windows_file.path())
+        .arg("--right")
+        .arg(bedscores_file.path())
+        .arg("--func")
+        .arg("min,max,mean,sum,median")
+        .arg("--empty")
+        .arg("missing")
+        .arg("--output")
+        .arg(granges_output_file.path())
+        .output()
+        .expect("granges map failed");
+
+    // copy_tempfile_for_inspection(&granges_output_file.path(), "granges.bed");
+
+    assert!(bedtools_output.status.success(), "{:?}", bedtools_output);
+    assert!(granges_output.status.success(), "{:?}", granges_output);
+
+    let genome = read_seqlens("tests_data/hg38_seqlens.tsv").unwrap();
+
+    #[derive(Deserialize)]
+    struct Stats {
+        #[serde(deserialize_with = "bed_missing")]
+        min: Option<f64>,
+        #[serde(deserialize_with = "bed_missing")]
+        max: Option<f64>,
+        #[serde(deserialize_with = "bed_missing")]
+        mean: Option<f64>,
+        #[serde(deserialize_with = "bed_missing")]
+        sum: Option<f64>,
+        #[serde(deserialize_with = "bed_missing")]
+        median: Option<f64>,
+    }
+
+    // head_file(&bedtools_path.path());
+    let bedtools_iter =
+        TsvRecordIterator::<GenomicRangeRecord<Stats>>::new(bedtools_path.path()).expect("HERE");
+    let mut bedtools_gr = GRanges::from_iter(bedtools_iter, &genome).unwrap();
+
+    let granges_iter = TsvRecordIterator::<GenomicRangeRecord<Stats>>::new(
+        granges_output_file.path().to_path_buf(),
+    )
+    .unwrap();
+
+    let mut granges_gr = GRanges::from_iter(granges_iter, &genome).unwrap();
+
+    let granges_data = granges_gr.take_data().unwrap();
+    let bedtools_data = bedtools_gr.take_data().unwrap();
+
+    granges_data
+        .iter()
+        .zip(bedtools_data.iter())
+        .for_each(|(gr, bd)| {
+            // dbg!((gr.min, bd.min));
+            assert_option_float_tol!(gr.min, bd.min);
+            assert_option_float_tol!(gr.max, bd.max);
+
+            // NOTE: with bedtools sum, zero overlapping ranges = '.' (None),
+            // not 0.0. Hence, we map above with `--empty missing`.
+            assert_option_float_tol!(gr.sum, bd.sum);
+
+            assert_option_float_tol!(gr.median, bd.median);
+            assert_option_float_tol!(gr.mean, bd.mean);
+        });
+}
+
 #[test]
 fn test_against_bedtools_merge_empty() {
     let num_ranges = BED_LENGTH;
@@ -0,0 +1,78 @@
+//! Golden-file tests of the command line tool, which (unlike
+//! `tests/bedtools_validation.rs`) don't require bedtools to be installed.
+//!
+//! Each directory in `tests_data/golden/cases/` is a test case, with a `cmd` file of
+//! the granges arguments (separated by whitespace) and an `expected` file of the
+//! standard output. Commands are run in `tests_data/golden/`, so they can refer to the
+//! small input files there by name.
+//!
+//! To add a case, create its directory with a `cmd` file, and run
+//! `GRANGES_BLESS=1 cargo test --test golden` to write its `expected` file from the
+//! current output (check it carefully before committing it!).
+
+use granges::testing::granges_binary_path;
+use std::{fs, path::Path, process::Command};
+
+const GOLDEN_DIR: &str = "tests_data/golden";
+
+/// Run a golden test case, returning a description of the failure, if any. In bless
+/// mode, the `expected` file is (over)written instead.
+fn run_case(case_dir: &Path, bless: bool) -> Result<(), String> {
+    let cmd = fs::read_to_string(case_dir.join("cmd")).map_err(|err| err.to_string())?;
+    let output = Command::new(granges_binary_path())
+        .args(cmd.split_whitespace())
+        .current_dir(GOLDEN_DIR)
+        .output()
+        .map_err(|err| format!("could not run granges: {}", err))?;
+    if !output.status.success() {
+        return Err(format!(
+            "'granges {}' failed:\n{}",
+            cmd.trim(),
+            String::from_utf8_lossy(&output.stderr)
+        ));
+    }
+
+    let expected_path = case_dir.join("expected");
+    if bless {
+        return fs::write(&expected_path, &output.stdout).map_err(|err| err.to_string());
+    }
+    let expected = fs::read_to_string(&expected_path).map_err(|err| err.to_string())?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    if stdout != expected {
+        return Err(format!(
+            "'granges {}' output differs\n--- expected:\n{}--- found:\n{}",
+            cmd.trim(),
+            expected,
+            stdout
+        ));
+    }
+    Ok(())
+}
+
+#[test]
+fn test_golden_files() {
+    let bless = std::env::var_os("GRANGES_BLESS").is_some();
+    let mut case_dirs: Vec<_> = fs::read_dir(Path::new(GOLDEN_DIR).join("cases"))
+        .expect("could not read the golden test cases")
+        .map(|entry| entry.unwrap().path())
+        .collect();
+    case_dirs.sort();
+    assert!(!case_dirs.is_empty());
+
+    // run all cases, to report all of the failures at once
+    let failures: Vec<String> = case_dirs
+        .iter()
+        .filter_map(|case_dir| {
+            run_case(case_dir, bless)
+                .err()
+                .map(|error| format!("{}: {}", case_dir.display(), error))
+        })
+        .collect();
+    assert!(
+        failures.is_empty(),
+        "{} of {} golden test cases failed:\n\n{}",
+        failures.len(),
+        case_dirs.len(),
+        failures.join("\n\n")
+    );
+}
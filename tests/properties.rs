@@ -0,0 +1,273 @@
+//! Property-based tests of the in-memory operations against naive reference
+//! implementations, on random small genomes and range sets.
+//!
+//! The bedtools validation tests (`tests/bedtools_validation.rs`) only compare the
+//! command line tools on a few inputs. These check the library operations on many
+//! random inputs, which include edge cases such as duplicate, nested, and book-ended
+//! ranges, and ranges at the ends of sequences.
+
+use granges::{prelude::*, ranges::GenomicRangeRecordEmpty, sequences::rle::RleList, Position};
+use indexmap::IndexMap;
+use quickcheck::{Arbitrary, Gen, QuickCheck};
+
+/// The number of random cases checked per property.
+const NUM_TESTS: u64 = 500;
+
+/// A range as `(seqname, start, end)`.
+type Record = (String, Position, Position);
+
+/// A random genome of a few short sequences, and two random sets of ranges on it.
+#[derive(Clone, Debug)]
+struct Case {
+    seqlens: IndexMap<String, Position>,
+    left: Vec<Record>,
+    right: Vec<Record>,
+}
+
+/// A random position in `[0, n)`.
+fn below(g: &mut Gen, n: Position) -> Position {
+    Position::arbitrary(g) % n
+}
+
+/// Random ranges of up to 30 basepairs on the sequences of `seqlens`.
+fn random_ranges(g: &mut Gen, seqlens: &IndexMap<String, Position>) -> Vec<Record> {
+    let seqnames: Vec<&String> = seqlens.keys().collect();
+    let num_ranges = usize::arbitrary(g) % (g.size() + 1);
+    (0..num_ranges)
+        .map(|_| {
+            let seqname = *g.choose(&seqnames).unwrap();
+            let length = seqlens[seqname];
+            let start = below(g, length);
+            let end = start + 1 + below(g, (length - start).min(30));
+            (seqname.clone(), start, end)
+        })
+        .collect()
+}
+
+impl Arbitrary for Case {
+    fn arbitrary(g: &mut Gen) -> Self {
+        let num_sequences = 1 + usize::arbitrary(g) % 3;
+        let seqlens = (0..num_sequences)
+            .map(|i| (format!("chr{}", i + 1), 1 + below(g, 200)))
+            .collect();
+        let left = random_ranges(g, &seqlens);
+        let right = random_ranges(g, &seqlens);
+        Case {
+            seqlens,
+            left,
+            right,
+        }
+    }
+}
+
+impl Case {
+    fn granges(&self, ranges: &[Record]) -> GRangesEmpty<VecRangesEmpty> {
+        let mut gr = GRangesEmpty::new_vec(&self.seqlens);
+        for (seqname, start, end) in ranges {
+            gr.push_range(seqname, *start, *end).unwrap();
+        }
+        gr
+    }
+
+    /// The number of ranges covering each basepair of each sequence.
+    fn depths(&self, ranges: &[Record]) -> IndexMap<String, Vec<usize>> {
+        let mut depths: IndexMap<String, Vec<usize>> = self
+            .seqlens
+            .iter()
+            .map(|(seqname, &length)| (seqname.clone(), vec![0; length as usize]))
+            .collect();
+        for (seqname, start, end) in ranges {
+            for depth in &mut depths[seqname][*start as usize..*end as usize] {
+                *depth += 1;
+            }
+        }
+        depths
+    }
+}
+
+fn overlaps(a: &Record, b: &Record) -> bool {
+    a.0 == b.0 && a.1 < b.2 && b.1 < a.2
+}
+
+/// Sort the items, to compare outputs regardless of their order.
+fn sorted<T: Ord>(mut items: Vec<T>) -> Vec<T> {
+    items.sort();
+    items
+}
+
+fn records(gr: &GRangesEmpty<VecRangesEmpty>) -> Vec<Record> {
+    sorted(
+        gr.iter_named_ranges()
+            .map(|range| (range.seqname.to_string(), range.start, range.end))
+            .collect(),
+    )
+}
+
+fn check(property: fn(Case) -> bool) {
+    QuickCheck::new().tests(NUM_TESTS).quickcheck(property);
+}
+
+fn prop_filter_joins(case: Case) -> bool {
+    let left = case.granges(&case.left);
+    let right = case.granges(&case.right).into_coitrees().unwrap();
+    let (expected_kept, expected_dropped): (Vec<Record>, Vec<Record>) = case
+        .left
+        .iter()
+        .cloned()
+        .partition(|range| case.right.iter().any(|other| overlaps(range, other)));
+
+    let kept = left.clone().filter_overlaps(&right).unwrap();
+    let dropped = left.antifilter_overlaps(&right).unwrap();
+    records(&kept) == sorted(expected_kept) && records(&dropped) == sorted(expected_dropped)
+}
+
+fn prop_count_overlaps(case: Case) -> bool {
+    let left = case.granges(&case.left);
+    let right = case.granges(&case.right).into_coitrees().unwrap();
+    let expected = sorted(
+        case.left
+            .iter()
+            .map(|range| {
+                let count = case.right.iter().filter(|other| overlaps(range, other));
+                (range.clone(), count.count())
+            })
+            .collect(),
+    );
+
+    let counts = left.count_overlaps(&right).unwrap();
+    let counts = sorted(
+        counts
+            .iter_with_data()
+            .map(|range| {
+                let record = (range.seqname.to_string(), range.start, range.end);
+                (record, *range.data)
+            })
+            .collect(),
+    );
+    counts == expected
+}
+
+fn prop_left_overlaps(case: Case) -> bool {
+    let left = case.granges(&case.left);
+    let right = case.granges(&case.right).into_coitrees().unwrap();
+    // the joins don't store the sequence names, so compare the ranges' positions
+    let expected = sorted(
+        case.left
+            .iter()
+            .map(|range| {
+                let rights = case
+                    .right
+                    .iter()
+                    .filter(|other| overlaps(range, other))
+                    .map(|other| (other.1, other.2))
+                    .collect();
+                ((range.1, range.2), sorted(rights))
+            })
+            .collect(),
+    );
+
+    let joined = left.left_overlaps(&right).unwrap();
+    let joins = sorted(
+        joined
+            .data()
+            .unwrap()
+            .joins
+            .iter()
+            .map(|join| {
+                let rights = join
+                    .rights
+                    .iter()
+                    .map(|other| (other.start(), other.end()))
+                    .collect();
+                ((join.left.start(), join.left.end()), sorted(rights))
+            })
+            .collect(),
+    );
+    joins == expected
+}
+
+fn prop_merge(case: Case) -> bool {
+    // the maximal runs of covered basepairs
+    let mut expected = Vec::new();
+    for (seqname, depths) in case.depths(&case.left) {
+        let mut start = None;
+        for (position, &depth) in depths.iter().chain([&0]).enumerate() {
+            match (start, depth > 0) {
+                (None, true) => start = Some(position as Position),
+                (Some(run_start), false) => {
+                    expected.push((seqname.clone(), run_start, position as Position));
+                    start = None;
+                }
+                _ => {}
+            }
+        }
+    }
+
+    let ranges = sorted(case.left.clone())
+        .into_iter()
+        .map(|(seqname, start, end)| GenomicRangeRecordEmpty::new(seqname, start, end));
+    let merged: Vec<Record> = MergingEmptyIterator::new(ranges, 0)
+        .map(|range| (range.seqname, range.start, range.end))
+        .collect();
+    sorted(merged) == sorted(expected)
+}
+
+fn prop_flank(case: Case) -> bool {
+    let (left_flank, right_flank) = (5, 12);
+    let mut expected = Vec::new();
+    for (seqname, start, end) in &case.left {
+        let length = case.seqlens[seqname];
+        if *start > 0 {
+            expected.push((seqname.clone(), start.saturating_sub(left_flank), *start));
+        }
+        if *end < length {
+            expected.push((seqname.clone(), *end, (end + right_flank).min(length)));
+        }
+    }
+
+    let flanks = case
+        .granges(&case.left)
+        .flanking_ranges(Some(left_flank), Some(right_flank))
+        .unwrap();
+    records(&flanks) == sorted(expected)
+}
+
+fn prop_coverage(case: Case) -> bool {
+    let coverage = RleList::<usize>::coverage(&case.granges(&case.left)).unwrap();
+    let expected = case.depths(&case.left);
+    let depths: IndexMap<String, Vec<usize>> = coverage
+        .iter()
+        .map(|(seqname, rle)| (seqname.clone(), rle.to_vec()))
+        .collect();
+    depths == expected
+}
+
+#[test]
+fn test_filter_joins() {
+    check(prop_filter_joins);
+}
+
+#[test]
+fn test_count_overlaps() {
+    check(prop_count_overlaps);
+}
+
+#[test]
+fn test_left_overlaps() {
+    check(prop_left_overlaps);
+}
+
+#[test]
+fn test_merge() {
+    check(prop_merge);
+}
+
+#[test]
+fn test_flank() {
+    check(prop_flank);
+}
+
+#[test]
+fn test_coverage() {
+    check(prop_coverage);
+}